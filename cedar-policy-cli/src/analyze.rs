@@ -0,0 +1,440 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Static analyses of a policy set, used by the `analyze` subcommand.
+//!
+//! The analyses are deliberately conservative: a finding is only reported
+//! when it holds for every possible entity store, so no finding depends on
+//! entity data the CLI hasn't seen.
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use cedar_policy::*;
+use cedar_policy_formatter::{lexer::get_token_stream, token::Token};
+use miette::{Diagnostic, NamedSource, Report, Result, SourceSpan, WrapErr};
+use thiserror::Error;
+
+use crate::loader::PolicySource;
+
+/// Byte spans of each policy in `src`, in order. Policies end at a `;` token,
+/// which can't appear anywhere else in a policy (outside of a string).
+fn policy_spans(src: &str) -> Vec<SourceSpan> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    for token in get_token_stream(src).unwrap_or_default() {
+        let policy_start = *start.get_or_insert(token.span.start);
+        if token.token == Token::SemiColon {
+            spans.push((policy_start..token.span.end).into());
+            start = None;
+        }
+    }
+    spans
+}
+
+/// Where a policy came from, for reporting findings
+#[derive(Debug, Clone)]
+struct Origin {
+    source: usize,
+    span: SourceSpan,
+}
+
+/// A policy set loaded from one or more sources, remembering where each
+/// policy and template came from
+#[derive(Debug)]
+pub struct LoadedPolicies {
+    /// The combined policy set
+    pub policy_set: PolicySet,
+    sources: Vec<PolicySource>,
+    origins: HashMap<PolicyId, Origin>,
+}
+
+impl LoadedPolicies {
+    /// Parse and combine `sources`. Policies and templates are named by their
+    /// `@id` annotation if they have one. Otherwise they're named `policy<N>`
+    /// by their position in the source, prefixed by the source name if there
+    /// is more than one source. The only policy or template in one of several
+    /// sources is instead named by the source name without its `.cedar`
+    /// extension, e.g., `admins/edit`.
+    pub fn new(sources: Vec<PolicySource>) -> Result<Self> {
+        let mut policy_set = PolicySet::new();
+        let mut origins = HashMap::new();
+        let multiple = sources.len() > 1;
+        for (i, source) in sources.iter().enumerate() {
+            let pset = PolicySet::from_str(&source.src)
+                .map_err(|err| {
+                    Report::new(err)
+                        .with_source_code(NamedSource::new(&source.name, source.src.clone()))
+                })
+                .wrap_err_with(|| format!("failed to parse policy set {}", source.name))?;
+            let spans = policy_spans(&source.src);
+            let only_one = pset.templates().count() + pset.policies().count() == 1;
+            let new_id = |id: &PolicyId, annotation: Option<&str>| -> Result<PolicyId> {
+                match annotation {
+                    Some(anno) => anno
+                        .parse()
+                        .wrap_err("failed to parse policy id annotation"),
+                    None if multiple && only_one => Ok(PolicyId::from_str(
+                        source.name.strip_suffix(".cedar").unwrap_or(&source.name),
+                    )?),
+                    None if multiple => Ok(PolicyId::from_str(&format!("{}:{id}", source.name))?),
+                    None => Ok(id.clone()),
+                }
+            };
+            // the parser names the `N`th policy of a source `policyN`
+            let origin = |id: &PolicyId| {
+                id.to_string()
+                    .strip_prefix("policy")
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .and_then(|n| spans.get(n))
+                    .map(|span| Origin {
+                        source: i,
+                        span: *span,
+                    })
+            };
+            for t in pset.templates() {
+                let id = new_id(t.id(), t.annotation("id"))?;
+                if let Some(origin) = origin(t.id()) {
+                    origins.insert(id.clone(), origin);
+                }
+                policy_set
+                    .add_template(t.new_id(id))
+                    .wrap_err_with(|| format!("failed to add template from {}", source.name))?;
+            }
+            for p in pset.policies() {
+                let id = new_id(p.id(), p.annotation("id"))?;
+                if let Some(origin) = origin(p.id()) {
+                    origins.insert(id.clone(), origin);
+                }
+                policy_set
+                    .add(p.new_id(id))
+                    .wrap_err_with(|| format!("failed to add policy from {}", source.name))?;
+            }
+        }
+        Ok(Self {
+            policy_set,
+            sources,
+            origins,
+        })
+    }
+
+    /// Source code and span of the policy or template `id`. The source is
+    /// empty and the span is `None` if they aren't known.
+    fn locate(&self, id: &PolicyId) -> (NamedSource, Option<SourceSpan>) {
+        let origin = self.origins.get(id);
+        match origin.and_then(|origin| Some((origin, self.sources.get(origin.source)?))) {
+            Some((origin, source)) => (
+                NamedSource::new(&source.name, source.src.clone()),
+                Some(origin.span),
+            ),
+            None => (NamedSource::new("", String::new()), None),
+        }
+    }
+}
+
+/// A problem found by [`analyze`]
+#[derive(Debug, Diagnostic, Error)]
+pub enum Finding {
+    /// The policy can never apply to any request
+    #[error("policy `{id}` can never apply to any request")]
+    #[diagnostic(code(analyze::unsatisfiable), severity(Warning), help("{reason}"))]
+    Unsatisfiable {
+        /// Id of the policy
+        id: PolicyId,
+        /// Why the policy can never apply
+        reason: String,
+        /// Source of the policy
+        #[source_code]
+        src: NamedSource,
+        /// Span of the policy
+        #[label("this policy")]
+        span: Option<SourceSpan>,
+    },
+    /// Every request the policy applies to is also matched by an
+    /// unconditional policy with the same effect, so removing it changes no
+    /// decision
+    #[error("policy `{id}` is shadowed by policy `{by}`")]
+    #[diagnostic(
+        code(analyze::shadowed),
+        severity(Warning),
+        help("`{by}` has the same effect, no conditions, and a scope that includes the scope of `{id}`, so `{id}` is redundant")
+    )]
+    Shadowed {
+        /// Id of the shadowed policy
+        id: PolicyId,
+        /// Id of the policy shadowing it
+        by: PolicyId,
+        /// Source of the shadowed policy
+        #[source_code]
+        src: NamedSource,
+        /// Span of the shadowed policy
+        #[label("this policy")]
+        span: Option<SourceSpan>,
+    },
+    /// Every request the permit policy applies to is also matched by an
+    /// unconditional forbid policy, so the permit can never allow anything
+    #[error("permit policy `{id}` is overridden by forbid policy `{by}`")]
+    #[diagnostic(
+        code(analyze::overridden),
+        severity(Warning),
+        help("`{by}` has no conditions and a scope that includes the scope of `{id}`, so `{id}` can never allow a request")
+    )]
+    Overridden {
+        /// Id of the permit policy
+        id: PolicyId,
+        /// Id of the forbid policy
+        by: PolicyId,
+        /// Source of the permit policy
+        #[source_code]
+        src: NamedSource,
+        /// Span of the permit policy
+        #[label("this policy")]
+        span: Option<SourceSpan>,
+    },
+    /// A template has no links, so it has no effect on authorization
+    #[error("template `{id}` is never linked")]
+    #[diagnostic(
+        code(analyze::unused_template),
+        severity(Warning),
+        help("link the template, or remove it")
+    )]
+    UnusedTemplate {
+        /// Id of the template
+        id: PolicyId,
+        /// Source of the template
+        #[source_code]
+        src: NamedSource,
+        /// Span of the template
+        #[label("this template")]
+        span: Option<SourceSpan>,
+    },
+}
+
+/// Run all analyses over `policies`. Unsatisfiability is checked against
+/// `schema` if one is provided, and syntactically otherwise. Findings are
+/// returned in policy order.
+pub fn analyze(policies: &LoadedPolicies, schema: Option<Schema>) -> Vec<Finding> {
+    let pset = &policies.policy_set;
+    let mut findings = Vec::new();
+    let actions = schema.as_ref().and_then(|s| s.action_entities().ok());
+
+    let mut unsatisfiable: HashMap<PolicyId, String> = HashMap::new();
+    for p in pset.policies() {
+        if let Some(reason) = trivially_false(p) {
+            unsatisfiable.insert(p.id().clone(), reason.to_owned());
+        }
+    }
+    if let Some(schema) = schema {
+        let result = Validator::new(schema).validate(pset, ValidationMode::default());
+        for err in result.validation_errors() {
+            let reason = match err.error_kind() {
+                ValidationErrorKind::TypeError(TypeErrorKind::ImpossiblePolicy) => {
+                    "according to the schema, its conditions are always false"
+                }
+                ValidationErrorKind::InvalidActionApplication(_) => {
+                    "according to the schema, none of its actions apply to its principals and resources"
+                }
+                _ => continue,
+            };
+            unsatisfiable
+                .entry(err.location().policy_id().clone())
+                .or_insert_with(|| reason.to_owned());
+        }
+    }
+
+    let scopes: Vec<(&Policy, Scope)> = pset.policies().map(|p| (p, Scope::of(p))).collect();
+    for (i, (p, scope)) in scopes.iter().enumerate() {
+        let (src, span) = policies.locate(p.id());
+        if let Some(reason) = unsatisfiable.remove(p.id()) {
+            findings.push(Finding::Unsatisfiable {
+                id: p.id().clone(),
+                reason,
+                src,
+                span,
+            });
+            continue;
+        }
+        let covering = scopes.iter().enumerate().find(|(j, (q, q_scope))| {
+            *j != i
+                && is_unconditional(q)
+                && q_scope.includes(scope, actions.as_ref())
+                && match (q.effect(), p.effect()) {
+                    (Effect::Forbid, Effect::Permit) => true,
+                    // of two identical unconditional policies, only report
+                    // the second as shadowed
+                    (q_effect, p_effect) if q_effect == p_effect => {
+                        !(is_unconditional(p)
+                            && scope.includes(q_scope, actions.as_ref())
+                            && i < *j)
+                    }
+                    _ => false,
+                }
+        });
+        if let Some((_, (q, _))) = covering {
+            let by = q.id().clone();
+            let id = p.id().clone();
+            findings.push(if q.effect() == p.effect() {
+                Finding::Shadowed { id, by, src, span }
+            } else {
+                Finding::Overridden { id, by, src, span }
+            });
+        }
+    }
+
+    let linked: HashSet<&PolicyId> = pset.policies().filter_map(Policy::template_id).collect();
+    for t in pset.templates() {
+        if !linked.contains(t.id()) {
+            let (src, span) = policies.locate(t.id());
+            findings.push(Finding::UnusedTemplate {
+                id: t.id().clone(),
+                src,
+                span,
+            });
+        }
+    }
+    findings
+}
+
+/// The conditions of `p` as they appear in its JSON representation
+pub(crate) fn conditions(p: &Policy) -> Vec<serde_json::Value> {
+    p.to_json()
+        .ok()
+        .and_then(|json| json.get("conditions").and_then(|c| c.as_array()).cloned())
+        .unwrap_or_default()
+}
+
+/// Whether the body of a JSON condition is the literal `value`
+fn is_literal(condition: &serde_json::Value, value: bool) -> bool {
+    condition.get("body") == Some(&serde_json::json!({ "Value": value }))
+}
+
+/// Whether every condition of `p` is trivially true, e.g., `when { true }`
+fn is_unconditional(p: &Policy) -> bool {
+    conditions(p)
+        .iter()
+        .all(|c| match c.get("kind").and_then(|k| k.as_str()) {
+            Some("when") => is_literal(c, true),
+            Some("unless") => is_literal(c, false),
+            _ => false,
+        })
+}
+
+/// Whether some condition of `p` is trivially false, e.g., `when { false }`
+fn trivially_false(p: &Policy) -> Option<&'static str> {
+    conditions(p)
+        .iter()
+        .any(|c| match c.get("kind").and_then(|k| k.as_str()) {
+            Some("when") => is_literal(c, false),
+            Some("unless") => is_literal(c, true),
+            _ => false,
+        })
+        .then_some("one of its conditions is always false")
+}
+
+/// The scope of a policy
+#[derive(Debug, PartialEq)]
+pub(crate) struct Scope {
+    principal: PrincipalConstraint,
+    action: ActionConstraint,
+    resource: ResourceConstraint,
+}
+
+impl Scope {
+    pub(crate) fn of(p: &Policy) -> Self {
+        Self {
+            principal: p.principal_constraint(),
+            action: p.action_constraint(),
+            resource: p.resource_constraint(),
+        }
+    }
+
+    /// Whether every request in scope for `other` is in scope for `self`,
+    /// regardless of the entity hierarchy. If the action hierarchy `actions`
+    /// is provided, it is used to compare action constraints.
+    pub(crate) fn includes(&self, other: &Scope, actions: Option<&Entities>) -> bool {
+        EntityScope::principal(&self.principal).includes(&EntityScope::principal(&other.principal))
+            && EntityScope::resource(&self.resource)
+                .includes(&EntityScope::resource(&other.resource))
+            && action_includes(&self.action, &other.action, actions)
+    }
+}
+
+/// A principal or resource scope constraint
+enum EntityScope<'a> {
+    Any,
+    In(&'a EntityUid),
+    Eq(&'a EntityUid),
+    Is(&'a EntityTypeName),
+    IsIn(&'a EntityTypeName, &'a EntityUid),
+}
+
+impl<'a> EntityScope<'a> {
+    fn principal(c: &'a PrincipalConstraint) -> Self {
+        match c {
+            PrincipalConstraint::Any => EntityScope::Any,
+            PrincipalConstraint::In(e) => EntityScope::In(e),
+            PrincipalConstraint::Eq(e) => EntityScope::Eq(e),
+            PrincipalConstraint::Is(t) => EntityScope::Is(t),
+            PrincipalConstraint::IsIn(t, e) => EntityScope::IsIn(t, e),
+        }
+    }
+
+    fn resource(c: &'a ResourceConstraint) -> Self {
+        match c {
+            ResourceConstraint::Any => EntityScope::Any,
+            ResourceConstraint::In(e) => EntityScope::In(e),
+            ResourceConstraint::Eq(e) => EntityScope::Eq(e),
+            ResourceConstraint::Is(t) => EntityScope::Is(t),
+            ResourceConstraint::IsIn(t, e) => EntityScope::IsIn(t, e),
+        }
+    }
+
+    fn includes(&self, other: &EntityScope<'_>) -> bool {
+        match (self, other) {
+            (EntityScope::Any, _) => true,
+            (EntityScope::Eq(e), EntityScope::Eq(f)) => e == f,
+            // `in` is reflexive
+            (EntityScope::In(e), EntityScope::Eq(f) | EntityScope::In(f))
+            | (EntityScope::In(e), EntityScope::IsIn(_, f)) => e == f,
+            (EntityScope::Is(t), EntityScope::Is(u) | EntityScope::IsIn(u, _)) => t == u,
+            (EntityScope::Is(t), EntityScope::Eq(f)) => *t == f.type_name(),
+            (EntityScope::IsIn(t, e), EntityScope::IsIn(u, f)) => t == u && e == f,
+            (EntityScope::IsIn(t, e), EntityScope::Eq(f)) => *t == f.type_name() && *e == *f,
+            _ => false,
+        }
+    }
+}
+
+pub(crate) fn action_includes(
+    c: &ActionConstraint,
+    other: &ActionConstraint,
+    hierarchy: Option<&Entities>,
+) -> bool {
+    // whether `b in a` holds for every entity store consistent with the schema
+    let is_in =
+        |a: &EntityUid, b: &EntityUid| a == b || hierarchy.is_some_and(|h| h.is_ancestor_of(a, b));
+    match (c, other) {
+        (ActionConstraint::Any, _) => true,
+        (ActionConstraint::Eq(a), ActionConstraint::Eq(b)) => a == b,
+        (ActionConstraint::In(actions), ActionConstraint::Eq(b)) => {
+            actions.iter().any(|a| is_in(a, b))
+        }
+        (ActionConstraint::In(actions), ActionConstraint::In(others)) => {
+            others.iter().all(|b| actions.iter().any(|a| is_in(a, b)))
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,132 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Timing statistics, used by the `bench` subcommand.
+
+use std::fmt::{self, Display};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Summary of the durations measured for one phase. Durations are in
+/// microseconds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Stats {
+    /// Number of measurements
+    pub count: usize,
+    /// Shortest duration
+    pub min: f64,
+    /// Average duration
+    pub mean: f64,
+    /// Median duration
+    pub p50: f64,
+    /// 90th percentile duration
+    pub p90: f64,
+    /// 99th percentile duration
+    pub p99: f64,
+    /// Longest duration
+    pub max: f64,
+}
+
+impl Stats {
+    /// Summarize `samples`, or return `None` if there are none
+    pub fn from_samples(samples: &[Duration]) -> Option<Self> {
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let micros = |d: &Duration| d.as_secs_f64() * 1e6;
+        // nearest-rank percentile
+        let percentile = |p: usize| {
+            let rank = (p * sorted.len()).div_ceil(100).max(1);
+            sorted.get(rank - 1).map_or(0.0, micros)
+        };
+        Some(Self {
+            count: sorted.len(),
+            min: micros(sorted.first()?),
+            mean: sorted.iter().map(micros).sum::<f64>() / sorted.len() as f64,
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: micros(sorted.last()?),
+        })
+    }
+}
+
+/// Timings of one phase of authorization
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Phase {
+    /// Name of the phase, e.g., `parse policies`
+    pub name: String,
+    #[serde(flatten)]
+    pub stats: Stats,
+}
+
+/// Timings of every phase which was measured
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BenchReport {
+    /// Phases in the order they were measured
+    pub phases: Vec<Phase>,
+}
+
+impl BenchReport {
+    /// Run `f` `iterations` times, and add the timings to the report as the
+    /// phase `name`
+    pub fn measure<T>(&mut self, name: &str, iterations: usize, mut f: impl FnMut() -> T) {
+        let mut samples = Vec::with_capacity(iterations);
+        for _ in 0..iterations {
+            let start = Instant::now();
+            std::hint::black_box(f());
+            samples.push(start.elapsed());
+        }
+        self.add(name, &samples);
+    }
+
+    /// Add `samples` to the report as the phase `name`. Nothing is added if
+    /// there are no samples.
+    pub fn add(&mut self, name: &str, samples: &[Duration]) {
+        if let Some(stats) = Stats::from_samples(samples) {
+            self.phases.push(Phase {
+                name: name.to_owned(),
+                stats,
+            });
+        }
+    }
+}
+
+impl Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .phases
+            .iter()
+            .map(|p| p.name.len())
+            .chain(["phase".len()])
+            .max()
+            .unwrap_or_default();
+        write!(
+            f,
+            "{:width$}  {:>7}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}  {:>10}",
+            "phase", "count", "min", "mean", "p50", "p90", "p99", "max"
+        )?;
+        for phase in &self.phases {
+            let s = &phase.stats;
+            write!(
+                f,
+                "\n{:width$}  {:>7}  {:>10.1}  {:>10.1}  {:>10.1}  {:>10.1}  {:>10.1}  {:>10.1}",
+                phase.name, s.count, s.min, s.mean, s.p50, s.p90, s.p99, s.max
+            )?;
+        }
+        write!(f, "\n(times in microseconds)")
+    }
+}
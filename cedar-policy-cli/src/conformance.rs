@@ -0,0 +1,291 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Checks entity data against a schema, used by the `validate-entities`
+//! subcommand.
+//!
+//! Parsing entities with a schema stops at the first problem. These checks
+//! instead report every problem in the data, each with the entity and
+//! attribute it's about.
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use cedar_policy::{Entities, EntityUid, Schema};
+use miette::{miette, Diagnostic, Result, WrapErr};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::entity_uid;
+use crate::schema_info::{qualify, SchemaInfo};
+
+/// A way an entity doesn't conform to the schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Diagnostic, Error)]
+#[error(
+    "entity {uid}{}: {message}",
+    .attribute.as_ref().map(|a| format!(", attribute `{a}`")).unwrap_or_default()
+)]
+pub struct Violation {
+    /// Uid of the entity, e.g., `User::"alice"`, or its position in the file
+    /// if it has no valid uid
+    pub uid: String,
+    /// Path to the attribute the violation is about, e.g., `address.zip`, if
+    /// it's about an attribute
+    pub attribute: Option<String>,
+    /// What is wrong
+    pub message: String,
+}
+
+/// Check `entities`, a list of entities in the JSON entity format, against
+/// `schema`, a schema in the JSON schema format, returning every violation
+pub fn check_entities(schema: &Value, entities: &Value) -> Result<Vec<Violation>> {
+    let parsed_schema =
+        Schema::from_json_value(schema.clone()).wrap_err("failed to parse schema")?;
+    let list = entities
+        .as_array()
+        .ok_or_else(|| miette!("expected entities to be a JSON array"))?;
+    let mut checker = Checker {
+        info: SchemaInfo::new(schema),
+        violations: Vec::new(),
+    };
+    let mut seen = HashSet::new();
+    for (i, entity) in list.iter().enumerate() {
+        let Some((ty, id)) = entity_uid(&entity["uid"]) else {
+            checker.violations.push(Violation {
+                uid: format!("#{i}"),
+                attribute: None,
+                message: "the entity has no valid `uid`".to_owned(),
+            });
+            continue;
+        };
+        let uid = format!("{ty}::{}", Value::from(id.as_str()));
+        if !seen.insert(uid.clone()) {
+            checker.violate(&uid, None, "the entity appears more than once".to_owned());
+        }
+        checker.entity(&uid, &ty, entity);
+    }
+    // anything these checks miss is still caught when parsing with the
+    // schema
+    if checker.violations.is_empty() {
+        Entities::from_json_value(entities.clone(), Some(&parsed_schema))
+            .wrap_err("entities don't conform to the schema")?;
+    }
+    Ok(checker.violations)
+}
+
+struct Checker<'a> {
+    info: SchemaInfo<'a>,
+    violations: Vec<Violation>,
+}
+
+impl Checker<'_> {
+    fn violate(&mut self, uid: &str, attribute: Option<&str>, message: String) {
+        self.violations.push(Violation {
+            uid: uid.to_owned(),
+            attribute: attribute.map(ToOwned::to_owned),
+            message,
+        });
+    }
+
+    fn entity(&mut self, uid: &str, ty: &str, entity: &Value) {
+        if ty == "Action" || ty.ends_with("::Action") {
+            let declared = EntityUid::from_str(uid)
+                .ok()
+                .and_then(|action| self.info.action(&action))
+                .is_some();
+            if !declared {
+                self.violate(
+                    uid,
+                    None,
+                    "the action is not declared in the schema".to_owned(),
+                );
+            }
+            return;
+        }
+        let Some(info) = self.info.entity_type(ty) else {
+            self.violate(
+                uid,
+                None,
+                format!("entity type `{ty}` is not declared in the schema"),
+            );
+            return;
+        };
+        let (namespace, shape) = self.info.resolve_common(info.namespace, info.shape);
+        let member_of = info.member_of.clone();
+
+        match &entity["parents"] {
+            Value::Array(parents) => {
+                for parent in parents {
+                    match entity_uid(parent) {
+                        Some((parent_ty, _)) if member_of.contains(&parent_ty) => (),
+                        Some((parent_ty, parent_id)) => self.violate(
+                            uid,
+                            None,
+                            format!(
+                                "parent {parent_ty}::{} is not allowed, because entities of \
+                                 type `{ty}` can't be members of `{parent_ty}`",
+                                Value::from(parent_id)
+                            ),
+                        ),
+                        None => self.violate(uid, None, "a parent is not an entity uid".to_owned()),
+                    }
+                }
+            }
+            Value::Null => (),
+            _ => self.violate(uid, None, "`parents` is not a list".to_owned()),
+        }
+
+        let empty = Map::new();
+        let attrs = match &entity["attrs"] {
+            Value::Object(attrs) => attrs,
+            Value::Null => &empty,
+            _ => {
+                self.violate(uid, None, "`attrs` is not an object".to_owned());
+                return;
+            }
+        };
+        let additional = shape["additionalAttributes"] == true;
+        self.record(uid, namespace, shape, additional, attrs, None);
+    }
+
+    /// Check `attrs`, a record at `path` (the entity's attributes if `None`),
+    /// against `ty`, a record type written in `namespace`
+    fn record(
+        &mut self,
+        uid: &str,
+        namespace: &str,
+        ty: &Value,
+        additional: bool,
+        attrs: &Map<String, Value>,
+        path: Option<&str>,
+    ) {
+        let attr_path = |name: &str| match path {
+            Some(path) => format!("{path}.{name}"),
+            None => name.to_owned(),
+        };
+        let declared = ty["attributes"].as_object();
+        for (name, attr_ty) in declared.into_iter().flatten() {
+            let path = attr_path(name);
+            match attrs.get(name) {
+                Some(value) => self.value(uid, namespace, attr_ty, value, &path),
+                None if attr_ty["required"] != false => self.violate(
+                    uid,
+                    Some(&path),
+                    "the required attribute is missing".to_owned(),
+                ),
+                None => (),
+            }
+        }
+        if !additional {
+            for name in attrs.keys() {
+                if declared.is_none_or(|declared| !declared.contains_key(name)) {
+                    self.violate(
+                        uid,
+                        Some(&attr_path(name)),
+                        "the attribute is not declared in the schema".to_owned(),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Check `value`, the value of the attribute at `path`, against `ty`, a
+    /// type written in `namespace`
+    fn value(&mut self, uid: &str, namespace: &str, ty: &Value, value: &Value, path: &str) {
+        let mismatch = |expected: &str| format!("expected {expected}, found {}", describe(value));
+        match ty["type"].as_str().unwrap_or_default() {
+            "Boolean" if !value.is_boolean() => {
+                self.violate(uid, Some(path), mismatch("a boolean"))
+            }
+            "Long" if !value.is_i64() => self.violate(uid, Some(path), mismatch("a Long")),
+            "String" if !value.is_string() => self.violate(uid, Some(path), mismatch("a String")),
+            "Boolean" | "Long" | "String" => (),
+            "Set" => match value.as_array() {
+                Some(elements) => {
+                    for element in elements {
+                        self.value(uid, namespace, &ty["element"], element, path);
+                    }
+                }
+                None => self.violate(uid, Some(path), mismatch("a set")),
+            },
+            "Record" => match value.as_object() {
+                Some(attrs) if !attrs.contains_key("__entity") && !attrs.contains_key("__extn") => {
+                    let additional = ty["additionalAttributes"] == true;
+                    self.record(uid, namespace, ty, additional, attrs, Some(path));
+                }
+                _ => self.violate(uid, Some(path), mismatch("a record")),
+            },
+            "Entity" => {
+                let expected = qualify(namespace, ty["name"].as_str().unwrap_or_default());
+                match entity_uid(value) {
+                    Some((found, _)) if found != expected => self.violate(
+                        uid,
+                        Some(path),
+                        format!(
+                            "expected an entity of type `{expected}`, found an entity of type \
+                             `{found}`"
+                        ),
+                    ),
+                    Some(_) => (),
+                    None => self.violate(
+                        uid,
+                        Some(path),
+                        mismatch(&format!("an entity of type `{expected}`")),
+                    ),
+                }
+            }
+            "Extension" => {
+                let expected = ty["name"].as_str().unwrap_or_default();
+                let extn = value.get("__extn").unwrap_or(value);
+                let found = match extn["fn"].as_str() {
+                    Some("ip") => Some("ipaddr"),
+                    other => other,
+                };
+                if found != Some(expected) {
+                    self.violate(
+                        uid,
+                        Some(path),
+                        mismatch(&format!("a value of type `{expected}`")),
+                    );
+                }
+            }
+            common => {
+                // references to undeclared common types are rejected when
+                // parsing the schema
+                if let Some((namespace, ty)) = self.info.common_type(namespace, common) {
+                    self.value(uid, namespace, ty, value, path);
+                }
+            }
+        }
+    }
+}
+
+/// A description of the kind of `value`, an attribute value in the JSON
+/// entity format, for violations
+fn describe(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(n) if n.is_i64() => "a Long",
+        Value::Number(_) => "a number which is not a Long",
+        Value::String(_) => "a String",
+        Value::Array(_) => "a set",
+        Value::Object(obj) if obj.contains_key("__entity") => "an entity",
+        Value::Object(obj) if obj.contains_key("__extn") => "an extension value",
+        Value::Object(_) => "a record",
+    }
+}
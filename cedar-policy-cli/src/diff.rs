@@ -0,0 +1,323 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Semantic comparison of two policy sets, used by the `diff-policies`
+//! subcommand.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+use cedar_policy::*;
+use serde_json::Value;
+
+use crate::analyze::{conditions, Scope};
+
+/// What kind of item in a policy set changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    /// A static policy
+    Policy,
+    /// A template
+    Template,
+    /// A template-linked policy
+    Link,
+}
+
+impl Display for ItemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ItemKind::Policy => write!(f, "policy"),
+            ItemKind::Template => write!(f, "template"),
+            ItemKind::Link => write!(f, "template-linked policy"),
+        }
+    }
+}
+
+/// One way in which a policy, template, or link changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeDetail {
+    /// The effect changed from `permit` to `forbid` or vice versa
+    Effect {
+        /// The new effect
+        now: Effect,
+    },
+    /// The new scope matches every request the old scope matched, and more
+    ScopeWidened,
+    /// The old scope matches every request the new scope matches, and more
+    ScopeNarrowed,
+    /// The scope changed in some other way
+    Scope,
+    /// The `when` and `unless` conditions changed
+    Conditions,
+    /// The annotations changed
+    Annotations,
+    /// A link now instantiates a different template
+    LinkedTemplate,
+}
+
+impl Display for ChangeDetail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangeDetail::Effect {
+                now: Effect::Permit,
+            } => {
+                write!(f, "effect changed from forbid to permit")
+            }
+            ChangeDetail::Effect {
+                now: Effect::Forbid,
+            } => {
+                write!(f, "effect changed from permit to forbid")
+            }
+            ChangeDetail::ScopeWidened => write!(f, "scope widened"),
+            ChangeDetail::ScopeNarrowed => write!(f, "scope narrowed"),
+            ChangeDetail::Scope => write!(f, "scope changed"),
+            ChangeDetail::Conditions => write!(f, "conditions changed"),
+            ChangeDetail::Annotations => write!(f, "annotations changed"),
+            ChangeDetail::LinkedTemplate => write!(f, "links a different template"),
+        }
+    }
+}
+
+/// A difference between two policy sets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyChange {
+    /// An item is only in the new policy set
+    Added {
+        /// Id of the item
+        id: PolicyId,
+        /// Kind of the item
+        kind: ItemKind,
+    },
+    /// An item is only in the old policy set
+    Removed {
+        /// Id of the item
+        id: PolicyId,
+        /// Kind of the item
+        kind: ItemKind,
+    },
+    /// An item with the same id is in both policy sets, but differs
+    Changed {
+        /// Id of the item
+        id: PolicyId,
+        /// Kind of the item in the new policy set
+        kind: ItemKind,
+        /// How the item changed
+        details: Vec<ChangeDetail>,
+    },
+}
+
+impl PolicyChange {
+    /// Whether this change can allow a request which was previously denied:
+    /// an added permit, a removed forbid, or a widened permit
+    pub fn may_grant_access(&self, old: &PolicySet, new: &PolicySet) -> bool {
+        let effect = |pset: &PolicySet, id: &PolicyId| {
+            pset.policy(id)
+                .map(Policy::effect)
+                .or_else(|| pset.template(id).map(Template::effect))
+        };
+        match self {
+            PolicyChange::Added { id, .. } => effect(new, id) == Some(Effect::Permit),
+            PolicyChange::Removed { id, .. } => effect(old, id) == Some(Effect::Forbid),
+            PolicyChange::Changed { id, details, .. } => {
+                details.iter().any(|d| match d {
+                    ChangeDetail::Effect { now } => *now == Effect::Permit,
+                    ChangeDetail::ScopeWidened => effect(new, id) == Some(Effect::Permit),
+                    ChangeDetail::ScopeNarrowed => effect(new, id) == Some(Effect::Forbid),
+                    // we can't tell which way these changes go
+                    ChangeDetail::Scope
+                    | ChangeDetail::Conditions
+                    | ChangeDetail::LinkedTemplate => true,
+                    ChangeDetail::Annotations => false,
+                })
+            }
+        }
+    }
+}
+
+impl Display for PolicyChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyChange::Added { id, kind } => write!(f, "+ {kind} `{id}`"),
+            PolicyChange::Removed { id, kind } => write!(f, "- {kind} `{id}`"),
+            PolicyChange::Changed { id, kind, details } => {
+                write!(f, "~ {kind} `{id}`: ")?;
+                let details: Vec<String> = details.iter().map(ToString::to_string).collect();
+                write!(f, "{}", details.join(", "))
+            }
+        }
+    }
+}
+
+/// An item in a policy set, for comparison
+struct Item<'a> {
+    id: &'a PolicyId,
+    kind: ItemKind,
+    /// JSON representation, for templates and static policies
+    json: Option<Value>,
+    policy: Option<&'a Policy>,
+    template: Option<&'a Template>,
+}
+
+/// The items in `pset`, keyed by id so that they are sorted by id
+fn items(pset: &PolicySet) -> BTreeMap<String, Item<'_>> {
+    let mut items = BTreeMap::new();
+    for t in pset.templates() {
+        items.insert(
+            t.id().to_string(),
+            Item {
+                id: t.id(),
+                kind: ItemKind::Template,
+                json: t.to_json().ok(),
+                policy: None,
+                template: Some(t),
+            },
+        );
+    }
+    for p in pset.policies() {
+        let (kind, json) = match p.template_id() {
+            Some(_) => (ItemKind::Link, None),
+            None => (ItemKind::Policy, p.to_json().ok()),
+        };
+        items.insert(
+            p.id().to_string(),
+            Item {
+                id: p.id(),
+                kind,
+                json,
+                policy: Some(p),
+                template: None,
+            },
+        );
+    }
+    items
+}
+
+/// Compare the old and new versions of an item with the same id
+fn compare(old: &Item<'_>, new: &Item<'_>, actions: Option<&Entities>) -> Vec<ChangeDetail> {
+    let mut details = Vec::new();
+    if let (Some(old_p), Some(new_p)) = (old.policy, new.policy) {
+        if old_p.template_id() != new_p.template_id() {
+            details.push(ChangeDetail::LinkedTemplate);
+        }
+        if old_p.effect() != new_p.effect() {
+            details.push(ChangeDetail::Effect {
+                now: new_p.effect(),
+            });
+        }
+        let (old_scope, new_scope) = (Scope::of(old_p), Scope::of(new_p));
+        if old_scope != new_scope {
+            match (
+                new_scope.includes(&old_scope, actions),
+                old_scope.includes(&new_scope, actions),
+            ) {
+                // equivalent scopes, e.g., a reordered action list
+                (true, true) => {}
+                (true, false) => details.push(ChangeDetail::ScopeWidened),
+                (false, true) => details.push(ChangeDetail::ScopeNarrowed),
+                (false, false) => details.push(ChangeDetail::Scope),
+            }
+        }
+        // links don't have conditions of their own, and their annotations
+        // come from their template
+        if old.kind == ItemKind::Policy && new.kind == ItemKind::Policy {
+            if conditions(old_p) != conditions(new_p) {
+                details.push(ChangeDetail::Conditions);
+            }
+            if old.json.as_ref().map(|j| &j["annotations"])
+                != new.json.as_ref().map(|j| &j["annotations"])
+            {
+                details.push(ChangeDetail::Annotations);
+            }
+        }
+    } else if let (Some(old_t), Some(new_t)) = (old.template, new.template) {
+        if old_t.effect() != new_t.effect() {
+            details.push(ChangeDetail::Effect {
+                now: new_t.effect(),
+            });
+        }
+        let field = |item: &Item<'_>, key: &str| item.json.as_ref().map(|j| j[key].clone());
+        if ["principal", "action", "resource"]
+            .iter()
+            .any(|key| field(old, key) != field(new, key))
+        {
+            details.push(ChangeDetail::Scope);
+        }
+        if field(old, "conditions") != field(new, "conditions") {
+            details.push(ChangeDetail::Conditions);
+        }
+        if field(old, "annotations") != field(new, "annotations") {
+            details.push(ChangeDetail::Annotations);
+        }
+    }
+    details
+}
+
+/// Compare two policy sets. Policies, templates, and links are matched by
+/// id. If the action hierarchy `actions` (e.g., from a schema) is provided, it
+/// is used to decide whether a changed scope was widened or narrowed. Removed
+/// and changed items are returned in order of id, followed by added items.
+pub fn diff_policies(
+    old: &PolicySet,
+    new: &PolicySet,
+    actions: Option<&Entities>,
+) -> Vec<PolicyChange> {
+    let old_items = items(old);
+    let new_items = items(new);
+    let mut changes = Vec::new();
+    for (key, old_item) in &old_items {
+        let id = old_item.id;
+        match new_items.get(key) {
+            None => changes.push(PolicyChange::Removed {
+                id: id.clone(),
+                kind: old_item.kind,
+            }),
+            // a policy replaced by a template (or vice versa) is reported
+            // as a removal and an addition
+            Some(new_item) if new_item.kind != old_item.kind => {
+                changes.push(PolicyChange::Removed {
+                    id: id.clone(),
+                    kind: old_item.kind,
+                });
+                changes.push(PolicyChange::Added {
+                    id: id.clone(),
+                    kind: new_item.kind,
+                });
+            }
+            Some(new_item) => {
+                if old_item.json.is_some() && old_item.json == new_item.json {
+                    continue;
+                }
+                let details = compare(old_item, new_item, actions);
+                if !details.is_empty() {
+                    changes.push(PolicyChange::Changed {
+                        id: id.clone(),
+                        kind: new_item.kind,
+                        details,
+                    });
+                }
+            }
+        }
+    }
+    for (key, new_item) in &new_items {
+        if !old_items.contains_key(key) {
+            changes.push(PolicyChange::Added {
+                id: new_item.id.clone(),
+                kind: new_item.kind,
+            });
+        }
+    }
+    changes
+}
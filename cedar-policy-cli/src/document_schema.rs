@@ -0,0 +1,525 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Renders a schema as human-readable documentation, in Markdown or HTML,
+//! used by the `document-schema` subcommand.
+//!
+//! The documentation has a section for each namespace, describing its common
+//! types, its entity types with a table of their attributes, and a matrix of
+//! the principal and resource types its actions apply to. It ends with the
+//! hierarchy of action groups. Types are written as in the Cedar schema
+//! format.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use cedar_policy::cedar_schema::{is_ident, quoted, sorted};
+use miette::Result;
+use serde_json::Value;
+
+use crate::schema_info::{qualify, SchemaInfo};
+use crate::translate_schema::validate_json;
+
+/// Documentation for `schema`, a schema in the JSON schema format, as
+/// Markdown
+pub fn schema_markdown(schema: &Value) -> Result<String> {
+    validate_json(schema)?;
+    Ok(markdown(&document(schema)))
+}
+
+/// Documentation for `schema`, a schema in the JSON schema format, as a
+/// standalone HTML page
+pub fn schema_html(schema: &Value) -> Result<String> {
+    validate_json(schema)?;
+    Ok(html(&document(schema)))
+}
+
+/// A run of text in the documentation
+enum Span {
+    Text(String),
+    Code(String),
+}
+
+/// A line of text, e.g., a paragraph or a table cell
+type Line = Vec<Span>;
+
+/// A block of the documentation, independent of the output format
+enum Block {
+    Heading(usize, Line),
+    Paragraph(Line),
+    /// Column headers, then rows of cells
+    Table(Vec<Line>, Vec<Vec<Line>>),
+    List(Vec<Item>),
+}
+
+/// An item of a list, with its nested items
+struct Item {
+    line: Line,
+    children: Vec<Item>,
+}
+
+fn text(s: &str) -> Line {
+    vec![Span::Text(s.to_owned())]
+}
+
+fn code(s: &str) -> Line {
+    vec![Span::Code(s.to_owned())]
+}
+
+/// Concatenate the non-empty `lines`, with `separator` between each of them
+fn join(lines: impl IntoIterator<Item = Line>, separator: &str) -> Line {
+    let mut joined = vec![];
+    for line in lines.into_iter().filter(|line| !line.is_empty()) {
+        if !joined.is_empty() {
+            joined.push(Span::Text(separator.to_owned()));
+        }
+        joined.extend(line);
+    }
+    joined
+}
+
+fn document(schema: &Value) -> Vec<Block> {
+    let info = SchemaInfo::new(schema);
+    let mut blocks = vec![Block::Heading(1, text("Schema"))];
+    for (namespace, ns_def) in sorted(schema) {
+        blocks.push(Block::Heading(
+            2,
+            if namespace.is_empty() {
+                text("Default namespace")
+            } else {
+                join([text("Namespace"), code(namespace)], " ")
+            },
+        ));
+        common_types(&mut blocks, namespace, ns_def);
+        entity_types(&mut blocks, &info, namespace, ns_def);
+        actions(&mut blocks, namespace, ns_def);
+    }
+    action_groups(&mut blocks, schema);
+    blocks
+}
+
+fn common_types(blocks: &mut Vec<Block>, namespace: &str, ns_def: &Value) {
+    let common_types = sorted(&ns_def["commonTypes"]);
+    if common_types.is_empty() {
+        return;
+    }
+    blocks.push(Block::Heading(3, text("Common types")));
+    let rows = common_types
+        .into_iter()
+        .map(|(name, ty)| vec![code(&qualify(namespace, name)), code(&type_str(ty))])
+        .collect();
+    blocks.push(Block::Table(vec![text("Name"), text("Type")], rows));
+}
+
+fn entity_types<'a>(
+    blocks: &mut Vec<Block>,
+    info: &SchemaInfo<'a>,
+    namespace: &'a str,
+    ns_def: &'a Value,
+) {
+    let entity_types = sorted(&ns_def["entityTypes"]);
+    if entity_types.is_empty() {
+        return;
+    }
+    blocks.push(Block::Heading(3, text("Entity types")));
+    for (name, entity_type) in entity_types {
+        blocks.push(Block::Heading(4, code(&qualify(namespace, name))));
+        if let Some(message) = entity_type["deprecated"].as_str() {
+            blocks.push(Block::Paragraph(text(&format!("Deprecated: {message}"))));
+        }
+        let annotated = annotations(&entity_type["annotations"]);
+        if !annotated.is_empty() {
+            blocks.push(Block::Paragraph(join(
+                [text("Annotations:"), annotated],
+                " ",
+            )));
+        }
+        let member_of: Vec<Line> = entity_type["memberOfTypes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .map(|ty| code(&qualify(namespace, ty)))
+            .collect();
+        if !member_of.is_empty() {
+            blocks.push(Block::Paragraph(join(
+                [text("Member of:"), join(member_of, ", ")],
+                " ",
+            )));
+        }
+
+        let shape = &entity_type["shape"];
+        let (_, resolved) = info.resolve_common(namespace, shape);
+        let attributes = sorted(&resolved["attributes"]);
+        if attributes.is_empty() {
+            blocks.push(Block::Paragraph(text("No attributes.")));
+            continue;
+        }
+        if resolved != shape {
+            blocks.push(Block::Paragraph(join(
+                [text("Shape:"), code(&type_str(shape))],
+                " ",
+            )));
+        }
+        let rows = attributes
+            .into_iter()
+            .map(|(attr, attr_ty)| {
+                let required = if attr_ty["required"] == false {
+                    "no"
+                } else {
+                    "yes"
+                };
+                vec![
+                    code(attr),
+                    code(&type_str(attr_ty)),
+                    text(required),
+                    notes(attr_ty),
+                ]
+            })
+            .collect();
+        blocks.push(Block::Table(
+            vec![
+                text("Attribute"),
+                text("Type"),
+                text("Required"),
+                text("Notes"),
+            ],
+            rows,
+        ));
+    }
+}
+
+/// The matrix of which principal and resource types each action in a
+/// namespace applies to
+fn actions(blocks: &mut Vec<Block>, namespace: &str, ns_def: &Value) {
+    let actions = sorted(&ns_def["actions"]);
+    if actions.is_empty() {
+        return;
+    }
+    let types = |action: &Value, key: &str| -> Option<BTreeSet<String>> {
+        action["appliesTo"].get(key)?.as_array().map(|types| {
+            types
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|ty| qualify(namespace, ty))
+                .collect()
+        })
+    };
+    let mut columns = BTreeSet::new();
+    let mut applies_to = vec![];
+    for (id, action) in actions {
+        let principals = types(action, "principalTypes");
+        let resources = types(action, "resourceTypes");
+        columns.extend(principals.iter().chain(&resources).flatten().cloned());
+        applies_to.push((id, action, principals, resources));
+    }
+
+    let mut rows = vec![];
+    for (id, action, principals, resources) in applies_to {
+        let mut row = vec![code(id)];
+        for ty in &columns {
+            let is_principal = principals.as_ref().is_some_and(|p| p.contains(ty));
+            let is_resource = resources.as_ref().is_some_and(|r| r.contains(ty));
+            row.push(text(match (is_principal, is_resource) {
+                (true, true) => "principal, resource",
+                (true, false) => "principal",
+                (false, true) => "resource",
+                (false, false) => "",
+            }));
+        }
+        row.push(match action["appliesTo"].get("context") {
+            Some(context) if !context.is_null() => code(&type_str(context)),
+            _ => vec![],
+        });
+        // an action without principal or resource types applies to
+        // unspecified entities, which have no type
+        let mut unspecified = vec![];
+        if principals.is_none() {
+            unspecified.push(text("unspecified principal"));
+        }
+        if resources.is_none() {
+            unspecified.push(text("unspecified resource"));
+        }
+        row.push(join([join(unspecified, ", "), notes(action)], "; "));
+        rows.push(row);
+    }
+
+    let mut header = vec![text("Action")];
+    header.extend(columns.iter().map(|ty| code(ty)));
+    header.extend([text("Context"), text("Notes")]);
+    blocks.push(Block::Heading(3, text("Actions")));
+    blocks.push(Block::Paragraph(text(
+        "Whether each entity type can be the principal or resource of a request for each action.",
+    )));
+    blocks.push(Block::Table(header, rows));
+}
+
+/// The hierarchy of action groups in every namespace, as a nested list
+/// starting from the groups which aren't members of another group
+fn action_groups(blocks: &mut Vec<Block>, schema: &Value) {
+    let mut members: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut in_group = BTreeSet::new();
+    for (namespace, ns_def) in sorted(schema) {
+        for (id, action) in sorted(&ns_def["actions"]) {
+            let uid = uid_str(&qualify(namespace, "Action"), id);
+            for parent in action["memberOf"].as_array().into_iter().flatten() {
+                let ty = qualify(namespace, parent["type"].as_str().unwrap_or("Action"));
+                let parent = uid_str(&ty, parent["id"].as_str().unwrap_or_default());
+                members.entry(parent).or_default().insert(uid.clone());
+                in_group.insert(uid.clone());
+            }
+        }
+    }
+    if members.is_empty() {
+        return;
+    }
+    let items = members
+        .keys()
+        .filter(|group| !in_group.contains(*group))
+        .map(|group| group_item(group, &members, &mut vec![]))
+        .collect();
+    blocks.push(Block::Heading(2, text("Action groups")));
+    blocks.push(Block::List(items));
+}
+
+/// A list item for `uid` with its members nested below it. `path` is the
+/// groups it is nested in, which stops a cycle from recurring forever.
+fn group_item<'a>(
+    uid: &'a str,
+    members: &'a BTreeMap<String, BTreeSet<String>>,
+    path: &mut Vec<&'a str>,
+) -> Item {
+    let mut children = vec![];
+    if !path.contains(&uid) {
+        path.push(uid);
+        for member in members.get(uid).into_iter().flatten() {
+            children.push(group_item(member, members, path));
+        }
+        path.pop();
+    }
+    Item {
+        line: code(uid),
+        children,
+    }
+}
+
+/// The annotations in a JSON object, as they're written in the Cedar schema
+/// format
+fn annotations(annotations: &Value) -> Line {
+    join(
+        sorted(annotations).into_iter().map(|(key, value)| {
+            code(&format!(
+                "@{key}({})",
+                quoted(value.as_str().unwrap_or_default())
+            ))
+        }),
+        ", ",
+    )
+}
+
+/// Notes about a declaration: whether it's deprecated, and its annotations
+fn notes(decl: &Value) -> Line {
+    let deprecated = decl["deprecated"]
+        .as_str()
+        .map(|message| text(&format!("Deprecated: {message}")));
+    join(
+        deprecated
+            .into_iter()
+            .chain([annotations(&decl["annotations"])]),
+        "; ",
+    )
+}
+
+/// A type on a single line, as it's written in the Cedar schema format
+fn type_str(ty: &Value) -> String {
+    match ty["type"].as_str().unwrap_or_default() {
+        "Boolean" => "Bool".to_owned(),
+        "Set" => format!("Set<{}>", type_str(&ty["element"])),
+        "Record" => {
+            let attributes: Vec<String> = sorted(&ty["attributes"])
+                .into_iter()
+                .map(|(name, attr_ty)| {
+                    let name = if is_ident(name) {
+                        name.clone()
+                    } else {
+                        quoted(name)
+                    };
+                    let optional = if attr_ty["required"] == false {
+                        "?"
+                    } else {
+                        ""
+                    };
+                    format!("{name}{optional}: {}", type_str(attr_ty))
+                })
+                .collect();
+            if attributes.is_empty() {
+                "{}".to_owned()
+            } else {
+                format!("{{ {} }}", attributes.join(", "))
+            }
+        }
+        "Entity" | "Extension" => ty["name"].as_str().unwrap_or_default().to_owned(),
+        // `String`, `Long`, and references to common types
+        name => name.to_owned(),
+    }
+}
+
+/// An entity uid in Cedar syntax
+fn uid_str(ty: &str, id: &str) -> String {
+    format!("{ty}::{}", quoted(id))
+}
+
+fn markdown(blocks: &[Block]) -> String {
+    let mut out = String::new();
+    for block in blocks {
+        match block {
+            Block::Heading(level, line) => {
+                out.push_str(&format!(
+                    "{} {}\n\n",
+                    "#".repeat(*level),
+                    markdown_line(line, false)
+                ));
+            }
+            Block::Paragraph(line) => {
+                out.push_str(&format!("{}\n\n", markdown_line(line, false)));
+            }
+            Block::Table(header, rows) => {
+                let row = |cells: &[Line]| -> String {
+                    let cells: Vec<String> = cells
+                        .iter()
+                        .map(|cell| format!(" {} ", markdown_line(cell, true)))
+                        .collect();
+                    format!("|{}|\n", cells.join("|"))
+                };
+                out.push_str(&row(header));
+                out.push_str(&format!("|{}|\n", vec![" --- "; header.len()].join("|")));
+                for cells in rows {
+                    out.push_str(&row(cells));
+                }
+                out.push('\n');
+            }
+            Block::List(items) => {
+                markdown_list(&mut out, items, 0);
+                out.push('\n');
+            }
+        }
+    }
+    format!("{}\n", out.trim_end())
+}
+
+fn markdown_list(out: &mut String, items: &[Item], depth: usize) {
+    for item in items {
+        out.push_str(&format!(
+            "{}- {}\n",
+            "  ".repeat(depth),
+            markdown_line(&item.line, false)
+        ));
+        markdown_list(out, &item.children, depth + 1);
+    }
+}
+
+/// A line of Markdown. Pipes are escaped in table cells, even in code spans,
+/// where they would otherwise end the cell.
+fn markdown_line(line: &Line, in_table: bool) -> String {
+    let mut out = String::new();
+    for span in line {
+        match span {
+            Span::Text(s) => {
+                for c in s.chars() {
+                    if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|') {
+                        out.push('\\');
+                    }
+                    out.push(c);
+                }
+            }
+            Span::Code(s) => {
+                let s = if in_table {
+                    s.replace('|', "\\|")
+                } else {
+                    s.clone()
+                };
+                if s.contains('`') {
+                    out.push_str(&format!("`` {s} ``"));
+                } else {
+                    out.push_str(&format!("`{s}`"));
+                }
+            }
+        }
+    }
+    out
+}
+
+fn html(blocks: &[Block]) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Schema</title>\n</head>\n<body>\n",
+    );
+    for block in blocks {
+        match block {
+            Block::Heading(level, line) => {
+                out.push_str(&format!("<h{level}>{}</h{level}>\n", html_line(line)));
+            }
+            Block::Paragraph(line) => {
+                out.push_str(&format!("<p>{}</p>\n", html_line(line)));
+            }
+            Block::Table(header, rows) => {
+                out.push_str("<table>\n<thead>\n<tr>");
+                for cell in header {
+                    out.push_str(&format!("<th>{}</th>", html_line(cell)));
+                }
+                out.push_str("</tr>\n</thead>\n<tbody>\n");
+                for cells in rows {
+                    out.push_str("<tr>");
+                    for cell in cells {
+                        out.push_str(&format!("<td>{}</td>", html_line(cell)));
+                    }
+                    out.push_str("</tr>\n");
+                }
+                out.push_str("</tbody>\n</table>\n");
+            }
+            Block::List(items) => html_list(&mut out, items),
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn html_list(out: &mut String, items: &[Item]) {
+    out.push_str("<ul>\n");
+    for item in items {
+        out.push_str(&format!("<li>{}", html_line(&item.line)));
+        if !item.children.is_empty() {
+            out.push('\n');
+            html_list(out, &item.children);
+        }
+        out.push_str("</li>\n");
+    }
+    out.push_str("</ul>\n");
+}
+
+fn html_line(line: &Line) -> String {
+    line.iter()
+        .map(|span| match span {
+            Span::Text(s) => html_escape(s),
+            Span::Code(s) => format!("<code>{}</code>", html_escape(s)),
+        })
+        .collect()
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,255 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Generates random requests and entities which conform to a schema, used by
+//! the `generate-requests` subcommand.
+//!
+//! Generated entities of each type have the ids `<type>0`, `<type>1`, and so
+//! on, e.g., `User::"user0"`, so that requests and entity attributes refer to
+//! entities which are in the generated entity data.
+
+use cedar_policy::Schema;
+use miette::{miette, Result, WrapErr};
+use serde_json::{json, Map, Value};
+
+use crate::schema_info::{qualify, SchemaInfo};
+
+/// Requests and entities generated from a schema
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratedData {
+    /// Requests, in the format expected by `--request-json`
+    pub requests: Vec<Value>,
+    /// Entities, in the JSON entity format
+    pub entities: Value,
+}
+
+/// Generate `count` random requests which conform to `schema`, a schema in
+/// the JSON schema format, along with `ids_per_type` entities of every entity
+/// type. The same `seed` always generates the same data.
+pub fn generate(
+    schema: &Value,
+    count: usize,
+    ids_per_type: usize,
+    seed: u64,
+) -> Result<GeneratedData> {
+    Schema::from_json_value(schema.clone()).wrap_err("failed to parse schema")?;
+    if ids_per_type == 0 {
+        return Err(miette!(
+            "at least one entity of each type must be generated"
+        ));
+    }
+    let info = SchemaInfo::new(schema);
+    let mut generator = Generator {
+        info: &info,
+        rng: Rng::new(seed),
+        ids_per_type,
+    };
+
+    let actions: Vec<_> = info
+        .actions()
+        .into_iter()
+        .filter(|(_, _, applies_to)| {
+            ["principalTypes", "resourceTypes"]
+                .iter()
+                .all(|key| applies_to[key].as_array().is_none_or(|t| !t.is_empty()))
+        })
+        .collect();
+    if count > 0 && actions.is_empty() {
+        return Err(miette!(
+            "the schema has no actions which apply to any principal and resource"
+        ));
+    }
+    let requests = (0..count)
+        .filter_map(|_| {
+            let (namespace, id, applies_to) = *actions.get(generator.rng.below(actions.len()))?;
+            Some(generator.request(namespace, id, applies_to))
+        })
+        .collect();
+
+    let entities = info
+        .entity_type_names()
+        .into_iter()
+        .flat_map(|ty| (0..ids_per_type).map(move |i| (ty, i)))
+        .map(|(ty, i)| generator.entity(ty, i))
+        .collect();
+    Ok(GeneratedData {
+        requests,
+        entities: Value::Array(entities),
+    })
+}
+
+/// Pseudorandom number generator (SplitMix64). Good statistical quality isn't
+/// needed here, only reproducibility from a seed.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`, which must be nonzero
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// `true` with probability `percent` / 100
+    fn chance(&mut self, percent: u64) -> bool {
+        self.next_u64() % 100 < percent
+    }
+}
+
+struct Generator<'a> {
+    info: &'a SchemaInfo<'a>,
+    rng: Rng,
+    ids_per_type: usize,
+}
+
+impl Generator<'_> {
+    /// The id of entity number `i` of type `ty`
+    fn id(ty: &str, i: usize) -> String {
+        let name = ty.rsplit("::").next().unwrap_or(ty);
+        format!("{}{i}", name.to_lowercase())
+    }
+
+    /// A random entity of type `ty`, as an entity reference in the JSON
+    /// entity format
+    fn entity_ref(&mut self, ty: &str) -> Value {
+        let i = self.rng.below(self.ids_per_type);
+        json!({ "type": ty, "id": Self::id(ty, i) })
+    }
+
+    /// A random entity of one of the types listed under `key` in `applies_to`,
+    /// as an entity uid in Cedar syntax, or `None` if the action applies to
+    /// unspecified entities
+    fn scope_entity(&mut self, namespace: &str, applies_to: &Value, key: &str) -> Option<String> {
+        let types = applies_to[key].as_array()?;
+        let ty = qualify(namespace, types.get(self.rng.below(types.len()))?.as_str()?);
+        let uid = self.entity_ref(&ty);
+        Some(format!("{ty}::{}", uid.get("id")?))
+    }
+
+    fn request(&mut self, namespace: &str, id: &str, applies_to: &Value) -> Value {
+        let principal = self.scope_entity(namespace, applies_to, "principalTypes");
+        let resource = self.scope_entity(namespace, applies_to, "resourceTypes");
+        let action = format!("{}::{}", qualify(namespace, "Action"), Value::from(id));
+        let context = match applies_to.get("context") {
+            Some(ty) => self.value(namespace, ty),
+            None => json!({}),
+        };
+        let mut request = Map::new();
+        if let Some(principal) = principal {
+            request.insert("principal".into(), principal.into());
+        }
+        request.insert("action".into(), action.into());
+        if let Some(resource) = resource {
+            request.insert("resource".into(), resource.into());
+        }
+        request.insert("context".into(), context);
+        Value::Object(request)
+    }
+
+    /// Entity number `i` of type `ty`, in the JSON entity format
+    fn entity(&mut self, ty: &str, i: usize) -> Value {
+        let schema_info = self.info;
+        let Some(info) = schema_info.entity_type(ty) else {
+            return Value::Null;
+        };
+        let attrs = match info.attributes {
+            Some(attributes) => self.record(info.namespace, attributes),
+            None => json!({}),
+        };
+        let mut parents = vec![];
+        for parent_ty in &info.member_of {
+            // parents which could have this entity as an ancestor come
+            // earlier in the numbering, so the hierarchy has no cycles
+            let candidates = if parent_ty == ty || schema_info.can_be_in(parent_ty, ty) {
+                i
+            } else {
+                self.ids_per_type
+            };
+            for j in 0..candidates {
+                if self.rng.chance(30) {
+                    parents.push(json!({ "type": parent_ty, "id": Self::id(parent_ty, j) }));
+                }
+            }
+        }
+        json!({
+            "uid": { "type": ty, "id": Self::id(ty, i) },
+            "attrs": attrs,
+            "parents": parents,
+        })
+    }
+
+    /// A random record with the attributes `attributes`, written in
+    /// `namespace`. Optional attributes are present half of the time.
+    fn record(&mut self, namespace: &str, attributes: &Map<String, Value>) -> Value {
+        let mut record = Map::new();
+        for (name, ty) in attributes {
+            if ty["required"] == false && self.rng.chance(50) {
+                continue;
+            }
+            record.insert(name.clone(), self.value(namespace, ty));
+        }
+        Value::Object(record)
+    }
+
+    /// A random value of the JSON schema type `ty`, written in `namespace`,
+    /// in the JSON format for attribute values
+    fn value(&mut self, namespace: &str, ty: &Value) -> Value {
+        match ty["type"].as_str().unwrap_or_default() {
+            "Boolean" => self.rng.chance(50).into(),
+            "Long" => (self.rng.below(201) as i64 - 100).into(),
+            "String" => format!("str{}", self.rng.below(10)).into(),
+            "Set" => {
+                let len = self.rng.below(4);
+                (0..len)
+                    .map(|_| self.value(namespace, &ty["element"]))
+                    .collect()
+            }
+            "Record" => match ty["attributes"].as_object() {
+                Some(attributes) => self.record(namespace, attributes),
+                None => json!({}),
+            },
+            "Entity" => {
+                let entity_ty = qualify(namespace, ty["name"].as_str().unwrap_or_default());
+                json!({ "__entity": self.entity_ref(&entity_ty) })
+            }
+            "Extension" => match ty["name"].as_str().unwrap_or_default() {
+                "ipaddr" => json!({ "__extn": {
+                    "fn": "ip",
+                    "arg": format!("10.0.{}.{}", self.rng.below(4), self.rng.below(256)),
+                } }),
+                "decimal" => json!({ "__extn": {
+                    "fn": "decimal",
+                    "arg": format!("{}.{:04}", self.rng.below(100), self.rng.below(10000)),
+                } }),
+                _ => Value::Null,
+            },
+            common => match self.info.common_type(namespace, common) {
+                Some((namespace, ty)) => self.value(namespace, ty),
+                None => Value::Null,
+            },
+        }
+    }
+}
@@ -0,0 +1,479 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Infers a draft schema from entity data, used by the `infer-schema`
+//! subcommand.
+//!
+//! Every entity type, attribute, and parent type which appears in the data is
+//! declared. An attribute is required if every entity of its type has it.
+//! Anything the data doesn't determine, e.g., the element type of sets which
+//! are always empty, is reported as an [`Ambiguity`] for the user to review.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
+
+use miette::{miette, Diagnostic, Result, WrapErr};
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+use crate::entity_uid;
+use crate::translate_schema::validate_json;
+
+/// Something about the inferred schema which the entity data doesn't
+/// determine
+#[derive(Debug, Clone, PartialEq, Eq, Diagnostic, Error)]
+#[error("{location}: {message}")]
+#[diagnostic(severity(Warning))]
+pub struct Ambiguity {
+    /// Entity type, action, or attribute the ambiguity is about, e.g.,
+    /// `User.address.zip`
+    pub location: String,
+    /// What is ambiguous, and what the inferred schema assumes
+    pub message: String,
+}
+
+/// A schema inferred from entity data
+#[derive(Debug, Clone, PartialEq)]
+pub struct InferredSchema {
+    /// The schema, in the JSON schema format
+    pub schema: Value,
+    /// Choices made while inferring the schema which should be reviewed
+    pub ambiguities: Vec<Ambiguity>,
+}
+
+/// Infer a schema from `entities`, a list of entities in the JSON entity
+/// format
+pub fn infer_schema(entities: &Value) -> Result<InferredSchema> {
+    let entities = entities
+        .as_array()
+        .ok_or_else(|| miette!("expected entities to be a JSON array"))?;
+    let mut inferrer = Inferrer::default();
+    for (i, entity) in entities.iter().enumerate() {
+        inferrer
+            .entity(entity)
+            .ok_or_else(|| miette!("entity {i} has no valid `uid`"))?;
+    }
+    let schema = inferrer.schema();
+    validate_json(&schema).wrap_err("inferred schema is invalid")?;
+    Ok(InferredSchema {
+        schema,
+        ambiguities: inferrer.ambiguities,
+    })
+}
+
+/// The type of an attribute, inferred from its values
+#[derive(Debug, Clone, PartialEq)]
+enum Ty {
+    Boolean,
+    Long,
+    String,
+    /// The element type is `None` if every set is empty
+    Set(Option<Box<Ty>>),
+    Record(Record),
+    Entity(String),
+    Extension(String),
+}
+
+impl Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ty::Boolean => write!(f, "Boolean"),
+            Ty::Long => write!(f, "Long"),
+            Ty::String => write!(f, "String"),
+            Ty::Set(_) => write!(f, "Set"),
+            Ty::Record(_) => write!(f, "Record"),
+            Ty::Entity(name) => write!(f, "entity type {name}"),
+            Ty::Extension(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+/// A record type, inferred from `count` values
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Record {
+    count: usize,
+    /// The type of each attribute, and the number of values which have it
+    attrs: BTreeMap<String, (Ty, usize)>,
+}
+
+/// What's known about an entity type from the data
+#[derive(Debug, Default)]
+struct EntityType {
+    /// Attributes of the entities of this type, or `None` if no entity of
+    /// this type is in the data
+    shape: Option<Record>,
+    member_of: BTreeSet<String>,
+}
+
+/// What's known about an action from the data
+#[derive(Debug, Default)]
+struct Action {
+    member_of: BTreeSet<(String, String)>,
+}
+
+#[derive(Debug, Default)]
+struct Inferrer {
+    entity_types: BTreeMap<String, EntityType>,
+    /// Actions by namespace and id
+    actions: BTreeMap<(String, String), Action>,
+    ambiguities: Vec<Ambiguity>,
+}
+
+impl Inferrer {
+    fn ambiguity(&mut self, location: &str, message: String) {
+        let ambiguity = Ambiguity {
+            location: location.to_owned(),
+            message,
+        };
+        if !self.ambiguities.contains(&ambiguity) {
+            self.ambiguities.push(ambiguity);
+        }
+    }
+
+    /// Add `entity`, in the JSON entity format, to what's known. Returns
+    /// `None` if it doesn't have a valid uid.
+    fn entity(&mut self, entity: &Value) -> Option<()> {
+        let (ty, id) = entity_uid(&entity["uid"])?;
+        let parents: Vec<(String, String)> = entity["parents"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(entity_uid)
+            .collect();
+        if let Some(namespace) = action_namespace(&ty) {
+            let key = (namespace.to_owned(), id);
+            let attrs_empty = entity["attrs"].as_object().is_none_or(Map::is_empty);
+            if !attrs_empty {
+                self.ambiguity(
+                    &format!("{ty}::{}", Value::from(key.1.as_str())),
+                    "action attributes aren't declared in the inferred schema".to_owned(),
+                );
+            }
+            let action = self.actions.entry(key).or_default();
+            for (parent_ty, parent_id) in parents {
+                if action_namespace(&parent_ty).is_some() {
+                    action.member_of.insert((parent_ty, parent_id));
+                }
+            }
+            return Some(());
+        }
+
+        let record = match entity["attrs"].as_object() {
+            Some(attrs) => self.record(&ty, attrs),
+            None => Record {
+                count: 1,
+                attrs: BTreeMap::new(),
+            },
+        };
+        let shape = match self.entity_types.get_mut(&ty).and_then(|t| t.shape.take()) {
+            Some(shape) => self.unify_records(&ty, shape, record),
+            None => record,
+        };
+        let entity_type = self.entity_types.entry(ty).or_default();
+        entity_type.shape = Some(shape);
+        entity_type
+            .member_of
+            .extend(parents.iter().map(|(parent_ty, _)| parent_ty.clone()));
+        for (parent_ty, _) in parents {
+            self.referenced(&parent_ty);
+        }
+        Some(())
+    }
+
+    /// Note that the entity type `ty` is referenced, so that it's declared
+    /// even if no entity of that type is in the data
+    fn referenced(&mut self, ty: &str) {
+        self.entity_types.entry(ty.to_owned()).or_default();
+    }
+
+    /// The record type of `attrs`, the attributes at `path`
+    fn record(&mut self, path: &str, attrs: &Map<String, Value>) -> Record {
+        let mut record = Record {
+            count: 1,
+            attrs: BTreeMap::new(),
+        };
+        for (name, value) in attrs {
+            let attr_path = format!("{path}.{name}");
+            if let Some(ty) = self.ty(&attr_path, value) {
+                record.attrs.insert(name.clone(), (ty, 1));
+            }
+        }
+        record
+    }
+
+    /// The type of `value`, an attribute value at `path` in the JSON entity
+    /// format, or `None` if it has no type in a schema
+    fn ty(&mut self, path: &str, value: &Value) -> Option<Ty> {
+        match value {
+            Value::Bool(_) => Some(Ty::Boolean),
+            Value::Number(n) if n.is_i64() => Some(Ty::Long),
+            Value::Number(_) => {
+                self.ambiguity(
+                    path,
+                    "Cedar has no floating-point type, so this attribute isn't declared".to_owned(),
+                );
+                None
+            }
+            Value::String(_) => Some(Ty::String),
+            Value::Null => {
+                self.ambiguity(
+                    path,
+                    "`null` isn't a Cedar value, so this attribute isn't declared".to_owned(),
+                );
+                None
+            }
+            Value::Array(elements) => {
+                let mut element_ty = None;
+                for element in elements {
+                    let Some(ty) = self.ty(path, element) else {
+                        continue;
+                    };
+                    element_ty = Some(match element_ty {
+                        Some(prev) => self.unify(path, prev, ty),
+                        None => ty,
+                    });
+                }
+                Some(Ty::Set(element_ty.map(Box::new)))
+            }
+            Value::Object(obj) => {
+                if let Some(uid) = obj.get("__entity") {
+                    let (ty, _) = entity_uid(uid)?;
+                    self.referenced(&ty);
+                    Some(Ty::Entity(ty))
+                } else if let Some(extn) = obj.get("__extn") {
+                    match extn["fn"].as_str() {
+                        Some("ip" | "ipaddr") => Some(Ty::Extension("ipaddr".to_owned())),
+                        Some("decimal") => Some(Ty::Extension("decimal".to_owned())),
+                        _ => {
+                            self.ambiguity(
+                                path,
+                                "unknown extension function, so this attribute isn't declared"
+                                    .to_owned(),
+                            );
+                            None
+                        }
+                    }
+                } else if obj.contains_key("__expr") {
+                    self.ambiguity(
+                        path,
+                        "the type of an `__expr` value can't be inferred, so this attribute \
+                         isn't declared"
+                            .to_owned(),
+                    );
+                    None
+                } else if obj.len() == 2
+                    && obj.get("type").is_some_and(Value::is_string)
+                    && obj.get("id").is_some_and(Value::is_string)
+                {
+                    let (ty, _) = entity_uid(value)?;
+                    self.ambiguity(
+                        path,
+                        format!(
+                            "assuming this is a reference to an entity of type {ty}, not a record"
+                        ),
+                    );
+                    self.referenced(&ty);
+                    Some(Ty::Entity(ty))
+                } else {
+                    Some(Ty::Record(self.record(path, obj)))
+                }
+            }
+        }
+    }
+
+    /// A type which `a` and `b`, the types of values at `path`, both have.
+    /// If there is none, `a` is used and the conflict is reported.
+    fn unify(&mut self, path: &str, a: Ty, b: Ty) -> Ty {
+        match (a, b) {
+            (Ty::Set(a), Ty::Set(b)) => Ty::Set(match (a, b) {
+                (Some(a), Some(b)) => Some(Box::new(self.unify(path, *a, *b))),
+                (a, None) | (None, a) => a,
+            }),
+            (Ty::Record(a), Ty::Record(b)) => Ty::Record(self.unify_records(path, a, b)),
+            (a, b) if a == b => a,
+            (a, b) => {
+                self.ambiguity(
+                    path,
+                    format!("found values of both {a} and {b}; assuming {a}"),
+                );
+                a
+            }
+        }
+    }
+
+    /// A record type which the records of types `a` and `b` at `path` both
+    /// have
+    fn unify_records(&mut self, path: &str, a: Record, b: Record) -> Record {
+        let mut attrs = a.attrs;
+        for (name, (ty, count)) in b.attrs {
+            let merged = match attrs.remove(&name) {
+                Some((prev, prev_count)) => (
+                    self.unify(&format!("{path}.{name}"), prev, ty),
+                    prev_count + count,
+                ),
+                None => (ty, count),
+            };
+            attrs.insert(name, merged);
+        }
+        Record {
+            count: a.count + b.count,
+            attrs,
+        }
+    }
+
+    /// The inferred schema, in the JSON schema format. Ambiguities found while
+    /// writing it are added to `self.ambiguities`.
+    fn schema(&mut self) -> Value {
+        let mut namespaces: BTreeMap<String, (Map<String, Value>, Map<String, Value>)> =
+            BTreeMap::new();
+        let entity_types = std::mem::take(&mut self.entity_types);
+        for (ty, info) in &entity_types {
+            let (namespace, name) = split_name(ty);
+            let mut def = Map::new();
+            if !info.member_of.is_empty() {
+                let member_of: Vec<String> = info
+                    .member_of
+                    .iter()
+                    .map(|parent| relative_name(namespace, parent))
+                    .collect();
+                def.insert("memberOfTypes".into(), member_of.into());
+            }
+            match &info.shape {
+                Some(shape) if !shape.attrs.is_empty() => {
+                    def.insert("shape".into(), self.record_json(namespace, ty, shape));
+                }
+                Some(_) => (),
+                None => self.ambiguity(
+                    ty,
+                    "no entity of this type is in the data, so its attributes are unknown"
+                        .to_owned(),
+                ),
+            }
+            namespaces
+                .entry(namespace.to_owned())
+                .or_default()
+                .0
+                .insert(name.to_owned(), Value::Object(def));
+        }
+        let actions = std::mem::take(&mut self.actions);
+        for ((namespace, id), action) in &actions {
+            let mut def = Map::new();
+            if !action.member_of.is_empty() {
+                let member_of: Vec<Value> = action
+                    .member_of
+                    .iter()
+                    .map(|(parent_ty, parent_id)| {
+                        if action_namespace(parent_ty) == Some(namespace.as_str()) {
+                            json!({ "id": parent_id })
+                        } else {
+                            json!({ "id": parent_id, "type": parent_ty })
+                        }
+                    })
+                    .collect();
+                def.insert("memberOf".into(), member_of.into());
+            }
+            namespaces
+                .entry(namespace.clone())
+                .or_default()
+                .1
+                .insert(id.clone(), Value::Object(def));
+        }
+        if !actions.is_empty() {
+            self.ambiguity(
+                "actions",
+                "the principals and resources actions apply to can't be inferred from entity \
+                 data; add `appliesTo` to each action"
+                    .to_owned(),
+            );
+        }
+        Value::Object(
+            namespaces
+                .into_iter()
+                .map(|(namespace, (entity_types, actions))| {
+                    (
+                        namespace,
+                        json!({ "entityTypes": entity_types, "actions": actions }),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// `record`, the type of values at `path`, in the JSON schema format for
+    /// a type written in `namespace`
+    // PANIC SAFETY: the JSON of a type is an object, so assigning to its keys doesn't panic
+    #[allow(clippy::indexing_slicing)]
+    fn record_json(&mut self, namespace: &str, path: &str, record: &Record) -> Value {
+        let mut attributes = Map::new();
+        for (name, (ty, count)) in &record.attrs {
+            let mut attr = self.ty_json(namespace, &format!("{path}.{name}"), ty);
+            if *count < record.count {
+                attr["required"] = false.into();
+            }
+            attributes.insert(name.clone(), attr);
+        }
+        json!({ "type": "Record", "attributes": attributes })
+    }
+
+    /// `ty`, the type of values at `path`, in the JSON schema format for a
+    /// type written in `namespace`
+    fn ty_json(&mut self, namespace: &str, path: &str, ty: &Ty) -> Value {
+        match ty {
+            Ty::Boolean => json!({ "type": "Boolean" }),
+            Ty::Long => json!({ "type": "Long" }),
+            Ty::String => json!({ "type": "String" }),
+            Ty::Set(element) => {
+                let element = match element {
+                    Some(element) => self.ty_json(namespace, path, element),
+                    None => {
+                        self.ambiguity(
+                            path,
+                            "every set is empty, so the element type is unknown; assuming String"
+                                .to_owned(),
+                        );
+                        json!({ "type": "String" })
+                    }
+                };
+                json!({ "type": "Set", "element": element })
+            }
+            Ty::Record(record) => self.record_json(namespace, path, record),
+            Ty::Entity(name) => json!({ "type": "Entity", "name": relative_name(namespace, name) }),
+            Ty::Extension(name) => json!({ "type": "Extension", "name": name }),
+        }
+    }
+}
+
+/// The namespace and basename of the entity type `ty`
+fn split_name(ty: &str) -> (&str, &str) {
+    ty.rsplit_once("::").unwrap_or(("", ty))
+}
+
+/// The namespace of actions of type `ty`, or `None` if `ty` isn't an action
+/// type
+fn action_namespace(ty: &str) -> Option<&str> {
+    match split_name(ty) {
+        (namespace, "Action") => Some(namespace),
+        _ => None,
+    }
+}
+
+/// The name for the entity type `ty` when written in `namespace`
+fn relative_name(namespace: &str, ty: &str) -> String {
+    match split_name(ty) {
+        (ns, name) if ns == namespace => name.to_owned(),
+        _ => ty.to_owned(),
+    }
+}
@@ -19,6 +19,23 @@
 // omitted.
 #![allow(clippy::needless_return)]
 
+pub mod analyze;
+pub mod bench;
+pub mod conformance;
+pub mod diff;
+pub mod document_schema;
+pub mod generate;
+pub mod infer;
+pub mod loader;
+pub mod repl;
+pub mod scaffold;
+mod schema_info;
+pub mod slice;
+mod text_diff;
+pub mod translate_schema;
+pub mod visualize;
+pub mod watch;
+
 use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
 use miette::{miette, IntoDiagnostic, NamedSource, Report, Result, WrapErr};
 use serde::{Deserialize, Serialize};
@@ -29,11 +46,12 @@ use std::{
     path::Path,
     process::{ExitCode, Termination},
     str::FromStr,
-    time::Instant,
+    sync::OnceLock,
+    time::{Duration, Instant},
 };
 
 use cedar_policy::*;
-use cedar_policy_formatter::{policies_str_to_pretty, Config};
+use cedar_policy_formatter::{is_formatted, policies_str_to_pretty, FormatterConfig};
 
 /// Basic Cedar CLI for evaluating authorization queries
 #[derive(Parser)]
@@ -51,6 +69,16 @@ pub struct Cli {
         value_enum
     )]
     pub err_fmt: ErrorFormat,
+    /// The output format to use for command results. `json` also reports
+    /// errors as JSON, regardless of `--error-format`.
+    #[arg(
+        global = true,
+        long = "format",
+        env = "CEDAR_OUTPUT_FORMAT",
+        default_value_t,
+        value_enum
+    )]
+    pub output_format: OutputFormat,
 }
 
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
@@ -80,6 +108,58 @@ impl Display for ErrorFormat {
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable results.
+    #[default]
+    Human,
+    /// Machine-readable JSON results, printed as a single line. Commands whose
+    /// output is a policy set or schema are unaffected.
+    Json,
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                OutputFormat::Human => "human",
+                OutputFormat::Json => "json",
+            }
+        )
+    }
+}
+
+static OUTPUT_FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+/// Set the format in which the command functions in this crate print their
+/// results. Only the first call has an effect; if it is never called, results
+/// are printed in `OutputFormat::Human`.
+pub fn set_output_format(format: OutputFormat) {
+    let _ = OUTPUT_FORMAT.set(format);
+}
+
+fn output_format() -> OutputFormat {
+    OUTPUT_FORMAT.get().copied().unwrap_or_default()
+}
+
+/// Print `value` as a single line of JSON, for `--format json`
+fn print_json(value: &impl Serialize) -> Result<()> {
+    println!("{}", serde_json::to_string(value).into_diagnostic()?);
+    Ok(())
+}
+
+/// Render `diagnostic` in miette's JSON format, which includes its labeled
+/// spans and related diagnostics
+fn diagnostic_json(diagnostic: &dyn miette::Diagnostic) -> serde_json::Value {
+    let mut out = String::new();
+    match miette::JSONReportHandler::new().render_report(&mut out, diagnostic) {
+        Ok(()) => serde_json::from_str(&out).unwrap_or_else(|_| serde_json::Value::String(out)),
+        Err(_) => serde_json::Value::String(diagnostic.to_string()),
+    }
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Evaluate an authorization request
@@ -90,12 +170,46 @@ pub enum Commands {
     Validate(ValidateArgs),
     /// Check that policies successfully parse
     CheckParse(CheckParseArgs),
-    /// Link a template
-    Link(LinkArgs),
+    /// Link templates and manage a file of template-linked policies
+    #[command(subcommand)]
+    Link(LinkCommands),
     /// Format a policy set
     Format(FormatArgs),
     /// Create a Cedar project
     New(NewArgs),
+    /// Report shadowed, unsatisfiable, and unused policies
+    Analyze(AnalyzeArgs),
+    /// Interactively evaluate requests and expressions
+    Repl(ReplArgs),
+    /// Translate a schema between the JSON and Cedar schema formats
+    TranslateSchema(TranslateSchemaArgs),
+    /// Evaluate an authorization request with unknowns, printing the
+    /// residual policies if there is no concrete decision. This command is
+    /// experimental and requires the `partial-eval` feature.
+    PartiallyAuthorize(PartiallyAuthorizeArgs),
+    /// Report added, removed, and changed policies between two policy sets
+    DiffPolicies(DiffPoliciesArgs),
+    /// Report which entity types, attributes, and ancestors the policies for
+    /// an action may read
+    SliceEntities(SliceEntitiesArgs),
+    /// Print a skeleton policy for an action, based on the schema
+    NewPolicy(NewPolicyArgs),
+    /// Time parsing, validation, entity loading, and authorization
+    Bench(BenchArgs),
+    /// Generate random requests, and optionally entities, which conform to a
+    /// schema
+    GenerateRequests(GenerateRequestsArgs),
+    /// Print Graphviz DOT graphs of the entity hierarchy or of which policies
+    /// apply to which entity types
+    #[command(subcommand)]
+    Visualize(VisualizeCommands),
+    /// Infer a draft schema from entity data
+    InferSchema(InferSchemaArgs),
+    /// Check that entity data conforms to a schema, reporting every violation
+    ValidateEntities(ValidateEntitiesArgs),
+    /// Print documentation for a schema, describing its entity types,
+    /// actions, and action groups
+    DocumentSchema(DocumentSchemaArgs),
 }
 
 #[derive(Args, Debug)]
@@ -114,6 +228,10 @@ pub struct ValidateArgs {
     /// experimental `partial-validate` feature enabled.
     #[arg(long = "partial-validate")]
     pub partial_validate: bool,
+    /// Validate again whenever the policies or schema change, until
+    /// interrupted
+    #[arg(long, requires = "policies_file")]
+    pub watch: bool,
 }
 
 #[derive(Args, Debug)]
@@ -143,7 +261,7 @@ pub struct RequestArgs {
     /// fields "principal", "action", "resource", and "context", where "context"
     /// is a (possibly empty) map from keys to values. This option replaces
     /// --principal, --action, etc.
-    #[arg(long = "request-json", value_name = "FILE", conflicts_with_all = &["principal", "action", "resource", "context_json_file"])]
+    #[arg(long = "request-json", visible_alias = "request", value_name = "FILE", conflicts_with_all = &["principal", "action", "resource", "context_json_file"])]
     pub request_json_file: Option<String>,
     /// Whether to enable request validation. This has no effect if a schema is
     /// not provided.
@@ -167,47 +285,7 @@ impl RequestArgs {
                 let qjson: RequestJSON = serde_json::from_str(&jsonstring)
                     .into_diagnostic()
                     .wrap_err_with(|| format!("failed to parse request-json file {jsonfile}"))?;
-                let principal = qjson
-                    .principal
-                    .map(|s| {
-                        s.parse().wrap_err_with(|| {
-                            format!("failed to parse principal in {jsonfile} as entity Uid")
-                        })
-                    })
-                    .transpose()?;
-                let action = qjson
-                    .action
-                    .map(|s| {
-                        s.parse().wrap_err_with(|| {
-                            format!("failed to parse action in {jsonfile} as entity Uid")
-                        })
-                    })
-                    .transpose()?;
-                let resource = qjson
-                    .resource
-                    .map(|s| {
-                        s.parse().wrap_err_with(|| {
-                            format!("failed to parse resource in {jsonfile} as entity Uid")
-                        })
-                    })
-                    .transpose()?;
-                let context = Context::from_json_value(
-                    qjson.context,
-                    schema.and_then(|s| Some((s, action.as_ref()?))),
-                )
-                .wrap_err_with(|| format!("failed to create a context from {jsonfile}"))?;
-                Request::new(
-                    principal,
-                    action,
-                    resource,
-                    context,
-                    if self.request_validation {
-                        schema
-                    } else {
-                        None
-                    },
-                )
-                .map_err(|e| miette!("{e}"))
+                qjson.get_request(jsonfile, schema, self.request_validation)
             }
             None => {
                 let principal = self
@@ -268,8 +346,9 @@ impl RequestArgs {
 /// This struct contains the arguments that together specify an input policy or policy set.
 #[derive(Args, Debug)]
 pub struct PoliciesArgs {
-    /// File containing the static Cedar policies and/or templates. If not provided, read policies from stdin.
-    #[arg(short, long = "policies", value_name = "FILE")]
+    /// File containing the static Cedar policies and/or templates, or a directory
+    /// of `.cedar` files. If not provided, read policies from stdin.
+    #[arg(short, long = "policies", value_name = "PATH")]
     pub policies_file: Option<String>,
     /// Format of policies in the `--policies` file
     #[arg(long = "policy-format", default_value_t, value_enum)]
@@ -279,9 +358,20 @@ pub struct PoliciesArgs {
 impl PoliciesArgs {
     /// Turn this `PoliciesArgs` into the appropriate `PolicySet` object
     fn get_policy_set(&self) -> Result<PolicySet> {
-        match self.policy_format {
-            PolicyFormat::Human => read_policy_set(self.policies_file.as_ref()),
-            PolicyFormat::Json => read_json_policy(self.policies_file.as_ref()),
+        let dir = self
+            .policies_file
+            .as_ref()
+            .filter(|p| Path::new(p).is_dir());
+        match (self.policy_format, dir) {
+            (PolicyFormat::Human, Some(dir)) => {
+                let sources = loader::read_policy_sources(Some(dir))?;
+                Ok(analyze::LoadedPolicies::new(sources)?.policy_set)
+            }
+            (PolicyFormat::Human, None) => read_policy_set(self.policies_file.as_ref()),
+            (PolicyFormat::Json, Some(dir)) => Err(miette!(
+                "{dir} is a directory, which is only supported for policies in human syntax"
+            )),
+            (PolicyFormat::Json, None) => read_json_policy(self.policies_file.as_ref()),
         }
     }
 }
@@ -312,6 +402,37 @@ pub struct AuthorizeArgs {
     /// Time authorization and report timing information
     #[arg(short, long)]
     pub timing: bool,
+    /// File containing newline-delimited JSON requests, each in the format
+    /// expected by --request-json. Every request is authorized against the
+    /// same policies, entities, and schema, and one JSON decision is printed
+    /// per line. Exits with a failure if any request could not be evaluated.
+    #[arg(long = "requests", value_name = "FILE", conflicts_with_all = &["principal", "action", "resource", "context_json_file", "request_json_file", "timing"])]
+    pub requests_file: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    /// Policies args (incorporated by reference)
+    #[command(flatten)]
+    pub policies: PoliciesArgs,
+    /// File containing template linked policies
+    #[arg(short = 'k', long = "template-linked", value_name = "FILE")]
+    pub template_linked_file: Option<String>,
+    /// File containing the schema. If provided, validation is timed, and
+    /// entities and requests are checked against the schema.
+    #[arg(short, long = "schema", value_name = "FILE")]
+    pub schema_file: Option<String>,
+    /// File containing JSON representation of the Cedar entity hierarchy
+    #[arg(long = "entities", value_name = "FILE")]
+    pub entities_file: String,
+    /// File containing newline-delimited JSON requests, each in the format
+    /// expected by --request-json
+    #[arg(long = "requests", value_name = "FILE")]
+    pub requests_file: String,
+    /// Number of times to run each phase. Authorization is timed once per
+    /// request in each iteration.
+    #[arg(short = 'n', long, default_value_t = 100)]
+    pub iterations: usize,
 }
 
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
@@ -323,6 +444,19 @@ pub enum PolicyFormat {
     Json,
 }
 
+#[derive(Subcommand, Debug)]
+pub enum LinkCommands {
+    /// Link a template, adding the template-linked policy to a file
+    Create(LinkArgs),
+    /// List the template-linked policies in a file
+    List(LinkListArgs),
+    /// Remove a template-linked policy from a file
+    Remove(LinkRemoveArgs),
+    /// Print the policy set with every template-linked policy instantiated
+    /// and without templates, e.g., for deployment
+    Export(LinkExportArgs),
+}
+
 #[derive(Args, Debug)]
 pub struct LinkArgs {
     /// Policies args (incorporated by reference)
@@ -342,6 +476,36 @@ pub struct LinkArgs {
     pub arguments: Arguments,
 }
 
+#[derive(Args, Debug)]
+pub struct LinkListArgs {
+    /// File containing template-linked policies
+    #[arg(short = 'k', long = "template-linked", value_name = "FILE")]
+    pub template_linked_file: String,
+    /// Only list template-linked policies of this template
+    #[arg(long)]
+    pub template_id: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct LinkRemoveArgs {
+    /// File containing template-linked policies
+    #[arg(short = 'k', long = "template-linked", value_name = "FILE")]
+    pub template_linked_file: String,
+    /// Id of the template-linked policy to remove
+    #[arg(long)]
+    pub link_id: String,
+}
+
+#[derive(Args, Debug)]
+pub struct LinkExportArgs {
+    /// Policies args (incorporated by reference)
+    #[command(flatten)]
+    pub policies: PoliciesArgs,
+    /// File containing template-linked policies
+    #[arg(short = 'k', long = "template-linked", value_name = "FILE")]
+    pub template_linked_file: String,
+}
+
 #[derive(Args, Debug)]
 pub struct FormatArgs {
     /// File containing the static Cedar policies and/or templates. If not provided, read policies from stdin.
@@ -355,6 +519,275 @@ pub struct FormatArgs {
     /// Custom indentation width (default: 2).
     #[arg(short, long, value_name = "INT", default_value_t = 2)]
     pub indent_width: isize,
+
+    /// Sort policies by `@id` annotation, effect, and scope, instead of keeping their input order.
+    #[arg(long)]
+    pub sort: bool,
+
+    /// Format again whenever the policies change, until interrupted
+    #[arg(long, requires = "policies_file")]
+    pub watch: bool,
+
+    /// Instead of printing the formatted policies, check that they are already
+    /// formatted, exiting with code 5 if they are not
+    #[arg(long)]
+    pub check: bool,
+
+    /// With --check, print a unified diff of the changes formatting would make
+    #[arg(long, requires = "check")]
+    pub diff: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct AnalyzeArgs {
+    /// File containing the static Cedar policies and/or templates, or a directory
+    /// of `.cedar` files. If not provided, read policies from stdin.
+    #[arg(short, long = "policies", value_name = "PATH")]
+    pub policies_path: Option<String>,
+    /// File containing the schema. If provided, the schema is also used to find
+    /// policies that can never apply.
+    #[arg(short, long = "schema", value_name = "FILE")]
+    pub schema_file: Option<String>,
+    /// File containing template-linked policies
+    #[arg(short = 'k', long = "template-linked", value_name = "FILE")]
+    pub template_linked_file: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ReplArgs {
+    /// File containing the static Cedar policies and/or templates. If not
+    /// provided, the REPL starts with an empty policy set.
+    #[arg(short, long = "policies", value_name = "FILE")]
+    pub policies_file: Option<String>,
+    /// File containing template linked policies
+    #[arg(short = 'k', long = "template-linked", value_name = "FILE")]
+    pub template_linked_file: Option<String>,
+    /// File containing schema information
+    ///
+    /// Used to populate the store with action entities, for schema-based
+    /// parsing of entity hierarchy and context, and for request validation, if
+    /// present
+    #[arg(short, long = "schema", value_name = "FILE")]
+    pub schema_file: Option<String>,
+    /// File containing JSON representation of the Cedar entity hierarchy.
+    /// This is optional; if not present, we'll just use an empty hierarchy.
+    #[arg(long = "entities", value_name = "FILE")]
+    pub entities_file: Option<String>,
+    /// File to load history from and save history to
+    #[arg(long = "history", value_name = "FILE")]
+    pub history_file: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct PartiallyAuthorizeArgs {
+    /// Principal for the request, e.g., User::"alice". If not provided, the
+    /// principal is unknown.
+    #[arg(short = 'l', long)]
+    pub principal: Option<String>,
+    /// Action for the request, e.g., Action::"view". If not provided, the
+    /// action is unknown.
+    #[arg(short, long)]
+    pub action: Option<String>,
+    /// Resource for the request, e.g., File::"myfile.txt". If not provided,
+    /// the resource is unknown.
+    #[arg(short, long)]
+    pub resource: Option<String>,
+    /// File containing a JSON object representing the context for the request.
+    /// If not provided, the context is unknown.
+    #[arg(short, long = "context", value_name = "FILE")]
+    pub context_json_file: Option<String>,
+    /// Policies args (incorporated by reference)
+    #[command(flatten)]
+    pub policies: PoliciesArgs,
+    /// File containing template linked policies
+    #[arg(short = 'k', long = "template-linked", value_name = "FILE")]
+    pub template_linked_file: Option<String>,
+    /// File containing schema information
+    ///
+    /// Used to populate the store with action entities, for schema-based
+    /// parsing of entity hierarchy and context, and for request validation, if
+    /// present
+    #[arg(short, long = "schema", value_name = "FILE")]
+    pub schema_file: Option<String>,
+    /// File containing JSON representation of the Cedar entity hierarchy.
+    /// Entities which are not in this file are treated as unknown.
+    #[arg(long = "entities", value_name = "FILE")]
+    pub entities_file: Option<String>,
+    /// Format to print residual policies in
+    #[arg(long = "residual-format", default_value_t, value_enum)]
+    pub residual_format: PolicyFormat,
+}
+
+#[derive(Args, Debug)]
+pub struct DiffPoliciesArgs {
+    /// File or directory of `.cedar` files containing the old policies
+    #[arg(value_name = "OLD")]
+    pub old_policies: String,
+    /// File or directory of `.cedar` files containing the new policies
+    #[arg(value_name = "NEW")]
+    pub new_policies: String,
+    /// File containing template-linked policies for the old policies
+    #[arg(long = "old-template-linked", value_name = "FILE")]
+    pub old_template_linked_file: Option<String>,
+    /// File containing template-linked policies for the new policies
+    #[arg(long = "new-template-linked", value_name = "FILE")]
+    pub new_template_linked_file: Option<String>,
+    /// File containing the schema. If provided, its action hierarchy is used to
+    /// decide whether a changed scope was widened or narrowed.
+    #[arg(short, long = "schema", value_name = "FILE")]
+    pub schema_file: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct SliceEntitiesArgs {
+    /// Policies args (incorporated by reference)
+    #[command(flatten)]
+    pub policies: PoliciesArgs,
+    /// File containing template linked policies
+    #[arg(short = 'k', long = "template-linked", value_name = "FILE")]
+    pub template_linked_file: Option<String>,
+    /// File containing the schema, in JSON format
+    #[arg(short, long = "schema", value_name = "FILE")]
+    pub schema_file: String,
+    /// Action to report on, e.g., Action::"view"
+    #[arg(short, long)]
+    pub action: String,
+}
+
+#[derive(Args, Debug)]
+pub struct NewPolicyArgs {
+    /// File containing the schema, in JSON format
+    #[arg(short, long = "schema", value_name = "FILE")]
+    pub schema_file: String,
+    /// Action the policy applies to, e.g., Action::"view"
+    #[arg(short, long)]
+    pub action: String,
+    /// Entity type the principal is constrained to. May be omitted if the
+    /// action applies to only one principal type.
+    #[arg(long, value_name = "TYPE")]
+    pub principal_type: Option<String>,
+    /// Entity type the resource is constrained to. May be omitted if the
+    /// action applies to only one resource type.
+    #[arg(long, value_name = "TYPE")]
+    pub resource_type: Option<String>,
+    /// Generate a `forbid` policy instead of a `permit` policy
+    #[arg(long)]
+    pub forbid: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct GenerateRequestsArgs {
+    /// File containing the schema, in JSON format
+    #[arg(short, long = "schema", value_name = "FILE")]
+    pub schema_file: String,
+    /// Number of requests to generate
+    #[arg(short = 'n', long, default_value_t = 100)]
+    pub count: usize,
+    /// Seed for the random generator. The same seed and schema always generate
+    /// the same requests and entities. If not provided, a seed is chosen and
+    /// printed to stderr.
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// File to write generated entities to, in JSON format. Requests only refer
+    /// to entities in this file.
+    #[arg(long = "entities", value_name = "FILE")]
+    pub entities_file: Option<String>,
+    /// Number of entities of each entity type which requests can refer to
+    #[arg(long, default_value_t = 5)]
+    pub entities_per_type: usize,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum VisualizeCommands {
+    /// Graph the entity hierarchy, with an edge from each entity to each of
+    /// its parents
+    Entities(VisualizeEntitiesArgs),
+    /// Graph which policies can apply to which principal and resource types
+    Policies(VisualizePoliciesArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct VisualizeEntitiesArgs {
+    /// File containing JSON representation of the Cedar entity hierarchy
+    #[arg(long = "entities", value_name = "FILE")]
+    pub entities_file: String,
+}
+
+#[derive(Args, Debug)]
+pub struct VisualizePoliciesArgs {
+    /// Policies args (incorporated by reference)
+    #[command(flatten)]
+    pub policies: PoliciesArgs,
+    /// File containing template linked policies
+    #[arg(short = 'k', long = "template-linked", value_name = "FILE")]
+    pub template_linked_file: Option<String>,
+    /// File containing the schema, in JSON format
+    #[arg(short, long = "schema", value_name = "FILE")]
+    pub schema_file: String,
+}
+
+#[derive(Args, Debug)]
+pub struct ValidateEntitiesArgs {
+    /// File containing the schema, in JSON format
+    #[arg(short, long = "schema", value_name = "FILE")]
+    pub schema_file: String,
+    /// File containing JSON representation of the Cedar entity hierarchy
+    #[arg(long = "entities", value_name = "FILE")]
+    pub entities_file: String,
+}
+
+#[derive(Args, Debug)]
+pub struct InferSchemaArgs {
+    /// File containing JSON representation of the Cedar entity hierarchy
+    #[arg(long = "entities", value_name = "FILE")]
+    pub entities_file: String,
+    /// Format to write the inferred schema in
+    #[arg(long, value_enum, default_value_t)]
+    pub format: SchemaFormat,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum SchemaFormat {
+    /// Cedar's JSON schema format, documented at https://docs.cedarpolicy.com/schema/json-schema.html
+    #[default]
+    Json,
+    /// The human-readable Cedar schema format
+    Cedar,
+}
+
+#[derive(Args, Debug)]
+pub struct TranslateSchemaArgs {
+    /// File containing the schema to translate. If not provided, read the schema from stdin.
+    #[arg(short, long = "schema", value_name = "FILE")]
+    pub schema_file: Option<String>,
+    /// Format of the input schema
+    #[arg(long, value_enum)]
+    pub from: SchemaFormat,
+    /// Format to translate the schema to
+    #[arg(long, value_enum)]
+    pub to: SchemaFormat,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum DocumentFormat {
+    /// Markdown, with tables in the GitHub-flavored Markdown syntax
+    #[default]
+    Markdown,
+    /// A standalone HTML page
+    Html,
+}
+
+#[derive(Args, Debug)]
+pub struct DocumentSchemaArgs {
+    /// File containing the schema to document. If not provided, read the schema from stdin.
+    #[arg(short, long = "schema", value_name = "FILE")]
+    pub schema_file: Option<String>,
+    /// Format of the schema
+    #[arg(long, value_enum, default_value_t)]
+    pub from: SchemaFormat,
+    /// Format of the documentation
+    #[arg(long, value_enum, default_value_t)]
+    pub to: DocumentFormat,
 }
 
 #[derive(Args, Debug)]
@@ -408,6 +841,56 @@ struct RequestJSON {
     context: serde_json::Value,
 }
 
+impl RequestJSON {
+    /// Turn this `RequestJSON` into a `Request`. `source` describes where the
+    /// JSON came from, for error messages.
+    ///
+    /// `schema` is used for schema-based parsing of the context, and also (if
+    /// `request_validation` is `true`) for request validation.
+    fn get_request(
+        self,
+        source: &str,
+        schema: Option<&Schema>,
+        request_validation: bool,
+    ) -> Result<Request> {
+        let principal = self
+            .principal
+            .map(|s| {
+                s.parse().wrap_err_with(|| {
+                    format!("failed to parse principal in {source} as entity Uid")
+                })
+            })
+            .transpose()?;
+        let action = self
+            .action
+            .map(|s| {
+                s.parse()
+                    .wrap_err_with(|| format!("failed to parse action in {source} as entity Uid"))
+            })
+            .transpose()?;
+        let resource = self
+            .resource
+            .map(|s| {
+                s.parse()
+                    .wrap_err_with(|| format!("failed to parse resource in {source} as entity Uid"))
+            })
+            .transpose()?;
+        let context = Context::from_json_value(
+            self.context,
+            schema.and_then(|s| Some((s, action.as_ref()?))),
+        )
+        .wrap_err_with(|| format!("failed to create a context from {source}"))?;
+        Request::new(
+            principal,
+            action,
+            resource,
+            context,
+            if request_validation { schema } else { None },
+        )
+        .map_err(|e| miette!("{e}"))
+    }
+}
+
 #[derive(Args, Debug)]
 pub struct EvaluateArgs {
     /// Request args (incorporated by reference)
@@ -440,80 +923,846 @@ pub enum CedarExitCode {
     // The command completed successfully, but it detected a validation failure
     // in the given schema and policies.
     ValidationFailure,
+    // The command completed successfully, but the analysis reported findings
+    // for the given policies.
+    AnalysisFindings,
+    // The command completed successfully, but the given policies are not
+    // formatted.
+    Unformatted,
+}
+
+impl Termination for CedarExitCode {
+    fn report(self) -> ExitCode {
+        match self {
+            CedarExitCode::Success => ExitCode::SUCCESS,
+            CedarExitCode::Failure => ExitCode::FAILURE,
+            CedarExitCode::AuthorizeDeny => ExitCode::from(2),
+            CedarExitCode::ValidationFailure => ExitCode::from(3),
+            CedarExitCode::AnalysisFindings => ExitCode::from(4),
+            CedarExitCode::Unformatted => ExitCode::from(5),
+        }
+    }
+}
+
+pub fn check_parse(args: &CheckParseArgs) -> CedarExitCode {
+    match args.policies.get_policy_set() {
+        Ok(_) => CedarExitCode::Success,
+        Err(e) => {
+            println!("{e:?}");
+            CedarExitCode::Failure
+        }
+    }
+}
+
+pub fn validate(args: &ValidateArgs) -> CedarExitCode {
+    if args.watch {
+        let mut paths = vec![args.schema_file.as_str()];
+        paths.extend(args.policies.policies_file.as_deref());
+        return run_watching(&paths, || validate_once(args));
+    }
+    validate_once(args)
+}
+
+fn validate_once(args: &ValidateArgs) -> CedarExitCode {
+    let mode = if args.partial_validate {
+        #[cfg(not(feature = "partial-validate"))]
+        {
+            println!("Error: arguments include the experimental option `--partial-validate`, but this executable was not built with `partial-validate` experimental feature enabled");
+            return CedarExitCode::Failure;
+        }
+        #[cfg(feature = "partial-validate")]
+        ValidationMode::Partial
+    } else {
+        ValidationMode::default()
+    };
+
+    let pset = match args.policies.get_policy_set() {
+        Ok(pset) => pset,
+        Err(e) => {
+            println!("{e:?}");
+            return CedarExitCode::Failure;
+        }
+    };
+
+    let schema = match read_schema_file(&args.schema_file) {
+        Ok(schema) => schema,
+        Err(e) => {
+            println!("{e:?}");
+            return CedarExitCode::Failure;
+        }
+    };
+
+    let validator = Validator::new(schema);
+    let result = validator.validate(&pset, mode);
+    let passed = result.validation_passed()
+        && (!args.deny_warnings || result.validation_passed_without_warnings());
+
+    if output_format() == OutputFormat::Json {
+        let json = serde_json::json!({
+            "validationPassed": passed,
+            "errors": result
+                .validation_errors()
+                .map(|e| diagnostic_json(e))
+                .collect::<Vec<_>>(),
+            "warnings": result
+                .validation_warnings()
+                .map(|w| diagnostic_json(w))
+                .collect::<Vec<_>>(),
+        });
+        if let Err(e) = print_json(&json) {
+            println!("{e:?}");
+            return CedarExitCode::Failure;
+        }
+        return if passed {
+            CedarExitCode::Success
+        } else {
+            CedarExitCode::ValidationFailure
+        };
+    }
+
+    if !passed {
+        println!(
+            "{:?}",
+            Report::new(result).wrap_err("policy set validation failed")
+        );
+        CedarExitCode::ValidationFailure
+    } else {
+        println!(
+            "{:?}",
+            Report::new(result).wrap_err("policy set validation passed")
+        );
+        CedarExitCode::Success
+    }
+}
+
+pub fn analyze(args: &AnalyzeArgs) -> CedarExitCode {
+    let mut policies = match loader::read_policy_sources(args.policies_path.as_ref())
+        .and_then(analyze::LoadedPolicies::new)
+    {
+        Ok(policies) => policies,
+        Err(e) => {
+            println!("{e:?}");
+            return CedarExitCode::Failure;
+        }
+    };
+    if let Some(links_filename) = args.template_linked_file.as_ref() {
+        if let Err(e) = add_template_links_to_set(links_filename, &mut policies.policy_set) {
+            println!("{e:?}");
+            return CedarExitCode::Failure;
+        }
+    }
+    let schema = match args.schema_file.as_ref().map(read_schema_file) {
+        None => None,
+        Some(Ok(schema)) => Some(schema),
+        Some(Err(e)) => {
+            println!("{e:?}");
+            return CedarExitCode::Failure;
+        }
+    };
+
+    let findings = analyze::analyze(&policies, schema);
+    let count = findings.len();
+    if output_format() == OutputFormat::Json {
+        let json = serde_json::json!({
+            "findings": findings
+                .iter()
+                .map(|f| diagnostic_json(f))
+                .collect::<Vec<_>>(),
+        });
+        if let Err(e) = print_json(&json) {
+            println!("{e:?}");
+            return CedarExitCode::Failure;
+        }
+        return if count == 0 {
+            CedarExitCode::Success
+        } else {
+            CedarExitCode::AnalysisFindings
+        };
+    }
+    for finding in findings {
+        println!("{:?}", Report::new(finding));
+    }
+    if count == 0 {
+        println!("analysis found no problems");
+        CedarExitCode::Success
+    } else {
+        println!("analysis reported {count} finding(s)");
+        CedarExitCode::AnalysisFindings
+    }
+}
+
+pub fn repl(args: &ReplArgs) -> CedarExitCode {
+    match repl_inner(args) {
+        Ok(()) => CedarExitCode::Success,
+        Err(err) => {
+            println!("{err:?}");
+            CedarExitCode::Failure
+        }
+    }
+}
+
+fn repl_inner(args: &ReplArgs) -> Result<()> {
+    let mut policies = match &args.policies_file {
+        Some(file) => read_policy_set(Some(file))?,
+        None => PolicySet::new(),
+    };
+    if let Some(links_filename) = &args.template_linked_file {
+        add_template_links_to_set(links_filename, &mut policies)?;
+    }
+    let schema = args
+        .schema_file
+        .as_ref()
+        .map(read_schema_file)
+        .transpose()?;
+    let entities = match &args.entities_file {
+        Some(file) => load_entities(file, schema.as_ref())?,
+        None => Entities::empty(),
+    };
+    let history = match &args.history_file {
+        Some(file) if Path::new(file).exists() => std::fs::read_to_string(file)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to read history file {file}"))?
+            .lines()
+            .map(ToOwned::to_owned)
+            .collect(),
+        _ => vec![],
+    };
+
+    let mut repl = repl::Repl::new(policies, schema, entities, history);
+    repl.run(std::io::stdin().lock(), &mut std::io::stdout())
+        .into_diagnostic()?;
+    if let Some(file) = &args.history_file {
+        let mut contents = repl.history().join("\n");
+        contents.push('\n');
+        std::fs::write(file, contents)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to write history file {file}"))?;
+    }
+    Ok(())
+}
+
+pub fn translate_schema(args: &TranslateSchemaArgs) -> CedarExitCode {
+    match translate_schema_inner(args) {
+        Ok(translated) => {
+            println!("{translated}");
+            CedarExitCode::Success
+        }
+        Err(err) => {
+            println!("{err:?}");
+            CedarExitCode::Failure
+        }
+    }
+}
+
+fn translate_schema_inner(args: &TranslateSchemaArgs) -> Result<String> {
+    let src = read_from_file_or_stdin(args.schema_file.as_ref(), "schema")?;
+    let (json, mut warnings) = match args.from {
+        SchemaFormat::Json => (
+            serde_json::from_str(&src)
+                .into_diagnostic()
+                .wrap_err("failed to parse schema as JSON")?,
+            vec![],
+        ),
+        SchemaFormat::Cedar => {
+            translate_schema::cedar_to_json(args.schema_file.as_deref().unwrap_or("<stdin>"), &src)?
+        }
+    };
+    let translated = match args.to {
+        SchemaFormat::Json => {
+            translate_schema::check_json(&json)?;
+            serde_json::to_string_pretty(&json).into_diagnostic()?
+        }
+        SchemaFormat::Cedar => {
+            let (translated, more_warnings) = translate_schema::json_to_cedar(&json)?;
+            warnings.extend(more_warnings);
+            translated
+        }
+    };
+    // warnings go to stderr, so that the translated schema can be redirected
+    // to a file
+    for warning in warnings {
+        eprintln!("{:?}", Report::new(warning));
+    }
+    Ok(translated)
+}
+
+pub fn document_schema(args: &DocumentSchemaArgs) -> CedarExitCode {
+    match document_schema_inner(args) {
+        Ok(doc) => {
+            print!("{doc}");
+            CedarExitCode::Success
+        }
+        Err(err) => {
+            println!("{err:?}");
+            CedarExitCode::Failure
+        }
+    }
+}
+
+fn document_schema_inner(args: &DocumentSchemaArgs) -> Result<String> {
+    let src = read_from_file_or_stdin(args.schema_file.as_ref(), "schema")?;
+    let json = match args.from {
+        SchemaFormat::Json => serde_json::from_str(&src)
+            .into_diagnostic()
+            .wrap_err("failed to parse schema as JSON")?,
+        SchemaFormat::Cedar => {
+            // the only translation warning is about dropped comments, which
+            // wouldn't be in the documentation anyway
+            let (json, _) = translate_schema::cedar_to_json(
+                args.schema_file.as_deref().unwrap_or("<stdin>"),
+                &src,
+            )?;
+            json
+        }
+    };
+    match args.to {
+        DocumentFormat::Markdown => document_schema::schema_markdown(&json),
+        DocumentFormat::Html => document_schema::schema_html(&json),
+    }
+}
+
+pub fn partially_authorize(args: &PartiallyAuthorizeArgs) -> CedarExitCode {
+    #[cfg(not(feature = "partial-eval"))]
+    {
+        let _ = args;
+        println!("Error: the `partially-authorize` command is experimental, but this executable was not built with `partial-eval` experimental feature enabled");
+        return CedarExitCode::Failure;
+    }
+    #[cfg(feature = "partial-eval")]
+    match partially_authorize_inner(args) {
+        Ok(code) => code,
+        Err(err) => {
+            println!("{err:?}");
+            CedarExitCode::Failure
+        }
+    }
+}
+
+#[cfg(feature = "partial-eval")]
+fn partially_authorize_inner(args: &PartiallyAuthorizeArgs) -> Result<CedarExitCode> {
+    let mut policies = args.policies.get_policy_set()?;
+    if let Some(links_filename) = &args.template_linked_file {
+        add_template_links_to_set(links_filename, &mut policies)?;
+    }
+    let schema = args
+        .schema_file
+        .as_ref()
+        .map(read_schema_file)
+        .transpose()?;
+    let entities = match &args.entities_file {
+        Some(file) => load_entities(file, schema.as_ref())?,
+        None => Entities::empty(),
+    }
+    .partial();
+
+    let parse_uid = |uid: &Option<String>, field: &str| {
+        uid.as_ref()
+            .map(|s| {
+                s.parse::<EntityUid>()
+                    .wrap_err_with(|| format!("failed to parse {field} {s} as entity Uid"))
+            })
+            .transpose()
+    };
+    let principal = parse_uid(&args.principal, "principal")?;
+    let action = parse_uid(&args.action, "action")?;
+    let resource = parse_uid(&args.resource, "resource")?;
+    let mut builder = Request::builder();
+    if let Some(principal) = principal {
+        builder = builder.principal(Some(principal));
+    }
+    if let Some(resource) = resource {
+        builder = builder.resource(Some(resource));
+    }
+    if let Some(jsonfile) = &args.context_json_file {
+        let f = std::fs::File::open(jsonfile)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("error while loading context from {jsonfile}"))?;
+        let context =
+            Context::from_json_file(f, schema.as_ref().and_then(|s| Some((s, action.as_ref()?))))
+                .wrap_err_with(|| format!("failed to create a context from {jsonfile}"))?;
+        builder = builder.context(context);
+    }
+    if let Some(action) = action {
+        builder = builder.action(Some(action));
+    }
+    if let Some(schema) = &schema {
+        builder = builder.schema(schema);
+    }
+    let request = builder
+        .build()
+        .map_err(|e| miette!("{e}"))
+        .wrap_err("failed to parse request")?;
+
+    let response = Authorizer::new().is_authorized_partial(&request, &policies, &entities);
+    if output_format() == OutputFormat::Json {
+        let (decision, residuals, errors) = match &response {
+            PartialResponse::Concrete(ans) => (
+                Some(ans.decision()),
+                vec![],
+                ans.diagnostics()
+                    .errors()
+                    .map(ToString::to_string)
+                    .collect(),
+            ),
+            PartialResponse::Residual(residual) => (
+                None,
+                residual
+                    .residuals()
+                    .policies()
+                    .map(|p| p.to_json().map_err(Report::new))
+                    .collect::<Result<Vec<_>>>()?,
+                residual
+                    .diagnostics()
+                    .errors()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>(),
+            ),
+        };
+        print_json(&serde_json::json!({
+            "decision": decision,
+            "residuals": residuals,
+            "errors": errors,
+        }))?;
+        return Ok(match decision {
+            Some(Decision::Deny) => CedarExitCode::AuthorizeDeny,
+            Some(Decision::Allow) | None => CedarExitCode::Success,
+        });
+    }
+    match response {
+        PartialResponse::Concrete(ans) => {
+            for err in ans.diagnostics().errors() {
+                println!("{err}");
+            }
+            match ans.decision() {
+                Decision::Allow => {
+                    println!("ALLOW");
+                    Ok(CedarExitCode::Success)
+                }
+                Decision::Deny => {
+                    println!("DENY");
+                    Ok(CedarExitCode::AuthorizeDeny)
+                }
+            }
+        }
+        PartialResponse::Residual(residual) => {
+            for err in residual.diagnostics().errors() {
+                println!("{err}");
+            }
+            println!("UNKNOWN");
+            println!("note: the decision depends on the following residual policies:");
+            for policy in residual.residuals().policies() {
+                match args.residual_format {
+                    PolicyFormat::Human => println!("{policy}"),
+                    PolicyFormat::Json => {
+                        let json = policy.to_json().map_err(Report::new)?;
+                        println!("{json}");
+                    }
+                }
+            }
+            Ok(CedarExitCode::Success)
+        }
+    }
+}
+
+pub fn diff_policies(args: &DiffPoliciesArgs) -> CedarExitCode {
+    match diff_policies_inner(args) {
+        Ok(()) => CedarExitCode::Success,
+        Err(err) => {
+            println!("{err:?}");
+            CedarExitCode::Failure
+        }
+    }
+}
+
+fn diff_policies_inner(args: &DiffPoliciesArgs) -> Result<()> {
+    let load = |path: &String, links: &Option<String>| -> Result<PolicySet> {
+        let mut policies =
+            analyze::LoadedPolicies::new(loader::read_policy_sources(Some(path))?)?.policy_set;
+        if let Some(links_filename) = links {
+            add_template_links_to_set(links_filename, &mut policies)?;
+        }
+        Ok(policies)
+    };
+    let old = load(&args.old_policies, &args.old_template_linked_file)?;
+    let new = load(&args.new_policies, &args.new_template_linked_file)?;
+    let actions = match &args.schema_file {
+        Some(file) => Some(
+            read_schema_file(file)?
+                .action_entities()
+                .wrap_err("failed to construct action entities from the schema")?,
+        ),
+        None => None,
+    };
+
+    let changes = diff::diff_policies(&old, &new, actions.as_ref());
+    if output_format() == OutputFormat::Json {
+        let changes: Vec<_> = changes
+            .iter()
+            .map(|change| {
+                let (change_kind, id, kind, details) = match change {
+                    diff::PolicyChange::Added { id, kind } => ("added", id, kind, &[][..]),
+                    diff::PolicyChange::Removed { id, kind } => ("removed", id, kind, &[][..]),
+                    diff::PolicyChange::Changed { id, kind, details } => {
+                        ("changed", id, kind, details.as_slice())
+                    }
+                };
+                serde_json::json!({
+                    "change": change_kind,
+                    "id": id.to_string(),
+                    "kind": kind.to_string(),
+                    "details": details.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                    "mayGrantAccess": change.may_grant_access(&old, &new),
+                })
+            })
+            .collect();
+        return print_json(&serde_json::json!({ "changes": changes }));
+    }
+    if changes.is_empty() {
+        println!("no differences");
+    }
+    for change in changes {
+        if change.may_grant_access(&old, &new) {
+            println!("{change} (may allow requests that were previously denied)");
+        } else {
+            println!("{change}");
+        }
+    }
+    Ok(())
+}
+
+pub fn slice_entities(args: &SliceEntitiesArgs) -> CedarExitCode {
+    match slice_entities_inner(args) {
+        Ok(()) => CedarExitCode::Success,
+        Err(err) => {
+            println!("{err:?}");
+            CedarExitCode::Failure
+        }
+    }
+}
+
+fn slice_entities_inner(args: &SliceEntitiesArgs) -> Result<()> {
+    let mut policies = args.policies.get_policy_set()?;
+    if let Some(links_filename) = args.template_linked_file.as_ref() {
+        add_template_links_to_set(links_filename, &mut policies)?;
+    }
+    let schema: serde_json::Value =
+        serde_json::from_str(&read_from_file(&args.schema_file, "schema")?)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to parse schema from file {}", args.schema_file))?;
+    let action = EntityUid::from_str(&args.action)
+        .wrap_err_with(|| format!("failed to parse action {} as entity Uid", args.action))?;
+    let slice = slice::slice_entities(&policies, &schema, &action)?;
+    match output_format() {
+        OutputFormat::Human => println!("{slice}"),
+        OutputFormat::Json => print_json(&slice)?,
+    }
+    Ok(())
+}
+
+pub fn new_policy(args: &NewPolicyArgs) -> CedarExitCode {
+    match new_policy_inner(args) {
+        Ok(()) => CedarExitCode::Success,
+        Err(err) => {
+            println!("{err:?}");
+            CedarExitCode::Failure
+        }
+    }
+}
+
+fn new_policy_inner(args: &NewPolicyArgs) -> Result<()> {
+    let schema: serde_json::Value =
+        serde_json::from_str(&read_from_file(&args.schema_file, "schema")?)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to parse schema from file {}", args.schema_file))?;
+    let action = EntityUid::from_str(&args.action)
+        .wrap_err_with(|| format!("failed to parse action {} as entity Uid", args.action))?;
+    let effect = if args.forbid {
+        Effect::Forbid
+    } else {
+        Effect::Permit
+    };
+    let policy = scaffold::new_policy(
+        &schema,
+        &action,
+        args.principal_type.as_deref(),
+        args.resource_type.as_deref(),
+        effect,
+    )?;
+    print!("{policy}");
+    Ok(())
+}
+
+pub fn bench(args: &BenchArgs) -> CedarExitCode {
+    match bench_inner(args) {
+        Ok(()) => CedarExitCode::Success,
+        Err(err) => {
+            println!("{err:?}");
+            CedarExitCode::Failure
+        }
+    }
 }
 
-impl Termination for CedarExitCode {
-    fn report(self) -> ExitCode {
-        match self {
-            CedarExitCode::Success => ExitCode::SUCCESS,
-            CedarExitCode::Failure => ExitCode::FAILURE,
-            CedarExitCode::AuthorizeDeny => ExitCode::from(2),
-            CedarExitCode::ValidationFailure => ExitCode::from(3),
+fn bench_inner(args: &BenchArgs) -> Result<()> {
+    if args.iterations == 0 {
+        return Err(miette!("--iterations must be at least 1"));
+    }
+    let mut report = bench::BenchReport::default();
+
+    // inputs are read once, so that only parsing and evaluation are timed
+    let policies_src = read_from_file_or_stdin(args.policies.policies_file.as_ref(), "policy set")?;
+    let name = args
+        .policies
+        .policies_file
+        .clone()
+        .unwrap_or_else(|| "<stdin>".to_owned());
+    let parse = || match args.policies.policy_format {
+        PolicyFormat::Human => parse_policy_set(&policies_src, &name),
+        PolicyFormat::Json => serde_json::from_str(&policies_src)
+            .into_diagnostic()
+            .and_then(|json| Policy::from_json(None, json).wrap_err("failed to parse JSON policy"))
+            .and_then(|policy| {
+                PolicySet::from_policies([policy])
+                    .wrap_err("failed to create policy set from JSON policy")
+            }),
+    };
+    let mut policies = parse()?;
+    report.measure("parse policies", args.iterations, parse);
+    if let Some(links_filename) = args.template_linked_file.as_ref() {
+        add_template_links_to_set(links_filename, &mut policies)?;
+    }
+
+    let schema = args
+        .schema_file
+        .as_ref()
+        .map(read_schema_file)
+        .transpose()?;
+    if let Some(schema) = &schema {
+        let validator = Validator::new(schema.clone());
+        report.measure("validate", args.iterations, || {
+            validator
+                .validate(&policies, ValidationMode::default())
+                .validation_passed()
+        });
+    }
+
+    let entities_src = read_from_file(&args.entities_file, "entities")?;
+    let load = || {
+        Entities::from_json_str(&entities_src, schema.as_ref())
+            .wrap_err_with(|| format!("failed to parse entities from file {}", args.entities_file))
+    };
+    let entities = load()?;
+    report.measure("load entities", args.iterations, load);
+    // without a schema, building the store only checks for duplicates and
+    // computes the transitive closure of the hierarchy
+    report.measure("transitive closure", args.iterations, || {
+        Entities::from_entities(entities.iter().cloned(), None)
+    });
+
+    let mut requests = vec![];
+    for (i, line) in read_from_file(&args.requests_file, "requests")?
+        .lines()
+        .enumerate()
+    {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let source = format!("{}:{}", args.requests_file, i + 1);
+        let qjson: RequestJSON = serde_json::from_str(line)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to parse request in {source}"))?;
+        requests.push(qjson.get_request(&source, schema.as_ref(), true)?);
+    }
+    let authorizer = Authorizer::new();
+    let mut samples = Vec::with_capacity(args.iterations * requests.len());
+    for _ in 0..args.iterations {
+        for request in &requests {
+            let start = Instant::now();
+            std::hint::black_box(authorizer.is_authorized(request, &policies, &entities));
+            samples.push(start.elapsed());
         }
     }
+    report.add("authorize", &samples);
+
+    match output_format() {
+        OutputFormat::Human => println!("{report}"),
+        OutputFormat::Json => print_json(&report)?,
+    }
+    Ok(())
 }
 
-pub fn check_parse(args: &CheckParseArgs) -> CedarExitCode {
-    match args.policies.get_policy_set() {
-        Ok(_) => CedarExitCode::Success,
-        Err(e) => {
-            println!("{e:?}");
+pub fn generate_requests(args: &GenerateRequestsArgs) -> CedarExitCode {
+    match generate_requests_inner(args) {
+        Ok(()) => CedarExitCode::Success,
+        Err(err) => {
+            println!("{err:?}");
             CedarExitCode::Failure
         }
     }
 }
 
-pub fn validate(args: &ValidateArgs) -> CedarExitCode {
-    let mode = if args.partial_validate {
-        #[cfg(not(feature = "partial-validate"))]
-        {
-            println!("Error: arguments include the experimental option `--partial-validate`, but this executable was not built with `partial-validate` experimental feature enabled");
-            return CedarExitCode::Failure;
+fn generate_requests_inner(args: &GenerateRequestsArgs) -> Result<()> {
+    let schema: serde_json::Value =
+        serde_json::from_str(&read_from_file(&args.schema_file, "schema")?)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to parse schema from file {}", args.schema_file))?;
+    let seed = match args.seed {
+        Some(seed) => seed,
+        None => {
+            let seed = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64);
+            eprintln!("seed: {seed}");
+            seed
         }
-        #[cfg(feature = "partial-validate")]
-        ValidationMode::Partial
-    } else {
-        ValidationMode::default()
     };
+    let generated = generate::generate(&schema, args.count, args.entities_per_type, seed)?;
+    if let Some(entities_file) = &args.entities_file {
+        std::fs::write(
+            entities_file,
+            serde_json::to_string_pretty(&generated.entities).into_diagnostic()?,
+        )
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to write entities file {entities_file}"))?;
+    }
+    for request in &generated.requests {
+        println!("{request}");
+    }
+    Ok(())
+}
 
-    let pset = match args.policies.get_policy_set() {
-        Ok(pset) => pset,
-        Err(e) => {
-            println!("{e:?}");
-            return CedarExitCode::Failure;
+pub fn visualize_entities(args: &VisualizeEntitiesArgs) -> CedarExitCode {
+    match visualize_entities_inner(args) {
+        Ok(()) => CedarExitCode::Success,
+        Err(err) => {
+            println!("{err:?}");
+            CedarExitCode::Failure
         }
-    };
+    }
+}
 
-    let schema = match read_schema_file(&args.schema_file) {
-        Ok(schema) => schema,
-        Err(e) => {
-            println!("{e:?}");
+fn visualize_entities_inner(args: &VisualizeEntitiesArgs) -> Result<()> {
+    let entities: serde_json::Value =
+        serde_json::from_str(&read_from_file(&args.entities_file, "entities")?)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                format!("failed to parse entities from file {}", args.entities_file)
+            })?;
+    print!("{}", visualize::entities_dot(&entities)?);
+    Ok(())
+}
+
+pub fn visualize_policies(args: &VisualizePoliciesArgs) -> CedarExitCode {
+    match visualize_policies_inner(args) {
+        Ok(()) => CedarExitCode::Success,
+        Err(err) => {
+            println!("{err:?}");
+            CedarExitCode::Failure
+        }
+    }
+}
+
+fn visualize_policies_inner(args: &VisualizePoliciesArgs) -> Result<()> {
+    let mut policies = args.policies.get_policy_set()?;
+    if let Some(links_filename) = args.template_linked_file.as_ref() {
+        add_template_links_to_set(links_filename, &mut policies)?;
+    }
+    let schema: serde_json::Value =
+        serde_json::from_str(&read_from_file(&args.schema_file, "schema")?)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to parse schema from file {}", args.schema_file))?;
+    print!("{}", visualize::policies_dot(&policies, &schema)?);
+    Ok(())
+}
+
+pub fn validate_entities(args: &ValidateEntitiesArgs) -> CedarExitCode {
+    let violations = match validate_entities_inner(args) {
+        Ok(violations) => violations,
+        Err(err) => {
+            println!("{err:?}");
             return CedarExitCode::Failure;
         }
     };
+    let passed = violations.is_empty();
+    match output_format() {
+        OutputFormat::Json => {
+            let json = serde_json::json!({
+                "validationPassed": passed,
+                "violations": violations,
+            });
+            if let Err(e) = print_json(&json) {
+                println!("{e:?}");
+                return CedarExitCode::Failure;
+            }
+        }
+        OutputFormat::Human => {
+            let count = violations.len();
+            for violation in violations {
+                println!("{:?}", Report::new(violation));
+            }
+            if passed {
+                println!("entities conform to the schema");
+            } else {
+                println!("found {count} schema violation(s)");
+            }
+        }
+    }
+    if passed {
+        CedarExitCode::Success
+    } else {
+        CedarExitCode::ValidationFailure
+    }
+}
 
-    let validator = Validator::new(schema);
-    let result = validator.validate(&pset, mode);
+fn validate_entities_inner(args: &ValidateEntitiesArgs) -> Result<Vec<conformance::Violation>> {
+    let schema: serde_json::Value =
+        serde_json::from_str(&read_from_file(&args.schema_file, "schema")?)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to parse schema from file {}", args.schema_file))?;
+    let entities: serde_json::Value =
+        serde_json::from_str(&read_from_file(&args.entities_file, "entities")?)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                format!("failed to parse entities from file {}", args.entities_file)
+            })?;
+    conformance::check_entities(&schema, &entities)
+}
 
-    if !result.validation_passed()
-        || (args.deny_warnings && !result.validation_passed_without_warnings())
-    {
-        println!(
-            "{:?}",
-            Report::new(result).wrap_err("policy set validation failed")
-        );
-        CedarExitCode::ValidationFailure
-    } else {
-        println!(
-            "{:?}",
-            Report::new(result).wrap_err("policy set validation passed")
-        );
-        CedarExitCode::Success
+pub fn infer_schema(args: &InferSchemaArgs) -> CedarExitCode {
+    match infer_schema_inner(args) {
+        Ok(schema) => {
+            println!("{schema}");
+            CedarExitCode::Success
+        }
+        Err(err) => {
+            println!("{err:?}");
+            CedarExitCode::Failure
+        }
+    }
+}
+
+fn infer_schema_inner(args: &InferSchemaArgs) -> Result<String> {
+    let entities: serde_json::Value =
+        serde_json::from_str(&read_from_file(&args.entities_file, "entities")?)
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                format!("failed to parse entities from file {}", args.entities_file)
+            })?;
+    let inferred = infer::infer_schema(&entities)?;
+    let schema = match args.format {
+        SchemaFormat::Json => serde_json::to_string_pretty(&inferred.schema).into_diagnostic()?,
+        SchemaFormat::Cedar => translate_schema::json_to_cedar(&inferred.schema)?.0,
+    };
+    // ambiguities go to stderr, so that the schema can be redirected to a file
+    for ambiguity in inferred.ambiguities {
+        eprintln!("{:?}", Report::new(ambiguity));
     }
+    Ok(schema)
 }
 
 pub fn evaluate(args: &EvaluateArgs) -> (CedarExitCode, EvalResult) {
-    println!();
+    if output_format() == OutputFormat::Human {
+        println!();
+    }
     let schema = match args.schema_file.as_ref().map(read_schema_file) {
         None => None,
         Some(Ok(schema)) => Some(schema),
@@ -554,12 +1803,42 @@ pub fn evaluate(args: &EvaluateArgs) -> (CedarExitCode, EvalResult) {
             return (CedarExitCode::Failure, EvalResult::Bool(false));
         }
         Ok(result) => {
-            println!("{result}");
+            match output_format() {
+                OutputFormat::Human => println!("{result}"),
+                OutputFormat::Json => {
+                    let json = serde_json::json!({ "result": eval_result_to_json(&result) });
+                    if let Err(e) = print_json(&json) {
+                        println!("{e:?}");
+                        return (CedarExitCode::Failure, result);
+                    }
+                }
+            }
             return (CedarExitCode::Success, result);
         }
     }
 }
 
+/// The JSON representation of `result`, in the format used for attribute
+/// values in the JSON entity format
+fn eval_result_to_json(result: &EvalResult) -> serde_json::Value {
+    match result {
+        EvalResult::Bool(b) => (*b).into(),
+        EvalResult::Long(i) => (*i).into(),
+        EvalResult::String(s) | EvalResult::ExtensionValue(s) => s.as_str().into(),
+        EvalResult::EntityUid(uid) => {
+            let id: &str = uid.id().as_ref();
+            serde_json::json!({ "__entity": { "type": uid.type_name().to_string(), "id": id } })
+        }
+        EvalResult::Set(set) => set.iter().map(eval_result_to_json).collect(),
+        EvalResult::Record(record) => serde_json::Value::Object(
+            record
+                .iter()
+                .map(|(k, v)| (k.clone(), eval_result_to_json(v)))
+                .collect(),
+        ),
+    }
+}
+
 pub fn link(args: &LinkArgs) -> CedarExitCode {
     if let Err(err) = link_inner(args) {
         println!("{err:?}");
@@ -569,22 +1848,57 @@ pub fn link(args: &LinkArgs) -> CedarExitCode {
     }
 }
 
-fn format_policies_inner(args: &FormatArgs) -> Result<()> {
+/// Format the policies, returning whether they were already formatted
+fn format_policies_inner(args: &FormatArgs) -> Result<bool> {
     let policies_str = read_from_file_or_stdin(args.policies_file.as_ref(), "policy set")?;
-    let config = Config {
+    let config = FormatterConfig {
         line_width: args.line_width,
         indent_width: args.indent_width,
+        sort_policies: args.sort,
+        ..FormatterConfig::default()
     };
-    println!("{}", policies_str_to_pretty(&policies_str, &config)?);
-    Ok(())
+    if !args.check {
+        println!("{}", policies_str_to_pretty(&policies_str, &config)?);
+        return Ok(true);
+    }
+    if is_formatted(&policies_str, &config) {
+        return Ok(true);
+    }
+    // reports the error if the policies can't be formatted
+    let formatted = policies_str_to_pretty(&policies_str, &config)?;
+    let name = args.policies_file.as_deref().unwrap_or("<stdin>");
+    if args.diff {
+        print!(
+            "{}",
+            text_diff::unified_diff(
+                &policies_str,
+                &formatted,
+                name,
+                &format!("{name} (formatted)")
+            )
+        );
+    } else {
+        println!("{name} is not formatted");
+    }
+    Ok(false)
 }
 
 pub fn format_policies(args: &FormatArgs) -> CedarExitCode {
-    if let Err(err) = format_policies_inner(args) {
-        println!("{err:?}");
-        CedarExitCode::Failure
-    } else {
-        CedarExitCode::Success
+    if args.watch {
+        let paths: Vec<&str> = args.policies_file.as_deref().into_iter().collect();
+        return run_watching(&paths, || format_policies_once(args));
+    }
+    format_policies_once(args)
+}
+
+fn format_policies_once(args: &FormatArgs) -> CedarExitCode {
+    match format_policies_inner(args) {
+        Ok(true) => CedarExitCode::Success,
+        Ok(false) => CedarExitCode::Unformatted,
+        Err(err) => {
+            println!("{err:?}");
+            CedarExitCode::Failure
+        }
     }
 }
 
@@ -694,6 +2008,8 @@ fn create_slot_env(data: &HashMap<SlotId, String>) -> Result<HashMap<SlotId, Ent
 
 fn link_inner(args: &LinkArgs) -> Result<()> {
     let mut policies = args.policies.get_policy_set()?;
+    // so that linking fails if the new id is already used by an existing link
+    add_template_links_to_set(&args.template_linked_file, &mut policies)?;
     let slotenv = create_slot_env(&args.arguments.data)?;
     policies.link(
         PolicyId::from_str(&args.template_id)?,
@@ -713,6 +2029,104 @@ fn link_inner(args: &LinkArgs) -> Result<()> {
     update_template_linked_file(&args.template_linked_file, linked)
 }
 
+pub fn link_list(args: &LinkListArgs) -> CedarExitCode {
+    match link_list_inner(args) {
+        Ok(()) => CedarExitCode::Success,
+        Err(err) => {
+            println!("{err:?}");
+            CedarExitCode::Failure
+        }
+    }
+}
+
+fn link_list_inner(args: &LinkListArgs) -> Result<()> {
+    let mut links = load_liked_file(&args.template_linked_file)?;
+    if let Some(template_id) = &args.template_id {
+        links.retain(|link| link.template_id == *template_id);
+    }
+    if output_format() == OutputFormat::Json {
+        return print_json(&links);
+    }
+    for link in links {
+        let mut slots: Vec<String> = link
+            .args
+            .iter()
+            .map(|(slot, value)| format!("{slot}: {value}"))
+            .collect();
+        slots.sort();
+        println!(
+            "{}: {} ({})",
+            link.link_id,
+            link.template_id,
+            slots.join(", ")
+        );
+    }
+    Ok(())
+}
+
+pub fn link_remove(args: &LinkRemoveArgs) -> CedarExitCode {
+    match link_remove_inner(args) {
+        Ok(()) => CedarExitCode::Success,
+        Err(err) => {
+            println!("{err:?}");
+            CedarExitCode::Failure
+        }
+    }
+}
+
+fn link_remove_inner(args: &LinkRemoveArgs) -> Result<()> {
+    let mut links = load_liked_file(&args.template_linked_file)?;
+    let count = links.len();
+    links.retain(|link| link.link_id != args.link_id);
+    if links.len() == count {
+        return Err(miette!(
+            "no template-linked policy with id `{}` in {}",
+            args.link_id,
+            args.template_linked_file
+        ));
+    }
+    write_template_linked_file(&links, &args.template_linked_file)?;
+    println!("Template Linked Policy Removed: {}", args.link_id);
+    Ok(())
+}
+
+pub fn link_export(args: &LinkExportArgs) -> CedarExitCode {
+    match link_export_inner(args) {
+        Ok(()) => CedarExitCode::Success,
+        Err(err) => {
+            println!("{err:?}");
+            CedarExitCode::Failure
+        }
+    }
+}
+
+fn link_export_inner(args: &LinkExportArgs) -> Result<()> {
+    let mut policies = args.policies.get_policy_set()?;
+    add_template_links_to_set(&args.template_linked_file, &mut policies)?;
+    let mut exported = Vec::new();
+    for policy in policies.policies() {
+        match policy.template_id() {
+            None => exported.push(policy.to_string()),
+            Some(_) => {
+                // record the id of the link in an `@id` annotation, replacing
+                // the template's, so that the exported policy keeps its id
+                let mut json = policy.to_json().map_err(Report::new)?;
+                let annotations = json.as_object_mut().map(|json| {
+                    json.entry("annotations")
+                        .or_insert_with(|| serde_json::json!({}))
+                });
+                if let Some(serde_json::Value::Object(annotations)) = annotations {
+                    annotations.insert("id".to_owned(), policy.id().to_string().into());
+                }
+                let linked = Policy::from_json(Some(policy.id().clone()), json)?;
+                exported.push(linked.to_string());
+            }
+        }
+    }
+    println!("{}", exported.join("\n\n"));
+    Ok(())
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(try_from = "LiteralTemplateLinked")]
 #[serde(into = "LiteralTemplateLinked")]
@@ -827,7 +2241,13 @@ fn write_template_linked_file(linked: &[TemplateLinked], path: impl AsRef<Path>)
 }
 
 pub fn authorize(args: &AuthorizeArgs) -> CedarExitCode {
-    println!();
+    if let Some(requests_file) = &args.requests_file {
+        return authorize_batch(args, requests_file);
+    }
+    let json = output_format() == OutputFormat::Json;
+    if !json {
+        println!();
+    }
     let ans = execute_request(
         &args.request,
         &args.policies,
@@ -837,6 +2257,16 @@ pub fn authorize(args: &AuthorizeArgs) -> CedarExitCode {
         args.timing,
     );
     match ans {
+        Ok(ans) if json => {
+            if let Err(e) = print_json(&DecisionJSON::from(&ans)) {
+                println!("{e:?}");
+                return CedarExitCode::Failure;
+            }
+            match ans.decision() {
+                Decision::Allow => CedarExitCode::Success,
+                Decision::Deny => CedarExitCode::AuthorizeDeny,
+            }
+        }
         Ok(ans) => {
             let status = match ans.decision() {
                 Decision::Allow => {
@@ -877,13 +2307,141 @@ pub fn authorize(args: &AuthorizeArgs) -> CedarExitCode {
     }
 }
 
+/// Result of authorizing one request, as printed in JSON
+#[derive(Serialize)]
+struct DecisionJSON {
+    /// Decision for the request, or `None` if it could not be evaluated
+    decision: Option<Decision>,
+    /// Ids of the policies that determined the decision
+    reasons: Vec<String>,
+    /// Errors encountered while parsing or evaluating the request
+    errors: Vec<String>,
+}
+
+impl From<&Response> for DecisionJSON {
+    fn from(ans: &Response) -> Self {
+        Self {
+            decision: Some(ans.decision()),
+            reasons: ans
+                .diagnostics()
+                .reason()
+                .map(|id| id.as_ref().to_owned())
+                .collect(),
+            errors: ans
+                .diagnostics()
+                .errors()
+                .map(ToString::to_string)
+                .collect(),
+        }
+    }
+}
+
+/// One line of output from `authorize --requests`
+#[derive(Serialize)]
+struct BatchDecision {
+    /// Line number of the request in the requests file, starting from 1
+    line: usize,
+    #[serde(flatten)]
+    result: DecisionJSON,
+}
+
+fn authorize_batch(args: &AuthorizeArgs, requests_file: &str) -> CedarExitCode {
+    let (policies, schema, entities, errs) = load_authorization_inputs(
+        &args.policies,
+        args.template_linked_file.as_ref(),
+        &args.entities_file,
+        args.schema_file.as_ref(),
+    );
+    if !errs.is_empty() {
+        for err in errs {
+            println!("{err:?}");
+        }
+        return CedarExitCode::Failure;
+    }
+    let requests = match std::fs::File::open(requests_file)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to open requests file {requests_file}"))
+    {
+        Ok(f) => std::io::BufReader::new(f),
+        Err(e) => {
+            println!("{e:?}");
+            return CedarExitCode::Failure;
+        }
+    };
+
+    let authorizer = Authorizer::new();
+    let mut exit_code = CedarExitCode::Success;
+    for (i, line) in std::io::BufRead::lines(requests).enumerate() {
+        let line_number = i + 1;
+        let source = format!("{requests_file}:{line_number}");
+        let request = line
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to read {source}"))
+            .and_then(|line| {
+                if line.trim().is_empty() {
+                    return Ok(None);
+                }
+                let qjson: RequestJSON = serde_json::from_str(&line)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("failed to parse request in {source}"))?;
+                qjson
+                    .get_request(&source, schema.as_ref(), args.request.request_validation)
+                    .map(Some)
+            });
+        let decision = match request {
+            Ok(None) => continue,
+            Ok(Some(request)) => {
+                let ans = authorizer.is_authorized(&request, &policies, &entities);
+                BatchDecision {
+                    line: line_number,
+                    result: DecisionJSON::from(&ans),
+                }
+            }
+            Err(e) => {
+                exit_code = CedarExitCode::Failure;
+                BatchDecision {
+                    line: line_number,
+                    result: DecisionJSON {
+                        decision: None,
+                        reasons: vec![],
+                        errors: vec![format!("{e:#}")],
+                    },
+                }
+            }
+        };
+        if let Err(e) = print_json(&decision) {
+            println!("{e:?}");
+            return CedarExitCode::Failure;
+        }
+    }
+    exit_code
+}
+
+/// How often `--watch` checks for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Run `command`, and then run it again whenever one of the files or
+/// directories in `paths` changes. Never returns; watching stops when the
+/// process is interrupted.
+fn run_watching(paths: &[&str], command: impl Fn() -> CedarExitCode) -> CedarExitCode {
+    let mut watcher = watch::Watcher::new(paths.iter().copied());
+    command();
+    loop {
+        let changed = watcher.wait(WATCH_INTERVAL);
+        let changed: Vec<String> = changed.iter().map(|p| p.display().to_string()).collect();
+        println!();
+        println!("--- {} changed ---", changed.join(", "));
+        command();
+    }
+}
+
 /// Load an `Entities` object from the given JSON filename and optional schema.
 fn load_entities(entities_filename: impl AsRef<Path>, schema: Option<&Schema>) -> Result<Entities> {
     match std::fs::OpenOptions::new()
         .read(true)
         .open(entities_filename.as_ref())
     {
-        Ok(f) => Entities::from_json_file(f, schema).wrap_err_with(|| {
+        Ok(f) => Entities::from_json_stream(f, schema).wrap_err_with(|| {
             format!(
                 "failed to parse entities from file {}",
                 entities_filename.as_ref().display()
@@ -949,6 +2507,16 @@ fn read_from_file_or_stdin(filename: Option<impl AsRef<Path>>, context: &str) ->
     Ok(src_str)
 }
 
+/// The type and id of an entity uid in the JSON entity format, with or without
+/// the `__entity` escape
+fn entity_uid(uid: &serde_json::Value) -> Option<(String, String)> {
+    let uid = uid.get("__entity").unwrap_or(uid);
+    Some((
+        uid.get("type")?.as_str()?.to_owned(),
+        uid.get("id")?.as_str()?.to_owned(),
+    ))
+}
+
 // Convenient wrapper around `read_from_file_or_stdin` to just read from a file
 fn read_from_file(filename: impl AsRef<Path>, context: &str) -> Result<String> {
     read_from_file_or_stdin(Some(filename), context)
@@ -957,17 +2525,20 @@ fn read_from_file(filename: impl AsRef<Path>, context: &str) -> Result<String> {
 /// Read a policy set, in Cedar human syntax, from the file given in `filename`,
 /// or from stdin if `filename` is `None`.
 fn read_policy_set(filename: Option<impl AsRef<Path> + std::marker::Copy>) -> Result<PolicySet> {
-    let context = "policy set";
-    let ps_str = read_from_file_or_stdin(filename, context)?;
-    let ps = PolicySet::from_str(&ps_str)
-        .map_err(|err| {
-            let name = filename.map_or_else(
-                || "<stdin>".to_owned(),
-                |n| n.as_ref().display().to_string(),
-            );
-            Report::new(err).with_source_code(NamedSource::new(name, ps_str))
-        })
-        .wrap_err_with(|| format!("failed to parse {context}"))?;
+    let ps_str = read_from_file_or_stdin(filename, "policy set")?;
+    let name = filename.map_or_else(
+        || "<stdin>".to_owned(),
+        |n| n.as_ref().display().to_string(),
+    );
+    parse_policy_set(&ps_str, &name)
+}
+
+/// Parse a policy set in Cedar human syntax, read from `name`, renaming
+/// policies based on their `@id` annotations.
+fn parse_policy_set(ps_str: &str, name: &str) -> Result<PolicySet> {
+    let ps = PolicySet::from_str(ps_str)
+        .map_err(|err| Report::new(err).with_source_code(NamedSource::new(name, ps_str.to_owned())))
+        .wrap_err("failed to parse policy set")?;
     rename_from_id_annotation(ps)
 }
 
@@ -1020,15 +2591,14 @@ fn read_schema_file(filename: impl AsRef<Path> + std::marker::Copy) -> Result<Sc
     })
 }
 
-/// This uses the Cedar API to call the authorization engine.
-fn execute_request(
-    request: &RequestArgs,
+/// Load the policies, schema, and entities used for authorization. Inputs which
+/// fail to load are replaced by empty ones, and the errors are returned.
+fn load_authorization_inputs(
     policies: &PoliciesArgs,
     links_filename: Option<impl AsRef<Path>>,
     entities_filename: impl AsRef<Path>,
     schema_filename: Option<impl AsRef<Path> + std::marker::Copy>,
-    compute_duration: bool,
-) -> Result<Response, Vec<Report>> {
+) -> (PolicySet, Option<Schema>, Entities, Vec<Report>) {
     let mut errs = vec![];
     let policies = match policies.get_policy_set().and_then(|mut pset| {
         if let Some(links_filename) = links_filename {
@@ -1057,6 +2627,20 @@ fn execute_request(
             Entities::empty()
         }
     };
+    (policies, schema, entities, errs)
+}
+
+/// This uses the Cedar API to call the authorization engine.
+fn execute_request(
+    request: &RequestArgs,
+    policies: &PoliciesArgs,
+    links_filename: Option<impl AsRef<Path>>,
+    entities_filename: impl AsRef<Path>,
+    schema_filename: Option<impl AsRef<Path> + std::marker::Copy>,
+    compute_duration: bool,
+) -> Result<Response, Vec<Report>> {
+    let (policies, schema, entities, mut errs) =
+        load_authorization_inputs(policies, links_filename, entities_filename, schema_filename);
     match request.get_request(schema.as_ref()) {
         Ok(request) if errs.is_empty() => {
             let authorizer = Authorizer::new();
@@ -0,0 +1,88 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Loading policies from a file, a directory of `.cedar` files, or stdin.
+
+use std::path::{Path, PathBuf};
+
+use miette::{IntoDiagnostic, Result, WrapErr};
+
+/// A single Cedar policy source, i.e., one file or stdin
+#[derive(Debug, Clone)]
+pub struct PolicySource {
+    /// File name, or `<stdin>`
+    pub name: String,
+    /// Policy text
+    pub src: String,
+}
+
+/// Read policy sources from `path`, which may be a `.cedar` file or a
+/// directory, searched recursively for `.cedar` files. Reads from stdin if
+/// `path` is `None`. Files in a directory are named by their path relative to
+/// the directory, so that the same policies in two directories get the same
+/// ids.
+pub fn read_policy_sources(path: Option<impl AsRef<Path>>) -> Result<Vec<PolicySource>> {
+    let Some(path) = path else {
+        let mut src = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut src)
+            .into_diagnostic()
+            .wrap_err("failed to read policy set from stdin")?;
+        return Ok(vec![PolicySource {
+            name: "<stdin>".to_owned(),
+            src,
+        }]);
+    };
+    let path = path.as_ref();
+    let mut files = Vec::new();
+    collect_cedar_files(path, &mut files)?;
+    // sorted so that ids and output don't depend on directory order
+    files.sort();
+    files
+        .into_iter()
+        .map(|file| {
+            let src = std::fs::read_to_string(&file)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("failed to open policy set file {}", file.display()))?;
+            Ok(PolicySource {
+                name: file
+                    .strip_prefix(path)
+                    .unwrap_or(&file)
+                    .display()
+                    .to_string(),
+                src,
+            })
+        })
+        .collect()
+}
+
+fn collect_cedar_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(path)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to read directory {}", path.display()))?
+    {
+        let entry_path = entry.into_diagnostic()?.path();
+        if entry_path.is_dir() {
+            collect_cedar_files(&entry_path, files)?;
+        } else if entry_path.extension().is_some_and(|ext| ext == "cedar") {
+            files.push(entry_path);
+        }
+    }
+    Ok(())
+}
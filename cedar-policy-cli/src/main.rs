@@ -20,14 +20,23 @@ use clap::Parser;
 use miette::ErrorHook;
 
 use cedar_policy_cli::{
-    authorize, check_parse, evaluate, format_policies, link, new, validate, CedarExitCode, Cli,
-    Commands, ErrorFormat,
+    analyze, authorize, bench, check_parse, diff_policies, document_schema, evaluate,
+    format_policies, generate_requests, infer_schema, link, link_export, link_list, link_remove,
+    new, new_policy, partially_authorize, repl, set_output_format, slice_entities,
+    translate_schema, validate, validate_entities, visualize_entities, visualize_policies,
+    CedarExitCode, Cli, Commands, ErrorFormat, LinkCommands, OutputFormat, VisualizeCommands,
 };
 
 fn main() -> CedarExitCode {
     let cli = Cli::parse();
 
-    let err_hook: Option<ErrorHook> = match cli.err_fmt {
+    set_output_format(cli.output_format);
+    // JSON results are accompanied by JSON errors
+    let err_fmt = match cli.output_format {
+        OutputFormat::Human => cli.err_fmt,
+        OutputFormat::Json => ErrorFormat::Json,
+    };
+    let err_hook: Option<ErrorHook> = match err_fmt {
         ErrorFormat::Human => None, // This is the default.
         ErrorFormat::Plain => Some(Box::new(|_| {
             Box::new(miette::NarratableReportHandler::new())
@@ -46,7 +55,24 @@ fn main() -> CedarExitCode {
         Commands::CheckParse(args) => check_parse(&args),
         Commands::Validate(args) => validate(&args),
         Commands::Format(args) => format_policies(&args),
-        Commands::Link(args) => link(&args),
+        Commands::Link(LinkCommands::Create(args)) => link(&args),
+        Commands::Link(LinkCommands::List(args)) => link_list(&args),
+        Commands::Link(LinkCommands::Remove(args)) => link_remove(&args),
+        Commands::Link(LinkCommands::Export(args)) => link_export(&args),
         Commands::New(args) => new(&args),
+        Commands::Analyze(args) => analyze(&args),
+        Commands::Repl(args) => repl(&args),
+        Commands::TranslateSchema(args) => translate_schema(&args),
+        Commands::PartiallyAuthorize(args) => partially_authorize(&args),
+        Commands::DiffPolicies(args) => diff_policies(&args),
+        Commands::SliceEntities(args) => slice_entities(&args),
+        Commands::NewPolicy(args) => new_policy(&args),
+        Commands::Bench(args) => bench(&args),
+        Commands::GenerateRequests(args) => generate_requests(&args),
+        Commands::Visualize(VisualizeCommands::Entities(args)) => visualize_entities(&args),
+        Commands::Visualize(VisualizeCommands::Policies(args)) => visualize_policies(&args),
+        Commands::InferSchema(args) => infer_schema(&args),
+        Commands::ValidateEntities(args) => validate_entities(&args),
+        Commands::DocumentSchema(args) => document_schema(&args),
     }
 }
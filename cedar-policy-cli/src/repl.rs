@@ -0,0 +1,232 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Interactive read-eval-print loop, used by the `repl` subcommand.
+
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+use cedar_policy::*;
+use miette::{miette, IntoDiagnostic, Result, WrapErr};
+
+use crate::RequestJSON;
+
+const HELP: &str = "\
+Enter a Cedar expression to evaluate it against the current request, or a
+JSON request (in the format expected by `--request-json`) to authorize it.
+
+Commands:
+  :principal [UID]   set the principal of the current request, or unset it
+  :action [UID]      set the action of the current request, or unset it
+  :resource [UID]    set the resource of the current request, or unset it
+  :context [JSON]    set the context of the current request, or reset it to {}
+  :request           show the current request
+  :authorize         authorize the current request
+  :history           show previously entered lines
+  :help              show this message
+  :quit              exit";
+
+/// State of a REPL session: the loaded policies, schema, and entities, the
+/// request being built up, and the lines entered so far
+#[derive(Debug)]
+pub struct Repl {
+    policies: PolicySet,
+    schema: Option<Schema>,
+    entities: Entities,
+    principal: Option<EntityUid>,
+    action: Option<EntityUid>,
+    resource: Option<EntityUid>,
+    context: serde_json::Value,
+    history: Vec<String>,
+}
+
+impl Repl {
+    /// Create a REPL session over the given inputs. `history` contains lines
+    /// entered in previous sessions.
+    pub fn new(
+        policies: PolicySet,
+        schema: Option<Schema>,
+        entities: Entities,
+        history: Vec<String>,
+    ) -> Self {
+        Self {
+            policies,
+            schema,
+            entities,
+            principal: None,
+            action: None,
+            resource: None,
+            context: serde_json::json!({}),
+            history,
+        }
+    }
+
+    /// Lines entered in this and previous sessions, oldest first
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Read lines from `input` until it ends or the user quits, writing
+    /// results to `output`
+    pub fn run(&mut self, input: impl BufRead, output: &mut impl Write) -> std::io::Result<()> {
+        write!(output, "> ")?;
+        output.flush()?;
+        for line in input.lines() {
+            let line = line?;
+            let line = line.trim();
+            if !line.is_empty() {
+                self.history.push(line.to_owned());
+                if matches!(line, ":quit" | ":q") {
+                    return Ok(());
+                }
+                match self.eval_line(line) {
+                    Ok(result) => writeln!(output, "{result}")?,
+                    Err(e) => writeln!(output, "{e:?}")?,
+                }
+            }
+            write!(output, "> ")?;
+            output.flush()?;
+        }
+        writeln!(output)
+    }
+
+    /// Evaluate one line of input, returning the text to display
+    fn eval_line(&mut self, line: &str) -> Result<String> {
+        let (command, arg) = match line.split_once(char::is_whitespace) {
+            Some((command, arg)) => (command, arg.trim()),
+            None => (line, ""),
+        };
+        match command {
+            ":help" => Ok(HELP.to_owned()),
+            ":history" => Ok(self
+                .history
+                .iter()
+                .enumerate()
+                .map(|(i, line)| format!("{:>4}  {line}", i + 1))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            ":principal" => {
+                self.principal = parse_uid(arg, "principal")?;
+                self.show_request()
+            }
+            ":action" => {
+                self.action = parse_uid(arg, "action")?;
+                self.show_request()
+            }
+            ":resource" => {
+                self.resource = parse_uid(arg, "resource")?;
+                self.show_request()
+            }
+            ":context" => {
+                self.context = if arg.is_empty() {
+                    serde_json::json!({})
+                } else {
+                    serde_json::from_str(arg)
+                        .into_diagnostic()
+                        .wrap_err("failed to parse context as JSON")?
+                };
+                self.show_request()
+            }
+            ":request" => self.show_request(),
+            ":authorize" => self.authorize(),
+            _ if command.starts_with(':') => Err(miette!(
+                "unknown command `{command}`; enter `:help` for a list of commands"
+            )),
+            _ if line.starts_with('{') => {
+                let qjson: RequestJSON = serde_json::from_str(line)
+                    .into_diagnostic()
+                    .wrap_err("failed to parse request")?;
+                self.principal =
+                    parse_uid(qjson.principal.as_deref().unwrap_or_default(), "principal")?;
+                self.action = parse_uid(qjson.action.as_deref().unwrap_or_default(), "action")?;
+                self.resource =
+                    parse_uid(qjson.resource.as_deref().unwrap_or_default(), "resource")?;
+                self.context = qjson.context;
+                self.authorize()
+            }
+            _ => self.evaluate(line),
+        }
+    }
+
+    fn request(&self) -> Result<Request> {
+        let context = Context::from_json_value(
+            self.context.clone(),
+            self.schema
+                .as_ref()
+                .and_then(|s| Some((s, self.action.as_ref()?))),
+        )
+        .wrap_err("failed to create a context")?;
+        Request::new(
+            self.principal.clone(),
+            self.action.clone(),
+            self.resource.clone(),
+            context,
+            self.schema.as_ref(),
+        )
+        .map_err(|e| miette!("{e}"))
+    }
+
+    fn show_request(&self) -> Result<String> {
+        let show = |uid: &Option<EntityUid>| {
+            uid.as_ref()
+                .map_or_else(|| "<unknown>".to_owned(), ToString::to_string)
+        };
+        Ok(format!(
+            "principal: {}\naction:    {}\nresource:  {}\ncontext:   {}",
+            show(&self.principal),
+            show(&self.action),
+            show(&self.resource),
+            self.context
+        ))
+    }
+
+    fn authorize(&self) -> Result<String> {
+        let request = self.request()?;
+        let ans = Authorizer::new().is_authorized(&request, &self.policies, &self.entities);
+        let mut lines = vec![match ans.decision() {
+            Decision::Allow => "ALLOW".to_owned(),
+            Decision::Deny => "DENY".to_owned(),
+        }];
+        if ans.diagnostics().reason().peekable().peek().is_none() {
+            lines.push("note: no policies applied to this request".to_owned());
+        } else {
+            lines.push("note: this decision was due to the following policies:".to_owned());
+            lines.extend(ans.diagnostics().reason().map(|id| format!("  {id}")));
+        }
+        lines.extend(ans.diagnostics().errors().map(ToString::to_string));
+        Ok(lines.join("\n"))
+    }
+
+    fn evaluate(&self, src: &str) -> Result<String> {
+        let expr = Expression::from_str(src)
+            .wrap_err("failed to parse the expression")
+            .map_err(|e| e.with_source_code(src.to_owned()))?;
+        let request = self.request()?;
+        let result = eval_expression(&request, &self.entities, &expr)
+            .wrap_err("failed to evaluate the expression")?;
+        Ok(result.to_string())
+    }
+}
+
+/// Parse `src` as an entity uid, or `None` if it is empty
+fn parse_uid(src: &str, field: &str) -> Result<Option<EntityUid>> {
+    if src.is_empty() {
+        return Ok(None);
+    }
+    src.parse()
+        .map(Some)
+        .wrap_err_with(|| format!("failed to parse {field} {src} as entity Uid"))
+}
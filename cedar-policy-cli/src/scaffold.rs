@@ -0,0 +1,218 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Generates skeleton policies from a schema, used by the `new-policy`
+//! subcommand.
+
+use std::collections::BTreeSet;
+
+use cedar_policy::cedar_schema::{is_ident, quoted, sorted};
+use cedar_policy::{Effect, EntityUid, Schema};
+use miette::{miette, Result, WrapErr};
+use serde_json::Value;
+
+use crate::schema_info::{qualify, SchemaInfo};
+
+/// Generate a skeleton policy for `action`, which is declared in `schema`, a
+/// schema in the JSON schema format.
+///
+/// The scope constrains the principal and resource to `principal_type` and
+/// `resource_type` if they are provided, or if the action applies to only one
+/// type. The `when` clause is a stub listing the context attributes of the
+/// action, with their types.
+pub fn new_policy(
+    schema: &Value,
+    action: &EntityUid,
+    principal_type: Option<&str>,
+    resource_type: Option<&str>,
+    effect: Effect,
+) -> Result<String> {
+    Schema::from_json_value(schema.clone()).wrap_err("failed to parse schema")?;
+    let info = SchemaInfo::new(schema);
+    let (namespace, applies_to) = info
+        .action(action)
+        .ok_or_else(|| miette!("action {action} is not declared in the schema"))?;
+    let principal = scope_type(
+        namespace,
+        applies_to,
+        "principal",
+        "principalTypes",
+        principal_type,
+    )?;
+    let resource = scope_type(
+        namespace,
+        applies_to,
+        "resource",
+        "resourceTypes",
+        resource_type,
+    )?;
+
+    let mut out = String::new();
+    for (var, ty) in [("principal", &principal), ("resource", &resource)] {
+        if let ScopeType::Any(types) = ty {
+            out.push_str(&format!(
+                "// {var} may be any of: {}\n",
+                types.iter().cloned().collect::<Vec<_>>().join(", ")
+            ));
+        }
+    }
+    let effect = match effect {
+        Effect::Permit => "permit",
+        Effect::Forbid => "forbid",
+    };
+    out.push_str(&format!(
+        "{effect} (\n  {},\n  action == {action},\n  {}\n)\nwhen {{\n",
+        principal.constraint("principal"),
+        resource.constraint("resource"),
+    ));
+    let mut attrs = Vec::new();
+    if let Some(context) = applies_to.get("context") {
+        context_attributes(&info, namespace, context, "context", &mut attrs);
+    }
+    if attrs.is_empty() {
+        out.push_str("  // the action has no context attributes\n");
+    } else {
+        out.push_str("  // context attributes:\n");
+        for attr in attrs {
+            out.push_str(&format!("  //   {attr}\n"));
+        }
+    }
+    out.push_str("  true\n};\n");
+    Ok(out)
+}
+
+/// The constraint on the principal or resource in the scope of a skeleton
+/// policy
+enum ScopeType {
+    /// Entities of this type
+    Is(String),
+    /// Any entity, which may have any of these types
+    Any(BTreeSet<String>),
+}
+
+impl ScopeType {
+    fn constraint(&self, var: &str) -> String {
+        match self {
+            ScopeType::Is(ty) => format!("{var} is {ty}"),
+            ScopeType::Any(_) => var.to_owned(),
+        }
+    }
+}
+
+/// The scope constraint on `var`, which may have the types listed under `key`
+/// in `applies_to`. `requested` must be one of them, if provided.
+fn scope_type(
+    namespace: &str,
+    applies_to: &Value,
+    var: &str,
+    key: &str,
+    requested: Option<&str>,
+) -> Result<ScopeType> {
+    let types: BTreeSet<String> = applies_to[key]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(|t| qualify(namespace, t))
+        .collect();
+    match requested {
+        Some(ty) => {
+            let ty = qualify(namespace, ty);
+            if types.contains(&ty) {
+                Ok(ScopeType::Is(ty))
+            } else if types.is_empty() {
+                Err(miette!("the action does not apply to any {var}"))
+            } else {
+                Err(miette!(
+                    "the action does not apply to {var} type {ty}; expected one of: {}",
+                    types.into_iter().collect::<Vec<_>>().join(", ")
+                ))
+            }
+        }
+        None => Ok(match types.first() {
+            Some(ty) if types.len() == 1 => ScopeType::Is(ty.clone()),
+            _ => ScopeType::Any(types),
+        }),
+    }
+}
+
+/// Add a line describing each attribute of `ty`, a record type which is the
+/// type of `path`, to `attrs`. Attributes of nested records are listed
+/// individually.
+fn context_attributes<'a>(
+    info: &SchemaInfo<'a>,
+    namespace: &'a str,
+    ty: &'a Value,
+    path: &str,
+    attrs: &mut Vec<String>,
+) {
+    let (namespace, ty) = info.resolve_common(namespace, ty);
+    for (name, attr_ty) in sorted(&ty["attributes"]) {
+        let name_str = if is_ident(name) {
+            name.clone()
+        } else {
+            quoted(name)
+        };
+        let attr_path = if is_ident(name) {
+            format!("{path}.{name}")
+        } else {
+            format!("{path}[{name_str}]")
+        };
+        let (attr_namespace, resolved) = info.resolve_common(namespace, attr_ty);
+        let is_record = resolved["type"] == "Record";
+        let ty_str = if is_record {
+            "record".to_owned()
+        } else {
+            type_name(namespace, attr_ty)
+        };
+        if attr_ty["required"] == false {
+            attrs.push(format!(
+                "{attr_path}: {ty_str} (optional, check with `{path} has {name_str}`)"
+            ));
+        } else if !is_record {
+            attrs.push(format!("{attr_path}: {ty_str}"));
+        }
+        if is_record {
+            context_attributes(info, attr_namespace, resolved, &attr_path, attrs);
+        }
+    }
+}
+
+/// The type `ty`, written in `namespace`, on one line
+fn type_name(namespace: &str, ty: &Value) -> String {
+    match ty["type"].as_str().unwrap_or_default() {
+        "Boolean" => "Bool".to_owned(),
+        "Set" => format!("Set<{}>", type_name(namespace, &ty["element"])),
+        "Record" => {
+            let attributes: Vec<String> = sorted(&ty["attributes"])
+                .into_iter()
+                .map(|(name, attr_ty)| {
+                    let optional = if attr_ty["required"] == false {
+                        "?"
+                    } else {
+                        ""
+                    };
+                    format!("{name}{optional}: {}", type_name(namespace, attr_ty))
+                })
+                .collect();
+            format!("{{{}}}", attributes.join(", "))
+        }
+        "Entity" => qualify(namespace, ty["name"].as_str().unwrap_or_default()),
+        "Extension" => ty["name"].as_str().unwrap_or_default().to_owned(),
+        // `String`, `Long`, and references to common types
+        name => name.to_owned(),
+    }
+}
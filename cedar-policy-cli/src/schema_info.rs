@@ -0,0 +1,173 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Lookups in a schema in the JSON schema format, shared by the subcommands
+//! which reason about policies using a schema.
+
+use std::collections::{BTreeSet, HashMap};
+
+use cedar_policy::EntityUid;
+use serde_json::{Map, Value};
+
+/// Qualify the type name `name`, written in `namespace`
+pub(crate) fn qualify(namespace: &str, name: &str) -> String {
+    if namespace.is_empty() || name.contains("::") {
+        name.to_owned()
+    } else {
+        format!("{namespace}::{name}")
+    }
+}
+
+/// An entity type declared in the schema
+pub(crate) struct EntityTypeInfo<'a> {
+    /// Namespace the type is declared in
+    pub(crate) namespace: &'a str,
+    /// The type's shape, or `null` if it has none
+    pub(crate) shape: &'a Value,
+    /// Attribute types of the type's shape, if it has attributes
+    pub(crate) attributes: Option<&'a Map<String, Value>>,
+    /// Types entities of this type can be members of
+    pub(crate) member_of: Vec<String>,
+}
+
+/// Declarations of a schema, with names fully qualified
+pub(crate) struct SchemaInfo<'a> {
+    entity_types: HashMap<String, EntityTypeInfo<'a>>,
+    common_types: HashMap<String, (&'a str, &'a Value)>,
+    schema: &'a Value,
+}
+
+impl<'a> SchemaInfo<'a> {
+    pub(crate) fn new(schema: &'a Value) -> Self {
+        let mut entity_types = HashMap::new();
+        let mut common_types = HashMap::new();
+        for (namespace, ns_def) in schema.as_object().into_iter().flatten() {
+            for (name, def) in ns_def["entityTypes"].as_object().into_iter().flatten() {
+                let member_of = def["memberOfTypes"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .map(|t| qualify(namespace, t))
+                    .collect();
+                entity_types.insert(
+                    qualify(namespace, name),
+                    EntityTypeInfo {
+                        namespace,
+                        shape: &def["shape"],
+                        attributes: def["shape"].get("attributes").and_then(Value::as_object),
+                        member_of,
+                    },
+                );
+            }
+            for (name, ty) in ns_def["commonTypes"].as_object().into_iter().flatten() {
+                common_types.insert(qualify(namespace, name), (namespace.as_str(), ty));
+            }
+        }
+        Self {
+            entity_types,
+            common_types,
+            schema,
+        }
+    }
+
+    /// The namespace and `appliesTo` of `action`
+    pub(crate) fn action(&self, action: &EntityUid) -> Option<(&'a str, &'a Value)> {
+        let id: &str = action.id().as_ref();
+        self.schema
+            .as_object()?
+            .iter()
+            .find_map(|(namespace, ns_def)| {
+                let def = ns_def["actions"].get(id)?;
+                (qualify(namespace, "Action") == action.type_name().to_string())
+                    .then(|| (namespace.as_str(), &def["appliesTo"]))
+            })
+    }
+
+    /// Every declared entity type, fully qualified, in sorted order
+    pub(crate) fn entity_type_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.entity_types.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// The namespace, id, and `appliesTo` of every declared action
+    pub(crate) fn actions(&self) -> Vec<(&'a str, &'a str, &'a Value)> {
+        self.schema
+            .as_object()
+            .into_iter()
+            .flatten()
+            .flat_map(|(namespace, ns_def)| {
+                ns_def["actions"]
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .map(move |(id, def)| (namespace.as_str(), id.as_str(), &def["appliesTo"]))
+            })
+            .collect()
+    }
+
+    /// The entity type named `name`, fully qualified
+    pub(crate) fn entity_type(&self, name: &str) -> Option<&EntityTypeInfo<'a>> {
+        self.entity_types.get(name)
+    }
+
+    /// The namespace and definition of the common type `name`, written in
+    /// `namespace`
+    pub(crate) fn common_type(&self, namespace: &str, name: &str) -> Option<(&'a str, &'a Value)> {
+        self.common_types.get(&qualify(namespace, name)).copied()
+    }
+
+    /// Follow references to common types until `ty`, written in `namespace`,
+    /// is a type written directly
+    pub(crate) fn resolve_common(
+        &self,
+        mut namespace: &'a str,
+        mut ty: &'a Value,
+    ) -> (&'a str, &'a Value) {
+        while let Some(name) = ty["type"].as_str() {
+            match self.common_type(namespace, name) {
+                Some(def) => (namespace, ty) = def,
+                None => break,
+            }
+        }
+        (namespace, ty)
+    }
+
+    /// Whether entities of type `ty` can have ancestors of type `ancestor`
+    pub(crate) fn can_be_in(&self, ty: &str, ancestor: &str) -> bool {
+        self.ancestor_types(ty).contains(ancestor)
+    }
+
+    /// Every type of ancestor entities of type `ty` can have
+    pub(crate) fn ancestor_types(&self, ty: &str) -> BTreeSet<String> {
+        let mut found = BTreeSet::new();
+        let mut todo = vec![ty.to_owned()];
+        while let Some(ty) = todo.pop() {
+            for parent in self
+                .entity_types
+                .get(&ty)
+                .into_iter()
+                .flat_map(|t| &t.member_of)
+            {
+                if found.insert(parent.clone()) {
+                    todo.push(parent.clone());
+                }
+            }
+        }
+        found
+    }
+}
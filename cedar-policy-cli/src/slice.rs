@@ -0,0 +1,395 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Computes which entity data the policies for an action can read, used by
+//! the `slice-entities` subcommand.
+//!
+//! Types of expressions are inferred from the schema, so the slice covers
+//! every entity store which conforms to it. Attributes and ancestors which the
+//! schema doesn't declare are never needed, because conforming entities can't
+//! have them.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
+
+use cedar_policy::*;
+use miette::{miette, Result, WrapErr};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::analyze::{action_includes, conditions};
+use crate::schema_info::{qualify, SchemaInfo};
+
+/// The entity data which may be read while evaluating the policies for one
+/// action
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntitySlice {
+    /// Entity types whose attributes or ancestors may be read, by name
+    pub entity_types: BTreeMap<String, EntityTypeSlice>,
+}
+
+/// The data which may be read for entities of one type
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct EntityTypeSlice {
+    /// Attributes which may be read or tested with `has`
+    pub attributes: BTreeSet<String>,
+    /// Types of ancestors which may be tested with `in`
+    pub ancestors: BTreeSet<String>,
+}
+
+impl Display for EntitySlice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.entity_types.is_empty() {
+            return write!(f, "no entity data is needed");
+        }
+        let list = |names: &BTreeSet<String>| names.iter().cloned().collect::<Vec<_>>().join(", ");
+        let mut first = true;
+        for (name, slice) in &self.entity_types {
+            if !first {
+                writeln!(f)?;
+            }
+            first = false;
+            write!(f, "{name}")?;
+            if !slice.attributes.is_empty() {
+                write!(f, "\n  attributes: {}", list(&slice.attributes))?;
+            }
+            if !slice.ancestors.is_empty() {
+                write!(f, "\n  ancestors: {}", list(&slice.ancestors))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compute the entity data which may be read while evaluating the policies in
+/// `policies` which apply to `action`. `schema` is a schema in the JSON schema
+/// format. Templates are only considered through their links.
+pub fn slice_entities(
+    policies: &PolicySet,
+    schema: &Value,
+    action: &EntityUid,
+) -> Result<EntitySlice> {
+    let hierarchy = Schema::from_json_value(schema.clone())
+        .wrap_err("failed to parse schema")?
+        .action_entities()
+        .wrap_err("failed to construct action entities from the schema")?;
+    let info = SchemaInfo::new(schema);
+    let (namespace, applies_to) = info
+        .action(action)
+        .ok_or_else(|| miette!("action {action} is not declared in the schema"))?;
+    let types = |key: &str| -> BTreeSet<String> {
+        applies_to[key]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .map(|t| qualify(namespace, t))
+            .collect()
+    };
+    let (principals, resources) = (types("principalTypes"), types("resourceTypes"));
+    let context = match applies_to.get("context") {
+        Some(ty) => resolve(&info, namespace, ty),
+        None => Ty::Other,
+    };
+
+    let mut slicer = Slicer {
+        info: &info,
+        slice: EntitySlice::default(),
+        principal: Ty::Other,
+        resource: Ty::Other,
+        context,
+    };
+    let this_action = ActionConstraint::Eq(action.clone());
+    for p in policies.policies() {
+        if !action_includes(&p.action_constraint(), &this_action, Some(&hierarchy)) {
+            continue;
+        }
+        let principal = match p.principal_constraint() {
+            PrincipalConstraint::Any => principals.clone(),
+            PrincipalConstraint::Eq(e) => slicer.is(&principals, e.type_name()),
+            PrincipalConstraint::Is(t) => slicer.is(&principals, &t),
+            PrincipalConstraint::In(e) => slicer.is_in(&principals, &e),
+            PrincipalConstraint::IsIn(t, e) => {
+                let principals = slicer.is(&principals, &t);
+                slicer.is_in(&principals, &e)
+            }
+        };
+        let resource = match p.resource_constraint() {
+            ResourceConstraint::Any => resources.clone(),
+            ResourceConstraint::Eq(e) => slicer.is(&resources, e.type_name()),
+            ResourceConstraint::Is(t) => slicer.is(&resources, &t),
+            ResourceConstraint::In(e) => slicer.is_in(&resources, &e),
+            ResourceConstraint::IsIn(t, e) => {
+                let resources = slicer.is(&resources, &t);
+                slicer.is_in(&resources, &e)
+            }
+        };
+        slicer.principal = Ty::Entity(principal);
+        slicer.resource = Ty::Entity(resource);
+        for condition in conditions(p) {
+            if let Some(body) = condition.get("body") {
+                slicer.expr(body);
+            }
+        }
+    }
+    Ok(slicer.slice)
+}
+
+/// The type of an expression, as far as it matters for slicing
+#[derive(Debug, Clone)]
+enum Ty {
+    /// An entity of one of these types
+    Entity(BTreeSet<String>),
+    /// A set of values of this type
+    Set(Box<Ty>),
+    /// A record with these attribute types, declared in this namespace
+    Record(String, Map<String, Value>),
+    /// Anything else
+    Other,
+}
+
+impl Ty {
+    /// The type of an expression which has type `self` or type `other`
+    fn union(self, other: Ty) -> Ty {
+        match (self, other) {
+            (Ty::Entity(mut a), Ty::Entity(b)) => {
+                a.extend(b);
+                Ty::Entity(a)
+            }
+            (Ty::Set(a), Ty::Set(b)) => Ty::Set(Box::new(a.union(*b))),
+            (Ty::Record(ns, a), Ty::Record(_, b)) if a == b => Ty::Record(ns, a),
+            _ => Ty::Other,
+        }
+    }
+
+    /// Entity types of this entity, or of the elements of this set
+    fn entity_types(&self) -> Option<&BTreeSet<String>> {
+        match self {
+            Ty::Entity(types) => Some(types),
+            Ty::Set(elem) => match elem.as_ref() {
+                Ty::Entity(types) => Some(types),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// The type described by the JSON type `ty`, written in `namespace`
+fn resolve(info: &SchemaInfo<'_>, namespace: &str, ty: &Value) -> Ty {
+    match ty["type"].as_str() {
+        Some("Entity") => Ty::Entity(
+            ty["name"]
+                .as_str()
+                .map(|name| qualify(namespace, name))
+                .into_iter()
+                .collect(),
+        ),
+        Some("Set") => Ty::Set(Box::new(resolve(info, namespace, &ty["element"]))),
+        Some("Record") => Ty::Record(
+            namespace.to_owned(),
+            ty["attributes"].as_object().cloned().unwrap_or_default(),
+        ),
+        Some("String" | "Long" | "Boolean" | "Extension") | None => Ty::Other,
+        Some(common) => match info.common_type(namespace, common) {
+            Some((namespace, ty)) => resolve(info, namespace, ty),
+            None => Ty::Other,
+        },
+    }
+}
+
+/// Walks the conditions of policies, recording the entity data they read
+struct Slicer<'a> {
+    info: &'a SchemaInfo<'a>,
+    slice: EntitySlice,
+    principal: Ty,
+    resource: Ty,
+    context: Ty,
+}
+
+impl Slicer<'_> {
+    /// The types in `types` which match `is ty`
+    fn is(&self, types: &BTreeSet<String>, ty: &EntityTypeName) -> BTreeSet<String> {
+        let ty = ty.to_string();
+        types.iter().filter(|t| **t == ty).cloned().collect()
+    }
+
+    /// The types in `types` which can match `in e`, recording the ancestors
+    /// which must be provided to decide it
+    fn is_in(&mut self, types: &BTreeSet<String>, e: &EntityUid) -> BTreeSet<String> {
+        let ancestor = e.type_name().to_string();
+        self.ancestors(types, &BTreeSet::from([ancestor.clone()]));
+        types
+            .iter()
+            .filter(|t| **t == ancestor || self.info.can_be_in(t, &ancestor))
+            .cloned()
+            .collect()
+    }
+
+    /// Record that entities of `types` may be tested for ancestors of `ancestors`
+    fn ancestors(&mut self, types: &BTreeSet<String>, ancestors: &BTreeSet<String>) {
+        for ty in types {
+            for ancestor in ancestors {
+                if self.info.can_be_in(ty, ancestor) {
+                    self.slice
+                        .entity_types
+                        .entry(ty.clone())
+                        .or_default()
+                        .ancestors
+                        .insert(ancestor.clone());
+                }
+            }
+        }
+    }
+
+    /// Record that attribute `attr` of `ty` may be read, returning its type
+    fn attr(&mut self, ty: &Ty, attr: &str) -> Ty {
+        match ty {
+            Ty::Entity(types) => {
+                let mut result: Option<Ty> = None;
+                for name in types {
+                    let Some(info) = self.info.entity_type(name) else {
+                        continue;
+                    };
+                    let Some(attr_ty) = info.attributes.and_then(|attrs| attrs.get(attr)) else {
+                        continue;
+                    };
+                    self.slice
+                        .entity_types
+                        .entry(name.clone())
+                        .or_default()
+                        .attributes
+                        .insert(attr.to_owned());
+                    let attr_ty = resolve(self.info, info.namespace, attr_ty);
+                    result = Some(match result {
+                        Some(result) => result.union(attr_ty),
+                        None => attr_ty,
+                    });
+                }
+                result.unwrap_or(Ty::Other)
+            }
+            Ty::Record(namespace, attrs) => match attrs.get(attr) {
+                Some(attr_ty) => resolve(self.info, namespace, attr_ty),
+                None => Ty::Other,
+            },
+            Ty::Set(_) | Ty::Other => Ty::Other,
+        }
+    }
+
+    /// The type of the literal value `value`, in the JSON entity format
+    fn value(&self, value: &Value) -> Ty {
+        match value {
+            Value::Object(o) => match o.get("__entity").and_then(|e| e["type"].as_str()) {
+                Some(ty) => Ty::Entity(BTreeSet::from([ty.to_owned()])),
+                None => Ty::Other,
+            },
+            Value::Array(elems) => Ty::Set(Box::new(
+                elems
+                    .iter()
+                    .map(|e| self.value(e))
+                    .reduce(Ty::union)
+                    .unwrap_or(Ty::Other),
+            )),
+            _ => Ty::Other,
+        }
+    }
+
+    /// Walk the expression `expr`, in the JSON policy format, returning its type
+    fn expr(&mut self, expr: &Value) -> Ty {
+        let Some((op, arg)) = expr.as_object().and_then(|o| o.iter().next()) else {
+            return Ty::Other;
+        };
+        match op.as_str() {
+            "Value" => self.value(arg),
+            "Var" => match arg.as_str() {
+                Some("principal") => self.principal.clone(),
+                Some("resource") => self.resource.clone(),
+                Some("context") => self.context.clone(),
+                _ => Ty::Other,
+            },
+            "." | "has" => {
+                let left = self.expr(&arg["left"]);
+                let ty = self.attr(&left, arg["attr"].as_str().unwrap_or_default());
+                if op == "." {
+                    ty
+                } else {
+                    Ty::Other
+                }
+            }
+            "in" | "is" => {
+                let left = self.expr(&arg["left"]);
+                // `e is T` reads no entity data, but `e is T in f` does
+                let right = match arg.get(if op == "in" { "right" } else { "in" }) {
+                    Some(right) => self.expr(right),
+                    None => return Ty::Other,
+                };
+                if let Some(types) = left.entity_types() {
+                    let ancestors = match right.entity_types() {
+                        Some(ancestors) => ancestors.clone(),
+                        // any ancestor may be tested
+                        None => types
+                            .iter()
+                            .flat_map(|t| self.info.ancestor_types(t))
+                            .collect(),
+                    };
+                    self.ancestors(types, &ancestors);
+                }
+                Ty::Other
+            }
+            "if-then-else" => {
+                self.expr(&arg["if"]);
+                let then_ty = self.expr(&arg["then"]);
+                let else_ty = self.expr(&arg["else"]);
+                then_ty.union(else_ty)
+            }
+            "Set" => Ty::Set(Box::new(
+                arg.as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|e| self.expr(e))
+                    .reduce(Ty::union)
+                    .unwrap_or(Ty::Other),
+            )),
+            "Record" => {
+                for value in arg.as_object().into_iter().flatten().map(|(_, v)| v) {
+                    self.expr(value);
+                }
+                Ty::Other
+            }
+            // other operators, and extension function calls, whose arguments
+            // are in an array
+            _ => {
+                match arg {
+                    Value::Array(args) => {
+                        for arg in args {
+                            self.expr(arg);
+                        }
+                    }
+                    Value::Object(args) => {
+                        for key in ["left", "right", "arg"] {
+                            if let Some(arg) = args.get(key) {
+                                self.expr(arg);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                Ty::Other
+            }
+        }
+    }
+}
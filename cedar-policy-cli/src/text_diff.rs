@@ -0,0 +1,151 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Line-based unified diffs, used by `format --check --diff`.
+
+/// Number of unchanged lines shown around each change
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    /// The line is in both texts
+    Same,
+    /// The line is only in the old text
+    Delete,
+    /// The line is only in the new text
+    Insert,
+}
+
+/// A unified diff from `old` to `new`, with the headers `--- old_name` and
+/// `+++ new_name`, or the empty string if they have the same lines.
+// `changes` are indices of `ops`, `positions` has an entry for each op and one
+// after the last, and the ops of a hunk step through the lines of `old` and
+// `new` from the positions before them, so every index is in bounds.
+// PANIC SAFETY: See above.
+#[allow(clippy::indexing_slicing)]
+pub(crate) fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let ops = edit_script(&old, &new);
+    if ops.iter().all(|op| *op == Op::Same) {
+        return String::new();
+    }
+
+    // the line of each text before each op
+    let mut positions = Vec::with_capacity(ops.len() + 1);
+    let (mut i, mut j) = (0, 0);
+    for op in &ops {
+        positions.push((i, j));
+        match op {
+            Op::Same => (i, j) = (i + 1, j + 1),
+            Op::Delete => i += 1,
+            Op::Insert => j += 1,
+        }
+    }
+    positions.push((i, j));
+
+    let mut out = format!("--- {old_name}\n+++ {new_name}\n");
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| **op != Op::Same)
+        .map(|(k, _)| k)
+        .collect();
+    let mut group_start = 0;
+    while group_start < changes.len() {
+        // changes separated by at most twice the context share a hunk
+        let mut group_end = group_start;
+        while group_end + 1 < changes.len()
+            && changes[group_end + 1] - changes[group_end] <= 2 * CONTEXT + 1
+        {
+            group_end += 1;
+        }
+        let start = changes[group_start].saturating_sub(CONTEXT);
+        let end = (changes[group_end] + CONTEXT + 1).min(ops.len());
+        let (old_start, new_start) = positions[start];
+        let (old_end, new_end) = positions[end];
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_end - old_start),
+            hunk_range(new_start, new_end - new_start)
+        ));
+        let (mut i, mut j) = (old_start, new_start);
+        for op in &ops[start..end] {
+            match op {
+                Op::Same => {
+                    out.push_str(&format!(" {}\n", old[i]));
+                    (i, j) = (i + 1, j + 1);
+                }
+                Op::Delete => {
+                    out.push_str(&format!("-{}\n", old[i]));
+                    i += 1;
+                }
+                Op::Insert => {
+                    out.push_str(&format!("+{}\n", new[j]));
+                    j += 1;
+                }
+            }
+        }
+        group_start = group_end + 1;
+    }
+    out
+}
+
+/// The range of a hunk in the `@@` header. An empty range is written with the
+/// line before it, as `diff -u` does.
+fn hunk_range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{start},0"),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{len}", start + 1),
+    }
+}
+
+/// A shortest sequence of ops turning `old` into `new`, from a longest common
+/// subsequence. Deletions are ordered before insertions.
+// PANIC SAFETY: `i` and `j` are less than the lengths of `old` and `new`, and the table has a row and a column more than them
+#[allow(clippy::indexing_slicing)]
+fn edit_script(old: &[&str], new: &[&str]) -> Vec<Op> {
+    // lcs[i][j] is the length of the longest common subsequence of
+    // `old[i..]` and `new[j..]`
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut ops = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            ops.push(Op::Same);
+            (i, j) = (i + 1, j + 1);
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            ops.push(Op::Delete);
+            i += 1;
+        } else {
+            ops.push(Op::Insert);
+            j += 1;
+        }
+    }
+    ops.extend(std::iter::repeat_n(Op::Delete, old.len() - i));
+    ops.extend(std::iter::repeat_n(Op::Insert, new.len() - j));
+    ops
+}
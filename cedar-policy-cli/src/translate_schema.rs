@@ -0,0 +1,49 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Translation between the JSON schema format and the human-readable Cedar
+//! schema format, used by the `translate-schema` subcommand. The translation
+//! itself is in [`cedar_policy::cedar_schema`].
+
+use cedar_policy::cedar_schema;
+use cedar_policy::Schema;
+use miette::{Result, WrapErr};
+use serde_json::Value;
+
+pub use cedar_policy::cedar_schema::{SyntaxError, TranslationWarning};
+
+/// Check that `json` is a valid schema in the JSON schema format
+pub fn validate_json(json: &Value) -> Result<()> {
+    Schema::from_json_value(json.clone()).wrap_err("failed to parse schema")?;
+    Ok(())
+}
+
+/// Check that `json` is a valid schema in the JSON schema format, or part of
+/// one which includes other schema files
+pub fn check_json(json: &Value) -> Result<()> {
+    cedar_schema::check_json(json).wrap_err("failed to parse schema")
+}
+
+/// Translate a schema in the JSON schema format to the Cedar schema format
+pub fn json_to_cedar(json: &Value) -> Result<(String, Vec<TranslationWarning>)> {
+    cedar_schema::json_to_cedar(json).wrap_err("failed to parse schema")
+}
+
+/// Translate a schema in the Cedar schema format to the JSON schema format.
+/// `name` is the name of the file the schema was read from, for errors.
+pub fn cedar_to_json(name: &str, src: &str) -> Result<(Value, Vec<TranslationWarning>)> {
+    Ok(cedar_schema::cedar_to_json(name, src)?)
+}
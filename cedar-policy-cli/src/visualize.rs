@@ -0,0 +1,223 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Renders entity hierarchies and policy applicability as Graphviz DOT
+//! graphs, used by the `visualize` subcommands.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+use cedar_policy::*;
+use miette::{miette, Result, WrapErr};
+use serde_json::Value;
+
+use crate::analyze::action_includes;
+use crate::entity_uid;
+use crate::schema_info::{qualify, SchemaInfo};
+
+/// A DOT graph of the entity hierarchy in `entities`, a list of entities in
+/// the JSON entity format. Entities are grouped by type, with an edge from
+/// each entity to each of its parents.
+pub fn entities_dot(entities: &Value) -> Result<String> {
+    let entities = entities
+        .as_array()
+        .ok_or_else(|| miette!("expected entities to be a JSON array"))?;
+    let mut types: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut edges = BTreeSet::new();
+    for (i, entity) in entities.iter().enumerate() {
+        let (ty, id) =
+            entity_uid(&entity["uid"]).ok_or_else(|| miette!("entity {i} has no valid `uid`"))?;
+        for parent in entity["parents"].as_array().into_iter().flatten() {
+            let (parent_ty, parent_id) = entity_uid(parent)
+                .ok_or_else(|| miette!("entity {i} has a parent which is not an entity uid"))?;
+            edges.insert((uid_str(&ty, &id), uid_str(&parent_ty, &parent_id)));
+            types.entry(parent_ty).or_default().insert(parent_id);
+        }
+        types.entry(ty).or_default().insert(id);
+    }
+
+    let mut out = "digraph entities {\n  rankdir=BT;\n  node [shape=box];\n".to_owned();
+    for (ty, ids) in &types {
+        out.push_str(&format!(
+            "  subgraph {} {{\n    label={};\n",
+            quoted(&format!("cluster_{ty}")),
+            quoted(ty)
+        ));
+        for id in ids {
+            out.push_str(&format!(
+                "    {} [label={}];\n",
+                quoted(&uid_str(ty, id)),
+                quoted(id)
+            ));
+        }
+        out.push_str("  }\n");
+    }
+    for (child, parent) in &edges {
+        out.push_str(&format!("  {} -> {};\n", quoted(child), quoted(parent)));
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+/// A DOT graph of which policies in `policies` can apply to which principal
+/// and resource types, according to `schema`, a schema in the JSON schema
+/// format. Principal types are on the left, policies in the middle, and
+/// resource types on the right. Templates are only shown through their links.
+pub fn policies_dot(policies: &PolicySet, schema: &Value) -> Result<String> {
+    let hierarchy = Schema::from_json_value(schema.clone())
+        .wrap_err("failed to parse schema")?
+        .action_entities()
+        .wrap_err("failed to construct action entities from the schema")?;
+    let info = SchemaInfo::new(schema);
+    let mut actions = vec![];
+    for (namespace, id, applies_to) in info.actions() {
+        let uid = format!("{}::{}", qualify(namespace, "Action"), Value::from(id));
+        let uid = EntityUid::from_str(&uid)
+            .wrap_err_with(|| format!("failed to parse action {uid} as entity Uid"))?;
+        let types = |key: &str| -> BTreeSet<String> {
+            match applies_to[key].as_array() {
+                Some(types) => types
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(|t| qualify(namespace, t))
+                    .collect(),
+                // unspecified entities, which have no type
+                None => BTreeSet::new(),
+            }
+        };
+        actions.push((id, uid, types("principalTypes"), types("resourceTypes")));
+    }
+
+    let mut principal_types = BTreeSet::new();
+    let mut resource_types = BTreeSet::new();
+    let mut nodes = vec![];
+    let mut edges = BTreeSet::new();
+    for p in policies.policies() {
+        let node = format!("policy:{}", p.id());
+        let mut applies_to = vec![];
+        for (id, uid, principals, resources) in &actions {
+            let this_action = ActionConstraint::Eq(uid.clone());
+            if !action_includes(&p.action_constraint(), &this_action, Some(&hierarchy)) {
+                continue;
+            }
+            let principals = match p.principal_constraint() {
+                PrincipalConstraint::Any => principals.clone(),
+                PrincipalConstraint::Eq(e) => {
+                    matching(&info, principals, Some(e.type_name()), None)
+                }
+                PrincipalConstraint::Is(t) => matching(&info, principals, Some(&t), None),
+                PrincipalConstraint::In(e) => matching(&info, principals, None, Some(&e)),
+                PrincipalConstraint::IsIn(t, e) => matching(&info, principals, Some(&t), Some(&e)),
+            };
+            let resources = match p.resource_constraint() {
+                ResourceConstraint::Any => resources.clone(),
+                ResourceConstraint::Eq(e) => matching(&info, resources, Some(e.type_name()), None),
+                ResourceConstraint::Is(t) => matching(&info, resources, Some(&t), None),
+                ResourceConstraint::In(e) => matching(&info, resources, None, Some(&e)),
+                ResourceConstraint::IsIn(t, e) => matching(&info, resources, Some(&t), Some(&e)),
+            };
+            if principals.is_empty() || resources.is_empty() {
+                continue;
+            }
+            applies_to.push(*id);
+            for ty in principals {
+                edges.insert((format!("principal:{ty}"), node.clone()));
+                principal_types.insert(ty);
+            }
+            for ty in resources {
+                edges.insert((node.clone(), format!("resource:{ty}")));
+                resource_types.insert(ty);
+            }
+        }
+        let (effect, color) = match p.effect() {
+            Effect::Permit => ("permit", "darkgreen"),
+            Effect::Forbid => ("forbid", "red"),
+        };
+        let action_names = if applies_to.is_empty() {
+            "no actions".to_owned()
+        } else {
+            applies_to.join(", ")
+        };
+        nodes.push(format!(
+            "  {} [shape=ellipse, color={color}, label={}];\n",
+            quoted(&node),
+            label(&[&p.id().to_string(), effect, &action_names])
+        ));
+    }
+
+    let mut out = "digraph policies {\n  rankdir=LR;\n  node [shape=box];\n".to_owned();
+    for (side, types) in [
+        ("principal", &principal_types),
+        ("resource", &resource_types),
+    ] {
+        for ty in types {
+            out.push_str(&format!(
+                "  {} [label={}];\n",
+                quoted(&format!("{side}:{ty}")),
+                quoted(ty)
+            ));
+        }
+    }
+    for node in nodes {
+        out.push_str(&node);
+    }
+    for (from, to) in &edges {
+        out.push_str(&format!("  {} -> {};\n", quoted(from), quoted(to)));
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+/// The types in `types` which can match `is ty in e`, either part of which
+/// may be omitted
+fn matching(
+    info: &SchemaInfo<'_>,
+    types: &BTreeSet<String>,
+    ty: Option<&EntityTypeName>,
+    e: Option<&EntityUid>,
+) -> BTreeSet<String> {
+    let ty = ty.map(ToString::to_string);
+    let ancestor = e.map(|e| e.type_name().to_string());
+    types
+        .iter()
+        .filter(|t| ty.as_ref().is_none_or(|ty| *t == ty))
+        .filter(|t| {
+            ancestor
+                .as_ref()
+                .is_none_or(|a| *t == a || info.can_be_in(t, a))
+        })
+        .cloned()
+        .collect()
+}
+
+/// An entity uid in Cedar syntax
+fn uid_str(ty: &str, id: &str) -> String {
+    format!("{ty}::{}", Value::from(id))
+}
+
+/// A DOT quoted string
+fn quoted(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// A DOT label with one line for each of `lines`
+fn label(lines: &[&str]) -> String {
+    let lines: Vec<String> = lines
+        .iter()
+        .map(|line| line.replace('\\', "\\\\").replace('"', "\\\""))
+        .collect();
+    format!("\"{}\"", lines.join("\\n"))
+}
@@ -0,0 +1,101 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Polling file watcher, used by the `--watch` flag of `validate` and
+//! `format`.
+//!
+//! Files are compared by modification time, which works on every platform
+//! without OS-specific notification APIs.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Watches a set of files and directories for changes
+#[derive(Debug)]
+pub struct Watcher {
+    paths: Vec<PathBuf>,
+    /// Modification time of every watched file, or `None` if it can't be read
+    modified: BTreeMap<PathBuf, Option<SystemTime>>,
+}
+
+impl Watcher {
+    /// Start watching `paths`. Directories are watched recursively, including
+    /// for files added later.
+    pub fn new(paths: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        let paths: Vec<PathBuf> = paths.into_iter().map(Into::into).collect();
+        let modified = snapshot(&paths);
+        Self { paths, modified }
+    }
+
+    /// Files which were added, modified, or removed since the watcher was
+    /// created or this method was last called, in sorted order
+    pub fn changed(&mut self) -> Vec<PathBuf> {
+        let modified = snapshot(&self.paths);
+        let mut changed: Vec<PathBuf> = modified
+            .iter()
+            .filter(|(path, time)| self.modified.get(*path) != Some(*time))
+            .map(|(path, _)| path.clone())
+            .chain(
+                self.modified
+                    .keys()
+                    .filter(|path| !modified.contains_key(*path))
+                    .cloned(),
+            )
+            .collect();
+        changed.sort();
+        self.modified = modified;
+        changed
+    }
+
+    /// Block until some files change, checking every `interval`, and return
+    /// them. Changes made shortly after the first one, e.g., by an editor
+    /// which saves in several steps, are reported together.
+    pub fn wait(&mut self, interval: Duration) -> Vec<PathBuf> {
+        loop {
+            std::thread::sleep(interval);
+            let mut changed = self.changed();
+            if !changed.is_empty() {
+                std::thread::sleep(interval);
+                changed.extend(self.changed());
+                changed.sort();
+                changed.dedup();
+                return changed;
+            }
+        }
+    }
+}
+
+/// Modification times of the files in `paths`
+fn snapshot(paths: &[PathBuf]) -> BTreeMap<PathBuf, Option<SystemTime>> {
+    let mut modified = BTreeMap::new();
+    for path in paths {
+        add_files(path, &mut modified);
+    }
+    modified
+}
+
+fn add_files(path: &Path, modified: &mut BTreeMap<PathBuf, Option<SystemTime>>) {
+    if path.is_dir() {
+        for entry in std::fs::read_dir(path).into_iter().flatten().flatten() {
+            add_files(&entry.path(), modified);
+        }
+    } else {
+        // a missing file is still watched, so that creating it is a change
+        let time = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        modified.insert(path.to_path_buf(), time);
+    }
+}
@@ -18,14 +18,22 @@
 #![allow(clippy::expect_used)]
 // PANIC SAFETY tests
 #![allow(clippy::unwrap_used)]
+// PANIC SAFETY tests
+#![allow(clippy::indexing_slicing)]
 use std::collections::HashMap;
 
 use cedar_policy::EvalResult;
 use cedar_policy::SlotId;
 use cedar_policy_cli::check_parse;
 use cedar_policy_cli::{
-    authorize, evaluate, link, validate, Arguments, AuthorizeArgs, CedarExitCode, CheckParseArgs,
-    EvaluateArgs, LinkArgs, PoliciesArgs, PolicyFormat, RequestArgs, ValidateArgs,
+    analyze, authorize, bench, diff_policies, document_schema, evaluate, generate_requests,
+    infer_schema, link, link_list, link_remove, new_policy, partially_authorize, slice_entities,
+    translate_schema, validate, validate_entities, visualize_entities, visualize_policies,
+    AnalyzeArgs, Arguments, AuthorizeArgs, BenchArgs, CedarExitCode, CheckParseArgs,
+    DiffPoliciesArgs, DocumentFormat, DocumentSchemaArgs, EvaluateArgs, GenerateRequestsArgs,
+    InferSchemaArgs, LinkArgs, LinkListArgs, LinkRemoveArgs, NewPolicyArgs, PartiallyAuthorizeArgs,
+    PoliciesArgs, PolicyFormat, RequestArgs, SchemaFormat, SliceEntitiesArgs, TranslateSchemaArgs,
+    ValidateArgs, ValidateEntitiesArgs, VisualizeEntitiesArgs, VisualizePoliciesArgs,
 };
 
 fn run_check_parse_test(policies_file: impl Into<String>, expected_exit_code: CedarExitCode) {
@@ -85,6 +93,7 @@ fn run_authorize_test_with_linked_policies(
         entities_file: entities_file.into(),
         verbose: true,
         timing: false,
+        requests_file: None,
     };
     let output = authorize(&cmd);
     assert_eq!(exit_code, output, "{:#?}", cmd,);
@@ -127,6 +136,16 @@ fn run_format_test(policies_file: &str) {
         std::str::from_utf8(&format_cmd.get_output().stdout).expect("output should be decodable"),
         std::fs::read_to_string(policies_file).unwrap()
     );
+    assert_cmd::Command::cargo_bin("cedar")
+        .expect("bin exists")
+        .arg("format")
+        .arg("-p")
+        .arg(policies_file)
+        .arg("--check")
+        .arg("--diff")
+        .assert()
+        .code(0)
+        .stdout("");
 }
 
 fn run_authorize_test_context(
@@ -156,6 +175,7 @@ fn run_authorize_test_context(
         entities_file: entities_file.into(),
         verbose: true,
         timing: false,
+        requests_file: None,
     };
     let output = authorize(&cmd);
     assert_eq!(exit_code, output, "{:#?}", cmd,);
@@ -185,11 +205,42 @@ fn run_authorize_test_json(
         entities_file: entities_file.into(),
         verbose: true,
         timing: false,
+        requests_file: None,
     };
     let output = authorize(&cmd);
     assert_eq!(exit_code, output, "{:#?}", cmd,);
 }
 
+fn run_authorize_batch_test(
+    policies_file: &str,
+    entities_file: &str,
+    requests_file: &str,
+    exit_code: CedarExitCode,
+) {
+    let cmd = AuthorizeArgs {
+        request: RequestArgs {
+            principal: None,
+            action: None,
+            resource: None,
+            context_json_file: None,
+            request_json_file: None,
+            request_validation: true,
+        },
+        policies: PoliciesArgs {
+            policies_file: Some(policies_file.into()),
+            policy_format: PolicyFormat::Human,
+        },
+        template_linked_file: None,
+        schema_file: None,
+        entities_file: entities_file.into(),
+        verbose: false,
+        timing: false,
+        requests_file: Some(requests_file.into()),
+    };
+    let output = authorize(&cmd);
+    assert_eq!(exit_code, output, "{:#?}", cmd);
+}
+
 #[test]
 fn test_authorize_samples() {
     run_check_parse_test(
@@ -465,11 +516,36 @@ fn run_validate_test(policies_file: &str, schema_file: &str, exit_code: CedarExi
         },
         deny_warnings: false,
         partial_validate: false,
+        watch: false,
     };
     let output = validate(&cmd);
     assert_eq!(exit_code, output, "{:#?}", cmd);
 }
 
+#[test]
+fn test_authorize_batch_samples() {
+    // Denied requests don't affect the exit code
+    run_authorize_batch_test(
+        "sample-data/sandbox_a/policies_1.cedar",
+        "sample-data/sandbox_a/entities.json",
+        "sample-data/sandbox_a/requests.ndjson",
+        CedarExitCode::Success,
+    );
+    // Contains a request with a malformed principal
+    run_authorize_batch_test(
+        "sample-data/sandbox_a/policies_1.cedar",
+        "sample-data/sandbox_a/entities.json",
+        "sample-data/sandbox_a/requests_bad.ndjson",
+        CedarExitCode::Failure,
+    );
+    run_authorize_batch_test(
+        "sample-data/sandbox_a/policies_1.cedar",
+        "sample-data/sandbox_a/entities.json",
+        "sample-data/doesnotexist.ndjson",
+        CedarExitCode::Failure,
+    );
+}
+
 #[test]
 fn test_validate_samples() {
     run_validate_test(
@@ -712,6 +788,9 @@ fn test_evaluate_samples() {
 
 #[test]
 fn test_link_samples() {
+    use cedar_policy::PolicySet;
+    use std::str::FromStr;
+
     run_authorize_test(
         "sample-data/sandbox_c/doesnotexist.cedar",
         "sample-data/sandbox_c/entities.json",
@@ -881,6 +960,62 @@ fn test_link_samples() {
         "Photo::\"VacationPhoto94.jpg\"",
         CedarExitCode::Success,
     );
+
+    // a link id can only be used once
+    run_link_test(
+        "sample-data/sandbox_c/policies.cedar",
+        &linked_file_name,
+        "AccessVacation",
+        "BobAccess",
+        [(SlotId::principal(), "User::\"bob\"".to_string())]
+            .into_iter()
+            .collect(),
+        CedarExitCode::Failure,
+    );
+
+    let cmd = LinkListArgs {
+        template_linked_file: linked_file_name.clone(),
+        template_id: Some("AccessVacation".into()),
+    };
+    assert_eq!(link_list(&cmd), CedarExitCode::Success);
+
+    let export_cmd = assert_cmd::Command::cargo_bin("cedar")
+        .unwrap()
+        .args([
+            "link",
+            "export",
+            "-p",
+            "sample-data/sandbox_c/policies.cedar",
+        ])
+        .args(["-k", &linked_file_name])
+        .assert()
+        .success();
+    let exported =
+        PolicySet::from_str(std::str::from_utf8(&export_cmd.get_output().stdout).unwrap()).unwrap();
+    assert_eq!(exported.templates().count(), 0);
+    let mut ids: Vec<_> = exported
+        .policies()
+        .filter_map(|p| p.annotation("id"))
+        .collect();
+    ids.sort_unstable();
+    assert_eq!(ids, vec!["AliceAccess", "BobAccess"]);
+
+    let cmd = LinkRemoveArgs {
+        template_linked_file: linked_file_name.clone(),
+        link_id: "BobAccess".into(),
+    };
+    assert_eq!(link_remove(&cmd), CedarExitCode::Success);
+    assert_eq!(link_remove(&cmd), CedarExitCode::Failure);
+
+    run_authorize_test_with_linked_policies(
+        "sample-data/sandbox_c/policies.cedar",
+        "sample-data/sandbox_c/entities.json",
+        Some(&linked_file_name),
+        "User::\"bob\"",
+        "Action::\"view\"",
+        "Photo::\"VacationPhoto94.jpg\"",
+        CedarExitCode::AuthorizeDeny,
+    );
 }
 
 #[test]
@@ -889,3 +1024,1138 @@ fn test_format_samples() {
     let ps_files = glob("sample-data/**/polic*.cedar").unwrap();
     ps_files.for_each(|ps_file| run_format_test(ps_file.unwrap().to_str().unwrap()));
 }
+
+#[test]
+fn test_format_check_diff() {
+    let unformatted = "permit(principal,action,resource);\n\nforbid(principal,action,resource);\n";
+    let check = |args: &[&str]| {
+        assert_cmd::Command::cargo_bin("cedar")
+            .expect("bin exists")
+            .arg("format")
+            .arg("--check")
+            .args(args)
+            .write_stdin(unformatted)
+            .assert()
+            .code(5)
+    };
+    check(&[]).stdout("<stdin> is not formatted\n");
+    let diff = check(&["--diff"]);
+    let stdout = std::str::from_utf8(&diff.get_output().stdout).unwrap();
+    assert!(
+        stdout.starts_with("--- <stdin>\n+++ <stdin> (formatted)\n@@ -1,3 +1,3 @@\n"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("\n-permit(principal,action,resource);\n"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("\n-forbid(principal,action,resource);\n"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("\n \n"), "{stdout}");
+
+    // policies which don't parse are reported as errors
+    assert_cmd::Command::cargo_bin("cedar")
+        .expect("bin exists")
+        .arg("format")
+        .arg("--check")
+        .write_stdin("permit(")
+        .assert()
+        .code(1);
+}
+
+fn run_analyze_test(policies_path: &str, schema_file: Option<&str>, exit_code: CedarExitCode) {
+    let cmd = AnalyzeArgs {
+        policies_path: Some(policies_path.into()),
+        schema_file: schema_file.map(Into::into),
+        template_linked_file: None,
+    };
+    let output = analyze(&cmd);
+    assert_eq!(exit_code, output, "{:#?}", cmd);
+}
+
+#[test]
+fn test_analyze_samples() {
+    run_analyze_test(
+        "sample-data/doesnotexist.cedar",
+        None,
+        CedarExitCode::Failure,
+    );
+    run_analyze_test(
+        "sample-data/sandbox_a/policies_2.cedar",
+        Some("sample-data/sandbox_a/schema.cedarschema.json"),
+        CedarExitCode::Success,
+    );
+    run_analyze_test(
+        "sample-data/analyze/findings.cedar",
+        None,
+        CedarExitCode::AnalysisFindings,
+    );
+    run_analyze_test(
+        "sample-data/analyze/findings.cedar",
+        Some("sample-data/sandbox_a/schema.cedarschema.json"),
+        CedarExitCode::AnalysisFindings,
+    );
+}
+
+#[test]
+fn test_repl_samples() {
+    use cedar_policy::{Entities, PolicySet};
+    use cedar_policy_cli::repl::Repl;
+    use std::str::FromStr;
+
+    let policies = PolicySet::from_str(
+        &std::fs::read_to_string("sample-data/sandbox_a/policies_1.cedar").unwrap(),
+    )
+    .unwrap();
+    let entities = Entities::from_json_file(
+        std::fs::File::open("sample-data/sandbox_a/entities.json").unwrap(),
+        None,
+    )
+    .unwrap();
+    let mut repl = Repl::new(policies, None, entities, vec![]);
+    let input = r#":principal User::"alice"
+:action Action::"view"
+:resource Photo::"VacationPhoto94.jpg"
+principal in UserGroup::"jane_friends"
+:authorize
+{"principal": "User::\"bob\"", "action": "Action::\"view\"", "resource": "Photo::\"VacationPhoto94.jpg\"", "context": {}}
+:quit
+1 + 1
+"#;
+    let mut output = Vec::new();
+    repl.run(input.as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("true"), "{output}");
+    assert!(output.contains("ALLOW"), "{output}");
+    assert!(output.contains("DENY"), "{output}");
+    // Nothing after `:quit` is evaluated
+    assert!(!output.contains('2'), "{output}");
+    assert_eq!(repl.history().len(), 7);
+}
+
+fn run_translate_schema_test(
+    schema_file: &str,
+    from: SchemaFormat,
+    to: SchemaFormat,
+    exit_code: CedarExitCode,
+) {
+    let cmd = TranslateSchemaArgs {
+        schema_file: Some(schema_file.into()),
+        from,
+        to,
+    };
+    let output = translate_schema(&cmd);
+    assert_eq!(exit_code, output, "{:#?}", cmd);
+}
+
+#[test]
+fn test_translate_schema_samples() {
+    use cedar_policy_cli::translate_schema::{cedar_to_json, json_to_cedar};
+
+    run_translate_schema_test(
+        "sample-data/sandbox_a/schema.cedarschema.json",
+        SchemaFormat::Json,
+        SchemaFormat::Cedar,
+        CedarExitCode::Success,
+    );
+    run_translate_schema_test(
+        "sample-data/sandbox_a/schema.cedarschema",
+        SchemaFormat::Cedar,
+        SchemaFormat::Json,
+        CedarExitCode::Success,
+    );
+    // Not in the Cedar schema format
+    run_translate_schema_test(
+        "sample-data/sandbox_a/schema.cedarschema.json",
+        SchemaFormat::Cedar,
+        SchemaFormat::Json,
+        CedarExitCode::Failure,
+    );
+
+    let json: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string("sample-data/sandbox_a/schema.cedarschema.json").unwrap(),
+    )
+    .unwrap();
+    let (cedar, warnings) = json_to_cedar(&json).unwrap();
+    assert!(warnings.is_empty());
+    assert_eq!(
+        cedar,
+        std::fs::read_to_string("sample-data/sandbox_a/schema.cedarschema")
+            .unwrap()
+            .trim_end()
+    );
+
+    let src = r#"namespace PhotoApp {
+  type Address = {
+    street: String,
+    zip?: String,
+  };
+  entity Album;
+  entity Photo in [Album] {
+    "file name": String,
+    tags: Set<String>,
+  };
+  entity User {
+    address: Address,
+    manager?: User,
+  };
+  action "read";
+  action "view" in ["read"] appliesTo {
+    principal: [User],
+    resource: [Photo, Album],
+    context: {
+      ip: ipaddr,
+    }
+  };
+}"#;
+    let (json, warnings) = cedar_to_json("<test>", src).unwrap();
+    assert!(warnings.is_empty());
+    assert_eq!(
+        json["PhotoApp"]["entityTypes"]["User"]["shape"]["attributes"]["manager"],
+        serde_json::json!({ "type": "Entity", "name": "User", "required": false })
+    );
+    assert_eq!(json_to_cedar(&json).unwrap().0, src);
+}
+
+#[test]
+fn test_translate_schema_annotations() {
+    use cedar_policy_cli::translate_schema::{cedar_to_json, json_to_cedar};
+
+    let src = r#"@owner("identity")
+@ui("hidden")
+entity User {
+  @sensitivity("pii")
+  email: String,
+  name: String,
+};
+@label("View a user")
+action "view" appliesTo {
+  principal: [User],
+  resource: [User]
+};"#;
+    let (json, warnings) = cedar_to_json("<test>", src).unwrap();
+    assert!(warnings.is_empty());
+    assert_eq!(
+        json[""]["entityTypes"]["User"]["annotations"],
+        serde_json::json!({ "owner": "identity", "ui": "hidden" })
+    );
+    assert_eq!(
+        json[""]["entityTypes"]["User"]["shape"]["attributes"]["email"]["annotations"],
+        serde_json::json!({ "sensitivity": "pii" })
+    );
+    assert_eq!(
+        json[""]["actions"]["view"]["annotations"],
+        serde_json::json!({ "label": "View a user" })
+    );
+    assert_eq!(json_to_cedar(&json).unwrap().0, src);
+
+    assert!(cedar_to_json("<test>", r#"@a("1") @a("2") entity User;"#).is_err());
+    assert!(cedar_to_json("<test>", r#"@a("1") type T = String;"#).is_err());
+    assert!(cedar_to_json("<test>", r#"@a(1) entity User;"#).is_err());
+}
+
+#[test]
+fn test_translate_schema_includes() {
+    use cedar_policy_cli::translate_schema::{cedar_to_json, json_to_cedar};
+
+    // `Common::User` is declared in the included file, so the schema isn't
+    // checked on its own
+    let src = r#"include "common.cedarschema";
+namespace PhotoApp {
+  entity Photo {
+    owner: Common::User,
+  };
+}"#;
+    let (json, warnings) = cedar_to_json("<test>", src).unwrap();
+    assert!(warnings.is_empty());
+    assert_eq!(json["$include"], serde_json::json!(["common.cedarschema"]));
+    assert_eq!(json_to_cedar(&json).unwrap().0, src);
+
+    assert!(cedar_to_json("<test>", "include common;").is_err());
+    assert!(cedar_to_json("<test>", r#"namespace A { include "b.json"; }"#).is_err());
+}
+
+fn run_partially_authorize_test(
+    principal: Option<&str>,
+    resource: Option<&str>,
+    exit_code: CedarExitCode,
+) {
+    let cmd = PartiallyAuthorizeArgs {
+        principal: principal.map(Into::into),
+        action: Some("Action::\"view\"".into()),
+        resource: resource.map(Into::into),
+        context_json_file: None,
+        policies: PoliciesArgs {
+            policies_file: Some("sample-data/sandbox_a/policies_1.cedar".into()),
+            policy_format: PolicyFormat::Human,
+        },
+        template_linked_file: None,
+        schema_file: None,
+        entities_file: Some("sample-data/sandbox_a/entities.json".into()),
+        residual_format: PolicyFormat::Human,
+    };
+    let output = partially_authorize(&cmd);
+    assert_eq!(exit_code, output, "{:#?}", cmd);
+}
+
+#[test]
+#[cfg(feature = "partial-eval")]
+fn test_partially_authorize_samples() {
+    // The resource is unknown, so both policies are residuals
+    run_partially_authorize_test(Some("User::\"alice\""), None, CedarExitCode::Success);
+    run_partially_authorize_test(
+        Some("User::\"alice\""),
+        Some("Photo::\"VacationPhoto94.jpg\""),
+        CedarExitCode::Success,
+    );
+    run_partially_authorize_test(
+        Some("User::\"tim\""),
+        Some("Photo::\"VacationPhoto94.jpg\""),
+        CedarExitCode::AuthorizeDeny,
+    );
+}
+
+#[test]
+#[cfg(not(feature = "partial-eval"))]
+fn test_partially_authorize_requires_feature() {
+    run_partially_authorize_test(Some("User::\"alice\""), None, CedarExitCode::Failure);
+}
+
+#[test]
+fn test_diff_policies_samples() {
+    use cedar_policy::{PolicyId, PolicySet};
+    use cedar_policy_cli::diff::{self, ChangeDetail, ItemKind, PolicyChange};
+    use std::str::FromStr;
+
+    let cmd = DiffPoliciesArgs {
+        old_policies: "sample-data/diff/old".into(),
+        new_policies: "sample-data/diff/new".into(),
+        old_template_linked_file: None,
+        new_template_linked_file: None,
+        schema_file: None,
+    };
+    assert_eq!(diff_policies(&cmd), CedarExitCode::Success, "{:#?}", cmd);
+    let cmd = DiffPoliciesArgs {
+        old_policies: "sample-data/doesnotexist".into(),
+        ..cmd
+    };
+    assert_eq!(diff_policies(&cmd), CedarExitCode::Failure, "{:#?}", cmd);
+
+    let load = |file: &str| {
+        let pset = PolicySet::from_str(&std::fs::read_to_string(file).unwrap()).unwrap();
+        let mut renamed = PolicySet::new();
+        for p in pset.policies() {
+            let id = PolicyId::from_str(p.annotation("id").unwrap()).unwrap();
+            renamed.add(p.new_id(id)).unwrap();
+        }
+        renamed
+    };
+    let old = load("sample-data/diff/old/photos.cedar");
+    let new = load("sample-data/diff/new/photos.cedar");
+    let id = |s: &str| PolicyId::from_str(s).unwrap();
+    let changes = diff::diff_policies(&old, &new, None);
+    assert_eq!(
+        changes,
+        vec![
+            PolicyChange::Changed {
+                id: id("alice's view policy"),
+                kind: ItemKind::Policy,
+                details: vec![ChangeDetail::ScopeWidened],
+            },
+            PolicyChange::Removed {
+                id: id("bob's view policy"),
+                kind: ItemKind::Policy,
+            },
+            PolicyChange::Changed {
+                id: id("disallow tim policy"),
+                kind: ItemKind::Policy,
+                details: vec![ChangeDetail::Conditions],
+            },
+            PolicyChange::Added {
+                id: id("carol's view policy"),
+                kind: ItemKind::Policy,
+            },
+        ]
+    );
+    let may_grant: Vec<bool> = changes
+        .iter()
+        .map(|c| c.may_grant_access(&old, &new))
+        .collect();
+    assert_eq!(may_grant, vec![true, false, true, true]);
+}
+
+fn run_slice_entities_test(policies_file: &str, action: &str, exit_code: CedarExitCode) {
+    let cmd = SliceEntitiesArgs {
+        policies: PoliciesArgs {
+            policies_file: Some(policies_file.into()),
+            policy_format: PolicyFormat::Human,
+        },
+        template_linked_file: None,
+        schema_file: "sample-data/sandbox_b/schema.cedarschema.json".into(),
+        action: action.into(),
+    };
+    let output = slice_entities(&cmd);
+    assert_eq!(exit_code, output, "{:#?}", cmd);
+}
+
+#[test]
+fn test_slice_entities_samples() {
+    use cedar_policy::{EntityUid, PolicySet};
+    use cedar_policy_cli::slice;
+    use std::str::FromStr;
+
+    run_slice_entities_test(
+        "sample-data/sandbox_b/policies_4.cedar",
+        "Action::\"view\"",
+        CedarExitCode::Success,
+    );
+    run_slice_entities_test(
+        "sample-data/sandbox_b/policies_4.cedar",
+        "Action::\"undeclared\"",
+        CedarExitCode::Failure,
+    );
+
+    let schema: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string("sample-data/sandbox_b/schema.cedarschema.json").unwrap(),
+    )
+    .unwrap();
+    let slice = |file: &str, action: &str| {
+        let policies = PolicySet::from_str(&std::fs::read_to_string(file).unwrap()).unwrap();
+        let action = EntityUid::from_str(action).unwrap();
+        slice::slice_entities(&policies, &schema, &action)
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(
+        slice("sample-data/sandbox_b/policies_4.cedar", "Action::\"view\""),
+        "Photo\n  ancestors: Album\nUser\n  attributes: department, jobLevel"
+    );
+    assert_eq!(
+        slice("sample-data/sandbox_b/policies_6.cedar", "Action::\"view\""),
+        "Album\n  ancestors: Account\nPhoto\n  ancestors: Account\nUser\n  ancestors: UserGroup"
+    );
+    // the prototypes policy doesn't apply to `edit`
+    assert_eq!(
+        slice("sample-data/sandbox_b/policies_4.cedar", "Action::\"edit\""),
+        "no entity data is needed"
+    );
+}
+
+#[test]
+fn test_new_policy_samples() {
+    use cedar_policy::{Effect, EntityUid, PolicySet};
+    use cedar_policy_cli::scaffold;
+    use std::str::FromStr;
+
+    let run = |action: &str, resource_type: Option<&str>, expected: CedarExitCode| {
+        let cmd = NewPolicyArgs {
+            schema_file: "sample-data/sandbox_b/schema.cedarschema.json".into(),
+            action: action.into(),
+            principal_type: None,
+            resource_type: resource_type.map(Into::into),
+            forbid: false,
+        };
+        assert_eq!(new_policy(&cmd), expected, "{cmd:#?}");
+    };
+    run("Action::\"view\"", None, CedarExitCode::Success);
+    run("Action::\"view\"", Some("Photo"), CedarExitCode::Success);
+    run("Action::\"view\"", Some("User"), CedarExitCode::Failure);
+    run("Action::\"undeclared\"", None, CedarExitCode::Failure);
+
+    let schema: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string("sample-data/sandbox_b/schema.cedarschema.json").unwrap(),
+    )
+    .unwrap();
+    let view = EntityUid::from_str("Action::\"view\"").unwrap();
+    let policy = scaffold::new_policy(&schema, &view, None, None, Effect::Permit).unwrap();
+    assert_eq!(
+        policy,
+        r#"// resource may be any of: Album, Photo
+permit (
+  principal is User,
+  action == Action::"view",
+  resource
+)
+when {
+  // context attributes:
+  //   context.source_ip: ipaddr
+  true
+};
+"#
+    );
+    PolicySet::from_str(&policy).unwrap();
+
+    let policy =
+        scaffold::new_policy(&schema, &view, Some("User"), Some("Photo"), Effect::Forbid).unwrap();
+    assert!(policy.starts_with(
+        "forbid (\n  principal is User,\n  action == Action::\"view\",\n  resource is Photo\n)"
+    ));
+    PolicySet::from_str(&policy).unwrap();
+}
+
+#[test]
+fn test_bench_samples() {
+    use cedar_policy_cli::bench::Stats;
+    use std::time::Duration;
+
+    let run = |policies_file: &str,
+               schema_file: Option<&str>,
+               entities_file: &str,
+               requests_file: &str,
+               expected: CedarExitCode| {
+        let cmd = BenchArgs {
+            policies: PoliciesArgs {
+                policies_file: Some(policies_file.into()),
+                policy_format: PolicyFormat::Human,
+            },
+            template_linked_file: None,
+            schema_file: schema_file.map(Into::into),
+            entities_file: entities_file.into(),
+            requests_file: requests_file.into(),
+            iterations: 3,
+        };
+        assert_eq!(bench(&cmd), expected, "{cmd:#?}");
+    };
+    run(
+        "sample-data/sandbox_a/policies_1.cedar",
+        None,
+        "sample-data/sandbox_a/entities.json",
+        "sample-data/sandbox_a/requests.ndjson",
+        CedarExitCode::Success,
+    );
+    // the entities of sandbox_a don't conform to its schema, so this uses a
+    // sandbox whose entities do
+    run(
+        "sample-data/tiny_sandboxes/sample2/policy.cedar",
+        Some("sample-data/tiny_sandboxes/sample2/schema.cedarschema.json"),
+        "sample-data/tiny_sandboxes/sample2/entity.json",
+        "sample-data/tiny_sandboxes/sample2/requests.ndjson",
+        CedarExitCode::Success,
+    );
+    run(
+        "sample-data/sandbox_a/policies_1.cedar",
+        None,
+        "sample-data/sandbox_a/entities.json",
+        "sample-data/sandbox_a/requests_bad.ndjson",
+        CedarExitCode::Failure,
+    );
+
+    let samples: Vec<Duration> = (1..=100).rev().map(Duration::from_micros).collect();
+    let stats = Stats::from_samples(&samples).unwrap();
+    assert_eq!(stats.count, 100);
+    assert_eq!(
+        (stats.min, stats.p50, stats.p90, stats.p99, stats.max),
+        (1.0, 50.0, 90.0, 99.0, 100.0)
+    );
+    assert_eq!(stats.mean, 50.5);
+    assert_eq!(Stats::from_samples(&[]), None);
+}
+
+#[test]
+fn test_generate_requests_samples() {
+    use cedar_policy::{Context, Entities, EntityUid, Request, Schema};
+    use cedar_policy_cli::generate;
+    use std::str::FromStr;
+
+    for schema_file in [
+        "sample-data/sandbox_a/schema.cedarschema.json",
+        "sample-data/sandbox_b/schema.cedarschema.json",
+    ] {
+        let schema_json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(schema_file).unwrap()).unwrap();
+        let schema = Schema::from_json_value(schema_json.clone()).unwrap();
+        let generated = generate::generate(&schema_json, 50, 4, 42).unwrap();
+        assert_eq!(
+            generated,
+            generate::generate(&schema_json, 50, 4, 42).unwrap()
+        );
+        assert_eq!(generated.requests.len(), 50);
+
+        // everything conforms to the schema
+        let entities = Entities::from_json_value(generated.entities, Some(&schema)).unwrap();
+        for request in generated.requests {
+            let uid = |key: &str| EntityUid::from_str(request[key].as_str().unwrap()).unwrap();
+            let (principal, action, resource) = (uid("principal"), uid("action"), uid("resource"));
+            assert!(entities.get(&principal).is_some(), "{request}");
+            assert!(entities.get(&resource).is_some(), "{request}");
+            let context =
+                Context::from_json_value(request["context"].clone(), Some((&schema, &action)))
+                    .unwrap();
+            Request::new(
+                Some(principal),
+                Some(action),
+                Some(resource),
+                context,
+                Some(&schema),
+            )
+            .unwrap();
+        }
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let entities_file = dir.path().join("entities.json");
+    let cmd = GenerateRequestsArgs {
+        schema_file: "sample-data/sandbox_b/schema.cedarschema.json".into(),
+        count: 3,
+        seed: Some(7),
+        entities_file: Some(entities_file.to_str().unwrap().into()),
+        entities_per_type: 2,
+    };
+    assert_eq!(generate_requests(&cmd), CedarExitCode::Success);
+    let entities: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&entities_file).unwrap()).unwrap();
+    assert!(entities.as_array().is_some_and(|e| !e.is_empty()));
+}
+
+// PANIC SAFETY: this is all test code
+#[allow(clippy::expect_used)]
+fn run_json_output_test(args: &[&str], stdin: &str) -> serde_json::Value {
+    let cmd = assert_cmd::Command::cargo_bin("cedar")
+        .expect("bin exists")
+        .arg("--format")
+        .arg("json")
+        .args(args)
+        .write_stdin(stdin)
+        .assert();
+    serde_json::from_slice(&cmd.get_output().stdout).expect("output should be JSON")
+}
+
+#[test]
+fn test_json_output_samples() {
+    use serde_json::json;
+
+    let json = run_json_output_test(
+        &[
+            "authorize",
+            "--policies",
+            "sample-data/sandbox_a/policies_1.cedar",
+            "--entities",
+            "sample-data/sandbox_a/entities.json",
+            "--principal",
+            "User::\"alice\"",
+            "--action",
+            "Action::\"view\"",
+            "--resource",
+            "Photo::\"VacationPhoto94.jpg\"",
+        ],
+        "",
+    );
+    assert_eq!(
+        json,
+        json!({
+            "decision": "Allow",
+            "reasons": ["jane's friends view-permission policy"],
+            "errors": [],
+        })
+    );
+
+    let json = run_json_output_test(
+        &[
+            "validate",
+            "--policies",
+            "sample-data/sandbox_a/policies_1_bad.cedar",
+            "--schema",
+            "sample-data/sandbox_a/schema.cedarschema.json",
+        ],
+        "",
+    );
+    assert_eq!(json["validationPassed"], json!(false));
+    // the misspelled `UsrGroup` is unrecognized, so `view` doesn't apply to it
+    assert_eq!(
+        json["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|err| err["code"].as_str().unwrap())
+            .collect::<Vec<_>>(),
+        [
+            "cedar::validation::unrecognized_entity_type",
+            "cedar::validation::invalid_action_application"
+        ]
+    );
+
+    // errors are reported as JSON, with their spans
+    let json = run_json_output_test(
+        &["check-parse"],
+        "permit(principal, action, resource) when { 1 + };",
+    );
+    assert!(json["message"].is_string(), "{json}");
+    assert!(json["labels"].is_array(), "{json}");
+
+    let json = run_json_output_test(&["evaluate", "[1, User::\"alice\"]"], "");
+    assert_eq!(
+        json,
+        json!({ "result": [1, { "__entity": { "type": "User", "id": "alice" } }] })
+    );
+}
+
+#[test]
+fn test_evaluate_expression_errors() {
+    use serde_json::json;
+
+    let json = run_json_output_test(
+        &[
+            "evaluate",
+            "--request",
+            "sample-data/tiny_sandboxes/sample1/request.json",
+            "--entities",
+            "sample-data/tiny_sandboxes/sample1/entity.json",
+            "principal",
+        ],
+        "",
+    );
+    assert_eq!(
+        json,
+        json!({ "result": { "__entity": { "type": "User", "id": "alice" } } })
+    );
+
+    // evaluation errors point at the subexpression which failed
+    let json = run_json_output_test(&["evaluate", "if true then 1 + \"a\" else 0"], "");
+    assert!(json["message"].is_string(), "{json}");
+    assert_eq!(
+        json["labels"][0]["span"],
+        json!({ "offset": 13, "length": 7 }),
+        "{json}"
+    );
+}
+
+#[test]
+fn test_watcher() {
+    use cedar_policy_cli::watch::Watcher;
+    use std::time::{Duration, SystemTime};
+
+    let dir = tempfile::tempdir().unwrap();
+    let policies = dir.path().join("policies.cedar");
+    std::fs::write(&policies, "permit(principal, action, resource);").unwrap();
+    let mut watcher = Watcher::new([dir.path()]);
+    assert_eq!(watcher.changed(), Vec::<std::path::PathBuf>::new());
+
+    // set the modification time explicitly, since the file system's clock may
+    // be too coarse to notice an immediate write
+    std::fs::File::options()
+        .write(true)
+        .open(&policies)
+        .unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(10))
+        .unwrap();
+    let added = dir.path().join("more.cedar");
+    std::fs::write(&added, "forbid(principal, action, resource);").unwrap();
+    assert_eq!(watcher.changed(), vec![added.clone(), policies.clone()]);
+    assert_eq!(watcher.changed(), Vec::<std::path::PathBuf>::new());
+
+    std::fs::remove_file(&added).unwrap();
+    assert_eq!(watcher.changed(), vec![added]);
+}
+
+#[test]
+fn test_visualize_samples() {
+    use cedar_policy::PolicySet;
+    use cedar_policy_cli::visualize;
+    use std::str::FromStr;
+
+    let cmd = VisualizeEntitiesArgs {
+        entities_file: "sample-data/sandbox_a/entities.json".into(),
+    };
+    assert_eq!(visualize_entities(&cmd), CedarExitCode::Success);
+    let cmd = VisualizeEntitiesArgs {
+        entities_file: "sample-data/sandbox_a/policies_1.cedar".into(),
+    };
+    assert_eq!(visualize_entities(&cmd), CedarExitCode::Failure);
+    let cmd = VisualizePoliciesArgs {
+        policies: PoliciesArgs {
+            policies_file: Some("sample-data/sandbox_b/policies_4.cedar".into()),
+            policy_format: PolicyFormat::Human,
+        },
+        template_linked_file: None,
+        schema_file: "sample-data/sandbox_b/schema.cedarschema.json".into(),
+    };
+    assert_eq!(visualize_policies(&cmd), CedarExitCode::Success);
+
+    let entities: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string("sample-data/sandbox_a/entities.json").unwrap(),
+    )
+    .unwrap();
+    let dot = visualize::entities_dot(&entities).unwrap();
+    assert!(dot.starts_with("digraph entities {"), "{dot}");
+    assert!(
+        dot.contains(r#"  "User::\"alice\"" -> "UserGroup::\"jane_friends\"";"#),
+        "{dot}"
+    );
+    assert!(!dot.contains(r#""User::\"bob\"" ->"#), "{dot}");
+
+    let schema: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string("sample-data/sandbox_b/schema.cedarschema.json").unwrap(),
+    )
+    .unwrap();
+    let policies = PolicySet::from_str(
+        &std::fs::read_to_string("sample-data/sandbox_b/policies_4.cedar").unwrap(),
+    )
+    .unwrap();
+    let dot = visualize::policies_dot(&policies, &schema).unwrap();
+    assert!(dot.starts_with("digraph policies {"), "{dot}");
+    assert!(
+        dot.contains(r#"  "principal:User" -> "policy:policy0";"#),
+        "{dot}"
+    );
+    assert!(
+        dot.contains(r#"  "policy:policy0" -> "resource:Photo";"#),
+        "{dot}"
+    );
+    assert!(
+        dot.contains(
+            r#""policy:policy0" [shape=ellipse, color=darkgreen, label="policy0\npermit\nview"];"#
+        ),
+        "{dot}"
+    );
+}
+
+#[test]
+fn test_policy_dir_samples() {
+    use cedar_policy::PolicyId;
+    use cedar_policy_cli::analyze::LoadedPolicies;
+    use cedar_policy_cli::loader::read_policy_sources;
+    use std::str::FromStr;
+
+    run_check_parse_test("sample-data/policy_dir", CedarExitCode::Success);
+    run_check_parse_test("sample-data/policy_dir_bad", CedarExitCode::Failure);
+    run_validate_test(
+        "sample-data/policy_dir",
+        "sample-data/sandbox_a/schema.cedarschema.json",
+        CedarExitCode::Success,
+    );
+    run_authorize_test(
+        "sample-data/policy_dir",
+        "sample-data/sandbox_a/entities.json",
+        "User::\"alice\"",
+        "Action::\"edit\"",
+        "Photo::\"VacationPhoto94.jpg\"",
+        CedarExitCode::Success,
+    );
+    run_authorize_test(
+        "sample-data/policy_dir",
+        "sample-data/sandbox_a/entities.json",
+        "User::\"tim\"",
+        "Action::\"edit\"",
+        "Photo::\"VacationPhoto94.jpg\"",
+        CedarExitCode::AuthorizeDeny,
+    );
+
+    let loaded =
+        LoadedPolicies::new(read_policy_sources(Some("sample-data/policy_dir")).unwrap()).unwrap();
+    let mut ids: Vec<&str> = loaded
+        .policy_set
+        .policies()
+        .map(|p| p.id().as_ref())
+        .collect();
+    ids.sort_unstable();
+    assert_eq!(
+        ids,
+        ["admins/edit.cedar:policy1", "alice's edit policy", "view"]
+    );
+    assert!(loaded
+        .policy_set
+        .policy(&PolicyId::from_str("view").unwrap())
+        .is_some());
+
+    let err = LoadedPolicies::new(read_policy_sources(Some("sample-data/policy_dir_bad")).unwrap())
+        .unwrap_err();
+    assert_eq!(err.to_string(), "failed to parse policy set broken.cedar");
+}
+
+#[test]
+fn test_infer_schema_samples() {
+    use cedar_policy::{Entities, Schema};
+    use cedar_policy_cli::infer;
+    use serde_json::json;
+
+    let run = |entities_file: &str, format: SchemaFormat, expected: CedarExitCode| {
+        let cmd = InferSchemaArgs {
+            entities_file: entities_file.into(),
+            format,
+        };
+        assert_eq!(infer_schema(&cmd), expected, "{cmd:#?}");
+    };
+    run(
+        "sample-data/sandbox_b/entities.json",
+        SchemaFormat::Json,
+        CedarExitCode::Success,
+    );
+    run(
+        "sample-data/sandbox_b/entities.json",
+        SchemaFormat::Cedar,
+        CedarExitCode::Success,
+    );
+    run(
+        "sample-data/sandbox_b/policies_4.cedar",
+        SchemaFormat::Json,
+        CedarExitCode::Failure,
+    );
+
+    // the entities conform to the schema inferred from them
+    let entities: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string("sample-data/sandbox_a/entities.json").unwrap(),
+    )
+    .unwrap();
+    let inferred = infer::infer_schema(&entities).unwrap();
+    let schema = Schema::from_json_value(inferred.schema).unwrap();
+    Entities::from_json_value(entities, Some(&schema)).unwrap();
+
+    let entities = json!([
+        {
+            "uid": { "type": "App::User", "id": "alice" },
+            "attrs": {
+                "age": 30,
+                "tags": [],
+                "manager": { "__entity": { "type": "App::User", "id": "bob" } }
+            },
+            "parents": [{ "type": "App::Team", "id": "admins" }]
+        },
+        {
+            "uid": { "type": "App::User", "id": "bob" },
+            "attrs": { "age": "unknown", "tags": [] },
+            "parents": []
+        }
+    ]);
+    let inferred = infer::infer_schema(&entities).unwrap();
+    assert_eq!(
+        inferred.schema,
+        json!({
+            "App": {
+                "entityTypes": {
+                    "Team": {},
+                    "User": {
+                        "memberOfTypes": ["Team"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "age": { "type": "Long" },
+                                "manager": { "type": "Entity", "name": "User", "required": false },
+                                "tags": { "type": "Set", "element": { "type": "String" } }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        })
+    );
+    let ambiguities: Vec<String> = inferred
+        .ambiguities
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        ambiguities,
+        [
+            "App::User.age: found values of both Long and String; assuming Long",
+            "App::Team: no entity of this type is in the data, so its attributes are unknown",
+            "App::User.tags: every set is empty, so the element type is unknown; assuming String",
+        ]
+    );
+}
+
+#[test]
+fn test_validate_entities_samples() {
+    use cedar_policy_cli::conformance::{self, Violation};
+    use serde_json::json;
+
+    let run = |schema_file: &str, entities_file: &str, expected: CedarExitCode| {
+        let cmd = ValidateEntitiesArgs {
+            schema_file: schema_file.into(),
+            entities_file: entities_file.into(),
+        };
+        assert_eq!(validate_entities(&cmd), expected, "{cmd:#?}");
+    };
+    run(
+        "sample-data/tiny_sandboxes/sample2/schema.cedarschema.json",
+        "sample-data/tiny_sandboxes/sample2/entity.json",
+        CedarExitCode::Success,
+    );
+    run(
+        "sample-data/sandbox_b/schema.cedarschema.json",
+        "sample-data/sandbox_b/entities.json",
+        CedarExitCode::ValidationFailure,
+    );
+    run(
+        "sample-data/sandbox_b/schema.cedarschema.json",
+        "sample-data/doesnotexist.json",
+        CedarExitCode::Failure,
+    );
+
+    let schema: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string("sample-data/sandbox_b/schema.cedarschema.json").unwrap(),
+    )
+    .unwrap();
+    let entities = json!([
+        {
+            "uid": { "type": "User", "id": "alice" },
+            "attrs": { "department": 5 },
+            "parents": [{ "type": "Album", "id": "vacation" }]
+        },
+        {
+            "uid": { "type": "Photo", "id": "selfie.jpg" },
+            "attrs": {
+                "private": false,
+                "account": { "__entity": { "type": "User", "id": "alice" } },
+                "admins": [],
+                "extra": 1
+            },
+            "parents": []
+        },
+        {
+            "uid": { "type": "Unknown", "id": "x" },
+            "attrs": {},
+            "parents": []
+        }
+    ]);
+    let violations = conformance::check_entities(&schema, &entities).unwrap();
+    assert!(violations.contains(&Violation {
+        uid: "User::\"alice\"".into(),
+        attribute: Some("jobLevel".into()),
+        message: "the required attribute is missing".into(),
+    }));
+    let mut messages: Vec<String> = violations.iter().map(ToString::to_string).collect();
+    messages.sort();
+    assert_eq!(
+        messages,
+        [
+            "entity Photo::\"selfie.jpg\", attribute `account`: expected an entity of type `Account`, found an entity of type `User`",
+            "entity Photo::\"selfie.jpg\", attribute `extra`: the attribute is not declared in the schema",
+            "entity Unknown::\"x\": entity type `Unknown` is not declared in the schema",
+            "entity User::\"alice\", attribute `department`: expected a String, found a Long",
+            "entity User::\"alice\", attribute `jobLevel`: the required attribute is missing",
+            "entity User::\"alice\": parent Album::\"vacation\" is not allowed, because entities of type `User` can't be members of `Album`",
+        ]
+    );
+}
+
+#[test]
+fn test_document_schema() {
+    use cedar_policy_cli::document_schema::{schema_html, schema_markdown};
+
+    let cmd = DocumentSchemaArgs {
+        schema_file: Some("sample-data/sandbox_b/schema.cedarschema.json".into()),
+        from: SchemaFormat::Json,
+        to: DocumentFormat::Markdown,
+    };
+    assert_eq!(document_schema(&cmd), CedarExitCode::Success);
+    let cmd = DocumentSchemaArgs {
+        schema_file: Some("sample-data/sandbox_b/policies_4.cedar".into()),
+        from: SchemaFormat::Json,
+        to: DocumentFormat::Html,
+    };
+    assert_eq!(document_schema(&cmd), CedarExitCode::Failure);
+
+    let schema = serde_json::json!({
+        "NS": {
+            "commonTypes": {
+                "Address": {
+                    "type": "Record",
+                    "attributes": { "city": { "type": "String", "required": false } }
+                }
+            },
+            "entityTypes": {
+                "User": {
+                    "memberOfTypes": ["Group"],
+                    "annotations": { "owner": "identity" },
+                    "shape": {
+                        "type": "Record",
+                        "attributes": {
+                            "address": { "type": "Address" },
+                            "age": { "type": "Long", "deprecated": "use `birthday`" },
+                            "tags": {
+                                "type": "Set",
+                                "element": { "type": "String" },
+                                "annotations": { "sensitivity": "pii" }
+                            }
+                        }
+                    }
+                },
+                "Group": { "deprecated": "use roles" },
+                "Photo": {}
+            },
+            "actions": {
+                "view": {
+                    "appliesTo": {
+                        "principalTypes": ["User"],
+                        "resourceTypes": ["Photo", "Group"],
+                        "context": {
+                            "type": "Record",
+                            "attributes": { "mfa": { "type": "Boolean" } }
+                        }
+                    },
+                    "memberOf": [{ "id": "read" }]
+                },
+                "edit": {
+                    "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Photo"] },
+                    "memberOf": [{ "id": "write" }]
+                },
+                "write": { "memberOf": [{ "id": "read" }] },
+                "read": {}
+            }
+        }
+    });
+
+    let markdown = schema_markdown(&schema).unwrap();
+    for expected in [
+        "# Schema\n\n## Namespace `NS`\n\n### Common types\n\n",
+        "| `NS::Address` | `{ city?: String }` |\n",
+        r#"#### `NS::User`
+
+Annotations: `@owner("identity")`
+
+Member of: `NS::Group`
+
+| Attribute | Type | Required | Notes |
+| --- | --- | --- | --- |
+| `address` | `Address` | yes |  |
+| `age` | `Long` | yes | Deprecated: use \`birthday\` |
+| `tags` | `Set<String>` | yes | `@sensitivity("pii")` |
+"#,
+        "#### `NS::Group`\n\nDeprecated: use roles\n\nNo attributes.\n",
+        r#"| Action | `NS::Group` | `NS::Photo` | `NS::User` | Context | Notes |
+| --- | --- | --- | --- | --- | --- |
+| `edit` |  | resource | principal |  |  |
+| `read` |  |  |  |  | unspecified principal, unspecified resource |
+| `view` | resource | resource | principal | `{ mfa: Bool }` |  |
+| `write` |  |  |  |  | unspecified principal, unspecified resource |
+"#,
+    ] {
+        assert!(
+            markdown.contains(expected),
+            "{expected}\n\nnot in\n\n{markdown}"
+        );
+    }
+    assert!(
+        markdown.ends_with(
+            r#"## Action groups
+
+- `NS::Action::"read"`
+  - `NS::Action::"view"`
+  - `NS::Action::"write"`
+    - `NS::Action::"edit"`
+"#
+        ),
+        "{markdown}"
+    );
+
+    let html = schema_html(&schema).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"), "{html}");
+    for expected in [
+        "<h4><code>NS::User</code></h4>",
+        "<td><code>{ mfa: Bool }</code></td>",
+        "<li><code>NS::Action::&quot;read&quot;</code>\n<ul>\n",
+    ] {
+        assert!(html.contains(expected), "{expected}\n\nnot in\n\n{html}");
+    }
+
+    let invalid = serde_json::json!({
+        "": {
+            "entityTypes": { "User": { "memberOfTypes": ["Group"] } },
+            "actions": {}
+        }
+    });
+    assert!(schema_markdown(&invalid).is_err());
+}
@@ -25,7 +25,8 @@ use miette::Diagnostic;
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, TryFromInto};
 use smol_str::SmolStr;
-use std::collections::{HashMap, HashSet};
+use std::collections::{hash_map, HashMap, HashSet};
+use std::sync::Arc;
 use thiserror::Error;
 
 /// We support two types of entities. The first is a nominal type (e.g., User, Action)
@@ -290,6 +291,15 @@ impl Entity {
         }
     }
 
+    /// The `Unknown` standing in for the attribute `attr` of the entity `uid`,
+    /// when that attribute is declared but its value is not yet known (e.g.,
+    /// via `unknownAttrs` in the entity JSON format).
+    ///
+    /// It is named `<uid>.<attr>`, e.g., `User::"alice".department`.
+    pub fn unknown_attr(uid: &EntityUID, attr: &str) -> Unknown {
+        Unknown::new_untyped(format!("{uid}.{attr}"))
+    }
+
     /// Get the UID of this entity
     pub fn uid(&self) -> EntityUID {
         self.uid.clone()
@@ -345,6 +355,48 @@ impl Entity {
         Ok(())
     }
 
+    /// Set the given attribute to `val`, or remove it if `val` is `None`,
+    /// returning its previous value
+    pub(crate) fn replace_attr(
+        &mut self,
+        attr: SmolStr,
+        val: Option<PartialValue>,
+    ) -> Option<PartialValue> {
+        match val {
+            Some(val) => self.attrs.insert(attr, val.into()),
+            None => self.attrs.remove(&attr),
+        }
+        .map(PartialValue::from)
+    }
+
+    /// Merge `other`, an entity with the same UID, into this one: its
+    /// ancestors are added to ours, and so are its attributes. An attribute
+    /// which both have with different values takes the value of `other` if
+    /// `overwrite`, and otherwise is returned as an error.
+    pub(crate) fn merge(&mut self, other: Entity, overwrite: bool) -> Result<(), SmolStr> {
+        for (attr, val) in other.attrs {
+            match self.attrs.entry(attr) {
+                hash_map::Entry::Occupied(mut o) if *o.get() != val => {
+                    if !overwrite {
+                        return Err(o.key().clone());
+                    }
+                    o.insert(val);
+                }
+                hash_map::Entry::Occupied(_) => (),
+                hash_map::Entry::Vacant(v) => {
+                    v.insert(val);
+                }
+            }
+        }
+        self.ancestors.extend(other.ancestors);
+        Ok(())
+    }
+
+    /// Replace the ancestors of this `Entity`
+    pub(crate) fn set_ancestors(&mut self, ancestors: HashSet<EntityUID>) {
+        self.ancestors = ancestors;
+    }
+
     /// Mark the given `UID` as an ancestor of this `Entity`.
     // When fuzzing, `add_ancestor()` is fully `pub`.
     #[cfg(not(fuzzing))]
@@ -390,6 +442,28 @@ impl TCNode<EntityUID> for Entity {
     }
 }
 
+// Entities are shared between clones of an `Entities`, so they are only copied
+// when an edge is actually added to them
+impl TCNode<EntityUID> for Arc<Entity> {
+    fn get_key(&self) -> EntityUID {
+        self.uid()
+    }
+
+    fn add_edge_to(&mut self, k: EntityUID) {
+        if !self.is_descendant_of(&k) {
+            Arc::make_mut(self).add_ancestor(k)
+        }
+    }
+
+    fn out_edges(&self) -> Box<dyn Iterator<Item = &EntityUID> + '_> {
+        Box::new(self.ancestors())
+    }
+
+    fn has_edge_to(&self, e: &EntityUID) -> bool {
+        self.is_descendant_of(e)
+    }
+}
+
 impl std::fmt::Display for Entity {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -451,6 +525,7 @@ impl std::fmt::Display for PartialValueSerializedAsExpr {
 /// `EvaluationError`.
 #[derive(Debug, Diagnostic, Error)]
 #[error("failed to evaluate attribute `{attr}` of `{uid}`: {err}")]
+#[diagnostic(code(cedar::entities::attr_evaluation))]
 pub struct EntityAttrEvaluationError {
     /// UID of the entity where the error was encountered
     pub uid: EntityUID,
@@ -257,6 +257,23 @@ impl<T> Expr<T> {
         expr_iterator::ExprIterator::new(self)
     }
 
+    /// The depth of this expression: 1 for an expression without
+    /// subexpressions, e.g., a literal, and otherwise 1 more than the depth of
+    /// its deepest subexpression
+    pub fn depth(&self) -> usize {
+        let mut depth = 0;
+        let mut stack = vec![(self, 1)];
+        while let Some((expr, d)) = stack.pop() {
+            depth = depth.max(d);
+            stack.extend(
+                expr_iterator::ExprIterator::children(expr)
+                    .into_iter()
+                    .map(|child| (child, d + 1)),
+            );
+        }
+        depth
+    }
+
     /// Iterate over all of the slots in this policy AST
     pub fn slots(&self) -> impl Iterator<Item = &SlotId> {
         self.subexpressions()
@@ -340,22 +357,22 @@ impl Expr {
         ExprBuilder::new().or(e1, e2)
     }
 
-    /// Create a '<' expression. Arguments must evaluate to Long type
+    /// Create a '<' expression. Arguments must evaluate to two Longs or two Strings
     pub fn less(e1: Expr, e2: Expr) -> Self {
         ExprBuilder::new().less(e1, e2)
     }
 
-    /// Create a '<=' expression. Arguments must evaluate to Long type
+    /// Create a '<=' expression. Arguments must evaluate to two Longs or two Strings
     pub fn lesseq(e1: Expr, e2: Expr) -> Self {
         ExprBuilder::new().lesseq(e1, e2)
     }
 
-    /// Create a '>' expression. Arguments must evaluate to Long type
+    /// Create a '>' expression. Arguments must evaluate to two Longs or two Strings
     pub fn greater(e1: Expr, e2: Expr) -> Self {
         ExprBuilder::new().greater(e1, e2)
     }
 
-    /// Create a '>=' expression. Arguments must evaluate to Long type
+    /// Create a '>=' expression. Arguments must evaluate to two Longs or two Strings
     pub fn greatereq(e1: Expr, e2: Expr) -> Self {
         ExprBuilder::new().greatereq(e1, e2)
     }
@@ -812,7 +829,7 @@ impl<T> ExprBuilder<T> {
         })
     }
 
-    /// Create a '<' expression. Arguments must evaluate to Long type
+    /// Create a '<' expression. Arguments must evaluate to two Longs or two Strings
     pub fn less(self, e1: Expr<T>, e2: Expr<T>) -> Expr<T> {
         self.with_expr_kind(ExprKind::BinaryApp {
             op: BinaryOp::Less,
@@ -821,7 +838,7 @@ impl<T> ExprBuilder<T> {
         })
     }
 
-    /// Create a '<=' expression. Arguments must evaluate to Long type
+    /// Create a '<=' expression. Arguments must evaluate to two Longs or two Strings
     pub fn lesseq(self, e1: Expr<T>, e2: Expr<T>) -> Expr<T> {
         self.with_expr_kind(ExprKind::BinaryApp {
             op: BinaryOp::LessEq,
@@ -1043,7 +1060,7 @@ impl<T: Clone> ExprBuilder<T> {
         })
     }
 
-    /// Create a '>' expression. Arguments must evaluate to Long type
+    /// Create a '>' expression. Arguments must evaluate to two Longs or two Strings
     pub fn greater(self, e1: Expr<T>, e2: Expr<T>) -> Expr<T> {
         // e1 > e2 is defined as !(e1 <= e2)
         let leq = Self::with_data(self.data.clone())
@@ -1052,7 +1069,7 @@ impl<T: Clone> ExprBuilder<T> {
         self.not(leq)
     }
 
-    /// Create a '>=' expression. Arguments must evaluate to Long type
+    /// Create a '>=' expression. Arguments must evaluate to two Longs or two Strings
     pub fn greatereq(self, e1: Expr<T>, e2: Expr<T>) -> Expr<T> {
         // e1 >= e2 is defined as !(e1 < e2)
         let leq = Self::with_data(self.data.clone())
@@ -1067,6 +1084,7 @@ impl<T: Clone> ExprBuilder<T> {
 pub enum ExprConstructionError {
     /// A key occurred twice (or more) in a record literal
     #[error("duplicate key `{key}` in record literal")]
+    #[diagnostic(code(cedar::request::duplicate_key_in_record_literal))]
     DuplicateKeyInRecordLiteral {
         /// The key which occurred twice (or more) in the record literal
         key: SmolStr,
@@ -1384,6 +1402,19 @@ mod test {
         }
     }
 
+    #[test]
+    fn depth() {
+        assert_eq!(Expr::val(true).depth(), 1);
+        let e = Expr::and(
+            Expr::is_eq(
+                Expr::get_attr(Expr::var(Var::Principal), "foo".into()),
+                Expr::val(1),
+            ),
+            Expr::val(true),
+        );
+        assert_eq!(e.depth(), 4);
+    }
+
     #[test]
     fn exprs() {
         assert_eq!(
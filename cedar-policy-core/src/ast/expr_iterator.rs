@@ -37,6 +37,16 @@ impl<'a, T> ExprIterator<'a, T> {
     }
 }
 
+impl<'a, T> ExprIterator<'a, T> {
+    /// The immediate subexpressions of `expr`, i.e., the ones which the
+    /// iterator visits next after `expr` itself
+    pub(crate) fn children(expr: &'a Expr<T>) -> Vec<&'a Expr<T>> {
+        let mut iter = Self::new(expr);
+        iter.next();
+        iter.expression_stack
+    }
+}
+
 impl<'a, T> Iterator for ExprIterator<'a, T> {
     type Item = &'a Expr<T>;
 
@@ -301,9 +301,8 @@ impl ExtensionFunction {
     pub fn is_constructor(&self) -> bool {
         // return type is an extension type
         matches!(self.return_type(), Some(SchemaType::Extension { .. }))
-        // all arg types are `Some()`
-        && self.arg_types().iter().all(Option::is_some)
-        // no argument is an extension type
+        // no argument is an extension type, though an argument may take
+        // several non-extension types, e.g., `decimal` takes a string or a long
         && !self.arg_types().iter().any(|ty| matches!(ty, Some(SchemaType::Extension { .. })))
     }
 
@@ -43,12 +43,14 @@ pub enum BinaryOp {
 
     /// <
     ///
-    /// Arguments must have Long type
+    /// Arguments must both have Long type, or both have String type, which
+    /// are compared lexicographically by their code points
     Less,
 
     /// <=
     ///
-    /// Arguments must have Long type
+    /// Arguments must both have Long type, or both have String type, which
+    /// are compared lexicographically by their code points
     LessEq,
 
     /// Integer addition
@@ -330,6 +330,7 @@ impl std::fmt::Display for Context {
 pub enum ContextCreationError {
     /// Tried to create a `Context` out of something other than a record
     #[error("expression is not a record: `{expr}`")]
+    #[diagnostic(code(cedar::request::context_not_a_record))]
     NotARecord {
         /// Expression which is not a record
         expr: Box<RestrictedExpr>,
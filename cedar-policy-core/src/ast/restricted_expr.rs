@@ -589,7 +589,7 @@ impl<'a> Hash for RestrictedExprShapeOnly<'a> {
 
 /// Error when constructing a restricted expression from unrestricted
 
-#[derive(Debug, Clone, PartialEq, Eq, Diagnostic, Error)]
+#[derive(Debug, Clone, PartialEq, Eq, Diagnostic, Error, Serialize, Deserialize)]
 pub enum RestrictedExprError {
     /// An expression was expected to be a "restricted" expression, but contained
     /// a feature that is not allowed in restricted expressions. The `feature`
@@ -597,6 +597,7 @@ pub enum RestrictedExprError {
     /// The `expr` argument is the expression that uses the disallowed feature.
     /// Note that it is potentially a sub-expression of a larger expression.
     #[error("not allowed to use {feature} in a restricted expression: `{expr}`")]
+    #[diagnostic(code(cedar::evaluation::invalid_restricted_expression))]
     InvalidRestrictedExpression {
         /// what disallowed feature appeared in the expression
         feature: SmolStr,
@@ -17,18 +17,99 @@
 use crate::ast::*;
 use crate::evaluator::EvaluationError;
 use miette::Diagnostic;
+use serde::Serialize;
 use thiserror::Error;
 
 /// Errors that can occur during authorization
-#[derive(Debug, PartialEq, Eq, Clone, Diagnostic, Error)]
+#[derive(Debug, PartialEq, Eq, Clone, Error, Serialize)]
+#[serde(tag = "kind")]
 pub enum AuthorizationError {
     /// An error occurred when evaluating a policy.
     #[error("while evaluating policy `{id}`: {error}")]
     PolicyEvaluationError {
         /// Id of the policy with an error
+        #[serde(rename = "policyId")]
         id: PolicyID,
         /// Underlying evaluation error
-        #[diagnostic(transparent)]
         error: EvaluationError,
     },
+    /// No policy was evaluated, because the policy set had to be signed and
+    /// its signature wasn't verified.
+    #[error("no policy was evaluated, because the signature of the policy set was not verified")]
+    UnverifiedPolicySet,
+}
+
+// custom impl of `Diagnostic`: everything is forwarded to the evaluation error,
+// if there is one
+impl Diagnostic for AuthorizationError {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        match self {
+            Self::PolicyEvaluationError { error, .. } => error.code(),
+            Self::UnverifiedPolicySet => {
+                Some(Box::new("cedar::authorization::unverified_policy_set"))
+            }
+        }
+    }
+
+    fn severity(&self) -> Option<miette::Severity> {
+        match self {
+            Self::PolicyEvaluationError { error, .. } => error.severity(),
+            Self::UnverifiedPolicySet => None,
+        }
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        match self {
+            Self::PolicyEvaluationError { error, .. } => error.help(),
+            Self::UnverifiedPolicySet => Some(Box::new(
+                "authorize against a signed policy set, e.g., with `Authorizer::is_authorized_signed`",
+            )),
+        }
+    }
+
+    fn url<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        match self {
+            Self::PolicyEvaluationError { error, .. } => error.url(),
+            Self::UnverifiedPolicySet => None,
+        }
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        match self {
+            Self::PolicyEvaluationError { error, .. } => error.source_code(),
+            Self::UnverifiedPolicySet => None,
+        }
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        match self {
+            Self::PolicyEvaluationError { error, .. } => error.labels(),
+            Self::UnverifiedPolicySet => None,
+        }
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        match self {
+            Self::PolicyEvaluationError { error, .. } => error.related(),
+            Self::UnverifiedPolicySet => None,
+        }
+    }
+
+    fn diagnostic_source(&self) -> Option<&dyn Diagnostic> {
+        match self {
+            Self::PolicyEvaluationError { error, .. } => error.diagnostic_source(),
+            Self::UnverifiedPolicySet => None,
+        }
+    }
+}
+
+impl AuthorizationError {
+    /// The stable code of the kind of the error, see
+    /// [`EvaluationErrorKind::error_code`](crate::evaluator::EvaluationErrorKind::error_code)
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            Self::PolicyEvaluationError { error, .. } => error.error_code(),
+            Self::UnverifiedPolicySet => "unverified_policy_set",
+        }
+    }
 }
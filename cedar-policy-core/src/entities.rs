@@ -17,13 +17,17 @@
 //! This module contains the `Entities` type and related functionality.
 
 use crate::ast::*;
+use crate::evaluator::RestrictedEvaluator;
 use crate::extensions::Extensions;
-use crate::transitive_closure::{compute_tc, enforce_tc_and_dag};
-use std::collections::{hash_map, HashMap};
+use crate::transitive_closure::enforce_tc_and_dag;
+use either::Either;
+use std::collections::{hash_map, HashMap, HashSet};
 use std::fmt::Write;
+use std::sync::{Arc, OnceLock};
 
 use serde::Serialize;
 use serde_with::serde_as;
+use smol_str::SmolStr;
 
 mod conformance;
 pub use conformance::*;
@@ -38,8 +42,11 @@ pub use json::*;
 /// Note that `Entities` is `Serialize`, but currently this is only used for the
 /// FFI layer in DRT. All others use (and should use) the `from_json_*()` and
 /// `write_to_json()` methods as necessary.
+///
+/// Cloning an `Entities` is cheap: the clone shares the entities with the
+/// original, and each of them copies the entities it changes on write.
 #[serde_as]
-#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct Entities {
     /// Serde cannot serialize a HashMap to JSON when the key to the map cannot
     /// be serialized to a JSON string. This is a limitation of the JSON format.
@@ -47,9 +54,26 @@ pub struct Entities {
     /// lists instead.
     ///
     /// Important internal invariant: for any `Entities` object that exists, the
-    /// the `ancestor` relation is transitively closed.
-    #[serde_as(as = "Vec<(_, _)>")]
-    entities: HashMap<EntityUID, Entity>,
+    /// the `ancestor` relation is transitively closed, unless
+    /// `lazy_ancestors` is present.
+    #[serde_as(as = "Arc<Vec<(_, _)>>")]
+    entities: Arc<HashMap<EntityUID, Arc<Entity>>>,
+
+    /// Present if the `ancestor` relation of `entities` only contains the
+    /// parents given for each entity, see [`TCComputation::ComputeOnDemand`].
+    /// Always use [`Entities::is_descendant_of()`] and
+    /// [`Entities::ancestors_of()`] rather than the ancestors of an [`Entity`].
+    #[serde(skip)]
+    lazy_ancestors: Option<Arc<LazyAncestors>>,
+
+    /// The uids of the entities of each type in `entities`, for
+    /// [`Entities::of_type()`]
+    #[serde(skip)]
+    types: Arc<HashMap<EntityType, HashSet<EntityUID>>>,
+
+    /// Incremented by each change of the entities, see [`Entities::version()`]
+    #[serde(skip)]
+    version: u64,
 
     /// The mode flag determines whether this store functions as a partial store or
     /// as a fully concrete store.
@@ -65,7 +89,10 @@ impl Entities {
     /// Create a fresh `Entities` with no entities
     pub fn new() -> Self {
         Self {
-            entities: HashMap::new(),
+            entities: Arc::new(HashMap::new()),
+            lazy_ancestors: None,
+            types: Arc::new(HashMap::new()),
+            version: 0,
             mode: Mode::default(),
         }
     }
@@ -77,6 +104,9 @@ impl Entities {
     pub fn partial(self) -> Self {
         Self {
             entities: self.entities,
+            lazy_ancestors: self.lazy_ancestors,
+            types: self.types,
+            version: self.version,
             mode: Mode::Partial,
         }
     }
@@ -84,7 +114,7 @@ impl Entities {
     /// Get the `Entity` with the given UID, if any
     pub fn entity(&self, uid: &EntityUID) -> Dereference<'_, Entity> {
         match self.entities.get(uid) {
-            Some(e) => Dereference::Data(e),
+            Some(e) => Dereference::Data(e.as_ref()),
             None => match self.mode {
                 Mode::Concrete => Dereference::NoSuchEntity,
                 #[cfg(feature = "partial-eval")]
@@ -100,7 +130,77 @@ impl Entities {
 
     /// Iterate over the `Entity`s in the `Entities`
     pub fn iter(&self) -> impl Iterator<Item = &Entity> {
-        self.entities.values()
+        self.entities.values().map(Arc::as_ref)
+    }
+
+    /// The version of this `Entities`, which is 0 when it is constructed, and
+    /// incremented by each method changing its entities. Clones keep the
+    /// version of the original until they are changed themselves.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Iterate over the `Entity`s of type `ty` in the `Entities`, without
+    /// iterating over those of other types
+    pub fn of_type<'a>(&'a self, ty: &EntityType) -> impl Iterator<Item = &'a Entity> {
+        self.types
+            .get(ty)
+            .into_iter()
+            .flatten()
+            .filter_map(|uid| self.entities.get(uid))
+            .map(Arc::as_ref)
+    }
+
+    /// Is `entity` a descendant of `ancestor` in the entity hierarchy?
+    ///
+    /// Unlike [`Entity::is_descendant_of()`], this is also correct when the
+    /// ancestors are computed on demand.
+    pub fn is_descendant_of(&self, entity: &Entity, ancestor: &EntityUID) -> bool {
+        match self.lazy_ancestors(entity) {
+            Some(ancestors) => ancestors.contains(ancestor),
+            None => entity.is_descendant_of(ancestor),
+        }
+    }
+
+    /// Iterate over the ancestors of `entity` in the entity hierarchy
+    ///
+    /// Unlike [`Entity::ancestors()`], this is also correct when the ancestors
+    /// are computed on demand.
+    pub fn ancestors_of<'a>(&'a self, entity: &'a Entity) -> impl Iterator<Item = &'a EntityUID> {
+        match self.lazy_ancestors(entity) {
+            Some(ancestors) => Either::Left(ancestors.iter()),
+            None => Either::Right(entity.ancestors()),
+        }
+    }
+
+    /// The ancestors of `entity`, if they are computed on demand and `entity`
+    /// is in this store. They are computed the first time they are needed.
+    fn lazy_ancestors(&self, entity: &Entity) -> Option<&HashSet<EntityUID>> {
+        let lazy = self.lazy_ancestors.as_ref()?;
+        let memo = lazy.0.get(&entity.uid())?;
+        Some(memo.get_or_init(|| {
+            let mut ancestors = HashSet::new();
+            let mut pending: Vec<&EntityUID> = entity.ancestors().collect();
+            // the visited set makes this terminate even if the hierarchy has
+            // a cycle, which isn't checked in this mode
+            while let Some(uid) = pending.pop() {
+                if !ancestors.insert(uid.clone()) {
+                    continue;
+                }
+                // the memo of an ancestor contains all of its own ancestors;
+                // memos which are being computed by another thread are
+                // skipped rather than waited for, to avoid deadlock
+                match lazy.0.get(uid).and_then(OnceLock::get) {
+                    Some(memo) => ancestors.extend(memo.iter().cloned()),
+                    None => {
+                        if let Some(parent) = self.entities.get(uid) {
+                            pending.extend(parent.ancestors());
+                        }
+                    }
+                }
+            }
+            ancestors
+        }))
     }
 
     /// Adds the [`crate::ast::Entity`]s in the iterator to this [`Entities`].
@@ -122,27 +222,159 @@ impl Entities {
         extensions: Extensions<'_>,
     ) -> Result<Self> {
         let checker = schema.map(|schema| EntitySchemaConformanceChecker::new(schema, extensions));
+        let entities = Arc::make_mut(&mut self.entities);
+        let types = Arc::make_mut(&mut self.types);
         for entity in collection.into_iter() {
             if let Some(checker) = checker.as_ref() {
                 checker.validate_entity(&entity)?;
             }
-            match self.entities.entry(entity.uid()) {
+            match entities.entry(entity.uid()) {
                 hash_map::Entry::Occupied(_) => return Err(EntitiesError::Duplicate(entity.uid())),
                 hash_map::Entry::Vacant(vacant_entry) => {
-                    vacant_entry.insert(entity);
+                    types
+                        .entry(entity.uid().entity_type().clone())
+                        .or_default()
+                        .insert(entity.uid());
+                    vacant_entry.insert(Arc::new(entity));
                 }
             }
         }
         match tc_computation {
-            TCComputation::AssumeAlreadyComputed => (),
+            TCComputation::AssumeAlreadyComputed | TCComputation::ComputeOnDemand => (),
             TCComputation::EnforceAlreadyComputed => {
-                enforce_tc_and_dag(&self.entities).map_err(Box::new)?
+                enforce_tc_and_dag(entities).map_err(Box::new)?
             }
-            TCComputation::ComputeNow => compute_tc(&mut self.entities, true).map_err(Box::new)?,
+            TCComputation::ComputeNow => compute_tc(entities)?,
         };
+        // the added entities may be ancestors of existing ones, so existing
+        // memos are discarded. Once the ancestors of any entity are computed
+        // on demand, so are those of all entities, unless the closure is
+        // computed for all of them now.
+        let lazy = match tc_computation {
+            TCComputation::ComputeOnDemand => true,
+            TCComputation::ComputeNow => false,
+            _ => self.lazy_ancestors.is_some(),
+        };
+        self.lazy_ancestors = lazy.then(|| Arc::new(LazyAncestors::new(&self.entities)));
+        self.version += 1;
+        Ok(self)
+    }
+
+    /// Replace the attributes of entities in this [`Entities`] with those of the
+    /// given [`crate::ast::Entity`]s, without re-computing the transitive
+    /// closure.
+    ///
+    /// Each entity in `collection` must already be in this [`Entities`], and
+    /// keeps the ancestors it has here. Its parents must be among those
+    /// ancestors, or this method returns an error; changing the hierarchy
+    /// requires [`Entities::add_entities()`] or [`Entities::from_entities()`]
+    /// instead. Note that this method can't detect that a parent was removed.
+    ///
+    /// If `schema` is present, then the updated entities will be validated
+    /// against the `schema`, returning an error if they do not conform to the
+    /// schema.
+    pub fn update_attrs(
+        mut self,
+        collection: impl IntoIterator<Item = Entity>,
+        schema: Option<&impl Schema>,
+        extensions: Extensions<'_>,
+    ) -> Result<Self> {
+        let checker = schema.map(|schema| EntitySchemaConformanceChecker::new(schema, extensions));
+        for mut entity in collection {
+            let uid = entity.uid();
+            let Some(existing) = self.entities.get(&uid) else {
+                return Err(EntitiesError::NotFound(uid));
+            };
+            if let Some(parent) = entity
+                .ancestors()
+                .find(|p| !self.is_descendant_of(existing, p))
+            {
+                return Err(EntitiesError::HierarchyChanged {
+                    uid,
+                    parent: parent.clone(),
+                });
+            }
+            // the hierarchy is unchanged, so memoized ancestors stay valid
+            entity.set_ancestors(existing.ancestors().cloned().collect());
+            if let Some(checker) = checker.as_ref() {
+                checker.validate_entity(&entity)?;
+            }
+            Arc::make_mut(&mut self.entities).insert(uid, Arc::new(entity));
+        }
+        self.version += 1;
         Ok(self)
     }
 
+    /// Set the attribute `attr` of the entity with `uid` to the value of `val`,
+    /// in place, without re-computing the transitive closure or changing any
+    /// other entity.
+    ///
+    /// If `schema` is present, then the updated entity will be validated
+    /// against the `schema`. If it doesn't conform, the entity is left
+    /// unchanged and an error is returned.
+    pub fn update_attr(
+        &mut self,
+        uid: &EntityUID,
+        attr: SmolStr,
+        val: RestrictedExpr,
+        schema: Option<&impl Schema>,
+        extensions: Extensions<'_>,
+    ) -> Result<()> {
+        let val = RestrictedEvaluator::new(&extensions)
+            .partial_interpret(val.as_borrowed())
+            .map_err(|err| EntityAttrEvaluationError {
+                uid: uid.clone(),
+                attr: attr.clone(),
+                err,
+            })?;
+        self.replace_attr(uid, attr, Some(val), schema, extensions)
+            .map(|_| ())
+    }
+
+    /// Remove the attribute `attr` of the entity with `uid`, in place, as in
+    /// [`Entities::update_attr()`]. Returns the removed value, or `None` if the
+    /// entity didn't have the attribute.
+    pub fn remove_attr(
+        &mut self,
+        uid: &EntityUID,
+        attr: SmolStr,
+        schema: Option<&impl Schema>,
+        extensions: Extensions<'_>,
+    ) -> Result<Option<PartialValue>> {
+        self.replace_attr(uid, attr, None, schema, extensions)
+    }
+
+    /// Set the attribute `attr` of the entity with `uid` to `val`, or remove it
+    /// if `val` is `None`, returning its previous value. The previous value is
+    /// restored if the entity then doesn't conform to `schema`.
+    fn replace_attr(
+        &mut self,
+        uid: &EntityUID,
+        attr: SmolStr,
+        val: Option<PartialValue>,
+        schema: Option<&impl Schema>,
+        extensions: Extensions<'_>,
+    ) -> Result<Option<PartialValue>> {
+        // checked first, so that a shared map isn't copied for nothing
+        if !self.entities.contains_key(uid) {
+            return Err(EntitiesError::NotFound(uid.clone()));
+        }
+        let entity = Arc::make_mut(&mut self.entities)
+            .get_mut(uid)
+            .map(Arc::make_mut)
+            .ok_or_else(|| EntitiesError::NotFound(uid.clone()))?;
+        let old = entity.replace_attr(attr.clone(), val);
+        if let Some(schema) = schema {
+            let checker = EntitySchemaConformanceChecker::new(schema, extensions);
+            if let Err(err) = checker.validate_entity(entity) {
+                entity.replace_attr(attr, old);
+                return Err(err.into());
+            }
+        }
+        self.version += 1;
+        Ok(old)
+    }
+
     /// Create an `Entities` object with the given entities.
     ///
     /// If `schema` is present, then action entities from that schema will also
@@ -158,7 +390,26 @@ impl Entities {
         tc_computation: TCComputation,
         extensions: Extensions<'_>,
     ) -> Result<Self> {
-        let mut entity_map = create_entity_map(entities.into_iter())?;
+        Self::from_entities_with_duplicates(
+            entities,
+            schema,
+            tc_computation,
+            DuplicateEntities::Reject,
+            extensions,
+        )
+    }
+
+    /// Create an `Entities` object with the given entities, as in
+    /// `from_entities()`, except that entities with the same UID are handled
+    /// according to `duplicates`.
+    pub fn from_entities_with_duplicates(
+        entities: impl IntoIterator<Item = Entity>,
+        schema: Option<&impl Schema>,
+        tc_computation: TCComputation,
+        duplicates: DuplicateEntities,
+        extensions: Extensions<'_>,
+    ) -> Result<Self> {
+        let entity_map = create_entity_map(entities.into_iter(), duplicates)?;
         if let Some(schema) = schema {
             // validate entities against schema.
             // we do this before adding the actions, because we trust the
@@ -168,25 +419,38 @@ impl Entities {
             for entity in entity_map.values() {
                 checker.validate_entity(entity)?;
             }
+        }
+        Self::from_validated_entity_map(entity_map, schema, tc_computation)
+    }
+
+    /// Create an `Entities` object from entities which were already validated
+    /// against the `schema` (if any), adding the action entities from the
+    /// `schema` and computing or enforcing TC as for `from_entities()`.
+    pub(crate) fn from_validated_entity_map(
+        mut entity_map: HashMap<EntityUID, Arc<Entity>>,
+        schema: Option<&impl Schema>,
+        tc_computation: TCComputation,
+    ) -> Result<Self> {
+        if let Some(schema) = schema {
             // now add the action entities from the schema
-            entity_map.extend(
-                schema
-                    .action_entities()
-                    .into_iter()
-                    .map(|e| (e.uid(), unwrap_or_clone(e))),
-            );
+            entity_map.extend(schema.action_entities().into_iter().map(|e| (e.uid(), e)));
         }
         match tc_computation {
-            TCComputation::AssumeAlreadyComputed => {}
+            TCComputation::AssumeAlreadyComputed | TCComputation::ComputeOnDemand => {}
             TCComputation::EnforceAlreadyComputed => {
                 enforce_tc_and_dag(&entity_map).map_err(Box::new)?;
             }
             TCComputation::ComputeNow => {
-                compute_tc(&mut entity_map, true).map_err(Box::new)?;
+                compute_tc(&mut entity_map)?;
             }
         }
+        let lazy_ancestors = (tc_computation == TCComputation::ComputeOnDemand)
+            .then(|| Arc::new(LazyAncestors::new(&entity_map)));
         Ok(Self {
-            entities: entity_map,
+            types: Arc::new(index_types(&entity_map)),
+            entities: Arc::new(entity_map),
+            lazy_ancestors,
+            version: 0,
             mode: Mode::default(),
         })
     }
@@ -221,23 +485,17 @@ impl Entities {
     fn to_ejsons(&self) -> Result<Vec<EntityJson>> {
         self.entities
             .values()
+            .map(Arc::as_ref)
             .map(EntityJson::from_entity)
             .collect::<std::result::Result<_, JsonSerializationError>>()
             .map_err(Into::into)
     }
 
     fn get_entities_by_entity_type(&self) -> HashMap<EntityType, Vec<&Entity>> {
-        let mut entities_by_type: HashMap<EntityType, Vec<&Entity>> = HashMap::new();
-        for entity in self.iter() {
-            let euid = entity.uid();
-            let entity_type = euid.entity_type();
-            if let Some(entities) = entities_by_type.get_mut(entity_type) {
-                entities.push(entity);
-            } else {
-                entities_by_type.insert(entity_type.clone(), Vec::from([entity]));
-            }
-        }
-        entities_by_type
+        self.types
+            .keys()
+            .map(|ty| (ty.clone(), self.of_type(ty).collect()))
+            .collect()
     }
 
     /// Write entities into a DOT graph
@@ -276,7 +534,7 @@ impl Entities {
 
         // adding edges
         for entity in self.iter() {
-            for ancestor in entity.ancestors() {
+            for ancestor in self.ancestors_of(entity) {
                 dot_str.write_str(&format!(
                     "\t{} -> {}\n",
                     to_dot_id(&entity.uid()),
@@ -290,27 +548,89 @@ impl Entities {
     }
 }
 
-/// Create a map from EntityUids to Entities, erroring if there are any duplicates
-fn create_entity_map(es: impl Iterator<Item = Entity>) -> Result<HashMap<EntityUID, Entity>> {
+/// Compute the transitive closure of the entity hierarchy, and check that it is
+/// a DAG. With the `rayon` feature, the closure is computed in parallel.
+fn compute_tc(entities: &mut HashMap<EntityUID, Arc<Entity>>) -> Result<()> {
+    #[cfg(feature = "rayon")]
+    crate::transitive_closure::compute_tc_parallel(entities, true).map_err(Box::new)?;
+    #[cfg(not(feature = "rayon"))]
+    crate::transitive_closure::compute_tc(entities, true).map_err(Box::new)?;
+    Ok(())
+}
+
+/// Create a map from EntityUids to Entities, handling any duplicates
+/// according to `duplicates`
+fn create_entity_map(
+    es: impl Iterator<Item = Entity>,
+    duplicates: DuplicateEntities,
+) -> Result<HashMap<EntityUID, Arc<Entity>>> {
     let mut map = HashMap::new();
     for e in es {
-        match map.entry(e.uid()) {
-            hash_map::Entry::Occupied(_) => return Err(EntitiesError::Duplicate(e.uid())),
-            hash_map::Entry::Vacant(v) => {
-                v.insert(e);
-            }
-        };
+        insert_entity(&mut map, e, duplicates)?;
     }
     Ok(map)
 }
 
+/// Insert `entity` into `map`. If there already is an entity with its UID,
+/// they are merged or an error is returned, according to `duplicates`.
+fn insert_entity(
+    map: &mut HashMap<EntityUID, Arc<Entity>>,
+    entity: Entity,
+    duplicates: DuplicateEntities,
+) -> Result<()> {
+    match map.entry(entity.uid()) {
+        hash_map::Entry::Occupied(mut o) => {
+            let overwrite = match duplicates {
+                DuplicateEntities::Reject => return Err(EntitiesError::Duplicate(entity.uid())),
+                DuplicateEntities::MergeLastWriterWins => true,
+                DuplicateEntities::MergeRejectConflicts => false,
+            };
+            let uid = entity.uid();
+            Arc::make_mut(o.get_mut())
+                .merge(entity, overwrite)
+                .map_err(|attr| EntitiesError::ConflictingDuplicate { uid, attr })
+        }
+        hash_map::Entry::Vacant(v) => {
+            v.insert(Arc::new(entity));
+            Ok(())
+        }
+    }
+}
+
+/// The uids of the entities of each type in `entities`
+fn index_types(
+    entities: &HashMap<EntityUID, Arc<Entity>>,
+) -> HashMap<EntityType, HashSet<EntityUID>> {
+    let mut types: HashMap<EntityType, HashSet<EntityUID>> = HashMap::new();
+    for uid in entities.keys() {
+        types
+            .entry(uid.entity_type().clone())
+            .or_default()
+            .insert(uid.clone());
+    }
+    types
+}
+
+// the version and the indexes are determined by the changes made to the
+// entities, rather than by the entities themselves
+impl PartialEq for Entities {
+    fn eq(&self, other: &Self) -> bool {
+        self.entities == other.entities && self.mode == other.mode
+    }
+}
+
+impl Eq for Entities {}
+
 impl IntoIterator for Entities {
     type Item = Entity;
 
-    type IntoIter = hash_map::IntoValues<EntityUID, Entity>;
+    type IntoIter =
+        std::iter::Map<hash_map::IntoValues<EntityUID, Arc<Entity>>, fn(Arc<Entity>) -> Entity>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.entities.into_values()
+        // entities shared with clones of this `Entities` are cloned
+        let unwrap: fn(Arc<Entity>) -> Entity = unwrap_or_clone;
+        unwrap_or_clone(self.entities).into_values().map(unwrap)
     }
 }
 
@@ -395,7 +715,6 @@ impl Default for Mode {
 
 /// Describes the option for how the TC (transitive closure) of the entity
 /// hierarchy is computed
-#[allow(dead_code)] // only `ComputeNow` is used currently, that's intentional
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum TCComputation {
     /// Assume that the TC has already been computed and that the input is a DAG before the call of
@@ -409,6 +728,44 @@ pub enum TCComputation {
     /// This doesn't make any assumptions about the input, which can in fact
     /// contain just parent edges and not transitive ancestor edges. Also checks for cycles and returns an error if found.
     ComputeNow,
+    /// Don't compute the TC during the call of `Entities::from_entities`, but
+    /// compute the ancestors of each entity the first time they are needed,
+    /// and memoize them. Like `ComputeNow`, this doesn't make any assumptions
+    /// about the input. Unlike it, this doesn't check for cycles.
+    ComputeOnDemand,
+}
+
+/// Describes how entities with the same UID are handled when constructing an
+/// [`Entities`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DuplicateEntities {
+    /// Return [`EntitiesError::Duplicate`]
+    #[default]
+    Reject,
+    /// Merge the entities into one, whose parents and attributes are the union
+    /// of theirs. An attribute with different values takes the value of the
+    /// entity which comes last.
+    MergeLastWriterWins,
+    /// Merge the entities into one, whose parents and attributes are the union
+    /// of theirs. An attribute with different values is an error,
+    /// [`EntitiesError::ConflictingDuplicate`].
+    MergeRejectConflicts,
+}
+
+/// Memoized ancestors of each entity, for [`TCComputation::ComputeOnDemand`]
+#[derive(Debug, Clone)]
+struct LazyAncestors(HashMap<EntityUID, OnceLock<HashSet<EntityUID>>>);
+
+impl LazyAncestors {
+    /// Empty memos for each of `entities`
+    fn new(entities: &HashMap<EntityUID, Arc<Entity>>) -> Self {
+        Self(
+            entities
+                .keys()
+                .map(|uid| (uid.clone(), OnceLock::new()))
+                .collect(),
+        )
+    }
 }
 
 // PANIC SAFETY: Unit Test Code
@@ -792,6 +1149,210 @@ mod json_parsing_tests {
         parser.from_json_value(json).expect("JSON is correct")
     }
 
+    #[test]
+    fn stream_parse() {
+        let parser: EntityJsonParser<'_, '_> =
+            EntityJsonParser::new(None, Extensions::all_available(), TCComputation::ComputeNow);
+        let expected = simple_entities(&parser);
+        let mut json = Vec::new();
+        expected.write_to_json(&mut json).unwrap();
+        let es = parser
+            .from_json_stream(json.as_slice())
+            .expect("JSON is correct");
+        simple_entities_still_sane(&es);
+        assert_eq!(es, expected);
+
+        let json = r#"[
+            { "uid": { "type": "Test", "id": "alice" }, "attrs": {}, "parents": [] },
+            { "uid": { "type": "Test", "id": "alice" }, "attrs": {}, "parents": [] }
+        ]"#;
+        let expected = r#"Test::"alice""#.parse().unwrap();
+        assert_matches!(
+            parser.from_json_stream(json.as_bytes()),
+            Err(EntitiesError::Duplicate(e)) => assert_eq!(e, expected)
+        );
+
+        // errors in an entity are reported as such, not as JSON errors
+        let json = r#"[
+            { "uid": { "type": "Test", "id": "alice" }, "attrs": {}, "parents": [] },
+            { "uid": "hello", "attrs": {}, "parents": [] },
+            { "uid": "#;
+        assert_matches!(
+            parser.from_json_stream(json.as_bytes()),
+            Err(EntitiesError::Deserialization(
+                JsonDeserializationError::ExpectedLiteralEntityRef { .. }
+            ))
+        );
+        // while malformed JSON is reported as a JSON error
+        let json = r#"[
+            { "uid": { "type": "Test", "id": "alice" }, "attrs": {}, "parents": [] },
+            { "uid": "#;
+        assert_matches!(
+            parser.from_json_stream(json.as_bytes()),
+            Err(EntitiesError::Deserialization(
+                JsonDeserializationError::Serde(_)
+            ))
+        );
+        let json = r#"{ "uid": { "type": "Test", "id": "alice" }, "attrs": {}, "parents": [] }"#;
+        assert_matches!(
+            parser.from_json_stream(json.as_bytes()),
+            Err(EntitiesError::Deserialization(
+                JsonDeserializationError::Serde(_)
+            ))
+        );
+        // as is anything after the entities
+        let json = r#"[] []"#;
+        assert_matches!(
+            parser.from_json_stream(json.as_bytes()),
+            Err(EntitiesError::Deserialization(
+                JsonDeserializationError::Serde(_)
+            ))
+        );
+    }
+
+    #[test]
+    fn merge_duplicates() {
+        let json = serde_json::json!([
+            {
+                "uid": { "type": "Test", "id": "alice" },
+                "attrs": { "age": 1, "name": "alice" },
+                "parents": [{ "type": "Test", "id": "bob" }]
+            },
+            {
+                "uid": { "type": "Test", "id": "alice" },
+                "attrs": { "age": 2, "name": "alice", "admin": true },
+                "parents": [{ "type": "Test", "id": "jane" }]
+            }
+        ]);
+        let parser: EntityJsonParser<'_, '_> =
+            EntityJsonParser::new(None, Extensions::all_available(), TCComputation::ComputeNow);
+        let alice: EntityUID = r#"Test::"alice""#.parse().unwrap();
+        assert_matches!(
+            parser.from_json_value(json.clone()),
+            Err(EntitiesError::Duplicate(e)) => assert_eq!(e, alice)
+        );
+
+        let es = parser
+            .clone()
+            .with_duplicates(DuplicateEntities::MergeLastWriterWins)
+            .from_json_value(json.clone())
+            .expect("duplicates should be merged");
+        let merged = es.entity(&alice).unwrap();
+        assert_eq!(merged.get("age"), Some(&PartialValue::from(2)));
+        assert_eq!(merged.get("name"), Some(&PartialValue::from("alice")));
+        assert_eq!(merged.get("admin"), Some(&PartialValue::from(true)));
+        assert!(merged.is_descendant_of(&r#"Test::"bob""#.parse().unwrap()));
+        assert!(merged.is_descendant_of(&r#"Test::"jane""#.parse().unwrap()));
+
+        let parser = parser.with_duplicates(DuplicateEntities::MergeRejectConflicts);
+        assert_matches!(
+            parser.from_json_value(json.clone()),
+            Err(EntitiesError::ConflictingDuplicate { uid, attr }) => {
+                assert_eq!(uid, alice);
+                assert_eq!(attr, "age");
+            }
+        );
+        // the same applies when streaming
+        assert_matches!(
+            parser.from_json_stream(json.to_string().as_bytes()),
+            Err(EntitiesError::ConflictingDuplicate { .. })
+        );
+        let json = serde_json::json!([
+            { "uid": { "type": "Test", "id": "alice" }, "attrs": { "age": 1 }, "parents": [] },
+            { "uid": { "type": "Test", "id": "alice" }, "attrs": { "age": 1 }, "parents": [] }
+        ]);
+        let es = parser
+            .from_json_stream(json.to_string().as_bytes())
+            .expect("equal values are not a conflict");
+        assert_eq!(
+            es.entity(&alice).unwrap().get("age"),
+            Some(&PartialValue::from(1))
+        );
+    }
+
+    #[test]
+    fn collect_errors() {
+        let parser: EntityJsonParser<'_, '_> =
+            EntityJsonParser::new(None, Extensions::all_available(), TCComputation::ComputeNow);
+        let json = serde_json::json!([
+            { "uid": { "type": "Test", "id": "alice" }, "attrs": {}, "parents": [] },
+            { "uid": { "type": "Test", "id": "bob" }, "attrs": [], "parents": [] },
+            { "uid": { "type": "Test", "id": "alice" }, "attrs": {}, "parents": [] },
+            { "uid": "hello", "attrs": {}, "parents": [] },
+            {
+                "uid": { "type": "Test", "id": "jane" },
+                "attrs": { "bar": 2 },
+                "parents": [{ "type": "Test", "id": "alice" }]
+            },
+        ]);
+        let check = |(es, skipped): (Entities, Vec<SkippedEntityError>)| {
+            let jane = es.entity(&r#"Test::"jane""#.parse().unwrap()).unwrap();
+            assert!(jane.is_descendant_of(&r#"Test::"alice""#.parse().unwrap()));
+            assert_eq!(es.iter().count(), 2);
+            assert_matches!(skipped.as_slice(), [bob, alice, hello] => {
+                assert_eq!(bob.index, 1);
+                assert_eq!(bob.uid, Some(r#"Test::"bob""#.parse().unwrap()));
+                assert_matches!(
+                    bob.err,
+                    EntitiesError::Deserialization(JsonDeserializationError::Serde(_))
+                );
+                assert_eq!(alice.index, 2);
+                assert_matches!(alice.err, EntitiesError::Duplicate(_));
+                assert_eq!(
+                    alice.to_string(),
+                    r#"skipped entity 2 (`Test::"alice"`): duplicate entity entry `Test::"alice"`"#
+                );
+                assert_eq!(hello.index, 3);
+                assert_eq!(hello.uid, None);
+            });
+        };
+        check(
+            parser
+                .from_json_value_collecting_errors(json.clone())
+                .unwrap(),
+        );
+        check(
+            parser
+                .from_json_str_collecting_errors(&json.to_string())
+                .unwrap(),
+        );
+        check(
+            parser
+                .from_json_file_collecting_errors(json.to_string().as_bytes())
+                .unwrap(),
+        );
+
+        // malformed JSON still fails
+        assert_matches!(
+            parser.from_json_str_collecting_errors(r#"[{ "uid": "#),
+            Err(EntitiesError::Deserialization(
+                JsonDeserializationError::Serde(_)
+            ))
+        );
+        // as does an invalid hierarchy
+        let parser: EntityJsonParser<'_, '_> = EntityJsonParser::new(
+            None,
+            Extensions::all_available(),
+            TCComputation::EnforceAlreadyComputed,
+        );
+        let json = serde_json::json!([
+            {
+                "uid": { "type": "Test", "id": "alice" },
+                "attrs": {},
+                "parents": [{ "type": "Test", "id": "bob" }]
+            },
+            {
+                "uid": { "type": "Test", "id": "bob" },
+                "attrs": {},
+                "parents": [{ "type": "Test", "id": "jane" }]
+            },
+        ]);
+        assert_matches!(
+            parser.from_json_value_collecting_errors(json),
+            Err(EntitiesError::TransitiveClosureError(_))
+        );
+    }
+
     /// Ensure the initial conditions of the entiites still hold
     fn simple_entities_still_sane(e: &Entities) {
         let bob = r#"Test::"bob""#.parse().unwrap();
@@ -1500,6 +2061,56 @@ mod json_parsing_tests {
         assert_matches!(eparser.from_json_value(json), Ok(_));
     }
 
+    /// test that attributes can be declared unknown with `unknownAttrs`
+    #[test]
+    fn unknown_attrs() {
+        let json = serde_json::json!(
+            [
+                {
+                    "uid": { "type": "User", "id": "alice" },
+                    "attrs": { "age": 19 },
+                    "unknownAttrs": [ "department" ],
+                    "parents": []
+                }
+            ]
+        );
+        let eparser: EntityJsonParser<'_, '_> =
+            EntityJsonParser::new(None, Extensions::all_available(), TCComputation::ComputeNow);
+        let es = eparser.from_json_value(json).expect("JSON is correct");
+        let alice_uid: EntityUID = r#"User::"alice""#.parse().unwrap();
+        let alice = es.entity(&alice_uid).unwrap();
+        assert_eq!(alice.get("age"), Some(&PartialValue::from(19)));
+        assert_eq!(
+            alice.get("department"),
+            Some(&PartialValue::Residual(Expr::unknown(
+                Unknown::new_untyped(r#"User::"alice".department"#)
+            )))
+        );
+
+        // declared unknowns are written back out as `unknownAttrs`
+        let json = es.to_json_value().expect("should serialize");
+        assert_eq!(json[0]["unknownAttrs"], serde_json::json!(["department"]));
+        assert_eq!(roundtrip(&es).expect("should roundtrip"), es);
+
+        // an attribute can't both have a value and be unknown
+        let json = serde_json::json!(
+            [
+                {
+                    "uid": { "type": "User", "id": "alice" },
+                    "attrs": { "age": 19 },
+                    "unknownAttrs": [ "age" ],
+                    "parents": []
+                }
+            ]
+        );
+        assert_matches!(eparser.from_json_value(json.clone()), Err(e) => {
+            expect_err(&json, &e, &ExpectedErrorMessage::error_and_help(
+                r#"error during entity deserialization: attribute `age` of `User::"alice"` is given more than once in `attrs` and `unknownAttrs`"#,
+                "an attribute can either have a value in `attrs` or be declared in `unknownAttrs`, not both",
+            ));
+        });
+    }
+
     /// test that duplicate keys in a record is an error
     #[test]
     fn duplicate_keys() {
@@ -1538,6 +2149,7 @@ mod json_parsing_tests {
 #[allow(clippy::panic)]
 mod entities_tests {
     use super::*;
+    use crate::transitive_closure::TcError;
 
     #[test]
     fn empty_entities() {
@@ -1575,6 +2187,75 @@ mod entities_tests {
         assert!(es_v.contains(&&e3));
     }
 
+    #[test]
+    fn test_of_type() {
+        let uid =
+            |ty: &str, eid: &str| EntityUID::with_eid_and_type(ty, eid).expect("valid type name");
+        let es = Entities::from_entities(
+            vec![
+                Entity::with_uid(uid("User", "alice")),
+                Entity::with_uid(uid("User", "bob")),
+                Entity::with_uid(uid("Photo", "vacation")),
+            ],
+            None::<&NoEntitiesSchema>,
+            TCComputation::ComputeNow,
+            Extensions::all_available(),
+        )
+        .expect("Failed to construct entities");
+        let mut users: Vec<_> = es
+            .of_type(uid("User", "alice").entity_type())
+            .map(|e| e.uid().to_string())
+            .collect();
+        users.sort();
+        assert_eq!(users, vec![r#"User::"alice""#, r#"User::"bob""#]);
+        let group = uid("Group", "admins");
+        assert_eq!(es.of_type(group.entity_type()).count(), 0);
+
+        // added entities are indexed too
+        let es = es
+            .add_entities(
+                vec![Entity::with_uid(group.clone())],
+                None::<&NoEntitiesSchema>,
+                TCComputation::ComputeNow,
+                Extensions::all_available(),
+            )
+            .expect("Failed to add entities");
+        assert_eq!(
+            es.of_type(group.entity_type()).collect::<Vec<_>>(),
+            vec![&Entity::with_uid(group.clone())]
+        );
+        assert_eq!(es.of_type(uid("Photo", "x").entity_type()).count(), 1);
+    }
+
+    #[test]
+    fn test_cycle() {
+        // a -> b -> c -> b
+        let mut a = Entity::with_uid(EntityUID::with_eid("a"));
+        a.add_ancestor(EntityUID::with_eid("b"));
+        let mut b = Entity::with_uid(EntityUID::with_eid("b"));
+        b.add_ancestor(EntityUID::with_eid("c"));
+        let mut c = Entity::with_uid(EntityUID::with_eid("c"));
+        c.add_ancestor(EntityUID::with_eid("b"));
+        let res = Entities::from_entities(
+            vec![a, b, c],
+            None::<&NoEntitiesSchema>,
+            TCComputation::ComputeNow,
+            Extensions::all_available(),
+        );
+        let b = EntityUID::with_eid("b");
+        let c = EntityUID::with_eid("c");
+        match res.map_err(|err| match err {
+            EntitiesError::TransitiveClosureError(err) => *err,
+            err => panic!("expected a cycle, got {err:?}"),
+        }) {
+            Err(TcError::HasCycle { cycle, .. }) => assert!(
+                cycle == [b.clone(), c.clone(), b.clone()] || cycle == [c.clone(), b, c],
+                "{cycle:?}"
+            ),
+            res => panic!("expected a cycle, got {res:?}"),
+        }
+    }
+
     #[test]
     fn test_enforce_already_computed_fail() {
         // Hierarchy
@@ -1620,6 +2301,263 @@ mod entities_tests {
         )
         .expect("Should have succeeded");
     }
+
+    #[test]
+    fn test_update_attrs() {
+        // Hierarchy
+        // a -> b -> c
+        let mut e1 = Entity::with_uid(EntityUID::with_eid("a"));
+        let mut e2 = Entity::with_uid(EntityUID::with_eid("b"));
+        let e3 = Entity::with_uid(EntityUID::with_eid("c"));
+        e1.add_ancestor(EntityUID::with_eid("b"));
+        e2.add_ancestor(EntityUID::with_eid("c"));
+        let es = Entities::from_entities(
+            vec![e1, e2, e3],
+            None::<&NoEntitiesSchema>,
+            TCComputation::ComputeNow,
+            Extensions::all_available(),
+        )
+        .expect("Failed to construct entities");
+
+        // the updated entity lists only its direct parent, but keeps the
+        // computed ancestors
+        let mut updated = Entity::with_uid(EntityUID::with_eid("a"));
+        updated.add_ancestor(EntityUID::with_eid("b"));
+        updated
+            .set_attr(
+                "level".into(),
+                RestrictedExpr::val(3),
+                &Extensions::all_available(),
+            )
+            .expect("Failed to set attribute");
+        let es = es
+            .update_attrs(
+                vec![updated],
+                None::<&NoEntitiesSchema>,
+                Extensions::all_available(),
+            )
+            .expect("Failed to update attributes");
+        let a = es.entity(&EntityUID::with_eid("a")).unwrap();
+        assert_eq!(a.get("level"), Some(&PartialValue::from(3)));
+        assert!(a.is_descendant_of(&EntityUID::with_eid("b")));
+        assert!(a.is_descendant_of(&EntityUID::with_eid("c")));
+
+        // adding a parent requires re-computing the transitive closure
+        let mut updated = Entity::with_uid(EntityUID::with_eid("c"));
+        updated.add_ancestor(EntityUID::with_eid("a"));
+        let err = es
+            .clone()
+            .update_attrs(
+                vec![updated],
+                None::<&NoEntitiesSchema>,
+                Extensions::all_available(),
+            )
+            .err()
+            .expect("Parent should be rejected");
+        match err {
+            EntitiesError::HierarchyChanged { uid, parent } => {
+                assert_eq!(uid, EntityUID::with_eid("c"));
+                assert_eq!(parent, EntityUID::with_eid("a"));
+            }
+            _ => panic!("Wrong Error!"),
+        }
+
+        let err = es
+            .update_attrs(
+                vec![Entity::with_uid(EntityUID::with_eid("d"))],
+                None::<&NoEntitiesSchema>,
+                Extensions::all_available(),
+            )
+            .err()
+            .expect("Entity should not exist");
+        match err {
+            EntitiesError::NotFound(uid) => assert_eq!(uid, EntityUID::with_eid("d")),
+            _ => panic!("Wrong Error!"),
+        }
+    }
+
+    #[test]
+    fn test_update_attr() {
+        let mut a = Entity::with_uid(EntityUID::with_eid("a"));
+        a.add_ancestor(EntityUID::with_eid("b"));
+        let mut es = Entities::from_entities(
+            vec![a, Entity::with_uid(EntityUID::with_eid("b"))],
+            None::<&NoEntitiesSchema>,
+            TCComputation::ComputeNow,
+            Extensions::all_available(),
+        )
+        .expect("Failed to construct entities");
+        let a_uid = EntityUID::with_eid("a");
+
+        for level in [3_i64, 4] {
+            es.update_attr(
+                &a_uid,
+                "level".into(),
+                RestrictedExpr::val(level),
+                None::<&NoEntitiesSchema>,
+                Extensions::all_available(),
+            )
+            .expect("Failed to update attribute");
+            let a = es.entity(&a_uid).unwrap();
+            assert_eq!(a.get("level"), Some(&PartialValue::from(level)));
+            assert!(a.is_descendant_of(&EntityUID::with_eid("b")));
+        }
+
+        let removed = es
+            .remove_attr(
+                &a_uid,
+                "level".into(),
+                None::<&NoEntitiesSchema>,
+                Extensions::all_available(),
+            )
+            .expect("Failed to remove attribute");
+        assert_eq!(removed, Some(PartialValue::from(4)));
+        assert_eq!(es.entity(&a_uid).unwrap().get("level"), None);
+        let removed = es
+            .remove_attr(
+                &a_uid,
+                "level".into(),
+                None::<&NoEntitiesSchema>,
+                Extensions::all_available(),
+            )
+            .expect("Removing a missing attribute succeeds");
+        assert_eq!(removed, None);
+
+        let err = es
+            .update_attr(
+                &EntityUID::with_eid("d"),
+                "level".into(),
+                RestrictedExpr::val(3),
+                None::<&NoEntitiesSchema>,
+                Extensions::all_available(),
+            )
+            .err()
+            .expect("Entity should not exist");
+        match err {
+            EntitiesError::NotFound(uid) => assert_eq!(uid, EntityUID::with_eid("d")),
+            _ => panic!("Wrong Error!"),
+        }
+    }
+
+    #[test]
+    fn test_copy_on_write() {
+        let mut a = Entity::with_uid(EntityUID::with_eid("a"));
+        a.add_ancestor(EntityUID::with_eid("b"));
+        let es = Entities::from_entities(
+            vec![a, Entity::with_uid(EntityUID::with_eid("b"))],
+            None::<&NoEntitiesSchema>,
+            TCComputation::ComputeNow,
+            Extensions::all_available(),
+        )
+        .expect("Failed to construct entities");
+        let a_uid = EntityUID::with_eid("a");
+        let b_uid = EntityUID::with_eid("b");
+
+        let mut updated = es.clone();
+        assert!(Arc::ptr_eq(&es.entities, &updated.entities));
+        updated
+            .update_attr(
+                &a_uid,
+                "level".into(),
+                RestrictedExpr::val(3),
+                None::<&NoEntitiesSchema>,
+                Extensions::all_available(),
+            )
+            .expect("Failed to update attribute");
+        assert_eq!((es.version(), updated.version()), (0, 1));
+        // only the updated entity is copied
+        assert_eq!(es.entity(&a_uid).unwrap().get("level"), None);
+        assert_eq!(
+            updated.entity(&a_uid).unwrap().get("level"),
+            Some(&PartialValue::from(3))
+        );
+        let shared = |es: &Entities, uid: &EntityUID| es.entities.get(uid).cloned();
+        assert!(Arc::ptr_eq(
+            &shared(&es, &b_uid).unwrap(),
+            &shared(&updated, &b_uid).unwrap()
+        ));
+
+        // computing the closure only copies the entities which gain ancestors
+        let mut c = Entity::with_uid(EntityUID::with_eid("c"));
+        c.add_ancestor(a_uid.clone());
+        let added = updated
+            .clone()
+            .add_entities(
+                vec![c],
+                None::<&NoEntitiesSchema>,
+                TCComputation::ComputeNow,
+                Extensions::all_available(),
+            )
+            .expect("Failed to add entities");
+        assert_eq!(added.version(), 2);
+        assert!(Arc::ptr_eq(
+            &shared(&updated, &a_uid).unwrap(),
+            &shared(&added, &a_uid).unwrap()
+        ));
+        let c = added.entity(&EntityUID::with_eid("c")).unwrap();
+        assert!(c.is_descendant_of(&b_uid));
+    }
+
+    #[test]
+    fn test_compute_on_demand() {
+        // Hierarchy
+        // a -> b -> c -> d
+        // x -> y -> x
+        let mut a = Entity::with_uid(EntityUID::with_eid("a"));
+        let mut b = Entity::with_uid(EntityUID::with_eid("b"));
+        let mut c = Entity::with_uid(EntityUID::with_eid("c"));
+        let d = Entity::with_uid(EntityUID::with_eid("d"));
+        let mut x = Entity::with_uid(EntityUID::with_eid("x"));
+        let mut y = Entity::with_uid(EntityUID::with_eid("y"));
+        a.add_ancestor(EntityUID::with_eid("b"));
+        b.add_ancestor(EntityUID::with_eid("c"));
+        c.add_ancestor(EntityUID::with_eid("d"));
+        x.add_ancestor(EntityUID::with_eid("y"));
+        y.add_ancestor(EntityUID::with_eid("x"));
+        let es = Entities::from_entities(
+            vec![a, b, c, d, x, y],
+            None::<&NoEntitiesSchema>,
+            TCComputation::ComputeOnDemand,
+            Extensions::all_available(),
+        )
+        .expect("Cycles are not checked");
+
+        // the stored entities only have their parents
+        let a = es.entity(&EntityUID::with_eid("a")).unwrap();
+        assert!(!a.is_descendant_of(&EntityUID::with_eid("d")));
+        // `b` is computed first, so `a` uses its memo
+        let b = es.entity(&EntityUID::with_eid("b")).unwrap();
+        assert!(es.is_descendant_of(b, &EntityUID::with_eid("d")));
+        assert!(es.is_descendant_of(a, &EntityUID::with_eid("d")));
+        assert!(!es.is_descendant_of(a, &EntityUID::with_eid("x")));
+        let mut ancestors: Vec<_> = es.ancestors_of(a).map(ToString::to_string).collect();
+        ancestors.sort();
+        assert_eq!(
+            ancestors,
+            vec![
+                r#"test_entity_type::"b""#,
+                r#"test_entity_type::"c""#,
+                r#"test_entity_type::"d""#
+            ]
+        );
+
+        // the cycle terminates
+        let x = es.entity(&EntityUID::with_eid("x")).unwrap();
+        assert!(es.is_descendant_of(x, &EntityUID::with_eid("y")));
+        assert!(es.is_descendant_of(x, &EntityUID::with_eid("x")));
+
+        // computing the closure now checks for cycles in all entities
+        let err = es
+            .add_entities(
+                vec![Entity::with_uid(EntityUID::with_eid("e"))],
+                None::<&NoEntitiesSchema>,
+                TCComputation::ComputeNow,
+                Extensions::all_available(),
+            )
+            .err()
+            .expect("The cycle is detected");
+        assert!(matches!(err, EntitiesError::TransitiveClosureError(_)));
+    }
 }
 
 // PANIC SAFETY: Unit Test Code
@@ -1962,6 +2900,60 @@ mod schema_based_parsing_tests {
         );
     }
 
+    #[cfg(all(feature = "decimal", feature = "ipaddr"))]
+    /// a long is also an implied `decimal` constructor argument
+    #[test]
+    fn implied_decimal_from_long() {
+        let entitiesjson = json!(
+            [
+                {
+                    "uid": { "type": "Employee", "id": "12UA45" },
+                    "attrs": {
+                        "isFullTime": true,
+                        "numDirectReports": 3,
+                        "department": "Sales",
+                        "manager": { "type": "Employee", "id": "34FB87" },
+                        "hr_contacts": [
+                            { "type": "HR", "id": "aaaaa" },
+                            { "type": "HR", "id": "bbbbb" }
+                        ],
+                        "json_blob": {
+                            "inner1": false,
+                            "inner2": "-*/",
+                            "inner3": { "innerinner": { "type": "Employee", "id": "09AE76" }},
+                        },
+                        "home_ip": "222.222.222.101",
+                        "work_ip": { "fn": "ip", "arg": "2.2.2.0/24" },
+                        "trust_score": 5,
+                        "tricky": { "type": "Employee", "id": "34FB87" }
+                    },
+                    "parents": []
+                }
+            ]
+        );
+        let eparser = EntityJsonParser::new(
+            Some(&MockSchema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        );
+        let parsed = eparser
+            .from_json_value(entitiesjson)
+            .expect("Should parse without error");
+        let parsed = parsed
+            .entity(&r#"Employee::"12UA45""#.parse().unwrap())
+            .expect("that should be the employee id");
+        assert_eq!(
+            parsed
+                .get("trust_score")
+                .cloned()
+                .map(RestrictedExpr::try_from),
+            Some(Ok(RestrictedExpr::call_extension_fn(
+                Name::parse_unqualified_name("decimal").expect("valid"),
+                vec![RestrictedExpr::val(5)]
+            ))),
+        );
+    }
+
     #[cfg(all(feature = "decimal", feature = "ipaddr"))]
     /// simple type mismatch with expected type
     #[test]
@@ -2376,6 +3368,14 @@ mod schema_based_parsing_tests {
                 r#"entity does not conform to the schema: expected entity `Employee::"12UA45"` to have attribute `numDirectReports`, but it does not"#
             ));
         });
+        // the streaming parser checks each entity as it is read
+        let json = serde_json::to_vec(&entitiesjson).unwrap();
+        assert_matches!(
+            eparser.from_json_stream(json.as_slice()),
+            Err(EntitiesError::InvalidEntity(
+                EntitySchemaConformanceError::MissingRequiredEntityAttr { .. }
+            ))
+        );
     }
 
     #[cfg(all(feature = "decimal", feature = "ipaddr"))]
@@ -2422,6 +3422,58 @@ mod schema_based_parsing_tests {
         });
     }
 
+    #[cfg(all(feature = "decimal", feature = "ipaddr"))]
+    /// attributes declared unknown must exist according to the schema, and
+    /// count towards its required attributes
+    #[test]
+    fn unknown_entity_attrs() {
+        let entitiesjson = json!(
+            [
+                {
+                    "uid": { "type": "Employee", "id": "12UA45" },
+                    "attrs": {
+                        "isFullTime": true,
+                        "manager": { "type": "Employee", "id": "34FB87" },
+                        "hr_contacts": [
+                            { "type": "HR", "id": "aaaaa" },
+                            { "type": "HR", "id": "bbbbb" }
+                        ],
+                        "json_blob": {
+                            "inner1": false,
+                            "inner2": "-*/",
+                            "inner3": { "innerinner": { "type": "Employee", "id": "09AE76" }},
+                        },
+                        "home_ip": "222.222.222.101",
+                        "work_ip": { "fn": "ip", "arg": "2.2.2.0/24" },
+                        "trust_score": "5.7",
+                    },
+                    "unknownAttrs": [ "numDirectReports", "department" ],
+                    "parents": []
+                }
+            ]
+        );
+        let eparser = EntityJsonParser::new(
+            Some(&MockSchema),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        );
+        let parsed = eparser
+            .from_json_value(entitiesjson.clone())
+            .expect("Should parse without error");
+        let parsed = parsed
+            .entity(&r#"Employee::"12UA45""#.parse().unwrap())
+            .expect("that should be the employee id");
+        assert_matches!(parsed.get("department"), Some(PartialValue::Residual(_)));
+
+        let mut entitiesjson = entitiesjson;
+        entitiesjson[0]["unknownAttrs"] = json!(["numDirectReports", "department", "wat"]);
+        assert_matches!(eparser.from_json_value(entitiesjson.clone()), Err(e) => {
+            expect_err(&entitiesjson, &e, &ExpectedErrorMessage::error(
+                r#"error during entity deserialization: attribute `wat` on `Employee::"12UA45"` should not exist according to the schema"#
+            ));
+        });
+    }
+
     #[cfg(all(feature = "decimal", feature = "ipaddr"))]
     /// Test that involves parents of wrong types
     #[test]
@@ -17,6 +17,7 @@ use thiserror::Error;
 pub enum EntitySchemaConformanceError {
     /// Encountered attribute that shouldn't exist on entities of this type
     #[error("attribute `{attr}` on `{uid}` should not exist according to the schema")]
+    #[diagnostic(code(cedar::entities::unexpected_entity_attr))]
     UnexpectedEntityAttr {
         /// Entity that had the unexpected attribute
         uid: EntityUID,
@@ -25,6 +26,7 @@ pub enum EntitySchemaConformanceError {
     },
     /// Didn't encounter attribute that should exist
     #[error("expected entity `{uid}` to have attribute `{attr}`, but it does not")]
+    #[diagnostic(code(cedar::entities::missing_required_entity_attr))]
     MissingRequiredEntityAttr {
         /// Entity that is missing a required attribute
         uid: EntityUID,
@@ -34,6 +36,7 @@ pub enum EntitySchemaConformanceError {
     /// The given attribute on the given entity had a different type than the
     /// schema indicated
     #[error("in attribute `{attr}` on `{uid}`, {err}")]
+    #[diagnostic(code(cedar::entities::type_mismatch))]
     TypeMismatch {
         /// Entity where the type mismatch occurred
         uid: EntityUID,
@@ -46,6 +49,7 @@ pub enum EntitySchemaConformanceError {
     /// Found a set whose elements don't all have the same type. This doesn't match
     /// any possible schema.
     #[error("in attribute `{attr}` on `{uid}`, {err}")]
+    #[diagnostic(code(cedar::entities::heterogeneous_set))]
     HeterogeneousSet {
         /// Entity where the error occurred
         uid: EntityUID,
@@ -59,6 +63,7 @@ pub enum EntitySchemaConformanceError {
     #[error(
         "`{uid}` is not allowed to have an ancestor of type `{ancestor_ty}` according to the schema"
     )]
+    #[diagnostic(code(cedar::entities::invalid_ancestor_type))]
     InvalidAncestorType {
         /// Entity that has an invalid ancestor type
         uid: EntityUID,
@@ -72,6 +77,7 @@ pub enum EntitySchemaConformanceError {
     UnexpectedEntityType(#[from] UnexpectedEntityTypeError),
     /// Encountered an action which was not declared in the schema
     #[error("found action entity `{uid}`, but it was not declared as an action in the schema")]
+    #[diagnostic(code(cedar::entities::undeclared_action))]
     UndeclaredAction {
         /// Action which was not declared in the schema
         uid: EntityUID,
@@ -79,7 +85,7 @@ pub enum EntitySchemaConformanceError {
     /// Encountered an action whose definition doesn't precisely match the
     /// schema's declaration of that action
     #[error("definition of action `{uid}` does not match its schema declaration")]
-    #[diagnostic(help(
+    #[diagnostic(code(cedar::entities::action_declaration_mismatch), help(
         "to use the schema's definition of `{uid}`, simply omit it from the entities input data"
     ))]
     ActionDeclarationMismatch {
@@ -90,6 +96,7 @@ pub enum EntitySchemaConformanceError {
     /// checking entity conformance because that may require getting information
     /// about any extension functions referenced in entity attribute values.
     #[error("in attribute `{attr}` on `{uid}`, {err}")]
+    #[diagnostic(code(cedar::entities::extension_function_lookup))]
     ExtensionFunctionLookup {
         /// Entity where the error occurred
         uid: EntityUID,
@@ -113,6 +120,10 @@ pub struct UnexpectedEntityTypeError {
 }
 
 impl Diagnostic for UnexpectedEntityTypeError {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new("cedar::entities::unexpected_entity_type"))
+    }
+
     fn help<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
         match self.suggested_types.as_slice() {
             [] => None,
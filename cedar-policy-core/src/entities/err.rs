@@ -17,6 +17,7 @@
 use super::EntityUID;
 use crate::transitive_closure;
 use miette::Diagnostic;
+use smol_str::SmolStr;
 use thiserror::Error;
 
 /// Error type for errors raised in entities.rs.
@@ -32,7 +33,38 @@ pub enum EntitiesError {
     Deserialization(#[from] crate::entities::JsonDeserializationError),
     /// Error constructing the `[crate::entities::Entities]` as there is a duplicate Entity UID
     #[error("duplicate entity entry `{0}`")]
+    #[diagnostic(code(cedar::entities::duplicate_entity))]
     Duplicate(EntityUID),
+    /// Error merging duplicate entities with
+    /// `[crate::entities::DuplicateEntities::MergeRejectConflicts]`, as they
+    /// have different values for an attribute
+    #[error("duplicate entity entry `{uid}` has a conflicting value for attribute `{attr}`")]
+    #[diagnostic(code(cedar::entities::conflicting_duplicate_entity))]
+    ConflictingDuplicate {
+        /// UID of the duplicate entities
+        uid: EntityUID,
+        /// Attribute with different values
+        attr: SmolStr,
+    },
+    /// Error updating the attributes of an entity which isn't in the
+    /// `[crate::entities::Entities]`
+    #[error("entity `{0}` does not exist")]
+    #[diagnostic(code(cedar::entities::entity_not_found))]
+    NotFound(EntityUID),
+    /// Error updating the attributes of an entity whose parents have changed,
+    /// which requires re-computing the transitive closure
+    #[error("entity `{uid}` has parent `{parent}`, which is not one of its existing ancestors")]
+    #[diagnostic(code(cedar::entities::hierarchy_changed))]
+    HierarchyChanged {
+        /// Entity whose parents have changed
+        uid: EntityUID,
+        /// Parent which is not one of the existing ancestors of the entity
+        parent: EntityUID,
+    },
+    /// Error evaluating the new value of an attribute of an entity
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    AttrEvaluation(#[from] crate::ast::EntityAttrEvaluationError),
     /// Errors occurring while computing or enforcing transitive closure on the
     /// entity hierarchy.
     #[error("transitive closure computation/enforcement error: {0}")]
@@ -44,5 +76,25 @@ pub enum EntitiesError {
     InvalidEntity(#[from] crate::entities::EntitySchemaConformanceError),
 }
 
+/// Error for an entity which was skipped when parsing entities with one of
+/// the `*_collecting_errors()` methods of
+/// [`crate::entities::EntityJsonParser`]
+#[derive(Debug, Diagnostic, Error)]
+#[error("skipped entity {index}{}: {err}", display_uid(.uid.as_ref()))]
+pub struct SkippedEntityError {
+    /// Position of the entity in the JSON array of entities, starting from 0
+    pub index: usize,
+    /// UID of the entity, if it could be parsed
+    pub uid: Option<EntityUID>,
+    /// Underlying error
+    #[diagnostic(transparent)]
+    pub err: EntitiesError,
+}
+
+/// Display the uid of a [`SkippedEntityError`], if there is one
+fn display_uid(uid: Option<&EntityUID>) -> String {
+    uid.map(|uid| format!(" (`{uid}`)")).unwrap_or_default()
+}
+
 /// Type alias for convenience
 pub type Result<T> = std::result::Result<T, EntitiesError>;
@@ -18,6 +18,7 @@ use super::{JsonDeserializationError, JsonDeserializationErrorContext, SchemaTyp
 use crate::ast::{Context, ContextCreationError};
 use crate::extensions::Extensions;
 use miette::Diagnostic;
+use smol_str::SmolStr;
 use std::collections::HashMap;
 use thiserror::Error;
 
@@ -25,6 +26,12 @@ use thiserror::Error;
 pub trait ContextSchema {
     /// `SchemaType` (expected to be a `Record`) for the context.
     fn context_type(&self) -> SchemaType;
+
+    /// Values (in JSON form) of optional context attributes, which are used
+    /// when the context JSON doesn't have the attribute.
+    fn context_defaults(&self) -> Vec<(SmolStr, serde_json::Value)> {
+        Vec::new()
+    }
 }
 
 /// Simple type that implements `ContextSchema` by expecting an empty context
@@ -47,7 +54,8 @@ pub struct ContextJsonParser<'e, 's, S: ContextSchema = NullContextSchema> {
     /// It will also ensure that the produced `Context` fully conforms to the
     /// `schema` -- for instance, it will error if attributes have the wrong
     /// types (e.g., string instead of integer), or if required attributes are
-    /// missing or superfluous attributes are provided.
+    /// missing or superfluous attributes are provided. Optional attributes
+    /// which are missing are given the schema's defaults for them, if any.
     schema: Option<&'s S>,
 
     /// Extensions which are active for the JSON parsing.
@@ -62,7 +70,8 @@ impl<'e, 's, S: ContextSchema> ContextJsonParser<'e, 's, S> {
     /// It will also ensure that the produced `Context` fully conforms to the
     /// `schema` -- for instance, it will error if attributes have the wrong
     /// types (e.g., string instead of integer), or if required attributes are
-    /// missing or superfluous attributes are provided.
+    /// missing or superfluous attributes are provided. Optional attributes
+    /// which are missing are given the schema's defaults for them, if any.
     pub fn new(schema: Option<&'s S>, extensions: Extensions<'e>) -> Self {
         Self { schema, extensions }
     }
@@ -78,6 +87,15 @@ impl<'e, 's, S: ContextSchema> ContextJsonParser<'e, 's, S> {
         &self,
         json: serde_json::Value,
     ) -> Result<Context, ContextJsonDeserializationError> {
+        let json = match (self.schema, json) {
+            (Some(schema), serde_json::Value::Object(mut attrs)) => {
+                for (attr, default) in schema.context_defaults() {
+                    attrs.entry(attr.to_string()).or_insert(default);
+                }
+                serde_json::Value::Object(attrs)
+            }
+            (_, json) => json,
+        };
         let vparser = ValueParser::new(self.extensions);
         let expected_ty = self.schema.map(|s| s.context_type());
         let rexpr = vparser.val_into_restricted_expr(json, expected_ty.as_ref(), || {
@@ -20,15 +20,17 @@ use super::{
     ValueParser,
 };
 use crate::ast::{
-    BorrowedRestrictedExpr, Entity, EntityType, EntityUID, PartialValue, RestrictedExpr,
+    BorrowedRestrictedExpr, Entity, EntityType, EntityUID, ExprKind, PartialValue, RestrictedExpr,
 };
 use crate::entities::{
-    schematype_of_partialvalue, unwrap_or_clone, Entities, EntitiesError,
-    EntitySchemaConformanceError, GetSchemaTypeError, TCComputation, UnexpectedEntityTypeError,
+    insert_entity, schematype_of_partialvalue, unwrap_or_clone, DuplicateEntities, Entities,
+    EntitiesError, EntitySchemaConformanceChecker, EntitySchemaConformanceError,
+    GetSchemaTypeError, SkippedEntityError, TCComputation, UnexpectedEntityTypeError,
 };
 use crate::extensions::Extensions;
 use crate::jsonvalue::JsonValueWithNoDuplicateKeys;
-use serde::{Deserialize, Serialize};
+use serde::de::{Error as _, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_with::serde_as;
 use smol_str::SmolStr;
 use std::collections::HashMap;
@@ -51,6 +53,15 @@ pub struct EntityJson {
     attrs: HashMap<SmolStr, JsonValueWithNoDuplicateKeys>,
     /// Parents of the entity, specified in any form accepted by `EntityUidJson`
     parents: Vec<EntityUidJson>,
+    /// Attributes which the entity has, but whose values are not yet known.
+    /// Each is bound to an unknown (named as in [`Entity::unknown_attr()`]),
+    /// so partial evaluation over the entity produces residuals for them.
+    #[serde(
+        default,
+        rename = "unknownAttrs",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    unknown_attrs: Vec<SmolStr>,
 }
 
 /// Struct used to parse entities from JSON.
@@ -69,6 +80,10 @@ pub struct EntityJsonParser<'e, 's, S: Schema = NoEntitiesSchema> {
     /// Whether to compute, enforce, or assume TC for entities parsed using this
     /// parser.
     tc_computation: TCComputation,
+
+    /// How entities with the same UID are handled, see
+    /// [`EntityJsonParser::with_duplicates()`]
+    duplicates: DuplicateEntities,
 }
 
 /// Schema information about a single entity can take one of these forms:
@@ -112,9 +127,16 @@ impl<'e, 's, S: Schema> EntityJsonParser<'e, 's, S> {
             schema,
             extensions,
             tc_computation,
+            duplicates: DuplicateEntities::Reject,
         }
     }
 
+    /// Set how entities with the same UID are handled. By default, they are
+    /// rejected with [`EntitiesError::Duplicate`].
+    pub fn with_duplicates(self, duplicates: DuplicateEntities) -> Self {
+        Self { duplicates, ..self }
+    }
+
     /// Parse an entities JSON file (in [`&str`] form) into an [`Entities`] object.
     ///
     /// If the `EntityJsonParser` has a `schema`, this also adds `Action`
@@ -145,6 +167,111 @@ impl<'e, 's, S: Schema> EntityJsonParser<'e, 's, S> {
         self.parse_ejsons(ejsons)
     }
 
+    /// Parse an entities JSON file (in [`std::io::Read`] form) into an
+    /// [`Entities`] object, one entity at a time.
+    ///
+    /// Unlike [`EntityJsonParser::from_json_file()`], this never holds the JSON
+    /// of more than one entity in memory: each entity is parsed, checked
+    /// against the `schema` (if any), and inserted as soon as it is read, so
+    /// memory use is bounded by the size of the resulting [`Entities`]. The
+    /// result is the same, except that errors in entities are reported even if
+    /// the JSON after them is malformed.
+    ///
+    /// If the `EntityJsonParser` has a `schema`, this also adds `Action`
+    /// entities declared in the `schema`.
+    pub fn from_json_stream(&self, json: impl std::io::Read) -> Result<Entities, EntitiesError> {
+        let deserializer = serde_json::Deserializer::from_reader(std::io::BufReader::new(json));
+        self.from_json_deserializer(deserializer, None)
+    }
+
+    /// Parse an entities JSON file (in [`&str`] form) into an [`Entities`]
+    /// object, skipping entities which fail to parse or don't conform to the
+    /// `schema` instead of failing.
+    ///
+    /// Returns the [`Entities`] with all the other entities, and an error for
+    /// each skipped entity. It still fails if `json` is not a JSON array, or is
+    /// malformed, or if the entity hierarchy is invalid.
+    ///
+    /// If the `EntityJsonParser` has a `schema`, this also adds `Action`
+    /// entities declared in the `schema`.
+    pub fn from_json_str_collecting_errors(
+        &self,
+        json: &str,
+    ) -> Result<(Entities, Vec<SkippedEntityError>), EntitiesError> {
+        let mut skipped = Vec::new();
+        let deserializer = serde_json::Deserializer::from_str(json);
+        let entities = self.from_json_deserializer(deserializer, Some(&mut skipped))?;
+        Ok((entities, skipped))
+    }
+
+    /// Parse an entities JSON file (in [`serde_json::Value`] form) into an
+    /// [`Entities`] object, skipping entities which fail to parse or don't
+    /// conform to the `schema` instead of failing, as in
+    /// [`EntityJsonParser::from_json_str_collecting_errors()`].
+    pub fn from_json_value_collecting_errors(
+        &self,
+        json: serde_json::Value,
+    ) -> Result<(Entities, Vec<SkippedEntityError>), EntitiesError> {
+        let jsons: Vec<serde_json::Value> =
+            serde_json::from_value(json).map_err(JsonDeserializationError::from)?;
+        let checker = self.checker();
+        let mut entity_map = HashMap::new();
+        let skipped = jsons
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, json)| {
+                self.insert_json_value(index, json, checker.as_ref(), &mut entity_map)
+                    .err()
+            })
+            .collect();
+        let entities =
+            Entities::from_validated_entity_map(entity_map, self.schema, self.tc_computation)?;
+        Ok((entities, skipped))
+    }
+
+    /// Parse an entities JSON file (in [`std::io::Read`] form) into an
+    /// [`Entities`] object, skipping entities which fail to parse or don't
+    /// conform to the `schema` instead of failing, as in
+    /// [`EntityJsonParser::from_json_str_collecting_errors()`].
+    ///
+    /// Like [`EntityJsonParser::from_json_stream()`], this reads one entity at
+    /// a time.
+    pub fn from_json_file_collecting_errors(
+        &self,
+        json: impl std::io::Read,
+    ) -> Result<(Entities, Vec<SkippedEntityError>), EntitiesError> {
+        let mut skipped = Vec::new();
+        let deserializer = serde_json::Deserializer::from_reader(std::io::BufReader::new(json));
+        let entities = self.from_json_deserializer(deserializer, Some(&mut skipped))?;
+        Ok((entities, skipped))
+    }
+
+    /// Internal function that creates an [`Entities`] from the JSON array of
+    /// entities read by `deserializer`, one entity at a time.
+    ///
+    /// If `skipped` is present, entities which fail to parse or validate are
+    /// reported there instead of failing.
+    fn from_json_deserializer<'de, R: serde_json::de::Read<'de>>(
+        &self,
+        mut deserializer: serde_json::Deserializer<R>,
+        skipped: Option<&mut Vec<SkippedEntityError>>,
+    ) -> Result<Entities, EntitiesError> {
+        let mut error = None;
+        let visitor = EntitiesStreamVisitor {
+            parser: self,
+            error: &mut error,
+            skipped,
+        };
+        let entity_map = match deserializer.deserialize_seq(visitor) {
+            Ok(entity_map) => entity_map,
+            Err(err) => {
+                return Err(error.unwrap_or_else(|| JsonDeserializationError::from(err).into()))
+            }
+        };
+        deserializer.end().map_err(JsonDeserializationError::from)?;
+        Entities::from_validated_entity_map(entity_map, self.schema, self.tc_computation)
+    }
+
     /// Parse an entities JSON file (in [`&str`] form) into an iterator over [`Entity`]s.
     ///
     /// If the `EntityJsonParser` has a `schema`, this also adds `Action`
@@ -184,6 +311,23 @@ impl<'e, 's, S: Schema> EntityJsonParser<'e, 's, S> {
         self.iter_ejson_to_iter_entity(ejsons)
     }
 
+    /// Parse the JSON of a single entity (in `&[u8]` form), e.g., a record of
+    /// an entity store, and check that it conforms to the `schema`, if there
+    /// is one.
+    ///
+    /// Unlike parsing an entities JSON file, this doesn't add the `Action`
+    /// entities declared in the `schema`, and the ancestors of the entity are
+    /// only its parents.
+    pub fn single_from_json_slice(&self, json: &[u8]) -> Result<Entity, EntitiesError> {
+        let ejson: EntityJson =
+            serde_json::from_slice(json).map_err(JsonDeserializationError::from)?;
+        let entity = self.parse_ejson(ejson)?;
+        if let Some(checker) = self.checker() {
+            checker.validate_entity(&entity)?;
+        }
+        Ok(entity)
+    }
+
     /// Internal function that converts an iterator over [`EntityJson`] into an
     /// iterator over [`Entity`] and also adds any `Action` entities declared in
     /// `self.schema`.
@@ -214,7 +358,59 @@ impl<'e, 's, S: Schema> EntityJsonParser<'e, 's, S> {
             .into_iter()
             .map(|ejson| self.parse_ejson(ejson))
             .collect::<Result<_, _>>()?;
-        Entities::from_entities(entities, self.schema, self.tc_computation, self.extensions)
+        Entities::from_entities_with_duplicates(
+            entities,
+            self.schema,
+            self.tc_computation,
+            self.duplicates,
+            self.extensions,
+        )
+    }
+
+    /// Internal function that creates a checker for conformance of the parsed
+    /// entities with `self.schema`, if there is one.
+    fn checker(&self) -> Option<EntitySchemaConformanceChecker<'_, S>> {
+        self.schema
+            .map(|schema| EntitySchemaConformanceChecker::new(schema, self.extensions))
+    }
+
+    /// Internal function that parses an `EntityJson` into an `Entity`, checks
+    /// it with `checker` (if any), and inserts it into `entity_map`.
+    fn insert_ejson(
+        &self,
+        ejson: EntityJson,
+        checker: Option<&EntitySchemaConformanceChecker<'_, S>>,
+        entity_map: &mut HashMap<EntityUID, Arc<Entity>>,
+    ) -> Result<(), EntitiesError> {
+        let entity = self.parse_ejson(ejson)?;
+        if let Some(checker) = checker {
+            checker.validate_entity(&entity)?;
+        }
+        insert_entity(entity_map, entity, self.duplicates)
+    }
+
+    /// Internal function like `insert_ejson()`, for the entity at position
+    /// `index` in the JSON array of entities, in [`serde_json::Value`] form.
+    /// If this fails, the error includes the uid of the entity, if it can be
+    /// parsed.
+    fn insert_json_value(
+        &self,
+        index: usize,
+        json: serde_json::Value,
+        checker: Option<&EntitySchemaConformanceChecker<'_, S>>,
+        entity_map: &mut HashMap<EntityUID, Arc<Entity>>,
+    ) -> Result<(), SkippedEntityError> {
+        let uid = json
+            .get("uid")
+            .and_then(|uid| EntityUidJson::deserialize(uid).ok())
+            .and_then(|uid| {
+                uid.into_euid(|| JsonDeserializationErrorContext::EntityUid)
+                    .ok()
+            });
+        serde_json::from_value(json)
+            .map_err(|err| JsonDeserializationError::from(err).into())
+            .and_then(|ejson| self.insert_ejson(ejson, checker, entity_map))
+            .map_err(|err| SkippedEntityError { index, uid, err })
     }
 
     /// Internal function that parses an `EntityJson` into an `Entity`.
@@ -255,7 +451,7 @@ impl<'e, 's, S: Schema> EntityJsonParser<'e, 's, S> {
             }
         };
         let vparser = ValueParser::new(self.extensions);
-        let attrs: HashMap<SmolStr, RestrictedExpr> = ejson
+        let mut attrs: HashMap<SmolStr, RestrictedExpr> = ejson
             .attrs
             .into_iter()
             .map(|(k, v)| match &entity_schema_info {
@@ -357,6 +553,39 @@ impl<'e, 's, S: Schema> EntityJsonParser<'e, 's, S> {
                 }
             })
             .collect::<Result<_, JsonDeserializationError>>()?;
+        for attr in ejson.unknown_attrs {
+            match &entity_schema_info {
+                EntitySchemaInfo::NoSchema => {}
+                EntitySchemaInfo::NonAction(desc) => {
+                    // `None` indicates the attribute shouldn't exist -- see
+                    // docs on the `attr_type()` trait method
+                    if desc.attr_type(&attr).is_none() && !desc.open_attributes() {
+                        return Err(JsonDeserializationError::EntitySchemaConformance(
+                            EntitySchemaConformanceError::UnexpectedEntityAttr {
+                                uid: uid.clone(),
+                                attr,
+                            },
+                        ));
+                    }
+                }
+                EntitySchemaInfo::Action(_) => {
+                    // action attributes are fully determined by the schema
+                    return Err(JsonDeserializationError::EntitySchemaConformance(
+                        EntitySchemaConformanceError::ActionDeclarationMismatch {
+                            uid: uid.clone(),
+                        },
+                    ));
+                }
+            }
+            if attrs.contains_key(&attr) {
+                return Err(JsonDeserializationError::DuplicateEntityAttr {
+                    uid: uid.clone(),
+                    attr,
+                });
+            }
+            let unknown = RestrictedExpr::unknown(Entity::unknown_attr(&uid, &attr));
+            attrs.insert(attr, unknown);
+        }
         let is_parent_allowed = |parent_euid: &EntityUID| {
             // full validation isn't done in this function (see doc comments on
             // this function), but we do need to do the following check which
@@ -393,16 +622,82 @@ impl<'e, 's, S: Schema> EntityJsonParser<'e, 's, S> {
     }
 }
 
+/// Visitor for the JSON array of entities in
+/// [`EntityJsonParser::from_json_deserializer()`], which parses, validates,
+/// and inserts each entity as soon as it is read
+struct EntitiesStreamVisitor<'p, 'e, 's, S: Schema> {
+    /// Parser for the individual entities
+    parser: &'p EntityJsonParser<'e, 's, S>,
+    /// The error which stopped the parsing, if it was not a JSON error.
+    /// (serde errors can only carry a message, so the error itself is returned
+    /// through here.)
+    error: &'p mut Option<EntitiesError>,
+    /// If present, entities which fail to parse or validate are reported here,
+    /// instead of stopping the parsing
+    skipped: Option<&'p mut Vec<SkippedEntityError>>,
+}
+
+impl<'de, 'p, 'e, 's, S: Schema> Visitor<'de> for EntitiesStreamVisitor<'p, 'e, 's, S> {
+    type Value = HashMap<EntityUID, Arc<Entity>>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "a sequence of entities")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let checker = self.parser.checker();
+        let mut entity_map = HashMap::new();
+        match self.skipped {
+            None => {
+                while let Some(ejson) = seq.next_element::<EntityJson>()? {
+                    if let Err(err) =
+                        self.parser
+                            .insert_ejson(ejson, checker.as_ref(), &mut entity_map)
+                    {
+                        let msg = err.to_string();
+                        *self.error = Some(err);
+                        return Err(A::Error::custom(msg));
+                    }
+                }
+            }
+            Some(skipped) => {
+                // each entity is first read as a JSON value, so that an entity
+                // of the wrong shape can be skipped
+                let mut index = 0;
+                while let Some(json) = seq.next_element::<JsonValueWithNoDuplicateKeys>()? {
+                    if let Err(err) = self.parser.insert_json_value(
+                        index,
+                        json.into(),
+                        checker.as_ref(),
+                        &mut entity_map,
+                    ) {
+                        skipped.push(err);
+                    }
+                    index += 1;
+                }
+            }
+        }
+        Ok(entity_map)
+    }
+}
+
 impl EntityJson {
     /// Convert an `Entity` into an `EntityJson`
     ///
     /// (for the reverse transformation, use `EntityJsonParser`)
     pub fn from_entity(entity: &Entity) -> Result<Self, JsonSerializationError> {
+        // attributes bound to their own unknown (see `Entity::unknown_attr()`)
+        // are encoded in `unknownAttrs`
+        let uid = entity.uid();
+        let (unknown_attrs, attrs): (Vec<_>, Vec<_>) = entity.attrs().partition(|(k, pvalue)| {
+            matches!(pvalue, PartialValue::Residual(expr)
+                if expr.expr_kind() == &ExprKind::Unknown(Entity::unknown_attr(&uid, k)))
+        });
         Ok(Self {
             // for now, we encode `uid` and `parents` using an implied `__entity` escape
-            uid: EntityUidJson::ImplicitEntityEscape(TypeAndId::from(entity.uid())),
-            attrs: entity
-                .attrs()
+            uid: EntityUidJson::ImplicitEntityEscape(TypeAndId::from(uid)),
+            attrs: attrs
+                .into_iter()
                 .map(|(k, pvalue)| match pvalue {
                     PartialValue::Value(value) => {
                         let cedarvaluejson = CedarValueJson::from_value(value.clone())?;
@@ -423,6 +718,7 @@ impl EntityJson {
                 .ancestors()
                 .map(|euid| EntityUidJson::ImplicitEntityEscape(TypeAndId::from(euid.clone())))
                 .collect(),
+            unknown_attrs: unknown_attrs.into_iter().map(|(k, _)| k.clone()).collect(),
         })
     }
 }
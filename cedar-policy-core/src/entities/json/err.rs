@@ -53,10 +53,11 @@ impl Display for EscapeKind {
 pub enum JsonDeserializationError {
     /// Error thrown by the `serde_json` crate
     #[error(transparent)]
+    #[diagnostic(code(cedar::json::invalid_json))]
     Serde(#[from] serde_json::Error),
     /// Contents of an escape failed to parse.
     #[error("failed to parse escape `{kind}`: {value}, errors: {errs}")]
-    #[diagnostic(help("{}", match .kind {
+    #[diagnostic(code(cedar::json::invalid_escape), help("{}", match .kind {
         EscapeKind::Entity => r#"an __entity escape should have a value like `{ "type": "SomeType", "id": "SomeId" }`"#,
         EscapeKind::Extension => r#"an __extn escape should have a value like `{ "fn": "SomeFn", "arg": "SomeArg" }`"#,
     }))]
@@ -75,7 +76,7 @@ pub enum JsonDeserializationError {
     RestrictedExpressionError(#[from] RestrictedExprError),
     /// A field that needs to be a literal entity reference, was some other JSON value
     #[error("{ctx}, expected a literal entity reference, but got `{}`", display_json_value(.got.as_ref()))]
-    #[diagnostic(help(
+    #[diagnostic(code(cedar::json::expected_literal_entity_ref), help(
         r#"literal entity references can be made with `{{ "type": "SomeType", "id": "SomeId" }}`"#
     ))]
     ExpectedLiteralEntityRef {
@@ -86,9 +87,10 @@ pub enum JsonDeserializationError {
     },
     /// A field that needs to be an extension value, was some other JSON value
     #[error("{ctx}, expected an extension value, but got `{}`", display_json_value(.got.as_ref()))]
-    #[diagnostic(help(
-        r#"extension values can be made with `{{ "fn": "SomeFn", "id": "SomeId" }}`"#
-    ))]
+    #[diagnostic(
+        code(cedar::json::expected_extn_value),
+        help(r#"extension values can be made with `{{ "fn": "SomeFn", "id": "SomeId" }}`"#)
+    )]
     ExpectedExtnValue {
         /// Context of this error
         ctx: Box<JsonDeserializationErrorContext>,
@@ -101,19 +103,35 @@ pub enum JsonDeserializationError {
     ContextCreation(#[from] ContextCreationError),
     /// Parents of actions should be actions, but this action has a non-action parent
     #[error("action `{uid}` has a non-action parent `{parent}`")]
-    #[diagnostic(help(
-        "parents of actions need to have type `Action` themselves, perhaps namespaced"
-    ))]
+    #[diagnostic(
+        code(cedar::json::action_parent_is_not_action),
+        help("parents of actions need to have type `Action` themselves, perhaps namespaced")
+    )]
     ActionParentIsNotAction {
         /// Action entity that had the invalid parent
         uid: EntityUID,
         /// Parent that is invalid
         parent: EntityUID,
     },
+    /// An attribute was given more than once across an entity's `attrs` and
+    /// `unknownAttrs`
+    #[error("attribute `{attr}` of `{uid}` is given more than once in `attrs` and `unknownAttrs`")]
+    #[diagnostic(code(cedar::json::duplicate_entity_attr), help(
+        "an attribute can either have a value in `attrs` or be declared in `unknownAttrs`, not both"
+    ))]
+    DuplicateEntityAttr {
+        /// Entity which had the duplicate attribute
+        uid: EntityUID,
+        /// Name of the duplicate attribute
+        attr: SmolStr,
+    },
     /// Schema-based parsing needed an implicit extension constructor, but no suitable
     /// constructor was found
     #[error("{ctx}, missing extension constructor for {arg_type} -> {return_type}")]
-    #[diagnostic(help("expected a value of type {return_type} because of the schema"))]
+    #[diagnostic(
+        code(cedar::json::missing_implied_constructor),
+        help("expected a value of type {return_type} because of the schema")
+    )]
     MissingImpliedConstructor {
         /// Context of this error
         ctx: Box<JsonDeserializationErrorContext>,
@@ -124,6 +142,7 @@ pub enum JsonDeserializationError {
     },
     /// The same key appears two or more times in a single record literal
     #[error("{ctx}, duplicate key `{key}` in record literal")]
+    #[diagnostic(code(cedar::json::duplicate_key_in_record_literal))]
     DuplicateKeyInRecordLiteral {
         /// Context of this error
         ctx: Box<JsonDeserializationErrorContext>,
@@ -145,6 +164,7 @@ pub enum JsonDeserializationError {
     /// During schema-based parsing, encountered this attribute on a record, but
     /// that attribute shouldn't exist on that record
     #[error("{ctx}, record attribute `{record_attr}` should not exist according to the schema")]
+    #[diagnostic(code(cedar::json::unexpected_record_attr))]
     UnexpectedRecordAttr {
         /// Context of this error
         ctx: Box<JsonDeserializationErrorContext>,
@@ -154,6 +174,7 @@ pub enum JsonDeserializationError {
     /// During schema-based parsing, didn't encounter this attribute of a
     /// record, but that attribute should have existed
     #[error("{ctx}, expected the record to have an attribute `{record_attr}`, but it does not")]
+    #[diagnostic(code(cedar::json::missing_required_record_attr))]
     MissingRequiredRecordAttr {
         /// Context of this error
         ctx: Box<JsonDeserializationErrorContext>,
@@ -167,6 +188,7 @@ pub enum JsonDeserializationError {
     /// this writing, that means this should only be used for schema-based
     /// parsing of the `Context`.)
     #[error("{ctx}, {err}")]
+    #[diagnostic(code(cedar::json::type_mismatch))]
     TypeMismatch {
         /// Context of this error, which will be something other than `EntityAttribute`.
         /// (Type mismatches in entity attributes are reported as
@@ -185,6 +207,7 @@ pub enum JsonDeserializationError {
     /// should only be used for schema-based parsing of the `Context`. Note that
     /// for non-schema-based parsing, heterogeneous sets are not an error.)
     #[error("{ctx}, {err}")]
+    #[diagnostic(code(cedar::json::heterogeneous_set))]
     HeterogeneousSet {
         /// Context of this error, which will be something other than `EntityAttribute`.
         /// (Heterogeneous sets in entity attributes are reported as
@@ -204,6 +227,7 @@ pub enum JsonDeserializationError {
     /// `Self::EntitySchemaConformance`. As of this writing, that means this
     /// should only be used for schema-based parsing of the `Context`.)
     #[error("{ctx}, {err}")]
+    #[diagnostic(code(cedar::json::extension_function_lookup))]
     ExtensionFunctionLookup {
         /// Context of this error, which will be something other than
         /// `EntityAttribute`.
@@ -220,7 +244,7 @@ pub enum JsonDeserializationError {
     /// To pass an unknown to an extension function, use the
     /// explicit-constructor form.
     #[error("{ctx}, argument `{arg}` to implicit constructor contains an unknown; this is not currently supported")]
-    #[diagnostic(help(
+    #[diagnostic(code(cedar::json::unknown_in_implicit_constructor_arg), help(
         r#"expected an extension value here because of the schema. To pass an unknown to an extension function, use the explicit constructor form: `{{ "fn": "SomeFn", "arg": "SomeArg" }}`"#
     ))]
     UnknownInImplicitConstructorArg {
@@ -231,7 +255,7 @@ pub enum JsonDeserializationError {
     },
     /// Raised when a JsonValue contains the no longer supported `__expr` escape
     #[error("{0}, the `__expr` escape is no longer supported")]
-    #[diagnostic(help("to create an entity reference, use `__entity`; to create an extension value, use `__extn`; and for all other values, use JSON directly"))]
+    #[diagnostic(code(cedar::json::expr_tag), help("to create an entity reference, use `__entity`; to create an extension value, use `__extn`; and for all other values, use JSON directly"))]
     ExprTag(Box<JsonDeserializationErrorContext>),
 }
 
@@ -240,11 +264,12 @@ pub enum JsonDeserializationError {
 pub enum JsonSerializationError {
     /// Error thrown by `serde_json`
     #[error(transparent)]
+    #[diagnostic(code(cedar::json::serialization))]
     Serde(#[from] serde_json::Error),
     /// Extension-function calls with 0 arguments are not currently supported in
     /// our JSON format.
     #[error("unsupported call to `{func}` with 0 arguments")]
-    #[diagnostic(help(
+    #[diagnostic(code(cedar::json::extension_call_0_arguments), help(
         "extension function calls with 0 arguments are not currently supported in our JSON format"
     ))]
     ExtnCall0Arguments {
@@ -254,7 +279,7 @@ pub enum JsonSerializationError {
     /// Extension-function calls with 2 or more arguments are not currently
     /// supported in our JSON format.
     #[error("unsupported call to `{func}` with 2 or more arguments")]
-    #[diagnostic(help("extension function calls with 2 or more arguments are not currently supported in our JSON format"))]
+    #[diagnostic(code(cedar::json::extension_call_2_or_more_arguments), help("extension function calls with 2 or more arguments are not currently supported in our JSON format"))]
     ExtnCall2OrMoreArguments {
         /// Name of the function which was called with 2 or more arguments
         func: Name,
@@ -262,6 +287,7 @@ pub enum JsonSerializationError {
     /// Encountered a `Record` which can't be serialized to JSON because it
     /// contains a key which is reserved as a JSON escape.
     #[error("record uses reserved key `{key}`")]
+    #[diagnostic(code(cedar::json::reserved_key))]
     ReservedKey {
         /// Reserved key which was used by the `Record`
         key: SmolStr,
@@ -270,6 +296,7 @@ pub enum JsonSerializationError {
     /// missing in `CedarValueJson::from_expr()`, or an internal invariant was
     /// violated and there is a non-restricted expression in `RestrictedExpr`
     #[error("unexpected restricted expression `{kind:?}`")]
+    #[diagnostic(code(cedar::json::unexpected_restricted_expr_kind))]
     UnexpectedRestrictedExprKind {
         /// `ExprKind` which we didn't expect to find
         kind: ExprKind,
@@ -277,6 +304,7 @@ pub enum JsonSerializationError {
     /// Encountered a (partial-evaluation) residual which can't be encoded in
     /// JSON
     #[error("cannot encode residual as JSON: {residual}")]
+    #[diagnostic(code(cedar::json::residual))]
     Residual {
         /// Residual which can't be encoded in JSON
         residual: Expr,
@@ -21,12 +21,13 @@ use crate::ast::{
 use crate::extensions::{ExtensionFunctionLookupError, Extensions};
 use itertools::Itertools;
 use miette::Diagnostic;
+use serde::{Deserialize, Serialize};
 use smol_str::SmolStr;
 use std::collections::HashMap;
 use thiserror::Error;
 
 /// Possible types that schema-based parsing can expect for Cedar values.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub enum SchemaType {
     /// Boolean
     Bool,
@@ -64,7 +65,7 @@ pub enum SchemaType {
 }
 
 /// Attribute type structure used in [`SchemaType`]
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct AttributeType {
     /// Type of the attribute
     attr_type: SchemaType,
@@ -275,6 +276,7 @@ pub enum GetSchemaTypeError {
     /// contains an [`Unknown`] that has insufficient type information
     /// associated in order to compute the `SchemaType`
     #[error("can't compute SchemaType because of insufficient type information for `{unknown}`")]
+    #[diagnostic(code(cedar::request::unknown_insufficient_type_info))]
     UnknownInsufficientTypeInfo {
         /// `Unknown` which has insufficient type information
         unknown: Unknown,
@@ -284,6 +286,7 @@ pub enum GetSchemaTypeError {
     /// attempt to compute the [`SchemaType`] in these cases, and just return
     /// this error.
     #[error("can't compute SchemaType of nontrivial residual `{residual}`")]
+    #[diagnostic(code(cedar::request::nontrivial_residual))]
     NontrivialResidual {
         /// Nontrivial residual which we were trying to compute the
         /// [`SchemaType`] of
@@ -295,7 +298,10 @@ pub enum GetSchemaTypeError {
 /// any possible schema.
 #[derive(Debug, Diagnostic, Error)]
 #[error("set elements have different types: {ty1} and {ty2}")]
-#[diagnostic(help("for sets declared in a schema, set elements must all have the same type"))]
+#[diagnostic(
+    code(cedar::request::heterogeneous_set),
+    help("for sets declared in a schema, set elements must all have the same type")
+)]
 pub struct HeterogeneousSetError {
     /// First element type which was found
     ty1: Box<SchemaType>,
@@ -28,6 +28,7 @@ use crate::ast;
 use crate::entities::EntityUidJson;
 use crate::parser::cst;
 use crate::parser::err::{ParseErrors, ToASTError, ToASTErrorKind};
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use smol_str::SmolStr;
@@ -69,6 +70,11 @@ pub enum Clause {
 }
 
 impl Policy {
+    /// The `when` and `unless` clauses of the policy, in order
+    pub fn conditions(&self) -> &[Clause] {
+        &self.conditions
+    }
+
     /// Fill in any slots in the policy using the values in `vals`. Throws an
     /// error if `vals` doesn't contain a necessary mapping, but does not throw
     /// an error if `vals` contains unused mappings -- and in particular if
@@ -294,7 +300,12 @@ impl From<ast::Expr> for Clause {
 
 impl std::fmt::Display for Policy {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for (k, v) in self.annotations.iter() {
+        // sorted so that the output doesn't depend on `HashMap` iteration order
+        for (k, v) in self
+            .annotations
+            .iter()
+            .sorted_by(|(k1, _), (k2, _)| k1.cmp(k2))
+        {
             writeln!(f, "@{k}(\"{}\") ", v.escape_debug())?;
         }
         write!(
@@ -2120,6 +2131,54 @@ mod test {
         assert_eq!(circular_roundtrip(est.clone()), est);
     }
 
+    #[test]
+    fn raw_strings() {
+        let est = |policy: &str| -> Policy {
+            parser::text_to_cst::parse_policy(policy)
+                .unwrap()
+                .node
+                .unwrap()
+                .try_into()
+                .unwrap()
+        };
+        let raw = est(r###"
+            permit(principal, action, resource)
+            when { resource.path like r"C:\docs\*" && context.json == r#"{"a": "b\n"}"# };
+        "###);
+        let escaped = est(r#"
+            permit(principal, action, resource)
+            when { resource.path like "C:\\docs\\*" && context.json == "{\"a\": \"b\\n\"}" };
+        "#);
+        assert_eq!(raw, escaped);
+        assert_eq!(ast_roundtrip(raw), ast_roundtrip(escaped));
+    }
+
+    #[test]
+    fn has_attribute_paths() {
+        let est = |policy: &str| -> Policy {
+            parser::text_to_cst::parse_policy(policy)
+                .unwrap()
+                .node
+                .unwrap()
+                .try_into()
+                .unwrap()
+        };
+        let path = est(r#"
+            permit(principal, action, resource)
+            when { principal has contactInfo.address.zip };
+        "#);
+        let chain = est(r#"
+            permit(principal, action, resource)
+            when {
+                principal has contactInfo &&
+                principal.contactInfo has address &&
+                principal.contactInfo.address has zip
+            };
+        "#);
+        assert_eq!(path, chain);
+        assert_eq!(ast_roundtrip(path), ast_roundtrip(chain));
+    }
+
     #[test]
     fn decimal() {
         let policy = r#"
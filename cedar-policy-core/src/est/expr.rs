@@ -859,7 +859,22 @@ impl TryFrom<&Node<Option<cst::Relation>>> for Expr {
                 Ok(expr)
             }
             cst::Relation::Has { target, field } => {
-                let target_expr = target.try_into()?;
+                let target_expr: Expr = target.try_into()?;
+                if let Some(path) = field.to_has_path() {
+                    // `e has a.b.c` is `e has a && e.a has b && e.a.b has c`
+                    let attr = |ident: &Node<Option<cst::Ident>>| -> Result<SmolStr, ParseErrors> {
+                        Ok(ident.ok_or_missing()?.to_string().into())
+                    };
+                    let head = attr(path.head)?;
+                    let mut expr = Expr::has_attr(target_expr.clone(), head.clone());
+                    let mut target = Expr::get_attr(target_expr, head);
+                    for ident in path.tail {
+                        let attr = attr(ident)?;
+                        expr = Expr::and(expr, Expr::has_attr(target.clone(), attr.clone()));
+                        target = Expr::get_attr(target, attr);
+                    }
+                    return Ok(expr);
+                }
                 match Expr::try_from(field) {
                     Ok(field_expr) => {
                         let field_str = field_expr
@@ -1304,7 +1319,9 @@ impl TryFrom<&Node<Option<cst::Literal>>> for Expr {
                     .map_err(|_| lit.to_ast_err(ToASTErrorKind::IntegerLiteralTooLarge(*n)))?,
             ))),
             cst::Literal::Str(node) => match node.ok_or_missing()? {
-                cst::Str::String(s) => Ok(Expr::lit(CedarValueJson::String(s.clone()))),
+                cst::Str::String(s) | cst::Str::Raw { escaped: s, .. } => {
+                    Ok(Expr::lit(CedarValueJson::String(s.clone())))
+                }
                 cst::Str::Invalid(invalid_str) => Err(node
                     .to_ast_err(ToASTErrorKind::InvalidString(invalid_str.to_string()))
                     .into()),
@@ -233,7 +233,15 @@ impl<'e> Evaluator<'e> {
     /// May return a residual expression, if the input expression is symbolic.
     /// May return an error, for instance if the `Expr` tries to access an
     /// attribute that doesn't exist.
+    ///
+    /// Errors are annotated with the source location of the innermost
+    /// expression whose evaluation failed, if it has one.
     pub fn partial_interpret(&self, e: &Expr, slots: &SlotEnv) -> Result<PartialValue> {
+        self.partial_interpret_internal(e, slots)
+            .map_err(|err| err.with_maybe_source_loc(e.source_loc()))
+    }
+
+    fn partial_interpret_internal(&self, e: &Expr, slots: &SlotEnv) -> Result<PartialValue> {
         stack_size_check()?;
 
         match e.expr_kind() {
@@ -347,13 +355,30 @@ impl<'e> Evaluator<'e> {
                 };
                 match op {
                     BinaryOp::Eq => Ok((arg1 == arg2).into()),
-                    // comparison and arithmetic operators, which only work on Longs
-                    BinaryOp::Less | BinaryOp::LessEq | BinaryOp::Add | BinaryOp::Sub => {
+                    // comparison operators, which work on two Longs, or on two
+                    // Strings, which are ordered lexicographically by their
+                    // code points
+                    BinaryOp::Less | BinaryOp::LessEq => {
+                        let ordering = match &arg1 {
+                            Value::Lit(Literal::Long(i1)) => i1.cmp(&arg2.get_as_long()?),
+                            Value::Lit(Literal::String(s1)) => s1.cmp(arg2.get_as_string()?),
+                            _ => {
+                                return Err(EvaluationError::type_error(
+                                    nonempty![Type::Long, Type::String],
+                                    arg1.type_of(),
+                                ))
+                            }
+                        };
+                        match op {
+                            BinaryOp::Less => Ok(ordering.is_lt().into()),
+                            _ => Ok(ordering.is_le().into()),
+                        }
+                    }
+                    // arithmetic operators, which only work on Longs
+                    BinaryOp::Add | BinaryOp::Sub => {
                         let i1 = arg1.get_as_long()?;
                         let i2 = arg2.get_as_long()?;
                         match op {
-                            BinaryOp::Less => Ok((i1 < i2).into()),
-                            BinaryOp::LessEq => Ok((i1 <= i2).into()),
                             BinaryOp::Add => match i1.checked_add(i2) {
                                 Some(sum) => Ok(sum.into()),
                                 None => Err(IntegerOverflowError::BinaryOp {
@@ -601,7 +626,7 @@ impl<'e> Evaluator<'e> {
         for uid2 in rhs {
             if uid1 == &uid2
                 || entity1
-                    .map(|e1| e1.is_descendant_of(&uid2))
+                    .map(|e1| self.entities.is_descendant_of(e1, &uid2))
                     .unwrap_or(false)
             {
                 return Ok(true.into());
@@ -2327,97 +2352,121 @@ pub mod test {
         // false < true
         assert_eq!(
             eval.interpret_inline_policy(&Expr::less(Expr::val(false), Expr::val(true))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::Bool))
+            Err(EvaluationError::type_error(
+                nonempty![Type::Long, Type::String],
+                Type::Bool
+            ))
         );
         // false < false
         assert_eq!(
             eval.interpret_inline_policy(&Expr::less(Expr::val(false), Expr::val(false))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::Bool))
+            Err(EvaluationError::type_error(
+                nonempty![Type::Long, Type::String],
+                Type::Bool
+            ))
         );
         // true <= false
         assert_eq!(
             eval.interpret_inline_policy(&Expr::lesseq(Expr::val(true), Expr::val(false))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::Bool))
+            Err(EvaluationError::type_error(
+                nonempty![Type::Long, Type::String],
+                Type::Bool
+            ))
         );
         // false <= false
         assert_eq!(
             eval.interpret_inline_policy(&Expr::lesseq(Expr::val(false), Expr::val(false))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::Bool))
+            Err(EvaluationError::type_error(
+                nonempty![Type::Long, Type::String],
+                Type::Bool
+            ))
         );
         // false > true
         assert_eq!(
             eval.interpret_inline_policy(&Expr::greater(Expr::val(false), Expr::val(true))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::Bool))
+            Err(EvaluationError::type_error(
+                nonempty![Type::Long, Type::String],
+                Type::Bool
+            ))
         );
         // true > true
         assert_eq!(
             eval.interpret_inline_policy(&Expr::greater(Expr::val(true), Expr::val(true))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::Bool))
+            Err(EvaluationError::type_error(
+                nonempty![Type::Long, Type::String],
+                Type::Bool
+            ))
         );
         // true >= false
         assert_eq!(
             eval.interpret_inline_policy(&Expr::greatereq(Expr::val(true), Expr::val(false))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::Bool))
+            Err(EvaluationError::type_error(
+                nonempty![Type::Long, Type::String],
+                Type::Bool
+            ))
         );
         // true >= true
         assert_eq!(
             eval.interpret_inline_policy(&Expr::greatereq(Expr::val(true), Expr::val(true))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::Bool))
+            Err(EvaluationError::type_error(
+                nonempty![Type::Long, Type::String],
+                Type::Bool
+            ))
         );
         // bc < zzz
         assert_eq!(
             eval.interpret_inline_policy(&Expr::less(Expr::val("bc"), Expr::val("zzz"))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::String))
+            Ok(Value::Lit(Literal::Bool(true)))
         );
         // banana < zzz
         assert_eq!(
             eval.interpret_inline_policy(&Expr::less(Expr::val("banana"), Expr::val("zzz"))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::String))
+            Ok(Value::Lit(Literal::Bool(true)))
         );
         // "" < zzz
         assert_eq!(
             eval.interpret_inline_policy(&Expr::less(Expr::val(""), Expr::val("zzz"))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::String))
+            Ok(Value::Lit(Literal::Bool(true)))
         );
         // a < 1
         assert_eq!(
             eval.interpret_inline_policy(&Expr::less(Expr::val("a"), Expr::val("1"))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::String))
+            Ok(Value::Lit(Literal::Bool(false)))
         );
         // a < A
         assert_eq!(
             eval.interpret_inline_policy(&Expr::less(Expr::val("a"), Expr::val("A"))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::String))
+            Ok(Value::Lit(Literal::Bool(false)))
         );
         // A < A
         assert_eq!(
             eval.interpret_inline_policy(&Expr::less(Expr::val("A"), Expr::val("A"))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::String))
+            Ok(Value::Lit(Literal::Bool(false)))
         );
         // zebra < zebras
         assert_eq!(
             eval.interpret_inline_policy(&Expr::less(Expr::val("zebra"), Expr::val("zebras"))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::String))
+            Ok(Value::Lit(Literal::Bool(true)))
         );
         // zebra <= zebras
         assert_eq!(
             eval.interpret_inline_policy(&Expr::lesseq(Expr::val("zebra"), Expr::val("zebras"))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::String))
+            Ok(Value::Lit(Literal::Bool(true)))
         );
         // zebras <= zebras
         assert_eq!(
             eval.interpret_inline_policy(&Expr::lesseq(Expr::val("zebras"), Expr::val("zebras"))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::String))
+            Ok(Value::Lit(Literal::Bool(true)))
         );
         // zebras <= Zebras
         assert_eq!(
             eval.interpret_inline_policy(&Expr::lesseq(Expr::val("zebras"), Expr::val("Zebras"))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::String))
+            Ok(Value::Lit(Literal::Bool(false)))
         );
         // 123 > 78
         assert_eq!(
             eval.interpret_inline_policy(&Expr::greater(Expr::val("123"), Expr::val("78"))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::String))
+            Ok(Value::Lit(Literal::Bool(false)))
         );
         // <space>zebras >= zebras
         assert_eq!(
@@ -2425,22 +2474,22 @@ pub mod test {
                 Expr::val(" zebras"),
                 Expr::val("zebras")
             )),
-            Err(EvaluationError::type_error_single(Type::Long, Type::String))
+            Ok(Value::Lit(Literal::Bool(false)))
         );
         // "" >= ""
         assert_eq!(
             eval.interpret_inline_policy(&Expr::greatereq(Expr::val(""), Expr::val(""))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::String))
+            Ok(Value::Lit(Literal::Bool(true)))
         );
         // "" >= _hi
         assert_eq!(
             eval.interpret_inline_policy(&Expr::greatereq(Expr::val(""), Expr::val("_hi"))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::String))
+            Ok(Value::Lit(Literal::Bool(false)))
         );
         // 🦀 >= _hi
         assert_eq!(
             eval.interpret_inline_policy(&Expr::greatereq(Expr::val("🦀"), Expr::val("_hi"))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::String))
+            Ok(Value::Lit(Literal::Bool(true)))
         );
         // 2 < "4"
         assert_eq!(
@@ -2450,12 +2499,15 @@ pub mod test {
         // "4" < 2
         assert_eq!(
             eval.interpret_inline_policy(&Expr::less(Expr::val("4"), Expr::val(2))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::String))
+            Err(EvaluationError::type_error_single(Type::String, Type::Long))
         );
         // false < 1
         assert_eq!(
             eval.interpret_inline_policy(&Expr::less(Expr::val(false), Expr::val(1))),
-            Err(EvaluationError::type_error_single(Type::Long, Type::Bool))
+            Err(EvaluationError::type_error(
+                nonempty![Type::Long, Type::String],
+                Type::Bool
+            ))
         );
         // 1 < false
         assert_eq!(
@@ -2468,7 +2520,10 @@ pub mod test {
                 Expr::set(vec![Expr::val(1), Expr::val(2)]),
                 Expr::set(vec![Expr::val(47), Expr::val(0)])
             )),
-            Err(EvaluationError::type_error_single(Type::Long, Type::Set))
+            Err(EvaluationError::type_error(
+                nonempty![Type::Long, Type::String],
+                Type::Set
+            ))
         );
     }
 
@@ -4719,7 +4774,7 @@ pub mod test {
     fn parital_if_both_error() {
         let guard = Expr::get_attr(Expr::unknown(Unknown::new_untyped("a")), "field".into());
         let cons = Expr::binary_app(BinaryOp::Add, Expr::val(1), Expr::val(true));
-        let alt = Expr::less(Expr::val("hello"), Expr::val("bye"));
+        let alt = Expr::less(Expr::val("hello"), Expr::val(1));
         let e = Expr::ite(guard, cons, alt);
 
         let es = Entities::new();
@@ -5306,4 +5361,98 @@ pub mod test {
         );
         assert!(eval.partial_eval_expr(&e).is_err());
     }
+
+    #[test]
+    fn error_source_loc() {
+        let request = basic_request();
+        let entities = basic_entities();
+        let exts = Extensions::none();
+        let eval = Evaluator::new(request, &entities, &exts);
+        let src = r#"if true then 1 + "a" else 0"#;
+        let err = eval
+            .interpret_inline_policy(&parse_expr(src).expect("parsing error"))
+            .expect_err("should be a type error");
+        // the innermost failing expression, not the whole `if`
+        let loc = err.source_loc().expect("error should have a location");
+        assert_eq!(loc.span, miette::SourceSpan::from(13..20));
+        assert_eq!(&loc.src[13..20], r#"1 + "a""#);
+        // the location doesn't affect equality
+        assert_eq!(
+            err,
+            EvaluationError::type_error_single(Type::Long, Type::String)
+        );
+
+        // expressions without locations produce errors without locations
+        let err = eval
+            .interpret_inline_policy(&Expr::add(Expr::val(1), Expr::val("a")))
+            .expect_err("should be a type error");
+        assert_eq!(err.source_loc(), None);
+    }
+
+    #[test]
+    fn error_codes() {
+        let request = basic_request();
+        let entities = basic_entities();
+        let exts = Extensions::none();
+        let eval = Evaluator::new(request, &entities, &exts);
+        let err = eval
+            .interpret_inline_policy(&Expr::add(Expr::val(1), Expr::val("a")))
+            .expect_err("should be a type error");
+        assert_eq!(err.error_code(), "type_error");
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "code": "type_error",
+                "details": { "expected": ["Long"], "actual": "String" },
+                "message": "type error: expected long, got string"
+            })
+        );
+
+        let mut err = EvaluationError::recursion_limit();
+        err.set_advice("simplify the policy".into());
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "code": "recursion_limit",
+                "message": "recursion limit reached",
+                "advice": "simplify the policy"
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(err.error_kind()).unwrap(),
+            serde_json::json!({ "code": "recursion_limit" })
+        );
+    }
+
+    #[test]
+    fn error_serde_roundtrip() {
+        let request = basic_request();
+        let entities = basic_entities();
+        let exts = Extensions::all_available();
+        let eval = Evaluator::new(request, &entities, &exts);
+        let exprs = [
+            Expr::add(Expr::val(1), Expr::val("a")),
+            Expr::get_attr(Expr::val(EntityUID::with_eid("missing")), "foo".into()),
+            Expr::get_attr(
+                Expr::record([("a".into(), Expr::val(1))]).unwrap(),
+                "b".into(),
+            ),
+            Expr::add(Expr::val(i64::MAX), Expr::val(1)),
+            Expr::call_extension_fn("decimal".parse().unwrap(), vec![Expr::val("x")]),
+            Expr::call_extension_fn("nosuchfn".parse().unwrap(), vec![Expr::val(1)]),
+            Expr::unknown(Unknown::new_untyped("u")),
+        ];
+        for expr in exprs {
+            let err = eval
+                .interpret_inline_policy(&expr)
+                .expect_err("should be an error");
+            let json = serde_json::to_value(&err).unwrap();
+            assert_eq!(json["code"], err.error_code());
+            let roundtripped: EvaluationError = serde_json::from_value(json).unwrap();
+            assert_eq!(roundtripped, err);
+            let kind: EvaluationErrorKind =
+                serde_json::from_value(serde_json::to_value(err.error_kind()).unwrap()).unwrap();
+            assert_eq!(&kind, err.error_kind());
+        }
+    }
 }
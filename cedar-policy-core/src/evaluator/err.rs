@@ -15,23 +15,38 @@
  */
 
 use crate::ast::*;
+use crate::parser::Loc;
 use itertools::Itertools;
 use miette::Diagnostic;
 use nonempty::{nonempty, NonEmpty};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use smol_str::SmolStr;
 use std::sync::Arc;
 use thiserror::Error;
 
 /// An error generated while evaluating an expression
-#[derive(Debug, PartialEq, Eq, Clone, Error)]
+#[derive(Debug, Clone, Error)]
 #[error("{error_kind}")]
 pub struct EvaluationError {
     /// The kind of error that occurred
     error_kind: EvaluationErrorKind,
     /// Optional advice on how to fix the error
     advice: Option<String>,
+    /// Source location of the innermost expression whose evaluation failed,
+    /// if it has one. Boxed to keep `Result`s of evaluation small.
+    source_loc: Option<Box<Loc>>,
 }
 
+// The source location is not compared, so that errors are equal whether or
+// not the expressions that produced them were parsed from source.
+impl PartialEq for EvaluationError {
+    fn eq(&self, other: &Self) -> bool {
+        self.error_kind == other.error_kind && self.advice == other.advice
+    }
+}
+
+impl Eq for EvaluationError {}
+
 // custom impl of `Diagnostic`: non-trivial implementation of `help()`,
 // everything else forwarded to `.error_kind`
 impl Diagnostic for EvaluationError {
@@ -57,11 +72,19 @@ impl Diagnostic for EvaluationError {
     }
 
     fn source_code(&self) -> Option<&dyn miette::SourceCode> {
-        self.error_kind.source_code()
+        match &self.source_loc {
+            Some(loc) => Some(&loc.src as &dyn miette::SourceCode),
+            None => self.error_kind.source_code(),
+        }
     }
 
     fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
-        self.error_kind.labels()
+        match &self.source_loc {
+            Some(loc) => Some(Box::new(std::iter::once(miette::LabeledSpan::underline(
+                loc.span,
+            )))),
+            None => self.error_kind.labels(),
+        }
     }
 
     fn diagnostic_source(&self) -> Option<&dyn Diagnostic> {
@@ -73,22 +96,81 @@ impl Diagnostic for EvaluationError {
     }
 }
 
+/// The JSON form of an `EvaluationError`
+#[derive(Serialize, Deserialize)]
+struct EvaluationErrorJson {
+    #[serde(flatten)]
+    error_kind: EvaluationErrorKind,
+    /// Only serialized, as it's determined by the error kind
+    #[serde(skip_deserializing)]
+    message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    advice: Option<String>,
+}
+
+/// Serialized as an object with the `code` and the `details` of the error
+/// kind, the `message`, and the `advice`, if any. The source location isn't
+/// serialized.
+impl Serialize for EvaluationError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        EvaluationErrorJson {
+            error_kind: self.error_kind.clone(),
+            message: self.to_string(),
+            advice: self.advice.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for EvaluationError {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let json = EvaluationErrorJson::deserialize(deserializer)?;
+        Ok(Self {
+            error_kind: json.error_kind,
+            advice: json.advice,
+            source_loc: None,
+        })
+    }
+}
+
 impl EvaluationError {
     /// Extract the kind of issue detected during evaluation
     pub fn error_kind(&self) -> &EvaluationErrorKind {
         &self.error_kind
     }
 
+    /// The stable code of the kind of the error, see
+    /// [`EvaluationErrorKind::error_code`]
+    pub fn error_code(&self) -> &'static str {
+        self.error_kind.error_code()
+    }
+
     /// Set the advice field of an error
     pub fn set_advice(&mut self, advice: String) {
         self.advice = Some(advice);
     }
 
+    /// Get the source location of the innermost expression whose evaluation
+    /// failed, if it is known
+    pub fn source_loc(&self) -> Option<&Loc> {
+        self.source_loc.as_deref()
+    }
+
+    /// Set the source location of the error to `source_loc`, unless it already
+    /// has one from a more deeply nested expression
+    pub(crate) fn with_maybe_source_loc(mut self, source_loc: Option<&Loc>) -> Self {
+        if self.source_loc.is_none() {
+            self.source_loc = source_loc.cloned().map(Box::new);
+        }
+        self
+    }
+
     /// Construct a [`EntityDoesNotExist`] error
     pub(crate) fn entity_does_not_exist(euid: Arc<EntityUID>) -> Self {
         Self {
             error_kind: EvaluationErrorKind::EntityDoesNotExist(euid),
             advice: None,
+            source_loc: None,
         }
     }
 
@@ -97,6 +179,7 @@ impl EvaluationError {
         Self {
             error_kind: EvaluationErrorKind::EntityAttrDoesNotExist { entity, attr },
             advice: None,
+            source_loc: None,
         }
     }
 
@@ -105,6 +188,7 @@ impl EvaluationError {
         Self {
             error_kind: EvaluationErrorKind::UnspecifiedEntityAccess(attr),
             advice: None,
+            source_loc: None,
         }
     }
 
@@ -113,6 +197,7 @@ impl EvaluationError {
         Self {
             error_kind: EvaluationErrorKind::RecordAttrDoesNotExist(attr, alternatives),
             advice: None,
+            source_loc: None,
         }
     }
 
@@ -121,6 +206,7 @@ impl EvaluationError {
         Self {
             error_kind: EvaluationErrorKind::TypeError { expected, actual },
             advice: None,
+            source_loc: None,
         }
     }
 
@@ -137,6 +223,7 @@ impl EvaluationError {
         Self {
             error_kind: EvaluationErrorKind::TypeError { expected, actual },
             advice: Some(advice),
+            source_loc: None,
         }
     }
 
@@ -157,6 +244,7 @@ impl EvaluationError {
                 actual,
             },
             advice: None,
+            source_loc: None,
         }
     }
 
@@ -165,6 +253,7 @@ impl EvaluationError {
         Self {
             error_kind: EvaluationErrorKind::UnlinkedSlot(id),
             advice: None,
+            source_loc: None,
         }
     }
 
@@ -176,6 +265,7 @@ impl EvaluationError {
                 msg,
             },
             advice: None,
+            source_loc: None,
         }
     }
 
@@ -184,6 +274,7 @@ impl EvaluationError {
         Self {
             error_kind: EvaluationErrorKind::NonValue(e),
             advice: Some("consider using the partial evaluation APIs".into()),
+            source_loc: None,
         }
     }
 
@@ -192,6 +283,7 @@ impl EvaluationError {
         Self {
             error_kind: EvaluationErrorKind::RecursionLimit,
             advice: None,
+            source_loc: None,
         }
     }
 }
@@ -201,6 +293,7 @@ impl From<crate::extensions::ExtensionFunctionLookupError> for EvaluationError {
         Self {
             error_kind: err.into(),
             advice: None,
+            source_loc: None,
         }
     }
 }
@@ -210,6 +303,7 @@ impl From<IntegerOverflowError> for EvaluationError {
         Self {
             error_kind: err.into(),
             advice: None,
+            source_loc: None,
         }
     }
 }
@@ -219,21 +313,28 @@ impl From<RestrictedExprError> for EvaluationError {
         Self {
             error_kind: err.into(),
             advice: None,
+            source_loc: None,
         }
     }
 }
 
-/// Enumeration of the possible errors that can occur during evaluation
-#[derive(Debug, PartialEq, Eq, Clone, Diagnostic, Error)]
+/// Enumeration of the possible errors that can occur during evaluation.
+///
+/// Serialized as an object with the [`code`](Self::error_code) of the kind
+/// and its `details`.
+#[derive(Debug, PartialEq, Eq, Clone, Diagnostic, Error, Serialize, Deserialize)]
+#[serde(tag = "code", content = "details", rename_all = "snake_case")]
 pub enum EvaluationErrorKind {
     /// Tried to lookup this entity UID, but it didn't exist in the provided
     /// entities
     #[error("entity `{0}` does not exist")]
+    #[diagnostic(code(cedar::evaluation::entity_does_not_exist))]
     EntityDoesNotExist(Arc<EntityUID>),
 
     /// Tried to get this attribute, but the specified entity didn't
     /// have that attribute
     #[error("`{}` does not have the attribute `{}`", &.entity, &.attr)]
+    #[diagnostic(code(cedar::evaluation::entity_attr_does_not_exist))]
     EntityAttrDoesNotExist {
         /// Entity that didn't have the attribute
         entity: Arc<EntityUID>,
@@ -243,22 +344,28 @@ pub enum EvaluationErrorKind {
 
     /// Tried to access an attribute of an unspecified entity
     #[error("cannot access attribute `{0}` of unspecified entity")]
+    #[diagnostic(code(cedar::evaluation::unspecified_entity_access))]
     UnspecifiedEntityAccess(SmolStr),
 
     /// Tried to get an attribute of a (non-entity) record, but that record
     /// didn't have that attribute
     #[error("record does not have the attribute `{0}`")]
-    #[diagnostic(help("available attributes: {1:?}"))]
+    #[diagnostic(
+        code(cedar::evaluation::record_attr_does_not_exist),
+        help("available attributes: {1:?}")
+    )]
     RecordAttrDoesNotExist(SmolStr, Vec<SmolStr>),
 
     /// An error occurred when looking up an extension function
     #[error(transparent)]
     #[diagnostic(transparent)]
+    #[serde(rename = "extension_function_lookup")]
     FailedExtensionFunctionLookup(#[from] crate::extensions::ExtensionFunctionLookupError),
 
     /// Tried to evaluate an operation on values with incorrect types for that
     /// operation
     #[error("{}", pretty_type_error(expected, actual))]
+    #[diagnostic(code(cedar::evaluation::type_error))]
     TypeError {
         /// Expected one of these types
         expected: NonEmpty<Type>,
@@ -268,6 +375,7 @@ pub enum EvaluationErrorKind {
 
     /// Wrong number of arguments provided to an extension function
     #[error("wrong number of arguments provided to extension function `{function_name}`: expected {expected}, got {actual}")]
+    #[diagnostic(code(cedar::evaluation::wrong_num_arguments))]
     WrongNumArguments {
         /// arguments to this function
         function_name: Name,
@@ -290,10 +398,13 @@ pub enum EvaluationErrorKind {
     /// Thrown when a policy is evaluated with a slot that is not linked to an
     /// [`EntityUID`]
     #[error("template slot `{0}` was not linked")]
+    #[diagnostic(code(cedar::evaluation::unlinked_slot))]
     UnlinkedSlot(SlotId),
 
     /// Evaluation error thrown by an extension function
     #[error("error while evaluating `{extension_name}` extension function: {msg}")]
+    #[diagnostic(code(cedar::evaluation::extension_function_application))]
+    #[serde(rename = "extension_function_application")]
     FailedExtensionFunctionApplication {
         /// Name of the extension throwing the error
         extension_name: Name,
@@ -305,13 +416,38 @@ pub enum EvaluationErrorKind {
     /// reduced to a [`Value`]. In order to return partial results, use the
     /// partial evaluation APIs instead.
     #[error("the expression contains unknown(s): `{0}`")]
+    #[diagnostic(code(cedar::evaluation::non_value))]
     NonValue(Expr),
 
     /// Maximum recursion limit reached for expression evaluation
     #[error("recursion limit reached")]
+    #[diagnostic(code(cedar::evaluation::recursion_limit))]
     RecursionLimit,
 }
 
+impl EvaluationErrorKind {
+    /// A stable code of the kind of the error, e.g., for counting errors by
+    /// kind. Unlike the messages, the codes never change, and the code of a
+    /// removed kind isn't reused.
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            Self::EntityDoesNotExist(_) => "entity_does_not_exist",
+            Self::EntityAttrDoesNotExist { .. } => "entity_attr_does_not_exist",
+            Self::UnspecifiedEntityAccess(_) => "unspecified_entity_access",
+            Self::RecordAttrDoesNotExist(_, _) => "record_attr_does_not_exist",
+            Self::FailedExtensionFunctionLookup(_) => "extension_function_lookup",
+            Self::TypeError { .. } => "type_error",
+            Self::WrongNumArguments { .. } => "wrong_num_arguments",
+            Self::IntegerOverflow(_) => "integer_overflow",
+            Self::InvalidRestrictedExpression(_) => "invalid_restricted_expression",
+            Self::UnlinkedSlot(_) => "unlinked_slot",
+            Self::FailedExtensionFunctionApplication { .. } => "extension_function_application",
+            Self::NonValue(_) => "non_value",
+            Self::RecursionLimit => "recursion_limit",
+        }
+    }
+}
+
 /// helper function for pretty-printing type errors
 fn pretty_type_error(expected: &NonEmpty<Type>, actual: &Type) -> String {
     if expected.len() == 1 {
@@ -324,10 +460,11 @@ fn pretty_type_error(expected: &NonEmpty<Type>, actual: &Type) -> String {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Diagnostic, Error)]
+#[derive(Debug, PartialEq, Eq, Clone, Diagnostic, Error, Serialize, Deserialize)]
 pub enum IntegerOverflowError {
     /// Overflow during a binary operation
     #[error("integer overflow while attempting to {} the values `{arg1}` and `{arg2}`", match .op { BinaryOp::Add => "add", BinaryOp::Sub => "subtract", _ => "perform an operation on" })]
+    #[diagnostic(code(cedar::evaluation::integer_overflow))]
     BinaryOp {
         /// overflow while evaluating this operator
         op: BinaryOp,
@@ -339,6 +476,7 @@ pub enum IntegerOverflowError {
 
     /// Overflow during multiplication
     #[error("integer overflow while attempting to multiply `{arg}` by `{constant}`")]
+    #[diagnostic(code(cedar::evaluation::integer_overflow))]
     Multiplication {
         /// first argument, which wasn't necessarily a constant in the policy
         arg: Value,
@@ -348,6 +486,7 @@ pub enum IntegerOverflowError {
 
     /// Overflow during a unary operation
     #[error("integer overflow while attempting to {} the value `{arg}`", match .op { UnaryOp::Neg => "negate", _ => "perform an operation on" })]
+    #[diagnostic(code(cedar::evaluation::integer_overflow))]
     UnaryOp {
         /// overflow while evaluating this operator
         op: UnaryOp,
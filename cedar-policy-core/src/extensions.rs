@@ -23,9 +23,13 @@ pub mod ipaddr;
 pub mod decimal;
 pub mod partial_evaluation;
 
+#[cfg(feature = "unicode")]
+pub mod unicode;
+
 use crate::ast::{Extension, ExtensionFunction, Name};
 use crate::entities::SchemaType;
 use miette::Diagnostic;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 lazy_static::lazy_static! {
@@ -34,6 +38,8 @@ lazy_static::lazy_static! {
         ipaddr::extension(),
         #[cfg(feature = "decimal")]
         decimal::extension(),
+        #[cfg(feature = "unicode")]
+        unicode::extension(),
         partial_evaluation::extension(),
     ];
 }
@@ -103,7 +109,8 @@ impl<'a> Extensions<'a> {
     }
 
     /// Lookup a single-argument constructor by its return type and argument type.
-    /// This will ignore polymorphic functions (that accept multiple argument types).
+    /// A polymorphic constructor (that accepts multiple argument types)
+    /// matches any argument type.
     ///
     /// `Ok(None)` means no constructor has that signature.
     /// `Err` is returned in the case that multiple constructors have that signature.
@@ -117,7 +124,11 @@ impl<'a> Extensions<'a> {
             .filter(|f| {
                 f.is_constructor()
                     && f.return_type() == Some(return_type)
-                    && f.arg_types().get(0).map(Option::as_ref) == Some(Some(arg_type))
+                    && match f.arg_types() {
+                        [None] => true,
+                        [Some(ty)] => ty == arg_type,
+                        _ => false,
+                    }
             })
             .collect::<Vec<_>>();
         match matches.get(0) {
@@ -134,10 +145,11 @@ impl<'a> Extensions<'a> {
 }
 
 /// Errors thrown when looking up an extension function in [`Extensions`].
-#[derive(Debug, PartialEq, Eq, Clone, Diagnostic, Error)]
+#[derive(Debug, PartialEq, Eq, Clone, Diagnostic, Error, Serialize, Deserialize)]
 pub enum ExtensionFunctionLookupError {
     /// Tried to call a function that doesn't exist
     #[error("extension function `{name}` does not exist")]
+    #[diagnostic(code(cedar::evaluation::extension_function_lookup))]
     FuncDoesNotExist {
         /// Name of the function that doesn't exist
         name: Name,
@@ -145,6 +157,7 @@ pub enum ExtensionFunctionLookupError {
 
     /// Attempted to typecheck an expression that had no type
     #[error("extension function `{name}` has no type")]
+    #[diagnostic(code(cedar::evaluation::extension_function_lookup))]
     HasNoType {
         /// Name of the function that returns no type
         name: Name,
@@ -153,6 +166,7 @@ pub enum ExtensionFunctionLookupError {
     /// Tried to call a function but it was defined multiple times (e.g., by
     /// multiple different extensions)
     #[error("extension function `{name}` is defined {num_defs} times")]
+    #[diagnostic(code(cedar::evaluation::extension_function_lookup))]
     FuncMultiplyDefined {
         /// Name of the function that is multiply defined
         name: Name,
@@ -165,6 +179,7 @@ pub enum ExtensionFunctionLookupError {
     #[error(
         "multiple extension constructors have the same type signature {arg_type} -> {return_type}"
     )]
+    #[diagnostic(code(cedar::evaluation::extension_function_lookup))]
     MultipleConstructorsSameSignature {
         /// return type of the shared constructor signature
         return_type: Box<SchemaType>,
@@ -25,6 +25,7 @@ use crate::ast::{
 use crate::entities::SchemaType;
 use crate::evaluator;
 use miette::Diagnostic;
+use nonempty::nonempty;
 use std::str::FromStr;
 use std::sync::Arc;
 use thiserror::Error;
@@ -145,6 +146,11 @@ impl Decimal {
         .map(|value| Self { value })
         .ok_or(Error::Overflow)
     }
+
+    /// Convert an integer into a `Decimal` value, which errors on overflow
+    fn from_long(long: i64) -> Result<Self, Error> {
+        checked_mul_pow(long, NUM_DIGITS).map(|value| Self { value })
+    }
 }
 
 impl std::fmt::Display for Decimal {
@@ -174,10 +180,19 @@ fn extension_err(msg: impl Into<String>) -> evaluator::EvaluationError {
 }
 
 /// Cedar function that constructs a `decimal` Cedar type from a
-/// Cedar string
-fn decimal_from_str(arg: Value) -> evaluator::Result<ExtensionOutputValue> {
-    let str = arg.get_as_string()?;
-    let decimal = Decimal::from_str(str.as_str()).map_err(|e| extension_err(e.to_string()))?;
+/// Cedar string or long
+fn decimal_from_str_or_long(arg: Value) -> evaluator::Result<ExtensionOutputValue> {
+    let decimal = match &arg {
+        Value::Lit(Literal::String(str)) => Decimal::from_str(str.as_str()),
+        Value::Lit(Literal::Long(long)) => Decimal::from_long(*long),
+        _ => {
+            return Err(evaluator::EvaluationError::type_error(
+                nonempty![Type::String, Type::Long],
+                arg.type_of(),
+            ))
+        }
+    }
+    .map_err(|e| extension_err(e.to_string()))?;
     let function_name = names::DECIMAL_FROM_STR_NAME.clone();
     let e = ExtensionValueWithArgs::new(Arc::new(decimal), function_name, vec![arg.into()]);
     Ok(Value::ExtensionValue(Arc::new(e)).into())
@@ -214,35 +229,50 @@ fn as_decimal(v: &Value) -> Result<&Decimal, evaluator::EvaluationError> {
     }
 }
 
+/// Check that `v` is a decimal or a long and, if it is, return its value
+/// times `10 ^ NUM_DIGITS`. This is an `i128`, so it doesn't overflow for
+/// any long, which lets a decimal be compared with any long.
+fn as_scaled_decimal_or_long(v: &Value) -> Result<i128, evaluator::EvaluationError> {
+    match v {
+        Value::Lit(Literal::Long(long)) => {
+            Ok(i128::from(*long) * i128::from(i64::pow(10, NUM_DIGITS)))
+        }
+        _ => as_decimal(v).map(|d| i128::from(d.value)),
+    }
+}
+
 /// Cedar function that tests whether the first `decimal` Cedar type is
-/// less than the second `decimal` Cedar type, returning a Cedar bool
+/// less than the second `decimal` Cedar type or Cedar long, returning a Cedar bool
 fn decimal_lt(left: Value, right: Value) -> evaluator::Result<ExtensionOutputValue> {
-    let left = as_decimal(&left)?;
-    let right = as_decimal(&right)?;
+    let left = i128::from(as_decimal(&left)?.value);
+    let right = as_scaled_decimal_or_long(&right)?;
     Ok(Value::Lit((left < right).into()).into())
 }
 
 /// Cedar function that tests whether the first `decimal` Cedar type is
-/// less than or equal to the second `decimal` Cedar type, returning a Cedar bool
+/// less than or equal to the second `decimal` Cedar type or Cedar long,
+/// returning a Cedar bool
 fn decimal_le(left: Value, right: Value) -> evaluator::Result<ExtensionOutputValue> {
-    let left = as_decimal(&left)?;
-    let right = as_decimal(&right)?;
+    let left = i128::from(as_decimal(&left)?.value);
+    let right = as_scaled_decimal_or_long(&right)?;
     Ok(Value::Lit((left <= right).into()).into())
 }
 
 /// Cedar function that tests whether the first `decimal` Cedar type is
-/// greater than the second `decimal` Cedar type, returning a Cedar bool
+/// greater than the second `decimal` Cedar type or Cedar long, returning a
+/// Cedar bool
 fn decimal_gt(left: Value, right: Value) -> evaluator::Result<ExtensionOutputValue> {
-    let left = as_decimal(&left)?;
-    let right = as_decimal(&right)?;
+    let left = i128::from(as_decimal(&left)?.value);
+    let right = as_scaled_decimal_or_long(&right)?;
     Ok(Value::Lit((left > right).into()).into())
 }
 
 /// Cedar function that tests whether the first `decimal` Cedar type is
-/// greater than or equal to the second `decimal` Cedar type, returning a Cedar bool
+/// greater than or equal to the second `decimal` Cedar type or Cedar long,
+/// returning a Cedar bool
 fn decimal_ge(left: Value, right: Value) -> evaluator::Result<ExtensionOutputValue> {
-    let left = as_decimal(&left)?;
-    let right = as_decimal(&right)?;
+    let left = i128::from(as_decimal(&left)?.value);
+    let right = as_scaled_decimal_or_long(&right)?;
     Ok(Value::Lit((left >= right).into()).into())
 }
 
@@ -257,37 +287,38 @@ pub fn extension() -> Extension {
             ExtensionFunction::unary(
                 names::DECIMAL_FROM_STR_NAME.clone(),
                 CallStyle::FunctionStyle,
-                Box::new(decimal_from_str),
+                Box::new(decimal_from_str_or_long),
                 decimal_type.clone(),
-                Some(SchemaType::String),
+                // a string or a long
+                None,
             ),
             ExtensionFunction::binary(
                 names::LESS_THAN.clone(),
                 CallStyle::MethodStyle,
                 Box::new(decimal_lt),
                 SchemaType::Bool,
-                (Some(decimal_type.clone()), Some(decimal_type.clone())),
+                (Some(decimal_type.clone()), None),
             ),
             ExtensionFunction::binary(
                 names::LESS_THAN_OR_EQUAL.clone(),
                 CallStyle::MethodStyle,
                 Box::new(decimal_le),
                 SchemaType::Bool,
-                (Some(decimal_type.clone()), Some(decimal_type.clone())),
+                (Some(decimal_type.clone()), None),
             ),
             ExtensionFunction::binary(
                 names::GREATER_THAN.clone(),
                 CallStyle::MethodStyle,
                 Box::new(decimal_gt),
                 SchemaType::Bool,
-                (Some(decimal_type.clone()), Some(decimal_type.clone())),
+                (Some(decimal_type.clone()), None),
             ),
             ExtensionFunction::binary(
                 names::GREATER_THAN_OR_EQUAL.clone(),
                 CallStyle::MethodStyle,
                 Box::new(decimal_ge),
                 SchemaType::Bool,
-                (Some(decimal_type.clone()), Some(decimal_type)),
+                (Some(decimal_type), None),
             ),
         ],
     )
@@ -465,6 +496,46 @@ mod tests {
         parse_expr(r#" "1.0".decimal() "#).expect_err("should fail");
     }
 
+    #[test]
+    fn decimal_from_long() {
+        let ext_array = [extension()];
+        let exts = Extensions::specific_extensions(&ext_array);
+        let request = basic_request();
+        let entities = basic_entities();
+        let eval = Evaluator::new(request, &entities, &exts);
+
+        // a long is the decimal with no fraction
+        assert_eq!(
+            eval.interpret_inline_policy(
+                &parse_expr(r#"decimal(12) == decimal("12.0")"#).expect("parsing error")
+            ),
+            Ok(Value::from(true))
+        );
+        assert_eq!(
+            eval.interpret_inline_policy(
+                &parse_expr(r#"decimal(-3) == decimal("-3.0")"#).expect("parsing error")
+            ),
+            Ok(Value::from(true))
+        );
+        assert_decimal_valid(eval.interpret_inline_policy(
+            &parse_expr(r#"decimal(922337203685477)"#).expect("parsing error"),
+        ));
+
+        // overflows
+        assert_decimal_err(eval.interpret_inline_policy(
+            &parse_expr(r#"decimal(922337203685478)"#).expect("parsing error"),
+        ));
+
+        // neither a string nor a long
+        assert_eq!(
+            eval.interpret_inline_policy(&parse_expr(r#"decimal(true)"#).expect("parsing error")),
+            Err(evaluator::EvaluationError::type_error(
+                nonempty![Type::String, Type::Long],
+                Type::Bool,
+            ))
+        );
+    }
+
     #[test]
     fn decimal_equality() {
         let ext_array = [extension()];
@@ -608,8 +679,8 @@ mod tests {
             eval.interpret_inline_policy(
                 &parse_expr(r#"decimal("1.23") < decimal("1.24")"#).expect("parsing error")
             ),
-            Err(evaluator::EvaluationError::type_error_single(
-                Type::Long,
+            Err(evaluator::EvaluationError::type_error(
+                nonempty![Type::Long, Type::String],
                 Type::Extension {
                     name: Name::parse_unqualified_name("decimal")
                         .expect("should be a valid identifier")
@@ -633,6 +704,62 @@ mod tests {
         parse_expr(r#"lessThan(decimal("-1.23"), decimal("1.23"))"#).expect_err("should fail");
     }
 
+    #[test]
+    fn decimal_long_ops() {
+        let a = parse_expr(r#"decimal("1.23")"#).expect("parsing error");
+        let b = parse_expr(r#"decimal("-1.0")"#).expect("parsing error");
+        let one = Expr::val(1);
+        let two = Expr::val(2);
+        let minus_one = Expr::val(-1);
+        let max = Expr::val(i64::MAX);
+
+        let tests = vec![
+            ((a.clone(), one.clone()), false),       // 1.23 < 1
+            ((a.clone(), two.clone()), true),        // 1.23 < 2
+            ((b.clone(), minus_one.clone()), false), // -1.0 < -1
+            ((a.clone(), max.clone()), true),        // 1.23 < 9223372036854775807
+        ];
+        decimal_ops_helper("lessThan", tests);
+
+        let tests = vec![
+            ((a.clone(), one.clone()), false),      // 1.23 <= 1
+            ((b.clone(), minus_one.clone()), true), // -1.0 <= -1
+        ];
+        decimal_ops_helper("lessThanOrEqual", tests);
+
+        let tests = vec![
+            ((a.clone(), one.clone()), true),        // 1.23 > 1
+            ((b.clone(), minus_one.clone()), false), // -1.0 > -1
+            ((a.clone(), max), false),               // 1.23 > 9223372036854775807
+        ];
+        decimal_ops_helper("greaterThan", tests);
+
+        let tests = vec![
+            ((a, two), false),      // 1.23 >= 2
+            ((b, minus_one), true), // -1.0 >= -1
+        ];
+        decimal_ops_helper("greaterThanOrEqual", tests);
+
+        // a long is only allowed as the argument, not the receiver
+        let ext_array = [extension()];
+        let exts = Extensions::specific_extensions(&ext_array);
+        let request = basic_request();
+        let entities = basic_entities();
+        let eval = Evaluator::new(request, &entities, &exts);
+        assert_eq!(
+            eval.interpret_inline_policy(
+                &parse_expr(r#"1.lessThan(decimal("1.23"))"#).expect("parsing error")
+            ),
+            Err(evaluator::EvaluationError::type_error_single(
+                Type::Extension {
+                    name: Name::parse_unqualified_name("decimal")
+                        .expect("should be a valid identifier")
+                },
+                Type::Long,
+            ))
+        );
+    }
+
     fn check_round_trip(s: &str) {
         let d = Decimal::from_str(s).expect("should be a valid decimal");
         assert_eq!(s, d.to_string());
@@ -409,6 +409,7 @@ mod tests {
     use crate::extensions::Extensions;
     use crate::parser::parse_expr;
     use cool_asserts::assert_matches;
+    use nonempty::nonempty;
 
     /// This helper function asserts that a `Result` is actually an
     /// `Err::ExtensionErr` with our extension name
@@ -564,8 +565,8 @@ mod tests {
         // test that < on ipaddr values is an error
         assert_eq!(
             eval.interpret_inline_policy(&Expr::less(ip("127.0.0.1"), ip("10.0.0.10"))),
-            Err(evaluator::EvaluationError::type_error_single(
-                Type::Long,
+            Err(evaluator::EvaluationError::type_error(
+                nonempty![Type::Long, Type::String],
                 Type::Extension {
                     name: Name::parse_unqualified_name("ipaddr")
                         .expect("should be a valid identifier")
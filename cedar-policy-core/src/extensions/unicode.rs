@@ -0,0 +1,151 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module contains the Cedar 'unicode' extension, which compares strings
+//! without regard to case or to how their characters are encoded.
+
+use crate::ast::{CallStyle, Extension, ExtensionFunction, ExtensionOutputValue, Name, Value};
+use crate::entities::SchemaType;
+use crate::evaluator;
+use unicode_normalization::UnicodeNormalization;
+
+// PANIC SAFETY All the names are valid names
+#[allow(clippy::expect_used)]
+mod names {
+    use super::Name;
+    lazy_static::lazy_static! {
+        pub static ref EXTENSION_NAME : Name = Name::parse_unqualified_name("unicode").expect("should be a valid identifier");
+        pub static ref EQUALS_IGNORE_CASE : Name = Name::parse_unqualified_name("equalsIgnoreCase").expect("should be a valid identifier");
+        pub static ref EQUALS_NORMALIZED : Name = Name::parse_unqualified_name("equalsNormalized").expect("should be a valid identifier");
+    }
+}
+
+/// The NFC normal form of `s`, in which canonically equivalent strings, e.g.,
+/// "é" as one code point or as "e" followed by a combining accent, are equal
+fn nfc(s: &str) -> String {
+    s.nfc().collect()
+}
+
+/// Cedar function that tests whether two Cedar strings are equal after
+/// lowercasing them and normalizing them to NFC, returning a Cedar bool
+fn equals_ignore_case(left: Value, right: Value) -> evaluator::Result<ExtensionOutputValue> {
+    let left = nfc(&left.get_as_string()?.to_lowercase());
+    let right = nfc(&right.get_as_string()?.to_lowercase());
+    Ok(Value::from(left == right).into())
+}
+
+/// Cedar function that tests whether two Cedar strings are equal after
+/// normalizing them to NFC, returning a Cedar bool
+fn equals_normalized(left: Value, right: Value) -> evaluator::Result<ExtensionOutputValue> {
+    let left = nfc(left.get_as_string()?);
+    let right = nfc(right.get_as_string()?);
+    Ok(Value::from(left == right).into())
+}
+
+/// Construct the extension
+pub fn extension() -> Extension {
+    Extension::new(
+        names::EXTENSION_NAME.clone(),
+        vec![
+            ExtensionFunction::binary(
+                names::EQUALS_IGNORE_CASE.clone(),
+                CallStyle::MethodStyle,
+                Box::new(equals_ignore_case),
+                SchemaType::Bool,
+                (Some(SchemaType::String), Some(SchemaType::String)),
+            ),
+            ExtensionFunction::binary(
+                names::EQUALS_NORMALIZED.clone(),
+                CallStyle::MethodStyle,
+                Box::new(equals_normalized),
+                SchemaType::Bool,
+                (Some(SchemaType::String), Some(SchemaType::String)),
+            ),
+        ],
+    )
+}
+
+// PANIC SAFETY: Unit Test Code
+#[allow(clippy::panic)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Type;
+    use crate::evaluator::test::{basic_entities, basic_request};
+    use crate::evaluator::Evaluator;
+    use crate::extensions::Extensions;
+    use crate::parser::parse_expr;
+
+    #[track_caller] // report the caller's location as the location of the panic, not the location in this function
+    fn eval(expr: &str) -> evaluator::Result<Value> {
+        let ext_array = [extension()];
+        let exts = Extensions::specific_extensions(&ext_array);
+        let request = basic_request();
+        let entities = basic_entities();
+        let eval = Evaluator::new(request, &entities, &exts);
+        eval.interpret_inline_policy(&parse_expr(expr).expect("parsing error"))
+    }
+
+    #[test]
+    fn equals_ignore_case() {
+        assert_eq!(
+            eval(r#""Alice".equalsIgnoreCase("aLICE")"#),
+            Ok(true.into())
+        );
+        assert_eq!(
+            eval(r#""ÉCOLE".equalsIgnoreCase("école")"#),
+            Ok(true.into())
+        );
+        // a composed and a decomposed "é" in different cases
+        assert_eq!(
+            eval(r#""\u{e9}".equalsIgnoreCase("E\u{301}")"#),
+            Ok(true.into())
+        );
+        assert_eq!(
+            eval(r#""Alice".equalsIgnoreCase("Alicia")"#),
+            Ok(false.into())
+        );
+        assert_eq!(
+            eval(r#""1".equalsIgnoreCase(1)"#),
+            Err(evaluator::EvaluationError::type_error_single(
+                Type::String,
+                Type::Long
+            ))
+        );
+    }
+
+    #[test]
+    fn equals_normalized() {
+        assert_eq!(
+            eval(r#""caf\u{e9}".equalsNormalized("cafe\u{301}")"#),
+            Ok(true.into())
+        );
+        assert_eq!(eval(r#""café".equalsNormalized("café")"#), Ok(true.into()));
+        // NFC doesn't ignore case, or compatibility differences like ligatures
+        assert_eq!(eval(r#""Café".equalsNormalized("café")"#), Ok(false.into()));
+        assert_eq!(
+            eval(r#""\u{fb01}".equalsNormalized("fi")"#),
+            Ok(false.into())
+        );
+    }
+
+    /// the methods can't be called as functions
+    #[test]
+    fn method_style() {
+        parse_expr(r#"equalsIgnoreCase("a", "A")"#).expect_err("should fail");
+        parse_expr(r#"equalsNormalized("a", "A")"#).expect_err("should fail");
+    }
+}
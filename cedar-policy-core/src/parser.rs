@@ -91,6 +91,43 @@ pub fn parse_policyset_and_also_return_policy_text(
     }
 }
 
+/// Like `parse_policyset_and_also_return_policy_text()`, but continues after
+/// errors to report the errors of every policy, returning the policies that
+/// parse along with the errors of those that don't. The parser skips from a
+/// syntax error to the `;` ending the policy, so a policy has the id it would
+/// have if every policy parsed, e.g., the third policy is `policy2` even if the
+/// second has an error. The policy set is empty if the parser couldn't
+/// continue after an error, e.g., after a string with no closing `"`.
+/// INVARIANT: as for `parse_policyset_and_also_return_policy_text()`, the
+/// `PolicyID` of every `Policy` and `Template` in the returned policy set
+/// appears as a key in the returned map.
+pub fn parse_policyset_with_recovery(
+    text: &str,
+) -> (
+    HashMap<ast::PolicyID, &str>,
+    ast::PolicySet,
+    err::ParseErrors,
+) {
+    let (cst, mut errs) = text_to_cst::parse_policies_with_recovery(text);
+    let Some(cst) = cst else {
+        return (HashMap::new(), ast::PolicySet::new(), errs);
+    };
+    let pset = cst.to_policyset_with_recovery(&mut errs);
+    // PANIC SAFETY Indexing is safe because of how the `SourceSpan` is constructed
+    #[allow(clippy::indexing_slicing)]
+    // The `PolicyID` keys for `texts` are generated by
+    // `cst.with_generated_policyids()`, as are the ids of the policies and
+    // templates in `cst.to_policyset_with_recovery()`. If the CST has no
+    // data, the policy set is empty.
+    let texts = cst
+        .with_generated_policyids()
+        .into_iter()
+        .flatten()
+        .map(|(id, policy)| (id, &text[policy.loc.start()..policy.loc.end()]))
+        .collect::<HashMap<ast::PolicyID, &str>>();
+    (texts, pset, errs)
+}
+
 /// Like `parse_policyset()`, but also returns the (lossless) ESTs -- that is,
 /// the ESTs of the original policies without any of the lossy transforms
 /// involved in converting to AST.
@@ -431,6 +468,41 @@ mod test {
         "#,
         ), Err(e) => assert!(e.len() >= 3, "expected at least 3 errors, but actual errors were:\n{:?}", miette::Report::new(e)) );
     }
+
+    #[test]
+    fn test_recovery() {
+        let src = r#"
+            permit(principal, action, resource);
+            permit(principal, action, resource) when { 1 / 2 };
+            permit(principal action, resource) when { "a;b" };
+            forbid(principal, action, resource) unless { context.ok };
+        "#;
+        let (texts, pset, errs) = parse_policyset_with_recovery(src);
+        // one error each for the division and the missing comma, rather than
+        // one for each of the policies the rest of the third would parse as
+        assert_eq!(errs.len(), 2, "{:?}", miette::Report::new(errs));
+        assert_eq!(
+            pset.policies()
+                .map(|p| p.id().to_string())
+                .collect::<HashSet<_>>(),
+            HashSet::from(["policy0".to_string(), "policy3".to_string()])
+        );
+        assert!(texts[&ast::PolicyID::from_string("policy3")].starts_with("forbid"));
+
+        // a policy missing its `;` runs to the end of the input
+        let (_, pset, errs) = parse_policyset_with_recovery(
+            "permit(principal, action, resource); permit(principal, action, resource)",
+        );
+        assert_eq!(pset.policies().count(), 1);
+        assert_eq!(errs.len(), 1);
+
+        // the parser can't continue after an invalid token
+        let (_, pset, errs) = parse_policyset_with_recovery(
+            r#"permit(principal, action, resource); forbid(principal, action, resource) when { "a };"#,
+        );
+        assert_eq!(pset.policies().count(), 0);
+        assert_eq!(errs.len(), 1);
+    }
 }
 
 #[cfg(test)]
@@ -39,6 +39,15 @@ pub struct Annotation {
 pub enum Str {
     /// regular quoted string
     String(SmolStr),
+    /// raw string, `r"..."` or `r#"..."#`, which has no escapes and may span
+    /// lines
+    Raw {
+        /// the text between the quotes
+        raw: SmolStr,
+        /// the same string as the text of a regular quoted string, i.e.,
+        /// with its backslashes, quotes, and carriage returns escaped
+        escaped: SmolStr,
+    },
     // this is not generated by the parser at time of comment,
     // but left as future improvement and to clarify the
     // validity of the above `String` form
@@ -47,6 +56,29 @@ pub enum Str {
     Invalid(SmolStr),
 }
 
+impl Str {
+    /// The string of the raw string literal `literal`, `r"..."` or `r#"..."#`
+    pub(crate) fn from_raw_literal(literal: &str) -> Self {
+        let hashes = usize::from(literal.starts_with("r#"));
+        let raw = literal
+            .get(2 + hashes..literal.len().saturating_sub(1 + hashes))
+            .unwrap_or_default();
+        let mut escaped = String::with_capacity(raw.len());
+        for c in raw.chars() {
+            match c {
+                '\\' => escaped.push_str(r"\\"),
+                '"' => escaped.push_str(r#"\""#),
+                '\r' => escaped.push_str(r"\r"),
+                c => escaped.push(c),
+            }
+        }
+        Self::Raw {
+            raw: raw.into(),
+            escaped: escaped.into(),
+        }
+    }
+}
+
 /// Policy statement, the main building block of the language
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Policy {
@@ -49,6 +49,7 @@ use crate::ast::{
 };
 use crate::est::extract_single_argument;
 use itertools::Either;
+use nonempty::NonEmpty;
 use smol_str::SmolStr;
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashSet};
@@ -99,14 +100,41 @@ impl Node<Option<cst::Policies>> {
 
     /// convert `cst::Policies` to `ast::PolicySet`
     pub fn to_policyset(&self, errs: &mut ParseErrors) -> Option<ast::PolicySet> {
+        let (pset, complete_set) = self.to_partial_policyset(errs)?;
+        // fail on any error
+        if complete_set {
+            Some(pset)
+        } else {
+            None
+        }
+    }
+
+    /// convert `cst::Policies` to an `ast::PolicySet` of the policies that
+    /// convert without errors, continuing after the policies that don't. The
+    /// policies have the ids generated by `with_generated_policyids()`, so a
+    /// policy has the same id whether or not the policies before it have
+    /// errors.
+    pub fn to_policyset_with_recovery(&self, errs: &mut ParseErrors) -> ast::PolicySet {
+        self.to_partial_policyset(errs)
+            .map(|(pset, _)| pset)
+            .unwrap_or_default()
+    }
+
+    /// convert `cst::Policies` to an `ast::PolicySet` of the policies that
+    /// convert without errors, and whether every policy did
+    fn to_partial_policyset(&self, errs: &mut ParseErrors) -> Option<(ast::PolicySet, bool)> {
         let mut pset = ast::PolicySet::new();
         let mut complete_set = true;
         // Caution: `parser::parse_policyset_and_also_return_policy_text()`
         // depends on this function returning a policy set with `PolicyID`s as
         // generated by `with_generated_policyids()` to maintain an invariant.
         for (policy_id, policy) in self.with_generated_policyids()? {
-            // policy may have convert error
-            match policy.to_policy_or_template(policy_id, errs) {
+            // policy may have convert error. It has errors of its own, since
+            // a policy doesn't convert if there are any errors in `errs`.
+            let mut policy_errs = ParseErrors::new();
+            let converted = policy.to_policy_or_template(policy_id, &mut policy_errs);
+            errs.extend(policy_errs);
+            match converted {
                 Some(Either::Right(template)) => {
                     if let Err(e) = pset.add_template(template) {
                         match e {
@@ -132,13 +160,7 @@ impl Node<Option<cst::Policies>> {
                 None => complete_set = false,
             };
         }
-
-        // fail on any error
-        if complete_set {
-            Some(pset)
-        } else {
-            None
-        }
+        Some((pset, complete_set))
     }
 }
 
@@ -759,7 +781,7 @@ impl Node<Option<cst::Str>> {
         let id = self.as_inner()?;
 
         match id {
-            cst::Str::String(s) => Some(s),
+            cst::Str::String(s) | cst::Str::Raw { escaped: s, .. } => Some(s),
             // at time of comment, all strings are valid
             cst::Str::Invalid(s) => {
                 errs.push(self.to_ast_err(ToASTErrorKind::InvalidString(s.to_string())));
@@ -1265,12 +1287,21 @@ impl Node<Option<cst::Relation>> {
                 }
             }
             cst::Relation::Has { target, field } => {
-                match (
-                    target.to_expr(errs),
-                    field.to_expr_or_special(errs)?.into_valid_attr(errs),
-                ) {
-                    (Some(t), Some(s)) => Some(ExprOrSpecial::Expr {
-                        expr: construct_expr_has(t, s, self.loc.clone()),
+                let maybe_target = target.to_expr(errs);
+                let maybe_path = match field.to_has_path() {
+                    Some(path) => path
+                        .into_iter()
+                        .map(|ident| ident.to_valid_ident(errs).map(|id| id.to_smolstr()))
+                        .collect::<Option<Vec<_>>>()
+                        .and_then(NonEmpty::from_vec),
+                    None => field
+                        .to_expr_or_special(errs)?
+                        .into_valid_attr(errs)
+                        .map(NonEmpty::new),
+                };
+                match (maybe_target, maybe_path) {
+                    (Some(t), Some(path)) => Some(ExprOrSpecial::Expr {
+                        expr: construct_expr_has_path(t, path, &self.loc),
                         loc: self.loc.clone(),
                     }),
                     _ => None,
@@ -1867,6 +1898,41 @@ impl Node<Option<cst::Member>> {
     }
 }
 
+impl Node<Option<cst::Add>> {
+    /// The identifiers of the attribute path `a.b.c` on the right of
+    /// `e has a.b.c`, if this is two or more identifiers separated by `.`s
+    pub(crate) fn to_has_path(&self) -> Option<NonEmpty<&Node<Option<cst::Ident>>>> {
+        let add = self.as_inner()?;
+        if !add.extended.is_empty() {
+            return None;
+        }
+        let mult = add.initial.as_inner()?;
+        if !mult.extended.is_empty() {
+            return None;
+        }
+        let unary = mult.initial.as_inner()?;
+        if unary.op.is_some() {
+            return None;
+        }
+        let member = unary.item.as_inner()?;
+        let cst::Primary::Name(name) = member.item.as_inner()? else {
+            return None;
+        };
+        let name = name.as_inner()?;
+        if !name.path.is_empty() || member.access.is_empty() {
+            return None;
+        }
+        let mut path = NonEmpty::new(&name.name);
+        for access in &member.access {
+            match access.as_inner()? {
+                cst::MemAccess::Field(ident) => path.push(ident),
+                _ => return None,
+            }
+        }
+        Some(path)
+    }
+}
+
 impl Node<Option<cst::MemAccess>> {
     fn to_access(&self, errs: &mut ParseErrors) -> Option<AstAccessor> {
         // if `self` doesn't have data, nothing we can do here, just propagate
@@ -2449,6 +2515,18 @@ fn construct_expr_mul(
 fn construct_expr_has(t: ast::Expr, s: SmolStr, loc: Loc) -> ast::Expr {
     ast::ExprBuilder::new().with_source_loc(loc).has_attr(t, s)
 }
+/// `t has a.b.c` is `t has a && t.a has b && t.a.b has c`
+fn construct_expr_has_path(t: ast::Expr, path: NonEmpty<SmolStr>, loc: &Loc) -> ast::Expr {
+    let NonEmpty { head, tail } = path;
+    let mut expr = construct_expr_has(t.clone(), head.clone(), loc.clone());
+    let mut target = construct_expr_attr(t, head, loc.clone());
+    for attr in tail {
+        let has = construct_expr_has(target.clone(), attr.clone(), loc.clone());
+        expr = construct_expr_and(expr, has, std::iter::empty(), loc);
+        target = construct_expr_attr(target, attr, loc.clone());
+    }
+    expr
+}
 fn construct_expr_attr(e: ast::Expr, s: SmolStr, loc: Loc) -> ast::Expr {
     ast::ExprBuilder::new().with_source_loc(loc).get_attr(e, s)
 }
@@ -3458,6 +3536,39 @@ mod tests {
         assert!(errs.len() == 1);
     }
 
+    #[test]
+    fn has_attribute_paths() {
+        let to_expr = |text: &str| {
+            let mut errs = ParseErrors::new();
+            let expr = text_to_cst::parse_expr(text)
+                .expect("failed parser")
+                .to_expr(&mut errs);
+            (expr, errs)
+        };
+
+        // ok: a path is a chain of `has` checks
+        let (path, errs) = to_expr(r#"User::"jane" has contactInfo.address.zip"#);
+        assert!(errs.is_empty());
+        let (chain, errs) = to_expr(
+            r#"User::"jane" has contactInfo && User::"jane".contactInfo has address && User::"jane".contactInfo.address has zip"#,
+        );
+        assert!(errs.is_empty());
+        assert!(path
+            .expect("failed convert")
+            .eq_shape(&chain.expect("failed convert")));
+
+        // not ok: the path has a method call, an index, or a reserved identifier
+        for text in [
+            r#"User::"jane" has contactInfo.address()"#,
+            r#"User::"jane" has contactInfo["address"]"#,
+            r#"User::"jane" has contactInfo.if"#,
+        ] {
+            let (e, errs) = to_expr(text);
+            assert!(e.is_none(), "{text}");
+            assert!(!errs.is_empty(), "{text}");
+        }
+    }
+
     #[test]
     fn relational_ops1() {
         let mut errs = ParseErrors::new();
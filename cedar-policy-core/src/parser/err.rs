@@ -80,6 +80,48 @@ impl ParseError {
                 .and_then(|mut it| it.next().map(|lspan| lspan.inner().clone())),
         }
     }
+
+    /// The kind of the error
+    pub fn kind(&self) -> ParseErrorKind {
+        match self {
+            ParseError::ToCST(_) => ParseErrorKind::Syntax,
+            ParseError::ToAST(_) => ParseErrorKind::Structure,
+            ParseError::RestrictedExpr(_) => ParseErrorKind::RestrictedExpression,
+            ParseError::ParseLiteral(_) => ParseErrorKind::Literal,
+        }
+    }
+
+    /// The message of the error, which is its `Display`
+    pub fn message(&self) -> String {
+        self.to_string()
+    }
+
+    /// Advice on how to fix the error, if any
+    pub fn help(&self) -> Option<String> {
+        Diagnostic::help(self).map(|help| help.to_string())
+    }
+
+    /// The byte range of the source the error is about, if known. This is the
+    /// range of [`ParseError::primary_source_span`].
+    pub fn source_range(&self) -> Option<std::ops::Range<usize>> {
+        self.primary_source_span()
+            .map(|span| span.offset()..span.offset() + span.len())
+    }
+}
+
+/// The kinds of [`ParseError`]s, which are the stages of parsing which found
+/// them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParseErrorKind {
+    /// The text doesn't follow the grammar, e.g., it has an unexpected token
+    Syntax,
+    /// The text follows the grammar, but isn't a well-formed policy or
+    /// expression, e.g., it uses an unsupported operator
+    Structure,
+    /// An expression isn't a restricted expression, where one is required
+    RestrictedExpression,
+    /// The text isn't a literal, where one is required
+    Literal,
 }
 
 /// Errors in the top-level parse literal entrypoint
@@ -88,6 +130,7 @@ pub enum ParseLiteralError {
     /// The top-level parser endpoint for parsing a literal encountered a non-literal.
     /// Since this can be any possible other expression, we just return it as a string.
     #[error("`{0}` is not a literal")]
+    #[diagnostic(code(cedar::parse::not_a_literal))]
     ParseLiteral(String),
 }
 
@@ -153,23 +196,32 @@ impl ToASTError {
 pub enum ToASTErrorKind {
     /// Returned when we attempt to parse a template with a conflicting id
     #[error("a template with id `{0}` already exists in the policy set")]
+    #[diagnostic(code(cedar::parse::duplicate_template_id))]
     DuplicateTemplateId(PolicyID),
     /// Returned when we attempt to parse a policy with a conflicting id
     #[error("a policy with id `{0}` already exists in the policy set")]
+    #[diagnostic(code(cedar::parse::duplicate_policy_id))]
     DuplicatePolicyId(PolicyID),
     /// Returned when a template is encountered but a static policy is expected
     #[error("expected a static policy, got a template containing the slot {slot}")]
-    #[diagnostic(help("try removing the template slot(s) from this policy"))]
+    #[diagnostic(
+        code(cedar::parse::unexpected_template),
+        help("try removing the template slot(s) from this policy")
+    )]
     UnexpectedTemplate {
         /// Slot that was found (which is not valid in a static policy)
         slot: cst::Slot,
     },
     /// Returned when we attempt to parse a policy with malformed or conflicting annotations
     #[error("this policy uses poorly formed or duplicate annotations")]
+    #[diagnostic(code(cedar::parse::bad_annotations))]
     BadAnnotations,
     /// Returned when a policy contains template slots in a when/unless clause. This is not currently supported. See RFC 3
     #[error("found template slot {slot} in a `{clausetype}` clause")]
-    #[diagnostic(help("slots are currently unsupported in `{clausetype}` clauses"))]
+    #[diagnostic(
+        code(cedar::parse::slots_in_condition_clause),
+        help("slots are currently unsupported in `{clausetype}` clauses")
+    )]
     SlotsInConditionClause {
         /// Slot that was found in a when/unless clause
         slot: cst::Slot,
@@ -178,38 +230,53 @@ pub enum ToASTErrorKind {
     },
     /// Returned when a policy is missing one of the 3 required scope clauses. (`principal`, `action`, and `resource`)
     #[error("this policy is missing the `{0}` variable in the scope")]
+    #[diagnostic(code(cedar::parse::missing_scope_constraint))]
     MissingScopeConstraint(Var),
     /// Returned when a policy has an extra scope clause. This is not valid syntax
     #[error("this policy has an extra head constraint in the scope: `{0}`")]
-    #[diagnostic(help(
-        "a policy must have exactly `principal`, `action`, and `resource` constraints"
-    ))]
+    #[diagnostic(
+        code(cedar::parse::extra_head_constraints),
+        help("a policy must have exactly `principal`, `action`, and `resource` constraints")
+    )]
     ExtraHeadConstraints(cst::VariableDef),
     /// Returned when a policy uses a reserved keyword as an identifier.
     #[error("this identifier is reserved and cannot be used: `{0}`")]
+    #[diagnostic(code(cedar::parse::reserved_identifier))]
     ReservedIdentifier(cst::Ident),
     /// Returned when a policy contains an invalid identifier.
     /// This error is not currently returned, but is here for future-proofing.
     /// See [`cst::Ident::Invalid`]
     #[error("not a valid identifier: `{0}`")]
+    #[diagnostic(code(cedar::parse::invalid_identifier))]
     InvalidIdentifier(String),
     /// Returned when a policy uses a effect keyword beyond `permit` or `forbid`
     #[error("not a valid policy effect: `{0}`")]
-    #[diagnostic(help("effect must be either `permit` or `forbid`"))]
+    #[diagnostic(
+        code(cedar::parse::invalid_effect),
+        help("effect must be either `permit` or `forbid`")
+    )]
     InvalidEffect(cst::Ident),
     /// Returned when a policy uses a condition keyword beyond `when` or `unless`
     #[error("not a valid policy condition: `{0}`")]
-    #[diagnostic(help("condition must be either `when` or `unless`"))]
+    #[diagnostic(
+        code(cedar::parse::invalid_condition),
+        help("condition must be either `when` or `unless`")
+    )]
     InvalidCondition(cst::Ident),
     /// Returned when a policy uses a variable in the scope beyond `principal`, `action`, or `resource`
     #[error("expected a variable that is valid in the policy scope; found: `{0}`")]
-    #[diagnostic(help("must be one of `principal`, `action`, or `resource`"))]
+    #[diagnostic(
+        code(cedar::parse::invalid_scope_constraint_variable),
+        help("must be one of `principal`, `action`, or `resource`")
+    )]
     InvalidScopeConstraintVariable(cst::Ident),
     /// Returned when a policy contains an invalid method name
     #[error("not a valid method name: `{0}`")]
+    #[diagnostic(code(cedar::parse::invalid_method_name))]
     InvalidMethodName(String),
     /// Returned when a policy scope clause contains the wrong variable. (`principal` must be in the first clause, etc...)
     #[error("the variable `{got}` is invalid in this policy scope clause, the variable `{expected}` is expected")]
+    #[diagnostic(code(cedar::parse::incorrect_variable))]
     IncorrectVariable {
         /// The variable that is expected in this clause
         expected: Var,
@@ -218,61 +285,91 @@ pub enum ToASTErrorKind {
     },
     /// Returned when a policy scope clauses uses an operator beyond `==` or `in`.
     #[error("not a valid policy scope constraint: {0}")]
-    #[diagnostic(help("policy scope constraints must either `==`, `in`, `is`, or `_ is _ in _`"))]
+    #[diagnostic(
+        code(cedar::parse::invalid_constraint_operator),
+        help("policy scope constraints must either `==`, `in`, `is`, or `_ is _ in _`")
+    )]
     InvalidConstraintOperator(cst::RelOp),
     /// Returned when the right hand side of `==` in a policy scope clause is not a single Entity UID or a template slot.
     /// This is valid in Cedar conditions, but not in the Scope
     #[error(
         "the right hand side of equality in the policy scope must be a single entity uid or a template slot"
     )]
+    #[diagnostic(code(cedar::parse::invalid_scope_equality_rhs))]
     InvalidScopeEqualityRHS,
     /// Returned when an Entity UID used as an action does not have the type `Action`
     #[error("expected an entity uid with the type `Action` but got `{0}`")]
-    #[diagnostic(help("action entities must have type `Action`, optionally in a namespace"))]
+    #[diagnostic(
+        code(cedar::parse::invalid_action_type),
+        help("action entities must have type `Action`, optionally in a namespace")
+    )]
     InvalidActionType(crate::ast::EntityUID),
     /// Returned when a condition clause is empty
     #[error("{}condition clause cannot be empty", match .0 { Some(ident) => format!("`{}` ", ident), None => "".to_string() })]
+    #[diagnostic(code(cedar::parse::empty_clause))]
     EmptyClause(Option<cst::Ident>),
     /// Returned when the internal invariant around annotation info has been violated
     #[error("internal invariant violated. No parse errors were reported but annotation information was missing")]
+    #[diagnostic(code(cedar::parse::annotation_invariant_violation))]
     AnnotationInvariantViolation,
     /// Returned when membership chains do not resolve to an expression, violating an internal invariant
     #[error("internal invariant violated. Membership chain did not resolve to an expression")]
+    #[diagnostic(code(cedar::parse::membership_invariant_violation))]
     MembershipInvariantViolation,
     /// Returned for a non-parse-able string literal
     #[error("invalid string literal: `{0}`")]
+    #[diagnostic(code(cedar::parse::invalid_string))]
     InvalidString(String),
     /// Returned for attempting to use an arbitrary variable name. Cedar does not support arbitrary variables.
     #[error("arbitrary variables are not supported; the valid Cedar variables are `principal`, `action`, `resource`, and `context`")]
-    #[diagnostic(help("did you mean to enclose `{0}` in quotes to make a string?"))]
+    #[diagnostic(
+        code(cedar::parse::arbitrary_variable),
+        help("did you mean to enclose `{0}` in quotes to make a string?")
+    )]
     ArbitraryVariable(SmolStr),
     /// Returned for attempting to use an invalid attribute name
     #[error("not a valid attribute name: `{0}`")]
-    #[diagnostic(help("attribute names can either be identifiers or string literals"))]
+    #[diagnostic(
+        code(cedar::parse::invalid_attribute),
+        help("attribute names can either be identifiers or string literals")
+    )]
     InvalidAttribute(SmolStr),
     /// Returned for attempting to use an invalid attribute name in a record name
     #[error("record literal has invalid attributes")]
+    #[diagnostic(code(cedar::parse::invalid_attributes_in_record_literal))]
     InvalidAttributesInRecordLiteral,
     /// Returned for attempting to use an attribute with a namespace
     #[error("`{0}` cannot be used as an attribute as it contains a namespace")]
+    #[diagnostic(code(cedar::parse::path_as_attribute))]
     PathAsAttribute(String),
     /// Returned when a policy attempts to call a method function-style
     #[error("`{0}` is a method, not a function")]
-    #[diagnostic(help("use a method-style call: `e.{0}(..)`"))]
+    #[diagnostic(
+        code(cedar::parse::function_call_on_method),
+        help("use a method-style call: `e.{0}(..)`")
+    )]
     FunctionCallOnMethod(crate::ast::Id),
     /// Returned when a policy attempts to call a function in the method style
     #[error("`{0}` is a function, not a method")]
-    #[diagnostic(help("use a function-style call: `{0}(..)`"))]
+    #[diagnostic(
+        code(cedar::parse::method_call_on_function),
+        help("use a function-style call: `{0}(..)`")
+    )]
     MethodCallOnFunction(crate::ast::Id),
     /// Returned when the right hand side of a `like` expression is not a constant pattern literal
     #[error("right hand side of a `like` expression must be a pattern literal, but got `{0}`")]
+    #[diagnostic(code(cedar::parse::invalid_pattern))]
     InvalidPattern(String),
     /// Returned when the right hand side of a `is` expression is not an entity type name
     #[error("right hand side of an `is` expression must be an entity type name, but got `{0}`")]
-    #[diagnostic(help("try using `==` to test for equality"))]
+    #[diagnostic(
+        code(cedar::parse::is_invalid_name),
+        help("try using `==` to test for equality")
+    )]
     IsInvalidName(String),
     /// Returned when an unexpected node is in the policy scope clause
     #[error("expected {expected}, found {got}")]
+    #[diagnostic(code(cedar::parse::wrong_node))]
     WrongNode {
         /// What the expected AST node kind was
         expected: &'static str,
@@ -285,53 +382,74 @@ pub enum ToASTErrorKind {
     /// Returned when a policy contains ambiguous ordering of operators.
     /// This can be resolved by using parenthesis to make order explicit
     #[error("multiple relational operators (>, ==, in, etc.) must be used with parentheses to make ordering explicit")]
+    #[diagnostic(code(cedar::parse::ambiguous_operators))]
     AmbiguousOperators,
     /// Returned when a policy uses the division operator (`/`), which is not supported
     #[error("division is not supported")]
+    #[diagnostic(code(cedar::parse::unsupported_division))]
     UnsupportedDivision,
     /// Returned when a policy uses the remainder/modulo operator (`%`), which is not supported
     #[error("remainder/modulo is not supported")]
+    #[diagnostic(code(cedar::parse::unsupported_modulo))]
     UnsupportedModulo,
     /// Returned when a policy attempts to multiply by a non-constant integer
     #[error("multiplication must be by an integer literal")]
+    #[diagnostic(code(cedar::parse::non_constant_multiplication))]
     NonConstantMultiplication,
     /// Returned when a policy contains an integer literal that is out of range
     #[error("integer literal `{0}` is too large")]
-    #[diagnostic(help("maximum allowed integer literal is `{}`", InputInteger::MAX))]
+    #[diagnostic(
+        code(cedar::parse::integer_literal_too_large),
+        help("maximum allowed integer literal is `{}`", InputInteger::MAX)
+    )]
     IntegerLiteralTooLarge(u64),
     /// Returned when a unary operator is chained more than 4 times in a row
     #[error("too many occurrences of `{0}`")]
-    #[diagnostic(help("cannot chain more the 4 applications of a unary operator"))]
+    #[diagnostic(
+        code(cedar::parse::unary_op_limit),
+        help("cannot chain more the 4 applications of a unary operator")
+    )]
     UnaryOpLimit(crate::ast::UnaryOp),
     /// Returned when a variable is called as a function, which is not allowed.
     /// Functions are not first class values in Cedar
     #[error("`{0}(...)` is not a valid function call")]
-    #[diagnostic(help("variables cannot be called as functions"))]
+    #[diagnostic(
+        code(cedar::parse::variable_call),
+        help("variables cannot be called as functions")
+    )]
     VariableCall(crate::ast::Var),
     /// Returned when a policy attempts to call a method on a value that has no methods
     #[error("attempted to call `{0}.{1}`, but `{0}` does not have any methods")]
+    #[diagnostic(code(cedar::parse::no_methods))]
     NoMethods(crate::ast::Name, ast::Id),
     /// Returned when a policy attempts to call a function that does not exist
     #[error("`{0}` is not a function")]
+    #[diagnostic(code(cedar::parse::not_a_function))]
     NotAFunction(crate::ast::Name),
     /// Returned when a policy attempts to write an entity literal
     #[error("entity literals are not supported")]
+    #[diagnostic(code(cedar::parse::unsupported_entity_literals))]
     UnsupportedEntityLiterals,
     /// Returned when an expression is the target of a function call.
     /// Functions are not first class values in Cedar
     #[error("function calls must be of the form: `<name>(arg1, arg2, ...)`")]
+    #[diagnostic(code(cedar::parse::expression_call))]
     ExpressionCall,
     /// Returned when a policy attempts to access the fields of a value with no fields
     #[error("incorrect member access `{0}.{1}`, `{0}` has no fields or methods")]
+    #[diagnostic(code(cedar::parse::invalid_access))]
     InvalidAccess(crate::ast::Name, SmolStr),
     /// Returned when a policy attempts to index on a fields of a value with no fields
     #[error("incorrect indexing expression `{0}[{1}]`, `{0}` has no fields")]
+    #[diagnostic(code(cedar::parse::invalid_index))]
     InvalidIndex(crate::ast::Name, SmolStr),
     /// Returned when the contents of an indexing expression is not a string literal
     #[error("the contents of an index expression must be a string literal")]
+    #[diagnostic(code(cedar::parse::non_string_index))]
     NonStringIndex,
     /// Returned when the same key appears two or more times in a single record literal
     #[error("duplicate key `{key}` in record literal")]
+    #[diagnostic(code(cedar::parse::duplicate_key_in_record_literal))]
     DuplicateKeyInRecordLiteral {
         /// The key that appeared two or more times
         key: SmolStr,
@@ -340,14 +458,18 @@ pub enum ToASTErrorKind {
     /// syntax was not adopted, but `is` can be used to write type constraints
     /// in the policy scope.
     #[error("type constraints using `:` are not supported")]
-    #[diagnostic(help("try using `is` instead"))]
+    #[diagnostic(code(cedar::parse::type_constraints), help("try using `is` instead"))]
     TypeConstraints,
     /// Returned when a policy uses a path in an invalid context
     #[error("a path is not valid in this context")]
+    #[diagnostic(code(cedar::parse::invalid_path))]
     InvalidPath,
     /// Returned when a string needs to be fully normalized
     #[error("`{kind}` needs to be normalized (e.g., whitespace removed): `{src}`")]
-    #[diagnostic(help("the normalized form is `{normalized_src}`"))]
+    #[diagnostic(
+        code(cedar::parse::non_normalized_string),
+        help("the normalized form is `{normalized_src}`")
+    )]
     NonNormalizedString {
         /// The kind of string we are expecting
         kind: &'static str,
@@ -358,15 +480,19 @@ pub enum ToASTErrorKind {
     },
     /// Returned when a CST node is empty
     #[error("data should not be empty")]
+    #[diagnostic(code(cedar::parse::missing_node_data))]
     MissingNodeData,
     /// Returned when the right hand side of a `has` expression is neither a field name or a string literal
     #[error("the right hand side of a `has` expression must be a field name or string literal")]
+    #[diagnostic(code(cedar::parse::has_non_literal_rhs))]
     HasNonLiteralRHS,
     /// Returned when a CST expression is invalid
     #[error("`{0}` is not a valid expression")]
+    #[diagnostic(code(cedar::parse::invalid_expression))]
     InvalidExpression(cst::Name),
     /// Returned when a function or method is called with the wrong arity
     #[error("call to `{name}` requires exactly {expected} argument{}, but got {got} argument{}", if .expected == &1 { "" } else { "s" }, if .got == &1 { "" } else { "s" })]
+    #[diagnostic(code(cedar::parse::wrong_arity))]
     WrongArity {
         /// Name of the function or method being called
         name: &'static str,
@@ -389,7 +515,10 @@ pub enum ToASTErrorKind {
     InvalidIs(#[from] InvalidIsError),
     /// Returned when a policy contains a template slot other than `?principal` or `?resource`
     #[error("`{0}` is not a valid template slot")]
-    #[diagnostic(help("a template slot may only be `?principal` or `?resource`"))]
+    #[diagnostic(
+        code(cedar::parse::invalid_slot),
+        help("a template slot may only be `?principal` or `?resource`")
+    )]
     InvalidSlot(SmolStr),
 }
 
@@ -425,6 +554,7 @@ impl ToASTErrorKind {
 pub enum RefCreationError {
     /// Error surrounding EntityUIds/Template slots in policy scopes
     #[error("expected {}, got: {got}", match .expected { Either::Left(r) => r.to_string(), Either::Right((r1, r2)) => format!("{r1} or {r2}") })]
+    #[diagnostic(code(cedar::parse::wrong_entity_argument))]
     RefCreation {
         /// What kinds of references the given scope clause required.
         /// Some scope clauses require exactly one kind of reference, some require one of two
@@ -477,11 +607,17 @@ impl std::fmt::Display for Ref {
 pub enum InvalidIsError {
     /// The action scope may not contain an `is`
     #[error("`is` cannot appear in the action scope")]
-    #[diagnostic(help("try moving `action is ..` into a `when` condition"))]
+    #[diagnostic(
+        code(cedar::parse::is_in_action_scope),
+        help("try moving `action is ..` into a `when` condition")
+    )]
     ActionScope,
     /// An `is` cannot appear with this operator in the policy scope
     #[error("`is` cannot appear in the scope at the same time as `{0}`")]
-    #[diagnostic(help("try moving `is` into a `when` condition"))]
+    #[diagnostic(
+        code(cedar::parse::is_with_scope_operator),
+        help("try moving `is` into a `when` condition")
+    )]
     WrongOp(cst::RelOp),
 }
 
@@ -538,6 +674,17 @@ impl Display for ToCSTError {
 }
 
 impl Diagnostic for ToCSTError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        let code = match &self.err {
+            OwnedRawParseError::InvalidToken { .. } => "cedar::parse::invalid_token",
+            OwnedRawParseError::UnrecognizedEof { .. } => "cedar::parse::unexpected_end_of_input",
+            OwnedRawParseError::UnrecognizedToken { .. } => "cedar::parse::unexpected_token",
+            OwnedRawParseError::ExtraToken { .. } => "cedar::parse::extra_token",
+            OwnedRawParseError::User { .. } => "cedar::parse::syntax",
+        };
+        Some(Box::new(code))
+    }
+
     fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
         let primary_source_span = self.primary_source_span();
         let labeled_span = match &self.err {
@@ -582,6 +729,7 @@ lazy_static! {
         ("IDENTIFIER", "identifier"),
         ("NUMBER", "number"),
         ("STRINGLIT", "string literal"),
+        ("RAWSTRINGLIT", "raw string literal"),
     ]);
 }
 
@@ -629,6 +777,13 @@ impl ParseErrors {
     pub fn errors_as_strings(&self) -> Vec<String> {
         self.0.iter().map(ToString::to_string).collect()
     }
+
+    /// Iterate over the errors, which have their kinds, messages, and source
+    /// spans. `ParseErrors` also iterates over the errors by value with
+    /// `into_iter()`.
+    pub fn errors(&self) -> impl Iterator<Item = &ParseError> {
+        self.0.iter()
+    }
 }
 
 impl Display for ParseErrors {
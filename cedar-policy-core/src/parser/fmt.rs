@@ -435,6 +435,8 @@ impl fmt::Display for Str {
             Str::String(s) | Str::Invalid(s) => {
                 write!(f, "\"{}\"", s)
             }
+            Str::Raw { raw, .. } if raw.contains('"') => write!(f, "r#\"{raw}\"#"),
+            Str::Raw { raw, .. } => write!(f, "r\"{raw}\""),
         }
     }
 }
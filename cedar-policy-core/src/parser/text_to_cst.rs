@@ -33,6 +33,7 @@ lalrpop_mod!(
 
 use super::*;
 use node::Node;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 /// This helper function calls a generated parser, collects errors that could be
@@ -48,6 +49,25 @@ fn parse_collect_errors<'a, P, T>(
     ) -> Result<T, err::RawParseError<'a>>,
     text: &'a str,
 ) -> Result<T, err::ParseErrors> {
+    match parse_recovering_errors(parser, parse, text) {
+        (Some(parsed), errors) if errors.is_empty() => Ok(parsed),
+        (_, errors) => Err(errors),
+    }
+}
+
+/// Like `parse_collect_errors()`, but returns the result of the parser even if
+/// it recovered from errors, along with those errors. The result is `None` if
+/// the parser couldn't recover.
+fn parse_recovering_errors<'a, P, T>(
+    parser: &P,
+    parse: impl FnOnce(
+        &P,
+        &mut Vec<err::RawErrorRecovery<'a>>,
+        &Arc<str>,
+        &'a str,
+    ) -> Result<T, err::RawParseError<'a>>,
+    text: &'a str,
+) -> (Option<T>, err::ParseErrors) {
     let mut errs = Vec::new();
     let result = parse(parser, &mut errs, &Arc::from(text), text);
 
@@ -55,17 +75,12 @@ fn parse_collect_errors<'a, P, T>(
         .into_iter()
         .map(err::ToCSTError::from_raw_err_recovery)
         .collect();
-    let parsed = match result {
-        Ok(parsed) => parsed,
+    match result {
+        Ok(parsed) => (Some(parsed), errors),
         Err(e) => {
             errors.push(err::ToCSTError::from_raw_parse_err(e));
-            return Err(errors);
+            (None, errors)
         }
-    };
-    if errors.is_empty() {
-        Ok(parsed)
-    } else {
-        Err(errors)
     }
 }
 
@@ -85,6 +100,80 @@ pub fn parse_policies(text: &str) -> Result<Node<Option<cst::Policies>>, err::Pa
     parse_collect_errors(&*POLICIES_PARSER, grammar::PoliciesParser::parse, text)
 }
 
+/// Create CST for multiple policies from text, continuing after syntax
+/// errors. The CST has the policies with errors as nodes without data, and is
+/// `None` if the parser couldn't continue after an error. After a syntax error,
+/// the rest of the policy up to its `;` is skipped, so a policy with an error
+/// is a single node with a single syntax error.
+pub fn parse_policies_with_recovery(
+    text: &str,
+) -> (Option<Node<Option<cst::Policies>>>, err::ParseErrors) {
+    match parse_recovering_errors(&*POLICIES_PARSER, grammar::PoliciesParser::parse, text) {
+        (Some(policies), errs) => {
+            let (policies, errs) = skip_to_end_of_policies(policies, errs);
+            (Some(policies), errs)
+        }
+        (None, errs) => (None, errs),
+    }
+}
+
+/// The parser resumes after a syntax error at the first token it can continue
+/// with, so the rest of a policy with an error may parse as more policies, each
+/// with errors of its own. Merge a policy with an error and the nodes following
+/// it up to the one ending with the `;` of the policy into a single node without
+/// data, and keep only the first error in it.
+fn skip_to_end_of_policies(
+    policies: Node<Option<cst::Policies>>,
+    mut errs: err::ParseErrors,
+) -> (Node<Option<cst::Policies>>, err::ParseErrors) {
+    let Node { node, loc } = policies;
+    let Some(cst::Policies(nodes)) = node else {
+        return (Node::with_source_loc(None, loc), errs);
+    };
+    // nodes end at tokens, and the only token ending with `;` is `;` itself
+    let ends_policy = |p: &Node<Option<cst::Policy>>| {
+        loc.src
+            .get(..p.loc.end())
+            .is_some_and(|before| before.ends_with(';'))
+    };
+    let mut merged = Vec::with_capacity(nodes.len());
+    // the ranges of the merged nodes, in which only the first error is kept
+    let mut skipped = Vec::new();
+    let mut nodes = nodes.into_iter();
+    while let Some(p) = nodes.next() {
+        if p.node.is_some() || ends_policy(&p) {
+            merged.push(p);
+            continue;
+        }
+        let mut end = p.loc.end();
+        for next in nodes.by_ref() {
+            end = next.loc.end();
+            if ends_policy(&next) {
+                break;
+            }
+        }
+        skipped.push(p.loc.start()..end);
+        merged.push(Node::with_source_loc(
+            None,
+            Loc::new(p.loc.start()..end, Arc::clone(&loc.src)),
+        ));
+    }
+    let mut reported = HashSet::new();
+    errs.retain(|err| {
+        match err
+            .primary_source_span()
+            .and_then(|span| skipped.iter().position(|r| r.contains(&span.offset())))
+        {
+            Some(i) => reported.insert(i),
+            None => true,
+        }
+    });
+    (
+        Node::with_source_loc(Some(cst::Policies(merged)), loc),
+        errs,
+    )
+}
+
 /// Create CST for one policy statement from text
 pub fn parse_policy(text: &str) -> Result<Node<Option<cst::Policy>>, err::ParseErrors> {
     parse_collect_errors(&*POLICY_PARSER, grammar::PolicyParser::parse, text)
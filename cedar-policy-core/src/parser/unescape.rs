@@ -68,6 +68,7 @@ pub(crate) fn to_pattern(s: &str) -> Result<Vec<PatternElem>, Vec<UnescapeError>
 
 /// Errors generated when processing escapes
 #[derive(Debug, Diagnostic, Error, PartialEq, Eq)]
+#[diagnostic(code(cedar::parse::invalid_escape))]
 pub struct UnescapeError {
     /// underlying EscapeError
     err: EscapeError,
@@ -220,4 +221,33 @@ mod test {
             [ParseError::ToAST(_), ParseError::ToAST(_)]
         ));
     }
+
+    #[test]
+    fn test_raw_strings() {
+        let mut errs = ParseErrors::new();
+        let mut parse = |text: &str| {
+            text_to_cst::parse_expr(text)
+                .expect("failed parsing")
+                .to_expr(&mut errs)
+                .expect("failed conversion")
+        };
+        for (text, value) in [
+            (r#"r"C:\docs\*.txt""#, r"C:\docs\*.txt"),
+            (r##"r#"{"a": "b\n"}"#"##, r#"{"a": "b\n"}"#),
+            (r##"r#"a"""#"##, "a\"\""),
+            ("r\"two\r\nlines\"", "two\r\nlines"),
+            (r#"r"""#, ""),
+        ] {
+            assert!(
+                matches!(parse(text).expr_kind(), ast::ExprKind::Lit(ast::Literal::String(s)) if s == value),
+                "{text}"
+            );
+        }
+
+        // no escapes in patterns either, but `*` is still a wildcard
+        assert!(matches!(
+            parse(r#""a" like r"\d*\*""#).expr_kind(),
+            ast::ExprKind::Like { expr: _, pattern } if pattern.to_string() == r"\\d*\\*"
+        ));
+    }
 }
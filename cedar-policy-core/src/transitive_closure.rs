@@ -18,7 +18,7 @@
 //! This is a generic utility, and not specific to Cedar.
 
 use std::cmp::Eq;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt::{Debug, Display};
 use std::hash::Hash;
 
@@ -56,31 +56,93 @@ where
     K: Clone + Eq + Hash + Debug + Display,
     V: TCNode<K>,
 {
-    let res = compute_tc_internal::<K, V>(nodes);
-    if res.is_ok() && enforce_dag {
-        return enforce_dag_from_tc(nodes);
+    let ancestors = collect_ancestors(nodes)?;
+    if enforce_dag {
+        // check before adding the ancestors as edges, so that a cycle is
+        // reported along the original edges
+        enforce_dag_from_ancestors(nodes, &ancestors)?;
     }
-    res
+    add_ancestor_edges(nodes, &ancestors);
+    Ok(())
+}
+
+/// Like [`compute_tc`], but computes the ancestors of each node in parallel,
+/// on `rayon`'s global thread pool.
+#[cfg(feature = "rayon")]
+pub fn compute_tc_parallel<K, V>(nodes: &mut HashMap<K, V>, enforce_dag: bool) -> Result<(), K>
+where
+    K: Clone + Eq + Hash + Debug + Display + Send + Sync,
+    V: TCNode<K> + Send + Sync,
+{
+    use rayon::prelude::*;
+    // As in `collect_ancestors`, first collect the ancestors of every node,
+    // which only needs immutable borrows of `nodes`, so it can be done for all
+    // the nodes in parallel
+    let ancestors = nodes
+        .par_iter()
+        .map(|(key, node)| {
+            let mut this_node_ancestors = HashSet::new();
+            add_ancestors_to_set(node, nodes, &mut this_node_ancestors)?;
+            Ok((key.clone(), this_node_ancestors))
+        })
+        .collect::<Result<HashMap<K, HashSet<K>>, K>>()?;
+    if enforce_dag {
+        enforce_dag_from_ancestors(nodes, &ancestors)?;
+    }
+    nodes.par_iter_mut().for_each(|(key, node)| {
+        // PANIC SAFETY All nodes in `ancestors` came from `nodes`
+        #[allow(clippy::expect_used)]
+        for ancestor_uid in ancestors
+            .get(key)
+            .expect("shouldn't have added any new values to the `nodes` map")
+        {
+            node.add_edge_to(ancestor_uid.clone());
+        }
+    });
+    Ok(())
 }
 
 /// Given graph as a map from keys with type `K` to implementations of `TCNode`
 /// with type `V`, compute the transitive closure of the hierarchy. In case of
 /// error, the result contains an error structure `Err<K>` which contains the
 /// keys (with type `K`) for the nodes in the graph which caused the error.
+#[cfg(test)]
 fn compute_tc_internal<K, V>(nodes: &mut HashMap<K, V>) -> Result<(), K>
 where
     K: Clone + Eq + Hash + Debug + Display,
     V: TCNode<K>,
 {
-    // To avoid needing both immutable and mutable borrows of `nodes`,
-    // we collect all the needed updates in this structure
-    // (maps keys to ancestor UIDs to add to it)
-    // and then do all the updates at once in a second loop
+    let ancestors = collect_ancestors(nodes)?;
+    add_ancestor_edges(nodes, &ancestors);
+    Ok(())
+}
+
+/// Collect the transitive ancestors of each node in `nodes`, keyed by the key
+/// of the node.
+///
+/// To avoid needing both immutable and mutable borrows of `nodes`, we collect
+/// all the needed updates in this structure, and then do all the updates at
+/// once in `add_ancestor_edges()`.
+fn collect_ancestors<K, V>(nodes: &HashMap<K, V>) -> Result<HashMap<K, HashSet<K>>, K>
+where
+    K: Clone + Eq + Hash + Debug + Display,
+    V: TCNode<K>,
+{
     let mut ancestors: HashMap<K, HashSet<K>> = HashMap::new();
     for node in nodes.values() {
         let this_node_ancestors: &mut HashSet<K> = ancestors.entry(node.get_key()).or_default();
         add_ancestors_to_set(node, nodes, this_node_ancestors)?;
     }
+    Ok(ancestors)
+}
+
+/// Add an edge from each node in `nodes` to each of its `ancestors`, as
+/// collected by `collect_ancestors()`.
+fn add_ancestor_edges<K, V>(nodes: &mut HashMap<K, V>, ancestors: &HashMap<K, HashSet<K>>)
+where
+    K: Clone + Eq + Hash + Debug + Display,
+    V: TCNode<K>,
+{
     for node in nodes.values_mut() {
         // PANIC SAFETY All nodes in `ancestors` came from `nodes`
         #[allow(clippy::expect_used)]
@@ -91,7 +153,6 @@ where
             node.add_edge_to(ancestor_uid.clone());
         }
     }
-    Ok(())
 }
 
 /// Given a graph (as a map from keys to `TCNode`), enforce that
@@ -176,6 +237,7 @@ where
         let key = entity.get_key();
         if entity.out_edges().contains(&key) {
             return Err(TcError::HasCycle {
+                cycle: find_cycle(&key, entities),
                 vertex_with_loop: key,
             });
         }
@@ -183,6 +245,68 @@ where
     Ok(())
 }
 
+/// Given a graph (as a map from keys to `TCNode`) and the transitive ancestors
+/// of each node in it (as collected by `collect_ancestors()`), enforce that the
+/// graph is a DAG, i.e., that no node is its own ancestor.
+fn enforce_dag_from_ancestors<K, V>(
+    nodes: &HashMap<K, V>,
+    ancestors: &HashMap<K, HashSet<K>>,
+) -> Result<(), K>
+where
+    K: Clone + Eq + Hash + Debug + Display,
+    V: TCNode<K>,
+{
+    for (key, this_node_ancestors) in ancestors {
+        if this_node_ancestors.contains(key) {
+            return Err(TcError::HasCycle {
+                vertex_with_loop: key.clone(),
+                cycle: find_cycle(key, nodes),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Find a shortest cycle through `start` along the edges of the graph `nodes`,
+/// as the keys of the nodes on it, starting and ending with `start`.
+///
+/// If there is no such cycle, this returns just `[start]`.
+fn find_cycle<K, V>(start: &K, nodes: &HashMap<K, V>) -> Vec<K>
+where
+    K: Clone + Eq + Hash + Debug + Display,
+    V: TCNode<K>,
+{
+    // breadth-first search from `start`, remembering how each node was reached
+    let mut predecessors: HashMap<&K, &K> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some(key) = queue.pop_front() {
+        let Some(node) = nodes.get(key) else {
+            continue;
+        };
+        for next in node.out_edges() {
+            if next == start {
+                let mut cycle = vec![start.clone()];
+                let mut current = key;
+                while current != start {
+                    cycle.push(current.clone());
+                    match predecessors.get(current) {
+                        Some(predecessor) => current = predecessor,
+                        None => break,
+                    }
+                }
+                cycle.push(start.clone());
+                cycle.reverse();
+                return cycle;
+            }
+            if !predecessors.contains_key(next) {
+                predecessors.insert(next, key);
+                queue.push_back(next);
+            }
+        }
+    }
+    vec![start.clone()]
+}
+
 // PANIC SAFETY test cases
 #[allow(clippy::indexing_slicing)]
 // PANIC SAFETY: Unit Test Code
@@ -567,7 +691,9 @@ mod tests {
         // fails cycle check
         match enforce_dag_from_tc(&entities) {
             Ok(_) => panic!("enforce_dag_from_tc should have returned an error"),
-            Err(TcError::HasCycle { vertex_with_loop }) => {
+            Err(TcError::HasCycle {
+                vertex_with_loop, ..
+            }) => {
                 assert!(vertex_with_loop == EntityUID::with_eid("B"));
             }
             Err(_) => panic!("Unexpected error in enforce_dag_from_tc"),
@@ -584,7 +710,9 @@ mod tests {
         // still fails cycle check
         match enforce_dag_from_tc(&entities) {
             Ok(_) => panic!("enforce_dag_from_tc should have returned an error"),
-            Err(TcError::HasCycle { vertex_with_loop }) => {
+            Err(TcError::HasCycle {
+                vertex_with_loop, ..
+            }) => {
                 assert!(vertex_with_loop == EntityUID::with_eid("B"));
             }
             Err(_) => panic!("Unexpected error in enforce_dag_from_tc"),
@@ -613,7 +741,9 @@ mod tests {
         // fails cycle check
         match enforce_dag_from_tc(&entities) {
             Ok(_) => panic!("enforce_dag_from_tc should have returned an error"),
-            Err(TcError::HasCycle { vertex_with_loop }) => {
+            Err(TcError::HasCycle {
+                vertex_with_loop, ..
+            }) => {
                 assert!(
                     vertex_with_loop == EntityUID::with_eid("A")
                         || vertex_with_loop == EntityUID::with_eid("B")
@@ -636,7 +766,9 @@ mod tests {
         // still fails cycle check
         match enforce_dag_from_tc(&entities) {
             Ok(_) => panic!("enforce_dag_from_tc should have returned an error"),
-            Err(TcError::HasCycle { vertex_with_loop }) => {
+            Err(TcError::HasCycle {
+                vertex_with_loop, ..
+            }) => {
                 assert!(
                     vertex_with_loop == EntityUID::with_eid("A")
                         || vertex_with_loop == EntityUID::with_eid("B")
@@ -689,7 +821,9 @@ mod tests {
         // still fails cycle check
         match enforce_dag_from_tc(&entities) {
             Ok(_) => panic!("enforce_dag_from_tc should have returned an error"),
-            Err(TcError::HasCycle { vertex_with_loop }) => {
+            Err(TcError::HasCycle {
+                vertex_with_loop, ..
+            }) => {
                 // two possible cycles
                 assert!(
                     vertex_with_loop == EntityUID::with_eid("B")
@@ -747,8 +881,108 @@ mod tests {
             Ok(_) => panic!("enforce_dag_from_tc should have returned an error"),
             Err(TcError::HasCycle {
                 vertex_with_loop: _,
+                ..
             }) => (), // Every vertex is in a cycle
             Err(_) => panic!("Unexpected error in enforce_dag_from_tc"),
         }
     }
+
+    #[test]
+    fn cycle_path() {
+        //          D
+        //        /
+        // A -> B -> C -> A
+        let mut a = Entity::with_uid(EntityUID::with_eid("A"));
+        a.add_ancestor(EntityUID::with_eid("B"));
+        let mut b = Entity::with_uid(EntityUID::with_eid("B"));
+        b.add_ancestor(EntityUID::with_eid("C"));
+        b.add_ancestor(EntityUID::with_eid("D"));
+        let mut c = Entity::with_uid(EntityUID::with_eid("C"));
+        c.add_ancestor(EntityUID::with_eid("A"));
+        let d = Entity::with_uid(EntityUID::with_eid("D"));
+        let mut entities = HashMap::from([(a.uid(), a), (b.uid(), b), (c.uid(), c), (d.uid(), d)]);
+        // the cycle is reported along the original edges, starting from
+        // whichever of its vertices was found first
+        let cycles = [
+            ["A", "B", "C", "A"],
+            ["B", "C", "A", "B"],
+            ["C", "A", "B", "C"],
+        ]
+        .map(|cycle| cycle.map(EntityUID::with_eid).to_vec());
+        match compute_tc(&mut entities.clone(), true) {
+            Err(TcError::HasCycle {
+                vertex_with_loop,
+                cycle,
+            }) => {
+                assert_eq!(cycle.first(), Some(&vertex_with_loop));
+                assert!(cycles.contains(&cycle), "{cycle:?}");
+            }
+            res => panic!("expected a cycle, got {res:?}"),
+        }
+
+        // after computing TC, each vertex on the cycle has an edge to itself
+        assert!(compute_tc_internal(&mut entities).is_ok());
+        let err = enforce_dag_from_tc(&entities).unwrap_err();
+        match &err {
+            TcError::HasCycle {
+                vertex_with_loop,
+                cycle,
+            } => {
+                assert_eq!(
+                    cycle,
+                    &vec![vertex_with_loop.clone(), vertex_with_loop.clone()]
+                );
+                assert_eq!(
+                    err.to_string(),
+                    format!(
+                        "input graph has a cycle containing vertex `{vertex_with_loop}`: \
+                         `{vertex_with_loop}` -> `{vertex_with_loop}`"
+                    )
+                );
+            }
+            err => panic!("expected a cycle, got {err:?}"),
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel() {
+        // a binary tree of depth 10, where each node is a child of its parent
+        // in the tree, plus a node with the leftmost leaf as its parent
+        let uid = |i: usize| EntityUID::with_eid(&i.to_string());
+        let mut entities: HashMap<_, _> = (1..2048)
+            .map(|i| {
+                let mut entity = Entity::with_uid(uid(i));
+                if i > 1 {
+                    entity.add_ancestor(uid(i / 2));
+                }
+                (entity.uid(), entity)
+            })
+            .collect();
+        let mut extra = Entity::with_uid(uid(0));
+        extra.add_ancestor(uid(1024));
+        entities.insert(extra.uid(), extra);
+        let mut sequential = entities.clone();
+        assert!(compute_tc(&mut sequential, true).is_ok());
+        assert!(compute_tc_parallel(&mut entities, true).is_ok());
+        for (key, entity) in &entities {
+            assert!(entity.deep_eq(&sequential[key]));
+        }
+        let extra = &entities[&uid(0)];
+        assert_eq!(extra.ancestors().count(), 11);
+        assert!(extra.is_descendant_of(&uid(1)));
+        assert!(!extra.is_descendant_of(&uid(3)));
+        assert!(enforce_tc_and_dag(&entities).is_ok());
+
+        // a cycle is still an error
+        let mut a = Entity::with_uid(EntityUID::with_eid("A"));
+        a.add_ancestor(EntityUID::with_eid("B"));
+        let mut b = Entity::with_uid(EntityUID::with_eid("B"));
+        b.add_ancestor(EntityUID::with_eid("A"));
+        let mut entities = HashMap::from([(a.uid(), a), (b.uid(), b)]);
+        assert!(matches!(
+            compute_tc_parallel(&mut entities, true),
+            Err(TcError::HasCycle { .. })
+        ));
+    }
 }
@@ -14,6 +14,7 @@
  * limitations under the License.
  */
 
+use itertools::Itertools;
 use miette::Diagnostic;
 use std::fmt::Debug;
 use std::fmt::Display;
@@ -28,6 +29,9 @@ pub enum TcError<K: Debug + Display> {
     /// Error raised when `TCComputation::EnforceAlreadyComputed` finds that the
     /// TC was in fact not already computed
     #[error("expected all transitive edges to exist, but `{child}` -> `{parent}` and `{parent}` -> `{grandparent}` exists, while `{child}` -> `{grandparent}` does not")]
+    #[diagnostic(code(cedar::entities::missing_tc_edge), help(
+        "the hierarchy isn't transitively closed; list `{grandparent}` as a parent of `{child}`, or compute the transitive closure instead of enforcing it"
+    ))]
     MissingTcEdge {
         /// Child entity at fault
         child: K,
@@ -38,12 +42,24 @@ pub enum TcError<K: Debug + Display> {
         grandparent: K,
     },
     /// Error raised when enforce_dag finds that the graph is not a DAG
-    #[error("input graph has a cycle containing vertex `{}`", .vertex_with_loop)]
+    #[error("input graph has a cycle containing vertex `{vertex_with_loop}`: {}", display_cycle(.cycle))]
+    #[diagnostic(
+        code(cedar::entities::hierarchy_cycle),
+        help("remove one of the edges in the cycle")
+    )]
     HasCycle {
-        /// Because DAG enforcement can only be called after compute_tc/enforce_tc, a cycle will manifest as a vertex with a loop
+        /// A vertex which is on a cycle, i.e., which is its own ancestor
         vertex_with_loop: K,
+        /// The vertices on a shortest cycle through `vertex_with_loop`, which
+        /// starts and ends with `vertex_with_loop`
+        cycle: Vec<K>,
     },
 }
 
+/// Display a cycle as the vertices on it, separated by arrows
+fn display_cycle<K: Display>(cycle: &[K]) -> String {
+    cycle.iter().map(|k| format!("`{k}`")).join(" -> ")
+}
+
 /// Type alias for convenience
 pub type Result<T, K> = std::result::Result<T, TcError<K>>;
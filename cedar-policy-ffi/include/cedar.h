@@ -0,0 +1,57 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/*
+ * C ABI for the JSON interface of Cedar. See `src/lib.rs` for the format of
+ * the input and output JSON.
+ *
+ * Every input string is a NUL-terminated UTF-8 JSON string, borrowed for the
+ * duration of the call. On `CEDAR_OK`, `*output` is set to a JSON string owned
+ * by the caller, which must be freed with `cedar_string_free`. Otherwise,
+ * `*output` is set to NULL (if `output` isn't NULL itself).
+ */
+
+#ifndef CEDAR_H
+#define CEDAR_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum CedarStatus {
+  /* The call produced an output string */
+  CEDAR_OK = 0,
+  /* `input` or `output` was NULL */
+  CEDAR_NULL_POINTER = 1,
+  /* `input` was not valid UTF-8 */
+  CEDAR_INVALID_UTF8 = 2,
+  /* Cedar panicked while handling the call. This is a bug in Cedar. */
+  CEDAR_PANIC = 3,
+} CedarStatus;
+
+CedarStatus cedar_is_authorized(const char *input, char **output);
+
+CedarStatus cedar_validate(const char *input, char **output);
+
+CedarStatus cedar_format(const char *input, char **output);
+
+void cedar_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CEDAR_H */
@@ -0,0 +1,349 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! `uniffi` definitions of authorization, validation, and formatting, from
+//! which `uniffi-bindgen` generates Kotlin, Swift, and Python bindings.
+//!
+//! Unlike the C ABI, the arguments and results are records, enums, and
+//! errors, so that the bindings don't build or parse JSON for the call itself.
+//! Entities, contexts, and schemas are still JSON strings, in the formats of
+//! [`Entities::from_json_str`], [`Context::from_json_str`], and
+//! [`Schema::from_json_value`].
+
+use cedar_policy::{
+    Authorizer, Context, Decision as CedarDecision, Entities, EntityId, EntityTypeName,
+    EntityUid as CedarEntityUid, PolicySet, Request, Schema, ValidationMode, Validator,
+};
+use cedar_policy_formatter::{policies_str_to_pretty, FormatterConfig};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Entity uid, as its type name and id, e.g., `User` and `alice` for
+/// `User::"alice"`
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct EntityUid {
+    /// Type name, possibly with namespaces, e.g., `PhotoApp::User`
+    pub type_name: String,
+    /// Id, which isn't escaped or quoted
+    pub id: String,
+}
+
+/// Arguments of [`is_authorized`]
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct AuthorizationCall {
+    /// Principal of the request, or `None` if it is unknown
+    pub principal: Option<EntityUid>,
+    /// Action of the request, or `None` if it is unknown
+    pub action: Option<EntityUid>,
+    /// Resource of the request, or `None` if it is unknown
+    pub resource: Option<EntityUid>,
+    /// Context of the request, as a JSON object
+    pub context: String,
+    /// Policies, as Cedar source text
+    pub policies: String,
+    /// Entities, as a JSON array
+    pub entities: String,
+    /// Schema as JSON. When given, the request, context, and entities are
+    /// checked against it.
+    pub schema: Option<String>,
+}
+
+/// Decision of [`is_authorized`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum Decision {
+    /// The request is allowed
+    Allow,
+    /// The request is denied
+    Deny,
+}
+
+/// Result of [`is_authorized`]
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct AuthorizationAnswer {
+    /// The decision
+    pub decision: Decision,
+    /// Ids of the policies which determined the decision, sorted
+    pub reason: Vec<String>,
+    /// Errors evaluating policies, which were skipped because of them
+    pub errors: Vec<String>,
+}
+
+/// Error or warning of [`validate`]
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct ValidationNote {
+    /// Id of the policy the note is about
+    pub policy_id: String,
+    /// Description of the problem
+    pub message: String,
+}
+
+/// Result of [`validate`]
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct ValidationAnswer {
+    /// Validation errors. The policies are valid if there are none.
+    pub errors: Vec<ValidationNote>,
+    /// Validation warnings, which don't make the policies invalid
+    pub warnings: Vec<ValidationNote>,
+}
+
+/// Errors of the functions of this module, which are thrown as exceptions by
+/// the bindings
+#[derive(Debug, Clone, PartialEq, Eq, Error, uniffi::Error)]
+#[non_exhaustive]
+pub enum CedarError {
+    /// Policies didn't parse
+    #[error("failed to parse policies: {message}")]
+    Policies {
+        /// Description of the parse errors
+        message: String,
+    },
+    /// Schema didn't parse, or was invalid
+    #[error("failed to parse schema: {message}")]
+    Schema {
+        /// Description of the error
+        message: String,
+    },
+    /// Entities didn't parse, or didn't conform to the schema
+    #[error("failed to parse entities: {message}")]
+    Entities {
+        /// Description of the error
+        message: String,
+    },
+    /// Request was invalid: an entity type name didn't parse, the context
+    /// didn't parse, or the request didn't conform to the schema
+    #[error("invalid request: {message}")]
+    Request {
+        /// Description of the error
+        message: String,
+    },
+}
+
+/// Authorize the request of `call`
+#[uniffi::export]
+pub fn is_authorized(call: AuthorizationCall) -> Result<AuthorizationAnswer, CedarError> {
+    let schema = call.schema.as_deref().map(parse_schema).transpose()?;
+    let policies = PolicySet::from_str(&call.policies).map_err(|e| CedarError::Policies {
+        message: e.to_string(),
+    })?;
+    let entities = Entities::from_json_str(&call.entities, schema.as_ref()).map_err(|e| {
+        CedarError::Entities {
+            message: e.to_string(),
+        }
+    })?;
+    let principal = call.principal.map(entity_uid).transpose()?;
+    let action = call.action.map(entity_uid).transpose()?;
+    let resource = call.resource.map(entity_uid).transpose()?;
+    let context_schema = schema.as_ref().zip(action.as_ref());
+    let context =
+        Context::from_json_str(&call.context, context_schema).map_err(|e| CedarError::Request {
+            message: e.to_string(),
+        })?;
+    let request =
+        Request::new(principal, action, resource, context, schema.as_ref()).map_err(|e| {
+            CedarError::Request {
+                message: e.to_string(),
+            }
+        })?;
+    let response = Authorizer::new().is_authorized(&request, &policies, &entities);
+    let mut reason: Vec<String> = response
+        .diagnostics()
+        .reason()
+        .map(ToString::to_string)
+        .collect();
+    reason.sort();
+    Ok(AuthorizationAnswer {
+        decision: match response.decision() {
+            CedarDecision::Allow => Decision::Allow,
+            CedarDecision::Deny => Decision::Deny,
+        },
+        reason,
+        errors: response
+            .diagnostics()
+            .errors()
+            .map(ToString::to_string)
+            .collect(),
+    })
+}
+
+/// Validate `policies`, as Cedar source text, against `schema`, as JSON
+#[uniffi::export]
+pub fn validate(policies: String, schema: String) -> Result<ValidationAnswer, CedarError> {
+    let policies = PolicySet::from_str(&policies).map_err(|e| CedarError::Policies {
+        message: e.to_string(),
+    })?;
+    let validator = Validator::new(parse_schema(&schema)?);
+    let result = validator.validate(&policies, ValidationMode::default());
+    Ok(ValidationAnswer {
+        errors: result
+            .validation_errors()
+            .map(|e| ValidationNote {
+                policy_id: e.location().policy_id().to_string(),
+                message: e.error_kind().to_string(),
+            })
+            .collect(),
+        warnings: result
+            .validation_warnings()
+            .map(|w| ValidationNote {
+                policy_id: w.location().policy_id().to_string(),
+                message: w.warning_kind().to_string(),
+            })
+            .collect(),
+    })
+}
+
+/// Format `policies`, as Cedar source text. `line_width` and `indent_width`
+/// default to those of [`FormatterConfig`].
+#[uniffi::export]
+pub fn format_policies(
+    policies: String,
+    line_width: Option<u32>,
+    indent_width: Option<u32>,
+) -> Result<String, CedarError> {
+    let default = FormatterConfig::default();
+    let config = FormatterConfig {
+        line_width: line_width.map_or(default.line_width, |width| width as usize),
+        indent_width: indent_width.map_or(default.indent_width, |width| width as isize),
+        ..default
+    };
+    policies_str_to_pretty(&policies, &config).map_err(|e| CedarError::Policies {
+        message: format!("{e:?}"),
+    })
+}
+
+fn parse_schema(schema: &str) -> Result<Schema, CedarError> {
+    serde_json::from_str(schema)
+        .map_err(|e| e.to_string())
+        .and_then(|json| Schema::from_json_value(json).map_err(|e| e.to_string()))
+        .map_err(|message| CedarError::Schema { message })
+}
+
+fn entity_uid(uid: EntityUid) -> Result<CedarEntityUid, CedarError> {
+    let type_name = EntityTypeName::from_str(&uid.type_name).map_err(|e| CedarError::Request {
+        message: e.to_string(),
+    })?;
+    Ok(CedarEntityUid::from_type_name_and_id(
+        type_name,
+        EntityId::from_raw(&uid.id),
+    ))
+}
+
+// PANIC SAFETY unit tests
+#[allow(clippy::panic, clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cool_asserts::assert_matches;
+
+    const SCHEMA: &str = r#"{ "": {
+        "entityTypes": { "User": {}, "Photo": {} },
+        "actions": {
+            "view": { "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["Photo"] } }
+        }
+    } }"#;
+
+    fn uid(type_name: &str, id: &str) -> Option<EntityUid> {
+        Some(EntityUid {
+            type_name: type_name.to_owned(),
+            id: id.to_owned(),
+        })
+    }
+
+    fn call(policies: &str, schema: Option<&str>) -> AuthorizationCall {
+        AuthorizationCall {
+            principal: uid("User", "alice"),
+            action: uid("Action", "view"),
+            resource: uid("Photo", "door"),
+            context: "{}".to_owned(),
+            policies: policies.to_owned(),
+            entities: "[]".to_owned(),
+            schema: schema.map(ToOwned::to_owned),
+        }
+    }
+
+    #[test]
+    fn authorizes() {
+        let answer = is_authorized(call(
+            r#"permit(principal == User::"alice", action, resource);"#,
+            Some(SCHEMA),
+        ))
+        .unwrap();
+        assert_eq!(
+            answer,
+            AuthorizationAnswer {
+                decision: Decision::Allow,
+                reason: vec!["policy0".to_owned()],
+                errors: vec![],
+            }
+        );
+
+        let answer = is_authorized(call("", None)).unwrap();
+        assert_eq!(answer.decision, Decision::Deny);
+    }
+
+    #[test]
+    fn authorization_errors() {
+        assert_matches!(
+            is_authorized(call("permit(", None)),
+            Err(CedarError::Policies { .. })
+        );
+        assert_matches!(
+            is_authorized(call("", Some("not json"))),
+            Err(CedarError::Schema { .. })
+        );
+        let mut invalid = call("", Some(SCHEMA));
+        invalid.principal = uid("Photo", "door");
+        assert_matches!(is_authorized(invalid), Err(CedarError::Request { .. }));
+        let mut invalid = call("", None);
+        invalid.entities = "{}".to_owned();
+        assert_matches!(is_authorized(invalid), Err(CedarError::Entities { .. }));
+    }
+
+    #[test]
+    fn validates() {
+        let answer = validate(
+            r#"permit(principal == User::"alice", action, resource);"#.to_owned(),
+            SCHEMA.to_owned(),
+        )
+        .unwrap();
+        assert!(answer.errors.is_empty(), "{answer:?}");
+
+        let answer = validate(
+            r#"permit(principal == Admin::"alice", action, resource);"#.to_owned(),
+            SCHEMA.to_owned(),
+        )
+        .unwrap();
+        assert_matches!(answer.errors.as_slice(), [note] => {
+            assert_eq!(note.policy_id, "policy0");
+        });
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(
+            format_policies(
+                "permit(principal,action,resource) when {true};".to_owned(),
+                None,
+                None
+            )
+            .unwrap(),
+            "permit (principal, action, resource)\nwhen { true };"
+        );
+        assert_matches!(
+            format_policies("permit(".to_owned(), None, None),
+            Err(CedarError::Policies { .. })
+        );
+    }
+}
@@ -0,0 +1,327 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! C ABI for the JSON interface of Cedar, for bindings from languages without
+//! good Rust interop. The C declarations are in `include/cedar.h`.
+//!
+//! Each of [`cedar_is_authorized`], [`cedar_validate`], and [`cedar_format`]
+//! takes a NUL-terminated UTF-8 JSON string, and on success writes a
+//! NUL-terminated JSON string to `*output`, in the same format as the result
+//! of [`json_is_authorized`].
+//!
+//! Ownership rules:
+//! - The input string is borrowed for the duration of the call and never
+//!   freed or retained by Cedar.
+//! - The output string is owned by the caller, and must be freed exactly once
+//!   with [`cedar_string_free`], not with the `free` of the C library.
+//! - `*output` is set to null whenever the status is not
+//!   [`CedarStatus::Ok`], so there is nothing to free.
+//!
+//! Errors in the call itself, e.g., a policy that doesn't parse, are reported
+//! in the output JSON with status [`CedarStatus::Ok`]. The other statuses
+//! mean that no output was produced.
+//!
+//! With the `uniffi` feature, the [`bindings`] module defines the same
+//! functions for `uniffi`, which generates Kotlin, Swift, and Python bindings.
+#![deny(missing_docs, missing_debug_implementations, rust_2018_idioms)]
+
+use std::ffi::{c_char, CStr, CString};
+use std::panic;
+
+use cedar_policy::frontend::is_authorized::json_is_authorized;
+use cedar_policy::frontend::utils::InterfaceResult;
+use cedar_policy::frontend::validate::json_validate;
+use cedar_policy_formatter::{policies_str_to_pretty, FormatterConfig};
+use serde::{Deserialize, Serialize};
+
+/// `uniffi` bindings, see comments in the module itself
+#[cfg(feature = "uniffi")]
+pub mod bindings;
+
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!("cedar");
+
+/// Status code returned by every function of the C ABI
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CedarStatus {
+    /// The call produced an output string
+    Ok = 0,
+    /// `input` or `output` was null
+    NullPointer = 1,
+    /// `input` was not valid UTF-8
+    InvalidUtf8 = 2,
+    /// Cedar panicked while handling the call. This is a bug in Cedar.
+    Panic = 3,
+}
+
+/// `json_is_authorized` through the C ABI; see the [module documentation](self)
+///
+/// # Safety
+///
+/// `input` must be null or point to a NUL-terminated string, and `output`
+/// must be null or valid for writes of a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn cedar_is_authorized(
+    input: *const c_char,
+    output: *mut *mut c_char,
+) -> CedarStatus {
+    // SAFETY: the caller upholds the requirements of `call`
+    unsafe { call(input, output, json_is_authorized) }
+}
+
+/// `json_validate` through the C ABI; see the [module documentation](self)
+///
+/// # Safety
+///
+/// `input` must be null or point to a NUL-terminated string, and `output`
+/// must be null or valid for writes of a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn cedar_validate(
+    input: *const c_char,
+    output: *mut *mut c_char,
+) -> CedarStatus {
+    // SAFETY: the caller upholds the requirements of `call`
+    unsafe { call(input, output, json_validate) }
+}
+
+/// [`json_format`] through the C ABI; see the [module documentation](self)
+///
+/// # Safety
+///
+/// `input` must be null or point to a NUL-terminated string, and `output`
+/// must be null or valid for writes of a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn cedar_format(
+    input: *const c_char,
+    output: *mut *mut c_char,
+) -> CedarStatus {
+    // SAFETY: the caller upholds the requirements of `call`
+    unsafe { call(input, output, json_format) }
+}
+
+/// Free a string returned by this library. Does nothing if `s` is null.
+///
+/// # Safety
+///
+/// `s` must be null or a string written to `*output` by a function of this
+/// library, which hasn't been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn cedar_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        // SAFETY: `s` was returned by `CString::into_raw` in `call`, and
+        // ownership is given back here exactly once
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+/// Input to [`json_format`]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+struct FormatCall {
+    /// Policies to format, as Cedar source text
+    policies: String,
+    /// Maximum width of a line, defaulting to that of [`FormatterConfig`]
+    line_width: Option<usize>,
+    /// Number of spaces for each level of indentation, defaulting to that of
+    /// [`FormatterConfig`]
+    indent_width: Option<isize>,
+}
+
+/// Result of a successful [`json_format`]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FormatAnswer {
+    /// The formatted policies
+    formatted_policies: String,
+}
+
+/// Format the policies in `input`, a JSON object with a `policies` string and
+/// optional `lineWidth` and `indentWidth`
+pub fn json_format(input: &str) -> InterfaceResult {
+    serde_json::from_str::<FormatCall>(input).map_or_else(
+        |e| InterfaceResult::fail_internally(format!("error parsing call: {e:}")),
+        |call| {
+            let default = FormatterConfig::default();
+            let config = FormatterConfig {
+                line_width: call.line_width.unwrap_or(default.line_width),
+                indent_width: call.indent_width.unwrap_or(default.indent_width),
+                ..default
+            };
+            match policies_str_to_pretty(&call.policies, &config) {
+                Ok(formatted_policies) => {
+                    InterfaceResult::succeed(FormatAnswer { formatted_policies })
+                }
+                Err(e) => InterfaceResult::fail_bad_request(vec![format!("{e:?}")]),
+            }
+        },
+    )
+}
+
+/// Run `f` on `input`, writing its result as JSON to `*output`
+///
+/// # Safety
+///
+/// `input` must be null or point to a NUL-terminated string, and `output`
+/// must be null or valid for writes of a pointer.
+unsafe fn call(
+    input: *const c_char,
+    output: *mut *mut c_char,
+    f: fn(&str) -> InterfaceResult,
+) -> CedarStatus {
+    if output.is_null() {
+        return CedarStatus::NullPointer;
+    }
+    // SAFETY: `output` is non-null, and valid for writes by the requirements
+    // of this function
+    unsafe { *output = std::ptr::null_mut() };
+    if input.is_null() {
+        return CedarStatus::NullPointer;
+    }
+    // SAFETY: `input` is non-null, and NUL-terminated by the requirements of
+    // this function
+    let Ok(input) = unsafe { CStr::from_ptr(input) }.to_str() else {
+        return CedarStatus::InvalidUtf8;
+    };
+    // panics must not unwind across the C ABI
+    let Ok(result) = panic::catch_unwind(|| f(input)) else {
+        return CedarStatus::Panic;
+    };
+    let json = serde_json::to_string(&result).unwrap_or_else(|e| {
+        serde_json::json!({
+            "success": "false",
+            "isInternal": true,
+            "errors": [format!("error serializing result: {e}")],
+        })
+        .to_string()
+    });
+    // JSON escapes NUL characters in strings, so `json` has none
+    let Ok(json) = CString::new(json) else {
+        return CedarStatus::Panic;
+    };
+    // SAFETY: as above
+    unsafe { *output = json.into_raw() };
+    CedarStatus::Ok
+}
+
+// PANIC SAFETY unit tests
+#[allow(clippy::panic, clippy::unwrap_used, clippy::expect_used)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cool_asserts::assert_matches;
+
+    /// Call `f` with `input` as a C string, and return its status and output
+    fn call_c(
+        f: unsafe extern "C" fn(*const c_char, *mut *mut c_char) -> CedarStatus,
+        input: &[u8],
+    ) -> (CedarStatus, Option<InterfaceResult>) {
+        let input = CString::new(input).unwrap();
+        let mut output = std::ptr::null_mut();
+        let status = unsafe { f(input.as_ptr(), &mut output) };
+        if output.is_null() {
+            return (status, None);
+        }
+        let json = unsafe { CStr::from_ptr(output) }
+            .to_str()
+            .unwrap()
+            .to_owned();
+        unsafe { cedar_string_free(output) };
+        let result = serde_json::from_str(&json).expect("output should be JSON");
+        (status, Some(result))
+    }
+
+    #[test]
+    fn is_authorized() {
+        let call = br#"
+        {
+            "principal": { "type": "User", "id": "alice" },
+            "action": { "type": "Photo", "id": "view" },
+            "resource": { "type": "Photo", "id": "door" },
+            "context": {},
+            "slice": {
+                "policies": "permit(principal == User::\"alice\", action, resource);",
+                "entities": []
+            }
+        }
+        "#;
+        let (status, result) = call_c(cedar_is_authorized, call);
+        assert_eq!(status, CedarStatus::Ok);
+        assert_matches!(result, Some(InterfaceResult::Success { result }) => {
+            assert!(result.contains("\"decision\":\"Allow\""), "{result}");
+        });
+    }
+
+    #[test]
+    fn validate() {
+        let (status, result) = call_c(cedar_validate, br#"{ "schema": {}, "policySet": {} }"#);
+        assert_eq!(status, CedarStatus::Ok);
+        assert_matches!(result, Some(InterfaceResult::Success { .. }));
+
+        let (status, result) = call_c(cedar_validate, b"not json");
+        assert_eq!(status, CedarStatus::Ok);
+        assert_matches!(
+            result,
+            Some(InterfaceResult::Failure {
+                is_internal: true,
+                ..
+            })
+        );
+    }
+
+    #[test]
+    fn format() {
+        let call = br#"{ "policies": "permit(principal,action,resource) when {true};" }"#;
+        let (status, result) = call_c(cedar_format, call);
+        assert_eq!(status, CedarStatus::Ok);
+        assert_matches!(result, Some(InterfaceResult::Success { result }) => {
+            assert_eq!(
+                result,
+                r#"{"formattedPolicies":"permit (principal, action, resource)\nwhen { true };"}"#
+            );
+        });
+
+        let (status, result) = call_c(cedar_format, br#"{ "policies": "permit(" }"#);
+        assert_eq!(status, CedarStatus::Ok);
+        assert_matches!(
+            result,
+            Some(InterfaceResult::Failure {
+                is_internal: false,
+                ..
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let mut output = std::ptr::null_mut();
+        let status = unsafe { cedar_validate(std::ptr::null(), &mut output) };
+        assert_eq!(status, CedarStatus::NullPointer);
+        assert!(output.is_null());
+
+        let input = CString::new("{}").unwrap();
+        let status = unsafe { cedar_validate(input.as_ptr(), std::ptr::null_mut()) };
+        assert_eq!(status, CedarStatus::NullPointer);
+
+        let (status, result) = call_c(cedar_format, b"\xff\xfe");
+        assert_eq!(status, CedarStatus::InvalidUtf8);
+        assert!(result.is_none());
+
+        // freeing null does nothing
+        unsafe { cedar_string_free(std::ptr::null_mut()) };
+    }
+}
@@ -16,15 +16,96 @@
 
 use super::token::WrappedToken;
 
-/// Configuraton struct that specifies line width and indentation width
-#[derive(Debug, Clone)]
-pub struct Config {
+/// Configuration for the formatter. Use [`FormatterConfig::default()`] and
+/// override individual fields to match an existing style guide.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatterConfig {
+    /// Maximum width of a line before the formatter tries to break it
     pub line_width: usize,
+    /// Number of spaces used for each level of indentation
     pub indent_width: isize,
+    /// Placement of the opening brace of `when` and `unless` clauses
+    pub brace_style: BraceStyle,
+    /// Placement of policy annotations relative to the policy effect
+    pub annotation_placement: AnnotationPlacement,
+    /// Whether the `when` and `unless` clauses of a policy are kept separate
+    /// or merged into a single `when` clause
+    pub clause_layout: ClauseLayout,
+    /// Wrapping of list literals, such as the entity UIDs in
+    /// `action in [...]`, which don't fit on a single line
+    pub list_wrap: ListWrap,
+    /// Sort the policies in a policy set into a canonical order: policies with
+    /// an `@id` annotation first, ordered by that annotation, then by effect
+    /// (`permit` before `forbid`), then by scope. When `false`, policies keep
+    /// their input order.
+    pub sort_policies: bool,
+}
+
+impl Default for FormatterConfig {
+    fn default() -> Self {
+        Self {
+            line_width: 80,
+            indent_width: 2,
+            brace_style: BraceStyle::default(),
+            annotation_placement: AnnotationPlacement::default(),
+            clause_layout: ClauseLayout::default(),
+            list_wrap: ListWrap::default(),
+            sort_policies: false,
+        }
+    }
+}
+
+/// Former name of [`FormatterConfig`]
+pub type Config = FormatterConfig;
+
+/// Placement of the opening brace of a `when` or `unless` clause that does not
+/// fit on a single line. Clauses which fit on one line are always printed as
+/// `when { ... }`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BraceStyle {
+    /// Put the opening brace on its own line, below `when` or `unless`
+    #[default]
+    NextLine,
+    /// Keep the opening brace on the same line as `when` or `unless`
+    SameLine,
+}
+
+/// Placement of policy annotations
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AnnotationPlacement {
+    /// Put each annotation on its own line, above the policy effect
+    #[default]
+    OwnLine,
+    /// Put all annotations on the same line as the policy effect
+    Inline,
+}
+
+/// Layout of the `when` and `unless` clauses of a policy with more than one
+/// clause
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClauseLayout {
+    /// Keep each clause as written
+    #[default]
+    Separate,
+    /// Merge all clauses into a single `when` clause by joining them with
+    /// `&&`, negating the bodies of `unless` clauses. Comments on the merged
+    /// clauses' keywords and braces are kept above the corresponding
+    /// conjunct.
+    Merge,
+}
+
+/// Wrapping of a list literal which doesn't fit on a single line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ListWrap {
+    /// Put each element on its own line
+    #[default]
+    OnePerLine,
+    /// Put as many elements on each line as fit
+    Fill,
 }
 
 #[derive(Debug)]
 pub struct Context<'a> {
-    pub config: &'a Config,
+    pub config: &'a FormatterConfig,
     pub tokens: Vec<WrappedToken>,
 }
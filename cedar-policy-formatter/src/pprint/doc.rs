@@ -15,8 +15,9 @@
  */
 
 use super::utils::*;
-use super::Context;
+use super::{AnnotationPlacement, BraceStyle, ClauseLayout, Context, ListWrap};
 use cedar_policy_core::parser::{cst::*, Node};
+use itertools::Itertools;
 use pretty::RcDoc;
 
 use super::token::Comment;
@@ -109,8 +110,16 @@ impl Doc for Node<Option<Cond>> {
         let rb_comment = get_comment_at_end(self.loc.span, &mut context.tokens)?;
         let cond_comment = get_comment_at_start(cond.cond.loc.span, &mut context.tokens)?;
 
-        let rb_doc = add_comment(RcDoc::text("}"), rb_comment, RcDoc::nil());
+        // the trailing comment of the clause follows its group, so that it
+        // doesn't force the clause onto several lines
+        let rb_doc =
+            get_leading_comment_doc_from_str(&rb_comment.leading_comment).append(RcDoc::text("}"));
+        let rb_trailing_doc = get_trailing_comment_doc_from_str(&rb_comment.trailing_comment);
         let cond_doc = cond.cond.to_doc(context)?;
+        let brace_sep = match context.config.brace_style {
+            BraceStyle::NextLine => RcDoc::line(),
+            BraceStyle::SameLine => RcDoc::space(),
+        };
         Some(match cond.expr.as_ref() {
             Some(expr) => {
                 let expr_leading_comment =
@@ -121,7 +130,7 @@ impl Doc for Node<Option<Cond>> {
                         .append(get_trailing_comment_doc_from_str(
                             &cond_comment.trailing_comment,
                         ))
-                        .append(RcDoc::line())
+                        .append(brace_sep)
                         .append(
                             get_leading_comment_doc_from_str(&lb_comment.leading_comment).append(
                                 RcDoc::text("{").append(
@@ -138,7 +147,8 @@ impl Doc for Node<Option<Cond>> {
                                 ),
                             ),
                         )
-                        .group(),
+                        .group()
+                        .append(rb_trailing_doc),
                 )
             }
             None => get_leading_comment_doc_from_str(&cond_comment.leading_comment).append(
@@ -146,7 +156,7 @@ impl Doc for Node<Option<Cond>> {
                     .append(get_trailing_comment_doc_from_str(
                         &cond_comment.trailing_comment,
                     ))
-                    .append(RcDoc::line())
+                    .append(brace_sep)
                     .append(
                         get_leading_comment_doc_from_str(&lb_comment.leading_comment).append(
                             RcDoc::text("{")
@@ -158,12 +168,93 @@ impl Doc for Node<Option<Cond>> {
                                 .group(),
                         ),
                     )
-                    .group(),
+                    .group()
+                    .append(rb_trailing_doc),
             ),
         })
     }
 }
 
+/// Whether `expr` is a single relation, i.e., has no top-level `&&`, `||`, or
+/// `if`, and so can be an operand of `&&` without parentheses
+fn is_relation(expr: &Node<Option<Expr>>) -> bool {
+    match expr.as_inner().map(|e| e.expr.as_ref()) {
+        Some(ExprData::Or(or)) => or.as_inner().is_some_and(|or| {
+            or.extended.is_empty()
+                && or
+                    .initial
+                    .as_inner()
+                    .is_some_and(|and| and.extended.is_empty())
+        }),
+        _ => false,
+    }
+}
+
+/// Doc for the clauses of a policy merged into a single `when` clause, e.g.,
+/// `when { a } unless { b }` becomes `when { a && !(b) }`. This is the same
+/// left-nested conjunction the parser builds from separate clauses.
+fn merged_conds_doc<'a>(
+    conds: &'a [Node<Option<Cond>>],
+    context: &mut Context<'_>,
+) -> Option<RcDoc<'a>> {
+    let mut body = RcDoc::nil();
+    for (i, c) in conds.iter().enumerate() {
+        let cond = c.as_inner()?;
+        let expr = cond.expr.as_ref()?;
+        let keyword_comment = get_comment_at_start(cond.cond.loc.span, &mut context.tokens)?;
+        let lb_comment = get_comment_after_end(cond.cond.loc.span, &mut context.tokens)?;
+        let rb_comment = get_comment_at_end(c.loc.span, &mut context.tokens)?;
+        let expr_leading_comment =
+            get_leading_comment_at_start(expr.loc.span, &mut context.tokens)?;
+        let comments = [
+            keyword_comment.leading_comment,
+            keyword_comment.trailing_comment,
+            lb_comment.leading_comment,
+            lb_comment.trailing_comment,
+            expr_leading_comment,
+            rb_comment.leading_comment,
+            rb_comment.trailing_comment,
+        ]
+        .iter()
+        .map(|c| c.trim())
+        .filter(|c| !c.is_empty())
+        .join("\n");
+        let expr_doc = expr.to_doc(context)?;
+        let conjunct = match cond.cond.as_inner()? {
+            Ident::Unless => add_brackets(expr_doc, RcDoc::text("!("), RcDoc::text(")")),
+            _ if is_relation(expr) => expr_doc,
+            _ => add_brackets(expr_doc, RcDoc::text("("), RcDoc::text(")")),
+        };
+        if i > 0 {
+            body = body
+                .append(RcDoc::space())
+                .append(RcDoc::text("&&"))
+                .append(RcDoc::line());
+        }
+        body = body
+            .append(get_leading_comment_doc_from_str(&comments))
+            .append(conjunct.group());
+    }
+    let brace_sep = match context.config.brace_style {
+        BraceStyle::NextLine => RcDoc::line(),
+        BraceStyle::SameLine => RcDoc::space(),
+    };
+    Some(
+        RcDoc::text("when")
+            .append(brace_sep)
+            .append(
+                RcDoc::text("{")
+                    .append(RcDoc::line())
+                    .append(body)
+                    .nest(context.config.indent_width)
+                    .append(RcDoc::line())
+                    .append(RcDoc::text("}"))
+                    .group(),
+            )
+            .group(),
+    )
+}
+
 impl Doc for Node<Option<Expr>> {
     fn to_doc(&self, context: &mut Context<'_>) -> Option<RcDoc<'_>> {
         match self.as_inner()?.expr.as_ref() {
@@ -181,8 +272,8 @@ impl Doc for Node<Option<Expr>> {
                     )
                 }
                 let if_comment = get_comment_at_start(self.loc.span, &mut context.tokens)?;
-                let else_comment = get_comment_after_end(c.loc.span, &mut context.tokens)?;
-                let then_comment = get_comment_after_end(t.loc.span, &mut context.tokens)?;
+                let then_comment = get_comment_after_end(c.loc.span, &mut context.tokens)?;
+                let else_comment = get_comment_after_end(t.loc.span, &mut context.tokens)?;
                 Some(
                     pp_group("if", if_comment, c, context)
                         .append(RcDoc::line())
@@ -586,6 +677,10 @@ impl Doc for Node<Option<Primary>> {
                 if el.is_empty() {
                     RcDoc::nil()
                 } else {
+                    let list_sep = match context.config.list_wrap {
+                        ListWrap::OnePerLine => RcDoc::line(),
+                        ListWrap::Fill => RcDoc::softline(),
+                    };
                     el.get(1..)?
                         .iter()
                         .try_fold((el.get(0)?.to_doc(context)?, el.get(0)?), |pair, v| {
@@ -596,7 +691,7 @@ impl Doc for Node<Option<Primary>> {
                                     get_comment_after_end(e.loc.span, &mut context.tokens)?,
                                     RcDoc::nil(),
                                 ))
-                                .append(RcDoc::line())
+                                .append(list_sep.clone())
                                 .append(v.to_doc(context)),
                                 v,
                             ))
@@ -738,7 +833,10 @@ impl Doc for Node<Option<Annotation>> {
         let rp_doc = add_comment(
             RcDoc::text(")"),
             get_comment_at_end(self.loc.span, &mut context.tokens)?,
-            RcDoc::hardline(),
+            match context.config.annotation_placement {
+                AnnotationPlacement::OwnLine => RcDoc::hardline(),
+                AnnotationPlacement::Inline => RcDoc::space(),
+            },
         );
         Some(
             at_doc
@@ -818,8 +916,10 @@ impl Doc for Node<Option<Policy>> {
                 .append(RcDoc::hardline())
         };
         let conds = &policy.conds;
-        let cond_doc =
-            RcDoc::intersperse(conds.iter().map(|c| c.to_doc(context)), RcDoc::hardline());
+        let cond_doc = match context.config.clause_layout {
+            ClauseLayout::Merge if conds.len() > 1 => merged_conds_doc(conds, context)?,
+            _ => RcDoc::intersperse(conds.iter().map(|c| c.to_doc(context)), RcDoc::hardline()),
+        };
         Some(
             anno_doc
                 .append(
@@ -14,24 +14,31 @@
  * limitations under the License.
  */
 
+use std::ops::Range;
+
 use miette::{miette, Result, WrapErr};
+use pretty::RcDoc;
 
-use cedar_policy_core::ast::{PolicySet, Template};
-use cedar_policy_core::parser::parse_policyset;
-use cedar_policy_core::parser::{err::ParseErrors, text_to_cst::parse_policies};
+use cedar_policy_core::ast::{Effect, Expr, PolicyID, PolicySet, StaticPolicy, Template};
+use cedar_policy_core::est;
+use cedar_policy_core::parser::text_to_cst::{parse_expr, parse_policies, parse_policy};
+use cedar_policy_core::parser::{self, err::ParseErrors, parse_policyset};
 
-use crate::token::get_comment;
+use crate::token::{get_comment, WrappedToken};
 
 use super::lexer::get_token_stream;
-use super::utils::remove_empty_lines;
+use super::utils::{remove_empty_lines, take_remaining_comments};
 
-use super::config::{self, Config};
+use super::config::{self, FormatterConfig};
 use super::doc::*;
 
 fn tree_to_pretty<T: Doc>(t: &T, context: &mut config::Context<'_>) -> Result<String> {
-    let mut w = Vec::new();
     let config = context.config;
-    let doc = t.to_doc(context);
+    render_doc(t.to_doc(context), config)
+}
+
+fn render_doc(doc: Option<RcDoc<'_>>, config: &FormatterConfig) -> Result<String> {
+    let mut w = Vec::new();
     doc.ok_or(miette!("failed to produce doc"))?
         .render(config.line_width, &mut w)
         .map_err(|err| miette!(format!("failed to render doc: {err}")))?;
@@ -39,18 +46,26 @@ fn tree_to_pretty<T: Doc>(t: &T, context: &mut config::Context<'_>) -> Result<St
         .map_err(|err| miette!(format!("failed to convert rendered doc to string: {err}")))
 }
 
-fn soundness_check(ps: &str, ast: &PolicySet) -> Result<()> {
-    let formatted_ast = parse_policyset(ps).wrap_err("formatter produces invalid policies")?;
-    let (formatted_policies, policies) = (
-        formatted_ast.templates().collect::<Vec<&Template>>(),
-        ast.templates().collect::<Vec<&Template>>(),
-    );
+fn policy_id(index: usize) -> PolicyID {
+    PolicyID::from_string(format!("policy{index}"))
+}
 
-    if formatted_policies.len() != policies.len() {
+/// Check that the formatted policies `ps` are the same as the input policies
+/// `ast`, where the `i`th formatted policy was produced from input policy
+/// `order[i]`.
+fn soundness_check(ps: &str, ast: &PolicySet, order: &[usize]) -> Result<()> {
+    let formatted_ast = parse_policyset(ps).wrap_err("formatter produces invalid policies")?;
+    if formatted_ast.all_templates().count() != ast.all_templates().count() {
         return Err(miette!("missing formatted policies"));
     }
 
-    for (f_p, p) in formatted_policies.into_iter().zip(policies.into_iter()) {
+    for (i, original) in order.iter().enumerate() {
+        let (Some(f_p), Some(p)) = (
+            formatted_ast.get_template(&policy_id(i)),
+            ast.get_template(&policy_id(*original)),
+        ) else {
+            return Err(miette!("missing formatted policies"));
+        };
         let (f_anno, anno) = (
             f_p.annotations()
                 .collect::<std::collections::HashMap<_, _>>(),
@@ -75,33 +90,78 @@ fn soundness_check(ps: &str, ast: &PolicySet) -> Result<()> {
     Ok(())
 }
 
-pub fn policies_str_to_pretty(ps: &str, config: &Config) -> Result<String> {
-    let cst = parse_policies(ps).wrap_err("cannot parse input policies to CSTs")?;
-    let mut errs = ParseErrors::new();
-    let ast = cst
-        .to_policyset(&mut errs)
-        .ok_or(errs)
-        .wrap_err("cannot parse input policies to ASTs")?;
-    let tokens = get_token_stream(ps).ok_or(miette!("cannot get token stream"))?;
-    let end_comment_str = ps
-        .get(
-            tokens
-                .last()
-                .ok_or(miette!("token stream is empty"))?
-                .span
-                .end..,
-        )
-        .ok_or(miette!("cannot get ending comment string"))?;
-    let mut context = config::Context { config, tokens };
-    let mut formatted_policies = cst
-        .as_inner()
-        .ok_or(miette!("fail to get input policy CST"))?
-        .0
+/// Key used to order policies when [`FormatterConfig::sort_policies`] is set:
+/// policies with an `@id` annotation come first, ordered by that annotation,
+/// then by effect (`permit` before `forbid`), then by scope.
+fn sort_key(t: &Template) -> (bool, Option<String>, bool, String) {
+    let id = t
+        .annotations()
+        .find(|(key, _)| key.as_ref() == "id")
+        .map(|(_, value)| value.to_string());
+    (
+        id.is_none(),
+        id,
+        t.effect() == Effect::Forbid,
+        format!(
+            "{} {} {}",
+            t.principal_constraint(),
+            t.action_constraint(),
+            t.resource_constraint()
+        ),
+    )
+}
+
+/// Collect the text of every comment in `src`, sorted so that comments can
+/// be compared without regard to where the formatter placed them.
+fn comments_in(src: &str) -> Result<Vec<String>> {
+    let tokens = get_token_stream(src).ok_or(miette!("cannot get token stream"))?;
+    let tail = match tokens.last() {
+        Some(t) => src
+            .get(t.span.end..)
+            .ok_or(miette!("cannot get ending comment string"))?,
+        None => src,
+    };
+    let tail_comment = get_comment(tail);
+    let mut comments: Vec<String> = tokens
         .iter()
-        .map(|p| Ok(remove_empty_lines(tree_to_pretty(p, &mut context)?.trim())))
-        .collect::<Result<Vec<String>>>()?
-        .join("\n\n");
-    // handle comment at the end of a policyset
+        .flat_map(|t| {
+            [
+                t.comment.leading_comment.as_str(),
+                t.comment.trailing_comment.as_str(),
+            ]
+        })
+        .chain(std::iter::once(tail_comment.as_str()))
+        .flat_map(str::lines)
+        .map(str::to_owned)
+        .collect();
+    comments.sort();
+    Ok(comments)
+}
+
+fn comment_check(formatted: &str, src: &str) -> Result<()> {
+    if comments_in(formatted)? == comments_in(src)? {
+        Ok(())
+    } else {
+        Err(miette!("formatter does not preserve comments"))
+    }
+}
+
+/// Get the text following the last token of `src`, which can only contain
+/// comments and whitespace.
+fn end_comment_str<'a>(src: &'a str, tokens: &[WrappedToken]) -> Result<&'a str> {
+    src.get(
+        tokens
+            .last()
+            .ok_or(miette!("token stream is empty"))?
+            .span
+            .end..,
+    )
+    .ok_or(miette!("cannot get ending comment string"))
+}
+
+/// Append the comments following the last token of the input to `formatted`,
+/// and trim trailing whitespace since the output never ends with a newline.
+fn append_end_comment(formatted: &mut String, end_comment_str: &str) {
     let (trailing_comment, end_comment) = match end_comment_str.split_once('\n') {
         Some((f, r)) => (get_comment(f), get_comment(r)),
         None => (get_comment(end_comment_str), String::new()),
@@ -109,30 +169,331 @@ pub fn policies_str_to_pretty(ps: &str, config: &Config) -> Result<String> {
     match (trailing_comment.as_ref(), end_comment.as_ref()) {
         ("", "") => {}
         (_, "") => {
-            formatted_policies.push(' ');
-            formatted_policies.push_str(&trailing_comment);
+            formatted.push(' ');
+            formatted.push_str(&trailing_comment);
         }
         ("", _) => {
-            formatted_policies.push('\n');
-            formatted_policies.push_str(&end_comment);
+            formatted.push('\n');
+            formatted.push_str(&end_comment);
         }
         _ => {
-            formatted_policies.push(' ');
-            formatted_policies.push_str(&trailing_comment);
-            formatted_policies.push_str(&end_comment);
+            formatted.push(' ');
+            formatted.push_str(&trailing_comment);
+            formatted.push_str(&end_comment);
+        }
+    };
+    formatted.truncate(formatted.trim_end().len());
+}
+
+/// Maximum number of formatting passes made while looking for a fixed point.
+const MAX_FORMAT_PASSES: usize = 4;
+
+/// Repeatedly apply `format_once` until the output stops changing, or until
+/// [`MAX_FORMAT_PASSES`] passes were made, in which case the last pass is
+/// returned. Every pass checks its output, so it's valid either way.
+fn to_fixed_point(
+    src: &str,
+    config: &FormatterConfig,
+    format_once: fn(&str, &FormatterConfig) -> Result<String>,
+) -> Result<String> {
+    // A single pass is not always a fixed point (e.g., a comment may move to a
+    // different token on the second pass), so we repeat until it is.
+    let mut formatted = format_once(src, config)?;
+    for _ in 1..MAX_FORMAT_PASSES {
+        let reformatted = format_once(&formatted, config)?;
+        if reformatted == formatted {
+            break;
+        }
+        formatted = reformatted;
+    }
+    Ok(formatted)
+}
+
+/// Format a policy set given as Cedar source text, according to `config`.
+/// Returns an error if the input does not parse, or if the formatted output
+/// would not parse to the same policies as the input.
+///
+/// Formatting is idempotent: formatting the output of this function again
+/// returns it unchanged. The output never ends with a newline.
+pub fn policies_str_to_pretty(ps: &str, config: &FormatterConfig) -> Result<String> {
+    to_fixed_point(ps, config, format_once)
+}
+
+/// Check whether a policy set given as Cedar source text is already formatted
+/// according to `config`, ignoring a single trailing newline. Returns `false`
+/// if the input cannot be formatted, e.g., because it does not parse.
+pub fn is_formatted(ps: &str, config: &FormatterConfig) -> bool {
+    policies_str_to_pretty(ps, config)
+        .is_ok_and(|formatted| formatted == ps.strip_suffix('\n').unwrap_or(ps))
+}
+
+/// An edit produced by [`policies_range_to_pretty`]: replacing the bytes of
+/// the input in `range` with `text` formats the selected policies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeEdit {
+    /// Byte range of the input to replace. This covers the requested range,
+    /// extended to whole policies.
+    pub range: Range<usize>,
+    /// Formatted text of the policies in `range`
+    pub text: String,
+}
+
+/// Format only the policies of a policy set which overlap the byte range
+/// `range` of `ps`, e.g., the current selection in an editor, according to
+/// `config`. The range is extended to cover every overlapping policy along
+/// with its comments, and to any other policy on the same line as one of
+/// them. An empty range selects the policy containing it. If no policy is
+/// selected, the returned edit leaves `ps` unchanged.
+///
+/// The whole of `ps` must parse, since a selection can't be formatted without
+/// knowing where its policies begin and end.
+// `first` and `last` are indices of `spans`, which are the ordered spans of the
+// policies of `ps`, so the gaps between them and the ranges around them are
+// ranges of `ps`, and they start and end at tokens, which are on char
+// boundaries.
+// PANIC SAFETY: See above.
+#[allow(clippy::indexing_slicing)]
+pub fn policies_range_to_pretty(
+    ps: &str,
+    range: Range<usize>,
+    config: &FormatterConfig,
+) -> Result<RangeEdit> {
+    let unchanged = ps
+        .get(range.clone())
+        .ok_or(miette!("range {range:?} is not a valid range of the input"))?
+        .to_string();
+    let cst = parse_policies(ps).wrap_err("cannot parse input policies to CSTs")?;
+    let spans: Vec<Range<usize>> = cst
+        .as_inner()
+        .ok_or(miette!("fail to get input policy CST"))?
+        .0
+        .iter()
+        .map(|p| p.loc.span.offset()..p.loc.span.offset() + p.loc.span.len())
+        .collect();
+    let selected = |span: &Range<usize>| {
+        if range.is_empty() {
+            span.start <= range.start && range.start <= span.end
+        } else {
+            span.start < range.end && range.start < span.end
         }
     };
+    let (Some(mut first), Some(mut last)) = (
+        spans.iter().position(selected),
+        spans.iter().rposition(selected),
+    ) else {
+        return Ok(RangeEdit {
+            range,
+            text: unchanged,
+        });
+    };
+    // the text between two policies, which holds the trailing comment of the
+    // first one on its line, and the leading comment of the second one
+    let gap = |i: usize| &ps[spans[i].end..spans[i + 1].start];
+    while first > 0 && !gap(first - 1).contains('\n') {
+        first -= 1;
+    }
+    while last + 1 < spans.len() && !gap(last).contains('\n') {
+        last += 1;
+    }
+
+    // start at the leading comment of the first policy, leaving blank lines
+    // before it alone
+    let start = if first == 0 {
+        let leading = &ps[..spans[0].start];
+        leading.len() - leading.trim_start().len()
+    } else {
+        let gap = gap(first - 1);
+        let leading = gap.split_once('\n').map_or("", |(_, leading)| leading);
+        spans[first].start - leading.trim_start().len()
+    };
+    // end after the trailing comment of the last policy, or at the end of the
+    // input (including end comments) for the last policy of the set
+    let end = if last + 1 == spans.len() {
+        ps.trim_end().len()
+    } else {
+        let trailing = gap(last)
+            .split_once('\n')
+            .map_or("", |(trailing, _)| trailing);
+        spans[last].end + trailing.trim_end().len()
+    };
+
+    Ok(RangeEdit {
+        range: start..end,
+        text: policies_str_to_pretty(&ps[start..end], config)?,
+    })
+}
+
+fn format_once(ps: &str, config: &FormatterConfig) -> Result<String> {
+    let cst = parse_policies(ps).wrap_err("cannot parse input policies to CSTs")?;
+    let mut errs = ParseErrors::new();
+    let ast = cst
+        .to_policyset(&mut errs)
+        .ok_or(errs)
+        .wrap_err("cannot parse input policies to ASTs")?;
+    let tokens = get_token_stream(ps).ok_or(miette!("cannot get token stream"))?;
+    let end_comment_str = end_comment_str(ps, &tokens)?;
+    let mut context = config::Context { config, tokens };
+    let mut formatted_policies = cst
+        .as_inner()
+        .ok_or(miette!("fail to get input policy CST"))?
+        .0
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let formatted = tree_to_pretty(p, &mut context)?;
+            // Any comment not placed by the doc for `p` is reattached as a
+            // leading comment of the policy rather than dropped.
+            let remaining = take_remaining_comments(p.loc.span, &mut context.tokens);
+            Ok((
+                i,
+                remove_empty_lines(&format!("{remaining}{}", formatted.trim())),
+            ))
+        })
+        .collect::<Result<Vec<(usize, String)>>>()?;
+    if config.sort_policies {
+        let keys = (0..formatted_policies.len())
+            .map(|i| {
+                ast.get_template(&policy_id(i))
+                    .map(|t| sort_key(&t))
+                    .ok_or(miette!("cannot find AST for policy {i}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // stable, so policies with equal keys keep their relative order
+        formatted_policies.sort_by(|(i, _), (j, _)| keys.get(*i).cmp(&keys.get(*j)));
+    }
+    let (order, formatted_policies): (Vec<usize>, Vec<String>) =
+        formatted_policies.into_iter().unzip();
+    let mut formatted_policies = formatted_policies.join("\n\n");
+    // handle comment at the end of a policyset
+    append_end_comment(&mut formatted_policies, end_comment_str);
     // add soundness check to make sure formatting doesn't alter policy ASTs
-    soundness_check(&formatted_policies, &ast)?;
+    soundness_check(&formatted_policies, &ast, &order)?;
+    // and that it doesn't drop or duplicate comments
+    comment_check(&formatted_policies, ps)?;
     Ok(formatted_policies)
 }
 
+/// Format a single Cedar expression given as source text, e.g., the body of a
+/// `when` clause, according to `config`. Returns an error if the input does
+/// not parse as an expression, or if the formatted output would not parse to
+/// the same expression as the input.
+pub fn expr_str_to_pretty(e: &str, config: &FormatterConfig) -> Result<String> {
+    to_fixed_point(e, config, format_expr_once)
+}
+
+fn format_expr_once(e: &str, config: &FormatterConfig) -> Result<String> {
+    let cst = parse_expr(e).wrap_err("cannot parse input expression to CST")?;
+    let ast: Expr = e.parse().wrap_err("cannot parse input expression to AST")?;
+    let tokens = get_token_stream(e).ok_or(miette!("cannot get token stream"))?;
+    let end_comment_str = end_comment_str(e, &tokens)?;
+    let mut context = config::Context { config, tokens };
+    // grouped as in the body of a clause, so it's only broken across lines
+    // when it doesn't fit
+    let formatted = render_doc(cst.to_doc(&mut context).map(RcDoc::group), config)?;
+    let remaining = take_remaining_comments(cst.loc.span, &mut context.tokens);
+    let mut formatted = remove_empty_lines(&format!("{remaining}{}", formatted.trim()));
+    append_end_comment(&mut formatted, end_comment_str);
+
+    let formatted_ast: Expr = formatted
+        .parse()
+        .wrap_err("formatter produces invalid expression")?;
+    if !formatted_ast.eq_shape(&ast) {
+        return Err(miette!(
+            "expressions differ:\nformatted: {formatted_ast}\ninput: {ast}"
+        ));
+    }
+    comment_check(&formatted, e)?;
+    Ok(formatted)
+}
+
+/// Policy head used to parse a lone `when` or `unless` clause. The clause is
+/// placed on its own line so that comments in it stay attached to its tokens.
+const CLAUSE_POLICY_HEAD: &str = "permit(principal, action, resource)\n";
+
+/// Format a single `when` or `unless` clause given as Cedar source text,
+/// e.g., `when { principal == User::"alice" }`, according to `config`.
+/// Returns an error if the input is not exactly one clause, or if the
+/// formatted output would not parse to the same clause as the input.
+pub fn clause_str_to_pretty(c: &str, config: &FormatterConfig) -> Result<String> {
+    to_fixed_point(c, config, format_clause_once)
+}
+
+/// Parse a clause by wrapping it in a policy with an unconstrained head.
+fn parse_clause_as_policy(c: &str) -> Result<StaticPolicy> {
+    parser::parse_policy(None, &format!("{CLAUSE_POLICY_HEAD}{c}\n;"))
+        .wrap_err("cannot parse input clause to AST")
+}
+
+fn format_clause_once(c: &str, config: &FormatterConfig) -> Result<String> {
+    let wrapped = format!("{CLAUSE_POLICY_HEAD}{c}\n;");
+    let cst = parse_policy(&wrapped).wrap_err("cannot parse input clause to CST")?;
+    let ast = parse_clause_as_policy(c)?;
+    let policy = cst
+        .as_inner()
+        .ok_or(miette!("fail to get input clause CST"))?;
+    let [cond] = policy.conds.as_slice() else {
+        return Err(miette!(
+            "expected exactly one `when` or `unless` clause, found {}",
+            policy.conds.len()
+        ));
+    };
+    let tokens = get_token_stream(&wrapped).ok_or(miette!("cannot get token stream"))?;
+    let mut context = config::Context { config, tokens };
+    let formatted = tree_to_pretty(cond, &mut context)?;
+    // this also picks up comments between the clause and the `;` we added
+    let remaining = take_remaining_comments(cst.loc.span, &mut context.tokens);
+    let mut formatted = remove_empty_lines(&format!("{remaining}{}", formatted.trim()));
+    formatted.truncate(formatted.trim_end().len());
+
+    let formatted_ast =
+        parse_clause_as_policy(&formatted).wrap_err("formatter produces invalid clause")?;
+    if !formatted_ast
+        .non_head_constraints()
+        .eq_shape(ast.non_head_constraints())
+    {
+        return Err(miette!(
+            "clauses differ:\nformatted: {}\ninput: {}",
+            formatted_ast.non_head_constraints(),
+            ast.non_head_constraints()
+        ));
+    }
+    comment_check(&formatted, c)?;
+    Ok(formatted)
+}
+
+/// Format a policy or template given in its JSON representation (aka EST),
+/// according to `config`. Returns an error if the EST is not a valid policy or
+/// template. Annotations are printed in order of their keys.
+pub fn est_to_pretty(policy: &est::Policy, config: &FormatterConfig) -> Result<String> {
+    // convert to an AST first to report invalid ESTs with a precise error
+    // rather than as a parse error in text the caller never wrote
+    policy
+        .clone()
+        .try_into_ast_template(None)
+        .wrap_err("cannot convert input EST to AST")?;
+    policies_str_to_pretty(&policy.to_string(), config)
+}
+
+/// Format a policy or template given as a JSON string in the EST format,
+/// according to `config`. See [`est_to_pretty`].
+pub fn est_json_str_to_pretty(json: &str, config: &FormatterConfig) -> Result<String> {
+    let policy: est::Policy = serde_json::from_str(json)
+        .map_err(|err| miette!(format!("cannot parse input EST: {err}")))?;
+    est_to_pretty(&policy, config)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    const TEST_CONFIG: &Config = &Config {
+    use crate::{AnnotationPlacement, BraceStyle, ClauseLayout, ListWrap};
+    const TEST_CONFIG: &FormatterConfig = &FormatterConfig {
         line_width: 40,
         indent_width: 2,
+        brace_style: BraceStyle::NextLine,
+        annotation_placement: AnnotationPlacement::OwnLine,
+        clause_layout: ClauseLayout::Separate,
+        list_wrap: ListWrap::OnePerLine,
+        sort_policies: false,
     };
 
     #[test]
@@ -147,6 +508,19 @@ mod tests {
         assert_eq!(policies_str_to_pretty(policy, TEST_CONFIG).unwrap(), policy);
     }
 
+    #[test]
+    fn raw_strings() {
+        let policy = r###"permit(principal, action, resource) when { context.a == r"C:\docs" && context.b like r#"*"quoted"
+lines*"# };"###;
+        let formatted = policies_str_to_pretty(policy, TEST_CONFIG).unwrap();
+        assert!(formatted.contains(r#"r"C:\docs""#));
+        assert!(formatted.contains("r#\"*\"quoted\"\nlines*\"#"));
+        assert_eq!(
+            policies_str_to_pretty(&formatted, TEST_CONFIG).unwrap(),
+            formatted
+        );
+    }
+
     #[test]
     fn action_in_set() {
         let policy = r#"permit (
@@ -166,15 +540,335 @@ mod tests {
         );
     }
 
+    #[test]
+    fn brace_style() {
+        let policy = r#"permit (principal, action, resource) when { principal.name == "alice" && resource.owner == principal };"#;
+        assert_eq!(
+            policies_str_to_pretty(policy, TEST_CONFIG).unwrap(),
+            r#"permit (principal, action, resource)
+when
+{
+  principal.name == "alice" &&
+  resource.owner == principal
+};"#
+        );
+        let config = FormatterConfig {
+            brace_style: BraceStyle::SameLine,
+            ..TEST_CONFIG.clone()
+        };
+        assert_eq!(
+            policies_str_to_pretty(policy, &config).unwrap(),
+            r#"permit (principal, action, resource)
+when {
+  principal.name == "alice" &&
+  resource.owner == principal
+};"#
+        );
+    }
+
+    #[test]
+    fn annotation_placement() {
+        let policy = r#"@id("a")
+@b("c")
+permit (principal, action, resource);"#;
+        let config = FormatterConfig::default();
+        assert_eq!(policies_str_to_pretty(policy, &config).unwrap(), policy);
+        let config = FormatterConfig {
+            annotation_placement: AnnotationPlacement::Inline,
+            ..config
+        };
+        assert_eq!(
+            policies_str_to_pretty(policy, &config).unwrap(),
+            r#"@id("a") @b("c") permit (principal, action, resource);"#
+        );
+    }
+
+    #[test]
+    fn comments_preserved() {
+        let policy = r#"// leading
+@id("a") // after annotation
+permit ( // after paren
+  principal in // after in
+    Group::"a", // after principal
+  action, // after action
+  resource // after resource
+) // after scope
+when // after when
+{ // after brace
+  [1, // after element
+   2].contains( // after call
+    1) && // after and
+  !context.b // after access
+} // after clause
+unless { // before expr
+  if context.c then // after then
+    false else true
+}; // after semicolon
+// trailing"#;
+        let formatted = policies_str_to_pretty(policy, TEST_CONFIG).unwrap();
+        assert_eq!(
+            comments_in(&formatted).unwrap(),
+            comments_in(policy).unwrap()
+        );
+        assert_eq!(comments_in(policy).unwrap().len(), 19);
+    }
+
+    #[test]
+    fn clause_layout() {
+        let config = FormatterConfig {
+            clause_layout: ClauseLayout::Merge,
+            ..TEST_CONFIG.clone()
+        };
+        let policy = r#"permit(principal,action,resource)
+        when { principal.a } // a
+        unless { context.b || context.c }
+        when { principal.n == 1 };"#;
+        assert_eq!(
+            policies_str_to_pretty(policy, &config).unwrap(),
+            r#"permit (principal, action, resource)
+when
+{
+  // a
+  principal.a &&
+  !(context.b || context.c) &&
+  principal.n == 1
+};"#
+        );
+        // a single clause is left alone
+        let policy = r#"permit (principal, action, resource)
+unless { context.b || context.c };"#;
+        assert_eq!(policies_str_to_pretty(policy, &config).unwrap(), policy);
+    }
+
+    #[test]
+    fn list_wrap() {
+        let policy = r#"permit(principal, action in [Action::"a", Action::"b", Action::"c", Action::"d", Action::"e"], resource);"#;
+        let lines_with_actions = |config: &FormatterConfig| {
+            let formatted = policies_str_to_pretty(policy, config).unwrap();
+            assert!(formatted.lines().all(|l| l.len() <= config.line_width));
+            formatted.lines().filter(|l| l.contains("Action::")).count()
+        };
+        assert_eq!(lines_with_actions(TEST_CONFIG), 5);
+        let fill = FormatterConfig {
+            list_wrap: ListWrap::Fill,
+            ..TEST_CONFIG.clone()
+        };
+        assert!(lines_with_actions(&fill) < 5);
+    }
+
+    #[test]
+    fn sort_policies() {
+        let config = FormatterConfig {
+            sort_policies: true,
+            ..TEST_CONFIG.clone()
+        };
+        let policy = r#"forbid(principal, action, resource);
+        // c
+        @id("c")
+        permit(principal, action, resource);
+        permit(principal == User::"b", action, resource);
+        permit(principal == User::"a", action, resource);
+        @id("b")
+        forbid(principal, action, resource);"#;
+        let expected = r#"@id("b")
+forbid (principal, action, resource);
+
+// c
+@id("c")
+permit (principal, action, resource);
+
+permit (
+  principal == User::"a",
+  action,
+  resource
+);
+
+permit (
+  principal == User::"b",
+  action,
+  resource
+);
+
+forbid (principal, action, resource);"#;
+        assert_eq!(policies_str_to_pretty(policy, &config).unwrap(), expected);
+        // sorting is deterministic regardless of input order
+        let mut reversed = expected.split("\n\n").collect::<Vec<_>>();
+        reversed.reverse();
+        let reversed = reversed.join("\n");
+        assert_eq!(
+            policies_str_to_pretty(&reversed, &config).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn format_est() {
+        let json = r#"{
+            "effect": "permit",
+            "principal": { "op": "==", "entity": { "type": "User", "id": "alice" } },
+            "action": { "op": "All" },
+            "resource": { "op": "All" },
+            "conditions": [
+                { "kind": "when", "body": { "==": {
+                    "left": { "Var": "principal" },
+                    "right": { "Var": "resource" }
+                } } },
+                { "kind": "unless", "body": { "Value": false } }
+            ],
+            "annotations": { "id": "p1", "advice": "hi" }
+        }"#;
+        assert_eq!(
+            est_json_str_to_pretty(json, TEST_CONFIG).unwrap(),
+            r#"@advice("hi")
+@id("p1")
+permit (
+  principal == User::"alice",
+  action,
+  resource
+)
+when { principal == resource }
+unless { false };"#
+        );
+        assert!(est_json_str_to_pretty(r#"{ "effect": "permit" }"#, TEST_CONFIG).is_err());
+    }
+
+    #[test]
+    fn range_formatting() {
+        let policies = r#"permit(principal,action,resource);
+
+// second
+@id("2")
+forbid(principal,action,resource)when{true}; // 2
+
+permit(principal,action,resource);forbid(principal,action,resource);
+// end"#;
+        let apply = |edit: &RangeEdit| {
+            format!(
+                "{}{}{}",
+                &policies[..edit.range.start],
+                edit.text,
+                &policies[edit.range.end..]
+            )
+        };
+
+        // selecting part of the second policy formats it and its comments
+        let offset = policies.find("forbid").unwrap();
+        let edit = policies_range_to_pretty(policies, offset..offset + 3, TEST_CONFIG).unwrap();
+        assert_eq!(
+            &policies[edit.range.clone()],
+            r#"// second
+@id("2")
+forbid(principal,action,resource)when{true}; // 2"#
+        );
+        assert_eq!(
+            apply(&edit),
+            r#"permit(principal,action,resource);
+
+// second
+@id("2")
+forbid (principal, action, resource)
+when { true }; // 2
+
+permit(principal,action,resource);forbid(principal,action,resource);
+// end"#
+        );
+
+        // a cursor in the third policy selects the fourth too, since it's on the
+        // same line, along with the end comment
+        let offset = policies.rfind("permit").unwrap();
+        let edit = policies_range_to_pretty(policies, offset..offset, TEST_CONFIG).unwrap();
+        assert_eq!(edit.range.end, policies.len());
+        assert_eq!(
+            edit.text,
+            r#"permit (principal, action, resource);
+
+forbid (principal, action, resource);
+// end"#
+        );
+
+        // a range between policies selects nothing
+        let offset = policies.find("\n\n").unwrap();
+        let edit = policies_range_to_pretty(policies, offset..offset + 1, TEST_CONFIG).unwrap();
+        assert_eq!(apply(&edit), policies);
+
+        assert!(policies_range_to_pretty(policies, 0..policies.len() + 1, TEST_CONFIG).is_err());
+    }
+
+    #[test]
+    fn check_formatted() {
+        let policy = r#"permit(principal,action,resource) when {
+            principal.name == "alice"
+        }; // trailing
+        // end"#;
+        assert!(!is_formatted(policy, TEST_CONFIG));
+        let formatted = policies_str_to_pretty(policy, TEST_CONFIG).unwrap();
+        assert!(is_formatted(&formatted, TEST_CONFIG));
+        assert!(is_formatted(&format!("{formatted}\n"), TEST_CONFIG));
+        assert!(!is_formatted(&format!("{formatted}\n\n"), TEST_CONFIG));
+        assert!(!is_formatted("permit(principal,", TEST_CONFIG));
+    }
+
+    #[test]
+    fn idempotent() {
+        use std::fs::read_to_string;
+        use std::path::Path;
+
+        let dir_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+        for config in [TEST_CONFIG.clone(), FormatterConfig::default()] {
+            for file in ["test.cedar", "policies.cedar", "is_policies.cedar"] {
+                let once =
+                    policies_str_to_pretty(&read_to_string(dir_path.join(file)).unwrap(), &config)
+                        .unwrap();
+                assert_eq!(policies_str_to_pretty(&once, &config).unwrap(), once);
+                assert!(is_formatted(&once, &config));
+            }
+        }
+    }
+
+    #[test]
+    fn format_expr() {
+        assert_eq!(
+            expr_str_to_pretty(
+                r#"principal.name=="alice"&&context.x // why
+            "#,
+                TEST_CONFIG
+            )
+            .unwrap(),
+            r#"principal.name == "alice" && context.x // why"#
+        );
+        assert_eq!(
+            expr_str_to_pretty("if  context.x then 1 else 2", TEST_CONFIG).unwrap(),
+            "if context.x then 1 else 2"
+        );
+        assert!(expr_str_to_pretty("1 +", TEST_CONFIG).is_err());
+    }
+
+    #[test]
+    fn format_clause() {
+        assert_eq!(
+            clause_str_to_pretty(
+                r#"// who
+                when{principal.name=="alice"}"#,
+                TEST_CONFIG
+            )
+            .unwrap(),
+            r#"// who
+when { principal.name == "alice" }"#
+        );
+        assert_eq!(
+            clause_str_to_pretty("unless { context.x } // trailing", TEST_CONFIG).unwrap(),
+            "unless { context.x } // trailing"
+        );
+        assert!(clause_str_to_pretty("principal.name", TEST_CONFIG).is_err());
+        assert!(clause_str_to_pretty("when { true } when { false }", TEST_CONFIG).is_err());
+    }
+
     #[test]
     fn test_format_files() {
         use std::fs::read_to_string;
         use std::path::Path;
 
-        let config = Config {
-            line_width: 80,
-            indent_width: 2,
-        };
+        let config = FormatterConfig::default();
         let dir_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
         let pairs = vec![
             ("test.cedar", "test_formatted.cedar"),
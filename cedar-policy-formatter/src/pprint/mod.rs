@@ -19,6 +19,8 @@ pub use fmt::*;
 mod config;
 pub use config::*;
 mod doc;
+mod schema;
+pub use schema::*;
 pub mod lexer;
 pub mod token;
 mod utils;
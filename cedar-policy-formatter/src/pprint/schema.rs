@@ -0,0 +1,306 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use miette::{miette, Result};
+use serde_json::{Map, Value};
+
+use super::config::FormatterConfig;
+
+/// The kind of JSON object being printed, which determines the order of its
+/// keys and the kinds of its children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// The whole schema: a map from namespace names to namespaces
+    Schema,
+    Namespace,
+    /// A map from declared names to declarations of the given kind
+    Declarations(Decl),
+    EntityType,
+    Action,
+    AppliesTo,
+    Type,
+    /// An element of an action's `memberOf` list
+    ActionRef,
+    /// Anything else, e.g., an unknown key. Keys are printed in sorted order.
+    Other,
+}
+
+/// The kind of declaration in a [`Kind::Declarations`] map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Decl {
+    Type,
+    EntityType,
+    Action,
+}
+
+impl Kind {
+    /// Known keys for this kind of object, in the order they are printed.
+    /// Other keys follow, in sorted order.
+    fn key_order(self) -> &'static [&'static str] {
+        match self {
+            Kind::Namespace => &["commonTypes", "entityTypes", "actions"],
+            Kind::EntityType => &["memberOfTypes", "shape"],
+            Kind::Action => &["memberOf", "appliesTo", "attributes"],
+            Kind::AppliesTo => &["principalTypes", "resourceTypes", "context"],
+            Kind::Type => &[
+                "type",
+                "name",
+                "element",
+                "attributes",
+                "additionalAttributes",
+                "required",
+            ],
+            Kind::ActionRef => &["id", "type"],
+            Kind::Schema | Kind::Declarations(_) | Kind::Other => &[],
+        }
+    }
+
+    /// The kind of the value stored under `key` in an object of this kind
+    fn child(self, key: &str) -> Kind {
+        match (self, key) {
+            (Kind::Schema, _) => Kind::Namespace,
+            (Kind::Namespace, "commonTypes") => Kind::Declarations(Decl::Type),
+            (Kind::Namespace, "entityTypes") => Kind::Declarations(Decl::EntityType),
+            (Kind::Namespace, "actions") => Kind::Declarations(Decl::Action),
+            (Kind::Declarations(Decl::Type), _) => Kind::Type,
+            (Kind::Declarations(Decl::EntityType), _) => Kind::EntityType,
+            (Kind::Declarations(Decl::Action), _) => Kind::Action,
+            (Kind::EntityType, "shape")
+            | (Kind::AppliesTo, "context")
+            | (Kind::Type, "element") => Kind::Type,
+            (Kind::Type, "attributes") => Kind::Declarations(Decl::Type),
+            (Kind::Action, "appliesTo") => Kind::AppliesTo,
+            _ => Kind::Other,
+        }
+    }
+
+    /// The kind of the elements of the array stored under `key` in an object
+    /// of this kind
+    fn element(self, key: &str) -> Kind {
+        match (self, key) {
+            (Kind::Action, "memberOf") => Kind::ActionRef,
+            _ => Kind::Other,
+        }
+    }
+}
+
+struct SchemaPrinter<'a> {
+    config: &'a FormatterConfig,
+    indent: usize,
+    out: String,
+}
+
+impl<'a> SchemaPrinter<'a> {
+    fn new(config: &'a FormatterConfig) -> Self {
+        Self {
+            config,
+            indent: usize::try_from(config.indent_width).unwrap_or_default(),
+            out: String::new(),
+        }
+    }
+
+    fn newline(&mut self, level: usize) {
+        self.out.push('\n');
+        self.out.push_str(&" ".repeat(level * self.indent));
+    }
+
+    fn column(&self) -> usize {
+        self.out.len() - self.out.rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    fn value(&mut self, value: &Value, kind: Kind, elements: Kind, level: usize) {
+        match value {
+            Value::Object(obj) => self.object(obj, kind, level),
+            Value::Array(arr) => self.array(arr, elements, level),
+            // serializing a `Value` can't fail
+            scalar => self.out.push_str(&scalar.to_string()),
+        }
+    }
+
+    fn object(&mut self, obj: &Map<String, Value>, kind: Kind, level: usize) {
+        if obj.is_empty() {
+            self.out.push_str("{}");
+            return;
+        }
+        let order = kind.key_order();
+        let rank = |key: &str| {
+            order
+                .iter()
+                .position(|known| *known == key)
+                .unwrap_or(order.len())
+        };
+        let mut entries: Vec<(&String, &Value)> = obj.iter().collect();
+        // known keys in their canonical order, followed by any other keys
+        // sorted alphabetically
+        entries.sort_by(|(a, _), (b, _)| (rank(a), a).cmp(&(rank(b), b)));
+        self.out.push('{');
+        for (i, (key, value)) in entries.into_iter().enumerate() {
+            if i > 0 {
+                self.out.push(',');
+            }
+            self.newline(level + 1);
+            self.out.push_str(&Value::String(key.clone()).to_string());
+            self.out.push_str(": ");
+            self.value(value, kind.child(key), kind.element(key), level + 1);
+        }
+        self.newline(level);
+        self.out.push('}');
+    }
+
+    fn array(&mut self, arr: &[Value], elements: Kind, level: usize) {
+        if arr.is_empty() {
+            self.out.push_str("[]");
+            return;
+        }
+        // arrays of scalars, e.g., lists of entity types, are kept on one line
+        // if they fit
+        if arr.iter().all(|v| !v.is_object() && !v.is_array()) {
+            let inline = format!(
+                "[{}]",
+                arr.iter()
+                    .map(Value::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            // leave room for a trailing comma
+            if self.column() + inline.len() < self.config.line_width {
+                self.out.push_str(&inline);
+                return;
+            }
+        }
+        self.out.push('[');
+        for (i, v) in arr.iter().enumerate() {
+            if i > 0 {
+                self.out.push(',');
+            }
+            self.newline(level + 1);
+            self.value(v, elements, Kind::Other, level + 1);
+        }
+        self.newline(level);
+        self.out.push(']');
+    }
+}
+
+/// Format a Cedar schema given in the JSON schema format, according to
+/// `config`. Known schema keys are printed in a fixed order (e.g.,
+/// `memberOfTypes` before `shape`), while namespaces, declarations, and
+/// attributes are sorted by name. Arrays of strings, such as lists of entity
+/// types, are kept on one line when they fit within `config.line_width`.
+///
+/// Returns an error if the input is not valid JSON. The input is not otherwise
+/// validated as a schema, so that schemas with errors can still be formatted.
+/// Like [`crate::policies_str_to_pretty`], the output never ends with a
+/// newline.
+pub fn schema_json_str_to_pretty(src: &str, config: &FormatterConfig) -> Result<String> {
+    let json: Value = serde_json::from_str(src)
+        .map_err(|err| miette!(format!("cannot parse input schema as JSON: {err}")))?;
+    let mut printer = SchemaPrinter::new(config);
+    printer.value(&json, Kind::Schema, Kind::Other, 0);
+    let formatted = printer.out;
+    // soundness check to make sure formatting doesn't alter the schema
+    let formatted_json: Value = serde_json::from_str(&formatted)
+        .map_err(|err| miette!(format!("formatter produces invalid JSON: {err}")))?;
+    if formatted_json != json {
+        return Err(miette!("formatted schema differs from the input"));
+    }
+    Ok(formatted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_key_order() {
+        let src = r#"{"NS": {"actions": {"view": {"appliesTo": {"resourceTypes": ["Photo"], "principalTypes": ["User", "Group"]}, "memberOf": [{"type": "NS::Action", "id": "read"}]}, "read": {}},
+            "entityTypes": {"User": {"shape": {"attributes": {"name": {"type": "String"}, "age": {"required": false, "type": "Long"}}, "type": "Record"}, "memberOfTypes": ["Group"]}, "Group": {}}}}"#;
+        let expected = r#"{
+  "NS": {
+    "entityTypes": {
+      "Group": {},
+      "User": {
+        "memberOfTypes": ["Group"],
+        "shape": {
+          "type": "Record",
+          "attributes": {
+            "age": {
+              "type": "Long",
+              "required": false
+            },
+            "name": {
+              "type": "String"
+            }
+          }
+        }
+      }
+    },
+    "actions": {
+      "read": {},
+      "view": {
+        "memberOf": [
+          {
+            "id": "read",
+            "type": "NS::Action"
+          }
+        ],
+        "appliesTo": {
+          "principalTypes": ["User", "Group"],
+          "resourceTypes": ["Photo"]
+        }
+      }
+    }
+  }
+}"#;
+        let config = FormatterConfig::default();
+        assert_eq!(schema_json_str_to_pretty(src, &config).unwrap(), expected);
+        assert_eq!(
+            schema_json_str_to_pretty(expected, &config).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn long_arrays_are_broken() {
+        let src =
+            r#"{"": {"entityTypes": {"A": {"memberOfTypes": ["Group1", "Group2", "Group3"]}}}}"#;
+        let config = FormatterConfig {
+            line_width: 40,
+            indent_width: 4,
+            ..FormatterConfig::default()
+        };
+        assert_eq!(
+            schema_json_str_to_pretty(src, &config).unwrap(),
+            r#"{
+    "": {
+        "entityTypes": {
+            "A": {
+                "memberOfTypes": [
+                    "Group1",
+                    "Group2",
+                    "Group3"
+                ]
+            }
+        }
+    }
+}"#
+        );
+    }
+
+    #[test]
+    fn invalid_json() {
+        assert!(schema_json_str_to_pretty("{", &FormatterConfig::default()).is_err());
+    }
+}
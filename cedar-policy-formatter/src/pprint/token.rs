@@ -38,6 +38,15 @@ pub fn get_comment(text: &str) -> String {
     }
 }
 
+// Lex the rest of a raw string literal delimited by `r#"` and `"#`. It ends at
+// the first `"#`, which logos can't find with a regex since it doesn't
+// backtrack.
+fn raw_string(lex: &mut logos::Lexer<'_, Token>) -> Option<SmolStr> {
+    let end = lex.remainder().find("\"#")?;
+    lex.bump(end + 2);
+    Some(SmolStr::new(lex.slice()))
+}
+
 // Represent Cedar comments
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Comment {
@@ -138,6 +147,8 @@ pub enum Token {
     Number(SmolStr),
 
     #[regex(r#""(\\.|[^"\\])*""#, |lex| SmolStr::new(lex.slice()))]
+    #[regex(r#"r"[^"]*""#, |lex| SmolStr::new(lex.slice()))]
+    #[token("r#\"", raw_string)]
     Str(SmolStr),
 
     #[token("@")]
@@ -112,6 +112,19 @@ pub fn get_comment_in_range(span: miette::SourceSpan, tokens: &mut [WrappedToken
         .collect()
 }
 
+// Consume the comments attached to tokens within `span` that were not
+// consumed while building a doc for the CST node covering `span`. Returns the
+// comments as newline-terminated lines, so they can be reattached to the
+// nearest enclosing policy instead of being dropped.
+pub fn take_remaining_comments(span: miette::SourceSpan, tokens: &mut [WrappedToken]) -> String {
+    tokens
+        .iter_mut()
+        .filter(|t| t.span.start >= span.offset() && t.span.end <= span.offset() + span.len())
+        .map(|t| t.consume_comment())
+        .flat_map(|c| [c.leading_comment, c.trailing_comment])
+        .collect()
+}
+
 // Wrap doc with comment
 pub fn add_comment<'a>(d: RcDoc<'a>, comment: Comment, next_doc: RcDoc<'a>) -> RcDoc<'a> {
     let leading_comment = comment.leading_comment;
@@ -1,3 +1,5 @@
+use crate::fuzzy_match::fuzzy_search;
+use crate::types::{EntityRecordKind, Type};
 use crate::{ValidatorEntityType, ValidatorSchema};
 use cedar_policy_core::entities::GetSchemaTypeError;
 use cedar_policy_core::extensions::Extensions;
@@ -242,18 +244,21 @@ impl<'a> ast::RequestSchema for CoreSchema<'a> {
 pub enum RequestValidationError {
     /// Request action is not declared in the schema
     #[error("request's action `{action}` is not declared in the schema")]
+    #[diagnostic(code(cedar::request::undeclared_action))]
     UndeclaredAction {
         /// Action which was not declared in the schema
         action: Arc<ast::EntityUID>,
     },
     /// Request principal is of a type not declared in the schema
     #[error("principal type `{principal_ty}` is not declared in the schema")]
+    #[diagnostic(code(cedar::request::undeclared_principal_type))]
     UndeclaredPrincipalType {
         /// Principal type which was not declared in the schema
         principal_ty: ast::EntityType,
     },
     /// Request resource is of a type not declared in the schema
     #[error("resource type `{resource_ty}` is not declared in the schema")]
+    #[diagnostic(code(cedar::request::undeclared_resource_type))]
     UndeclaredResourceType {
         /// Resource type which was not declared in the schema
         resource_ty: ast::EntityType,
@@ -261,6 +266,7 @@ pub enum RequestValidationError {
     /// Request principal is of a type that is declared in the schema, but is
     /// not valid for the request action
     #[error("principal type `{principal_ty}` is not valid for `{action}`")]
+    #[diagnostic(code(cedar::request::invalid_principal_type))]
     InvalidPrincipalType {
         /// Principal type which is not valid
         principal_ty: ast::EntityType,
@@ -270,6 +276,7 @@ pub enum RequestValidationError {
     /// Request resource is of a type that is declared in the schema, but is
     /// not valid for the request action
     #[error("resource type `{resource_ty}` is not valid for `{action}`")]
+    #[diagnostic(code(cedar::request::invalid_resource_type))]
     InvalidResourceType {
         /// Resource type which is not valid
         resource_ty: ast::EntityType,
@@ -278,6 +285,7 @@ pub enum RequestValidationError {
     },
     /// Context does not comply with the shape specified for the request action
     #[error("context `{context}` is not valid for `{action}`")]
+    #[diagnostic(code(cedar::request::invalid_context))]
     InvalidContext {
         /// Context which is not valid
         context: ast::Context,
@@ -297,6 +305,8 @@ pub struct ContextSchema(
     // INVARIANT: The `Type` stored in this struct must be representable as a
     // `SchemaType` to avoid panicking in `context_type`.
     crate::types::Type,
+    // The defaults of optional attributes, in their JSON form
+    HashMap<SmolStr, serde_json::Value>,
 );
 
 /// A `Type` contains all the information we need for a Core `ContextSchema`.
@@ -309,6 +319,13 @@ impl entities::ContextSchema for ContextSchema {
             .try_into()
             .expect("failed to convert validator type into Core SchemaType")
     }
+
+    fn context_defaults(&self) -> Vec<(SmolStr, serde_json::Value)> {
+        self.1
+            .iter()
+            .map(|(attr, value)| (attr.clone(), value.clone()))
+            .collect()
+    }
 }
 
 /// Since different Actions have different schemas for `Context`, you must
@@ -325,7 +342,60 @@ pub fn context_schema_for_action(
     // as their values are representable. The values are representable
     // because they are taken from the context of a `ValidatorActionId`
     // which was constructed directly from a schema.
-    schema.context_type(action).map(ContextSchema)
+    let action = schema.get_action_id(action)?;
+    Some(ContextSchema(
+        action.context_type(),
+        action.context_defaults.clone(),
+    ))
+}
+
+/// An attribute of a context which isn't declared for the action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndeclaredContextAttribute {
+    /// The attribute
+    pub attr: SmolStr,
+    /// The declared attribute whose name is nearest to `attr`, if the action
+    /// declares any
+    pub suggestion: Option<SmolStr>,
+}
+
+impl std::fmt::Display for UndeclaredContextAttribute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}`", self.attr)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean `{suggestion}`?)")?;
+        }
+        Ok(())
+    }
+}
+
+/// The attributes of `context`, the JSON object of a context, which aren't
+/// declared in the context of `action`, in order of their names. This includes
+/// attributes which an open context type would accept.
+///
+/// Returns `None` if the action is not in the schema.
+pub fn undeclared_context_attributes(
+    schema: &ValidatorSchema,
+    action: &ast::EntityUID,
+    context: &serde_json::Map<String, serde_json::Value>,
+) -> Option<Vec<UndeclaredContextAttribute>> {
+    let declared: Vec<SmolStr> = match schema.context_type(action)? {
+        Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) => {
+            attrs.keys().cloned().collect()
+        }
+        // `ValidatorSchema::context_type` always returns a record type
+        _ => Vec::new(),
+    };
+    let mut undeclared: Vec<_> = context
+        .keys()
+        .filter(|attr| !declared.iter().any(|d| d.as_str() == attr.as_str()))
+        .map(|attr| UndeclaredContextAttribute {
+            attr: attr.into(),
+            suggestion: fuzzy_search(attr, &declared).map(SmolStr::from),
+        })
+        .collect();
+    undeclared.sort_by(|a, b| a.attr.cmp(&b.attr));
+    Some(undeclared)
 }
 
 #[cfg(test)]
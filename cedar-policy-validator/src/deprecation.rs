@@ -0,0 +1,368 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Contains the checks for policies referencing schema elements that are
+//! marked as deprecated.
+
+use cedar_policy_core::ast::{EntityType, EntityUID, ExprKind, Literal, Name, Policy, Template};
+use cedar_policy_core::parser::Loc;
+
+use crate::{
+    typecheck::{PolicyCheck, Typechecker},
+    types::{EntityRecordKind, Type},
+    SourceLocation, ValidationMode, ValidationWarning, ValidationWarningKind, Validator,
+};
+
+impl Validator {
+    /// Generate a warning for every reference in a static policy or template
+    /// to an entity type, action, or entity attribute that the schema marks as
+    /// deprecated. References in the policy scope have no source location.
+    pub(crate) fn deprecation_warnings<'a>(
+        &'a self,
+        t: &'a Template,
+        mode: ValidationMode,
+    ) -> impl Iterator<Item = ValidationWarning<'a>> + 'a {
+        let scope = t
+            .principal_constraint()
+            .as_inner()
+            .iter_entity_type_names()
+            .chain(t.resource_constraint().as_inner().iter_entity_type_names())
+            .filter_map(move |name| self.entity_type_deprecation(name))
+            .chain(
+                t.action_constraint()
+                    .iter_euids()
+                    .filter_map(move |euid| self.action_deprecation(euid)),
+            )
+            .map(|kind| (None, kind));
+        let body = t
+            .non_head_constraints()
+            .subexpressions()
+            .filter_map(move |e| {
+                let kind = match e.expr_kind() {
+                    ExprKind::Lit(Literal::EntityUID(euid)) => match euid.entity_type() {
+                        EntityType::Specified(name) => self
+                            .entity_type_deprecation(name)
+                            .or_else(|| self.action_deprecation(euid)),
+                        EntityType::Unspecified => None,
+                    },
+                    ExprKind::Is { entity_type, .. } => self.entity_type_deprecation(entity_type),
+                    _ => None,
+                }?;
+                Some((e.source_loc().cloned(), kind))
+            });
+        scope
+            .chain(body)
+            .chain(self.attribute_deprecations(t, mode))
+            .map(move |(loc, kind)| ValidationWarning::new(SourceLocation::new(t.id(), loc), kind))
+    }
+
+    /// Generate a warning for every entity in the slots of a template-linked
+    /// policy which has an entity type that the schema marks as deprecated.
+    pub(crate) fn deprecation_warnings_in_slots<'a>(
+        &'a self,
+        p: &'a Policy,
+    ) -> impl Iterator<Item = ValidationWarning<'a>> + 'a {
+        p.env()
+            .values()
+            .filter_map(move |euid| match euid.entity_type() {
+                EntityType::Specified(name) => self.entity_type_deprecation(name),
+                EntityType::Unspecified => None,
+            })
+            .map(move |kind| ValidationWarning::new(SourceLocation::new(p.id(), None), kind))
+    }
+
+    fn entity_type_deprecation(&self, name: &Name) -> Option<ValidationWarningKind> {
+        let message = self.schema.get_entity_type(name)?.deprecated()?;
+        Some(ValidationWarningKind::DeprecatedEntityType(
+            name.to_string(),
+            message.to_string(),
+        ))
+    }
+
+    fn action_deprecation(&self, euid: &EntityUID) -> Option<ValidationWarningKind> {
+        let message = self.schema.get_action_id(euid)?.deprecated()?;
+        Some(ValidationWarningKind::DeprecatedAction(
+            euid.to_string(),
+            message.to_string(),
+        ))
+    }
+
+    /// Find accesses to deprecated entity attributes. We need to typecheck the
+    /// policy to know the entity types that attributes are accessed on, so we
+    /// skip this when the schema doesn't deprecate any attributes. Accesses
+    /// are only found in request environments where the policy typechecks.
+    fn attribute_deprecations(
+        &self,
+        t: &Template,
+        mode: ValidationMode,
+    ) -> Vec<(Option<Loc>, ValidationWarningKind)> {
+        let mut deprecations = Vec::new();
+        if self
+            .schema
+            .entity_types()
+            .all(|(_, ety)| ety.deprecated_attributes.is_empty())
+        {
+            return deprecations;
+        }
+        let typechecker = Typechecker::new(&self.schema, mode);
+        for (_, check) in typechecker.typecheck_by_request_env(t) {
+            let PolicyCheck::Success(typed_expr) = check else {
+                continue;
+            };
+            for e in typed_expr.subexpressions() {
+                let (ExprKind::GetAttr { expr, attr } | ExprKind::HasAttr { expr, attr }) =
+                    e.expr_kind()
+                else {
+                    continue;
+                };
+                let Some(Type::EntityOrRecord(EntityRecordKind::Entity(lub))) = expr.data() else {
+                    continue;
+                };
+                for name in lub.iter() {
+                    let Some(message) = self
+                        .schema
+                        .get_entity_type(name)
+                        .and_then(|ety| ety.attr_deprecated(attr))
+                    else {
+                        continue;
+                    };
+                    let deprecation = (
+                        e.source_loc().cloned(),
+                        ValidationWarningKind::DeprecatedAttribute(
+                            name.to_string(),
+                            attr.to_string(),
+                            message.to_string(),
+                        ),
+                    );
+                    // The same access is found once for every request
+                    // environment the policy typechecks in.
+                    if !deprecations.contains(&deprecation) {
+                        deprecations.push(deprecation);
+                    }
+                }
+            }
+        }
+        deprecations
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use cedar_policy_core::{
+        ast::{PolicyID, PolicySet, SlotId},
+        parser,
+    };
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{SchemaError, ValidatorSchema};
+
+    fn schema() -> ValidatorSchema {
+        serde_json::from_value::<crate::SchemaFragment>(serde_json::json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "login": {
+                                    "type": "String",
+                                    "deprecated": "use `name` instead"
+                                }
+                            }
+                        }
+                    },
+                    "Group": { "deprecated": "groups are being retired" },
+                    "Photo": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": [ "User" ],
+                            "resourceTypes": [ "Photo" ]
+                        }
+                    },
+                    "look": {
+                        "appliesTo": {
+                            "principalTypes": [ "User" ],
+                            "resourceTypes": [ "Photo" ]
+                        },
+                        "deprecated": "use `view` instead"
+                    }
+                }
+            }
+        }))
+        .expect("schema should parse")
+        .try_into()
+        .expect("schema should be valid")
+    }
+
+    fn warnings(src: &str) -> Vec<ValidationWarningKind> {
+        let mut set = PolicySet::new();
+        set.add_static(parser::parse_policy(None, src).expect("policy should parse"))
+            .expect("policy should be added");
+        let validator = Validator::new(schema());
+        let result = validator.validate(&set, ValidationMode::default());
+        result
+            .validation_warnings()
+            .map(|w| w.kind().clone())
+            .collect()
+    }
+
+    #[test]
+    fn no_deprecated_references() {
+        assert_eq!(
+            warnings(
+                r#"permit(principal, action == Action::"view", resource) when { principal.name == "alice" };"#
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn deprecated_entity_type() {
+        assert_eq!(
+            warnings(
+                r#"permit(principal in Group::"admins", action == Action::"view", resource);"#
+            ),
+            vec![ValidationWarningKind::DeprecatedEntityType(
+                "Group".into(),
+                "groups are being retired".into()
+            )]
+        );
+
+        let mut set = PolicySet::new();
+        set.add_static(
+            parser::parse_policy(
+                None,
+                r#"permit(principal, action == Action::"view", resource) when { principal in Group::"admins" };"#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let validator = Validator::new(schema());
+        let result = validator.validate(&set, ValidationMode::default());
+        let warning = result.validation_warnings().next().unwrap();
+        assert_eq!(
+            warning.kind(),
+            &ValidationWarningKind::DeprecatedEntityType(
+                "Group".into(),
+                "groups are being retired".into()
+            )
+        );
+        assert!(warning.location().source_loc().is_some());
+    }
+
+    #[test]
+    fn deprecated_action() {
+        assert_eq!(
+            warnings(r#"permit(principal, action in [Action::"view", Action::"look"], resource);"#),
+            vec![ValidationWarningKind::DeprecatedAction(
+                r#"Action::"look""#.into(),
+                "use `view` instead".into()
+            )]
+        );
+        assert_eq!(
+            warnings(r#"permit(principal, action, resource) when { action == Action::"look" };"#),
+            vec![ValidationWarningKind::DeprecatedAction(
+                r#"Action::"look""#.into(),
+                "use `view` instead".into()
+            )]
+        );
+    }
+
+    #[test]
+    fn deprecated_attribute() {
+        let deprecated_login = ValidationWarningKind::DeprecatedAttribute(
+            "User".into(),
+            "login".into(),
+            "use `name` instead".into(),
+        );
+        // The policy applies to both actions, but each access is reported once.
+        assert_eq!(
+            warnings(r#"permit(principal, action, resource) when { principal.login == "alice" };"#),
+            vec![deprecated_login.clone()]
+        );
+        assert_eq!(
+            warnings(
+                r#"permit(principal, action == Action::"view", resource) when { principal has login };"#
+            ),
+            vec![deprecated_login]
+        );
+    }
+
+    #[test]
+    fn deprecated_entity_type_in_slot() {
+        let mut set = PolicySet::new();
+        let template = parser::parse_policy_template(
+            Some("t".to_string()),
+            r#"permit(principal in ?principal, action == Action::"view", resource);"#,
+        )
+        .unwrap();
+        set.add_template(template).unwrap();
+        set.link(
+            PolicyID::from_string("t"),
+            PolicyID::from_string("link"),
+            HashMap::from([(SlotId::principal(), r#"Group::"admins""#.parse().unwrap())]),
+        )
+        .unwrap();
+        let validator = Validator::new(schema());
+        let result = validator.validate(&set, ValidationMode::default());
+        assert_eq!(
+            result
+                .validation_warnings()
+                .map(|w| (w.location().policy_id().to_string(), w.kind().clone()))
+                .collect::<Vec<_>>(),
+            vec![(
+                "link".to_string(),
+                ValidationWarningKind::DeprecatedEntityType(
+                    "Group".into(),
+                    "groups are being retired".into()
+                )
+            )]
+        );
+    }
+
+    #[test]
+    fn deprecated_nested_attribute_is_rejected() {
+        let schema = serde_json::from_value::<crate::SchemaFragment>(serde_json::json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "address": {
+                                    "type": "Record",
+                                    "attributes": {
+                                        "zip": { "type": "String", "deprecated": "no" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap();
+        match ValidatorSchema::try_from(schema) {
+            Err(SchemaError::UnsupportedAttributeDeprecation(attr)) => assert_eq!(attr, "zip"),
+            r => panic!("expected a deprecation error, got {r:?}"),
+        }
+    }
+}
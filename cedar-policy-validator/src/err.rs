@@ -23,8 +23,11 @@ use cedar_policy_core::{
 };
 use itertools::Itertools;
 use miette::Diagnostic;
+use smol_str::SmolStr;
 use thiserror::Error;
 
+use crate::StrictSchemaFinding;
+
 #[derive(Debug, Diagnostic, Error)]
 pub enum SchemaError {
     /// Error thrown by the `serde_json` crate during deserialization
@@ -94,6 +97,11 @@ pub enum SchemaError {
     #[error("parse error in common type identifier: {}", Self::format_parse_errs(.0))]
     #[diagnostic(transparent)]
     ParseCommonType(ParseErrors),
+    /// Parse errors occurring while parsing the key of an annotation on an
+    /// entity type, attribute, or action.
+    #[error("parse error in annotation key: {}", Self::format_parse_errs(.0))]
+    #[diagnostic(transparent)]
+    ParseAnnotationKey(ParseErrors),
     /// The schema file included an entity type `Action` in the entity type
     /// list. The `Action` entity type is always implicitly declared, and it
     /// cannot currently have attributes or be in any groups, so there is no
@@ -128,6 +136,29 @@ pub enum SchemaError {
     #[error("the `__expr` escape is no longer supported")]
     #[diagnostic(help("to create an entity reference, use `__entity`; to create an extension value, use `__extn`; and for all other values, use JSON directly"))]
     ExprEscapeUsed,
+    /// An action has a context default for an attribute which isn't an
+    /// optional attribute of its context.
+    #[error("action `{0}` has a context default for `{1}`, which is not an optional attribute of its context")]
+    #[diagnostic(help(
+        "defaults can only be given for context attributes declared with `\"required\": false`"
+    ))]
+    ContextDefaultNotOptional(EntityUID, SmolStr),
+    /// An action has a context default which doesn't have the type of the
+    /// context attribute.
+    #[error("action `{0}` has an invalid context default for `{1}`: {2}")]
+    InvalidContextDefault(EntityUID, SmolStr, String),
+    /// An attribute which is not declared directly in the shape of an entity
+    /// type is marked as deprecated.
+    #[error("attribute `{0}` is marked as deprecated, but only attributes declared directly in an entity type's shape can be deprecated")]
+    UnsupportedAttributeDeprecation(SmolStr),
+    /// An attribute which is not declared directly in the shape of an entity
+    /// type has annotations.
+    #[error("attribute `{0}` has annotations, but only attributes declared directly in an entity type's shape can be annotated")]
+    UnsupportedAttributeAnnotations(SmolStr),
+    /// Findings from parsing a schema in strict mode. These are unknown keys
+    /// and suspicious empty constructs, all reported together.
+    #[error("schema failed strict-mode checks with {} finding(s)", .0.len())]
+    StrictModeViolations(#[related] Vec<StrictSchemaFinding>),
 }
 
 impl From<transitive_closure::TcError<EntityUID>> for SchemaError {
@@ -139,9 +170,9 @@ impl From<transitive_closure::TcError<EntityUID>> for SchemaError {
             transitive_closure::TcError::MissingTcEdge { .. } => {
                 SchemaError::ActionTransitiveClosure(Box::new(e))
             }
-            transitive_closure::TcError::HasCycle { vertex_with_loop } => {
-                SchemaError::CycleInActionHierarchy(vertex_with_loop)
-            }
+            transitive_closure::TcError::HasCycle {
+                vertex_with_loop, ..
+            } => SchemaError::CycleInActionHierarchy(vertex_with_loop),
         }
     }
 }
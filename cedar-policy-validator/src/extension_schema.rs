@@ -67,8 +67,8 @@ pub(crate) type ArgumentCheckFn = Box<dyn Fn(&[Expr]) -> Result<(), String>>;
 pub struct ExtensionFunctionType {
     /// Function name
     name: Name,
-    /// Argument types
-    argument_types: Vec<Type>,
+    /// Argument types. An argument may have any one of its types.
+    argument_types: Vec<Vec<Type>>,
     /// Return type
     return_type: Type,
     /// Custom argument validation (optional)
@@ -82,6 +82,22 @@ impl ExtensionFunctionType {
         argument_types: Vec<Type>,
         return_type: Type,
         check_arguments: Option<ArgumentCheckFn>,
+    ) -> Self {
+        Self::overloaded(
+            name,
+            argument_types.into_iter().map(|ty| vec![ty]).collect(),
+            return_type,
+            check_arguments,
+        )
+    }
+
+    /// Create a new `ExtensionFunctionType` where each argument may have any
+    /// one of several types
+    pub fn overloaded(
+        name: Name,
+        argument_types: Vec<Vec<Type>>,
+        return_type: Type,
+        check_arguments: Option<ArgumentCheckFn>,
     ) -> Self {
         Self {
             name,
@@ -96,8 +112,9 @@ impl ExtensionFunctionType {
         &self.name
     }
 
-    /// Get the extension function argument types
-    pub fn argument_types(&self) -> &Vec<Type> {
+    /// Get the extension function argument types, which are the types each
+    /// argument may have
+    pub fn argument_types(&self) -> &[Vec<Type>] {
         &self.argument_types
     }
 
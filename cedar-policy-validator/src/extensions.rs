@@ -24,6 +24,9 @@ pub mod ipaddr;
 #[cfg(feature = "decimal")]
 pub mod decimal;
 
+#[cfg(feature = "unicode")]
+pub mod unicode;
+
 pub mod partial_evaluation;
 
 /// Get schemas for all the available extensions.
@@ -33,6 +36,8 @@ pub fn all_available_extension_schemas() -> Vec<ExtensionSchema> {
         ipaddr::extension_schema(),
         #[cfg(feature = "decimal")]
         decimal::extension_schema(),
+        #[cfg(feature = "unicode")]
+        unicode::extension_schema(),
         partial_evaluation::extension_schema(),
     ]
 }
@@ -32,12 +32,13 @@ use std::str::FromStr;
 
 // PANIC SAFETY see `Note on safety` above
 #[allow(clippy::panic)]
-fn get_argument_types(fname: &str, decimal_ty: &Type) -> Vec<types::Type> {
+fn get_argument_types(fname: &str, decimal_ty: &Type) -> Vec<Vec<types::Type>> {
     match fname {
-        "decimal" => vec![Type::primitive_string()],
-        "lessThan" | "lessThanOrEqual" | "greaterThan" | "greaterThanOrEqual" => {
-            vec![decimal_ty.clone(), decimal_ty.clone()]
-        }
+        "decimal" => vec![vec![Type::primitive_string(), Type::primitive_long()]],
+        "lessThan" | "lessThanOrEqual" | "greaterThan" | "greaterThanOrEqual" => vec![
+            vec![decimal_ty.clone()],
+            vec![decimal_ty.clone(), Type::primitive_long()],
+        ],
         _ => panic!("unexpected decimal extension function name: {fname}"),
     }
 }
@@ -58,7 +59,7 @@ fn get_return_type(fname: &str, decimal_ty: &Type) -> Type {
 #[allow(clippy::panic)]
 fn get_argument_check(fname: &str) -> Option<ArgumentCheckFn> {
     match fname {
-        "decimal" => Some(Box::new(validate_decimal_string)),
+        "decimal" => Some(Box::new(validate_decimal_literal)),
         "lessThan" | "lessThanOrEqual" | "greaterThan" | "greaterThanOrEqual" => None,
         _ => panic!("unexpected decimal extension function name: {fname}"),
     }
@@ -79,7 +80,7 @@ pub fn extension_schema() -> ExtensionSchema {
                 .return_type()
                 .map(|ty| return_type.is_consistent_with(ty))
                 .unwrap_or_else(|| return_type == Type::Never));
-            ExtensionFunctionType::new(
+            ExtensionFunctionType::overloaded(
                 fname.clone(),
                 get_argument_types(&fstring, &decimal_ty),
                 return_type,
@@ -90,11 +91,17 @@ pub fn extension_schema() -> ExtensionSchema {
     ExtensionSchema::new(decimal_ext.name().clone(), fun_tys)
 }
 
-/// Extra validation step for the `decimal` function.
+/// Extra validation step for the `decimal` function, which checks that a
+/// string literal is a decimal value, and that a long literal isn't too big.
 /// Note that `exprs` will have already been checked to contain the correct number of arguments.
-fn validate_decimal_string(exprs: &[Expr]) -> Result<(), String> {
+fn validate_decimal_literal(exprs: &[Expr]) -> Result<(), String> {
     match exprs.get(0) {
-        Some(arg) if matches!(arg.expr_kind(), ExprKind::Lit(Literal::String(_))) => {
+        Some(arg)
+            if matches!(
+                arg.expr_kind(),
+                ExprKind::Lit(Literal::String(_) | Literal::Long(_))
+            ) =>
+        {
             let exts = Extensions::all_available();
             let evaluator = RestrictedEvaluator::new(&exts);
             match RestrictedExpr::from_str(&format!("decimal({arg})")) {
@@ -0,0 +1,83 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Note on panic safety
+//! If any of the panics in this file are triggered, that means that this file has become
+//! out-of-date with the unicode extension definition in CedarCore.
+//! This is tested by the `extension_schema_correctness()` test
+
+use crate::extension_schema::{ExtensionFunctionType, ExtensionSchema};
+use crate::types::{self, Type};
+use cedar_policy_core::extensions::unicode;
+
+/// Note on safety:
+/// This module depends on the Cedar parser only constructing AST with valid extension calls
+/// If any of the panics in this file are triggered, that means that this file has become
+/// out-of-date with the unicode extension definition in CedarCore.
+
+// PANIC SAFETY see `Note on safety` above
+#[allow(clippy::panic)]
+fn get_argument_types(fname: &str) -> Vec<types::Type> {
+    match fname {
+        "equalsIgnoreCase" | "equalsNormalized" => {
+            vec![Type::primitive_string(), Type::primitive_string()]
+        }
+        _ => panic!("unexpected unicode extension function name: {fname}"),
+    }
+}
+
+// PANIC SAFETY see `Note on safety` above
+#[allow(clippy::panic)]
+fn get_return_type(fname: &str) -> Type {
+    match fname {
+        "equalsIgnoreCase" | "equalsNormalized" => Type::primitive_boolean(),
+        _ => panic!("unexpected unicode extension function name: {fname}"),
+    }
+}
+
+/// Construct the extension schema
+pub fn extension_schema() -> ExtensionSchema {
+    let unicode_ext = unicode::extension();
+    let fun_tys: Vec<ExtensionFunctionType> = unicode_ext
+        .funcs()
+        .map(|f| {
+            let fname = f.name();
+            let fstring = fname.to_string();
+            let return_type = get_return_type(&fstring);
+            debug_assert!(f
+                .return_type()
+                .map(|ty| return_type.is_consistent_with(ty))
+                .unwrap_or_else(|| return_type == Type::Never));
+            ExtensionFunctionType::new(
+                fname.clone(),
+                get_argument_types(&fstring),
+                return_type,
+                None,
+            )
+        })
+        .collect();
+    ExtensionSchema::new(unicode_ext.name().clone(), fun_tys)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Ensures that `extension_schema()` does not panic
+    #[test]
+    fn extension_schema_correctness() {
+        let _ = extension_schema();
+    }
+}
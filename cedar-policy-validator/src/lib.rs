@@ -25,6 +25,7 @@ mod err;
 pub use err::*;
 mod coreschema;
 pub use coreschema::*;
+mod deprecation;
 mod expr_iterator;
 mod extension_schema;
 mod extensions;
@@ -103,9 +104,18 @@ impl Validator {
             .policies()
             .filter_map(|p| self.validate_slots(p, mode))
             .flatten();
+        let deprecation_warnings = policies
+            .all_templates()
+            .flat_map(move |t| self.deprecation_warnings(t, mode))
+            .chain(
+                policies
+                    .policies()
+                    .filter(|p| !p.is_static())
+                    .flat_map(|p| self.deprecation_warnings_in_slots(p)),
+            );
         ValidationResult::new(
             template_and_static_policy_errs.chain(link_errs),
-            confusable_string_checks(policies.all_templates()),
+            confusable_string_checks(policies.all_templates()).chain(deprecation_warnings),
         )
     }
 
@@ -197,7 +207,7 @@ impl Validator {
 
 #[cfg(test)]
 mod test {
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, HashMap};
 
     use crate::types::Type;
 
@@ -220,6 +230,8 @@ mod test {
                     EntityType {
                         member_of_types: vec![],
                         shape: AttributesOrContext::default(),
+                        deprecated: None,
+                        annotations: BTreeMap::new(),
                     },
                 ),
                 (
@@ -227,6 +239,8 @@ mod test {
                     EntityType {
                         member_of_types: vec![],
                         shape: AttributesOrContext::default(),
+                        deprecated: None,
+                        annotations: BTreeMap::new(),
                     },
                 ),
             ],
@@ -238,8 +252,11 @@ mod test {
                         principal_types: None,
                         context: AttributesOrContext::default(),
                     }),
+                    deprecated: None,
+                    annotations: BTreeMap::new(),
                     member_of: None,
                     attributes: None,
+                    context_defaults: None,
                 },
             )],
         );
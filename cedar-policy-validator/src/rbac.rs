@@ -482,6 +482,8 @@ mod test {
                     EntityType {
                         member_of_types: vec![group_type.into()],
                         shape: AttributesOrContext::default(),
+                        deprecated: None,
+                        annotations: BTreeMap::new(),
                     },
                 ),
                 (
@@ -489,6 +491,8 @@ mod test {
                     EntityType {
                         member_of_types: vec![],
                         shape: AttributesOrContext::default(),
+                        deprecated: None,
+                        annotations: BTreeMap::new(),
                     },
                 ),
                 (
@@ -496,6 +500,8 @@ mod test {
                     EntityType {
                         member_of_types: vec![bin_type.into()],
                         shape: AttributesOrContext::default(),
+                        deprecated: None,
+                        annotations: BTreeMap::new(),
                     },
                 ),
                 (
@@ -503,6 +509,8 @@ mod test {
                     EntityType {
                         member_of_types: vec![],
                         shape: AttributesOrContext::default(),
+                        deprecated: None,
+                        annotations: BTreeMap::new(),
                     },
                 ),
             ],
@@ -514,8 +522,11 @@ mod test {
                         principal_types: Some(vec![user_type.into()]),
                         context: AttributesOrContext::default(),
                     }),
+                    deprecated: None,
+                    annotations: BTreeMap::new(),
                     member_of: None,
                     attributes: None,
+                    context_defaults: None,
                 },
             )],
         );
@@ -558,6 +569,8 @@ mod test {
                 EntityType {
                     member_of_types: vec![],
                     shape: AttributesOrContext::default(),
+                    deprecated: None,
+                    annotations: BTreeMap::new(),
                 },
             )],
             [],
@@ -593,6 +606,8 @@ mod test {
                 EntityType {
                     member_of_types: vec![],
                     shape: AttributesOrContext::default(),
+                    deprecated: None,
+                    annotations: BTreeMap::new(),
                 },
             )],
             [],
@@ -678,8 +693,11 @@ mod test {
                 foo_name.into(),
                 ActionType {
                     applies_to: None,
+                    deprecated: None,
+                    annotations: BTreeMap::new(),
                     member_of: None,
                     attributes: None,
+                    context_defaults: None,
                 },
             )],
         );
@@ -714,6 +732,8 @@ mod test {
                 EntityType {
                     member_of_types: vec![],
                     shape: AttributesOrContext::default(),
+                    deprecated: None,
+                    annotations: BTreeMap::new(),
                 },
             )],
             [],
@@ -738,6 +758,8 @@ mod test {
                 EntityType {
                     member_of_types: vec![],
                     shape: AttributesOrContext::default(),
+                    deprecated: None,
+                    annotations: BTreeMap::new(),
                 },
             )],
             [],
@@ -762,6 +784,8 @@ mod test {
                 EntityType {
                     member_of_types: vec![],
                     shape: AttributesOrContext::default(),
+                    deprecated: None,
+                    annotations: BTreeMap::new(),
                 },
             )],
             [],
@@ -805,8 +829,11 @@ mod test {
                 "foo_name".into(),
                 ActionType {
                     applies_to: None,
+                    deprecated: None,
+                    annotations: BTreeMap::new(),
                     member_of: None,
                     attributes: None,
+                    context_defaults: None,
                 },
             )],
         );
@@ -995,8 +1022,11 @@ mod test {
                 foo_name.into(),
                 ActionType {
                     applies_to: None,
+                    deprecated: None,
+                    annotations: BTreeMap::new(),
                     member_of: None,
                     attributes: None,
+                    context_defaults: None,
                 },
             )],
         );
@@ -1024,8 +1054,11 @@ mod test {
                 foo_name.into(),
                 ActionType {
                     applies_to: None,
+                    deprecated: None,
+                    annotations: BTreeMap::new(),
                     member_of: None,
                     attributes: None,
+                    context_defaults: None,
                 },
             )],
         );
@@ -1053,8 +1086,11 @@ mod test {
                 foo_name.into(),
                 ActionType {
                     applies_to: None,
+                    deprecated: None,
+                    annotations: BTreeMap::new(),
                     member_of: None,
                     attributes: None,
+                    context_defaults: None,
                 },
             )],
         );
@@ -1082,6 +1118,8 @@ mod test {
                 EntityType {
                     member_of_types: vec![],
                     shape: AttributesOrContext::default(),
+                    deprecated: None,
+                    annotations: BTreeMap::new(),
                 },
             )],
             [],
@@ -1118,6 +1156,8 @@ mod test {
                     EntityType {
                         member_of_types: vec![],
                         shape: AttributesOrContext::default(),
+                        deprecated: None,
+                        annotations: BTreeMap::new(),
                     },
                 ),
                 (
@@ -1125,6 +1165,8 @@ mod test {
                     EntityType {
                         member_of_types: vec![],
                         shape: AttributesOrContext::default(),
+                        deprecated: None,
+                        annotations: BTreeMap::new(),
                     },
                 ),
             ],
@@ -1136,8 +1178,11 @@ mod test {
                         principal_types: Some(vec![principal_type.into()]),
                         context: AttributesOrContext::default(),
                     }),
+                    deprecated: None,
+                    annotations: BTreeMap::new(),
                     member_of: Some(vec![]),
                     attributes: None,
+                    context_defaults: None,
                 },
             )],
         )
@@ -1525,6 +1570,8 @@ mod test {
                     EntityType {
                         member_of_types: vec![],
                         shape: AttributesOrContext::default(),
+                        deprecated: None,
+                        annotations: BTreeMap::new(),
                     },
                 ),
                 (
@@ -1532,6 +1579,8 @@ mod test {
                     EntityType {
                         member_of_types: vec![resource_parent_type.into()],
                         shape: AttributesOrContext::default(),
+                        deprecated: None,
+                        annotations: BTreeMap::new(),
                     },
                 ),
                 (
@@ -1539,6 +1588,8 @@ mod test {
                     EntityType {
                         member_of_types: vec![resource_grandparent_type.into()],
                         shape: AttributesOrContext::default(),
+                        deprecated: None,
+                        annotations: BTreeMap::new(),
                     },
                 ),
                 (
@@ -1546,6 +1597,8 @@ mod test {
                     EntityType {
                         member_of_types: vec![],
                         shape: AttributesOrContext::default(),
+                        deprecated: None,
+                        annotations: BTreeMap::new(),
                     },
                 ),
             ],
@@ -1558,30 +1611,39 @@ mod test {
                             principal_types: Some(vec![principal_type.into()]),
                             context: AttributesOrContext::default(),
                         }),
+                        deprecated: None,
+                        annotations: BTreeMap::new(),
                         member_of: Some(vec![ActionEntityUID {
                             ty: None,
                             id: action_parent_name.into(),
                         }]),
                         attributes: None,
+                        context_defaults: None,
                     },
                 ),
                 (
                     action_parent_name.into(),
                     ActionType {
                         applies_to: None,
+                        deprecated: None,
+                        annotations: BTreeMap::new(),
                         member_of: Some(vec![ActionEntityUID {
                             ty: None,
                             id: action_grandparent_name.into(),
                         }]),
                         attributes: None,
+                        context_defaults: None,
                     },
                 ),
                 (
                     action_grandparent_name.into(),
                     ActionType {
                         applies_to: None,
+                        deprecated: None,
+                        annotations: BTreeMap::new(),
                         member_of: Some(vec![]),
                         attributes: None,
+                        context_defaults: None,
                     },
                 ),
             ],
@@ -24,12 +24,16 @@ use std::collections::{hash_map::Entry, HashMap, HashSet};
 
 use cedar_policy_core::{
     ast::{Entity, EntityType, EntityUID, Name},
-    entities::{Entities, EntitiesError, TCComputation},
+    entities::{
+        typecheck_restricted_expr_against_schematype, CedarValueJson, Entities, EntitiesError,
+        JsonDeserializationErrorContext, SchemaType, TCComputation, ValueParser,
+    },
     extensions::Extensions,
     transitive_closure::compute_tc,
 };
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
+use smol_str::SmolStr;
 
 use super::NamespaceDefinition;
 use crate::{
@@ -41,13 +45,17 @@ use crate::{
 mod action;
 pub use action::ValidatorActionId;
 pub(crate) use action::ValidatorApplySpec;
+mod compatibility;
+pub use compatibility::{check_compatibility, AttributeOwner, CompatibilityReport, SchemaChange};
 mod entity_type;
 pub use entity_type::ValidatorEntityType;
 mod namespace_def;
 pub(crate) use namespace_def::is_action_entity_type;
 pub use namespace_def::ValidatorNamespaceDef;
+mod strict;
 #[cfg(test)]
 pub(crate) use namespace_def::ACTION_ENTITY_TYPE;
+pub use strict::{strict_schema_findings, StrictSchemaFinding};
 
 // We do not have a dafny model for action attributes, so we disable them by defualt.
 #[derive(Eq, PartialEq, Copy, Clone, Default)]
@@ -166,6 +174,21 @@ impl ValidatorSchema {
         )
     }
 
+    /// Construct a `ValidatorSchema` from a JSON value in strict mode. Before
+    /// parsing, the whole document is checked for unknown (e.g., misspelled)
+    /// keys and suspicious empty constructs. If there are any, all of them
+    /// are returned together in a `SchemaError::StrictModeViolations`.
+    pub fn from_json_value_strict(
+        json: serde_json::Value,
+        extensions: Extensions<'_>,
+    ) -> Result<Self> {
+        let findings = strict_schema_findings(&json);
+        if !findings.is_empty() {
+            return Err(SchemaError::StrictModeViolations(findings));
+        }
+        Self::from_json_value(json, extensions)
+    }
+
     /// Construct a `ValidatorSchema` directly from a file.
     pub fn from_file(file: impl std::io::Read, extensions: Extensions<'_>) -> Result<Self> {
         Self::from_schema_file(
@@ -267,6 +290,10 @@ impl ValidatorSchema {
                         descendants,
                         attributes,
                         open_attributes,
+                        deprecated: entity_type.deprecated,
+                        deprecated_attributes: entity_type.deprecated_attributes,
+                        annotations: entity_type.annotations,
+                        attribute_annotations: entity_type.attribute_annotations,
                     },
                 ))
             })
@@ -290,6 +317,8 @@ impl ValidatorSchema {
                         .ok_or(SchemaError::ContextOrShapeNotRecord(
                             ContextOrShape::ActionContext(name.clone()),
                         ))?;
+                let context_defaults =
+                    Self::check_context_defaults(&name, &context, action.context_defaults)?;
                 Ok((
                     name.clone(),
                     ValidatorActionId {
@@ -302,6 +331,9 @@ impl ValidatorSchema {
                         ),
                         attribute_types: action.attribute_types,
                         attributes: action.attributes,
+                        context_defaults,
+                        deprecated: action.deprecated,
+                        annotations: action.annotations,
                     },
                 ))
             })
@@ -409,6 +441,45 @@ impl ValidatorSchema {
         Ok(())
     }
 
+    /// Check that each of the context `defaults` of `action` is for an
+    /// optional attribute of its `context`, and has the attribute's type.
+    /// Returns the defaults in their JSON form, which is how they're added to
+    /// a context before it's parsed.
+    fn check_context_defaults(
+        action: &EntityUID,
+        context: &Attributes,
+        defaults: HashMap<SmolStr, CedarValueJson>,
+    ) -> Result<HashMap<SmolStr, serde_json::Value>> {
+        let extensions = Extensions::all_available();
+        defaults
+            .into_iter()
+            .map(|(attr, value)| {
+                let attr_type = match context.get_attr(&attr) {
+                    Some(attr_type) if !attr_type.is_required => attr_type,
+                    _ => return Err(SchemaError::ContextDefaultNotOptional(action.clone(), attr)),
+                };
+                let json = serde_json::to_value(value)?;
+                let invalid = |err: String| {
+                    SchemaError::InvalidContextDefault(action.clone(), attr.clone(), err)
+                };
+                let expected: SchemaType =
+                    attr_type.attr_type.clone().try_into().map_err(invalid)?;
+                let expr = ValueParser::new(extensions)
+                    .val_into_restricted_expr(json.clone(), Some(&expected), || {
+                        JsonDeserializationErrorContext::Context
+                    })
+                    .map_err(|err| invalid(err.to_string()))?;
+                typecheck_restricted_expr_against_schematype(
+                    expr.as_borrowed(),
+                    &expected,
+                    extensions,
+                )
+                .map_err(|err| invalid(err.to_string()))?;
+                Ok((attr, json))
+            })
+            .collect()
+    }
+
     fn record_attributes_or_none(ty: Type) -> Option<(Attributes, OpenTag)> {
         match ty {
             Type::EntityOrRecord(EntityRecordKind::Record {
@@ -459,6 +530,12 @@ impl ValidatorSchema {
         self.action_ids.get(action_id)
     }
 
+    /// An iterator over the action ids in the schema, with their
+    /// `ValidatorActionId` objects.
+    pub fn action_ids(&self) -> impl Iterator<Item = (&EntityUID, &ValidatorActionId)> {
+        self.action_ids.iter()
+    }
+
     /// Lookup the ValidatorEntityType object in the schema with the given name.
     pub fn get_entity_type<'a>(&'a self, entity_type_id: &Name) -> Option<&'a ValidatorEntityType> {
         self.entity_types.get(entity_type_id)
@@ -1672,6 +1749,103 @@ mod test {
         }
     }
 
+    #[test]
+    fn annotations() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "annotations": { "owner": "identity", "ui": "hidden" },
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "email": {
+                                    "type": "String",
+                                    "annotations": { "sensitivity": "pii" }
+                                },
+                                "name": { "type": "String" }
+                            }
+                        }
+                    }
+                },
+                "actions": {
+                    "view": { "annotations": { "label": "View" } }
+                }
+            }
+        }))
+        .unwrap();
+        let schema: ValidatorSchema = fragment.try_into().unwrap();
+        let user = schema
+            .get_entity_type(&Name::parse_unqualified_name("User").unwrap())
+            .unwrap();
+        assert_eq!(
+            user.annotations().collect::<Vec<_>>(),
+            vec![
+                (&"owner".into(), &"identity".into()),
+                (&"ui".into(), &"hidden".into())
+            ]
+        );
+        assert_eq!(
+            user.attr_annotations("email").collect::<Vec<_>>(),
+            vec![(&"sensitivity".into(), &"pii".into())]
+        );
+        assert_eq!(user.attr_annotations("name").count(), 0);
+        let view = schema
+            .get_action_id(&EntityUID::with_eid_and_type("Action", "view").unwrap())
+            .unwrap();
+        assert_eq!(
+            view.annotations().collect::<Vec<_>>(),
+            vec![(&"label".into(), &"View".into())]
+        );
+    }
+
+    #[test]
+    fn annotation_key_not_identifier() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "": {
+                "entityTypes": {
+                    "User": { "annotations": { "the owner": "identity" } }
+                },
+                "actions": {}
+            }
+        }))
+        .unwrap();
+        assert_matches!(
+            TryInto::<ValidatorSchema>::try_into(fragment),
+            Err(SchemaError::ParseAnnotationKey(_))
+        );
+    }
+
+    #[test]
+    fn annotations_on_context_attribute() {
+        let fragment: SchemaFragment = serde_json::from_value(json!({
+            "": {
+                "entityTypes": {},
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "ip": {
+                                        "type": "Extension",
+                                        "name": "ipaddr",
+                                        "annotations": { "sensitivity": "pii" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        assert_matches!(
+            TryInto::<ValidatorSchema>::try_into(fragment),
+            Err(SchemaError::UnsupportedAttributeAnnotations(attr)) => assert_eq!(attr, "ip")
+        );
+    }
+
     /// This test checks for regressions on (adapted versions of) the examples
     /// mentioned in the thread at
     /// [cedar#134](https://github.com/cedar-policy/cedar/pull/134)
@@ -6,7 +6,7 @@ use cedar_policy_core::{
 };
 use serde::Serialize;
 use smol_str::SmolStr;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use crate::types::{Attributes, Type};
 
@@ -41,6 +41,21 @@ pub struct ValidatorActionId {
     /// Attributes are serialized as `RestrictedExpr`s, so that roundtripping
     /// works seamlessly.
     pub(crate) attributes: HashMap<SmolStr, PartialValueSerializedAsExpr>,
+
+    /// The values of optional context attributes, in their JSON form, which
+    /// are added to a context parsed with the schema when it doesn't have
+    /// the attribute.
+    #[serde(rename = "contextDefaults")]
+    pub(crate) context_defaults: HashMap<SmolStr, serde_json::Value>,
+
+    /// The message explaining why this action is deprecated, if the schema
+    /// marks it as deprecated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) deprecated: Option<SmolStr>,
+
+    /// The annotations on this action in the schema.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) annotations: BTreeMap<SmolStr, SmolStr>,
 }
 
 impl ValidatorActionId {
@@ -50,6 +65,26 @@ impl ValidatorActionId {
     pub fn context_type(&self) -> Type {
         self.context.clone()
     }
+
+    /// The principal entity types the action applies to.
+    pub fn applicable_principal_types(&self) -> impl Iterator<Item = &EntityType> {
+        self.applies_to.applicable_principal_types()
+    }
+
+    /// The resource entity types the action applies to.
+    pub fn applicable_resource_types(&self) -> impl Iterator<Item = &EntityType> {
+        self.applies_to.applicable_resource_types()
+    }
+
+    /// The deprecation message for this action, if it is deprecated.
+    pub fn deprecated(&self) -> Option<&str> {
+        self.deprecated.as_deref()
+    }
+
+    /// An iterator over the annotations on this action.
+    pub fn annotations(&self) -> impl Iterator<Item = (&SmolStr, &SmolStr)> {
+        self.annotations.iter()
+    }
 }
 
 impl TCNode<EntityUID> for ValidatorActionId {
@@ -0,0 +1,610 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module contains the comparison of two versions of a schema, which
+//! reports every difference between them and whether it can break policies
+//! which validated against the old version, or entities and requests which
+//! conformed to it.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+use cedar_policy_core::ast::EntityType;
+use thiserror::Error;
+
+use super::{ValidatorActionId, ValidatorEntityType, ValidatorSchema};
+use crate::types::{Attributes, EntityRecordKind, Type};
+
+/// The entity type or action context which declares an attribute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttributeOwner {
+    /// An attribute of the entity type with this name
+    EntityType(String),
+    /// An attribute of the context of the action with this uid
+    Context(String),
+}
+
+impl Display for AttributeOwner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EntityType(name) => write!(f, "entity type `{name}`"),
+            Self::Context(action) => write!(f, "the context of action `{action}`"),
+        }
+    }
+}
+
+/// A difference between an old and a new version of a schema. Use
+/// [`SchemaChange::is_breaking`] to tell whether it is backward compatible.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SchemaChange {
+    /// An entity type was declared
+    #[error("entity type `{0}` was added")]
+    EntityTypeAdded(String),
+    /// An entity type is no longer declared
+    #[error("entity type `{0}` was removed")]
+    EntityTypeRemoved(String),
+    /// An entity type or context gained an attribute
+    #[error("{owner} gained the {} attribute `{attr}`", if *required { "required" } else { "optional" })]
+    AttributeAdded {
+        /// Entity type or context with the attribute
+        owner: AttributeOwner,
+        /// The attribute
+        attr: String,
+        /// Whether the attribute is required
+        required: bool,
+    },
+    /// An entity type or context lost an attribute
+    #[error("attribute `{attr}` of {owner} was removed")]
+    AttributeRemoved {
+        /// Entity type or context which had the attribute
+        owner: AttributeOwner,
+        /// The attribute
+        attr: String,
+    },
+    /// The type of an attribute changed
+    #[error("the type of attribute `{attr}` of {owner} changed from {old} to {new}")]
+    AttributeTypeChanged {
+        /// Entity type or context with the attribute
+        owner: AttributeOwner,
+        /// The attribute
+        attr: String,
+        /// The old type of the attribute, in the JSON schema format
+        old: String,
+        /// The new type of the attribute, in the JSON schema format
+        new: String,
+    },
+    /// A required attribute became optional
+    #[error("attribute `{attr}` of {owner} became optional")]
+    AttributeMadeOptional {
+        /// Entity type or context with the attribute
+        owner: AttributeOwner,
+        /// The attribute
+        attr: String,
+    },
+    /// An optional attribute became required
+    #[error("attribute `{attr}` of {owner} became required")]
+    AttributeMadeRequired {
+        /// Entity type or context with the attribute
+        owner: AttributeOwner,
+        /// The attribute
+        attr: String,
+    },
+    /// Entities of an entity type can have ancestors of another entity type,
+    /// directly or transitively, which they couldn't before
+    #[error("entities of type `{entity_type}` can now have ancestors of type `{ancestor}`")]
+    AncestorTypeAdded {
+        /// The entity type
+        entity_type: String,
+        /// The new type of ancestor
+        ancestor: String,
+    },
+    /// Entities of an entity type can no longer have ancestors of another
+    /// entity type
+    #[error("entities of type `{entity_type}` can no longer have ancestors of type `{ancestor}`")]
+    AncestorTypeRemoved {
+        /// The entity type
+        entity_type: String,
+        /// The type of ancestor which is no longer allowed
+        ancestor: String,
+    },
+    /// An action was declared
+    #[error("action `{0}` was added")]
+    ActionAdded(String),
+    /// An action is no longer declared
+    #[error("action `{0}` was removed")]
+    ActionRemoved(String),
+    /// An action applies to a principal type it didn't apply to before
+    #[error("action `{action}` now applies to principals of type `{ty}`")]
+    PrincipalTypeAdded {
+        /// The action
+        action: String,
+        /// The principal type
+        ty: String,
+    },
+    /// An action no longer applies to a principal type
+    #[error("action `{action}` no longer applies to principals of type `{ty}`")]
+    PrincipalTypeRemoved {
+        /// The action
+        action: String,
+        /// The principal type
+        ty: String,
+    },
+    /// An action applies to a resource type it didn't apply to before
+    #[error("action `{action}` now applies to resources of type `{ty}`")]
+    ResourceTypeAdded {
+        /// The action
+        action: String,
+        /// The resource type
+        ty: String,
+    },
+    /// An action no longer applies to a resource type
+    #[error("action `{action}` no longer applies to resources of type `{ty}`")]
+    ResourceTypeRemoved {
+        /// The action
+        action: String,
+        /// The resource type
+        ty: String,
+    },
+    /// An action became a member of an action group, directly or transitively
+    #[error("action `{action}` is now in action group `{group}`")]
+    ActionGroupAdded {
+        /// The action
+        action: String,
+        /// The action group
+        group: String,
+    },
+    /// An action is no longer a member of an action group
+    #[error("action `{action}` is no longer in action group `{group}`")]
+    ActionGroupRemoved {
+        /// The action
+        action: String,
+        /// The action group
+        group: String,
+    },
+}
+
+impl SchemaChange {
+    /// Whether this change may break policies which validated against the
+    /// old schema, or entities and requests which conformed to it. Adding
+    /// declarations, optional attributes, and allowed types is backward
+    /// compatible. Removing or narrowing anything is breaking, as is any
+    /// change to the type of an attribute or to whether it is required.
+    pub fn is_breaking(&self) -> bool {
+        match self {
+            Self::EntityTypeAdded(_)
+            | Self::ActionAdded(_)
+            | Self::AncestorTypeAdded { .. }
+            | Self::PrincipalTypeAdded { .. }
+            | Self::ResourceTypeAdded { .. }
+            | Self::ActionGroupAdded { .. } => false,
+            // existing entities and requests don't have a new required
+            // attribute
+            Self::AttributeAdded { required, .. } => *required,
+            Self::EntityTypeRemoved(_)
+            | Self::AttributeRemoved { .. }
+            | Self::AttributeTypeChanged { .. }
+            // policies may access the attribute without checking `has`
+            | Self::AttributeMadeOptional { .. }
+            | Self::AttributeMadeRequired { .. }
+            | Self::AncestorTypeRemoved { .. }
+            | Self::ActionRemoved(_)
+            | Self::PrincipalTypeRemoved { .. }
+            | Self::ResourceTypeRemoved { .. }
+            | Self::ActionGroupRemoved { .. } => true,
+        }
+    }
+}
+
+/// Every difference between an old and a new version of a schema, as
+/// computed by [`check_compatibility`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompatibilityReport {
+    changes: Vec<SchemaChange>,
+}
+
+impl CompatibilityReport {
+    /// Every change, both breaking and backward compatible, ordered by the
+    /// entity type or action they are about
+    pub fn changes(&self) -> impl Iterator<Item = &SchemaChange> {
+        self.changes.iter()
+    }
+
+    /// The changes which may break existing policies, entities, or requests
+    pub fn breaking_changes(&self) -> impl Iterator<Item = &SchemaChange> {
+        self.changes.iter().filter(|change| change.is_breaking())
+    }
+
+    /// Whether the new schema is backward compatible with the old one, i.e.,
+    /// none of the changes are breaking
+    pub fn is_backward_compatible(&self) -> bool {
+        self.breaking_changes().next().is_none()
+    }
+}
+
+/// Compare the `old` and `new` versions of a schema, classifying each
+/// difference as backward compatible or breaking. The schemas are compared
+/// after common types are resolved, so replacing a type with an identical
+/// common type is not a change.
+pub fn check_compatibility(old: &ValidatorSchema, new: &ValidatorSchema) -> CompatibilityReport {
+    let mut changes = vec![];
+    let old_types = entity_types(old);
+    let new_types = entity_types(new);
+    let names: BTreeSet<&String> = old_types.keys().chain(new_types.keys()).collect();
+    for name in names {
+        match (old_types.get(name), new_types.get(name)) {
+            (Some(_), None) => changes.push(SchemaChange::EntityTypeRemoved(name.clone())),
+            (None, Some(_)) => changes.push(SchemaChange::EntityTypeAdded(name.clone())),
+            (Some(old_ty), Some(new_ty)) => {
+                attribute_changes(
+                    &mut changes,
+                    &AttributeOwner::EntityType(name.clone()),
+                    &old_ty.attributes,
+                    &new_ty.attributes,
+                );
+                let old_ancestors = ancestor_types(&old_types, old_ty);
+                let new_ancestors = ancestor_types(&new_types, new_ty);
+                for ancestor in old_ancestors.difference(&new_ancestors) {
+                    changes.push(SchemaChange::AncestorTypeRemoved {
+                        entity_type: name.clone(),
+                        ancestor: ancestor.clone(),
+                    });
+                }
+                for ancestor in new_ancestors.difference(&old_ancestors) {
+                    changes.push(SchemaChange::AncestorTypeAdded {
+                        entity_type: name.clone(),
+                        ancestor: ancestor.clone(),
+                    });
+                }
+            }
+            (None, None) => (),
+        }
+    }
+
+    let old_actions = actions(old);
+    let new_actions = actions(new);
+    let uids: BTreeSet<&String> = old_actions.keys().chain(new_actions.keys()).collect();
+    for uid in uids {
+        match (old_actions.get(uid), new_actions.get(uid)) {
+            (Some(_), None) => changes.push(SchemaChange::ActionRemoved(uid.clone())),
+            (None, Some(_)) => changes.push(SchemaChange::ActionAdded(uid.clone())),
+            (Some(old_action), Some(new_action)) => {
+                action_changes(&mut changes, uid, old_action, new_action);
+                let old_groups = action_groups(&old_actions, old_action);
+                let new_groups = action_groups(&new_actions, new_action);
+                for group in old_groups.difference(&new_groups) {
+                    changes.push(SchemaChange::ActionGroupRemoved {
+                        action: uid.clone(),
+                        group: group.clone(),
+                    });
+                }
+                for group in new_groups.difference(&old_groups) {
+                    changes.push(SchemaChange::ActionGroupAdded {
+                        action: uid.clone(),
+                        group: group.clone(),
+                    });
+                }
+            }
+            (None, None) => (),
+        }
+    }
+    CompatibilityReport { changes }
+}
+
+/// The entity types declared in `schema`, by name
+fn entity_types(schema: &ValidatorSchema) -> BTreeMap<String, &ValidatorEntityType> {
+    schema
+        .entity_types()
+        .map(|(name, ty)| (name.to_string(), ty))
+        .collect()
+}
+
+/// The actions declared in `schema`, by uid
+fn actions(schema: &ValidatorSchema) -> BTreeMap<String, &ValidatorActionId> {
+    schema
+        .action_ids()
+        .map(|(uid, action)| (uid.to_string(), action))
+        .collect()
+}
+
+/// The names of the entity types in `types` which can be ancestors of
+/// entities of type `ty`
+fn ancestor_types(
+    types: &BTreeMap<String, &ValidatorEntityType>,
+    ty: &ValidatorEntityType,
+) -> BTreeSet<String> {
+    types
+        .iter()
+        .filter(|(_, ancestor)| ancestor.descendants.contains(&ty.name))
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// The uids of the actions in `actions` which are action groups containing
+/// `action`
+fn action_groups(
+    actions: &BTreeMap<String, &ValidatorActionId>,
+    action: &ValidatorActionId,
+) -> BTreeSet<String> {
+    actions
+        .iter()
+        .filter(|(_, group)| group.descendants.contains(&action.name))
+        .map(|(uid, _)| uid.clone())
+        .collect()
+}
+
+/// Changes to the principal types, resource types, and context of an action
+fn action_changes(
+    changes: &mut Vec<SchemaChange>,
+    uid: &str,
+    old: &ValidatorActionId,
+    new: &ValidatorActionId,
+) {
+    let old_principals = type_names(old.applicable_principal_types());
+    let new_principals = type_names(new.applicable_principal_types());
+    for ty in old_principals.difference(&new_principals) {
+        changes.push(SchemaChange::PrincipalTypeRemoved {
+            action: uid.to_owned(),
+            ty: ty.clone(),
+        });
+    }
+    for ty in new_principals.difference(&old_principals) {
+        changes.push(SchemaChange::PrincipalTypeAdded {
+            action: uid.to_owned(),
+            ty: ty.clone(),
+        });
+    }
+    let old_resources = type_names(old.applicable_resource_types());
+    let new_resources = type_names(new.applicable_resource_types());
+    for ty in old_resources.difference(&new_resources) {
+        changes.push(SchemaChange::ResourceTypeRemoved {
+            action: uid.to_owned(),
+            ty: ty.clone(),
+        });
+    }
+    for ty in new_resources.difference(&old_resources) {
+        changes.push(SchemaChange::ResourceTypeAdded {
+            action: uid.to_owned(),
+            ty: ty.clone(),
+        });
+    }
+
+    let no_attributes = Attributes::with_attributes([]);
+    attribute_changes(
+        changes,
+        &AttributeOwner::Context(uid.to_owned()),
+        context_attributes(&old.context).unwrap_or(&no_attributes),
+        context_attributes(&new.context).unwrap_or(&no_attributes),
+    );
+}
+
+fn type_names<'a>(types: impl Iterator<Item = &'a EntityType>) -> BTreeSet<String> {
+    types.map(ToString::to_string).collect()
+}
+
+/// The attributes of a context type, which is always a record
+fn context_attributes(context: &Type) -> Option<&Attributes> {
+    match context {
+        Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) => Some(attrs),
+        _ => None,
+    }
+}
+
+/// Changes to the attributes of an entity type or context
+fn attribute_changes(
+    changes: &mut Vec<SchemaChange>,
+    owner: &AttributeOwner,
+    old: &Attributes,
+    new: &Attributes,
+) {
+    let attrs: BTreeSet<_> = old.keys().chain(new.keys()).collect();
+    for attr in attrs {
+        match (old.get_attr(attr), new.get_attr(attr)) {
+            (Some(_), None) => changes.push(SchemaChange::AttributeRemoved {
+                owner: owner.clone(),
+                attr: attr.to_string(),
+            }),
+            (None, Some(new_attr)) => changes.push(SchemaChange::AttributeAdded {
+                owner: owner.clone(),
+                attr: attr.to_string(),
+                required: new_attr.is_required,
+            }),
+            (Some(old_attr), Some(new_attr)) => {
+                if old_attr.attr_type != new_attr.attr_type {
+                    changes.push(SchemaChange::AttributeTypeChanged {
+                        owner: owner.clone(),
+                        attr: attr.to_string(),
+                        old: old_attr.attr_type.to_string(),
+                        new: new_attr.attr_type.to_string(),
+                    });
+                }
+                match (old_attr.is_required, new_attr.is_required) {
+                    (true, false) => changes.push(SchemaChange::AttributeMadeOptional {
+                        owner: owner.clone(),
+                        attr: attr.to_string(),
+                    }),
+                    (false, true) => changes.push(SchemaChange::AttributeMadeRequired {
+                        owner: owner.clone(),
+                        attr: attr.to_string(),
+                    }),
+                    _ => (),
+                }
+            }
+            (None, None) => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cedar_policy_core::extensions::Extensions;
+    use serde_json::json;
+
+    fn schema(json: serde_json::Value) -> ValidatorSchema {
+        ValidatorSchema::from_json_value(json, Extensions::all_available())
+            .expect("schema should be valid")
+    }
+
+    fn base() -> serde_json::Value {
+        json!({
+            "NS": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Group"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "age": { "type": "Long", "required": false }
+                            }
+                        }
+                    },
+                    "Group": {},
+                    "Photo": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User"],
+                            "resourceTypes": ["Photo"],
+                            "context": {
+                                "type": "Record",
+                                "attributes": { "mfa": { "type": "Boolean" } }
+                            }
+                        },
+                        "memberOf": [{ "id": "read" }]
+                    },
+                    "read": {}
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn identical_schemas() {
+        let report = check_compatibility(&schema(base()), &schema(base()));
+        assert_eq!(report, CompatibilityReport::default());
+        assert!(report.is_backward_compatible());
+    }
+
+    #[test]
+    fn additions_are_compatible() {
+        let mut new = base();
+        new["NS"]["entityTypes"]["Album"] = json!({});
+        new["NS"]["entityTypes"]["Photo"] = json!({ "memberOfTypes": ["Album"] });
+        new["NS"]["entityTypes"]["User"]["shape"]["attributes"]["email"] =
+            json!({ "type": "String", "required": false });
+        new["NS"]["actions"]["view"]["appliesTo"]["resourceTypes"] = json!(["Photo", "Album"]);
+        new["NS"]["actions"]["edit"] = json!({});
+        let report = check_compatibility(&schema(base()), &schema(new));
+        assert_eq!(
+            report.changes().cloned().collect::<Vec<_>>(),
+            vec![
+                SchemaChange::EntityTypeAdded("NS::Album".to_string()),
+                SchemaChange::AncestorTypeAdded {
+                    entity_type: "NS::Photo".to_string(),
+                    ancestor: "NS::Album".to_string(),
+                },
+                SchemaChange::AttributeAdded {
+                    owner: AttributeOwner::EntityType("NS::User".to_string()),
+                    attr: "email".to_string(),
+                    required: false,
+                },
+                SchemaChange::ActionAdded(r#"NS::Action::"edit""#.to_string()),
+                SchemaChange::ResourceTypeAdded {
+                    action: r#"NS::Action::"view""#.to_string(),
+                    ty: "NS::Album".to_string(),
+                },
+            ]
+        );
+        assert!(report.is_backward_compatible());
+    }
+
+    #[test]
+    fn removals_and_narrowing_are_breaking() {
+        let mut new = base();
+        new["NS"]["entityTypes"]["User"] = json!({
+            "shape": {
+                "type": "Record",
+                "attributes": {
+                    "name": { "type": "Long" },
+                    "age": { "type": "Long" }
+                }
+            }
+        });
+        new["NS"]["actions"]["view"] = json!({
+            "appliesTo": {
+                "principalTypes": ["User"],
+                "resourceTypes": ["Group"],
+                "context": {
+                    "type": "Record",
+                    "attributes": { "ip": { "type": "Extension", "name": "ipaddr" } }
+                }
+            }
+        });
+        new["NS"]["actions"].as_object_mut().unwrap().remove("read");
+        let report = check_compatibility(&schema(base()), &schema(new));
+        let user = AttributeOwner::EntityType("NS::User".to_string());
+        let context = AttributeOwner::Context(r#"NS::Action::"view""#.to_string());
+        assert_eq!(
+            report.changes().cloned().collect::<Vec<_>>(),
+            vec![
+                SchemaChange::AttributeMadeRequired {
+                    owner: user.clone(),
+                    attr: "age".to_string(),
+                },
+                SchemaChange::AttributeTypeChanged {
+                    owner: user,
+                    attr: "name".to_string(),
+                    old: r#"{"type":"String"}"#.to_string(),
+                    new: r#"{"type":"Long"}"#.to_string(),
+                },
+                SchemaChange::AncestorTypeRemoved {
+                    entity_type: "NS::User".to_string(),
+                    ancestor: "NS::Group".to_string(),
+                },
+                SchemaChange::ActionRemoved(r#"NS::Action::"read""#.to_string()),
+                SchemaChange::ResourceTypeRemoved {
+                    action: r#"NS::Action::"view""#.to_string(),
+                    ty: "NS::Photo".to_string(),
+                },
+                SchemaChange::ResourceTypeAdded {
+                    action: r#"NS::Action::"view""#.to_string(),
+                    ty: "NS::Group".to_string(),
+                },
+                SchemaChange::AttributeAdded {
+                    owner: context.clone(),
+                    attr: "ip".to_string(),
+                    required: true,
+                },
+                SchemaChange::AttributeRemoved {
+                    owner: context,
+                    attr: "mfa".to_string(),
+                },
+                SchemaChange::ActionGroupRemoved {
+                    action: r#"NS::Action::"view""#.to_string(),
+                    group: r#"NS::Action::"read""#.to_string(),
+                },
+            ]
+        );
+        assert!(!report.is_backward_compatible());
+        assert_eq!(report.breaking_changes().count(), 8);
+        assert_eq!(
+            report.changes().nth(1).unwrap().to_string(),
+            r#"the type of attribute `name` of entity type `NS::User` changed from {"type":"String"} to {"type":"Long"}"#
+        );
+    }
+}
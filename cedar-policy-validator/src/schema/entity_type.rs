@@ -2,7 +2,7 @@
 
 use serde::Serialize;
 use smol_str::SmolStr;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use cedar_policy_core::{
     ast::{EntityType, Name},
@@ -35,6 +35,25 @@ pub struct ValidatorEntityType {
     /// their type when they are present. Attempting to access an undeclared
     /// attribute under standard validation is an error regardless of this flag.
     pub(crate) open_attributes: OpenTag,
+
+    /// The message explaining why this entity type is deprecated, if the
+    /// schema marks it as deprecated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) deprecated: Option<SmolStr>,
+
+    /// The messages explaining why attributes of this entity type are
+    /// deprecated. Keys are the identifiers of the deprecated attributes.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) deprecated_attributes: HashMap<SmolStr, SmolStr>,
+
+    /// The annotations on this entity type in the schema.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) annotations: BTreeMap<SmolStr, SmolStr>,
+
+    /// The annotations on attributes of this entity type in the schema. Keys
+    /// are the identifiers of the attributes which have annotations.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) attribute_annotations: HashMap<SmolStr, BTreeMap<SmolStr, SmolStr>>,
 }
 
 impl ValidatorEntityType {
@@ -48,6 +67,28 @@ impl ValidatorEntityType {
         self.attributes.iter()
     }
 
+    /// Get the deprecation message for this entity type, if it is deprecated
+    pub fn deprecated(&self) -> Option<&str> {
+        self.deprecated.as_deref()
+    }
+
+    /// Get the deprecation message for the attribute with the given name, if
+    /// it is deprecated
+    pub fn attr_deprecated(&self, attr: &str) -> Option<&str> {
+        self.deprecated_attributes.get(attr).map(SmolStr::as_str)
+    }
+
+    /// An iterator over the annotations on this entity type
+    pub fn annotations(&self) -> impl Iterator<Item = (&SmolStr, &SmolStr)> {
+        self.annotations.iter()
+    }
+
+    /// An iterator over the annotations on the attribute with the given name.
+    /// This is empty if the attribute doesn't exist or has no annotations.
+    pub fn attr_annotations(&self, attr: &str) -> impl Iterator<Item = (&SmolStr, &SmolStr)> {
+        self.attribute_annotations.get(attr).into_iter().flatten()
+    }
+
     /// Return `true` if this entity type has an `EntityType` declared as a
     /// possible descendant in the schema. This takes an `EntityType` rather
     /// than a `Name`, It's not possible to declare the unspecified entity type
@@ -1,7 +1,7 @@
 //! This module contains the definition of `ValidatorNamespaceDef` and of types
 //! it relies on
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use cedar_policy_core::{
     ast::{
@@ -97,6 +97,15 @@ pub struct EntityTypeFragment {
     /// namespace, so we will check if they are declared in any fragment when
     /// constructing a `ValidatorSchema`.
     pub(super) parents: HashSet<Name>,
+    /// The deprecation message for this entity type, if it is deprecated.
+    pub(super) deprecated: Option<SmolStr>,
+    /// The deprecation messages for the attributes of this entity type which
+    /// are deprecated.
+    pub(super) deprecated_attributes: HashMap<SmolStr, SmolStr>,
+    /// The annotations on this entity type.
+    pub(super) annotations: BTreeMap<SmolStr, SmolStr>,
+    /// The annotations on the attributes of this entity type which have any.
+    pub(super) attribute_annotations: HashMap<SmolStr, BTreeMap<SmolStr, SmolStr>>,
 }
 
 /// Action declarations held in a `ValidatorNamespaceDef`. Entity types
@@ -122,6 +131,13 @@ pub struct ActionFragment {
     /// separately so that we can later extract use these values to construct
     /// the actual `Entity` objects defined by the schema.
     pub(super) attributes: HashMap<SmolStr, PartialValueSerializedAsExpr>,
+    /// The values of optional context attributes which are absent from a
+    /// context, which are checked against the context type once it's resolved.
+    pub(super) context_defaults: HashMap<SmolStr, CedarValueJson>,
+    /// The deprecation message for this action, if it is deprecated.
+    pub(super) deprecated: Option<SmolStr>,
+    /// The annotations on this action.
+    pub(super) annotations: BTreeMap<SmolStr, SmolStr>,
 }
 
 type ResolveFunc<T> = dyn FnOnce(&HashMap<Name, Type>) -> Result<T>;
@@ -297,16 +313,25 @@ impl ValidatorNamespaceDef {
                         })
                         .collect::<Result<HashSet<_>>>()?;
 
-                    let attributes = Self::try_schema_type_into_validator_type(
-                        schema_namespace,
-                        entity_type.shape.into_inner(),
-                    )?;
+                    let mut shape = entity_type.shape.into_inner();
+                    let deprecated_attributes = Self::take_attribute_deprecations(&mut shape);
+                    let attribute_annotations = Self::take_attribute_annotations(&mut shape);
+                    Self::check_annotation_keys(&entity_type.annotations)?;
+                    for annotations in attribute_annotations.values() {
+                        Self::check_annotation_keys(annotations)?;
+                    }
+                    let attributes =
+                        Self::try_schema_type_into_validator_type(schema_namespace, shape)?;
 
                     Ok((
                         name,
                         EntityTypeFragment {
                             attributes,
                             parents,
+                            deprecated: entity_type.deprecated,
+                            deprecated_attributes,
+                            annotations: entity_type.annotations,
+                            attribute_annotations,
                         },
                     ))
                 })
@@ -314,6 +339,47 @@ impl ValidatorNamespaceDef {
         })
     }
 
+    /// Remove the deprecation messages from the attributes declared directly
+    /// in the shape of an entity type, returning a map from attribute name to
+    /// message. Deprecations anywhere else are rejected when the shape is
+    /// converted into a `Type`.
+    fn take_attribute_deprecations(shape: &mut SchemaType) -> HashMap<SmolStr, SmolStr> {
+        match shape {
+            SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => attributes
+                .iter_mut()
+                .filter_map(|(attr, ty)| Some((attr.clone(), ty.deprecated.take()?)))
+                .collect(),
+            _ => HashMap::new(),
+        }
+    }
+
+    /// Remove the annotations from the attributes declared directly in the
+    /// shape of an entity type, returning a map from attribute name to its
+    /// annotations for the attributes which have any. Like deprecations,
+    /// annotations anywhere else are rejected when the shape is converted into
+    /// a `Type`.
+    fn take_attribute_annotations(
+        shape: &mut SchemaType,
+    ) -> HashMap<SmolStr, BTreeMap<SmolStr, SmolStr>> {
+        match shape {
+            SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => attributes
+                .iter_mut()
+                .filter(|(_, ty)| !ty.annotations.is_empty())
+                .map(|(attr, ty)| (attr.clone(), std::mem::take(&mut ty.annotations)))
+                .collect(),
+            _ => HashMap::new(),
+        }
+    }
+
+    /// Check that the keys of some annotations are identifiers, as they are
+    /// for the annotations on policies.
+    fn check_annotation_keys(annotations: &BTreeMap<SmolStr, SmolStr>) -> Result<()> {
+        for key in annotations.keys() {
+            Id::from_normalized_str(key).map_err(SchemaError::ParseAnnotationKey)?;
+        }
+        Ok(())
+    }
+
     // Helper to get types from `CedarValueJson`s. Currently doesn't support all
     // `CedarValueJson` types. Note: If this function is extended to cover move
     // `CedarValueJson`s, we must update `convert_attr_jsonval_map_to_attributes` to
@@ -476,6 +542,8 @@ impl ValidatorNamespaceDef {
                             extensions,
                         )?;
 
+                    Self::check_annotation_keys(&action_type.annotations)?;
+
                     Ok((
                         action_id,
                         ActionFragment {
@@ -484,6 +552,9 @@ impl ValidatorNamespaceDef {
                             parents,
                             attribute_types,
                             attributes,
+                            context_defaults: action_type.context_defaults.unwrap_or_default(),
+                            deprecated: action_type.deprecated,
+                            annotations: action_type.annotations,
                         },
                     ))
                 })
@@ -538,6 +609,12 @@ impl ValidatorNamespaceDef {
         let attrs_with_type_defs = attrs
             .into_iter()
             .map(|(attr, ty)| -> Result<_> {
+                if ty.deprecated.is_some() {
+                    return Err(SchemaError::UnsupportedAttributeDeprecation(attr));
+                }
+                if !ty.annotations.is_empty() {
+                    return Err(SchemaError::UnsupportedAttributeAnnotations(attr));
+                }
                 Ok((
                     attr,
                     (
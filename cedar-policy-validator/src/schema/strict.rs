@@ -0,0 +1,367 @@
+//! This module contains the strict-mode checks applied to a JSON schema before
+//! it is parsed. The ordinary parser rejects the first unknown key it finds (or,
+//! for some constructs, quietly accepts odd-but-legal input such as an action
+//! that applies to no principals). Strict mode instead walks the whole document
+//! and reports every finding at once.
+
+use miette::Diagnostic;
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::fuzzy_match::fuzzy_search;
+
+/// A single problem found while checking a JSON schema in strict mode.
+/// Locations are JSON-pointer style paths into the schema document, e.g.,
+/// `/NS/entityTypes/User/shape`.
+#[derive(Debug, Clone, PartialEq, Eq, Diagnostic, Error)]
+pub enum StrictSchemaFinding {
+    /// An object in the schema contained a key which is not meaningful in that
+    /// position. This is usually a misspelled field name.
+    #[error("unknown key `{key}` at `{location}`")]
+    UnknownKey {
+        /// Path to the object containing the key
+        location: String,
+        /// The unknown key
+        key: String,
+        /// Suggestion for the key that was probably intended
+        #[help]
+        suggestion: Option<String>,
+    },
+    /// The `principalTypes` or `resourceTypes` list for an action is
+    /// explicitly empty, so the action can never apply to any request.
+    #[error("`{field}` is empty at `{location}`, so this action cannot apply to any request")]
+    #[diagnostic(help(
+        "omit `{field}` to leave it unspecified, or list the entity types this action applies to"
+    ))]
+    EmptyAppliesTo {
+        /// Path to the `appliesTo` object
+        location: String,
+        /// Either `principalTypes` or `resourceTypes`
+        field: &'static str,
+    },
+    /// A namespace declares no common types, entity types, or actions.
+    #[error("namespace `{0}` does not declare any common types, entity types, or actions")]
+    EmptyNamespace(String),
+    /// An entity type, action, common type, or attribute is declared with the
+    /// empty string as its name.
+    #[error("empty name declared at `{location}`")]
+    EmptyName {
+        /// Path to the declaration
+        location: String,
+    },
+}
+
+/// Check a JSON schema (which should be an object matching the
+/// `SchemaFileFormat` shape) in strict mode, returning every finding. Parts
+/// of the document which do not have the expected JSON shape (e.g., a string
+/// where an object is expected) are skipped here because the ordinary parser
+/// already reports them precisely.
+pub fn strict_schema_findings(json: &Value) -> Vec<StrictSchemaFinding> {
+    let mut checker = StrictChecker::default();
+    if let Some(namespaces) = json.as_object() {
+        for (namespace, ns_def) in namespaces {
+            checker.check_namespace(namespace, ns_def);
+        }
+    }
+    checker.findings
+}
+
+#[derive(Debug, Default)]
+struct StrictChecker {
+    findings: Vec<StrictSchemaFinding>,
+}
+
+impl StrictChecker {
+    fn check_keys(&mut self, location: &str, obj: &Map<String, Value>, allowed: &[&str]) {
+        for key in obj.keys() {
+            if !allowed.contains(&key.as_str()) {
+                self.findings.push(StrictSchemaFinding::UnknownKey {
+                    location: location.to_string(),
+                    key: key.clone(),
+                    suggestion: fuzzy_search(key, allowed).map(|s| format!("did you mean `{s}`?")),
+                });
+            }
+        }
+    }
+
+    fn check_name(&mut self, location: &str, name: &str) {
+        if name.is_empty() {
+            self.findings.push(StrictSchemaFinding::EmptyName {
+                location: location.to_string(),
+            });
+        }
+    }
+
+    fn check_namespace(&mut self, namespace: &str, ns_def: &Value) {
+        let Some(obj) = ns_def.as_object() else {
+            return;
+        };
+        let location = format!("/{namespace}");
+        self.check_keys(&location, obj, &["commonTypes", "entityTypes", "actions"]);
+
+        let mut declares_anything = false;
+        if let Some(common_types) = obj.get("commonTypes").and_then(Value::as_object) {
+            for (name, ty) in common_types {
+                declares_anything = true;
+                let ty_location = format!("{location}/commonTypes/{name}");
+                self.check_name(&ty_location, name);
+                self.check_type(&ty_location, ty, false);
+            }
+        }
+        if let Some(entity_types) = obj.get("entityTypes").and_then(Value::as_object) {
+            for (name, entity_type) in entity_types {
+                declares_anything = true;
+                self.check_entity_type(
+                    &format!("{location}/entityTypes/{name}"),
+                    name,
+                    entity_type,
+                );
+            }
+        }
+        if let Some(actions) = obj.get("actions").and_then(Value::as_object) {
+            for (name, action) in actions {
+                declares_anything = true;
+                self.check_action(&format!("{location}/actions/{name}"), name, action);
+            }
+        }
+        if !declares_anything {
+            self.findings
+                .push(StrictSchemaFinding::EmptyNamespace(namespace.to_string()));
+        }
+    }
+
+    fn check_entity_type(&mut self, location: &str, name: &str, entity_type: &Value) {
+        self.check_name(location, name);
+        let Some(obj) = entity_type.as_object() else {
+            return;
+        };
+        self.check_keys(
+            location,
+            obj,
+            &["memberOfTypes", "shape", "deprecated", "annotations"],
+        );
+        if let Some(shape) = obj.get("shape") {
+            self.check_type(&format!("{location}/shape"), shape, false);
+        }
+    }
+
+    fn check_action(&mut self, location: &str, name: &str, action: &Value) {
+        self.check_name(location, name);
+        let Some(obj) = action.as_object() else {
+            return;
+        };
+        self.check_keys(
+            location,
+            obj,
+            &[
+                "attributes",
+                "contextDefaults",
+                "appliesTo",
+                "memberOf",
+                "deprecated",
+                "annotations",
+            ],
+        );
+
+        if let Some(applies_to) = obj.get("appliesTo").and_then(Value::as_object) {
+            let applies_to_location = format!("{location}/appliesTo");
+            self.check_keys(
+                &applies_to_location,
+                applies_to,
+                &["resourceTypes", "principalTypes", "context"],
+            );
+            for field in ["principalTypes", "resourceTypes"] {
+                if applies_to
+                    .get(field)
+                    .and_then(Value::as_array)
+                    .is_some_and(Vec::is_empty)
+                {
+                    self.findings.push(StrictSchemaFinding::EmptyAppliesTo {
+                        location: applies_to_location.clone(),
+                        field,
+                    });
+                }
+            }
+            if let Some(context) = applies_to.get("context") {
+                self.check_type(&format!("{applies_to_location}/context"), context, false);
+            }
+        }
+
+        if let Some(member_of) = obj.get("memberOf").and_then(Value::as_array) {
+            for (i, parent) in member_of.iter().enumerate() {
+                if let Some(parent) = parent.as_object() {
+                    self.check_keys(&format!("{location}/memberOf/{i}"), parent, &["id", "type"]);
+                }
+            }
+        }
+    }
+
+    /// Check a type declaration. Attribute types additionally permit the
+    /// `required`, `deprecated`, and `annotations` keys.
+    fn check_type(&mut self, location: &str, ty: &Value, is_attribute: bool) {
+        let Some(obj) = ty.as_object() else {
+            return;
+        };
+        let mut allowed = match obj.get("type").and_then(Value::as_str) {
+            Some("Set") => vec!["type", "element"],
+            Some("Record") => vec!["type", "attributes", "additionalAttributes"],
+            Some("Entity" | "Extension") => vec!["type", "name"],
+            Some(_) => vec!["type"],
+            // Without a `type` we can't know which keys are expected, so we
+            // only flag keys that are not valid for any type.
+            None => vec![
+                "type",
+                "element",
+                "attributes",
+                "additionalAttributes",
+                "name",
+            ],
+        };
+        if is_attribute {
+            allowed.extend(["required", "deprecated", "annotations"]);
+        }
+        self.check_keys(location, obj, &allowed);
+
+        if let Some(element) = obj.get("element") {
+            self.check_type(&format!("{location}/element"), element, false);
+        }
+        if let Some(attributes) = obj.get("attributes").and_then(Value::as_object) {
+            for (name, attr_ty) in attributes {
+                let attr_location = format!("{location}/attributes/{name}");
+                self.check_name(&attr_location, name);
+                self.check_type(&attr_location, attr_ty, true);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn well_formed_schema_has_no_findings() {
+        let src = json!({
+            "NS": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Group"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String" },
+                                "tags": { "type": "Set", "element": { "type": "String" }, "required": false }
+                            }
+                        }
+                    },
+                    "Group": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User"],
+                            "resourceTypes": ["Group"],
+                            "context": { "type": "Record", "attributes": {} }
+                        },
+                        "memberOf": [{ "id": "read" }]
+                    },
+                    "read": {}
+                }
+            }
+        });
+        assert_eq!(strict_schema_findings(&src), vec![]);
+    }
+
+    #[test]
+    fn misspelled_keys_are_all_reported() {
+        let src = json!({
+            "": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypess": ["Group"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "name": { "type": "String", "requird": false }
+                            }
+                        }
+                    },
+                    "Group": {}
+                },
+                "actions": {
+                    "view": { "appliesto": {} }
+                }
+            }
+        });
+        assert_eq!(
+            strict_schema_findings(&src),
+            vec![
+                StrictSchemaFinding::UnknownKey {
+                    location: "//entityTypes/User".to_string(),
+                    key: "memberOfTypess".to_string(),
+                    suggestion: Some("did you mean `memberOfTypes`?".to_string()),
+                },
+                StrictSchemaFinding::UnknownKey {
+                    location: "//entityTypes/User/shape/attributes/name".to_string(),
+                    key: "requird".to_string(),
+                    suggestion: Some("did you mean `required`?".to_string()),
+                },
+                StrictSchemaFinding::UnknownKey {
+                    location: "//actions/view".to_string(),
+                    key: "appliesto".to_string(),
+                    suggestion: Some("did you mean `appliesTo`?".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn key_for_wrong_type_variant() {
+        let src = json!({
+            "": {
+                "commonTypes": {
+                    "T": { "type": "String", "element": { "type": "Long" } }
+                }
+            }
+        });
+        assert_eq!(
+            strict_schema_findings(&src),
+            vec![StrictSchemaFinding::UnknownKey {
+                location: "//commonTypes/T".to_string(),
+                key: "element".to_string(),
+                suggestion: Some("did you mean `type`?".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn empty_constructs() {
+        let src = json!({
+            "A": { "entityTypes": {}, "actions": {} },
+            "B": {
+                "entityTypes": { "": {} },
+                "actions": {
+                    "view": { "appliesTo": { "principalTypes": [], "resourceTypes": [] } }
+                }
+            }
+        });
+        assert_eq!(
+            strict_schema_findings(&src),
+            vec![
+                StrictSchemaFinding::EmptyNamespace("A".to_string()),
+                StrictSchemaFinding::EmptyName {
+                    location: "/B/entityTypes/".to_string()
+                },
+                StrictSchemaFinding::EmptyAppliesTo {
+                    location: "/B/actions/view/appliesTo".to_string(),
+                    field: "principalTypes",
+                },
+                StrictSchemaFinding::EmptyAppliesTo {
+                    location: "/B/actions/view/appliesTo".to_string(),
+                    field: "resourceTypes",
+                },
+            ]
+        );
+    }
+}
@@ -91,6 +91,16 @@ pub struct EntityType {
     pub member_of_types: Vec<SmolStr>,
     #[serde(default)]
     pub shape: AttributesOrContext,
+    /// When present, the entity type is deprecated, and the validator warns
+    /// about policies that reference it using this message.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<SmolStr>,
+    /// Arbitrary key-value annotations, e.g., the team owning the entity
+    /// type. Keys must be identifiers.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<SmolStr, SmolStr>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -126,12 +136,27 @@ pub struct ActionType {
     /// canonical representation of a cedar value as JSON.
     #[serde(default)]
     pub attributes: Option<HashMap<SmolStr, CedarValueJson>>,
+    /// Values for optional attributes of the context, which are used when a
+    /// context parsed with the schema doesn't have the attribute
+    #[serde(default)]
+    #[serde(rename = "contextDefaults")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_defaults: Option<HashMap<SmolStr, CedarValueJson>>,
     #[serde(default)]
     #[serde(rename = "appliesTo")]
     pub applies_to: Option<ApplySpec>,
     #[serde(default)]
     #[serde(rename = "memberOf")]
     pub member_of: Option<Vec<ActionEntityUID>>,
+    /// When present, the action is deprecated, and the validator warns about
+    /// policies that reference it using this message.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<SmolStr>,
+    /// Arbitrary key-value annotations. Keys must be identifiers.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<SmolStr, SmolStr>,
 }
 
 /// The apply spec specifies what principals and resources an action can be used
@@ -594,6 +619,18 @@ pub struct TypeOfAttribute {
     pub ty: SchemaType,
     #[serde(default = "record_attribute_required_default")]
     pub required: bool,
+    /// When present, the attribute is deprecated, and the validator warns
+    /// about policies that access it using this message. Only the attributes
+    /// declared directly in the shape of an entity type may be deprecated.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<SmolStr>,
+    /// Arbitrary key-value annotations. Keys must be identifiers. Like
+    /// `deprecated`, these are only allowed on the attributes declared
+    /// directly in the shape of an entity type.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<SmolStr, SmolStr>,
 }
 
 /// By default schema properties which enable parts of partial schema validation
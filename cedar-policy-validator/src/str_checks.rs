@@ -32,6 +32,10 @@ pub struct ValidationWarning<'a> {
 }
 
 impl<'a> ValidationWarning<'a> {
+    pub(crate) fn new(location: SourceLocation<'a>, kind: ValidationWarningKind) -> Self {
+        Self { location, kind }
+    }
+
     pub fn location(&self) -> &SourceLocation<'a> {
         &self.location
     }
@@ -62,19 +66,36 @@ impl std::fmt::Display for ValidationWarning<'_> {
 pub enum ValidationWarningKind {
     /// A string contains mixed scripts. Different scripts can contain visually similar characters which may be confused for each other.
     #[error("string `\"{0}\"` contains mixed scripts")]
+    #[diagnostic(code(cedar::validation::mixed_script_string))]
     MixedScriptString(String),
     /// A string contains BIDI control characters. These can be used to create crafted pieces of code that obfuscate true control flow.
     #[error("string `\"{0}\"` contains BIDI control characters")]
+    #[diagnostic(code(cedar::validation::bidi_chars_in_string))]
     BidiCharsInString(String),
     /// An id contains BIDI control characters. These can be used to create crafted pieces of code that obfuscate true control flow.
     #[error("identifier `{0}` contains BIDI control characters")]
+    #[diagnostic(code(cedar::validation::bidi_chars_in_identifier))]
     BidiCharsInIdentifier(String),
     /// An id contains mixed scripts. This can cause characters to be confused for each other.
     #[error("identifier `{0}` contains mixed scripts")]
+    #[diagnostic(code(cedar::validation::mixed_script_identifier))]
     MixedScriptIdentifier(String),
     /// An id contains characters that fall outside of the General Security Profile for Identifiers. We recommend adhering to this if possible. See Unicode® Technical Standard #39 for more info.
     #[error("identifier `{0}` contains characters that fall outside of the General Security Profile for Identifiers")]
+    #[diagnostic(code(cedar::validation::confusable_identifier))]
     ConfusableIdentifier(String),
+    /// A policy references an entity type that the schema marks as deprecated. The second field is the deprecation message from the schema.
+    #[error("entity type `{0}` is deprecated: {1}")]
+    #[diagnostic(code(cedar::validation::deprecated_entity_type))]
+    DeprecatedEntityType(String, String),
+    /// A policy references an action that the schema marks as deprecated. The second field is the deprecation message from the schema.
+    #[error("action `{0}` is deprecated: {1}")]
+    #[diagnostic(code(cedar::validation::deprecated_action))]
+    DeprecatedAction(String, String),
+    /// A policy accesses an attribute of an entity type (the first field) that the schema marks as deprecated. The third field is the deprecation message from the schema.
+    #[error("attribute `{1}` of entity type `{0}` is deprecated: {2}")]
+    #[diagnostic(code(cedar::validation::deprecated_attribute))]
+    DeprecatedAttribute(String, String, String),
 }
 
 /// Perform identifier and string safety checks.
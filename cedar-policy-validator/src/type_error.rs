@@ -250,6 +250,7 @@ pub enum TypeErrorKind {
     UnexpectedType(UnexpectedType),
     /// The typechecker could not compute a least upper bound for `types`.
     #[error("unable to find upper bound for types: [{}]", .0.types.iter().join(","))]
+    #[diagnostic(code(cedar::validation::incompatible_types))]
     IncompatibleTypes(IncompatibleTypes),
     /// The typechecker detected an access to a record or entity attribute
     /// that it could not statically guarantee would be present.
@@ -265,26 +266,33 @@ pub enum TypeErrorKind {
     #[error(
         "policy is impossible: the policy expression evaluates to false for all valid requests"
     )]
+    #[diagnostic(code(cedar::validation::impossible_policy))]
     ImpossiblePolicy,
     /// Undefined extension function.
     #[error("undefined extension function: {}", .0.name)]
+    #[diagnostic(code(cedar::validation::undefined_function))]
     UndefinedFunction(UndefinedFunction),
     /// Multiply defined extension function.
     #[error("extension function defined multiple times: {}", .0.name)]
+    #[diagnostic(code(cedar::validation::multiply_defined_function))]
     MultiplyDefinedFunction(MultiplyDefinedFunction),
     /// Incorrect number of arguments in an extension function application.
     #[error("wrong number of arguments in extension function application. Expected {}, got {}", .0.expected, .0.actual)]
+    #[diagnostic(code(cedar::validation::wrong_number_arguments))]
     WrongNumberArguments(WrongNumberArguments),
     /// Incorrect call style in an extension function application.
     #[error("wrong call style in extension function application. Expected {}, got {}", .0.expected, .0.actual)]
+    #[diagnostic(code(cedar::validation::wrong_call_style))]
     WrongCallStyle(WrongCallStyle),
     /// Error returned by custom extension function argument validation
     #[error("error during extension function argument validation: {0}")]
     #[diagnostic(transparent)]
     FunctionArgumentValidationError(FunctionArgumentValidationError),
     #[error("empty set literals are forbidden in policies")]
+    #[diagnostic(code(cedar::validation::empty_set_forbidden))]
     EmptySetForbidden,
     #[error("extension constructors may not be called with non-literal expressions")]
+    #[diagnostic(code(cedar::validation::non_lit_ext_constructor))]
     NonLitExtConstructor,
     /// To pass strict validation a policy cannot contain an `in` expression
     /// where the entity type on the left might not be able to be a member of
@@ -303,6 +311,7 @@ pub enum TypeErrorKind {
     },
     .actual
 )]
+#[diagnostic(code(cedar::validation::unexpected_type))]
 pub struct UnexpectedType {
     expected: BTreeSet<Type>,
     actual: Type,
@@ -352,6 +361,10 @@ pub struct UnsafeAttributeAccess {
 }
 
 impl Diagnostic for UnsafeAttributeAccess {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new("cedar::validation::unsafe_attribute_access"))
+    }
+
     fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
         match (&self.suggestion, self.may_exist) {
             (Some(suggestion), false) => Some(Box::new(format!("did you mean `{suggestion}`?"))),
@@ -365,7 +378,7 @@ impl Diagnostic for UnsafeAttributeAccess {
 /// Structure containing details about an unsafe optional attribute error.
 #[derive(Error, Diagnostic, Debug, Clone, Hash, Eq, PartialEq)]
 #[error("unable to guarantee safety of access to optional attribute {attribute_access}")]
-#[diagnostic(help("try testing for the attribute with `{} && ..`", attribute_access.suggested_has_guard()))]
+#[diagnostic(code(cedar::validation::unsafe_optional_attribute_access), help("try testing for the attribute with `{} && ..`", attribute_access.suggested_has_guard()))]
 pub struct UnsafeOptionalAttributeAccess {
     attribute_access: AttributeAccess,
 }
@@ -399,6 +412,7 @@ pub struct WrongCallStyle {
 /// Structure containing details about a function argument validation error.
 #[derive(Debug, Clone, Hash, Eq, PartialEq, Diagnostic, Error)]
 #[error("{msg}")]
+#[diagnostic(code(cedar::validation::function_argument_validation))]
 pub struct FunctionArgumentValidationError {
     msg: String,
 }
@@ -412,6 +426,10 @@ pub struct HierarchyNotRespected {
 }
 
 impl Diagnostic for HierarchyNotRespected {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new("cedar::validation::hierarchy_not_respected"))
+    }
+
     fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
         match (&self.in_lhs, &self.in_rhs) {
             (Some(in_lhs), Some(in_rhs)) => Some(Box::new(format!(
@@ -1386,20 +1386,31 @@ impl<'a> Typechecker<'a> {
             }
 
             BinaryOp::Less | BinaryOp::LessEq => {
-                let ans_arg1 = self.expect_type(
+                let comparable = [Type::primitive_long(), Type::primitive_string()];
+                let ans_arg1 = self.expect_one_of_types(
                     request_env,
                     prior_eff,
                     arg1,
-                    Type::primitive_long(),
+                    &comparable,
                     type_errors,
                     |_| None,
                 );
                 ans_arg1.then_typecheck(|expr_ty_arg1, _| {
-                    let ans_arg2 = self.expect_type(
+                    // Longs are compared to Longs, and Strings to Strings
+                    let expected = match expr_ty_arg1.data() {
+                        Some(Type::Primitive {
+                            primitive_type: Primitive::Long,
+                        }) => vec![Type::primitive_long()],
+                        Some(Type::Primitive {
+                            primitive_type: Primitive::String,
+                        }) => vec![Type::primitive_string()],
+                        _ => comparable.to_vec(),
+                    };
+                    let ans_arg2 = self.expect_one_of_types(
                         request_env,
                         prior_eff,
                         arg2,
-                        Type::primitive_long(),
+                        &expected,
                         type_errors,
                         |_| None,
                     );
@@ -2506,12 +2517,12 @@ impl<'a> Typechecker<'a> {
                         None => TypecheckAnswer::RecursionLimit,
                     }
                 } else {
-                    let typechecked_args = zip(args.as_ref(), arg_tys).map(|(arg, ty)| {
-                        self.expect_type(
+                    let typechecked_args = zip(args.as_ref(), arg_tys).map(|(arg, tys)| {
+                        self.expect_one_of_types(
                             request_env,
                             prior_eff,
                             arg,
-                            ty.clone(),
+                            tys,
                             type_errors,
                             |_| None,
                         )
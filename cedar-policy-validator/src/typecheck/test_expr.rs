@@ -19,7 +19,7 @@
 #![cfg(test)]
 // GRCOV_STOP_COVERAGE
 
-use std::{str::FromStr, vec};
+use std::{collections::BTreeMap, str::FromStr, vec};
 
 use cedar_policy_core::ast::{BinaryOp, EntityUID, Expr, PatternElem, SlotId, Var};
 use serde_json::json;
@@ -59,6 +59,8 @@ fn slot_in_typechecks() {
     let etype = EntityType {
         member_of_types: vec![],
         shape: AttributesOrContext::default(),
+        deprecated: None,
+        annotations: BTreeMap::new(),
     };
     let schema = NamespaceDefinition::new([("typename".into(), etype)], []);
     assert_typechecks_for_mode(
@@ -88,6 +90,8 @@ fn slot_equals_typechecks() {
     let etype = EntityType {
         member_of_types: vec![],
         shape: AttributesOrContext::default(),
+        deprecated: None,
+        annotations: BTreeMap::new(),
     };
     // These don't typecheck in strict mode because the test_util expression
     // typechecker doesn't have access to a schema, so it can't instantiate
@@ -944,7 +948,11 @@ fn less_than_typechecks() {
     assert_typechecks_empty_schema(
         Expr::less(Expr::val(1), Expr::val(2)),
         Type::primitive_boolean(),
-    )
+    );
+    assert_typechecks_empty_schema(
+        Expr::lesseq(Expr::val("2024-01-01"), Expr::val("2024-12-31")),
+        Type::primitive_boolean(),
+    );
 }
 
 #[test]
@@ -953,20 +961,40 @@ fn less_than_typecheck_fails() {
         Expr::less(Expr::val(true), Expr::val(false)),
         Type::primitive_boolean(),
         vec![
-            TypeError::expected_type(
+            TypeError::expected_one_of_types(
                 Expr::val(true),
-                Type::primitive_long(),
+                vec![Type::primitive_long(), Type::primitive_string()],
                 Type::singleton_boolean(true),
                 None,
             ),
-            TypeError::expected_type(
+            TypeError::expected_one_of_types(
                 Expr::val(false),
-                Type::primitive_long(),
+                vec![Type::primitive_long(), Type::primitive_string()],
                 Type::singleton_boolean(false),
                 None,
             ),
         ],
-    )
+    );
+    assert_typecheck_fails_empty_schema(
+        Expr::less(Expr::val("a"), Expr::val(1)),
+        Type::primitive_boolean(),
+        vec![TypeError::expected_type(
+            Expr::val(1),
+            Type::primitive_string(),
+            Type::primitive_long(),
+            None,
+        )],
+    );
+    assert_typecheck_fails_empty_schema(
+        Expr::less(Expr::val(1), Expr::val("a")),
+        Type::primitive_boolean(),
+        vec![TypeError::expected_type(
+            Expr::val("a"),
+            Type::primitive_long(),
+            Type::primitive_string(),
+            None,
+        )],
+    );
 }
 
 #[test]
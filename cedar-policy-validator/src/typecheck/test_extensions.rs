@@ -86,7 +86,7 @@ fn decimal_extension_typechecks() {
     let decimal_name =
         Name::parse_unqualified_name("decimal").expect("should be a valid identifier");
     let expr = Expr::from_str("decimal(\"1.23\")").expect("parsing should succeed");
-    assert_typechecks_empty_schema(expr, Type::extension(decimal_name));
+    assert_typechecks_empty_schema(expr, Type::extension(decimal_name.clone()));
     let expr = Expr::from_str("decimal(\"1.23\").lessThan(decimal(\"1.24\"))")
         .expect("parsing should succeed");
     assert_typechecks_empty_schema(expr, Type::primitive_boolean());
@@ -99,6 +99,13 @@ fn decimal_extension_typechecks() {
     let expr = Expr::from_str("decimal(\"1.23\").greaterThanOrEqual(decimal(\"1.24\"))")
         .expect("parsing should succeed");
     assert_typechecks_empty_schema(expr, Type::primitive_boolean());
+    let expr = Expr::from_str("decimal(3)").expect("parsing should succeed");
+    assert_typechecks_empty_schema(expr, Type::extension(decimal_name));
+    let expr = Expr::from_str("decimal(\"1.23\").lessThan(3)").expect("parsing should succeed");
+    assert_typechecks_empty_schema(expr, Type::primitive_boolean());
+    let expr =
+        Expr::from_str("decimal(\"1.23\").greaterThanOrEqual(3)").expect("parsing should succeed");
+    assert_typechecks_empty_schema(expr, Type::primitive_boolean());
 }
 
 #[test]
@@ -106,17 +113,26 @@ fn decimal_extension_typechecks() {
 fn decimal_extension_typecheck_fails() {
     let decimal_name =
         Name::parse_unqualified_name("decimal").expect("should be a valid identifier");
-    let expr = Expr::from_str("decimal(3)").expect("parsing should succeed");
+    let expr = Expr::from_str("decimal(true)").expect("parsing should succeed");
     assert_typecheck_fails_empty_schema(
         expr,
         Type::extension(decimal_name.clone()),
-        vec![TypeError::expected_type(
-            Expr::val(3),
-            Type::primitive_string(),
-            Type::primitive_long(),
+        vec![TypeError::expected_one_of_types(
+            Expr::val(true),
+            [Type::primitive_string(), Type::primitive_long()],
+            Type::singleton_boolean(true),
             None,
         )],
     );
+    let expr = Expr::from_str("decimal(1000000000000000)").expect("parsing should succeed");
+    assert_typecheck_fails_empty_schema(
+        expr.clone(),
+        Type::extension(decimal_name.clone()),
+        vec![TypeError::arg_validation_error(
+            expr,
+            "Failed to parse as a decimal value: `1000000000000000`".into(),
+        )],
+    );
     let expr = Expr::from_str("decimal(\"foo\")").expect("parsing should succeed");
     assert_typecheck_fails_empty_schema(
         expr.clone(),
@@ -132,15 +148,48 @@ fn decimal_extension_typecheck_fails() {
         Type::primitive_boolean(),
         vec![TypeError::wrong_number_args(expr, 2, 3)],
     );
-    let expr = Expr::from_str("decimal(\"1.23\").lessThan(3)").expect("parsing should succeed");
+    let expr = Expr::from_str("decimal(\"1.23\").lessThan(\"3\")").expect("parsing should succeed");
+    assert_typecheck_fails_empty_schema(
+        expr,
+        Type::primitive_boolean(),
+        vec![TypeError::expected_one_of_types(
+            Expr::val("3"),
+            [Type::extension(decimal_name), Type::primitive_long()],
+            Type::primitive_string(),
+            None,
+        )],
+    );
+}
+
+#[test]
+#[cfg(feature = "unicode")]
+fn unicode_extension_typechecks() {
+    let expr =
+        Expr::from_str("\"Alice\".equalsIgnoreCase(\"alice\")").expect("parsing should succeed");
+    assert_typechecks_empty_schema(expr, Type::primitive_boolean());
+    let expr = Expr::from_str("\"caf\\u{e9}\".equalsNormalized(\"cafe\\u{301}\")")
+        .expect("parsing should succeed");
+    assert_typechecks_empty_schema(expr, Type::primitive_boolean());
+}
+
+#[test]
+#[cfg(feature = "unicode")]
+fn unicode_extension_typecheck_fails() {
+    let expr = Expr::from_str("\"1\".equalsIgnoreCase(1)").expect("parsing should succeed");
     assert_typecheck_fails_empty_schema(
         expr,
         Type::primitive_boolean(),
         vec![TypeError::expected_type(
-            Expr::val(3),
-            Type::extension(decimal_name),
+            Expr::val(1),
+            Type::primitive_string(),
             Type::primitive_long(),
             None,
         )],
     );
+    let expr = Expr::from_str("\"a\".equalsNormalized()").expect("parsing should succeed");
+    assert_typecheck_fails_empty_schema(
+        expr.clone(),
+        Type::primitive_boolean(),
+        vec![TypeError::wrong_number_args(expr, 2, 1)],
+    );
 }
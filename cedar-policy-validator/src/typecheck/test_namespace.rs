@@ -135,9 +135,9 @@ fn namespaced_entity_can_type_error() {
     assert_expr_typecheck_fails_namespace_schema(
         Expr::from_str(r#"N::S::Foo::"alice" > 1"#).expect("Expr should parse."),
         Some(Type::primitive_boolean()),
-        vec![TypeError::expected_type(
+        vec![TypeError::expected_one_of_types(
             Expr::from_str(r#"N::S::Foo::"alice""#).expect("Expr should parse."),
-            Type::primitive_long(),
+            vec![Type::primitive_long(), Type::primitive_string()],
             Type::named_entity_reference_from_str("N::S::Foo"),
             None,
         )],
@@ -614,6 +614,68 @@ fn record_optional_attrs() {
     );
 }
 
+#[test]
+fn nested_optional_attrs() {
+    let schema = serde_json::from_str::<NamespaceDefinition>(
+        r#"
+{
+    "entityTypes": {
+        "User": {
+            "shape": {
+                "type": "Record",
+                "attributes": {
+                    "record": {
+                        "type": "Record",
+                        "required": false,
+                        "attributes": {
+                            "name": { "type": "String", "required": false}
+                        }
+                    }
+                }
+            }
+        }
+    },
+    "actions": {
+        "view_photo": {
+            "appliesTo": {
+                "principalTypes": ["User"],
+                "resourceTypes": ["User"]
+            }
+        }
+    }
+}
+    "#,
+    )
+    .expect("Expected valid schema.");
+
+    let passing_policy = parse_policy(
+        Some("0".to_string()),
+        r#"permit(principal, action, resource) when { principal has record.name && principal.record.name == "foo" };"#,
+    )
+    .expect("Policy should parse.");
+    assert_policy_typechecks(schema.clone(), passing_policy);
+
+    let failing_policy = parse_policy(
+        Some("0".to_string()),
+        r#"permit(principal, action, resource) when { principal has record && principal.record.name == "foo" };"#,
+    )
+    .expect("Policy should parse.");
+    assert_policy_typecheck_fails(
+        schema,
+        failing_policy,
+        vec![TypeError::unsafe_optional_attribute_access(
+            Expr::get_attr(
+                Expr::get_attr(Expr::var(Var::Principal), "record".into()),
+                "name".into(),
+            ),
+            AttributeAccess::EntityLUB(
+                EntityLUB::single_entity("User".parse().unwrap()),
+                vec!["name".into(), "record".into()],
+            ),
+        )],
+    );
+}
+
 #[test]
 fn action_attrs_passing() {
     let schema = serde_json::from_str::<NamespaceDefinitionWithActionAttributes>(
@@ -1065,7 +1065,7 @@ impl EntityLUB {
 
     /// An iterator over the entity type `Name`s in the set of entity types
     /// comprising this LUB.
-    pub(crate) fn iter(&self) -> impl Iterator<Item = &Name> {
+    pub fn iter(&self) -> impl Iterator<Item = &Name> {
         self.lub_elements.iter()
     }
 
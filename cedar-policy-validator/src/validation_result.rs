@@ -223,6 +223,10 @@ pub struct UnrecognizedEntityType {
 }
 
 impl Diagnostic for UnrecognizedEntityType {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new("cedar::validation::unrecognized_entity_type"))
+    }
+
     fn help<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
         match &self.suggested_entity_type {
             Some(s) => Some(Box::new(format!("did you mean `{s}`?"))),
@@ -244,6 +248,10 @@ pub struct UnrecognizedActionId {
 }
 
 impl Diagnostic for UnrecognizedActionId {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new("cedar::validation::unrecognized_action_id"))
+    }
+
     fn help<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
         match &self.suggested_action_id {
             Some(s) => Some(Box::new(format!("did you mean `{s}`?"))),
@@ -262,6 +270,10 @@ pub struct InvalidActionApplication {
 }
 
 impl Diagnostic for InvalidActionApplication {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new("cedar::validation::invalid_action_application"))
+    }
+
     fn help<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
         match (self.would_in_fix_principal, self.would_in_fix_resource) {
             (true, false) => Some(Box::new(
@@ -282,7 +294,10 @@ impl Diagnostic for InvalidActionApplication {
 #[derive(Debug, Clone, Diagnostic, Error)]
 #[cfg_attr(test, derive(Eq, PartialEq))]
 #[error("unspecified entity with id `{entity_id}`")]
-#[diagnostic(help("unspecified entities cannot be used in policies"))]
+#[diagnostic(
+    code(cedar::validation::unspecified_entity),
+    help("unspecified entities cannot be used in policies")
+)]
 pub struct UnspecifiedEntityError {
     /// EID of the unspecified entity.
     pub(crate) entity_id: String,
@@ -0,0 +1,2469 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module checks whether two policies, or two policy sets, are
+//! equivalent: whether they make the same authorization decision for every
+//! request which is valid for a schema, and for all entity data. This is the
+//! check to run before refactoring policies, e.g., before replacing repeated
+//! policies with links of a template. It also finds redundant policies,
+//! which are subsumed by another policy of the same effect, compares two
+//! versions of a policy by what they allow, and suggests templates to
+//! replace repeated policies with.
+//! ```ignore
+//! match check_equivalence(&before, &after, &schema, &Solver::z3())? {
+//!     Equivalence::Equivalent => (),
+//!     Equivalence::NotEquivalent(counterexample) => {
+//!         // `counterexample.request()` and `counterexample.entities()` get
+//!         // different decisions
+//!     }
+//! }
+//! ```
+//!
+//! The policies are translated, per request environment of the schema, to an
+//! SMT-LIB 2 query which is decided by an SMT solver, e.g., `z3` or `cvc5`,
+//! run as an external process. The attributes of entities are uninterpreted
+//! functions of the entity ids, and the entity hierarchy is an uninterpreted
+//! transitive relation for each pair of entity types which the schema allows.
+//! Errors, e.g., of reading a missing optional attribute or of overflow, are
+//! modeled, so a policy which errors is not satisfied.
+//!
+//! The policies must validate against the schema in strict mode. Extension
+//! functions, `==` on records and sets, `contains`, `containsAll`, and
+//! `containsAny` on sets other than set literals, `in` a set other than a set
+//! literal, record literals, and attributes of actions or of set type are not
+//! supported.
+#![allow(clippy::module_name_repetitions)]
+
+use crate::{
+    Context, Entities, EntitiesError, Entity, EntityUid, Policy, PolicyId, PolicySet, Request,
+    RestrictedExpression, Schema, Template,
+};
+use cedar_policy_core::ast::{
+    self, BinaryOp, Effect, EntityReference, EntityType, Expr, ExprKind, ExprShapeOnly, Literal,
+    Name, Pattern, PatternElem, PrincipalOrResourceConstraint, SlotEnv, SlotId, UnaryOp, Var,
+};
+use cedar_policy_validator::typecheck::{PolicyCheck, Typechecker};
+use cedar_policy_validator::types::{AttributeType, Attributes, EntityRecordKind, Primitive, Type};
+use cedar_policy_validator::{ValidationMode, ValidatorEntityType, ValidatorSchema};
+use itertools::Itertools;
+use miette::Diagnostic;
+use ref_cast::RefCast;
+use smol_str::SmolStr;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ffi::OsString;
+use std::fmt::{self, Display};
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use thiserror::Error;
+
+/// An SMT solver, run as an external process which reads SMT-LIB 2 commands
+/// on its standard input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solver {
+    program: OsString,
+    args: Vec<OsString>,
+}
+
+impl Solver {
+    /// Run `program` with the arguments `args`
+    pub fn new(
+        program: impl Into<OsString>,
+        args: impl IntoIterator<Item = impl Into<OsString>>,
+    ) -> Self {
+        Self {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Run `z3`, found in the `PATH`
+    pub fn z3() -> Self {
+        Self::new("z3", ["-in", "-smt2"])
+    }
+
+    /// Run `cvc5`, found in the `PATH`
+    pub fn cvc5() -> Self {
+        Self::new("cvc5", ["--lang=smt2", "--incremental", "--produce-models"])
+    }
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Self::z3()
+    }
+}
+
+/// The result of checking the equivalence of policies
+#[derive(Debug)]
+pub enum Equivalence {
+    /// The policies make the same decision for every valid request
+    Equivalent,
+    /// The policies make different decisions for the request of the
+    /// counterexample
+    NotEquivalent(Box<Counterexample>),
+}
+
+/// A request and entities for which policies make different decisions
+#[derive(Debug)]
+pub struct Counterexample {
+    request: Request,
+    entities: Entities,
+}
+
+impl Counterexample {
+    /// The request. Its context only has the attributes which the policies
+    /// read.
+    pub fn request(&self) -> &Request {
+        &self.request
+    }
+
+    /// The entities. They only have the attributes and ancestors which the
+    /// policies read, and the actions of the schema are included.
+    pub fn entities(&self) -> &Entities {
+        &self.entities
+    }
+}
+
+/// Errors checking the equivalence of policies
+#[derive(Debug, Diagnostic, Error)]
+#[non_exhaustive]
+pub enum AnalysisError {
+    /// A policy does not validate against the schema
+    #[error("policy `{0}` does not validate against the schema")]
+    #[diagnostic(help("validate the policies in strict mode to see the errors"))]
+    Validation(PolicyId),
+    /// A policy uses a feature which the analysis does not support
+    #[error("policy `{policy}` uses {feature}, which the analysis does not support")]
+    Unsupported {
+        /// The policy
+        policy: PolicyId,
+        /// The feature it uses
+        feature: &'static str,
+    },
+    /// Failed to run the solver, or to communicate with it
+    #[error("failed to run the solver: {0}")]
+    Io(#[from] io::Error),
+    /// The solver responded with an error, or with something unexpected
+    #[error("unexpected response from the solver: {0}")]
+    Response(String),
+    /// The solver could not decide the query
+    #[error("the solver could not decide whether the policies are equivalent")]
+    #[diagnostic(help("try another solver, or simpler policies"))]
+    Unknown,
+    /// Failed to construct the entities of the schema's actions, or of a
+    /// counterexample
+    #[error(transparent)]
+    Entities(#[from] EntitiesError),
+    /// Failed to construct the request of a counterexample from the model of
+    /// the solver
+    #[error("failed to construct a counterexample: {0}")]
+    Model(String),
+}
+
+/// Check whether `first` and `second` make the same authorization decision
+/// for every request which is valid for `schema`, and for all entity data
+pub fn check_equivalence(
+    first: &PolicySet,
+    second: &PolicySet,
+    schema: &Schema,
+    solver: &Solver,
+) -> Result<Equivalence, AnalysisError> {
+    let mut analysis = Analysis::new(schema)?;
+    let first = analysis.typecheck_all(first.ast.policies())?;
+    let second = analysis.typecheck_all(second.ast.policies())?;
+    for key in env_keys(first.iter().chain(&second)) {
+        let mut encoder = Encoder::new(&analysis, &key);
+        let first = encoder.decision(&first)?;
+        let second = encoder.decision(&second)?;
+        if let Some(counterexample) =
+            encoder.solve(&format!("(distinct {first} {second})"), solver)?
+        {
+            return Ok(Equivalence::NotEquivalent(Box::new(counterexample)));
+        }
+    }
+    Ok(Equivalence::Equivalent)
+}
+
+/// Check whether `first` and `second` have the same effect on every request
+/// which is valid for `schema`, and for all entity data: whether they are
+/// satisfied by the same requests if they have the same effect, and whether
+/// neither is ever satisfied if they don't. So either can replace the other
+/// in any policy set.
+pub fn check_policy_equivalence(
+    first: &Policy,
+    second: &Policy,
+    schema: &Schema,
+    solver: &Solver,
+) -> Result<Equivalence, AnalysisError> {
+    let mut analysis = Analysis::new(schema)?;
+    let first = analysis.typecheck(&first.ast)?;
+    let second = analysis.typecheck(&second.ast)?;
+    let same_effect = first.policy.effect() == second.policy.effect();
+    for key in env_keys([&first, &second].into_iter()) {
+        let mut encoder = Encoder::new(&analysis, &key);
+        let first = encoder.satisfied_any(std::iter::once(&first))?;
+        let second = encoder.satisfied_any(std::iter::once(&second))?;
+        let query = if same_effect {
+            format!("(distinct {first} {second})")
+        } else {
+            or([first, second])
+        };
+        if let Some(counterexample) = encoder.solve(&query, solver)? {
+            return Ok(Equivalence::NotEquivalent(Box::new(counterexample)));
+        }
+    }
+    Ok(Equivalence::Equivalent)
+}
+
+/// How a new version of a policy changes what the policy allows, compared to
+/// the old version. A policy allows more if it permits more requests, or
+/// forbids fewer. The counterexamples are witnesses of the change.
+#[derive(Debug)]
+pub enum PolicyComparison {
+    /// The versions permit and forbid the same requests
+    Equivalent,
+    /// The new version allows more: it permits the request of the
+    /// counterexample, or stops forbidding it, and allows nothing less
+    MorePermissive(Box<Counterexample>),
+    /// The new version allows less: it stops permitting the request of the
+    /// counterexample, or forbids it, and allows nothing more
+    LessPermissive(Box<Counterexample>),
+    /// The new version allows more for some requests, and less for others
+    Incomparable {
+        /// A request which the new version allows more
+        more_permissive: Box<Counterexample>,
+        /// A request which the new version allows less
+        less_permissive: Box<Counterexample>,
+    },
+}
+
+/// Compare the `before` and `after` versions of a policy, for every request
+/// which is valid for `schema` and for all entity data: whether `after`
+/// permits or forbids more or fewer requests than `before`, whatever the
+/// other policies of the policy set are. The versions may have different
+/// effects, e.g., a `permit` replaced by a `forbid` is less permissive if the
+/// `permit` was ever satisfied.
+pub fn compare_policies(
+    before: &Policy,
+    after: &Policy,
+    schema: &Schema,
+    solver: &Solver,
+) -> Result<PolicyComparison, AnalysisError> {
+    let mut analysis = Analysis::new(schema)?;
+    let before = analysis.typecheck(&before.ast)?;
+    let after = analysis.typecheck(&after.ast)?;
+    let mut more_permissive = None;
+    let mut less_permissive = None;
+    for key in env_keys([&before, &after].into_iter()) {
+        let mut encoder = Encoder::new(&analysis, &key);
+        // the requests each version permits, and those it forbids
+        let mut permits_forbids = |policy: &TypedPolicy<'_>| {
+            let satisfied = encoder.satisfied_any(std::iter::once(policy))?;
+            Ok::<_, AnalysisError>(match policy.policy.effect() {
+                Effect::Permit => (satisfied, "false".to_string()),
+                Effect::Forbid => ("false".to_string(), satisfied),
+            })
+        };
+        let (before_permits, before_forbids) = permits_forbids(&before)?;
+        let (after_permits, after_forbids) = permits_forbids(&after)?;
+        if more_permissive.is_none() {
+            let query = or([
+                and([after_permits.clone(), not(&before_permits)]),
+                and([before_forbids.clone(), not(&after_forbids)]),
+            ]);
+            more_permissive = encoder.solve(&query, solver)?;
+        }
+        if less_permissive.is_none() {
+            let query = or([
+                and([before_permits, not(&after_permits)]),
+                and([after_forbids, not(&before_forbids)]),
+            ]);
+            less_permissive = encoder.solve(&query, solver)?;
+        }
+        if more_permissive.is_some() && less_permissive.is_some() {
+            break;
+        }
+    }
+    Ok(match (more_permissive, less_permissive) {
+        (None, None) => PolicyComparison::Equivalent,
+        (Some(more), None) => PolicyComparison::MorePermissive(Box::new(more)),
+        (None, Some(less)) => PolicyComparison::LessPermissive(Box::new(less)),
+        (Some(more), Some(less)) => PolicyComparison::Incomparable {
+            more_permissive: Box::new(more),
+            less_permissive: Box::new(less),
+        },
+    })
+}
+
+/// A redundant policy: every request which satisfies it also satisfies
+/// another policy of the same effect
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redundancy {
+    policy: PolicyId,
+    subsumed_by: PolicyId,
+}
+
+impl Redundancy {
+    /// The redundant policy
+    pub fn policy(&self) -> &PolicyId {
+        &self.policy
+    }
+
+    /// The policy which is satisfied whenever the redundant one is
+    pub fn subsumed_by(&self) -> &PolicyId {
+        &self.subsumed_by
+    }
+}
+
+/// Find the policies of `policies` which are subsumed by another policy of
+/// the same effect, for every request which is valid for `schema` and for all
+/// entity data, so removing one of them doesn't change any decision. Policies
+/// which subsume each other, e.g., duplicates, are all reported, but only one
+/// of them can be removed. The result is sorted by policy id.
+pub fn find_redundant_policies(
+    policies: &PolicySet,
+    schema: &Schema,
+    solver: &Solver,
+) -> Result<Vec<Redundancy>, AnalysisError> {
+    let mut analysis = Analysis::new(schema)?;
+    let mut typed = analysis.typecheck_all(policies.ast.policies())?;
+    typed.sort_by_cached_key(|policy| policy.policy.id().to_string());
+    // the pairs of a policy and another which may subsume it
+    let mut candidates: BTreeSet<(usize, usize)> = BTreeSet::new();
+    for (i, policy) in typed.iter().enumerate() {
+        for (j, other) in typed.iter().enumerate() {
+            if i != j && policy.policy.effect() == other.policy.effect() {
+                candidates.insert((i, j));
+            }
+        }
+    }
+    for key in env_keys(typed.iter()) {
+        let mut encoder = Encoder::new(&analysis, &key);
+        let satisfied = typed
+            .iter()
+            .map(|policy| encoder.satisfied_any(std::iter::once(policy)))
+            .collect::<Result<Vec<_>, _>>()?;
+        // a policy is subsumed in the environments in which it doesn't apply
+        let pairs: Vec<(usize, usize)> = candidates
+            .iter()
+            .filter(|(i, _)| {
+                typed
+                    .get(*i)
+                    .is_some_and(|policy| policy.conditions.contains_key(&key))
+            })
+            .copied()
+            .collect();
+        if pairs.is_empty() {
+            continue;
+        }
+        let mut process = Process::spawn(solver)?;
+        process.send(&encoder.declarations())?;
+        for (i, j) in pairs {
+            let (Some(policy), Some(other)) = (satisfied.get(i), satisfied.get(j)) else {
+                continue;
+            };
+            let query = and([policy.clone(), not(other)]);
+            process.send(&format!("(push 1)\n(assert {query})\n"))?;
+            if process.check_sat()? {
+                candidates.remove(&(i, j));
+            }
+            process.send("(pop 1)\n")?;
+        }
+    }
+    let id = |i: usize| {
+        typed
+            .get(i)
+            .map(|policy| PolicyId::ref_cast(policy.policy.id()).clone())
+    };
+    Ok(candidates
+        .into_iter()
+        .filter_map(|(i, j)| {
+            Some(Redundancy {
+                policy: id(i)?,
+                subsumed_by: id(j)?,
+            })
+        })
+        .collect())
+}
+
+/// A template which can replace repeated static policies, and the links of it
+/// which replace each of them
+#[derive(Debug, Clone)]
+pub struct TemplateSuggestion {
+    template: Template,
+    links: Vec<(PolicyId, HashMap<crate::SlotId, EntityUid>)>,
+}
+
+impl TemplateSuggestion {
+    /// The suggested template
+    pub fn template(&self) -> &Template {
+        &self.template
+    }
+
+    /// The links of the template, one for each policy it replaces, with the id
+    /// of that policy and the values of the slots. The links are sorted by
+    /// policy id.
+    pub fn links(&self) -> &[(PolicyId, HashMap<crate::SlotId, EntityUid>)] {
+        &self.links
+    }
+}
+
+/// Suggest templates to replace the static policies of `policies` which are
+/// identical but for the entities of their principal and resource scope
+/// constraints, e.g., `permit(principal == User::"alice", action, resource)
+/// when { resource.owner == principal };` and the same policy for
+/// `User::"bob"`. Each suggested template has a slot for the principal or
+/// resource if the entities of the policies differ there, and replaces at
+/// least two policies. Replacing the policies by the links, with the same
+/// ids, doesn't change any decision; annotations are only kept when the
+/// policies have the same ones. The templates have ids `template0`,
+/// `template1`, etc., skipping ids which are used in `policies`, and are
+/// sorted by the id of the first policy they replace.
+///
+/// This is a syntactic check, so it doesn't need a schema or solver:
+/// conditions which are equivalent but written differently aren't grouped.
+pub fn suggest_templates(policies: &PolicySet) -> Vec<TemplateSuggestion> {
+    let mut clusters: HashMap<TemplateShape<'_>, Vec<&ast::Policy>> = HashMap::new();
+    for policy in policies
+        .ast
+        .static_policies()
+        .sorted_by(|a, b| a.id().as_ref().cmp(b.id().as_ref()))
+    {
+        let template = policy.template();
+        let (principal, _) = scope_slot(template.principal_constraint().as_inner());
+        let (resource, _) = scope_slot(template.resource_constraint().as_inner());
+        let shape = TemplateShape {
+            effect: template.effect(),
+            annotations: template.annotations().collect(),
+            principal,
+            action: template.action_constraint(),
+            resource,
+            condition: ExprShapeOnly::new(template.non_head_constraints()),
+        };
+        clusters.entry(shape).or_default().push(policy);
+    }
+
+    let mut ids = (0..).map(|i| ast::PolicyID::from_string(format!("template{i}")));
+    clusters
+        .into_values()
+        .filter(|cluster| cluster.len() > 1)
+        // the policies of each cluster are sorted, so this sorts by first policy
+        .sorted_by(|a, b| {
+            a.first()
+                .map(|p| p.id().as_ref())
+                .cmp(&b.first().map(|p| p.id().as_ref()))
+        })
+        .filter_map(|cluster| {
+            // the entities of each policy, and whether they differ between policies
+            let entities: Vec<(Option<ast::EntityUID>, Option<ast::EntityUID>)> = cluster
+                .iter()
+                .map(|policy| {
+                    let template = policy.template();
+                    (
+                        scope_slot(template.principal_constraint().as_inner()).1,
+                        scope_slot(template.resource_constraint().as_inner()).1,
+                    )
+                })
+                .collect();
+            let principal_slot = !entities.iter().map(|(p, _)| p).all_equal();
+            let resource_slot = !entities.iter().map(|(_, r)| r).all_equal();
+            if !principal_slot && !resource_slot {
+                return None;
+            }
+            let first = cluster.first()?.template();
+            let scope = |constraint: &PrincipalOrResourceConstraint, slot: bool| {
+                if slot {
+                    scope_slot(constraint).0
+                } else {
+                    constraint.clone()
+                }
+            };
+            let id = ids.by_ref().find(|id| {
+                policies.ast.get(id).is_none() && policies.ast.get_template(id).is_none()
+            })?;
+            let template = ast::Template::new(
+                id,
+                first
+                    .annotations()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect(),
+                first.effect(),
+                ast::PrincipalConstraint::new(scope(
+                    first.principal_constraint().as_inner(),
+                    principal_slot,
+                )),
+                first.action_constraint().clone(),
+                ast::ResourceConstraint::new(scope(
+                    first.resource_constraint().as_inner(),
+                    resource_slot,
+                )),
+                first.non_head_constraints().clone(),
+            );
+            let links = cluster
+                .iter()
+                .zip(entities)
+                .map(|(policy, (principal, resource))| {
+                    let values = [
+                        (principal_slot, crate::SlotId::principal(), principal),
+                        (resource_slot, crate::SlotId::resource(), resource),
+                    ]
+                    .into_iter()
+                    .filter(|(slot, _, _)| *slot)
+                    .filter_map(|(_, slot, euid)| Some((slot, EntityUid(euid?))))
+                    .collect();
+                    (PolicyId::ref_cast(policy.id()).clone(), values)
+                })
+                .collect();
+            Some(TemplateSuggestion {
+                template: Template::from_ast(template),
+                links,
+            })
+        })
+        .collect()
+}
+
+/// What static policies which can be links of the same template have in
+/// common: everything but the entities of the principal and resource scope
+/// constraints, which are replaced by slots
+#[derive(PartialEq, Eq, Hash)]
+struct TemplateShape<'a> {
+    effect: Effect,
+    annotations: BTreeMap<&'a ast::Id, &'a SmolStr>,
+    principal: PrincipalOrResourceConstraint,
+    action: &'a ast::ActionConstraint,
+    resource: PrincipalOrResourceConstraint,
+    condition: ExprShapeOnly<'a>,
+}
+
+/// The principal or resource scope constraint `constraint` with its entity
+/// replaced by a slot, and the entity
+fn scope_slot(
+    constraint: &PrincipalOrResourceConstraint,
+) -> (PrincipalOrResourceConstraint, Option<ast::EntityUID>) {
+    match constraint {
+        PrincipalOrResourceConstraint::Eq(EntityReference::EUID(euid)) => (
+            PrincipalOrResourceConstraint::Eq(EntityReference::Slot),
+            Some(euid.as_ref().clone()),
+        ),
+        PrincipalOrResourceConstraint::In(EntityReference::EUID(euid)) => (
+            PrincipalOrResourceConstraint::In(EntityReference::Slot),
+            Some(euid.as_ref().clone()),
+        ),
+        PrincipalOrResourceConstraint::IsIn(ty, EntityReference::EUID(euid)) => (
+            PrincipalOrResourceConstraint::IsIn(ty.clone(), EntityReference::Slot),
+            Some(euid.as_ref().clone()),
+        ),
+        PrincipalOrResourceConstraint::Any
+        | PrincipalOrResourceConstraint::Eq(EntityReference::Slot)
+        | PrincipalOrResourceConstraint::In(EntityReference::Slot)
+        | PrincipalOrResourceConstraint::IsIn(_, EntityReference::Slot)
+        | PrincipalOrResourceConstraint::Is(_) => (constraint.clone(), None),
+    }
+}
+
+/// A request environment of the schema
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct EnvKey {
+    principal: EntityType,
+    action: ast::EntityUID,
+    resource: EntityType,
+}
+
+/// The request environments in which any of `policies` applies
+fn env_keys<'a, 'p: 'a>(policies: impl Iterator<Item = &'a TypedPolicy<'p>>) -> BTreeSet<EnvKey> {
+    policies
+        .flat_map(|policy| policy.conditions.keys().cloned())
+        .collect()
+}
+
+/// A policy with its condition typechecked in each request environment in
+/// which it applies
+struct TypedPolicy<'a> {
+    policy: &'a ast::Policy,
+    conditions: BTreeMap<EnvKey, Expr<Option<Type>>>,
+}
+
+/// The data of the schema shared by all queries
+struct Analysis<'a> {
+    schema: &'a ValidatorSchema,
+    typechecker: Typechecker<'a>,
+    /// The action entities of the schema
+    actions: Vec<ast::Entity>,
+    /// The attributes of the context in each request environment
+    contexts: BTreeMap<EnvKey, Attributes>,
+}
+
+impl<'a> Analysis<'a> {
+    fn new(schema: &'a Schema) -> Result<Self, AnalysisError> {
+        let actions = schema.0.action_entities()?.iter().cloned().collect();
+        Ok(Self {
+            schema: &schema.0,
+            typechecker: Typechecker::new(&schema.0, ValidationMode::Strict),
+            actions,
+            contexts: BTreeMap::new(),
+        })
+    }
+
+    fn typecheck_all<'p>(
+        &mut self,
+        policies: impl Iterator<Item = &'p ast::Policy>,
+    ) -> Result<Vec<TypedPolicy<'p>>, AnalysisError> {
+        policies.map(|policy| self.typecheck(policy)).collect()
+    }
+
+    /// Typecheck `policy` in the request environments which are consistent
+    /// with the types of the entities it's linked to, if it's a link
+    fn typecheck<'p>(&mut self, policy: &'p ast::Policy) -> Result<TypedPolicy<'p>, AnalysisError> {
+        let slot_type = |slot: SlotId| policy.env().get(&slot).map(ast::EntityUID::entity_type);
+        let mut conditions = BTreeMap::new();
+        for (env, check) in self.typechecker.typecheck_by_request_env(policy.template()) {
+            if env.principal_slot().as_ref() != slot_type(SlotId::principal())
+                || env.resource_slot().as_ref() != slot_type(SlotId::resource())
+            {
+                continue;
+            }
+            let expr = match check {
+                PolicyCheck::Success(expr) => expr,
+                // the policy never applies in this environment
+                PolicyCheck::Irrelevant(_) => continue,
+                PolicyCheck::Fail(_) => {
+                    return Err(AnalysisError::Validation(
+                        PolicyId::ref_cast(policy.id()).clone(),
+                    ))
+                }
+            };
+            let (Some(principal), Some(action), Some(resource)) = (
+                env.principal_entity_type(),
+                env.action_entity_uid(),
+                env.resource_entity_type(),
+            ) else {
+                continue;
+            };
+            let key = EnvKey {
+                principal: principal.clone(),
+                action: action.clone(),
+                resource: resource.clone(),
+            };
+            if let Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) = env.context_type()
+            {
+                self.contexts.entry(key.clone()).or_insert(attrs);
+            }
+            conditions.insert(key, expr);
+        }
+        Ok(TypedPolicy { policy, conditions })
+    }
+
+    /// Whether entities of type `child` can be in entities of type `parent`,
+    /// other than by being the same entity
+    fn may_be_in(&self, child: &Name, parent: &Name) -> bool {
+        if is_action(child) && is_action(parent) {
+            return true;
+        }
+        self.schema
+            .get_entity_type(parent)
+            .is_some_and(|parent| parent.descendants.contains(child))
+    }
+}
+
+/// Whether `name` is the type of actions
+fn is_action(name: &Name) -> bool {
+    EntityType::Specified(name.clone()).is_action()
+}
+
+/// Whose attribute a symbol is
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Owner {
+    /// The context of the request
+    Context,
+    /// Entities of a type, so the symbol is a function of the entity id
+    Entity(Name),
+}
+
+/// The type of an attribute whose value is a symbol
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Leaf {
+    Bool,
+    Long,
+    String,
+    Entity(Name),
+}
+
+/// The type of an attribute
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Shape {
+    Leaf(Leaf),
+    Record,
+    Set,
+}
+
+/// An uninterpreted constant or function of the query
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Symbol {
+    /// The id of the principal
+    Principal,
+    /// The id of the resource
+    Resource,
+    /// The value of the attribute at a path of attributes
+    Attr(Owner, Vec<SmolStr>, Leaf),
+    /// Whether the optional attribute at a path of attributes is present
+    Has(Owner, Vec<SmolStr>, Shape),
+    /// Whether an entity of the first type is in an entity of the second
+    In(Name, Name),
+}
+
+/// A path of attributes from the context or an entity
+#[derive(Debug, Clone)]
+struct Path {
+    owner: Owner,
+    /// The id of the entity, for an entity
+    id: Option<String>,
+    attrs: Vec<SmolStr>,
+}
+
+/// The SMT terms of a Cedar value
+#[derive(Debug)]
+enum Term {
+    Bool(String),
+    Long(String),
+    String(String),
+    /// An entity, whose id is a term of sort `String`
+    Entity(EntityType, String),
+    /// A record, whose attributes are read from a path and have the types
+    /// `attrs`
+    Record(Path, Attributes),
+    /// A set literal
+    Set(Vec<Value>),
+}
+
+impl Term {
+    fn bool(self) -> Result<String, &'static str> {
+        match self {
+            Self::Bool(b) => Ok(b),
+            _ => Err("an ill-typed expression"),
+        }
+    }
+
+    fn long(self) -> Result<String, &'static str> {
+        match self {
+            Self::Long(i) => Ok(i),
+            _ => Err("an ill-typed expression"),
+        }
+    }
+
+    fn string(self) -> Result<String, &'static str> {
+        match self {
+            Self::String(s) => Ok(s),
+            _ => Err("an ill-typed expression"),
+        }
+    }
+
+    fn set(self) -> Result<Vec<Value>, &'static str> {
+        match self {
+            Self::Set(values) => Ok(values),
+            _ => Err("a set which is not a set literal"),
+        }
+    }
+}
+
+/// The result of evaluating an expression: its value, if the term `err` is
+/// false
+#[derive(Debug)]
+struct Value {
+    term: Term,
+    err: String,
+}
+
+impl Value {
+    fn ok(term: Term) -> Self {
+        Self {
+            term,
+            err: "false".into(),
+        }
+    }
+}
+
+/// The bounds of Cedar's 64-bit integers
+const MIN: &str = "(- 9223372036854775808)";
+const MAX: &str = "9223372036854775807";
+
+/// Translates the policies of one request environment to SMT-LIB 2
+struct Encoder<'a> {
+    analysis: &'a Analysis<'a>,
+    key: &'a EnvKey,
+    /// The symbols used, in the order of their names `s0`, `s1`, ...
+    symbols: Vec<Symbol>,
+    names: HashMap<Symbol, String>,
+    /// The entity literals of the policies
+    literals: BTreeSet<ast::EntityUID>,
+}
+
+impl<'a> Encoder<'a> {
+    fn new(analysis: &'a Analysis<'a>, key: &'a EnvKey) -> Self {
+        Self {
+            analysis,
+            key,
+            symbols: Vec::new(),
+            names: HashMap::new(),
+            literals: BTreeSet::new(),
+        }
+    }
+
+    /// The term which is true iff `policies` allow the request
+    fn decision(&mut self, policies: &[TypedPolicy<'_>]) -> Result<String, AnalysisError> {
+        let permits = policies
+            .iter()
+            .filter(|policy| policy.policy.effect() == Effect::Permit);
+        let forbids = policies
+            .iter()
+            .filter(|policy| policy.policy.effect() == Effect::Forbid);
+        let permitted = self.satisfied_any(permits)?;
+        let forbidden = self.satisfied_any(forbids)?;
+        Ok(and([permitted, not(&forbidden)]))
+    }
+
+    /// The term which is true iff any of `policies` is satisfied
+    fn satisfied_any<'p, 'q: 'p>(
+        &mut self,
+        policies: impl Iterator<Item = &'p TypedPolicy<'q>>,
+    ) -> Result<String, AnalysisError> {
+        let mut satisfied = Vec::new();
+        for policy in policies {
+            let Some(expr) = policy.conditions.get(self.key) else {
+                continue;
+            };
+            let unsupported = |feature| AnalysisError::Unsupported {
+                policy: PolicyId::ref_cast(policy.policy.id()).clone(),
+                feature,
+            };
+            let value = self.expr(expr, policy.policy.env()).map_err(unsupported)?;
+            let condition = value.term.bool().map_err(unsupported)?;
+            satisfied.push(and([not(&value.err), condition]));
+        }
+        Ok(or(satisfied))
+    }
+
+    /// The name of `symbol`
+    fn symbol(&mut self, symbol: Symbol) -> String {
+        if let Some(name) = self.names.get(&symbol) {
+            return name.clone();
+        }
+        let name = format!("s{}", self.symbols.len());
+        self.symbols.push(symbol.clone());
+        self.names.insert(symbol, name.clone());
+        name
+    }
+
+    fn expr(&mut self, e: &Expr<Option<Type>>, slots: &SlotEnv) -> Result<Value, &'static str> {
+        Ok(match e.expr_kind() {
+            ExprKind::Lit(lit) => Value::ok(self.literal(lit)),
+            ExprKind::Var(Var::Principal) => Value::ok(Term::Entity(
+                self.key.principal.clone(),
+                self.symbol(Symbol::Principal),
+            )),
+            ExprKind::Var(Var::Action) => {
+                let key = self.key;
+                Value::ok(self.entity(&key.action))
+            }
+            ExprKind::Var(Var::Resource) => Value::ok(Term::Entity(
+                self.key.resource.clone(),
+                self.symbol(Symbol::Resource),
+            )),
+            ExprKind::Var(Var::Context) => {
+                let attrs = self
+                    .analysis
+                    .contexts
+                    .get(self.key)
+                    .cloned()
+                    .unwrap_or_else(|| Attributes {
+                        attrs: BTreeMap::new(),
+                    });
+                let path = Path {
+                    owner: Owner::Context,
+                    id: None,
+                    attrs: Vec::new(),
+                };
+                Value::ok(Term::Record(path, attrs))
+            }
+            ExprKind::Slot(slot) => {
+                let uid = slots.get(slot).ok_or("templates which are not linked")?;
+                Value::ok(self.entity(uid))
+            }
+            ExprKind::Unknown(_) => return Err("unknowns"),
+            ExprKind::If {
+                test_expr,
+                then_expr,
+                else_expr,
+            } => {
+                let test = self.expr(test_expr, slots)?;
+                let then = self.expr(then_expr, slots)?;
+                let els = self.expr(else_expr, slots)?;
+                let test_term = test.term.bool()?;
+                let ite = |a: &str, b: &str| format!("(ite {test_term} {a} {b})");
+                let term = match (then.term, els.term) {
+                    (Term::Bool(a), Term::Bool(b)) => Term::Bool(ite(&a, &b)),
+                    (Term::Long(a), Term::Long(b)) => Term::Long(ite(&a, &b)),
+                    (Term::String(a), Term::String(b)) => Term::String(ite(&a, &b)),
+                    (Term::Entity(ty, a), Term::Entity(other, b)) if ty == other => {
+                        Term::Entity(ty, ite(&a, &b))
+                    }
+                    _ => return Err("`if` with branches of different or compound types"),
+                };
+                let err = or([test.err, ite(&then.err, &els.err)]);
+                Value { term, err }
+            }
+            ExprKind::And { left, right } => {
+                let left = self.expr(left, slots)?;
+                let right = self.expr(right, slots)?;
+                let (l, r) = (left.term.bool()?, right.term.bool()?);
+                Value {
+                    err: or([left.err, and([l.clone(), right.err])]),
+                    term: Term::Bool(and([l, r])),
+                }
+            }
+            ExprKind::Or { left, right } => {
+                let left = self.expr(left, slots)?;
+                let right = self.expr(right, slots)?;
+                let (l, r) = (left.term.bool()?, right.term.bool()?);
+                Value {
+                    err: or([left.err, and([not(&l), right.err])]),
+                    term: Term::Bool(or([l, r])),
+                }
+            }
+            ExprKind::UnaryApp { op, arg } => {
+                let arg = self.expr(arg, slots)?;
+                match op {
+                    UnaryOp::Not => Value {
+                        term: Term::Bool(not(&arg.term.bool()?)),
+                        err: arg.err,
+                    },
+                    UnaryOp::Neg => arithmetic(format!("(- {})", arg.term.long()?), arg.err),
+                }
+            }
+            ExprKind::BinaryApp { op, arg1, arg2 } => {
+                let arg1 = self.expr(arg1, slots)?;
+                let arg2 = self.expr(arg2, slots)?;
+                let err = or([arg1.err, arg2.err]);
+                let (a, b) = (arg1.term, arg2.term);
+                let term = match op {
+                    BinaryOp::Eq => Term::Bool(eq(&a, &b)?),
+                    BinaryOp::Less | BinaryOp::LessEq => {
+                        let op = if *op == BinaryOp::Less { "<" } else { "<=" };
+                        match a {
+                            Term::String(a) => {
+                                Term::Bool(format!("(str.{op} {a} {})", b.string()?))
+                            }
+                            a => Term::Bool(format!("({op} {} {})", a.long()?, b.long()?)),
+                        }
+                    }
+                    BinaryOp::Add => {
+                        return Ok(arithmetic(format!("(+ {} {})", a.long()?, b.long()?), err))
+                    }
+                    BinaryOp::Sub => {
+                        return Ok(arithmetic(format!("(- {} {})", a.long()?, b.long()?), err))
+                    }
+                    BinaryOp::In => Term::Bool(self.is_in(a, b)?),
+                    BinaryOp::Contains => Term::Bool(contains(&a.set()?, &b)?),
+                    BinaryOp::ContainsAll => {
+                        let set = a.set()?;
+                        let all = b
+                            .set()?
+                            .iter()
+                            .map(|value| contains(&set, &value.term))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        Term::Bool(and(all))
+                    }
+                    BinaryOp::ContainsAny => {
+                        let set = a.set()?;
+                        let any = b
+                            .set()?
+                            .iter()
+                            .map(|value| contains(&set, &value.term))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        Term::Bool(or(any))
+                    }
+                };
+                Value { term, err }
+            }
+            ExprKind::MulByConst { arg, constant } => {
+                let arg = self.expr(arg, slots)?;
+                arithmetic(
+                    format!("(* {} {})", arg.term.long()?, int(*constant)),
+                    arg.err,
+                )
+            }
+            ExprKind::ExtensionFunctionApp { .. } => return Err("extension functions"),
+            ExprKind::GetAttr { expr, attr } => {
+                let value = self.expr(expr, slots)?;
+                let attr = self.get_attr(value.term, attr)?;
+                Value {
+                    term: attr.term,
+                    err: or([value.err, attr.err]),
+                }
+            }
+            ExprKind::HasAttr { expr, attr } => {
+                let value = self.expr(expr, slots)?;
+                Value {
+                    term: Term::Bool(self.has_attr(value.term, attr)?),
+                    err: value.err,
+                }
+            }
+            ExprKind::Like { expr, pattern } => {
+                let value = self.expr(expr, slots)?;
+                let s = value.term.string()?;
+                Value {
+                    term: Term::Bool(format!("(str.in_re {s} {})", regex(pattern))),
+                    err: value.err,
+                }
+            }
+            ExprKind::Is { expr, entity_type } => {
+                let value = self.expr(expr, slots)?;
+                let Term::Entity(ty, _) = value.term else {
+                    return Err("an ill-typed expression");
+                };
+                let is = ty == EntityType::Specified(entity_type.clone());
+                Value {
+                    term: Term::Bool(is.to_string()),
+                    err: value.err,
+                }
+            }
+            ExprKind::Set(elements) => {
+                let values = elements
+                    .iter()
+                    .map(|e| self.expr(e, slots))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let err = or(values.iter().map(|value| value.err.clone()));
+                Value {
+                    term: Term::Set(values),
+                    err,
+                }
+            }
+            ExprKind::Record(_) => return Err("record literals"),
+        })
+    }
+
+    fn literal(&mut self, lit: &Literal) -> Term {
+        match lit {
+            Literal::Bool(b) => Term::Bool(b.to_string()),
+            Literal::Long(i) => Term::Long(int(*i)),
+            Literal::String(s) => Term::String(string_literal(s)),
+            Literal::EntityUID(uid) => self.entity(uid),
+        }
+    }
+
+    fn entity(&mut self, uid: &ast::EntityUID) -> Term {
+        if !uid.is_action() {
+            self.literals.insert(uid.clone());
+        }
+        Term::Entity(
+            uid.entity_type().clone(),
+            string_literal(uid.eid().as_ref()),
+        )
+    }
+
+    /// The schema of entities of type `ty`, and the path to their attributes
+    /// from an entity with the id `id`
+    fn entity_attrs(
+        &self,
+        ty: &EntityType,
+        id: String,
+    ) -> Result<(Path, &'a ValidatorEntityType), &'static str> {
+        let EntityType::Specified(name) = ty else {
+            return Err("attributes of an unspecified entity");
+        };
+        if is_action(name) {
+            return Err("attributes of actions");
+        }
+        let entity_type = self
+            .analysis
+            .schema
+            .get_entity_type(name)
+            .ok_or("an entity type which is not in the schema")?;
+        let path = Path {
+            owner: Owner::Entity(name.clone()),
+            id: Some(id),
+            attrs: Vec::new(),
+        };
+        Ok((path, entity_type))
+    }
+
+    fn get_attr(&mut self, term: Term, attr: &SmolStr) -> Result<Value, &'static str> {
+        let (path, ty) = match term {
+            Term::Entity(ty, id) => {
+                let (path, entity_type) = self.entity_attrs(&ty, id)?;
+                (path, entity_type.attr(attr).cloned())
+            }
+            Term::Record(path, mut attrs) => (path, attrs.attrs.remove(attr)),
+            _ => return Err("an ill-typed expression"),
+        };
+        let ty = ty.ok_or("attributes which are not in the schema")?;
+        self.attr(path, attr, &ty)
+    }
+
+    /// The value of the attribute `attr`, of type `ty`, of the value at
+    /// `path`
+    fn attr(
+        &mut self,
+        mut path: Path,
+        attr: &SmolStr,
+        ty: &AttributeType,
+    ) -> Result<Value, &'static str> {
+        path.attrs.push(attr.clone());
+        let shape = shape(&ty.attr_type)?;
+        let err = if ty.is_required {
+            "false".into()
+        } else {
+            not(&self.has(&path, shape.clone()))
+        };
+        let term = match shape {
+            Shape::Leaf(leaf) => {
+                let name = self.symbol(Symbol::Attr(
+                    path.owner.clone(),
+                    path.attrs.clone(),
+                    leaf.clone(),
+                ));
+                let value = apply(&name, path.id.iter());
+                match leaf {
+                    Leaf::Bool => Term::Bool(value),
+                    Leaf::Long => Term::Long(value),
+                    Leaf::String => Term::String(value),
+                    Leaf::Entity(name) => Term::Entity(EntityType::Specified(name), value),
+                }
+            }
+            Shape::Record => match &ty.attr_type {
+                Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) => {
+                    Term::Record(path, attrs.clone())
+                }
+                _ => return Err("an ill-typed expression"),
+            },
+            Shape::Set => return Err("attributes of set type"),
+        };
+        Ok(Value { term, err })
+    }
+
+    fn has_attr(&mut self, term: Term, attr: &SmolStr) -> Result<String, &'static str> {
+        let (mut path, ty) = match term {
+            Term::Entity(ty, id) => {
+                let (path, entity_type) = self.entity_attrs(&ty, id)?;
+                (path, entity_type.attr(attr).cloned())
+            }
+            Term::Record(path, mut attrs) => (path, attrs.attrs.remove(attr)),
+            _ => return Err("an ill-typed expression"),
+        };
+        Ok(match ty {
+            None => "false".into(),
+            Some(ty) if ty.is_required => "true".into(),
+            Some(ty) => {
+                path.attrs.push(attr.clone());
+                self.has(&path, shape(&ty.attr_type)?)
+            }
+        })
+    }
+
+    /// The term which is true iff the optional attribute at `path` is present
+    fn has(&mut self, path: &Path, shape: Shape) -> String {
+        let name = self.symbol(Symbol::Has(path.owner.clone(), path.attrs.clone(), shape));
+        apply(&name, path.id.iter())
+    }
+
+    fn is_in(&mut self, a: Term, b: Term) -> Result<String, &'static str> {
+        let Term::Entity(ty, id) = a else {
+            return Err("an ill-typed expression");
+        };
+        match b {
+            Term::Entity(other_ty, other) => Ok(self.in_entity(&ty, &id, &other_ty, &other)),
+            Term::Set(values) => {
+                let mut ins = Vec::new();
+                for value in values {
+                    let Term::Entity(other_ty, other) = value.term else {
+                        return Err("an ill-typed expression");
+                    };
+                    ins.push(self.in_entity(&ty, &id, &other_ty, &other));
+                }
+                Ok(or(ins))
+            }
+            _ => Err("`in` a set which is not a set literal"),
+        }
+    }
+
+    fn in_entity(
+        &mut self,
+        ty: &EntityType,
+        id: &str,
+        other_ty: &EntityType,
+        other: &str,
+    ) -> String {
+        let same = if ty == other_ty {
+            format!("(= {id} {other})")
+        } else {
+            "false".into()
+        };
+        let descendant = match (ty, other_ty) {
+            (EntityType::Specified(child), EntityType::Specified(parent))
+                if self.analysis.may_be_in(child, parent) =>
+            {
+                let name = self.symbol(Symbol::In(child.clone(), parent.clone()));
+                format!("({name} {id} {other})")
+            }
+            _ => "false".into(),
+        };
+        or([same, descendant])
+    }
+
+    /// The SMT-LIB 2 declarations of the symbols, and their axioms
+    fn declarations(&self) -> String {
+        let mut script = String::from("(set-option :produce-models true)\n(set-logic ALL)\n");
+        for (index, symbol) in self.symbols.iter().enumerate() {
+            let name = format!("s{index}");
+            let declaration = match symbol {
+                Symbol::Principal | Symbol::Resource => format!("(declare-const {name} String)"),
+                Symbol::Attr(Owner::Context, _, leaf) => {
+                    format!("(declare-const {name} {})", sort(leaf))
+                }
+                Symbol::Attr(Owner::Entity(_), _, leaf) => {
+                    format!("(declare-fun {name} (String) {})", sort(leaf))
+                }
+                Symbol::Has(Owner::Context, _, _) => format!("(declare-const {name} Bool)"),
+                Symbol::Has(Owner::Entity(_), _, _) => {
+                    format!("(declare-fun {name} (String) Bool)")
+                }
+                Symbol::In(child, parent) if is_action(child) => {
+                    // the hierarchy of actions is given by the schema
+                    let child = EntityType::Specified(child.clone());
+                    let parent = &EntityType::Specified(parent.clone());
+                    let pairs = self
+                        .analysis
+                        .actions
+                        .iter()
+                        .filter(|action| action.uid().entity_type() == &child)
+                        .flat_map(move |action| {
+                            let id = string_literal(action.uid().eid().as_ref());
+                            action
+                                .ancestors()
+                                .filter(move |ancestor| ancestor.entity_type() == parent)
+                                .map(move |ancestor| {
+                                    let other = string_literal(ancestor.eid().as_ref());
+                                    format!("(and (= x {id}) (= y {other}))")
+                                })
+                        });
+                    format!(
+                        "(define-fun {name} ((x String) (y String)) Bool {})",
+                        or(pairs)
+                    )
+                }
+                Symbol::In(_, _) => format!("(declare-fun {name} (String String) Bool)"),
+            };
+            script.push_str(&declaration);
+            script.push('\n');
+            match symbol {
+                Symbol::Attr(Owner::Context, _, Leaf::Long) => {
+                    script.push_str(&format!("(assert {})\n", in_range(&name)));
+                }
+                Symbol::Attr(Owner::Entity(_), _, Leaf::Long) => {
+                    script.push_str(&format!(
+                        "(assert (forall ((x String)) {}))\n",
+                        in_range(&format!("({name} x)"))
+                    ));
+                }
+                _ => (),
+            }
+        }
+        script.push_str(&self.transitivity());
+        script
+    }
+
+    /// The axioms making the entity hierarchy transitive
+    fn transitivity(&self) -> String {
+        let relations: BTreeMap<(&Name, &Name), &String> = self
+            .names
+            .iter()
+            .filter_map(|(symbol, name)| match symbol {
+                Symbol::In(child, parent) if !is_action(child) => Some(((child, parent), name)),
+                _ => None,
+            })
+            .collect();
+        let mut axioms = String::new();
+        for ((a, b), ab) in &relations {
+            for ((_, c), bc) in relations.iter().filter(|((other, _), _)| other == b) {
+                if let Some(ac) = relations.get(&(*a, *c)) {
+                    axioms.push_str(&format!(
+                        "(assert (forall ((x String) (y String) (z String)) \
+                         (=> (and ({ab} x y) ({bc} y z)) ({ac} x z))))\n"
+                    ));
+                }
+            }
+        }
+        axioms
+    }
+
+    /// Run `solver` on `query`, and return a counterexample if it's
+    /// satisfiable
+    fn solve(&self, query: &str, solver: &Solver) -> Result<Option<Counterexample>, AnalysisError> {
+        let mut process = Process::spawn(solver)?;
+        process.send(&self.declarations())?;
+        process.send(&format!("(assert {query})\n"))?;
+        if process.check_sat()? {
+            Model::new(self, &mut process).counterexample().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// The value of `op`, which errors on overflow, if `err` is false
+fn arithmetic(op: String, err: String) -> Value {
+    Value {
+        err: or([err, not(&in_range(&op))]),
+        term: Term::Long(op),
+    }
+}
+
+fn in_range(term: &str) -> String {
+    format!("(and (<= {MIN} {term}) (<= {term} {MAX}))")
+}
+
+fn eq(a: &Term, b: &Term) -> Result<String, &'static str> {
+    match (a, b) {
+        (Term::Bool(a), Term::Bool(b))
+        | (Term::Long(a), Term::Long(b))
+        | (Term::String(a), Term::String(b)) => Ok(format!("(= {a} {b})")),
+        (Term::Entity(ty, a), Term::Entity(other, b)) => Ok(if ty == other {
+            format!("(= {a} {b})")
+        } else {
+            "false".into()
+        }),
+        (Term::Record(..) | Term::Set(_), _) | (_, Term::Record(..) | Term::Set(_)) => {
+            Err("`==` on records or sets")
+        }
+        // values of different types are never equal
+        _ => Ok("false".into()),
+    }
+}
+
+/// The term which is true iff `term` is in the set literal `set`
+fn contains(set: &[Value], term: &Term) -> Result<String, &'static str> {
+    let eqs = set
+        .iter()
+        .map(|value| eq(&value.term, term))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(or(eqs))
+}
+
+fn and(terms: impl IntoIterator<Item = String>) -> String {
+    connective("and", "true", "false", terms)
+}
+
+fn or(terms: impl IntoIterator<Item = String>) -> String {
+    connective("or", "false", "true", terms)
+}
+
+/// Apply `op` to `terms`, simplifying its `unit` and `zero`
+fn connective(op: &str, unit: &str, zero: &str, terms: impl IntoIterator<Item = String>) -> String {
+    let mut terms: Vec<String> = terms.into_iter().filter(|term| term != unit).collect();
+    if terms.iter().any(|term| term == zero) {
+        return zero.into();
+    }
+    match terms.len() {
+        0 => unit.into(),
+        1 => terms.pop().unwrap_or_default(),
+        _ => format!("({op} {})", terms.join(" ")),
+    }
+}
+
+fn not(term: &str) -> String {
+    match term {
+        "true" => "false".into(),
+        "false" => "true".into(),
+        _ => format!("(not {term})"),
+    }
+}
+
+/// Apply the function `name` to `args`, or `name` itself if there are none
+fn apply<'s>(name: &str, args: impl Iterator<Item = &'s String>) -> String {
+    let args: Vec<&str> = args.map(String::as_str).collect();
+    if args.is_empty() {
+        name.into()
+    } else {
+        format!("({name} {})", args.join(" "))
+    }
+}
+
+fn sort(leaf: &Leaf) -> &'static str {
+    match leaf {
+        Leaf::Bool => "Bool",
+        Leaf::Long => "Int",
+        Leaf::String | Leaf::Entity(_) => "String",
+    }
+}
+
+fn shape(ty: &Type) -> Result<Shape, &'static str> {
+    Ok(match ty {
+        Type::True
+        | Type::False
+        | Type::Primitive {
+            primitive_type: Primitive::Bool,
+        } => Shape::Leaf(Leaf::Bool),
+        Type::Primitive {
+            primitive_type: Primitive::Long,
+        } => Shape::Leaf(Leaf::Long),
+        Type::Primitive {
+            primitive_type: Primitive::String,
+        } => Shape::Leaf(Leaf::String),
+        Type::EntityOrRecord(EntityRecordKind::Entity(lub)) => {
+            let name = lub
+                .get_single_entity()
+                .ok_or("attributes of more than one entity type")?;
+            Shape::Leaf(Leaf::Entity(name.clone()))
+        }
+        Type::EntityOrRecord(EntityRecordKind::Record { .. }) => Shape::Record,
+        Type::Set { .. } => Shape::Set,
+        Type::ExtensionType { .. } => return Err("attributes of extension types"),
+        _ => return Err("attributes of an unknown type"),
+    })
+}
+
+fn int(i: i64) -> String {
+    if i < 0 {
+        format!("(- {})", i.unsigned_abs())
+    } else {
+        i.to_string()
+    }
+}
+
+/// An SMT-LIB 2 string literal of `s`
+fn string_literal(s: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => literal.push_str("\"\""),
+            // a backslash may start an escape sequence
+            ' '..='~' if c != '\\' => literal.push(c),
+            _ => literal.push_str(&format!("\\u{{{:x}}}", u32::from(c))),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// A regular expression of the strings matching `pattern`
+fn regex(pattern: &Pattern) -> String {
+    let mut parts = Vec::new();
+    let mut chars = String::new();
+    for elem in pattern.iter() {
+        match elem {
+            PatternElem::Char(c) => chars.push(*c),
+            PatternElem::Wildcard => {
+                if !chars.is_empty() {
+                    parts.push(format!("(str.to_re {})", string_literal(&chars)));
+                    chars.clear();
+                }
+                parts.push("re.all".into());
+            }
+        }
+    }
+    if !chars.is_empty() || parts.is_empty() {
+        parts.push(format!("(str.to_re {})", string_literal(&chars)));
+    }
+    match parts.len() {
+        1 => parts.pop().unwrap_or_default(),
+        _ => format!("(re.++ {})", parts.join(" ")),
+    }
+}
+
+/// A running solver
+struct Process {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Process {
+    fn spawn(solver: &Solver) -> io::Result<Self> {
+        let mut child = Command::new(&solver.program)
+            .args(&solver.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        match (child.stdin.take(), child.stdout.take()) {
+            (Some(stdin), Some(stdout)) => Ok(Self {
+                child,
+                stdin,
+                stdout: BufReader::new(stdout),
+            }),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "failed to open the standard input and output of the solver",
+            )),
+        }
+    }
+
+    fn send(&mut self, commands: &str) -> io::Result<()> {
+        self.stdin.write_all(commands.as_bytes())?;
+        self.stdin.flush()
+    }
+
+    /// Read one response
+    fn read(&mut self) -> Result<Sexp, AnalysisError> {
+        let mut response = String::new();
+        loop {
+            if self.stdout.read_line(&mut response)? == 0 {
+                return Err(AnalysisError::Response(format!(
+                    "the solver exited: {response}"
+                )));
+            }
+            match Sexp::parse(&response) {
+                Ok(Some(Sexp::List(list))) if list.first() == Some(&Sexp::atom("error")) => {
+                    let message = list.get(1).map(ToString::to_string).unwrap_or_default();
+                    return Err(AnalysisError::Response(message));
+                }
+                Ok(Some(sexp)) => return Ok(sexp),
+                // the response continues on the next line
+                Ok(None) => (),
+                Err(()) => return Err(AnalysisError::Response(response)),
+            }
+        }
+    }
+
+    /// Whether the assertions are satisfiable
+    fn check_sat(&mut self) -> Result<bool, AnalysisError> {
+        self.send("(check-sat)\n")?;
+        match self.read()? {
+            Sexp::Atom(response) if response == "sat" => Ok(true),
+            Sexp::Atom(response) if response == "unsat" => Ok(false),
+            Sexp::Atom(response) if response == "unknown" => Err(AnalysisError::Unknown),
+            response => Err(AnalysisError::Response(response.to_string())),
+        }
+    }
+
+    /// The values of `terms` in the model
+    fn values(&mut self, terms: &[String]) -> Result<Vec<Sexp>, AnalysisError> {
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.send(&format!("(get-value ({}))\n", terms.join(" ")))?;
+        let response = self.read()?;
+        let pairs = match response {
+            Sexp::List(pairs) if pairs.len() == terms.len() => pairs,
+            response => return Err(AnalysisError::Response(response.to_string())),
+        };
+        pairs
+            .into_iter()
+            .map(|pair| match pair {
+                Sexp::List(pair) if pair.len() == 2 => {
+                    Ok(pair.into_iter().nth(1).unwrap_or(Sexp::List(Vec::new())))
+                }
+                pair => Err(AnalysisError::Response(pair.to_string())),
+            })
+            .collect()
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        // the solver may still be running if we stopped reading its responses
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// An s-expression of a response of the solver
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Sexp {
+    Atom(String),
+    /// A string literal, unescaped
+    Str(String),
+    List(Vec<Sexp>),
+}
+
+impl Sexp {
+    fn atom(s: &str) -> Self {
+        Self::Atom(s.into())
+    }
+
+    /// Parse the first s-expression of `s`. `Ok(None)` if it's incomplete.
+    fn parse(s: &str) -> Result<Option<Self>, ()> {
+        /// Add `sexp` to the innermost list, or return it if there is none
+        fn push(stack: &mut [Vec<Sexp>], sexp: Sexp) -> Option<Sexp> {
+            match stack.last_mut() {
+                Some(list) => {
+                    list.push(sexp);
+                    None
+                }
+                None => Some(sexp),
+            }
+        }
+        let mut stack: Vec<Vec<Sexp>> = Vec::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            let sexp = match c {
+                '(' => {
+                    stack.push(Vec::new());
+                    continue;
+                }
+                ')' => Sexp::List(stack.pop().ok_or(())?),
+                '"' => {
+                    let mut raw = String::new();
+                    loop {
+                        match chars.next() {
+                            None => return Ok(None),
+                            Some('"') if chars.peek() == Some(&'"') => {
+                                chars.next();
+                                raw.push('"');
+                            }
+                            Some('"') => break,
+                            Some(c) => raw.push(c),
+                        }
+                    }
+                    Sexp::Str(unescape(&raw))
+                }
+                c if c.is_whitespace() => continue,
+                c => {
+                    let mut atom = String::from(c);
+                    while let Some(c) =
+                        chars.next_if(|c| !c.is_whitespace() && !"()\"".contains(*c))
+                    {
+                        atom.push(c);
+                    }
+                    Sexp::Atom(atom)
+                }
+            };
+            if let Some(sexp) = push(&mut stack, sexp) {
+                return Ok(Some(sexp));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Display for Sexp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Atom(atom) => write!(f, "{atom}"),
+            Self::Str(s) => write!(f, "{s:?}"),
+            Self::List(list) => {
+                write!(f, "(")?;
+                for (index, sexp) in list.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{sexp}")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// Unescape the `\u{..}` and `\u....` escape sequences of an SMT-LIB 2
+/// string literal
+fn unescape(raw: &str) -> String {
+    let mut s = String::new();
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' && chars.peek() == Some(&'u') {
+            let mut rest = chars.clone();
+            rest.next();
+            let hex: String = if rest.next_if_eq(&'{').is_some() {
+                rest.by_ref().take_while(|c| *c != '}').collect()
+            } else {
+                rest.by_ref().take(4).collect()
+            };
+            if let Some(c) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                s.push(c);
+                chars = rest;
+                continue;
+            }
+        }
+        s.push(c);
+    }
+    s
+}
+
+/// The bound on the rounds of reading entity-valued attributes of the
+/// entities found so far, to find the entities of a counterexample
+const MAX_ROUNDS: usize = 8;
+
+/// Reads a counterexample from the model of the solver
+struct Model<'e, 'p> {
+    encoder: &'e Encoder<'e>,
+    process: &'p mut Process,
+    /// The ids of the entities of the counterexample, per entity type
+    entities: BTreeMap<Name, BTreeSet<String>>,
+}
+
+impl<'e, 'p> Model<'e, 'p> {
+    fn new(encoder: &'e Encoder<'e>, process: &'p mut Process) -> Self {
+        Self {
+            encoder,
+            process,
+            entities: BTreeMap::new(),
+        }
+    }
+
+    fn counterexample(mut self) -> Result<Counterexample, AnalysisError> {
+        let principal = self.id(Symbol::Principal, "principal")?;
+        let resource = self.id(Symbol::Resource, "resource")?;
+        let encoder = self.encoder;
+        let key = encoder.key;
+        self.add_entity(&key.principal, principal.clone());
+        self.add_entity(&key.resource, resource.clone());
+        for uid in &encoder.literals {
+            self.add_entity(
+                uid.entity_type(),
+                AsRef::<str>::as_ref(uid.eid()).to_owned(),
+            );
+        }
+        let context = self.fields(&Owner::Context, None)?;
+        self.find_entities()?;
+
+        let mut entities = Vec::new();
+        for (name, ids) in self.entities.clone() {
+            for id in ids {
+                let uid = entity_uid(&name, &id);
+                let attrs = self.fields(&Owner::Entity(name.clone()), Some(&id))?;
+                let attrs = attrs.into_pairs()?.into_iter().collect();
+                let parents = self.parents(&name, &id)?;
+                let entity = Entity::new(uid, attrs, parents)
+                    .map_err(|e| AnalysisError::Model(e.to_string()))?;
+                entities.push(entity);
+            }
+        }
+        for action in &encoder.analysis.actions {
+            let parents = action
+                .ancestors()
+                .map(|uid| EntityUid::ref_cast(uid).clone())
+                .collect();
+            entities.push(Entity::new_no_attrs(
+                EntityUid::ref_cast(&action.uid()).clone(),
+                parents,
+            ));
+        }
+        let entities = Entities::from_entities(entities, None)?;
+
+        let context = Context::from_pairs(context.into_pairs()?)
+            .map_err(|e| AnalysisError::Model(e.to_string()))?;
+        let uid = |ty: &EntityType, id: &str| match ty {
+            EntityType::Specified(name) => Some(entity_uid(name, id)),
+            EntityType::Unspecified => None,
+        };
+        let request = Request::new(
+            uid(&key.principal, &principal),
+            Some(EntityUid::ref_cast(&key.action).clone()),
+            uid(&key.resource, &resource),
+            context,
+            None,
+        )
+        .map_err(|e| AnalysisError::Model(e.to_string()))?;
+        Ok(Counterexample { request, entities })
+    }
+
+    /// The id which is the value of `symbol`, or `default` if the policies
+    /// don't read it
+    fn id(&mut self, symbol: Symbol, default: &str) -> Result<String, AnalysisError> {
+        let Some(name) = self.encoder.names.get(&symbol) else {
+            return Ok(default.into());
+        };
+        match self.process.values(&[name.clone()])?.pop() {
+            Some(Sexp::Str(id)) => Ok(id),
+            value => Err(unexpected(value)),
+        }
+    }
+
+    fn add_entity(&mut self, ty: &EntityType, id: String) {
+        if let EntityType::Specified(name) = ty {
+            if !is_action(name) {
+                self.entities.entry(name.clone()).or_default().insert(id);
+            }
+        }
+    }
+
+    /// Add the entities which are the values of entity-valued attributes of
+    /// the entities found so far
+    fn find_entities(&mut self) -> Result<(), AnalysisError> {
+        let mut read = HashSet::new();
+        for _ in 0..MAX_ROUNDS {
+            let mut terms = Vec::new();
+            let mut types = Vec::new();
+            for (symbol, name) in &self.encoder.names {
+                let Symbol::Attr(Owner::Entity(owner), _, Leaf::Entity(ty)) = symbol else {
+                    continue;
+                };
+                for id in self.entities.get(owner).into_iter().flatten() {
+                    let term = format!("({name} {})", string_literal(id));
+                    if read.insert(term.clone()) {
+                        terms.push(term);
+                        types.push(ty.clone());
+                    }
+                }
+            }
+            if terms.is_empty() {
+                break;
+            }
+            for (ty, value) in types.iter().zip(self.process.values(&terms)?) {
+                match value {
+                    Sexp::Str(id) => self.add_entity(&EntityType::Specified(ty.clone()), id),
+                    value => return Err(unexpected(Some(value))),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The attributes of the context or of the entity with the id `id`
+    fn fields(&mut self, owner: &Owner, id: Option<&str>) -> Result<Fields, AnalysisError> {
+        let apply = |name: &str| match id {
+            Some(id) => format!("({name} {})", string_literal(id)),
+            None => name.to_string(),
+        };
+        let encoder = self.encoder;
+        let mut leaves = Vec::new();
+        let mut has = Vec::new();
+        for (index, symbol) in encoder.symbols.iter().enumerate() {
+            match symbol {
+                Symbol::Attr(o, path, leaf) if o == owner => {
+                    leaves.push((path, leaf, apply(&format!("s{index}"))));
+                }
+                Symbol::Has(o, path, shape) if o == owner => {
+                    has.push((path, shape, apply(&format!("s{index}"))));
+                }
+                _ => (),
+            }
+        }
+        let has_terms: Vec<String> = has.iter().map(|(_, _, term)| term.clone()).collect();
+        let mut present = Vec::new();
+        let mut absent = Vec::new();
+        for ((path, shape, _), value) in has.into_iter().zip(self.process.values(&has_terms)?) {
+            match value {
+                Sexp::Atom(b) if b == "true" => present.push((path, shape)),
+                Sexp::Atom(b) if b == "false" => absent.push(path),
+                value => return Err(unexpected(Some(value))),
+            }
+        }
+        let is_absent = |path: &[SmolStr]| {
+            absent
+                .iter()
+                .any(|absent| path.starts_with(absent.as_slice()))
+        };
+
+        let leaf_terms: Vec<String> = leaves.iter().map(|(_, _, term)| term.clone()).collect();
+        let mut fields = Fields::default();
+        for ((path, leaf, _), value) in leaves.into_iter().zip(self.process.values(&leaf_terms)?) {
+            if is_absent(path) {
+                continue;
+            }
+            // an entity referenced from the context is in the counterexample
+            if let (Leaf::Entity(name), Sexp::Str(id), Owner::Context) = (leaf, &value, owner) {
+                self.add_entity(&EntityType::Specified(name.clone()), id.clone());
+            }
+            fields.insert(path, Field::Value(decode(leaf, value)?));
+        }
+        // an attribute which is only checked with `has` has any value
+        for (path, shape) in present {
+            if !is_absent(path) {
+                fields.insert(path, placeholder(shape));
+            }
+        }
+        Ok(fields)
+    }
+
+    /// The parents of the entity with the id `id` of type `name`
+    fn parents(&mut self, name: &Name, id: &str) -> Result<HashSet<EntityUid>, AnalysisError> {
+        let mut terms = Vec::new();
+        let mut uids = Vec::new();
+        for (symbol, relation) in &self.encoder.names {
+            let Symbol::In(child, parent) = symbol else {
+                continue;
+            };
+            if child != name {
+                continue;
+            }
+            for other in self.entities.get(parent).into_iter().flatten() {
+                if parent == name && other == id {
+                    continue;
+                }
+                terms.push(format!(
+                    "({relation} {} {})",
+                    string_literal(id),
+                    string_literal(other)
+                ));
+                uids.push(entity_uid(parent, other));
+            }
+        }
+        let mut parents = HashSet::new();
+        for (uid, value) in uids.into_iter().zip(self.process.values(&terms)?) {
+            match value {
+                Sexp::Atom(b) if b == "true" => {
+                    parents.insert(uid);
+                }
+                Sexp::Atom(b) if b == "false" => (),
+                value => return Err(unexpected(Some(value))),
+            }
+        }
+        Ok(parents)
+    }
+}
+
+fn unexpected(value: Option<Sexp>) -> AnalysisError {
+    AnalysisError::Response(value.map(|value| value.to_string()).unwrap_or_default())
+}
+
+fn entity_uid(name: &Name, id: &str) -> EntityUid {
+    EntityUid::ref_cast(&ast::EntityUID::from_components(
+        name.clone(),
+        ast::Eid::new(id),
+    ))
+    .clone()
+}
+
+/// The Cedar value of `value`, of type `leaf`, in the model
+fn decode(leaf: &Leaf, value: Sexp) -> Result<RestrictedExpression, AnalysisError> {
+    match (leaf, value) {
+        (Leaf::Bool, Sexp::Atom(b)) if b == "true" => Ok(RestrictedExpression::new_bool(true)),
+        (Leaf::Bool, Sexp::Atom(b)) if b == "false" => Ok(RestrictedExpression::new_bool(false)),
+        (Leaf::Long, Sexp::Atom(i)) => i
+            .parse()
+            .map(RestrictedExpression::new_long)
+            .map_err(|_| AnalysisError::Response(i)),
+        (Leaf::Long, Sexp::List(list)) => {
+            if let [Sexp::Atom(minus), Sexp::Atom(i)] = list.as_slice() {
+                if minus == "-" {
+                    return format!("-{i}")
+                        .parse()
+                        .map(RestrictedExpression::new_long)
+                        .map_err(|_| AnalysisError::Response(i.clone()));
+                }
+            }
+            Err(unexpected(Some(Sexp::List(list))))
+        }
+        (Leaf::String, Sexp::Str(s)) => Ok(RestrictedExpression::new_string(s)),
+        (Leaf::Entity(name), Sexp::Str(id)) => {
+            Ok(RestrictedExpression::new_entity_uid(entity_uid(name, &id)))
+        }
+        (_, value) => Err(unexpected(Some(value))),
+    }
+}
+
+/// Some value of an attribute of the shape `shape`
+fn placeholder(shape: &Shape) -> Field {
+    match shape {
+        Shape::Leaf(Leaf::Bool) => Field::Value(RestrictedExpression::new_bool(false)),
+        Shape::Leaf(Leaf::Long) => Field::Value(RestrictedExpression::new_long(0)),
+        Shape::Leaf(Leaf::String) => Field::Value(RestrictedExpression::new_string(String::new())),
+        Shape::Leaf(Leaf::Entity(name)) => {
+            Field::Value(RestrictedExpression::new_entity_uid(entity_uid(name, "")))
+        }
+        Shape::Record => Field::Record(Fields::default()),
+        Shape::Set => Field::Value(RestrictedExpression::new_set([])),
+    }
+}
+
+/// The attributes of a record in a counterexample
+#[derive(Debug, Default)]
+struct Fields(BTreeMap<SmolStr, Field>);
+
+#[derive(Debug)]
+enum Field {
+    Value(RestrictedExpression),
+    Record(Fields),
+}
+
+impl Fields {
+    /// Insert `field` at `path`, unless there is already a value there
+    fn insert(&mut self, path: &[SmolStr], field: Field) {
+        let Some((attr, rest)) = path.split_first() else {
+            return;
+        };
+        if rest.is_empty() {
+            self.0.entry(attr.clone()).or_insert(field);
+            return;
+        }
+        let record = self
+            .0
+            .entry(attr.clone())
+            .or_insert_with(|| Field::Record(Fields::default()));
+        if let Field::Record(record) = record {
+            record.insert(rest, field);
+        }
+    }
+
+    fn into_pairs(self) -> Result<Vec<(String, RestrictedExpression)>, AnalysisError> {
+        self.0
+            .into_iter()
+            .map(|(attr, field)| {
+                let value = match field {
+                    Field::Value(value) => value,
+                    Field::Record(record) => RestrictedExpression::new_record(record.into_pairs()?)
+                        .map_err(|e| AnalysisError::Model(e.to_string()))?,
+                };
+                Ok((attr.to_string(), value))
+            })
+            .collect()
+    }
+}
+
+// PANIC SAFETY unit tests
+#[allow(clippy::panic)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Authorizer;
+    use cool_asserts::assert_matches;
+    use std::str::FromStr;
+
+    fn schema() -> Schema {
+        Schema::from_json_value(serde_json::json!({ "": {
+            "entityTypes": {
+                "User": {
+                    "memberOfTypes": ["Group"],
+                    "shape": {
+                        "type": "Record",
+                        "attributes": {
+                            "age": { "type": "Long" },
+                            "name": { "type": "String", "required": false }
+                        }
+                    }
+                },
+                "Group": {},
+                "Photo": {
+                    "shape": {
+                        "type": "Record",
+                        "attributes": {
+                            "owner": { "type": "Entity", "name": "User" }
+                        }
+                    }
+                }
+            },
+            "actions": {
+                "view": {
+                    "appliesTo": {
+                        "principalTypes": ["User"],
+                        "resourceTypes": ["Photo"],
+                        "context": {
+                            "type": "Record",
+                            "attributes": { "ip": { "type": "String" } }
+                        }
+                    }
+                }
+            }
+        }}))
+        .expect("schema should be valid")
+    }
+
+    /// `z3`, which must be installed. The tests which run a solver are ignored
+    /// by default, and run with `cargo test -- --ignored`.
+    fn z3() -> Solver {
+        assert!(
+            Command::new("z3").arg("-version").output().is_ok(),
+            "z3 must be installed to run the tests which run a solver"
+        );
+        Solver::z3()
+    }
+
+    /// A solver which doesn't exist, for tests which fail before running it
+    fn no_solver() -> Solver {
+        Solver::new("no-such-solver", Vec::<String>::new())
+    }
+
+    fn policy(src: &str) -> Policy {
+        Policy::parse(None, src).expect("policy should parse")
+    }
+
+    #[test]
+    fn string_literals() {
+        assert_eq!(string_literal("ab"), r#""ab""#);
+        assert_eq!(string_literal("a\"b"), r#""a""b""#);
+        assert_eq!(string_literal("\\é\n"), r#""\u{5c}\u{e9}\u{a}""#);
+        for s in ["", "a\"b", "\\u{41}", "é\u{1F600}\t"] {
+            assert_eq!(
+                Sexp::parse(&string_literal(s)),
+                Ok(Some(Sexp::Str(s.to_string())))
+            );
+        }
+        assert_eq!(unescape(r"\u0041\u{42}\u{zz}"), r"AB\u{zz}");
+    }
+
+    #[test]
+    fn sexps() {
+        assert_eq!(Sexp::parse("sat\n"), Ok(Some(Sexp::atom("sat"))));
+        assert_eq!(
+            Sexp::parse("((s0 \"a\")\n (s1 (- 5)))\n"),
+            Ok(Some(Sexp::List(vec![
+                Sexp::List(vec![Sexp::atom("s0"), Sexp::Str("a".into())]),
+                Sexp::List(vec![
+                    Sexp::atom("s1"),
+                    Sexp::List(vec![Sexp::atom("-"), Sexp::atom("5")])
+                ]),
+            ])))
+        );
+        assert_eq!(Sexp::parse("((s0 \"a"), Ok(None));
+        assert_eq!(Sexp::parse("((s0 1)\n"), Ok(None));
+        assert_eq!(Sexp::parse(")"), Err(()));
+    }
+
+    #[test]
+    fn encoding() {
+        let schema = schema();
+        let mut analysis = Analysis::new(&schema).unwrap();
+        let policy = policy(
+            r#"permit(principal in Group::"admins", action, resource)
+            when { principal has name && principal.name like "a*b" && principal.age + 1 > 18 && principal.name < "m" };"#,
+        );
+        let typed = analysis.typecheck(&policy.ast).unwrap();
+        let key = typed.conditions.keys().next().unwrap().clone();
+        let mut encoder = Encoder::new(&analysis, &key);
+        let decision = encoder.decision(std::slice::from_ref(&typed)).unwrap();
+        let script = encoder.declarations() + &decision;
+        assert!(script.contains("(declare-fun s1 (String String) Bool)"));
+        assert!(script.contains(r#"(s1 s0 "admins")"#));
+        assert!(script
+            .contains(r#"(str.in_re (s3 s0) (re.++ (str.to_re "a") re.all (str.to_re "b")))"#));
+        assert!(script.contains(r#"(str.< (s3 s0) "m")"#));
+        // reading the optional `name` is an error if it's missing
+        assert!(script.contains("(not (s2 s0))"));
+        // as is overflow
+        assert!(script.contains(&format!("(not {})", in_range("(+ (s4 s0) 1)"))));
+        assert!(script.contains(&format!(
+            "(assert (forall ((x String)) {}))",
+            in_range("(s4 x)")
+        )));
+        assert_eq!(encoder.literals.len(), 1);
+    }
+
+    #[test]
+    fn unsupported() {
+        let schema = schema();
+        let first = policy(r#"permit(principal, action, resource);"#);
+        let second = policy(
+            r#"permit(principal, action, resource)
+            when { decimal("1.0").lessThan(decimal("2.0")) };"#,
+        );
+        assert_matches!(
+            check_policy_equivalence(&first, &second, &schema, &no_solver()),
+            Err(AnalysisError::Unsupported {
+                feature: "extension functions",
+                ..
+            })
+        );
+        let second =
+            policy(r#"permit(principal, action, resource) when { context == { ip: "" } };"#);
+        assert_matches!(
+            check_policy_equivalence(&first, &second, &schema, &no_solver()),
+            Err(AnalysisError::Unsupported { .. })
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let schema = schema();
+        let first = policy(r#"permit(principal, action, resource);"#);
+        let second = policy(r#"permit(principal, action, resource) when { principal.typo };"#);
+        assert_matches!(
+            check_policy_equivalence(&first, &second, &schema, &no_solver()),
+            Err(AnalysisError::Validation(id)) => assert_eq!(id.as_ref(), "policy0")
+        );
+    }
+
+    #[test]
+    #[ignore = "needs z3"]
+    fn equivalent_policies() {
+        let solver = z3();
+        let schema = schema();
+        let first = policy(r#"permit(principal == User::"alice", action, resource);"#);
+        let second = policy(
+            r#"permit(principal, action, resource)
+            when { principal == User::"alice" || principal.age < principal.age };"#,
+        );
+        assert_matches!(
+            check_policy_equivalence(&first, &second, &schema, &solver),
+            Ok(Equivalence::Equivalent)
+        );
+        // `forbid` policies which are never satisfied are equivalent to `permit` ones
+        let first = policy(r#"permit(principal, action, resource) when { 1 > 2 };"#);
+        let second =
+            policy(r#"forbid(principal, action, resource) when { principal has age && false };"#);
+        assert_matches!(
+            check_policy_equivalence(&first, &second, &schema, &solver),
+            Ok(Equivalence::Equivalent)
+        );
+    }
+
+    #[test]
+    #[ignore = "needs z3"]
+    fn template_refactoring() {
+        let solver = z3();
+        let schema = schema();
+        let before = PolicySet::from_str(
+            r#"permit(principal == User::"alice", action, resource) when { resource.owner == principal };
+            permit(principal == User::"bob", action, resource) when { resource.owner == principal };"#,
+        )
+        .unwrap();
+        let mut after = PolicySet::new();
+        after
+            .add_template(
+                Template::parse(
+                    Some("owner".into()),
+                    r#"permit(principal == ?principal, action, resource) when { resource.owner == principal };"#,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        for user in ["alice", "bob"] {
+            after
+                .link(
+                    PolicyId::from_str("owner").unwrap(),
+                    PolicyId::from_str(user).unwrap(),
+                    HashMap::from([(
+                        crate::SlotId::principal(),
+                        EntityUid::from_str(&format!(r#"User::"{user}""#)).unwrap(),
+                    )]),
+                )
+                .unwrap();
+        }
+        assert_matches!(
+            check_equivalence(&before, &after, &schema, &solver),
+            Ok(Equivalence::Equivalent)
+        );
+    }
+
+    #[test]
+    #[ignore = "needs z3"]
+    fn counterexample() {
+        let solver = z3();
+        let schema = schema();
+        let before = PolicySet::from_str(
+            r#"permit(principal in Group::"adults", action, resource)
+            when { principal.age >= 18 && context.ip like "10.*" };"#,
+        )
+        .unwrap();
+        let after = PolicySet::from_str(
+            r#"permit(principal in Group::"adults", action, resource)
+            when { principal.age > 18 && context.ip like "10.*" };"#,
+        )
+        .unwrap();
+        let counterexample = assert_matches!(
+            check_equivalence(&before, &after, &schema, &solver),
+            Ok(Equivalence::NotEquivalent(counterexample)) => counterexample
+        );
+        let authorizer = Authorizer::new();
+        let decide = |policies: &PolicySet| {
+            authorizer
+                .is_authorized(
+                    counterexample.request(),
+                    policies,
+                    counterexample.entities(),
+                )
+                .decision()
+        };
+        assert_ne!(decide(&before), decide(&after));
+    }
+
+    #[test]
+    #[ignore = "needs z3"]
+    fn redundant_policies() {
+        let solver = z3();
+        let schema = schema();
+        let policies = PolicySet::from_str(
+            r#"permit(principal == User::"alice", action, resource) when { principal.age > 18 };
+            permit(principal, action, resource) when { principal.age > 10 };
+            forbid(principal == User::"alice", action, resource);
+            permit(principal in Group::"admins", action, resource);
+            permit(principal in Group::"admins", action, resource);"#,
+        )
+        .unwrap();
+        let redundant = find_redundant_policies(&policies, &schema, &solver).unwrap();
+        let redundant: Vec<(&str, &str)> = redundant
+            .iter()
+            .map(|r| (r.policy().as_ref(), r.subsumed_by().as_ref()))
+            .collect();
+        assert_eq!(
+            redundant,
+            [
+                ("policy0", "policy1"),
+                ("policy3", "policy4"),
+                ("policy4", "policy3")
+            ]
+        );
+    }
+
+    #[test]
+    #[ignore = "needs z3"]
+    fn template_suggestions() {
+        let policies = PolicySet::from_str(
+            r#"permit(principal == User::"alice", action, resource) when { resource.owner == principal };
+            permit(principal == User::"bob", action, resource) when { resource.owner == principal };
+            permit(principal == User::"carol", action, resource) when { principal.age > 18 };
+            permit(principal in Group::"admins", action == Action::"view", resource == Photo::"a");
+            permit(principal in Group::"admins", action == Action::"view", resource == Photo::"b");
+            @reason("other") permit(principal in Group::"admins", action == Action::"view", resource == Photo::"c");"#,
+        )
+        .unwrap();
+        let suggestions = suggest_templates(&policies);
+        assert_eq!(suggestions.len(), 2);
+
+        let owner = &suggestions[0];
+        assert_eq!(owner.template().id().as_ref(), "template0");
+        assert_eq!(
+            owner.template().principal_constraint(),
+            crate::TemplatePrincipalConstraint::Eq(None)
+        );
+        assert_eq!(
+            owner.links(),
+            [
+                (
+                    PolicyId::from_str("policy0").unwrap(),
+                    HashMap::from([(
+                        crate::SlotId::principal(),
+                        EntityUid::from_str(r#"User::"alice""#).unwrap()
+                    )])
+                ),
+                (
+                    PolicyId::from_str("policy1").unwrap(),
+                    HashMap::from([(
+                        crate::SlotId::principal(),
+                        EntityUid::from_str(r#"User::"bob""#).unwrap()
+                    )])
+                ),
+            ]
+        );
+
+        // the principal is the same, so it isn't a slot
+        let photos = &suggestions[1];
+        assert_eq!(
+            photos.template().principal_constraint(),
+            crate::TemplatePrincipalConstraint::In(Some(
+                EntityUid::from_str(r#"Group::"admins""#).unwrap()
+            ))
+        );
+        assert_eq!(
+            photos.template().slots().collect::<Vec<_>>(),
+            [&crate::SlotId::resource()]
+        );
+        let linked: Vec<&str> = photos.links().iter().map(|(id, _)| id.as_ref()).collect();
+        assert_eq!(linked, ["policy3", "policy4"]);
+
+        // replacing the policies by the links doesn't change any decision
+        let solver = z3();
+        let mut after = PolicySet::new();
+        for suggestion in &suggestions {
+            after.add_template(suggestion.template().clone()).unwrap();
+            for (id, values) in suggestion.links() {
+                after
+                    .link(
+                        suggestion.template().id().clone(),
+                        id.clone(),
+                        values.clone(),
+                    )
+                    .unwrap();
+            }
+        }
+        for id in ["policy2", "policy5"] {
+            let id = PolicyId::from_str(id).unwrap();
+            after.add(policies.policy(&id).unwrap().clone()).unwrap();
+        }
+        assert_matches!(
+            check_equivalence(&policies, &after, &schema(), &solver),
+            Ok(Equivalence::Equivalent)
+        );
+    }
+
+    #[test]
+    #[ignore = "needs z3"]
+    fn policy_comparison() {
+        let solver = z3();
+        let schema = schema();
+        let before = policy(r#"permit(principal, action, resource) when { principal.age > 18 };"#);
+        let compare = |before: &Policy, after: &str| {
+            compare_policies(before, &policy(after), &schema, &solver).unwrap()
+        };
+        assert_matches!(
+            compare(
+                &before,
+                r#"permit(principal, action, resource) when { 18 < principal.age };"#
+            ),
+            PolicyComparison::Equivalent
+        );
+        let more = assert_matches!(
+            compare(
+                &before,
+                r#"permit(principal, action, resource) when { principal.age > 10 };"#
+            ),
+            PolicyComparison::MorePermissive(more) => more
+        );
+        let authorizer = Authorizer::new();
+        let decide = |src: &str, counterexample: &Counterexample| {
+            let policies = PolicySet::from_str(src).unwrap();
+            authorizer
+                .is_authorized(
+                    counterexample.request(),
+                    &policies,
+                    counterexample.entities(),
+                )
+                .decision()
+        };
+        assert_eq!(
+            decide(
+                r#"permit(principal, action, resource) when { principal.age > 10 };"#,
+                &more
+            ),
+            crate::Decision::Allow
+        );
+        assert_eq!(
+            decide(
+                r#"permit(principal, action, resource) when { principal.age > 18 };"#,
+                &more
+            ),
+            crate::Decision::Deny
+        );
+        assert_matches!(
+            compare(
+                &before,
+                r#"permit(principal, action, resource) when { principal.age > 20 };"#
+            ),
+            PolicyComparison::LessPermissive(_)
+        );
+        assert_matches!(
+            compare(
+                &before,
+                r#"permit(principal, action, resource) when { principal.age > 10 && context.ip like "10.*" };"#
+            ),
+            PolicyComparison::Incomparable { .. }
+        );
+        assert_matches!(
+            compare(
+                &before,
+                r#"forbid(principal, action, resource) when { principal.age > 18 };"#
+            ),
+            PolicyComparison::LessPermissive(_)
+        );
+
+        // forbidding more is less permissive
+        let before = policy(r#"forbid(principal, action, resource) when { principal.age < 18 };"#);
+        assert_matches!(
+            compare(
+                &before,
+                r#"forbid(principal, action, resource) when { principal.age < 21 };"#
+            ),
+            PolicyComparison::LessPermissive(_)
+        );
+        assert_matches!(
+            compare(
+                &before,
+                r#"forbid(principal, action, resource) when { principal.age < 16 };"#
+            ),
+            PolicyComparison::MorePermissive(_)
+        );
+    }
+}
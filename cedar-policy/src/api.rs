@@ -20,6 +20,9 @@
     clippy::missing_errors_doc,
     clippy::similar_names
 )]
+use crate::audit::{AuditEvent, AuditSink};
+use crate::render::{PolicyOrigin, PolicySource, SourceMap, SourcePosition};
+use crate::signing::Verifier;
 pub use ast::Effect;
 pub use authorizer::Decision;
 use cedar_policy_core::ast;
@@ -38,13 +41,14 @@ pub use cedar_policy_core::evaluator::{EvaluationError, EvaluationErrorKind};
 pub use cedar_policy_core::extensions;
 use cedar_policy_core::extensions::Extensions;
 use cedar_policy_core::parser;
-pub use cedar_policy_core::parser::err::ParseErrors;
+pub use cedar_policy_core::parser::err::{ParseError, ParseErrorKind, ParseErrors};
 use cedar_policy_core::FromNormalizedStr;
 use cedar_policy_validator::RequestValidationError; // this type is unsuitable for `pub use` because it contains internal types like `EntityUID` and `EntityType`
 pub use cedar_policy_validator::{
-    TypeErrorKind, UnsupportedFeature, ValidationErrorKind, ValidationWarningKind,
+    AttributeOwner, CompatibilityReport, SchemaChange, StrictSchemaFinding, TypeErrorKind,
+    UndeclaredContextAttribute, UnsupportedFeature, ValidationErrorKind, ValidationWarningKind,
 };
-use itertools::Itertools;
+use itertools::{Either, Itertools};
 use miette::Diagnostic;
 use ref_cast::RefCast;
 use serde::{Deserialize, Serialize};
@@ -53,6 +57,7 @@ use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::convert::Infallible;
 use std::marker::PhantomData;
 use std::str::FromStr;
+use std::sync::Arc;
 use thiserror::Error;
 
 /// Identifier for a Template slot
@@ -94,7 +99,7 @@ impl From<SlotId> for ast::SlotId {
 // INVARIANT(UidOfEntityNotUnspecified): The `EntityUid` of an `Entity` cannot be unspecified
 #[repr(transparent)]
 #[derive(Debug, Clone, PartialEq, Eq, RefCast)]
-pub struct Entity(ast::Entity);
+pub struct Entity(pub(crate) ast::Entity);
 
 impl Entity {
     /// Create a new `Entity` with this Uid, attributes, and parents.
@@ -137,6 +142,47 @@ impl Entity {
         )?))
     }
 
+    /// Create a new `Entity` with this Uid, attributes, and parents, which
+    /// additionally has the attributes in `unknown_attrs` with values that are
+    /// not yet known.
+    ///
+    /// Each unknown attribute is bound to an unknown named `<uid>.<attr>`
+    /// (e.g., `User::"alice".department`), so that partial evaluation over the
+    /// entity produces residuals instead of missing-attribute errors. This
+    /// corresponds to `unknownAttrs` in the entity JSON format.
+    /// If an attribute appears in both `attrs` and `unknown_attrs`, it is
+    /// unknown.
+    /// ```
+    /// # use cedar_policy::{Entity, EntityUid, RestrictedExpression};
+    /// # use std::collections::{HashMap, HashSet};
+    /// # use std::str::FromStr;
+    /// let euid = EntityUid::from_str(r#"User::"alice""#).unwrap();
+    /// let attrs = HashMap::from([
+    ///     ("age".to_string(), RestrictedExpression::from_str("21").unwrap()),
+    /// ]);
+    /// let unknown_attrs = HashSet::from(["department".to_string()]);
+    /// let alice = Entity::new_with_unknown_attrs(euid, attrs, unknown_attrs, HashSet::new())
+    ///     .unwrap();
+    /// assert!(alice.attr("department").unwrap().is_err());
+    /// ```
+    #[cfg(feature = "partial-eval")]
+    pub fn new_with_unknown_attrs(
+        uid: EntityUid,
+        attrs: HashMap<String, RestrictedExpression>,
+        unknown_attrs: HashSet<String>,
+        parents: HashSet<EntityUid>,
+    ) -> Result<Self, EntityAttrEvaluationError> {
+        let unknowns = unknown_attrs.into_iter().map(|attr| {
+            let unknown = ast::Entity::unknown_attr(&uid.0, &attr);
+            (
+                attr,
+                RestrictedExpression(ast::RestrictedExpr::unknown(unknown)),
+            )
+        });
+        let attrs = attrs.into_iter().chain(unknowns).collect();
+        Self::new(uid, attrs, parents)
+    }
+
     /// Create a new `Entity` with no attributes.
     ///
     /// Unlike [`Entity::new()`], this constructor cannot error.
@@ -223,9 +269,202 @@ impl std::fmt::Display for Entity {
 #[derive(Debug, Clone, Default, PartialEq, Eq, RefCast)]
 pub struct Entities(pub(crate) entities::Entities);
 
-pub use entities::EntitiesError;
+pub use entities::{DuplicateEntities, TCComputation};
+pub use entities::{EntitiesError, SkippedEntityError};
+
+/// Builder for [`Entities`], for choosing how the transitive closure of the
+/// entity hierarchy is computed
+///
+/// The constructors of [`Entities`] always compute the transitive closure.
+/// Entities from a store which keeps the closed hierarchy, i.e., where each
+/// entity already lists all its ancestors as parents, don't need that; see
+/// [`TCComputation`].
+#[derive(Debug)]
+pub struct EntitiesBuilder<'a> {
+    /// Here, `None` means no schema-based parsing or validation
+    schema: Option<&'a Schema>,
+    tc_computation: TCComputation,
+    duplicates: DuplicateEntities,
+}
+
+impl<'a> Default for EntitiesBuilder<'a> {
+    fn default() -> Self {
+        Self {
+            schema: None,
+            tc_computation: TCComputation::ComputeNow,
+            duplicates: DuplicateEntities::Reject,
+        }
+    }
+}
+
+impl<'a> EntitiesBuilder<'a> {
+    /// Set the schema. If present, this will be used as in
+    /// [`Entities::from_entities`] and [`Entities::from_json_str`]: it is a
+    /// source of `Action` entities, it informs parsing, and the entities must
+    /// conform to it.
+    #[must_use]
+    pub fn schema(self, schema: &'a Schema) -> Self {
+        Self {
+            schema: Some(schema),
+            ..self
+        }
+    }
+
+    /// Set how the transitive closure of the entity hierarchy is computed.
+    /// The default is [`TCComputation::ComputeNow`].
+    ///
+    /// With [`TCComputation::EnforceAlreadyComputed`], building fails with
+    /// [`EntitiesError::TransitiveClosureError`] if an ancestor of an
+    /// ancestor of an entity is not one of its own ancestors. With
+    /// [`TCComputation::AssumeAlreadyComputed`], nothing is checked, and
+    /// `in` gives wrong results for a hierarchy which isn't closed.
+    ///
+    /// With [`TCComputation::ComputeOnDemand`], the ancestors of each entity
+    /// are computed the first time they are needed, e.g., by `in`. This is
+    /// faster for large hierarchies of which each request only needs a small
+    /// part, but cycles in the hierarchy are not detected.
+    #[must_use]
+    pub fn tc_computation(self, tc_computation: TCComputation) -> Self {
+        Self {
+            tc_computation,
+            ..self
+        }
+    }
+
+    /// Set how entities with the same uid are handled. The default is
+    /// [`DuplicateEntities::Reject`], failing with
+    /// [`EntitiesError::Duplicate`].
+    ///
+    /// With [`DuplicateEntities::MergeLastWriterWins`] or
+    /// [`DuplicateEntities::MergeRejectConflicts`], they are merged into one
+    /// entity with the union of their parents and attributes, e.g., when
+    /// combining entities from several sources. They differ in how an
+    /// attribute with different values is handled: the former keeps the value
+    /// of the entity which comes last, and the latter fails with
+    /// [`EntitiesError::ConflictingDuplicate`].
+    #[must_use]
+    pub fn duplicates(self, duplicates: DuplicateEntities) -> Self {
+        Self { duplicates, ..self }
+    }
+
+    fn core_schema(&self) -> Option<cedar_policy_validator::CoreSchema<'a>> {
+        self.schema
+            .map(|s| cedar_policy_validator::CoreSchema::new(&s.0))
+    }
+
+    /// Create the [`Entities`] with the given entities, as in
+    /// [`Entities::from_entities`]
+    pub fn from_entities(
+        self,
+        entities: impl IntoIterator<Item = Entity>,
+    ) -> Result<Entities, EntitiesError> {
+        entities::Entities::from_entities_with_duplicates(
+            entities.into_iter().map(|e| e.0),
+            self.core_schema().as_ref(),
+            self.tc_computation,
+            self.duplicates,
+            Extensions::all_available(),
+        )
+        .map(Entities)
+    }
+
+    /// Parse an entities JSON file (in `&str` form) into [`Entities`], as in
+    /// [`Entities::from_json_str`]
+    pub fn from_json_str(self, json: &str) -> Result<Entities, EntitiesError> {
+        let schema = self.core_schema();
+        let eparser = entities::EntityJsonParser::new(
+            schema.as_ref(),
+            Extensions::all_available(),
+            self.tc_computation,
+        )
+        .with_duplicates(self.duplicates);
+        eparser.from_json_str(json).map(Entities)
+    }
+
+    /// Parse an entities JSON file (in [`serde_json::Value`] form) into
+    /// [`Entities`], as in [`Entities::from_json_value`]
+    pub fn from_json_value(self, json: serde_json::Value) -> Result<Entities, EntitiesError> {
+        let schema = self.core_schema();
+        let eparser = entities::EntityJsonParser::new(
+            schema.as_ref(),
+            Extensions::all_available(),
+            self.tc_computation,
+        )
+        .with_duplicates(self.duplicates);
+        eparser.from_json_value(json).map(Entities)
+    }
+
+    /// Parse an entities JSON file (in [`std::io::Read`] form) into
+    /// [`Entities`], as in [`Entities::from_json_file`]
+    pub fn from_json_file(self, json: impl std::io::Read) -> Result<Entities, EntitiesError> {
+        let schema = self.core_schema();
+        let eparser = entities::EntityJsonParser::new(
+            schema.as_ref(),
+            Extensions::all_available(),
+            self.tc_computation,
+        )
+        .with_duplicates(self.duplicates);
+        eparser.from_json_file(json).map(Entities)
+    }
+
+    /// Parse an entities JSON file (in [`std::io::Read`] form) into
+    /// [`Entities`] one entity at a time, as in [`Entities::from_json_stream`]
+    pub fn from_json_stream(self, json: impl std::io::Read) -> Result<Entities, EntitiesError> {
+        let schema = self.core_schema();
+        let eparser = entities::EntityJsonParser::new(
+            schema.as_ref(),
+            Extensions::all_available(),
+            self.tc_computation,
+        )
+        .with_duplicates(self.duplicates);
+        eparser.from_json_stream(json).map(Entities)
+    }
+}
+
+/// An immutable snapshot of an [`Entities`], see [`Entities::snapshot`]
+///
+/// It dereferences to the [`Entities`] it was taken of, for looking up
+/// entities and authorizing requests, but can't be changed.
+#[derive(Debug, Clone)]
+pub struct EntitiesSnapshot(Entities);
+
+impl std::ops::Deref for EntitiesSnapshot {
+    type Target = Entities;
+
+    fn deref(&self) -> &Entities {
+        &self.0
+    }
+}
+
+/// A page of the entities in an [`Entities`], see [`Entities::page`]
+#[derive(Debug, Clone)]
+pub struct EntitiesPage<'a> {
+    /// Entities of the page, ordered by uid
+    entities: Vec<&'a Entity>,
+    /// Uid of the last entity of the page, if there are more entities after it
+    next: Option<EntityUid>,
+}
+
+impl<'a> EntitiesPage<'a> {
+    /// Iterate over the entities of this page, ordered by uid
+    pub fn entities(&self) -> impl Iterator<Item = &'a Entity> + '_ {
+        self.entities.iter().copied()
+    }
+
+    /// The cursor of the next page, to pass to [`Entities::page`], or `None`
+    /// if this is the last page
+    pub fn next_cursor(&self) -> Option<&EntityUid> {
+        self.next.as_ref()
+    }
+}
 
 impl Entities {
+    /// Create an [`EntitiesBuilder`], for choosing how the transitive closure
+    /// of the entity hierarchy is computed
+    pub fn builder<'a>() -> EntitiesBuilder<'a> {
+        EntitiesBuilder::default()
+    }
+
     /// Create a fresh `Entities` with no entities
     /// ```
     /// # use cedar_policy::Entities;
@@ -257,6 +496,130 @@ impl Entities {
         self.0.iter().map(Entity::ref_cast)
     }
 
+    /// Take an immutable snapshot of the `Entities`, e.g., for an in-flight
+    /// authorization to see a consistent store while it is being updated.
+    ///
+    /// Taking a snapshot is cheap, as the snapshot shares the entities with
+    /// this `Entities` rather than copying them. Changing an entity afterwards,
+    /// e.g., with [`Entities::update_attr`], copies just that entity, and the
+    /// map of uids to entities, and leaves the snapshot unchanged.
+    /// ```
+    /// # use cedar_policy::{Entities, EntityUid, RestrictedExpression};
+    /// # use std::str::FromStr;
+    /// let mut entities = Entities::from_json_value(serde_json::json!([
+    ///     { "uid": { "type": "User", "id": "alice" }, "attrs": { "logins": 1 }, "parents": [] },
+    /// ]), None).unwrap();
+    /// let snapshot = entities.snapshot();
+    /// let alice = EntityUid::from_str(r#"User::"alice""#).unwrap();
+    /// let logins = RestrictedExpression::new_long(2);
+    /// entities.update_attr(&alice, "logins", logins, None).unwrap();
+    /// assert_eq!(snapshot.version(), 0);
+    /// assert_eq!(entities.version(), 1);
+    /// ```
+    pub fn snapshot(&self) -> EntitiesSnapshot {
+        EntitiesSnapshot(self.clone())
+    }
+
+    /// The version of the `Entities`, which is 0 when it is constructed, and
+    /// incremented by each method changing the entities, e.g.,
+    /// [`Entities::update_attr`] and [`Entities::add_entities`]. A snapshot
+    /// keeps the version of the `Entities` it was taken of.
+    pub fn version(&self) -> u64 {
+        self.0.version()
+    }
+
+    /// Iterate over the `Entity`'s of type `ty` in the `Entities`. The
+    /// `Entities` keeps an index of the entities of each type, so this doesn't
+    /// iterate over the entities of other types.
+    /// ```
+    /// # use cedar_policy::{Entities, EntityTypeName};
+    /// # use std::str::FromStr;
+    /// let entities = Entities::from_json_value(serde_json::json!([
+    ///     { "uid": { "type": "User", "id": "alice" }, "attrs": {}, "parents": [] },
+    ///     { "uid": { "type": "Photo", "id": "vacation" }, "attrs": {}, "parents": [] },
+    /// ]), None).unwrap();
+    /// let users = entities.of_type(&EntityTypeName::from_str("User").unwrap());
+    /// assert_eq!(users.count(), 1);
+    /// ```
+    pub fn of_type<'a>(&'a self, ty: &EntityTypeName) -> impl Iterator<Item = &'a Entity> {
+        self.0
+            .of_type(&ast::EntityType::Specified(ty.0.clone()))
+            .map(Entity::ref_cast)
+    }
+
+    /// Iterate over the `Entity`'s in the `Entities` for which `predicate`
+    /// holds, e.g., those with a given attribute value, without cloning them
+    pub fn iter_filtered<'a>(
+        &'a self,
+        mut predicate: impl FnMut(&Entity) -> bool + 'a,
+    ) -> impl Iterator<Item = &'a Entity> {
+        self.iter().filter(move |e| predicate(e))
+    }
+
+    /// Get a page of at most `limit` (and at least one) entities, ordered by
+    /// uid, for which `predicate` holds. If `ty` is given, only entities of
+    /// that type are considered, using the index of [`Entities::of_type`].
+    ///
+    /// `after` is the cursor of the page, i.e., only entities with uids after
+    /// it are returned. Pass `None` for the first page and
+    /// [`EntitiesPage::next_cursor`] for the following ones. As the cursor is
+    /// a uid, it stays valid if the `Entities` is rebuilt between pages;
+    /// entities added before the cursor are then skipped.
+    /// ```
+    /// # use cedar_policy::{Entities, EntityTypeName};
+    /// # use std::str::FromStr;
+    /// let entities = Entities::from_json_value(serde_json::json!([
+    ///     { "uid": { "type": "User", "id": "alice" }, "attrs": {}, "parents": [] },
+    ///     { "uid": { "type": "User", "id": "bob" }, "attrs": {}, "parents": [] },
+    ///     { "uid": { "type": "User", "id": "carol" }, "attrs": {}, "parents": [] },
+    /// ]), None).unwrap();
+    /// let user = EntityTypeName::from_str("User").unwrap();
+    /// let mut pages = Vec::new();
+    /// let mut cursor = None;
+    /// loop {
+    ///     let page = entities.page(Some(&user), cursor.as_ref(), 2, |_| true);
+    ///     pages.push(page.entities().map(|e| e.uid().id().to_string()).collect::<Vec<_>>());
+    ///     match page.next_cursor() {
+    ///         Some(next) => cursor = Some(next.clone()),
+    ///         None => break,
+    ///     }
+    /// }
+    /// assert_eq!(pages, vec![vec!["alice", "bob"], vec!["carol"]]);
+    /// ```
+    pub fn page<'a>(
+        &'a self,
+        ty: Option<&EntityTypeName>,
+        after: Option<&EntityUid>,
+        limit: usize,
+        mut predicate: impl FnMut(&Entity) -> bool,
+    ) -> EntitiesPage<'a> {
+        let limit = limit.max(1);
+        let candidates = match ty {
+            Some(ty) => Either::Left(self.of_type(ty)),
+            None => Either::Right(self.iter()),
+        };
+        let mut matches: Vec<(EntityUid, &Entity)> = candidates
+            .map(|e| (e.uid(), e))
+            .filter(|(uid, e)| after.is_none_or(|after| uid > after) && predicate(e))
+            .collect();
+        let more = matches.len() > limit;
+        if more {
+            // only the entities of this page are sorted
+            matches.select_nth_unstable_by(limit, |(a, _), (b, _)| a.cmp(b));
+            matches.truncate(limit);
+        }
+        matches.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        let next = if more {
+            matches.last().map(|(uid, _)| uid.clone())
+        } else {
+            None
+        };
+        EntitiesPage {
+            entities: matches.into_iter().map(|(_, e)| e).collect(),
+            next,
+        }
+    }
+
     /// Create an `Entities` object with the given entities.
     ///
     /// `schema` represents a source of `Action` entities, which will be added
@@ -314,6 +677,90 @@ impl Entities {
         ))
     }
 
+    /// Replace the attributes of [`Entity`]s in this [`Entities`] structure
+    /// with those of the given [`Entity`]s, reusing the transitive closure
+    /// computed when they were added.
+    ///
+    /// Each given entity must already be in this [`Entities`], and keeps the
+    /// ancestors it has here. This method errors if an entity has a parent
+    /// which is not one of those ancestors; changing the entity hierarchy
+    /// requires [`Entities::add_entities`] or [`Entities::from_entities`]
+    /// instead. Note that this method can't detect that a parent was removed.
+    ///
+    /// If a `schema` is provided, this method will ensure that the updated
+    /// entities fully conform to the schema, as in [`Entities::add_entities`].
+    pub fn update_attributes(
+        self,
+        entities: impl IntoIterator<Item = Entity>,
+        schema: Option<&Schema>,
+    ) -> Result<Self, EntitiesError> {
+        Ok(Self(
+            self.0.update_attrs(
+                entities.into_iter().map(|e| e.0),
+                schema
+                    .map(|s| cedar_policy_validator::CoreSchema::new(&s.0))
+                    .as_ref(),
+                Extensions::all_available(),
+            )?,
+        ))
+    }
+
+    /// Set the attribute `attr` of the entity with `uid` to `value`, in place.
+    ///
+    /// Unlike [`Entities::update_attributes`], this only evaluates `value`, and
+    /// doesn't parse or copy the rest of the entity, nor any other entity. It
+    /// never re-computes the transitive closure.
+    ///
+    /// If a `schema` is provided, the updated entity must conform to it.
+    /// Otherwise, the entity is left unchanged and an error is returned.
+    /// ```
+    /// # use cedar_policy::{Entities, EntityUid, RestrictedExpression};
+    /// # use std::str::FromStr;
+    /// let mut entities = Entities::from_json_value(serde_json::json!([
+    ///     { "uid": { "type": "User", "id": "alice" }, "attrs": { "logins": 1 }, "parents": [] },
+    /// ]), None).unwrap();
+    /// let alice = EntityUid::from_str(r#"User::"alice""#).unwrap();
+    /// let logins = RestrictedExpression::new_long(2);
+    /// entities.update_attr(&alice, "logins", logins, None).unwrap();
+    /// ```
+    pub fn update_attr(
+        &mut self,
+        uid: &EntityUid,
+        attr: &str,
+        value: RestrictedExpression,
+        schema: Option<&Schema>,
+    ) -> Result<(), EntitiesError> {
+        self.0.update_attr(
+            &uid.0,
+            SmolStr::from(attr),
+            value.0,
+            schema
+                .map(|s| cedar_policy_validator::CoreSchema::new(&s.0))
+                .as_ref(),
+            Extensions::all_available(),
+        )
+    }
+
+    /// Remove the attribute `attr` of the entity with `uid`, in place, as in
+    /// [`Entities::update_attr`]. Returns whether the entity had the
+    /// attribute.
+    pub fn remove_attr(
+        &mut self,
+        uid: &EntityUid,
+        attr: &str,
+        schema: Option<&Schema>,
+    ) -> Result<bool, EntitiesError> {
+        let removed = self.0.remove_attr(
+            &uid.0,
+            SmolStr::from(attr),
+            schema
+                .map(|s| cedar_policy_validator::CoreSchema::new(&s.0))
+                .as_ref(),
+            Extensions::all_available(),
+        )?;
+        Ok(removed.is_some())
+    }
+
     /// Parse an entities JSON file (in [&str] form) and add them into this
     /// [`Entities`] structure, re-computing the transitive closure
     ///
@@ -552,11 +999,110 @@ impl Entities {
         eparser.from_json_file(json).map(Entities)
     }
 
+    /// Parse an entities JSON file (in `std::io::Read` form) into an `Entities`
+    /// object, one entity at a time
+    ///
+    /// This is like [`Entities::from_json_file`], but never holds the JSON of
+    /// more than one entity in memory: each entity is parsed, checked against
+    /// the `schema`, and inserted as soon as it is read. Prefer it for large
+    /// entity files, which `from_json_file` needs several times their size in
+    /// memory to load. Errors in entities are reported even if the JSON after
+    /// them is malformed.
+    /// ```
+    /// # use cedar_policy::{Entities, EntityUid};
+    /// # use std::str::FromStr;
+    /// let json = r#"[{ "uid": { "type": "User", "id": "alice" }, "attrs": {}, "parents": [] }]"#;
+    /// let entities = Entities::from_json_stream(json.as_bytes(), None).unwrap();
+    /// let alice = EntityUid::from_str(r#"User::"alice""#).unwrap();
+    /// assert!(entities.get(&alice).is_some());
+    /// ```
+    pub fn from_json_stream(
+        json: impl std::io::Read,
+        schema: Option<&Schema>,
+    ) -> Result<Self, entities::EntitiesError> {
+        let schema = schema.map(|s| cedar_policy_validator::CoreSchema::new(&s.0));
+        let eparser = entities::EntityJsonParser::new(
+            schema.as_ref(),
+            Extensions::all_available(),
+            entities::TCComputation::ComputeNow,
+        );
+        eparser.from_json_stream(json).map(Entities)
+    }
+
+    /// Parse an entities JSON file (in `&str` form) into an `Entities` object,
+    /// like [`Entities::from_json_str`], but skipping the entities which fail
+    /// to parse or don't conform to the `schema` instead of failing
+    ///
+    /// Returns the `Entities` with all the other entities, and an error for
+    /// each skipped entity, with its position in the JSON array and its uid (if
+    /// that could be parsed). This still fails if the JSON is malformed or not
+    /// an array, or if the entity hierarchy is invalid.
+    /// ```
+    /// # use cedar_policy::Entities;
+    /// let json = r#"[
+    ///     { "uid": { "type": "User", "id": "alice" }, "attrs": {}, "parents": [] },
+    ///     { "uid": { "type": "User", "id": "bob" }, "attrs": [], "parents": [] }
+    /// ]"#;
+    /// let (entities, skipped) = Entities::from_json_str_collecting_errors(json, None).unwrap();
+    /// assert_eq!(entities.iter().count(), 1);
+    /// assert_eq!(skipped.len(), 1);
+    /// assert_eq!(skipped[0].index, 1);
+    /// assert_eq!(skipped[0].uid.as_ref().unwrap().to_string(), r#"User::"bob""#);
+    /// ```
+    pub fn from_json_str_collecting_errors(
+        json: &str,
+        schema: Option<&Schema>,
+    ) -> Result<(Self, Vec<SkippedEntityError>), entities::EntitiesError> {
+        let schema = schema.map(|s| cedar_policy_validator::CoreSchema::new(&s.0));
+        let eparser = entities::EntityJsonParser::new(
+            schema.as_ref(),
+            Extensions::all_available(),
+            entities::TCComputation::ComputeNow,
+        );
+        let (entities, skipped) = eparser.from_json_str_collecting_errors(json)?;
+        Ok((Entities(entities), skipped))
+    }
+
+    /// Parse an entities JSON file (in [`serde_json::Value`] form) into an
+    /// `Entities` object, skipping the entities which fail to parse or don't
+    /// conform to the `schema`, as in [`Entities::from_json_str_collecting_errors`]
+    pub fn from_json_value_collecting_errors(
+        json: serde_json::Value,
+        schema: Option<&Schema>,
+    ) -> Result<(Self, Vec<SkippedEntityError>), entities::EntitiesError> {
+        let schema = schema.map(|s| cedar_policy_validator::CoreSchema::new(&s.0));
+        let eparser = entities::EntityJsonParser::new(
+            schema.as_ref(),
+            Extensions::all_available(),
+            entities::TCComputation::ComputeNow,
+        );
+        let (entities, skipped) = eparser.from_json_value_collecting_errors(json)?;
+        Ok((Entities(entities), skipped))
+    }
+
+    /// Parse an entities JSON file (in `std::io::Read` form) into an
+    /// `Entities` object one entity at a time, skipping the entities which
+    /// fail to parse or don't conform to the `schema`, as in
+    /// [`Entities::from_json_str_collecting_errors`]
+    pub fn from_json_file_collecting_errors(
+        json: impl std::io::Read,
+        schema: Option<&Schema>,
+    ) -> Result<(Self, Vec<SkippedEntityError>), entities::EntitiesError> {
+        let schema = schema.map(|s| cedar_policy_validator::CoreSchema::new(&s.0));
+        let eparser = entities::EntityJsonParser::new(
+            schema.as_ref(),
+            Extensions::all_available(),
+            entities::TCComputation::ComputeNow,
+        );
+        let (entities, skipped) = eparser.from_json_file_collecting_errors(json)?;
+        Ok((Entities(entities), skipped))
+    }
+
     /// Is entity `a` an ancestor of entity `b`?
     /// Same semantics as `b in a` in the Cedar language
     pub fn is_ancestor_of(&self, a: &EntityUid, b: &EntityUid) -> bool {
         match self.0.entity(&b.0) {
-            Dereference::Data(b) => b.is_descendant_of(&a.0),
+            Dereference::Data(b) => self.0.is_descendant_of(b, &a.0),
             _ => a == b, // if b doesn't exist, `b in a` is only true if `b == a`
         }
     }
@@ -572,7 +1118,7 @@ impl Entities {
             Dereference::Data(e) => Some(e),
         }?;
         // Invariant: No way to write down the unspecified EntityUid, so no way to have ancestors that are unspecified
-        Some(entity.ancestors().map(EntityUid::ref_cast))
+        Some(self.0.ancestors_of(entity).map(EntityUid::ref_cast))
     }
 
     /// Dump an `Entities` object into an entities JSON file.
@@ -591,9 +1137,23 @@ impl Entities {
 }
 
 /// Authorizer object, which provides responses to authorization queries
-#[repr(transparent)]
-#[derive(Debug, RefCast)]
-pub struct Authorizer(authorizer::Authorizer);
+pub struct Authorizer {
+    authorizer: authorizer::Authorizer,
+    /// Where to record an audit event of every decision, if anywhere
+    audit_sink: Option<Arc<dyn AuditSink>>,
+    /// What checks the signatures of policy sets, if they must be signed
+    pub(crate) verifier: Option<Arc<dyn Verifier>>,
+}
+
+impl std::fmt::Debug for Authorizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Authorizer")
+            .field("authorizer", &self.authorizer)
+            .field("audit_sink", &self.audit_sink.is_some())
+            .field("verifier", &self.verifier.is_some())
+            .finish()
+    }
+}
 
 impl Default for Authorizer {
     fn default() -> Self {
@@ -654,7 +1214,56 @@ impl Authorizer {
     /// let r = authorizer.is_authorized(&request, &policy, &entities);
     /// ```
     pub fn new() -> Self {
-        Self(authorizer::Authorizer::new())
+        Self {
+            authorizer: authorizer::Authorizer::new(),
+            audit_sink: None,
+            verifier: None,
+        }
+    }
+
+    /// Record an [`AuditEvent`](crate::audit::AuditEvent) of every decision
+    /// this `Authorizer` makes to `sink`. Partial evaluations are only
+    /// recorded when they reach a decision.
+    #[must_use]
+    pub fn with_audit_sink(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.audit_sink = Some(sink);
+        self
+    }
+
+    /// Only authorize against policy sets signed for `verifier`, with
+    /// [`Authorizer::is_authorized_signed`]. Every request
+    /// authorized against an unsigned `PolicySet`, e.g., with
+    /// [`Authorizer::is_authorized`], is denied.
+    #[must_use]
+    pub fn with_verifier(mut self, verifier: Arc<dyn Verifier>) -> Self {
+        self.verifier = Some(verifier);
+        self
+    }
+
+    /// The response to a request against an unsigned `PolicySet` when policy
+    /// sets must be signed: a deny, determined by no policy, with an
+    /// [`AuthorizationError::UnverifiedPolicySet`] error
+    fn refuse_unsigned(&self, r: &Request, p: &PolicySet) -> Response {
+        let response = Response::new(
+            Decision::Deny,
+            HashSet::new(),
+            vec![AuthorizationError::UnverifiedPolicySet],
+        );
+        self.audit(r, p, &response, None);
+        response
+    }
+
+    /// Record the audit event of `response`, if there's a sink
+    fn audit(
+        &self,
+        request: &Request,
+        policies: &PolicySet,
+        response: &Response,
+        duration: Option<std::time::Duration>,
+    ) {
+        if let Some(sink) = &self.audit_sink {
+            sink.record(&AuditEvent::new(request, policies, response, duration));
+        }
     }
 
     /// Returns an authorization response for `r` with respect to the given
@@ -711,7 +1320,31 @@ impl Authorizer {
     /// assert_eq!(response.decision(), Decision::Allow);
     /// ```
     pub fn is_authorized(&self, r: &Request, p: &PolicySet, e: &Entities) -> Response {
-        self.0.is_authorized(r.0.clone(), &p.ast, &e.0).into()
+        if self.verifier.is_some() {
+            return self.refuse_unsigned(r, p);
+        }
+        self.authorize(r, p, e)
+    }
+
+    /// Authorize `r` against `p`, whether or not policy sets must be signed
+    pub(crate) fn authorize(&self, r: &Request, p: &PolicySet, e: &Entities) -> Response {
+        let start = clock_start(cfg!(feature = "metrics") || self.audit_sink.is_some());
+        let response: Response = self
+            .authorizer
+            .is_authorized(r.0.clone(), &p.ast, &e.0)
+            .into();
+        self.audit(r, p, &response, start.map(|start| start.elapsed()));
+        #[cfg(feature = "metrics")]
+        crate::metrics::record(
+            match response.decision {
+                Decision::Allow => "allow",
+                Decision::Deny => "deny",
+            },
+            p.ast.policies().count(),
+            start.map(|start| start.elapsed()).unwrap_or_default(),
+            &response.diagnostics.errors,
+        );
+        response
     }
 
     /// A partially evaluated authorization request.
@@ -725,16 +1358,47 @@ impl Authorizer {
         policy_set: &PolicySet,
         entities: &Entities,
     ) -> PartialResponse {
-        let response = self
-            .0
-            .is_authorized_core(query.0.clone(), &policy_set.ast, &entities.0);
-        match response {
+        if self.verifier.is_some() {
+            return PartialResponse::Concrete(self.refuse_unsigned(query, policy_set));
+        }
+        let start = clock_start(cfg!(feature = "metrics") || self.audit_sink.is_some());
+        let response =
+            self.authorizer
+                .is_authorized_core(query.0.clone(), &policy_set.ast, &entities.0);
+        let response = match response {
             authorizer::ResponseKind::FullyEvaluated(a) => PartialResponse::Concrete(a.into()),
             authorizer::ResponseKind::Partial(p) => PartialResponse::Residual(p.into()),
+        };
+        if let PartialResponse::Concrete(r) = &response {
+            self.audit(query, policy_set, r, start.map(|start| start.elapsed()));
         }
+        #[cfg(feature = "metrics")]
+        {
+            let (decision, diagnostics) = match &response {
+                PartialResponse::Concrete(r) => match r.decision {
+                    Decision::Allow => ("allow", &r.diagnostics),
+                    Decision::Deny => ("deny", &r.diagnostics),
+                },
+                PartialResponse::Residual(r) => ("residual", &r.diagnostics),
+            };
+            crate::metrics::record(
+                decision,
+                policy_set.ast.policies().count(),
+                start.map(|start| start.elapsed()).unwrap_or_default(),
+                &diagnostics.errors,
+            );
+        }
+        response
     }
 }
 
+/// The time now, if it's `needed` and there's a clock, which
+/// `wasm32-unknown-unknown` doesn't have
+fn clock_start(needed: bool) -> Option<std::time::Instant> {
+    (needed && !cfg!(all(target_arch = "wasm32", target_os = "unknown")))
+        .then(std::time::Instant::now)
+}
+
 /// Authorization response returned from the `Authorizer`
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Response {
@@ -1020,13 +1684,23 @@ impl Validator {
     /// included in the input policy set does not appear in the output iterator, then
     /// that policy passed the validator. If the function `validation_passed`
     /// returns true, then there were no validation errors found, so all
-    /// policies in the policy set have passed the validator.
+    /// policies in the policy set have passed the validator. An error the
+    /// validator doesn't find the location of is located at the text of its
+    /// policy, if the policy set has its source, e.g., from
+    /// [`PolicySet::from_source`].
     pub fn validate<'a>(
         &'a self,
         pset: &'a PolicySet,
         mode: ValidationMode,
     ) -> ValidationResult<'static> {
-        ValidationResult::from(self.0.validate(&pset.ast, mode.into()))
+        let mut result = ValidationResult::from(self.0.validate(&pset.ast, mode.into()));
+        for err in &mut result.validation_errors {
+            err.location.locate_in(pset);
+        }
+        for warning in &mut result.validation_warnings {
+            warning.location.locate_in(pset);
+        }
+        result
     }
 }
 
@@ -1136,6 +1810,21 @@ impl Schema {
         ))
     }
 
+    /// Create a `Schema` from a JSON value in strict mode. In addition to the
+    /// checks performed by [`Schema::from_json_value`], this rejects unknown
+    /// or misspelled keys (e.g., `memberOfTypess`) and suspicious empty
+    /// constructs such as an action whose `principalTypes` list is empty.
+    /// All such findings are returned together in
+    /// [`SchemaError::StrictModeViolations`].
+    pub fn from_json_value_strict(json: serde_json::Value) -> Result<Self, SchemaError> {
+        Ok(Self(
+            cedar_policy_validator::ValidatorSchema::from_json_value_strict(
+                json,
+                Extensions::all_available(),
+            )?,
+        ))
+    }
+
     /// Create a `Schema` directly from a file.
     pub fn from_file(file: impl std::io::Read) -> Result<Self, SchemaError> {
         Ok(Self(cedar_policy_validator::ValidatorSchema::from_file(
@@ -1149,6 +1838,60 @@ impl Schema {
     pub fn action_entities(&self) -> Result<Entities, EntitiesError> {
         Ok(Entities(self.0.action_entities()?))
     }
+
+    /// Get the annotations on an entity type declared in the schema as
+    /// key-value pairs, or `None` if the entity type isn't declared.
+    pub fn entity_type_annotations(
+        &self,
+        ty: &EntityTypeName,
+    ) -> Option<impl Iterator<Item = (&str, &str)>> {
+        Some(
+            self.0
+                .get_entity_type(&ty.0)?
+                .annotations()
+                .map(|(k, v)| (k.as_str(), v.as_str())),
+        )
+    }
+
+    /// Get the annotations on an attribute of an entity type declared in the
+    /// schema as key-value pairs, or `None` if the entity type or the
+    /// attribute isn't declared.
+    pub fn attribute_annotations(
+        &self,
+        ty: &EntityTypeName,
+        attr: &str,
+    ) -> Option<impl Iterator<Item = (&str, &str)>> {
+        let entity_type = self.0.get_entity_type(&ty.0)?;
+        entity_type.attr(attr)?;
+        Some(
+            entity_type
+                .attr_annotations(attr)
+                .map(|(k, v)| (k.as_str(), v.as_str())),
+        )
+    }
+
+    /// Get the annotations on an action declared in the schema as key-value
+    /// pairs, or `None` if the action isn't declared.
+    pub fn action_annotations(
+        &self,
+        action: &EntityUid,
+    ) -> Option<impl Iterator<Item = (&str, &str)>> {
+        Some(
+            self.0
+                .get_action_id(&action.0)?
+                .annotations()
+                .map(|(k, v)| (k.as_str(), v.as_str())),
+        )
+    }
+
+    /// Compare the `old` and `new` versions of a schema, e.g., before
+    /// deploying `new`. The report lists every difference between them,
+    /// classified as backward compatible or as breaking, i.e., possibly
+    /// causing policies which validate against `old` to fail validation, or
+    /// entities and requests which conform to `old` to no longer conform.
+    pub fn check_compatibility(old: &Schema, new: &Schema) -> CompatibilityReport {
+        cedar_policy_validator::check_compatibility(&old.0, &new.0)
+    }
 }
 
 /// Errors encountered during construction of a Validation Schema
@@ -1211,6 +1954,11 @@ pub enum SchemaError {
     #[error("parse error in common type identifier: {0}")]
     #[diagnostic(transparent)]
     ParseCommonType(ParseErrors),
+    /// Parse errors occurring while parsing the key of an annotation on an
+    /// entity type, attribute, or action.
+    #[error("parse error in annotation key: {0}")]
+    #[diagnostic(transparent)]
+    ParseAnnotationKey(ParseErrors),
     /// The schema file included an entity type `Action` in the entity type
     /// list. The `Action` entity type is always implicitly declared, and it
     /// cannot currently have attributes or be in any groups, so there is no
@@ -1237,6 +1985,26 @@ pub enum SchemaError {
     /// Support for this escape form has been dropped.
     #[error("schema contained the non-supported `__expr` escape")]
     ExprEscapeUsed,
+    /// An action has a context default for an attribute which isn't an
+    /// optional attribute of its context
+    #[error("action `{0}` has a context default for `{1}`, which is not an optional attribute of its context")]
+    ContextDefaultNotOptional(EntityUid, SmolStr),
+    /// An action has a context default which doesn't have the type of the
+    /// context attribute
+    #[error("action `{0}` has an invalid context default for `{1}`: {2}")]
+    InvalidContextDefault(EntityUid, SmolStr, String),
+    /// An attribute which is not declared directly in the shape of an entity
+    /// type is marked as deprecated
+    #[error("attribute `{0}` is marked as deprecated, but only attributes declared directly in an entity type's shape can be deprecated")]
+    UnsupportedAttributeDeprecation(SmolStr),
+    /// An attribute which is not declared directly in the shape of an entity
+    /// type has annotations
+    #[error("attribute `{0}` has annotations, but only attributes declared directly in an entity type's shape can be annotated")]
+    UnsupportedAttributeAnnotations(SmolStr),
+    /// Findings from parsing a schema in strict mode, see
+    /// [`Schema::from_json_value_strict`].
+    #[error("schema failed strict-mode checks with {} finding(s)", .0.len())]
+    StrictModeViolations(#[related] Vec<StrictSchemaFinding>),
 }
 
 /// Error when evaluating an entity attribute
@@ -1332,6 +2100,9 @@ impl From<cedar_policy_validator::SchemaError> for SchemaError {
             cedar_policy_validator::SchemaError::ParseEntityType(e) => Self::ParseEntityType(e),
             cedar_policy_validator::SchemaError::ParseNamespace(e) => Self::ParseNamespace(e),
             cedar_policy_validator::SchemaError::ParseCommonType(e) => Self::ParseCommonType(e),
+            cedar_policy_validator::SchemaError::ParseAnnotationKey(e) => {
+                Self::ParseAnnotationKey(e)
+            }
             cedar_policy_validator::SchemaError::ParseExtensionType(e) => {
                 Self::ParseExtensionType(e)
             }
@@ -1351,6 +2122,21 @@ impl From<cedar_policy_validator::SchemaError> for SchemaError {
                 Self::ActionAttrEval(err.into())
             }
             cedar_policy_validator::SchemaError::ExprEscapeUsed => Self::ExprEscapeUsed,
+            cedar_policy_validator::SchemaError::ContextDefaultNotOptional(action, attr) => {
+                Self::ContextDefaultNotOptional(EntityUid(action), attr)
+            }
+            cedar_policy_validator::SchemaError::InvalidContextDefault(action, attr, err) => {
+                Self::InvalidContextDefault(EntityUid(action), attr, err)
+            }
+            cedar_policy_validator::SchemaError::UnsupportedAttributeDeprecation(attr) => {
+                Self::UnsupportedAttributeDeprecation(attr)
+            }
+            cedar_policy_validator::SchemaError::UnsupportedAttributeAnnotations(attr) => {
+                Self::UnsupportedAttributeAnnotations(attr)
+            }
+            cedar_policy_validator::SchemaError::StrictModeViolations(findings) => {
+                Self::StrictModeViolations(findings)
+            }
         }
     }
 }
@@ -1575,11 +2361,28 @@ pub struct SourceLocation<'a> {
 }
 
 impl<'a> SourceLocation<'a> {
+    /// Create a location in the policy `policy_id`, at `source_loc` if known
+    pub(crate) fn new(policy_id: PolicyId, source_loc: Option<parser::Loc>) -> Self {
+        Self {
+            policy_id,
+            source_loc,
+            phantom: PhantomData,
+        }
+    }
+
     /// Get the `PolicyId` for the policy at this source location.
     pub fn policy_id(&self) -> &PolicyId {
         &self.policy_id
     }
 
+    /// Locate an issue the validator didn't find the location of at the
+    /// text of its policy in the sources of `pset`, if `pset` has them
+    fn locate_in(&mut self, pset: &PolicySet) {
+        if self.source_loc.is_none() {
+            self.source_loc = pset.policy_loc(&self.policy_id);
+        }
+    }
+
     /// Get the start of the location. Returns `None` if this location does not
     /// have a range.
     pub fn range_start(&self) -> Option<usize> {
@@ -1714,10 +2517,37 @@ impl<'a> Diagnostic for ValidationWarning<'a> {
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, RefCast)]
 pub struct EntityId(ast::Eid);
 
+impl EntityId {
+    /// Create an `EntityId` from the raw id, e.g., `a"b` for the id written as
+    /// `"a\"b"` in Cedar syntax. The id is taken verbatim: quotes, backslashes,
+    /// and unicode characters need no escaping, and [`EntityId::as_ref`]
+    /// returns exactly `id`. Ids are in this form in the JSON formats, e.g., in
+    /// the `id` of an `__entity` escape.
+    ///
+    /// This is the same as [`EntityId::from_str`], without the `Result`.
+    /// ```
+    /// # use cedar_policy::EntityId;
+    /// let id = EntityId::from_raw(r#"a"b\c"#);
+    /// assert_eq!(id.as_ref(), r#"a"b\c"#);
+    /// assert_eq!(id.escaped(), r#"a\"b\\c"#);
+    /// ```
+    pub fn from_raw(id: &str) -> Self {
+        Self(ast::Eid::new(id))
+    }
+
+    /// The id escaped for Cedar syntax, i.e., as it is written between the
+    /// quotes of an entity uid in policies, e.g., `a\"b` for the raw id `a"b`.
+    /// Quotes, backslashes, and non-printable characters are escaped. This is
+    /// also the [`Display`](std::fmt::Display) of the `EntityId`.
+    pub fn escaped(&self) -> String {
+        self.to_string()
+    }
+}
+
 impl FromStr for EntityId {
     type Err = Infallible;
     fn from_str(eid_str: &str) -> Result<Self, Self::Err> {
-        Ok(Self(ast::Eid::new(eid_str)))
+        Ok(Self::from_raw(eid_str))
     }
 }
 
@@ -1728,8 +2558,8 @@ impl AsRef<str> for EntityId {
 }
 
 // Note that this Display formatter will format the EntityId as it would be expected
-// in the EntityUid string form. For instance, the `"alice"` in `User::"alice"`.
-// This means it adds quotes and potentially performs some escaping.
+// in the EntityUid string form. For instance, the `alice` in `User::"alice"`.
+// This means it potentially performs some escaping, but doesn't add the quotes.
 impl std::fmt::Display for EntityId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
@@ -1752,7 +2582,7 @@ impl std::fmt::Display for EntityId {
 /// ```
 #[repr(transparent)]
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, RefCast)]
-pub struct EntityTypeName(ast::Name);
+pub struct EntityTypeName(pub(crate) ast::Name);
 
 impl EntityTypeName {
     /// Get the basename of the `EntityTypeName` (ie, with namespaces stripped).
@@ -1850,7 +2680,7 @@ impl std::fmt::Display for EntityNamespace {
 // INVARIANT: this can never be an `ast::EntityType::Unspecified`
 #[repr(transparent)]
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, RefCast)]
-pub struct EntityUid(ast::EntityUID);
+pub struct EntityUid(pub(crate) ast::EntityUID);
 
 impl EntityUid {
     /// Returns the portion of the Euid that represents namespace and entity type
@@ -1907,13 +2737,53 @@ impl EntityUid {
     /// # assert_eq!(euid.type_name(), &EntityTypeName::from_str("User").unwrap());
     /// # assert_eq!(euid.id(), &EntityId::from_str("123abc").unwrap());
     /// ```
-    #[allow(clippy::result_large_err)]
-    pub fn from_json(json: serde_json::Value) -> Result<Self, impl miette::Diagnostic> {
-        let parsed: entities::EntityUidJson = serde_json::from_value(json)?;
-        // INVARIANT: There is no way to write down the unspecified entityuid
-        Ok::<Self, entities::JsonDeserializationError>(Self(
-            parsed.into_euid(|| JsonDeserializationErrorContext::EntityUid)?,
-        ))
+    #[allow(clippy::result_large_err)]
+    pub fn from_json(json: serde_json::Value) -> Result<Self, impl miette::Diagnostic> {
+        let parsed: entities::EntityUidJson = serde_json::from_value(json)?;
+        // INVARIANT: There is no way to write down the unspecified entityuid
+        Ok::<Self, entities::JsonDeserializationError>(Self(
+            parsed.into_euid(|| JsonDeserializationErrorContext::EntityUid)?,
+        ))
+    }
+
+    /// Render the `EntityUid` in Cedar syntax, e.g., `User::"a\"b"` for the
+    /// raw id `a"b`, escaping quotes, backslashes, and non-printable characters
+    /// in the id. The result always parses back to an equal `EntityUid` with
+    /// [`EntityUid::from_str`]. This is also the
+    /// [`Display`](std::fmt::Display) of the `EntityUid`.
+    /// ```
+    /// # use cedar_policy::{EntityId, EntityTypeName, EntityUid};
+    /// # use std::str::FromStr;
+    /// let type_name = EntityTypeName::from_str("User").unwrap();
+    /// let euid = EntityUid::from_type_name_and_id(type_name, EntityId::from_raw(r#"a"b"#));
+    /// assert_eq!(euid.display_escaped(), r#"User::"a\"b""#);
+    /// assert_eq!(EntityUid::from_str(&euid.display_escaped()).unwrap(), euid);
+    /// ```
+    pub fn display_escaped(&self) -> String {
+        self.to_string()
+    }
+
+    /// Convert the `EntityUid` to JSON, in the explicit `__entity` form which
+    /// [`EntityUid::from_json`] and the entities and request JSON formats
+    /// accept. The id is raw, i.e., not escaped.
+    /// ```
+    /// # use cedar_policy::{EntityId, EntityTypeName, EntityUid};
+    /// # use std::str::FromStr;
+    /// let type_name = EntityTypeName::from_str("User").unwrap();
+    /// let euid = EntityUid::from_type_name_and_id(type_name, EntityId::from_raw(r#"a"b"#));
+    /// assert_eq!(
+    ///     euid.to_json(),
+    ///     serde_json::json!({ "__entity": { "type": "User", "id": "a\"b" } })
+    /// );
+    /// assert_eq!(EntityUid::from_json(euid.to_json()).unwrap(), euid);
+    /// ```
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "__entity": {
+                "type": self.type_name().to_string(),
+                "id": self.id().as_ref(),
+            }
+        })
     }
 
     /// Testing utility for creating `EntityUids` a bit easier
@@ -2029,6 +2899,10 @@ pub struct PolicySet {
     policies: HashMap<PolicyId, Policy>,
     /// Templates in the set
     templates: HashMap<PolicyId, Template>,
+    /// Source texts the policies were parsed from, for rendering errors
+    sources: SourceMap,
+    /// Where in `sources` the policies and templates were parsed from
+    origins: HashMap<PolicyId, PolicyOrigin>,
 }
 
 impl PartialEq for PolicySet {
@@ -2047,10 +2921,83 @@ impl FromStr for PolicySet {
     /// Policy ids will default to "policy*" with numbers from 0.
     /// If you load more policies, do not use the default id, or there will be conflicts.
     ///
-    /// See [`Policy`] for more.
+    /// See [`Policy`] for more. The source text is retained, without a name,
+    /// see [`PolicySet::source_map`].
     fn from_str(policies: &str) -> Result<Self, Self::Err> {
+        Self::parse(None, policies)
+    }
+}
+
+impl PolicySet {
+    /// Parse `policies`, retaining them as the source named `name`, if any
+    fn parse(name: Option<String>, policies: &str) -> Result<Self, ParseErrors> {
         let (texts, pset) = parser::parse_policyset_and_also_return_policy_text(policies)?;
-        // PANIC SAFETY: By the invariant on `parse_policyset_and_also_return_policy_text(policies)`, every `PolicyId` in `pset.policies()` occurs as a key in `text`.
+        let mut set = Self::from_ast_and_texts(pset, &texts);
+        set.add_source(name, policies, &texts);
+        Ok(set)
+    }
+
+    /// Retain `text` as the source named `name`, if any, of the policies and
+    /// templates of the set whose texts are in `texts`, which are slices of
+    /// `text`
+    fn add_source(
+        &mut self,
+        name: Option<String>,
+        text: &str,
+        texts: &HashMap<ast::PolicyID, &str>,
+    ) {
+        let source = self.sources.push(name, text);
+        for (id, policy) in texts {
+            let id = PolicyId(id.clone());
+            if self.policies.contains_key(&id) || self.templates.contains_key(&id) {
+                // the offset of the slice `policy` in `text`
+                let start = policy.as_ptr() as usize - text.as_ptr() as usize;
+                self.origins.insert(
+                    id,
+                    PolicyOrigin {
+                        source,
+                        range: start..start + policy.len(),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Add the policies and templates of `parsed`, which was parsed from one
+    /// source, with their sources. The policies and templates are renumbered
+    /// from the number of policies and templates in the set, e.g., `policy0`
+    /// of `parsed` is `policy2` if the set has two.
+    // PANIC SAFETY: the new ids are numbered from the number of policies and templates in the set, so they aren't in the set
+    #[allow(clippy::expect_used)]
+    fn append_parsed(&mut self, parsed: Self) {
+        let first = self.policies.len() + self.templates.len();
+        let first_source = self.sources.append(parsed.sources);
+        for i in 0..parsed.policies.len() + parsed.templates.len() {
+            let id = PolicyId(ast::PolicyID::from_string(format!("policy{i}")));
+            let new_id = PolicyId(ast::PolicyID::from_string(format!("policy{}", first + i)));
+            if let Some(policy) = parsed.policies.get(&id) {
+                self.add(policy.new_id(new_id.clone()))
+                    .expect("renumbered policy should not be in the set");
+            } else if let Some(template) = parsed.templates.get(&id) {
+                self.add_template(template.new_id(new_id.clone()))
+                    .expect("renumbered template should not be in the set");
+            }
+            if let Some(origin) = parsed.origins.get(&id) {
+                self.origins.insert(
+                    new_id,
+                    PolicyOrigin {
+                        source: first_source + origin.source,
+                        range: origin.range.clone(),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Build a `PolicySet` from `pset` and the texts of its policies and
+    /// templates, which must have the `PolicyID` of every one of them as a key
+    fn from_ast_and_texts(pset: ast::PolicySet, texts: &HashMap<ast::PolicyID, &str>) -> Self {
+        // PANIC SAFETY: By the invariant on `texts`, which `parse_policyset_and_also_return_policy_text()` and `parse_policyset_with_recovery()` uphold, every `PolicyId` in `pset.policies()` occurs as a key in `texts`.
         #[allow(clippy::expect_used)]
         let policies = pset.policies().map(|p|
             (
@@ -2058,7 +3005,7 @@ impl FromStr for PolicySet {
                 Policy { lossless: LosslessPolicy::policy_or_template_text(*texts.get(p.id()).expect("internal invariant violation: policy id exists in asts but not texts")), ast: p.clone() }
             )
         ).collect();
-        // PANIC SAFETY: By the same invariant, every `PolicyId` in `pset.templates()` also occurs as a key in `text`.
+        // PANIC SAFETY: By the same invariant, every `PolicyId` in `pset.templates()` also occurs as a key in `texts`.
         #[allow(clippy::expect_used)]
         let templates = pset.templates().map(|t|
             (
@@ -2066,24 +3013,99 @@ impl FromStr for PolicySet {
                 Template { lossless: LosslessPolicy::policy_or_template_text(*texts.get(t.id()).expect("internal invariant violation: template id exists in asts but not ests")), ast: t.clone() }
             )
         ).collect();
-        Ok(Self {
+        Self {
             ast: pset,
             policies,
             templates,
-        })
+            sources: SourceMap::new(),
+            origins: HashMap::new(),
+        }
     }
-}
 
-impl PolicySet {
     /// Create a fresh empty `PolicySet`
     pub fn new() -> Self {
         Self {
             ast: ast::PolicySet::new(),
             policies: HashMap::new(),
             templates: HashMap::new(),
+            sources: SourceMap::new(),
+            origins: HashMap::new(),
+        }
+    }
+
+    /// Parse a policy set from `policies`, like [`PolicySet::from_str`],
+    /// retaining the source text as the source named `name`, e.g., the name
+    /// of the file it's from, so errors can be rendered with
+    /// [`render_diagnostic`](crate::render_diagnostic) and
+    /// [`PolicySet::source_map`].
+    pub fn from_source(name: impl Into<String>, policies: &str) -> Result<Self, ParseErrors> {
+        Self::parse(Some(name.into()), policies)
+    }
+
+    /// Parse a policy set from the policies of `sources`, e.g., the files of
+    /// a repository, retaining their texts and names, so the locations of
+    /// errors and of policies are in the sources they're from, see
+    /// [`SourceMap::locate`] and [`PolicySet::policy_location`]. Policy ids
+    /// are "policy*" with numbers from 0 across the sources, in order, as if
+    /// the sources were one text. This returns the errors of every source
+    /// that doesn't parse.
+    pub fn from_sources(
+        sources: impl IntoIterator<Item = PolicySource>,
+    ) -> Result<Self, ParseErrors> {
+        let mut set = Self::new();
+        let mut errs = ParseErrors::new();
+        for source in sources {
+            match Self::parse(Some(source.name().to_string()), source.text()) {
+                Ok(parsed) => set.append_parsed(parsed),
+                Err(e) => errs.extend(e),
+            }
+        }
+        if errs.is_empty() {
+            Ok(set)
+        } else {
+            Err(errs)
         }
     }
 
+    /// The location of the start of the policy or template `id` in the
+    /// source it was parsed from, if it was parsed by
+    /// [`PolicySet::from_sources`], [`PolicySet::from_source`],
+    /// [`PolicySet::from_str`], or [`PolicySet::parse_with_recovery`]
+    pub fn policy_location(&self, id: &PolicyId) -> Option<SourcePosition> {
+        let origin = self.origins.get(id)?;
+        self.sources.location(origin.source, origin.range.start)
+    }
+
+    /// The text of the policy or template `id` in the source it was parsed
+    /// from, if it was parsed from one
+    fn policy_loc(&self, id: &PolicyId) -> Option<parser::Loc> {
+        let origin = self.origins.get(id)?;
+        self.sources.loc(origin.source, origin.range.clone())
+    }
+
+    /// Parse `policies`, continuing after errors to report the errors of every
+    /// policy rather than only the first, e.g., for authors fixing a large
+    /// file. This returns the policy set of the policies without errors, and
+    /// the errors of the others, if any. Policies have the ids they'd have if
+    /// every policy parsed, e.g., the third policy is `policy2` even if the
+    /// second has an error, since the parser skips from an error to the `;`
+    /// ending the policy. The policy set is empty if the parser can't
+    /// continue, e.g., after a string with no closing `"`.
+    pub fn parse_with_recovery(policies: &str) -> (Self, Option<ParseErrors>) {
+        let (texts, pset, errs) = parser::parse_policyset_with_recovery(policies);
+        let mut set = Self::from_ast_and_texts(pset, &texts);
+        set.add_source(None, policies, &texts);
+        (set, (!errs.is_empty()).then_some(errs))
+    }
+
+    /// The source texts this policy set was parsed from, by
+    /// [`PolicySet::from_sources`], [`PolicySet::from_source`], or
+    /// [`PolicySet::from_str`]. Policies added to the set afterwards don't
+    /// add their sources.
+    pub fn source_map(&self) -> &SourceMap {
+        &self.sources
+    }
+
     /// Create a `PolicySet` from the given policies
     pub fn from_policies(
         policies: impl IntoIterator<Item = Policy>,
@@ -2121,6 +3143,7 @@ impl PolicySet {
             .remove_static(&ast::PolicyID::from_string(policy_id.to_string()))
             .is_ok()
         {
+            self.origins.remove(&policy_id);
             Ok(policy)
         } else {
             //Restore self.policies
@@ -2152,7 +3175,10 @@ impl PolicySet {
             .ast
             .remove_template(&ast::PolicyID::from_string(template_id.to_string()))
         {
-            Ok(_) => Ok(template),
+            Ok(_) => {
+                self.origins.remove(&template_id);
+                Ok(template)
+            }
             Err(ast::PolicySetTemplateRemovalError::RemoveTemplateWithLinksError(_)) => {
                 self.templates.insert(template_id.clone(), template);
                 Err(PolicySetError::RemoveTemplateWithActiveLinksError(
@@ -2230,6 +3256,133 @@ impl PolicySet {
         self.ast.is_empty()
     }
 
+    /// Compute complexity metrics of the policy set, e.g., to enforce a
+    /// budget on its size, or to track its growth over time.
+    ///
+    /// The metrics of expressions are computed once for each template, and
+    /// once for each static policy, as template-linked policies share the
+    /// conditions of their templates.
+    pub fn metrics(&self) -> PolicySetMetrics {
+        let mut metrics = PolicySetMetrics::default();
+        for policy in self.ast.policies() {
+            match policy.effect() {
+                Effect::Permit => metrics.permits += 1,
+                Effect::Forbid => metrics.forbids += 1,
+            }
+            if !policy.is_static() {
+                metrics.template_links += 1;
+            }
+            metrics.entity_literals.extend(
+                policy
+                    .env()
+                    .values()
+                    .map(|uid| EntityUid::ref_cast(uid).clone()),
+            );
+        }
+        for template in self.ast.all_templates() {
+            if !template.is_static() {
+                metrics.templates += 1;
+            }
+            let condition = template.condition();
+            *metrics.depths.entry(condition.depth()).or_default() += 1;
+            for expr in condition.subexpressions() {
+                match expr.expr_kind() {
+                    ast::ExprKind::ExtensionFunctionApp { fn_name, .. } => {
+                        *metrics
+                            .extension_functions
+                            .entry(fn_name.to_string())
+                            .or_default() += 1;
+                    }
+                    ast::ExprKind::Lit(ast::Literal::EntityUID(uid)) => {
+                        metrics
+                            .entity_literals
+                            .insert(EntityUid::ref_cast(uid).clone());
+                    }
+                    _ => (),
+                }
+            }
+        }
+        metrics
+    }
+
+    /// Evaluate the policies on each request of `corpus`, e.g., requests
+    /// recorded in production with the entities they were authorized against,
+    /// and report how often each policy, its scope, and each of its `when`
+    /// and `unless` clauses were satisfied, not satisfied, or errored.
+    /// Policies which are never satisfied are candidates for removal.
+    ///
+    /// As when authorizing, the clauses of a policy are evaluated in order
+    /// after its scope, and only until one of them isn't satisfied or errors.
+    pub fn coverage<'a>(
+        &self,
+        corpus: impl IntoIterator<Item = (&'a Request, &'a Entities)>,
+    ) -> PolicySetCoverage {
+        let policies = self
+            .policies()
+            .map(|policy| (policy, policy.clauses()))
+            .collect::<Vec<_>>();
+        let mut coverage = PolicySetCoverage {
+            requests: 0,
+            policies: policies
+                .iter()
+                .map(|(policy, clauses)| {
+                    let clauses = clauses
+                        .iter()
+                        .map(|(kind, _)| ClauseCoverage {
+                            kind: *kind,
+                            counts: CoverageCounts::default(),
+                        })
+                        .collect();
+                    (
+                        policy.id().clone(),
+                        PolicyCoverage {
+                            counts: CoverageCounts::default(),
+                            scope: CoverageCounts::default(),
+                            clauses,
+                        },
+                    )
+                })
+                .collect(),
+        };
+        let extensions = Extensions::all_available();
+        for (request, entities) in corpus {
+            coverage.requests += 1;
+            let evaluator = Evaluator::new(request.0.clone(), &entities.0, &extensions);
+            for (policy, clauses) in &policies {
+                let Some(policy_coverage) = coverage.policies.get_mut(policy.id()) else {
+                    continue;
+                };
+                let eval = |expr: &ast::Expr| match evaluator.interpret(expr, policy.ast.env()) {
+                    Ok(ast::Value::Lit(ast::Literal::Bool(b))) => Some(b),
+                    _ => None,
+                };
+                let scope = ast::Expr::and(
+                    ast::Expr::and(
+                        policy.ast.principal_constraint().as_expr(),
+                        policy.ast.action_constraint().as_expr(),
+                    ),
+                    policy.ast.resource_constraint().as_expr(),
+                );
+                let mut satisfied = eval(&scope);
+                policy_coverage.scope.record(satisfied);
+                for ((kind, expr), clause_coverage) in
+                    clauses.iter().zip(policy_coverage.clauses.iter_mut())
+                {
+                    if satisfied != Some(true) {
+                        break;
+                    }
+                    satisfied = eval(expr).map(|b| match kind {
+                        ClauseKind::When => b,
+                        ClauseKind::Unless => !b,
+                    });
+                    clause_coverage.counts.record(satisfied);
+                }
+                policy_coverage.counts.record(satisfied);
+            }
+        }
+        coverage
+    }
+
     /// Attempt to link a template and add the new template-linked policy to the policy set.
     /// If link fails, the `PolicySet` is not modified.
     /// Failure can happen for three reasons
@@ -2351,8 +3504,7 @@ impl PolicySet {
     /// create the ESTs from the policy text or CST instead, as the conversion
     /// to AST is lossy. ESTs generated by this method will reflect the AST and
     /// not the original policy syntax.
-    #[cfg_attr(not(feature = "partial-eval"), allow(unused))]
-    fn from_ast(ast: ast::PolicySet) -> Self {
+    pub(crate) fn from_ast(ast: ast::PolicySet) -> Self {
         let policies = ast
             .policies()
             .map(|p| (PolicyId(p.id().clone()), Policy::from_ast(p.clone())))
@@ -2365,7 +3517,313 @@ impl PolicySet {
             ast,
             policies,
             templates,
+            sources: SourceMap::new(),
+            origins: HashMap::new(),
+        }
+    }
+}
+
+/// Coverage of a [`PolicySet`] by a corpus of requests, see
+/// [`PolicySet::coverage`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PolicySetCoverage {
+    requests: usize,
+    policies: HashMap<PolicyId, PolicyCoverage>,
+}
+
+impl PolicySetCoverage {
+    /// Number of requests in the corpus
+    pub fn requests(&self) -> usize {
+        self.requests
+    }
+
+    /// Coverage of the policy with id `id`, static or template-linked
+    pub fn policy(&self, id: &PolicyId) -> Option<&PolicyCoverage> {
+        self.policies.get(id)
+    }
+
+    /// Iterate over the coverage of every policy, static or template-linked
+    pub fn policies(&self) -> impl Iterator<Item = (&PolicyId, &PolicyCoverage)> {
+        self.policies.iter()
+    }
+
+    /// Iterate over the ids of the policies which no request of the corpus
+    /// satisfied
+    pub fn never_satisfied(&self) -> impl Iterator<Item = &PolicyId> {
+        self.policies
+            .iter()
+            .filter(|(_, coverage)| coverage.counts.satisfied == 0)
+            .map(|(id, _)| id)
+    }
+}
+
+/// Coverage of a policy by a corpus of requests
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PolicyCoverage {
+    counts: CoverageCounts,
+    scope: CoverageCounts,
+    clauses: Vec<ClauseCoverage>,
+}
+
+impl PolicyCoverage {
+    /// Number of requests which satisfied the policy
+    pub fn satisfied(&self) -> usize {
+        self.counts.satisfied
+    }
+
+    /// Number of requests which didn't satisfy the policy, without errors
+    pub fn not_satisfied(&self) -> usize {
+        self.counts.not_satisfied
+    }
+
+    /// Number of requests for which evaluating the policy errored
+    pub fn errored(&self) -> usize {
+        self.counts.errored
+    }
+
+    /// Coverage of the scope of the policy, i.e., its constraints on the
+    /// principal, action, and resource
+    pub fn scope(&self) -> &CoverageCounts {
+        &self.scope
+    }
+
+    /// Coverage of each `when` and `unless` clause of the policy, in order
+    pub fn clauses(&self) -> &[ClauseCoverage] {
+        &self.clauses
+    }
+}
+
+/// Coverage of a `when` or `unless` clause of a policy by a corpus of
+/// requests. A clause is only evaluated for the requests which satisfy the
+/// scope and the clauses before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClauseCoverage {
+    kind: ClauseKind,
+    counts: CoverageCounts,
+}
+
+impl ClauseCoverage {
+    /// Whether the clause is a `when` or `unless` clause
+    pub fn kind(&self) -> ClauseKind {
+        self.kind
+    }
+
+    /// Number of requests which satisfied the clause and its condition: for
+    /// an `unless` clause, for which its condition was false
+    pub fn satisfied(&self) -> usize {
+        self.counts.satisfied
+    }
+
+    /// Number of requests which didn't satisfy the clause, without errors
+    pub fn not_satisfied(&self) -> usize {
+        self.counts.not_satisfied
+    }
+
+    /// Number of requests for which evaluating the clause errored
+    pub fn errored(&self) -> usize {
+        self.counts.errored
+    }
+}
+
+/// The kind of a clause of a policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClauseKind {
+    /// A `when` clause
+    When,
+    /// An `unless` clause
+    Unless,
+}
+
+/// Number of requests which satisfied, didn't satisfy, or errored on a
+/// part of a policy
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CoverageCounts {
+    satisfied: usize,
+    not_satisfied: usize,
+    errored: usize,
+}
+
+impl CoverageCounts {
+    /// Number of requests which satisfied it
+    pub fn satisfied(&self) -> usize {
+        self.satisfied
+    }
+
+    /// Number of requests which didn't satisfy it, without errors
+    pub fn not_satisfied(&self) -> usize {
+        self.not_satisfied
+    }
+
+    /// Number of requests for which evaluating it errored
+    pub fn errored(&self) -> usize {
+        self.errored
+    }
+
+    /// Count a result of evaluation: `None` is an error
+    fn record(&mut self, satisfied: Option<bool>) {
+        match satisfied {
+            Some(true) => self.satisfied += 1,
+            Some(false) => self.not_satisfied += 1,
+            None => self.errored += 1,
+        }
+    }
+}
+
+/// Complexity metrics of a [`PolicySet`], see [`PolicySet::metrics`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PolicySetMetrics {
+    permits: usize,
+    forbids: usize,
+    templates: usize,
+    template_links: usize,
+    /// Number of templates and static policies with each expression depth
+    depths: BTreeMap<usize, usize>,
+    /// Number of calls of each extension function
+    extension_functions: BTreeMap<String, usize>,
+    entity_literals: BTreeSet<EntityUid>,
+}
+
+impl PolicySetMetrics {
+    /// Number of `permit` policies, static or template-linked
+    pub fn permits(&self) -> usize {
+        self.permits
+    }
+
+    /// Number of `forbid` policies, static or template-linked
+    pub fn forbids(&self) -> usize {
+        self.forbids
+    }
+
+    /// Number of templates
+    pub fn templates(&self) -> usize {
+        self.templates
+    }
+
+    /// Number of template-linked policies
+    pub fn template_links(&self) -> usize {
+        self.template_links
+    }
+
+    /// Depth of the deepest condition of a template or static policy, or 0 if
+    /// there are none. The condition includes the constraints of the scope,
+    /// and the depth of `permit(principal, action, resource);` is 1.
+    pub fn max_expression_depth(&self) -> usize {
+        self.depths.keys().next_back().copied().unwrap_or(0)
+    }
+
+    /// Distribution of the depths of the conditions of templates and static
+    /// policies: the number of them with each depth
+    pub fn expression_depths(&self) -> &BTreeMap<usize, usize> {
+        &self.depths
+    }
+
+    /// Number of calls of each extension function, by its name, e.g.,
+    /// `ip` or `isInRange`
+    pub fn extension_function_calls(&self) -> &BTreeMap<String, usize> {
+        &self.extension_functions
+    }
+
+    /// Entity literals referenced by the policies, including the values of
+    /// the slots of template-linked policies
+    pub fn entity_literals(&self) -> &BTreeSet<EntityUid> {
+        &self.entity_literals
+    }
+}
+
+/// Estimate of the worst-case cost of evaluating a policy or template, see
+/// [`Policy::complexity`]. The condition includes the constraints of the
+/// scope.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PolicyComplexity {
+    expression_size: usize,
+    hierarchy_operations: usize,
+    set_operations: usize,
+    pattern_elements: usize,
+    max_set_literal: usize,
+}
+
+impl PolicyComplexity {
+    /// Weight of an operation traversing the entity hierarchy in the score
+    pub const HIERARCHY_WEIGHT: u64 = 20;
+    /// Weight of an element comparison of a set operation in the score
+    pub const SET_WEIGHT: u64 = 5;
+
+    fn of(condition: &ast::Expr) -> Self {
+        let set_size = |expr: &ast::Expr| match expr.expr_kind() {
+            ast::ExprKind::Set(elements) => elements.len().max(1),
+            _ => 1,
+        };
+        let mut complexity = Self::default();
+        for expr in condition.subexpressions() {
+            complexity.expression_size += 1;
+            match expr.expr_kind() {
+                // `e in [a, b]` traverses the hierarchy for each element
+                ast::ExprKind::BinaryApp {
+                    op: ast::BinaryOp::In,
+                    arg2,
+                    ..
+                } => complexity.hierarchy_operations += set_size(arg2),
+                ast::ExprKind::BinaryApp {
+                    op: ast::BinaryOp::Contains,
+                    ..
+                } => complexity.set_operations += 1,
+                ast::ExprKind::BinaryApp {
+                    op: ast::BinaryOp::ContainsAll | ast::BinaryOp::ContainsAny,
+                    arg1,
+                    arg2,
+                } => complexity.set_operations += set_size(arg1) * set_size(arg2),
+                ast::ExprKind::Like { pattern, .. } => {
+                    complexity.pattern_elements += pattern.get_elems().len();
+                }
+                ast::ExprKind::Set(elements) => {
+                    complexity.max_set_literal = complexity.max_set_literal.max(elements.len());
+                }
+                _ => (),
+            }
         }
+        complexity
+    }
+
+    /// Number of nodes of the condition
+    pub fn expression_size(&self) -> usize {
+        self.expression_size
+    }
+
+    /// Number of traversals of the entity hierarchy, by `in`, counting each
+    /// element of a set literal on the right of `in`
+    pub fn hierarchy_operations(&self) -> usize {
+        self.hierarchy_operations
+    }
+
+    /// Number of set operations, `contains`, `containsAll`, and
+    /// `containsAny`, counting `containsAll` and `containsAny` of set
+    /// literals as the product of their sizes
+    pub fn set_operations(&self) -> usize {
+        self.set_operations
+    }
+
+    /// Number of characters and wildcards of the patterns of `like`
+    pub fn pattern_elements(&self) -> usize {
+        self.pattern_elements
+    }
+
+    /// Number of elements of the largest set literal, or 0 if there are none
+    pub fn max_set_literal(&self) -> usize {
+        self.max_set_literal
+    }
+
+    /// The estimate, as a single number: the expression size, plus the
+    /// pattern elements, plus the hierarchy operations weighted by
+    /// [`Self::HIERARCHY_WEIGHT`], plus the set operations weighted by
+    /// [`Self::SET_WEIGHT`]. Sets and hierarchies of unknown size, e.g.,
+    /// attributes, count as one element, so compare scores to a budget
+    /// rather than to measured times.
+    pub fn score(&self) -> u64 {
+        let count = |n: usize| u64::try_from(n).unwrap_or(u64::MAX);
+        count(self.expression_size)
+            .saturating_add(count(self.pattern_elements))
+            .saturating_add(count(self.hierarchy_operations).saturating_mul(Self::HIERARCHY_WEIGHT))
+            .saturating_add(count(self.set_operations).saturating_mul(Self::SET_WEIGHT))
     }
 }
 
@@ -2531,6 +3989,12 @@ impl Template {
         }
     }
 
+    /// Estimate of the worst-case cost of evaluating the template, see
+    /// [`Policy::complexity`]
+    pub fn complexity(&self) -> PolicyComplexity {
+        PolicyComplexity::of(&self.ast.condition())
+    }
+
     /// Create a `Template` from its JSON representation.
     /// If `id` is Some, the policy will be given that Policy Id.
     /// If `id` is None, then "JSON policy" will be used.
@@ -2559,8 +4023,7 @@ impl Template {
     /// create the EST from the policy text or CST instead, as the conversion
     /// to AST is lossy. ESTs generated by this method will reflect the AST and
     /// not the original policy syntax.
-    #[cfg_attr(not(feature = "partial-eval"), allow(unused))]
-    fn from_ast(ast: ast::Template) -> Self {
+    pub(crate) fn from_ast(ast: ast::Template) -> Self {
         let text = ast.to_string(); // assume that pretty-printing is faster than `est::Policy::from(ast.clone())`; is that true?
         Self {
             ast,
@@ -2721,7 +4184,7 @@ impl AsRef<str> for PolicyId {
 pub struct Policy {
     /// AST representation of the policy, used for most operations.
     /// In particular, the `ast` contains the authoritative `PolicyId` for the policy.
-    ast: ast::Policy,
+    pub(crate) ast: ast::Policy,
     /// Some "lossless" representation of the policy, whichever is most
     /// convenient to provide (and can be provided with the least overhead).
     /// This is used just for `to_json()`.
@@ -2867,6 +4330,13 @@ impl Policy {
         }
     }
 
+    /// Estimate of the worst-case cost of evaluating the policy, e.g., to
+    /// reject policies over a budget before adding them to a store. A
+    /// template-linked policy has the complexity of its template.
+    pub fn complexity(&self) -> PolicyComplexity {
+        PolicyComplexity::of(&self.ast.condition())
+    }
+
     /// To avoid panicking, this function may only be called when `slot` is the
     /// `SlotId` corresponding to the scope constraint from which the entity
     /// reference `r` was extracted. I.e., If `r` is taken from the principal
@@ -3005,6 +4475,28 @@ impl Policy {
         Ok::<_, PolicyToJsonError>(json)
     }
 
+    /// The `when` and `unless` clauses of this policy, in order, or its whole
+    /// condition as a single `when` clause if its lossless representation
+    /// can't be converted to clauses
+    fn clauses(&self) -> Vec<(ClauseKind, ast::Expr)> {
+        let clauses = self.lossless.est().ok().and_then(|est| {
+            est.conditions()
+                .iter()
+                .map(|clause| match clause {
+                    est::Clause::When(expr) => Some((
+                        ClauseKind::When,
+                        expr.clone().try_into_ast(self.ast.id().clone()).ok()?,
+                    )),
+                    est::Clause::Unless(expr) => Some((
+                        ClauseKind::Unless,
+                        expr.clone().try_into_ast(self.ast.id().clone()).ok()?,
+                    )),
+                })
+                .collect::<Option<Vec<_>>>()
+        });
+        clauses.unwrap_or_else(|| vec![(ClauseKind::When, self.ast.non_head_constraints().clone())])
+    }
+
     /// Create a `Policy` from its AST representation only. The `LosslessPolicy`
     /// will reflect the AST structure. When possible, don't use this method and
     /// create the `Policy` from the policy text, CST, or EST instead, as the
@@ -3181,6 +4673,12 @@ impl Expression {
     pub fn new_set(values: impl IntoIterator<Item = Self>) -> Self {
         Self(ast::Expr::set(values.into_iter().map(|v| v.0)))
     }
+
+    /// Create an expression testing whether `expr` matches `pattern`, as with
+    /// the `like` operator.
+    pub fn like(expr: Self, pattern: &Pattern) -> Self {
+        Self(ast::Expr::like(expr.0, pattern.0.iter().copied()))
+    }
 }
 
 impl FromStr for Expression {
@@ -3192,6 +4690,100 @@ impl FromStr for Expression {
     }
 }
 
+/// A pattern of the `like` operator, built from literal text and wildcards.
+/// Literal text is matched exactly, even if it has `*`s, so a pattern can
+/// have text from a user without the user being able to add wildcards.
+///
+/// It displays as a string literal in the Cedar syntax, with the `*`s of
+/// literal text escaped, so it can also be put in the text of a policy.
+/// ```
+/// # use cedar_policy::{Expression, Pattern, Policy};
+/// # use std::str::FromStr;
+/// let folder = "reports/*";
+/// let pattern = Pattern::literal(folder).wildcard().then_literal(".pdf");
+/// assert!(pattern.matches("reports/*/2023.pdf"));
+/// assert!(!pattern.matches("reports/2023/2023.pdf"));
+/// assert_eq!(pattern.to_string(), r#""reports/\**.pdf""#);
+///
+/// let expr = Expression::like(Expression::from_str("resource.path").unwrap(), &pattern);
+/// let policy = format!("permit(principal, action, resource) when {{ resource.path like {pattern} }};");
+/// assert!(Policy::parse(None, policy).is_ok());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Pattern(Vec<ast::PatternElem>);
+
+impl Pattern {
+    /// The empty pattern, which only matches the empty string
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A pattern matching exactly `text`
+    pub fn literal(text: &str) -> Self {
+        Self::new().then_literal(text)
+    }
+
+    /// A pattern matching any string
+    pub fn any() -> Self {
+        Self::new().wildcard()
+    }
+
+    /// A pattern matching strings which start with `prefix`
+    pub fn starts_with(prefix: &str) -> Self {
+        Self::literal(prefix).wildcard()
+    }
+
+    /// A pattern matching strings which end with `suffix`
+    pub fn ends_with(suffix: &str) -> Self {
+        Self::any().then_literal(suffix)
+    }
+
+    /// A pattern matching strings which contain `text`
+    pub fn contains(text: &str) -> Self {
+        Self::any().then_literal(text).wildcard()
+    }
+
+    /// This pattern followed by exactly `text`
+    #[must_use]
+    pub fn then_literal(mut self, text: &str) -> Self {
+        self.0.extend(text.chars().map(ast::PatternElem::Char));
+        self
+    }
+
+    /// This pattern followed by a wildcard, which matches any string. A
+    /// wildcard right after another one is dropped, as it matches nothing
+    /// more.
+    #[must_use]
+    pub fn wildcard(mut self) -> Self {
+        if self.0.last() != Some(&ast::PatternElem::Wildcard) {
+            self.0.push(ast::PatternElem::Wildcard);
+        }
+        self
+    }
+
+    /// This pattern followed by `other`
+    #[must_use]
+    pub fn then(mut self, other: &Self) -> Self {
+        self.0.extend(other.0.iter().copied());
+        // a wildcard right after another one matches nothing more
+        self.0
+            .dedup_by(|a, b| *a == ast::PatternElem::Wildcard && a == b);
+        self
+    }
+
+    /// Whether `text` matches the pattern, as with the `like` operator
+    pub fn matches(&self, text: &str) -> bool {
+        ast::Pattern::new(self.0.iter().copied()).wildcard_match(text)
+    }
+}
+
+/// A string literal in the Cedar syntax, e.g., `"a\**"`
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\"{}\"", ast::Pattern::new(self.0.iter().copied()))
+    }
+}
+
 /// "Restricted" expressions are used for attribute values and `context`.
 ///
 /// Restricted expressions can contain only the following:
@@ -3247,6 +4839,15 @@ impl RestrictedExpression {
     pub fn new_set(values: impl IntoIterator<Item = Self>) -> Self {
         Self(ast::RestrictedExpr::set(values.into_iter().map(|v| v.0)))
     }
+
+    /// Create an unknown expression, which partial evaluation will leave in
+    /// its residuals.
+    #[cfg(feature = "partial-eval")]
+    pub fn new_unknown(name: impl AsRef<str>) -> Self {
+        Self(ast::RestrictedExpr::unknown(ast::Unknown::new_untyped(
+            name.as_ref(),
+        )))
+    }
 }
 
 impl FromStr for RestrictedExpression {
@@ -3580,7 +5181,9 @@ impl Context {
     /// will allow `__entity` and `__extn` escapes to be implicit, and it will error
     /// if attributes have the wrong types (e.g., string instead of integer).
     /// Since different Actions have different schemas for `Context`, you also
-    /// must specify the `Action` for schema-based parsing.
+    /// must specify the `Action` for schema-based parsing. Optional attributes
+    /// which `json` doesn't have are given the values in the action's
+    /// `contextDefaults`, if it has any for them.
     /// ```
     /// # use cedar_policy::{Context, EntityUid, EntityId, EntityTypeName, RestrictedExpression, Request, Schema};
     /// # use std::str::FromStr;
@@ -3633,6 +5236,33 @@ impl Context {
         Ok(Self(context))
     }
 
+    /// Create a `Context` from a `serde_json::Value` like
+    /// [`Context::from_json_value`] with a schema, but error if `json` has
+    /// attributes which aren't declared in the context of the action, even if
+    /// the context type has `additionalAttributes`. The error lists all of
+    /// them, with the nearest declared names, e.g., to catch a misspelled
+    /// optional attribute, which the policies would otherwise see as absent.
+    pub fn from_json_value_strict(
+        json: serde_json::Value,
+        schema: (&Schema, &EntityUid),
+    ) -> Result<Self, ContextJsonError> {
+        let (s, action) = schema;
+        if let serde_json::Value::Object(attrs) = &json {
+            let undeclared =
+                cedar_policy_validator::undeclared_context_attributes(&s.0, &action.0, attrs)
+                    .ok_or_else(|| ContextJsonError::MissingAction {
+                        action: action.clone(),
+                    })?;
+            if !undeclared.is_empty() {
+                return Err(ContextJsonError::UndeclaredAttributes {
+                    action: action.clone(),
+                    attrs: undeclared,
+                });
+            }
+        }
+        Self::from_json_value(json, Some(schema))
+    }
+
     /// Create a `Context` from a JSON file.  The JSON file must contain a JSON
     /// object, not any other JSON type, or you will get an error here.
     /// JSON here must use the `__entity` and `__extn` escapes for entity
@@ -3703,6 +5333,15 @@ pub enum ContextJsonError {
         /// UID of the action which doesn't exist
         action: EntityUid,
     },
+    /// The context has attributes which aren't declared for the action, see
+    /// [`Context::from_json_value_strict`]
+    #[error("context has attributes not declared for action `{action}`: {}", .attrs.iter().join(", "))]
+    UndeclaredAttributes {
+        /// UID of the action
+        action: EntityUid,
+        /// The undeclared attributes, with the nearest declared names
+        attrs: Vec<UndeclaredContextAttribute>,
+    },
 }
 
 impl std::fmt::Display for Request {
@@ -3862,6 +5501,70 @@ pub fn eval_expression(
     ))
 }
 
+/// Find the requests whose decision changes from the policy set `old` to `new`.
+///
+/// Each of `requests`, e.g., requests recorded in production before deploying
+/// `new`, is authorized against `entities` with both policy sets. The changes
+/// are returned in the order of the requests, with the responses of both
+/// policy sets, whose diagnostics give the policies responsible for each
+/// decision.
+pub fn compare_decisions<'a>(
+    old: &PolicySet,
+    new: &PolicySet,
+    requests: impl IntoIterator<Item = &'a Request>,
+    entities: &Entities,
+) -> Vec<DecisionChange<'a>> {
+    let authorizer = Authorizer::new();
+    requests
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, request)| {
+            let old = authorizer.is_authorized(request, old, entities);
+            let new = authorizer.is_authorized(request, new, entities);
+            (old.decision() != new.decision()).then_some(DecisionChange {
+                index,
+                request,
+                old,
+                new,
+            })
+        })
+        .collect()
+}
+
+/// A request whose decision changes between two policy sets, see
+/// [`compare_decisions`]
+#[derive(Debug, Clone)]
+pub struct DecisionChange<'a> {
+    index: usize,
+    request: &'a Request,
+    old: Response,
+    new: Response,
+}
+
+impl<'a> DecisionChange<'a> {
+    /// Position of the request in the requests which were compared
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The request
+    pub fn request(&self) -> &'a Request {
+        self.request
+    }
+
+    /// Response of the old policy set. Its reason is the policies responsible
+    /// for the old decision.
+    pub fn old_response(&self) -> &Response {
+        &self.old
+    }
+
+    /// Response of the new policy set. Its reason is the policies responsible
+    /// for the new decision.
+    pub fn new_response(&self) -> &Response {
+        &self.new
+    }
+}
+
 #[cfg(test)]
 #[cfg(feature = "partial-eval")]
 mod partial_eval_test {
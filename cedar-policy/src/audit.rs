@@ -0,0 +1,295 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module contains the audit events which an
+//! [`Authorizer`](crate::Authorizer) emits for each decision to an
+//! [`AuditSink`], and a sink which writes them as JSON lines.
+//! ```ignore
+//! let sink = Arc::new(JsonLinesAuditSink::new(File::create("decisions.jsonl")?));
+//! let authorizer = Authorizer::new().with_audit_sink(sink.clone());
+//! authorizer.is_authorized(&request, &policies, &entities);
+//! ```
+//!
+//! An event has a fingerprint of the request, the principal, action, and
+//! resource, the decision, the policies which determined it with their
+//! annotations, the errors evaluating policies, and the time taken. The
+//! context is only included in the fingerprint, as it may be large or
+//! contain sensitive data.
+
+use crate::fingerprint::Fingerprint;
+use crate::{Decision, PolicySet, Request, Response};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A destination for the audit events of an [`Authorizer`](crate::Authorizer),
+/// which calls [`AuditSink::record`] once for every decision it makes
+pub trait AuditSink: Send + Sync {
+    /// Record the audit event of a decision. This is called on the thread
+    /// which made the decision, before the response is returned, so it should
+    /// be fast, e.g., by queuing the event.
+    fn record(&self, event: &AuditEvent);
+}
+
+/// The record of an authorization decision
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEvent {
+    request_fingerprint: String,
+    principal: Option<String>,
+    action: Option<String>,
+    resource: Option<String>,
+    decision: Decision,
+    determining_policies: Vec<DeterminingPolicy>,
+    errors: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_micros: Option<u64>,
+}
+
+impl AuditEvent {
+    /// The event of the decision `response` for `request` by `policies`
+    pub(crate) fn new(
+        request: &Request,
+        policies: &PolicySet,
+        response: &Response,
+        duration: Option<Duration>,
+    ) -> Self {
+        let mut determining_policies: Vec<DeterminingPolicy> = response
+            .diagnostics()
+            .reason()
+            .map(|id| DeterminingPolicy {
+                id: id.to_string(),
+                annotations: policies
+                    .policy(id)
+                    .map(|policy| {
+                        policy
+                            .annotations()
+                            .map(|(k, v)| (k.to_string(), v.to_string()))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            })
+            .collect();
+        determining_policies.sort_by(|a, b| a.id.cmp(&b.id));
+        Self {
+            request_fingerprint: request_fingerprint(request),
+            principal: request.principal().map(ToString::to_string),
+            action: request.action().map(ToString::to_string),
+            resource: request.resource().map(ToString::to_string),
+            decision: response.decision(),
+            determining_policies,
+            errors: response
+                .diagnostics()
+                .errors()
+                .map(ToString::to_string)
+                .collect(),
+            // durations over 584,000 years saturate
+            #[allow(clippy::cast_possible_truncation)]
+            duration_micros: duration.map(|d| d.as_micros().min(u128::from(u64::MAX)) as u64),
+        }
+    }
+
+    /// A fingerprint of the request, including its context: the 32 hex
+    /// digits of a [`Fingerprint`], which are the same for equal requests
+    pub fn request_fingerprint(&self) -> &str {
+        &self.request_fingerprint
+    }
+
+    /// The principal, if it's known
+    pub fn principal(&self) -> Option<&str> {
+        self.principal.as_deref()
+    }
+
+    /// The action, if it's known
+    pub fn action(&self) -> Option<&str> {
+        self.action.as_deref()
+    }
+
+    /// The resource, if it's known
+    pub fn resource(&self) -> Option<&str> {
+        self.resource.as_deref()
+    }
+
+    /// The decision
+    pub fn decision(&self) -> Decision {
+        self.decision
+    }
+
+    /// The policies which determined the decision, sorted by id
+    pub fn determining_policies(&self) -> &[DeterminingPolicy] {
+        &self.determining_policies
+    }
+
+    /// The errors evaluating policies
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+
+    /// The time taken to make the decision. This is `None` on
+    /// `wasm32-unknown-unknown`, which has no clock.
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration_micros.map(Duration::from_micros)
+    }
+}
+
+/// A policy which determined a decision, with its annotations
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeterminingPolicy {
+    id: String,
+    annotations: BTreeMap<String, String>,
+}
+
+impl DeterminingPolicy {
+    /// The id of the policy
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The annotations of the policy
+    pub fn annotations(&self) -> &BTreeMap<String, String> {
+        &self.annotations
+    }
+}
+
+/// The fingerprint of the text of `request`
+fn request_fingerprint(request: &Request) -> String {
+    Fingerprint::of(request.to_string().as_bytes()).to_string()
+}
+
+/// An [`AuditSink`] which writes each event as a line of JSON. Failing to
+/// write an event doesn't fail the authorization call: the error is kept,
+/// and returned by [`JsonLinesAuditSink::take_error`].
+pub struct JsonLinesAuditSink<W> {
+    writer: Mutex<W>,
+    error: Mutex<Option<io::Error>>,
+}
+
+impl<W: Write + Send> JsonLinesAuditSink<W> {
+    /// Write the events to `writer`. Each event is written with one call of
+    /// `write_all`, so wrap `writer` in a `BufWriter` to batch them.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+            error: Mutex::new(None),
+        }
+    }
+
+    /// The first error writing an event since the last call, if any
+    pub fn take_error(&self) -> Option<io::Error> {
+        self.error.lock().ok().and_then(|mut error| error.take())
+    }
+
+    /// The writer
+    pub fn into_inner(self) -> W {
+        self.writer
+            .into_inner()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl<W: Write + Send> AuditSink for JsonLinesAuditSink<W> {
+    fn record(&self, event: &AuditEvent) {
+        let result = serde_json::to_vec(event)
+            .map_err(io::Error::from)
+            .and_then(|mut line| {
+                line.push(b'\n');
+                let mut writer = self
+                    .writer
+                    .lock()
+                    .map_err(|_| io::Error::new(io::ErrorKind::Other, "audit writer poisoned"))?;
+                writer.write_all(&line)
+            });
+        if let Err(e) = result {
+            if let Ok(mut error) = self.error.lock() {
+                error.get_or_insert(e);
+            }
+        }
+    }
+}
+
+impl<W> fmt::Debug for JsonLinesAuditSink<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonLinesAuditSink").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Authorizer, Context, Entities, EntityUid};
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    fn request(principal: &str) -> Request {
+        let euid = |s: &str| Some(EntityUid::from_str(s).unwrap());
+        Request::new(
+            euid(principal),
+            euid(r#"Action::"view""#),
+            euid(r#"Photo::"a""#),
+            Context::empty(),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn fingerprints() {
+        let alice = request_fingerprint(&request(r#"User::"alice""#));
+        assert_eq!(alice.len(), 32);
+        assert_eq!(alice, request_fingerprint(&request(r#"User::"alice""#)));
+        assert_ne!(alice, request_fingerprint(&request(r#"User::"bob""#)));
+    }
+
+    #[test]
+    fn json_lines() {
+        let policies = PolicySet::from_str(
+            r#"@reason("owner") permit(principal == User::"alice", action, resource);
+            forbid(principal, action, resource) when { principal.suspended };"#,
+        )
+        .unwrap();
+        let sink = Arc::new(JsonLinesAuditSink::new(Vec::new()));
+        let authorizer = Authorizer::new().with_audit_sink(sink.clone());
+        authorizer.is_authorized(&request(r#"User::"alice""#), &policies, &Entities::empty());
+        authorizer.is_authorized(&request(r#"User::"bob""#), &policies, &Entities::empty());
+        drop(authorizer);
+        assert!(sink.take_error().is_none());
+
+        let output = Arc::try_unwrap(sink).unwrap().into_inner();
+        let lines: Vec<serde_json::Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["principal"], r#"User::"alice""#);
+        assert_eq!(lines[0]["decision"], "Allow");
+        assert_eq!(
+            lines[0]["determiningPolicies"],
+            serde_json::json!([{ "id": "policy0", "annotations": { "reason": "owner" } }])
+        );
+        // the forbid policy errors for both requests, as there are no entities
+        assert_eq!(lines[0]["errors"].as_array().map(Vec::len), Some(1));
+        assert_eq!(lines[1]["decision"], "Deny");
+        assert_eq!(lines[1]["determiningPolicies"], serde_json::json!([]));
+        assert_eq!(
+            lines[0]["requestFingerprint"],
+            request_fingerprint(&request(r#"User::"alice""#))
+        );
+    }
+}
@@ -0,0 +1,919 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module contains the translation between the JSON schema format and
+//! the human-readable Cedar schema format, e.g., for the `translate-schema`
+//! subcommand of the CLI.
+//!
+//! The Cedar schema format looks like
+//!
+//! ```text
+//! namespace PhotoApp {
+//!   type Address = { street: String, zip?: String };
+//!   @owner("identity")
+//!   entity User in [Group] { name: String, @sensitivity("pii") address: Address };
+//!   entity Group;
+//!   action "view" in ["read"] appliesTo {
+//!     principal: [User],
+//!     resource: [Photo],
+//!     context: { ip: ipaddr }
+//!   };
+//! }
+//! ```
+//!
+//! Declarations outside of a `namespace` block belong to the empty namespace.
+//! Entity types, actions, and the attributes of an entity type's shape can be
+//! preceded by annotations, like `@owner("identity")`.
+//!
+//! A schema file can include other schema files, in either format, with
+//! `include "common.cedarschema";` statements outside of any `namespace`
+//! block, which are written as a `"$include"` array of paths in the JSON
+//! schema format. Includes are resolved by
+//! [`Schema::from_files`](crate::Schema::from_files). A schema with includes
+//! is only part of a schema, so it isn't checked when it's translated.
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::tokens::{schema_tokens, TokenKind};
+use crate::{Schema, SchemaError};
+use miette::{Diagnostic, NamedSource, SourceSpan};
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+/// The key of the paths a schema in the JSON schema format includes
+pub(crate) const INCLUDE_KEY: &str = "$include";
+
+/// A construct which was dropped while translating a schema, because the
+/// target format can't express it
+#[derive(Debug, Diagnostic, Error)]
+pub enum TranslationWarning {
+    /// Action attributes can only be written in the JSON schema format
+    #[error("attributes of action `{action}` can't be expressed in the Cedar schema format and were dropped")]
+    #[diagnostic(severity(Warning))]
+    ActionAttributes {
+        /// Path to the action in the JSON schema
+        action: String,
+    },
+    /// `additionalAttributes` can only be written in the JSON schema format
+    #[error("`additionalAttributes` at `{location}` can't be expressed in the Cedar schema format and was dropped")]
+    #[diagnostic(severity(Warning))]
+    AdditionalAttributes {
+        /// Path to the record type in the JSON schema
+        location: String,
+    },
+    /// Comments can't be written in the JSON schema format
+    #[error("comments can't be expressed in the JSON schema format and were dropped")]
+    #[diagnostic(severity(Warning))]
+    Comments,
+}
+
+/// An error in the syntax of a schema in the Cedar schema format
+#[derive(Debug, Diagnostic, Error)]
+#[error("{message}")]
+pub struct SyntaxError {
+    message: String,
+    #[source_code]
+    src: NamedSource,
+    #[label]
+    span: SourceSpan,
+}
+
+/// An error translating a schema in the Cedar schema format
+#[derive(Debug, Diagnostic, Error)]
+pub enum CedarSchemaError {
+    /// The schema isn't syntactically valid
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Syntax(#[from] SyntaxError),
+    /// The schema is syntactically valid, but isn't a valid schema
+    #[error("translated schema is invalid")]
+    Schema(#[source] SchemaError),
+}
+
+/// Check that `json` is a valid schema in the JSON schema format, unless it
+/// includes other schema files, in which case it's only part of a schema
+pub fn check_json(json: &Value) -> Result<(), SchemaError> {
+    if json.get(INCLUDE_KEY).is_none() {
+        Schema::from_json_value(json.clone())?;
+    }
+    Ok(())
+}
+
+/// Translate a schema in the JSON schema format to the Cedar schema format
+pub fn json_to_cedar(json: &Value) -> Result<(String, Vec<TranslationWarning>), SchemaError> {
+    check_json(json)?;
+    let mut printer = CedarPrinter::default();
+    for path in json[INCLUDE_KEY].as_array().into_iter().flatten() {
+        printer.line(
+            0,
+            &format!("include {};", quoted(path.as_str().unwrap_or_default())),
+        );
+    }
+    for (namespace, ns_def) in sorted(json) {
+        if namespace != INCLUDE_KEY {
+            printer.namespace(namespace, ns_def);
+        }
+    }
+    Ok((printer.out.trim_end().to_owned(), printer.warnings))
+}
+
+/// Translate a schema in the Cedar schema format to the JSON schema format.
+/// `name` is the name of the file the schema was read from, for errors.
+pub fn cedar_to_json(
+    name: &str,
+    src: &str,
+) -> Result<(Value, Vec<TranslationWarning>), CedarSchemaError> {
+    let (json, has_comments) = parse(name, src)?;
+    let json = Value::Object(json);
+    check_json(&json).map_err(CedarSchemaError::Schema)?;
+    let warnings = if has_comments {
+        vec![TranslationWarning::Comments]
+    } else {
+        vec![]
+    };
+    Ok((json, warnings))
+}
+
+/// Parse a schema in the Cedar schema format into the JSON schema format,
+/// without checking that it's a valid schema. Also returns whether the schema
+/// had comments, which were dropped.
+pub(crate) fn parse(name: &str, src: &str) -> Result<(Map<String, Value>, bool), SyntaxError> {
+    let syntax_error = |message: String, span: Range<usize>| SyntaxError {
+        message,
+        src: NamedSource::new(name, src.to_owned()),
+        span: span.into(),
+    };
+    let (tokens, has_comments) = lex(src).map_err(|(msg, span)| syntax_error(msg, span))?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end: src.len(),
+    };
+    let (includes, namespaces) = parser
+        .schema()
+        .map_err(|(msg, span)| syntax_error(msg, span))?;
+    let mut json: Map<String, Value> = namespaces
+        .iter()
+        .map(|ns| (ns.name.clone(), ns.to_json()))
+        .collect();
+    if !includes.is_empty() {
+        json.insert(INCLUDE_KEY.to_owned(), json!(includes));
+    }
+    Ok((json, has_comments))
+}
+
+/// Entries of a JSON object sorted by key, or nothing if it isn't an object,
+/// e.g., to print the declarations of a JSON schema in a stable order
+pub fn sorted(value: &Value) -> Vec<(&String, &Value)> {
+    let mut entries: Vec<_> = value.as_object().into_iter().flatten().collect();
+    entries.sort_by_key(|(k, _)| *k);
+    entries
+}
+
+/// Whether `s` is an identifier, which can be written without quotes in the
+/// Cedar schema format
+pub fn is_ident(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// A JSON string literal, which is also a Cedar schema string literal
+pub fn quoted(s: &str) -> String {
+    Value::String(s.to_owned()).to_string()
+}
+
+#[derive(Debug, Default)]
+struct CedarPrinter {
+    out: String,
+    warnings: Vec<TranslationWarning>,
+}
+
+impl CedarPrinter {
+    fn line(&mut self, indent: usize, text: &str) {
+        self.out.push_str(&"  ".repeat(indent));
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn namespace(&mut self, namespace: &str, ns_def: &Value) {
+        let location = format!("/{namespace}");
+        let indent = if namespace.is_empty() {
+            0
+        } else {
+            self.line(0, &format!("namespace {namespace} {{"));
+            1
+        };
+        for (name, ty) in sorted(&ns_def["commonTypes"]) {
+            let ty = self.ty(ty, indent, &format!("{location}/commonTypes/{name}"));
+            self.line(indent, &format!("type {name} = {ty};"));
+        }
+        for (name, entity) in sorted(&ns_def["entityTypes"]) {
+            self.annotations(indent, &entity["annotations"]);
+            let mut decl = format!("entity {name}");
+            let member_of = self.names(&entity["memberOfTypes"]);
+            if !member_of.is_empty() {
+                decl.push_str(&format!(" in [{member_of}]"));
+            }
+            let shape = &entity["shape"];
+            let shape_location = format!("{location}/entityTypes/{name}/shape");
+            if shape["type"] == "Record" {
+                if !is_empty_record(shape) {
+                    decl.push(' ');
+                    decl.push_str(&self.ty(shape, indent, &shape_location));
+                }
+            } else if !shape.is_null() {
+                decl.push_str(" = ");
+                decl.push_str(&self.ty(shape, indent, &shape_location));
+            }
+            decl.push(';');
+            self.line(indent, &decl);
+        }
+        for (name, action) in sorted(&ns_def["actions"]) {
+            let action_location = format!("{location}/actions/{name}");
+            self.annotations(indent, &action["annotations"]);
+            let mut decl = format!("action {}", quoted(name));
+            if let Some(member_of) = action["memberOf"].as_array() {
+                let refs: Vec<String> = member_of
+                    .iter()
+                    .map(|parent| {
+                        let id = quoted(parent["id"].as_str().unwrap_or_default());
+                        match parent["type"].as_str() {
+                            Some(ty) => format!("{ty}::{id}"),
+                            None => id,
+                        }
+                    })
+                    .collect();
+                decl.push_str(&format!(" in [{}]", refs.join(", ")));
+            }
+            let applies_to = &action["appliesTo"];
+            if applies_to.is_object() {
+                let mut fields = Vec::new();
+                for (field, key) in [
+                    ("principal", "principalTypes"),
+                    ("resource", "resourceTypes"),
+                ] {
+                    if applies_to[key].is_array() {
+                        fields.push(format!("{field}: [{}]", self.names(&applies_to[key])));
+                    }
+                }
+                let context = &applies_to["context"];
+                if !context.is_null() && !is_empty_record(context) {
+                    let ty = self.ty(
+                        context,
+                        indent + 1,
+                        &format!("{action_location}/appliesTo/context"),
+                    );
+                    fields.push(format!("context: {ty}"));
+                }
+                if fields.is_empty() {
+                    decl.push_str(" appliesTo {}");
+                } else {
+                    decl.push_str(" appliesTo {");
+                    let field_indent = "  ".repeat(indent + 1);
+                    decl.push_str(
+                        &fields
+                            .iter()
+                            .map(|f| format!("\n{field_indent}{f}"))
+                            .collect::<Vec<_>>()
+                            .join(","),
+                    );
+                    decl.push('\n');
+                    decl.push_str(&"  ".repeat(indent));
+                    decl.push('}');
+                }
+            }
+            if !action["attributes"].is_null() {
+                self.warnings.push(TranslationWarning::ActionAttributes {
+                    action: action_location,
+                });
+            }
+            decl.push(';');
+            self.line(indent, &decl);
+        }
+        if !namespace.is_empty() {
+            self.line(0, "}");
+        }
+    }
+
+    /// Print each annotation in a JSON object on its own line
+    fn annotations(&mut self, indent: usize, annotations: &Value) {
+        for (key, value) in sorted(annotations) {
+            let value = quoted(value.as_str().unwrap_or_default());
+            self.line(indent, &format!("@{key}({value})"));
+        }
+    }
+
+    /// A comma-separated list of the strings in a JSON array
+    fn names(&self, list: &Value) -> String {
+        list.as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Print a type. Records are printed over multiple lines, with their
+    /// closing brace at `indent`.
+    fn ty(&mut self, ty: &Value, indent: usize, location: &str) -> String {
+        match ty["type"].as_str().unwrap_or_default() {
+            "Boolean" => "Bool".to_owned(),
+            "Set" => format!(
+                "Set<{}>",
+                self.ty(&ty["element"], indent, &format!("{location}/element"))
+            ),
+            "Record" => {
+                if ty["additionalAttributes"] == true {
+                    self.warnings
+                        .push(TranslationWarning::AdditionalAttributes {
+                            location: location.to_owned(),
+                        });
+                }
+                let attributes = sorted(&ty["attributes"]);
+                if attributes.is_empty() {
+                    return "{}".to_owned();
+                }
+                let mut out = "{\n".to_owned();
+                for (name, attr_ty) in attributes {
+                    let name_str = if is_ident(name) {
+                        name.clone()
+                    } else {
+                        quoted(name)
+                    };
+                    for (key, value) in sorted(&attr_ty["annotations"]) {
+                        out.push_str(&format!(
+                            "{}@{key}({})\n",
+                            "  ".repeat(indent + 1),
+                            quoted(value.as_str().unwrap_or_default())
+                        ));
+                    }
+                    let optional = if attr_ty["required"] == false {
+                        "?"
+                    } else {
+                        ""
+                    };
+                    let attr_ty = self.ty(
+                        attr_ty,
+                        indent + 1,
+                        &format!("{location}/attributes/{name}"),
+                    );
+                    out.push_str(&format!(
+                        "{}{name_str}{optional}: {attr_ty},\n",
+                        "  ".repeat(indent + 1)
+                    ));
+                }
+                out.push_str(&"  ".repeat(indent));
+                out.push('}');
+                out
+            }
+            "Entity" | "Extension" => ty["name"].as_str().unwrap_or_default().to_owned(),
+            // `String`, `Long`, and references to common types
+            name => name.to_owned(),
+        }
+    }
+}
+
+fn is_empty_record(ty: &Value) -> bool {
+    ty["type"] == "Record"
+        && ty["attributes"].as_object().map_or(true, Map::is_empty)
+        && ty["additionalAttributes"] != true
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Ident(String),
+    Str(String),
+    Punct(&'static str),
+}
+
+type ParseResult<T> = std::result::Result<T, (String, Range<usize>)>;
+
+/// Split `src` into tokens, also returning whether it contained any comments
+fn lex(src: &str) -> ParseResult<(Vec<(Tok, Range<usize>)>, bool)> {
+    const PUNCTS: [&str; 15] = [
+        "::", "{", "}", "[", "]", "<", ">", ",", ";", ":", "?", "=", "@", "(", ")",
+    ];
+    let mut tokens = Vec::new();
+    let mut has_comments = false;
+    for token in schema_tokens(src) {
+        let span = token.span();
+        match token.kind() {
+            TokenKind::Comment => has_comments = true,
+            TokenKind::String => {
+                let s = serde_json::from_str(token.text())
+                    .map_err(|e| (format!("invalid string: {e}"), span.clone()))?;
+                tokens.push((Tok::Str(s), span));
+            }
+            TokenKind::Keyword | TokenKind::Identifier => {
+                tokens.push((Tok::Ident(token.text().to_owned()), span));
+            }
+            _ => {
+                // the other tokens are punctuation, or invalid
+                let Some(p) = PUNCTS.iter().find(|p| **p == token.text()) else {
+                    return Err(if token.text().starts_with('"') {
+                        ("unterminated string".to_owned(), span)
+                    } else {
+                        (format!("unexpected character `{}`", token.text()), span)
+                    });
+                };
+                tokens.push((Tok::Punct(*p), span));
+            }
+        }
+    }
+    Ok((tokens, has_comments))
+}
+
+/// Annotation keys and values, in the order they're written
+type Annotations = Vec<(String, String)>;
+
+/// A type in the Cedar schema format, before names are resolved
+#[derive(Debug, Clone)]
+enum Ty {
+    Set(Box<Ty>),
+    /// Attribute annotations, names, whether they're required, and their types
+    Record(Vec<(Annotations, String, bool, Ty)>),
+    Path(String),
+}
+
+#[derive(Debug)]
+struct EntityDecl {
+    annotations: Annotations,
+    name: String,
+    member_of: Vec<String>,
+    shape: Option<Ty>,
+}
+
+#[derive(Debug)]
+struct ActionDecl {
+    annotations: Annotations,
+    name: String,
+    /// Action type, if given, and id of each parent action
+    member_of: Option<Vec<(Option<String>, String)>>,
+    applies_to: Option<AppliesTo>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct AppliesTo {
+    principals: Option<Vec<String>>,
+    resources: Option<Vec<String>>,
+    context: Option<Ty>,
+}
+
+#[derive(Debug, Default)]
+struct NamespaceDecl {
+    name: String,
+    types: Vec<(String, Ty)>,
+    entities: Vec<EntityDecl>,
+    actions: Vec<ActionDecl>,
+}
+
+impl NamespaceDecl {
+    fn to_json(&self) -> Value {
+        let common: HashSet<&str> = self.types.iter().map(|(name, _)| name.as_str()).collect();
+        let mut ns = Map::new();
+        if !self.types.is_empty() {
+            let types = self
+                .types
+                .iter()
+                .map(|(name, ty)| (name.clone(), ty_to_json(ty, &common)))
+                .collect();
+            ns.insert("commonTypes".to_owned(), Value::Object(types));
+        }
+        let entities = self
+            .entities
+            .iter()
+            .map(|decl| {
+                let mut entity = Map::new();
+                if !decl.annotations.is_empty() {
+                    entity.insert(
+                        "annotations".to_owned(),
+                        annotations_to_json(&decl.annotations),
+                    );
+                }
+                if !decl.member_of.is_empty() {
+                    entity.insert("memberOfTypes".to_owned(), json!(decl.member_of));
+                }
+                if let Some(shape) = &decl.shape {
+                    entity.insert("shape".to_owned(), ty_to_json(shape, &common));
+                }
+                (decl.name.clone(), Value::Object(entity))
+            })
+            .collect();
+        ns.insert("entityTypes".to_owned(), Value::Object(entities));
+        let actions = self
+            .actions
+            .iter()
+            .map(|action| {
+                let mut a = Map::new();
+                if !action.annotations.is_empty() {
+                    a.insert(
+                        "annotations".to_owned(),
+                        annotations_to_json(&action.annotations),
+                    );
+                }
+                if let Some(member_of) = &action.member_of {
+                    let refs = member_of
+                        .iter()
+                        .map(|(ty, id)| match ty {
+                            Some(ty) => json!({ "id": id, "type": ty }),
+                            None => json!({ "id": id }),
+                        })
+                        .collect();
+                    a.insert("memberOf".to_owned(), Value::Array(refs));
+                }
+                if let Some(applies_to) = &action.applies_to {
+                    let mut spec = Map::new();
+                    if let Some(principals) = &applies_to.principals {
+                        spec.insert("principalTypes".to_owned(), json!(principals));
+                    }
+                    if let Some(resources) = &applies_to.resources {
+                        spec.insert("resourceTypes".to_owned(), json!(resources));
+                    }
+                    if let Some(context) = &applies_to.context {
+                        spec.insert("context".to_owned(), ty_to_json(context, &common));
+                    }
+                    a.insert("appliesTo".to_owned(), Value::Object(spec));
+                }
+                (action.name.clone(), Value::Object(a))
+            })
+            .collect();
+        ns.insert("actions".to_owned(), Value::Object(actions));
+        Value::Object(ns)
+    }
+}
+
+fn annotations_to_json(annotations: &Annotations) -> Value {
+    Value::Object(
+        annotations
+            .iter()
+            .map(|(key, value)| (key.clone(), Value::String(value.clone())))
+            .collect(),
+    )
+}
+
+// PANIC SAFETY: assigning to a key of a JSON object doesn't panic, and `attr` is an object
+#[allow(clippy::indexing_slicing)]
+fn ty_to_json(ty: &Ty, common: &HashSet<&str>) -> Value {
+    match ty {
+        Ty::Set(element) => json!({ "type": "Set", "element": ty_to_json(element, common) }),
+        Ty::Record(attrs) => {
+            let attributes: Map<String, Value> = attrs
+                .iter()
+                .map(|(annotations, name, required, ty)| {
+                    let mut attr = ty_to_json(ty, common);
+                    if !required {
+                        attr["required"] = Value::Bool(false);
+                    }
+                    if !annotations.is_empty() {
+                        attr["annotations"] = annotations_to_json(annotations);
+                    }
+                    (name.clone(), attr)
+                })
+                .collect();
+            json!({ "type": "Record", "attributes": attributes })
+        }
+        Ty::Path(path) if common.contains(path.as_str()) => json!({ "type": path }),
+        Ty::Path(path) => match path.strip_prefix("__cedar::").unwrap_or(path) {
+            "String" => json!({ "type": "String" }),
+            "Long" => json!({ "type": "Long" }),
+            "Bool" | "Boolean" => json!({ "type": "Boolean" }),
+            ext @ ("ipaddr" | "decimal") => json!({ "type": "Extension", "name": ext }),
+            _ => json!({ "type": "Entity", "name": path }),
+        },
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Tok, Range<usize>)>,
+    pos: usize,
+    /// Length of the source, for errors at the end of input
+    end: usize,
+}
+
+impl Parser {
+    fn peek_at(&self, n: usize) -> Option<&Tok> {
+        self.tokens.get(self.pos + n).map(|(tok, _)| tok)
+    }
+
+    fn peek(&self) -> Option<&Tok> {
+        self.peek_at(0)
+    }
+
+    fn span(&self) -> Range<usize> {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.end..self.end, |(_, span)| span.clone())
+    }
+
+    fn error<T>(&self, expected: &str) -> ParseResult<T> {
+        let found = match self.peek() {
+            Some(Tok::Ident(s)) => format!("`{s}`"),
+            Some(Tok::Str(s)) => quoted(s),
+            Some(Tok::Punct(p)) => format!("`{p}`"),
+            None => "end of input".to_owned(),
+        };
+        Err((format!("expected {expected}, found {found}"), self.span()))
+    }
+
+    fn is_punct(&self, p: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Punct(q)) if *q == p)
+    }
+
+    fn is_keyword(&self, kw: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Ident(s)) if s == kw)
+    }
+
+    fn eat_punct(&mut self, p: &str) -> bool {
+        let found = self.is_punct(p);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_punct(&mut self, p: &str) -> ParseResult<()> {
+        if self.eat_punct(p) {
+            Ok(())
+        } else {
+            self.error(&format!("`{p}`"))
+        }
+    }
+
+    fn ident(&mut self) -> ParseResult<String> {
+        match self.peek() {
+            Some(Tok::Ident(s)) => {
+                let s = s.clone();
+                self.pos += 1;
+                Ok(s)
+            }
+            _ => self.error("an identifier"),
+        }
+    }
+
+    /// An identifier or a string
+    fn name(&mut self) -> ParseResult<String> {
+        match self.peek() {
+            Some(Tok::Str(s)) => {
+                let s = s.clone();
+                self.pos += 1;
+                Ok(s)
+            }
+            _ => self.ident(),
+        }
+    }
+
+    /// A `::`-separated path of identifiers. Stops before a `::` which isn't
+    /// followed by an identifier.
+    fn path(&mut self) -> ParseResult<String> {
+        let mut path = self.ident()?;
+        while self.is_punct("::") && matches!(self.peek_at(1), Some(Tok::Ident(_))) {
+            self.pos += 1;
+            path.push_str("::");
+            path.push_str(&self.ident()?);
+        }
+        Ok(path)
+    }
+
+    /// A comma-separated list of items, ending with `close`. A trailing comma
+    /// is allowed.
+    fn list<T>(
+        &mut self,
+        close: &str,
+        mut item: impl FnMut(&mut Self) -> ParseResult<T>,
+    ) -> ParseResult<Vec<T>> {
+        let mut items = Vec::new();
+        while !self.eat_punct(close) {
+            items.push(item(self)?);
+            if !self.eat_punct(",") {
+                self.expect_punct(close)?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    /// A single path, or a list of paths in brackets
+    fn paths(&mut self) -> ParseResult<Vec<String>> {
+        if self.eat_punct("[") {
+            self.list("]", Self::path)
+        } else {
+            Ok(vec![self.path()?])
+        }
+    }
+
+    /// The paths the schema includes, and its namespaces
+    fn schema(&mut self) -> ParseResult<(Vec<String>, Vec<NamespaceDecl>)> {
+        let mut includes = Vec::new();
+        let mut namespaces: Vec<NamespaceDecl> = Vec::new();
+        while self.peek().is_some() {
+            if self.is_keyword("include") {
+                self.pos += 1;
+                let Some(Tok::Str(path)) = self.peek() else {
+                    return self.error("a string");
+                };
+                includes.push(path.clone());
+                self.pos += 1;
+                self.expect_punct(";")?;
+                continue;
+            }
+            let (name, in_block) = if self.is_keyword("namespace") {
+                self.pos += 1;
+                let name = self.path()?;
+                self.expect_punct("{")?;
+                (name, true)
+            } else {
+                (String::new(), false)
+            };
+            // a namespace declared again is taken out and put back in its place
+            let (idx, mut ns) = match namespaces.iter().position(|ns| ns.name == name) {
+                Some(idx) => (idx, namespaces.remove(idx)),
+                None => (
+                    namespaces.len(),
+                    NamespaceDecl {
+                        name,
+                        ..NamespaceDecl::default()
+                    },
+                ),
+            };
+            if in_block {
+                while !self.eat_punct("}") {
+                    self.decl(&mut ns)?;
+                }
+            } else {
+                self.decl(&mut ns)?;
+            }
+            namespaces.insert(idx, ns);
+        }
+        Ok((includes, namespaces))
+    }
+
+    fn decl(&mut self, ns: &mut NamespaceDecl) -> ParseResult<()> {
+        let annotations_span = self.span();
+        let annotations = self.annotations()?;
+        if self.is_keyword("type") {
+            if !annotations.is_empty() {
+                return Err((
+                    "common types can't have annotations".to_owned(),
+                    annotations_span,
+                ));
+            }
+            self.pos += 1;
+            let name = self.ident()?;
+            self.expect_punct("=")?;
+            let ty = self.ty()?;
+            ns.types.push((name, ty));
+        } else if self.is_keyword("entity") {
+            self.pos += 1;
+            let mut names = vec![self.ident()?];
+            while self.eat_punct(",") {
+                names.push(self.ident()?);
+            }
+            let member_of = if self.is_keyword("in") {
+                self.pos += 1;
+                self.paths()?
+            } else {
+                vec![]
+            };
+            let shape = if self.eat_punct("=") || self.is_punct("{") {
+                Some(self.ty()?)
+            } else {
+                None
+            };
+            for name in names {
+                ns.entities.push(EntityDecl {
+                    annotations: annotations.clone(),
+                    name,
+                    member_of: member_of.clone(),
+                    shape: shape.clone(),
+                });
+            }
+        } else if self.is_keyword("action") {
+            self.pos += 1;
+            let mut names = vec![self.name()?];
+            while self.eat_punct(",") {
+                names.push(self.name()?);
+            }
+            let member_of = if self.is_keyword("in") {
+                self.pos += 1;
+                Some(if self.eat_punct("[") {
+                    self.list("]", Self::action_ref)?
+                } else {
+                    vec![self.action_ref()?]
+                })
+            } else {
+                None
+            };
+            let applies_to = if self.is_keyword("appliesTo") {
+                self.pos += 1;
+                self.expect_punct("{")?;
+                Some(self.applies_to()?)
+            } else {
+                None
+            };
+            for name in names {
+                ns.actions.push(ActionDecl {
+                    annotations: annotations.clone(),
+                    name,
+                    member_of: member_of.clone(),
+                    applies_to: applies_to.clone(),
+                });
+            }
+        } else {
+            return self.error("`type`, `entity`, or `action`");
+        }
+        self.expect_punct(";")
+    }
+
+    /// Annotations, like `@owner("identity")`, before a declaration
+    fn annotations(&mut self) -> ParseResult<Annotations> {
+        let mut annotations: Annotations = Vec::new();
+        while self.eat_punct("@") {
+            let span = self.span();
+            let key = self.ident()?;
+            if annotations.iter().any(|(k, _)| *k == key) {
+                return Err((format!("duplicate annotation `@{key}`"), span));
+            }
+            self.expect_punct("(")?;
+            let Some(Tok::Str(value)) = self.peek() else {
+                return self.error("a string");
+            };
+            let value = value.clone();
+            self.pos += 1;
+            self.expect_punct(")")?;
+            annotations.push((key, value));
+        }
+        Ok(annotations)
+    }
+
+    fn action_ref(&mut self) -> ParseResult<(Option<String>, String)> {
+        if let Some(Tok::Str(_)) = self.peek() {
+            return Ok((None, self.name()?));
+        }
+        let path = self.path()?;
+        if self.eat_punct("::") {
+            Ok((Some(path), self.name()?))
+        } else {
+            Ok((None, path))
+        }
+    }
+
+    /// The fields of an `appliesTo` block, after the opening brace
+    fn applies_to(&mut self) -> ParseResult<AppliesTo> {
+        let mut applies_to = AppliesTo::default();
+        self.list("}", |p| {
+            let span = p.span();
+            let field = p.ident()?;
+            p.expect_punct(":")?;
+            match field.as_str() {
+                "principal" => applies_to.principals = Some(p.paths()?),
+                "resource" => applies_to.resources = Some(p.paths()?),
+                "context" => applies_to.context = Some(p.ty()?),
+                _ => {
+                    return Err((
+                        format!("expected `principal`, `resource`, or `context`, found `{field}`"),
+                        span,
+                    ))
+                }
+            }
+            Ok(())
+        })?;
+        Ok(applies_to)
+    }
+
+    fn ty(&mut self) -> ParseResult<Ty> {
+        if self.eat_punct("{") {
+            let attrs = self.list("}", |p| {
+                let annotations = p.annotations()?;
+                let name = p.name()?;
+                let required = !p.eat_punct("?");
+                p.expect_punct(":")?;
+                Ok((annotations, name, required, p.ty()?))
+            })?;
+            Ok(Ty::Record(attrs))
+        } else if self.is_keyword("Set") && matches!(self.peek_at(1), Some(Tok::Punct("<"))) {
+            self.pos += 2;
+            let element = self.ty()?;
+            self.expect_punct(">")?;
+            Ok(Ty::Set(Box::new(element)))
+        } else {
+            Ok(Ty::Path(self.path()?))
+        }
+    }
+}
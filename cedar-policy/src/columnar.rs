@@ -0,0 +1,568 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module loads [`Entities`] from columnar data: Arrow record batches, or
+//! Parquet files. Each row is an entity of a single type, and each column is
+//! an attribute, whose Cedar type is taken from the schema.
+//!
+//! Columns are converted as follows:
+//! - `Boolean` attributes from boolean columns
+//! - `Long` attributes from signed or unsigned integer columns
+//! - `String` attributes from string columns
+//! - entity attributes from string columns of entity ids
+//! - extension attributes, e.g., `ipaddr`, from string columns of the argument
+//!   of their constructor, e.g., `10.0.0.1`
+//! - `Set` attributes from list columns
+//! - `Record` attributes from struct columns
+//!
+//! A null value means that the attribute is absent.
+#![allow(clippy::module_name_repetitions)]
+
+use crate::{
+    Entities, EntitiesError, Entity, EntityAttrEvaluationError, EntityId, EntityTypeName,
+    EntityUid, ParseErrors, RestrictedExpression, Schema,
+};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{
+    Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use arrow_array::{Array, RecordBatch};
+use arrow_schema::{ArrowError, DataType};
+use cedar_policy_core::ast::{ExprConstructionError, Name, RestrictedExprParseError};
+use cedar_policy_validator::types::{EntityRecordKind, Primitive, Type};
+use cedar_policy_validator::ValidatorEntityType;
+use miette::Diagnostic;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::errors::ParquetError;
+use parquet::file::reader::ChunkReader;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// How the columns of record batches map to entities of one type
+#[derive(Debug, Clone)]
+pub struct ColumnMapping {
+    entity_type: EntityTypeName,
+    id_column: String,
+    parents_column: Option<String>,
+}
+
+impl ColumnMapping {
+    /// Entities of type `entity_type`, whose ids are in the string column
+    /// `id_column`. Every other column is the attribute of the same name.
+    pub fn new(entity_type: EntityTypeName, id_column: impl Into<String>) -> Self {
+        Self {
+            entity_type,
+            id_column: id_column.into(),
+            parents_column: None,
+        }
+    }
+
+    /// Take the parents of each entity from `column`, a string column of
+    /// entity uids, e.g., `Group::"admins"`, or a list column of them
+    #[must_use]
+    pub fn with_parents_column(mut self, column: impl Into<String>) -> Self {
+        self.parents_column = Some(column.into());
+        self
+    }
+}
+
+/// Errors loading entities from columnar data
+#[derive(Debug, Diagnostic, Error)]
+#[non_exhaustive]
+pub enum ColumnarError {
+    /// The entity type of the mapping is not declared in the schema
+    #[error("entity type `{0}` is not declared in the schema")]
+    UndeclaredEntityType(EntityTypeName),
+    /// A column of the mapping is missing
+    #[error("column `{0}` is missing")]
+    MissingColumn(String),
+    /// A column is not an attribute of the entity type in the schema
+    #[error("column `{column}` is not an attribute of entity type `{entity_type}`")]
+    UndeclaredAttribute {
+        /// Name of the column
+        column: String,
+        /// Entity type of the mapping
+        entity_type: EntityTypeName,
+    },
+    /// A column has a type that can't hold values of the attribute's type
+    #[error("column `{column}` has type {data_type}, which can't hold values of type {expected}")]
+    ColumnType {
+        /// Name of the column, or path to the field, e.g., `address.city`
+        column: String,
+        /// Arrow type of the column
+        data_type: DataType,
+        /// Cedar type of the attribute
+        expected: String,
+    },
+    /// A value in a column can't be converted to a Cedar value
+    #[error("invalid value in row {row} of column `{column}`: {message}")]
+    InvalidValue {
+        /// Name of the column
+        column: String,
+        /// Index of the row in its record batch
+        row: usize,
+        /// What is wrong with the value
+        message: String,
+    },
+    /// Error parsing an entity type name
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Parse(#[from] ParseErrors),
+    /// Error parsing a value of an extension type
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    ExtensionValue(#[from] RestrictedExprParseError),
+    /// Error constructing a record from a struct column
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Record(#[from] ExprConstructionError),
+    /// Error evaluating the attributes of an entity
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Attribute(#[from] EntityAttrEvaluationError),
+    /// Error constructing the entities, e.g., because they don't conform to
+    /// the schema
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Entities(#[from] EntitiesError),
+    /// Error reading record batches
+    #[error(transparent)]
+    Arrow(#[from] ArrowError),
+    /// Error reading a Parquet file
+    #[error(transparent)]
+    Parquet(#[from] ParquetError),
+}
+
+impl Entities {
+    /// Create an `Entities` object from the rows of `batches`, each of which is
+    /// an entity as described by `mapping`. The attributes are converted
+    /// according to their types in `schema`, and the entities must conform to
+    /// `schema`, as with [`Entities::from_entities`].
+    pub fn from_record_batches<'a>(
+        batches: impl IntoIterator<Item = &'a RecordBatch>,
+        mapping: &ColumnMapping,
+        schema: &Schema,
+    ) -> Result<Self, ColumnarError> {
+        let name = Name::from_str(&mapping.entity_type.to_string())?;
+        let entity_type = schema
+            .0
+            .get_entity_type(&name)
+            .ok_or_else(|| ColumnarError::UndeclaredEntityType(mapping.entity_type.clone()))?;
+        let mut entities = vec![];
+        for batch in batches {
+            load_batch(batch, mapping, entity_type, &mut entities)?;
+        }
+        Ok(Self::from_entities(entities, Some(schema))?)
+    }
+
+    /// Create an `Entities` object from the rows of a Parquet file, as with
+    /// [`Entities::from_record_batches`]. `file` may be a [`std::fs::File`].
+    pub fn from_parquet(
+        file: impl ChunkReader + 'static,
+        mapping: &ColumnMapping,
+        schema: &Schema,
+    ) -> Result<Self, ColumnarError> {
+        let batches = ParquetRecordBatchReaderBuilder::try_new(file)?
+            .build()?
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_record_batches(&batches, mapping, schema)
+    }
+}
+
+/// Add the entities in the rows of `batch` to `entities`
+fn load_batch(
+    batch: &RecordBatch,
+    mapping: &ColumnMapping,
+    entity_type: &ValidatorEntityType,
+    entities: &mut Vec<Entity>,
+) -> Result<(), ColumnarError> {
+    let column = |name: &String| {
+        batch
+            .column_by_name(name)
+            .ok_or_else(|| ColumnarError::MissingColumn(name.clone()))
+    };
+    let ids = column(&mapping.id_column)?;
+    let parents = mapping.parents_column.as_ref().map(column).transpose()?;
+    let mut attributes = vec![];
+    for (field, array) in batch.schema().fields().iter().zip(batch.columns()) {
+        let name = field.name();
+        if *name == mapping.id_column || Some(name) == mapping.parents_column.as_ref() {
+            continue;
+        }
+        let attr = entity_type
+            .attr(name)
+            .ok_or_else(|| ColumnarError::UndeclaredAttribute {
+                column: name.clone(),
+                entity_type: mapping.entity_type.clone(),
+            })?;
+        attributes.push((name, array, &attr.attr_type));
+    }
+
+    for row in 0..batch.num_rows() {
+        let id = string_at(&mapping.id_column, ids, row)?.ok_or_else(|| {
+            ColumnarError::InvalidValue {
+                column: mapping.id_column.clone(),
+                row,
+                message: "the entity id is null".to_owned(),
+            }
+        })?;
+        let uid =
+            EntityUid::from_type_name_and_id(mapping.entity_type.clone(), EntityId::from_raw(id));
+        let mut attrs = HashMap::new();
+        for (name, array, ty) in &attributes {
+            if !array.is_null(row) {
+                attrs.insert((*name).clone(), value(name, array.as_ref(), row, ty)?);
+            }
+        }
+        let parents = match (&mapping.parents_column, parents) {
+            (Some(name), Some(array)) => parents_at(name, array.as_ref(), row)?,
+            _ => HashSet::new(),
+        };
+        entities.push(Entity::new(uid, attrs, parents)?);
+    }
+    Ok(())
+}
+
+/// The value at `row` of `array`, which is not null, as a value of type `ty`
+fn value(
+    column: &str,
+    array: &dyn Array,
+    row: usize,
+    ty: &Type,
+) -> Result<RestrictedExpression, ColumnarError> {
+    let mismatch = || ColumnarError::ColumnType {
+        column: column.to_owned(),
+        data_type: array.data_type().clone(),
+        expected: ty.to_string(),
+    };
+    match ty {
+        Type::Primitive {
+            primitive_type: Primitive::Bool,
+        }
+        | Type::True
+        | Type::False => array
+            .as_boolean_opt()
+            .map(|array| RestrictedExpression::new_bool(array.value(row)))
+            .ok_or_else(mismatch),
+        Type::Primitive {
+            primitive_type: Primitive::Long,
+        } => long_at(column, array, row)?
+            .map(RestrictedExpression::new_long)
+            .ok_or_else(mismatch),
+        Type::Primitive {
+            primitive_type: Primitive::String,
+        } => string_at(column, array, row)?
+            .map(|s| RestrictedExpression::new_string(s.to_owned()))
+            .ok_or_else(mismatch),
+        Type::Set {
+            element_type: Some(element_type),
+        } => {
+            let elements = list_at(array, row).ok_or_else(mismatch)?;
+            let elements = (0..elements.len())
+                .filter(|i| !elements.is_null(*i))
+                .map(|i| value(column, elements.as_ref(), i, element_type))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(RestrictedExpression::new_set(elements))
+        }
+        Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) => {
+            let array = array.as_struct_opt().ok_or_else(mismatch)?;
+            let mut fields = vec![];
+            for (field, values) in array.fields().iter().zip(array.columns()) {
+                let path = format!("{column}.{}", field.name());
+                let attr = attrs.attrs.get(field.name().as_str()).ok_or_else(|| {
+                    ColumnarError::InvalidValue {
+                        column: column.to_owned(),
+                        row,
+                        message: format!("`{path}` is not an attribute of type {ty}"),
+                    }
+                })?;
+                if !values.is_null(row) {
+                    let value = value(&path, values.as_ref(), row, &attr.attr_type)?;
+                    fields.push((field.name().clone(), value));
+                }
+            }
+            Ok(RestrictedExpression::new_record(fields)?)
+        }
+        Type::EntityOrRecord(EntityRecordKind::Entity(lub)) => {
+            let entity_type = lub.get_single_entity().ok_or_else(mismatch)?;
+            let id = string_at(column, array, row)?.ok_or_else(mismatch)?;
+            let entity_type = EntityTypeName::from_str(&entity_type.to_string())?;
+            Ok(RestrictedExpression::new_entity_uid(
+                EntityUid::from_type_name_and_id(entity_type, EntityId::from_raw(id)),
+            ))
+        }
+        Type::ExtensionType { name } => {
+            let arg = string_at(column, array, row)?.ok_or_else(mismatch)?;
+            let constructor = match name.to_string().as_str() {
+                "ipaddr" => "ip".to_owned(),
+                other => other.to_owned(),
+            };
+            // Cedar string literals have the same escapes as Rust ones
+            Ok(RestrictedExpression::from_str(&format!(
+                "{constructor}({arg:?})"
+            ))?)
+        }
+        _ => Err(mismatch()),
+    }
+}
+
+/// The value at `row` of `array`, if it's a string column
+fn string_at<'a>(
+    column: &str,
+    array: &'a dyn Array,
+    row: usize,
+) -> Result<Option<&'a str>, ColumnarError> {
+    let value = match array.data_type() {
+        DataType::Utf8 => array.as_string::<i32>().value(row),
+        DataType::LargeUtf8 => array.as_string::<i64>().value(row),
+        _ => return Ok(None),
+    };
+    if array.is_null(row) {
+        return Err(ColumnarError::InvalidValue {
+            column: column.to_owned(),
+            row,
+            message: "the value is null".to_owned(),
+        });
+    }
+    Ok(Some(value))
+}
+
+/// The value at `row` of `array`, if it's an integer column
+fn long_at(column: &str, array: &dyn Array, row: usize) -> Result<Option<i64>, ColumnarError> {
+    let value = match array.data_type() {
+        DataType::Int8 => i64::from(array.as_primitive::<Int8Type>().value(row)),
+        DataType::Int16 => i64::from(array.as_primitive::<Int16Type>().value(row)),
+        DataType::Int32 => i64::from(array.as_primitive::<Int32Type>().value(row)),
+        DataType::Int64 => array.as_primitive::<Int64Type>().value(row),
+        DataType::UInt8 => i64::from(array.as_primitive::<UInt8Type>().value(row)),
+        DataType::UInt16 => i64::from(array.as_primitive::<UInt16Type>().value(row)),
+        DataType::UInt32 => i64::from(array.as_primitive::<UInt32Type>().value(row)),
+        DataType::UInt64 => {
+            let value = array.as_primitive::<UInt64Type>().value(row);
+            i64::try_from(value).map_err(|_| ColumnarError::InvalidValue {
+                column: column.to_owned(),
+                row,
+                message: format!("{value} is too large for a Long"),
+            })?
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+/// The list at `row` of `array`, if it's a list column
+fn list_at(array: &dyn Array, row: usize) -> Option<arrow_array::ArrayRef> {
+    match array.data_type() {
+        DataType::List(_) => Some(array.as_list::<i32>().value(row)),
+        DataType::LargeList(_) => Some(array.as_list::<i64>().value(row)),
+        _ => None,
+    }
+}
+
+/// The parents at `row` of `array`, a column of entity uids or lists of them
+fn parents_at(
+    column: &str,
+    array: &dyn Array,
+    row: usize,
+) -> Result<HashSet<EntityUid>, ColumnarError> {
+    if array.is_null(row) {
+        return Ok(HashSet::new());
+    }
+    let (uids, rows) = match list_at(array, row) {
+        Some(list) => {
+            let rows = (0..list.len()).filter(|i| !list.is_null(*i)).collect();
+            (list, rows)
+        }
+        None => (array.slice(row, 1), vec![0]),
+    };
+    rows.into_iter()
+        .map(|i| {
+            let uid =
+                string_at(column, uids.as_ref(), i)?.ok_or_else(|| ColumnarError::ColumnType {
+                    column: column.to_owned(),
+                    data_type: array.data_type().clone(),
+                    expected: "entity uid".to_owned(),
+                })?;
+            Ok(EntityUid::from_str(uid)?)
+        })
+        .collect()
+}
+
+// PANIC SAFETY unit tests
+#[allow(clippy::panic)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use arrow_array::builder::{ListBuilder, StringBuilder};
+    use arrow_array::{ArrayRef, BooleanArray, Int32Array, StringArray, StructArray};
+    use arrow_schema::Field;
+    use cool_asserts::assert_matches;
+    use std::sync::Arc;
+
+    fn schema() -> Schema {
+        Schema::from_json_value(serde_json::json!({ "": {
+            "entityTypes": {
+                "User": {
+                    "memberOfTypes": ["Group"],
+                    "shape": {
+                        "type": "Record",
+                        "attributes": {
+                            "age": { "type": "Long" },
+                            "admin": { "type": "Boolean", "required": false },
+                            "manager": { "type": "Entity", "name": "User", "required": false },
+                            "ip": { "type": "Extension", "name": "ipaddr", "required": false },
+                            "tags": {
+                                "type": "Set",
+                                "element": { "type": "String" },
+                                "required": false
+                            },
+                            "address": {
+                                "type": "Record",
+                                "attributes": { "city": { "type": "String" } },
+                                "required": false
+                            }
+                        }
+                    }
+                },
+                "Group": {}
+            },
+            "actions": {}
+        }}))
+        .unwrap()
+    }
+
+    fn mapping() -> ColumnMapping {
+        ColumnMapping::new("User".parse().unwrap(), "id").with_parents_column("groups")
+    }
+
+    fn batch() -> RecordBatch {
+        let mut groups = ListBuilder::new(StringBuilder::new());
+        groups.values().append_value(r#"Group::"admins""#);
+        groups.append(true);
+        groups.append(true);
+        let mut tags = ListBuilder::new(StringBuilder::new());
+        tags.values().append_value("a");
+        tags.values().append_value("b");
+        tags.append(true);
+        tags.append(false);
+        let cities: ArrayRef = Arc::new(StringArray::from(vec!["Seattle", "Boston"]));
+        let address = StructArray::from(vec![(
+            Arc::new(Field::new("city", DataType::Utf8, false)),
+            cities,
+        )]);
+        let columns: Vec<(&str, ArrayRef)> = vec![
+            ("id", Arc::new(StringArray::from(vec!["alice", "bob"]))),
+            ("groups", Arc::new(groups.finish())),
+            ("age", Arc::new(Int32Array::from(vec![42, 17]))),
+            (
+                "admin",
+                Arc::new(BooleanArray::from(vec![Some(true), None])),
+            ),
+            (
+                "manager",
+                Arc::new(StringArray::from(vec![None, Some("alice")])),
+            ),
+            (
+                "ip",
+                Arc::new(StringArray::from(vec![Some("10.0.0.1"), None])),
+            ),
+            ("tags", Arc::new(tags.finish())),
+            ("address", Arc::new(address)),
+        ];
+        RecordBatch::try_from_iter(columns).unwrap()
+    }
+
+    #[test]
+    fn load_record_batch() {
+        let entities = Entities::from_record_batches([&batch()], &mapping(), &schema()).unwrap();
+        let alice = EntityUid::from_str(r#"User::"alice""#).unwrap();
+        let bob = EntityUid::from_str(r#"User::"bob""#).unwrap();
+        let admins = EntityUid::from_str(r#"Group::"admins""#).unwrap();
+        assert!(entities.is_ancestor_of(&admins, &alice));
+        assert!(!entities.is_ancestor_of(&admins, &bob));
+
+        let attr = |uid: &EntityUid, attr: &str| {
+            entities
+                .get(uid)
+                .and_then(|e| e.attr(attr))
+                .map(|v| v.unwrap().to_string())
+        };
+        assert_eq!(attr(&alice, "age").as_deref(), Some("42"));
+        assert_eq!(attr(&alice, "admin").as_deref(), Some("true"));
+        assert_eq!(attr(&bob, "admin"), None);
+        assert_eq!(attr(&bob, "manager").as_deref(), Some(r#"User::"alice""#));
+        assert_eq!(attr(&alice, "tags").as_deref(), Some(r#"["a", "b"]"#));
+        assert_eq!(attr(&bob, "tags"), None);
+        assert_eq!(
+            attr(&bob, "address").as_deref(),
+            Some(r#"{"city": "Boston"}"#)
+        );
+        assert!(attr(&alice, "ip").is_some());
+    }
+
+    #[test]
+    fn load_parquet() {
+        let batch = batch();
+        let mut buf = vec![];
+        let mut writer =
+            parquet::arrow::ArrowWriter::try_new(&mut buf, batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+        let entities =
+            Entities::from_parquet(bytes::Bytes::from(buf), &mapping(), &schema()).unwrap();
+        assert_eq!(entities.iter().count(), 2);
+    }
+
+    #[test]
+    fn errors() {
+        let columns: Vec<(&str, ArrayRef)> = vec![
+            ("id", Arc::new(StringArray::from(vec!["alice"]))),
+            ("age", Arc::new(StringArray::from(vec!["42"]))),
+        ];
+        let batch = RecordBatch::try_from_iter(columns).unwrap();
+        let mapping = ColumnMapping::new("User".parse().unwrap(), "id");
+        assert_matches!(
+            Entities::from_record_batches([&batch], &mapping, &schema()),
+            Err(ColumnarError::ColumnType { column, .. }) => assert_eq!(column, "age")
+        );
+
+        let columns: Vec<(&str, ArrayRef)> = vec![
+            ("id", Arc::new(StringArray::from(vec!["alice"]))),
+            ("height", Arc::new(Int32Array::from(vec![180]))),
+        ];
+        let batch = RecordBatch::try_from_iter(columns).unwrap();
+        assert_matches!(
+            Entities::from_record_batches([&batch], &mapping, &schema()),
+            Err(ColumnarError::UndeclaredAttribute { column, .. }) => assert_eq!(column, "height")
+        );
+
+        // the required attribute `age` is missing
+        let columns: Vec<(&str, ArrayRef)> =
+            vec![("id", Arc::new(StringArray::from(vec!["alice"])))];
+        let batch = RecordBatch::try_from_iter(columns).unwrap();
+        assert_matches!(
+            Entities::from_record_batches([&batch], &mapping, &schema()),
+            Err(ColumnarError::Entities(_))
+        );
+
+        let mapping = ColumnMapping::new("Photo".parse().unwrap(), "id");
+        assert_matches!(
+            Entities::from_record_batches([&batch], &mapping, &schema()),
+            Err(ColumnarError::UndeclaredEntityType(_))
+        );
+    }
+}
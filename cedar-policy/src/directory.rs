@@ -0,0 +1,545 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module imports users and groups from a directory, as SCIM resources
+//! or LDIF, into [`Entities`], with group memberships as parents.
+//!
+//! A [`Directory`] keeps the users and groups it has been given, so that it
+//! can be refreshed incrementally, e.g., from SCIM provisioning requests,
+//! and then converted to [`Entities`] again. The conversion computes the
+//! transitive closure of the memberships, so members of nested groups are
+//! `in` every enclosing group.
+//!
+//! Users have the optional attributes `userName`, `displayName`, `active`,
+//! and `emails` (a set of strings), and groups have `displayName`. In LDIF,
+//! these are taken from `uid`, `cn`, and `mail`, and entries are identified
+//! by their DN.
+#![allow(clippy::module_name_repetitions)]
+
+use crate::{
+    Entities, EntitiesError, Entity, EntityAttrEvaluationError, EntityId, EntityTypeName,
+    EntityUid, RestrictedExpression, Schema,
+};
+use miette::Diagnostic;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use thiserror::Error;
+
+/// Users, groups, and memberships imported from a directory
+#[derive(Debug, Clone)]
+pub struct Directory {
+    user_type: EntityTypeName,
+    group_type: EntityTypeName,
+    users: BTreeMap<String, Attributes>,
+    groups: BTreeMap<String, Attributes>,
+    /// Members of each group, by group id
+    members: BTreeMap<String, BTreeSet<Member>>,
+}
+
+/// A member of a group
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Member {
+    User(String),
+    Group(String),
+    /// A member whose kind is not given, which is a group if there is a group
+    /// with its id, and a user otherwise
+    Unresolved(String),
+}
+
+impl Member {
+    fn id(&self) -> &str {
+        match self {
+            Self::User(id) | Self::Group(id) | Self::Unresolved(id) => id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Attributes {
+    strings: BTreeMap<&'static str, String>,
+    active: Option<bool>,
+    emails: Option<Vec<String>>,
+}
+
+/// Errors importing users and groups
+#[derive(Debug, Diagnostic, Error)]
+#[non_exhaustive]
+pub enum DirectoryError {
+    /// A SCIM resource is malformed
+    #[error("invalid SCIM resource: {0}")]
+    InvalidScim(String),
+    /// LDIF input is malformed, or uses an unsupported feature
+    #[error("invalid LDIF on line {line}: {message}")]
+    InvalidLdif {
+        /// Line number, counting from 1
+        line: usize,
+        /// What is wrong with the line
+        message: String,
+    },
+    /// Error constructing an entity
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Entity(#[from] EntityAttrEvaluationError),
+    /// Error constructing the entities, e.g., because they don't conform to
+    /// the schema
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Entities(#[from] EntitiesError),
+}
+
+impl Directory {
+    /// An empty directory, whose users and groups are entities of type
+    /// `user_type` and `group_type`
+    pub fn new(user_type: EntityTypeName, group_type: EntityTypeName) -> Self {
+        Self {
+            user_type,
+            group_type,
+            users: BTreeMap::new(),
+            groups: BTreeMap::new(),
+            members: BTreeMap::new(),
+        }
+    }
+
+    /// Add or replace a SCIM `User` resource. If it has `groups`, they replace
+    /// the groups the user is a direct member of.
+    pub fn upsert_scim_user(&mut self, user: &Value) -> Result<(), DirectoryError> {
+        let id = scim_id(user)?;
+        let mut attrs = Attributes::default();
+        for (key, scim_key) in [("userName", "userName"), ("displayName", "displayName")] {
+            if let Some(value) = user.get(scim_key).and_then(Value::as_str) {
+                attrs.strings.insert(key, value.to_owned());
+            }
+        }
+        attrs.active = user.get("active").and_then(Value::as_bool);
+        attrs.emails = user
+            .get("emails")
+            .and_then(Value::as_array)
+            .map(|emails| scim_values(emails).map(str::to_owned).collect());
+        if let Some(groups) = user.get("groups").and_then(Value::as_array) {
+            self.remove_memberships(
+                |member| matches!(member, Member::User(m) | Member::Unresolved(m) if m == id),
+            );
+            // indirect memberships follow from those of the groups
+            let direct = groups
+                .iter()
+                .filter(|group| group.get("type").and_then(Value::as_str) != Some("indirect"));
+            for group in direct {
+                let group = scim_id_in(group, "value")?;
+                self.members
+                    .entry(group.to_owned())
+                    .or_default()
+                    .insert(Member::User(id.to_owned()));
+            }
+        }
+        self.users.insert(id.to_owned(), attrs);
+        Ok(())
+    }
+
+    /// Add or replace a SCIM `Group` resource. If it has `members`, they
+    /// replace the members of the group.
+    pub fn upsert_scim_group(&mut self, group: &Value) -> Result<(), DirectoryError> {
+        let id = scim_id(group)?;
+        let mut attrs = Attributes::default();
+        if let Some(name) = group.get("displayName").and_then(Value::as_str) {
+            attrs.strings.insert("displayName", name.to_owned());
+        }
+        if let Some(members) = group.get("members").and_then(Value::as_array) {
+            let members = members
+                .iter()
+                .map(|member| {
+                    let member_id = scim_id_in(member, "value")?.to_owned();
+                    Ok(match member.get("type").and_then(Value::as_str) {
+                        Some("User") => Member::User(member_id),
+                        Some("Group") => Member::Group(member_id),
+                        _ => Member::Unresolved(member_id),
+                    })
+                })
+                .collect::<Result<_, DirectoryError>>()?;
+            self.members.insert(id.to_owned(), members);
+        }
+        self.groups.insert(id.to_owned(), attrs);
+        Ok(())
+    }
+
+    /// Remove the user with `id`, and its memberships. Returns whether there
+    /// was such a user.
+    pub fn remove_user(&mut self, id: &str) -> bool {
+        self.remove_memberships(
+            |member| matches!(member, Member::User(m) | Member::Unresolved(m) if m == id),
+        );
+        self.users.remove(id).is_some()
+    }
+
+    /// Remove the group with `id`, its memberships, and those of its
+    /// members. Returns whether there was such a group.
+    pub fn remove_group(&mut self, id: &str) -> bool {
+        self.remove_memberships(
+            |member| matches!(member, Member::Group(m) | Member::Unresolved(m) if m == id),
+        );
+        self.members.remove(id);
+        self.groups.remove(id).is_some()
+    }
+
+    /// Apply the records of `ldif`, either content records, or change records
+    /// adding or deleting entries. Entries whose `objectClass` is
+    /// `groupOfNames` or `groupOfUniqueNames` are groups, whose members are
+    /// given by `member` or `uniqueMember`, and entries whose `objectClass`
+    /// is `person`, `organizationalPerson`, or `inetOrPerson` are users.
+    /// Other entries are ignored.
+    pub fn load_ldif(&mut self, ldif: &str) -> Result<(), DirectoryError> {
+        for record in parse_ldif(ldif)? {
+            let Some((_, dn)) = record.iter().find(|(_, (key, _))| key == "dn") else {
+                let line = record.first().map_or(0, |(line, _)| *line);
+                return Err(DirectoryError::InvalidLdif {
+                    line,
+                    message: "record has no `dn`".to_owned(),
+                });
+            };
+            let dn = dn.1.clone();
+            let values = |key| ldif_values(&record, key);
+            match values("changetype").next() {
+                None | Some("add") => (),
+                Some("delete") => {
+                    self.remove_user(&dn);
+                    self.remove_group(&dn);
+                    continue;
+                }
+                Some(other) => {
+                    let line = record.first().map_or(0, |(line, _)| *line);
+                    return Err(DirectoryError::InvalidLdif {
+                        line,
+                        message: format!("unsupported changetype `{other}`"),
+                    });
+                }
+            }
+            let is_class = |classes: &[&str]| {
+                values("objectClass").any(|c| classes.iter().any(|k| c.eq_ignore_ascii_case(k)))
+            };
+            let mut attrs = Attributes::default();
+            if is_class(&["groupOfNames", "groupOfUniqueNames"]) {
+                if let Some(name) = values("cn").next() {
+                    attrs.strings.insert("displayName", name.to_owned());
+                }
+                let members = values("member")
+                    .chain(values("uniqueMember"))
+                    .map(|member| Member::Unresolved(member.to_owned()))
+                    .collect();
+                self.members.insert(dn.clone(), members);
+                self.groups.insert(dn, attrs);
+            } else if is_class(&["person", "organizationalPerson", "inetOrPerson"]) {
+                if let Some(uid) = values("uid").next() {
+                    attrs.strings.insert("userName", uid.to_owned());
+                }
+                if let Some(name) = values("cn").next() {
+                    attrs.strings.insert("displayName", name.to_owned());
+                }
+                let emails: Vec<_> = values("mail").map(str::to_owned).collect();
+                attrs.emails = (!emails.is_empty()).then_some(emails);
+                self.users.insert(dn, attrs);
+            }
+        }
+        Ok(())
+    }
+
+    /// The users and groups as entities, with the groups they are members of
+    /// as parents, and memberships computed transitively. Only users and
+    /// groups which have been added are included, but memberships of the
+    /// others are kept, in case they are added later. If `schema` is given,
+    /// the entities must conform to it.
+    pub fn entities(&self, schema: Option<&Schema>) -> Result<Entities, DirectoryError> {
+        let mut parents: HashMap<EntityUid, HashSet<EntityUid>> = HashMap::new();
+        for (group, members) in &self.members {
+            let group = uid(&self.group_type, group);
+            for member in members {
+                parents
+                    .entry(self.member_uid(member))
+                    .or_default()
+                    .insert(group.clone());
+            }
+        }
+        let users = self
+            .users
+            .iter()
+            .map(|(id, attrs)| (&self.user_type, id, attrs));
+        let groups = self
+            .groups
+            .iter()
+            .map(|(id, attrs)| (&self.group_type, id, attrs));
+        let entities = users
+            .chain(groups)
+            .map(|(entity_type, id, attrs)| {
+                let uid = uid(entity_type, id);
+                let parents = parents.remove(&uid).unwrap_or_default();
+                Entity::new(uid, attrs.to_expressions(), parents)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Entities::from_entities(entities, schema)?)
+    }
+
+    fn member_uid(&self, member: &Member) -> EntityUid {
+        let is_group = match member {
+            Member::User(_) => false,
+            Member::Group(_) => true,
+            Member::Unresolved(id) => self.groups.contains_key(id),
+        };
+        let entity_type = if is_group {
+            &self.group_type
+        } else {
+            &self.user_type
+        };
+        uid(entity_type, member.id())
+    }
+
+    fn remove_memberships(&mut self, f: impl Fn(&Member) -> bool) {
+        for members in self.members.values_mut() {
+            members.retain(|member| !f(member));
+        }
+    }
+}
+
+impl Attributes {
+    fn to_expressions(&self) -> HashMap<String, RestrictedExpression> {
+        let strings = self.strings.iter().map(|(key, value)| {
+            (
+                (*key).to_owned(),
+                RestrictedExpression::new_string(value.clone()),
+            )
+        });
+        let active = self
+            .active
+            .map(|active| ("active".to_owned(), RestrictedExpression::new_bool(active)));
+        let emails = self.emails.as_ref().map(|emails| {
+            let emails = emails.iter().cloned().map(RestrictedExpression::new_string);
+            ("emails".to_owned(), RestrictedExpression::new_set(emails))
+        });
+        strings.chain(active).chain(emails).collect()
+    }
+}
+
+fn uid(entity_type: &EntityTypeName, id: &str) -> EntityUid {
+    EntityUid::from_type_name_and_id(entity_type.clone(), EntityId::from_raw(id))
+}
+
+/// The `id` of a SCIM resource
+fn scim_id(resource: &Value) -> Result<&str, DirectoryError> {
+    scim_id_in(resource, "id")
+}
+
+/// The string `key` of `value`, which identifies a SCIM resource
+fn scim_id_in<'a>(value: &'a Value, key: &str) -> Result<&'a str, DirectoryError> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| DirectoryError::InvalidScim(format!("missing string `{key}` in {value}")))
+}
+
+/// The `value`s of a SCIM multi-valued attribute, e.g., `emails`
+fn scim_values(values: &[Value]) -> impl Iterator<Item = &str> {
+    values
+        .iter()
+        .filter_map(|value| value.get("value").and_then(Value::as_str))
+}
+
+/// A record of LDIF: each line number, with the attribute and value on it
+type LdifRecord = Vec<(usize, (String, String))>;
+
+/// Split `ldif` into records, unfolding continuation lines
+fn parse_ldif(ldif: &str) -> Result<Vec<LdifRecord>, DirectoryError> {
+    let mut records = vec![];
+    let mut record: LdifRecord = vec![];
+    // the current logical line, and the number of its first physical line
+    let mut current: Option<(usize, String)> = None;
+    for (i, text) in ldif.lines().enumerate() {
+        let line = i + 1;
+        if let Some(continuation) = text.strip_prefix(' ') {
+            match &mut current {
+                Some((_, current)) => current.push_str(continuation),
+                None => {
+                    return Err(DirectoryError::InvalidLdif {
+                        line,
+                        message: "continuation of nothing".to_owned(),
+                    })
+                }
+            }
+            continue;
+        }
+        push_ldif_line(current.take(), &mut record)?;
+        if text.trim().is_empty() {
+            if !record.is_empty() {
+                records.push(std::mem::take(&mut record));
+            }
+        } else if !text.starts_with('#') && !(record.is_empty() && text.starts_with("version:")) {
+            current = Some((line, text.to_owned()));
+        }
+    }
+    push_ldif_line(current, &mut record)?;
+    if !record.is_empty() {
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Add `line`, a logical line of LDIF with its line number, to `record`
+fn push_ldif_line(
+    line: Option<(usize, String)>,
+    record: &mut LdifRecord,
+) -> Result<(), DirectoryError> {
+    if let Some((line, text)) = line {
+        let invalid = |message: &str| DirectoryError::InvalidLdif {
+            line,
+            message: message.to_owned(),
+        };
+        let (key, value) = text
+            .split_once(':')
+            .ok_or_else(|| invalid("expected `:`"))?;
+        if value.starts_with(':') || value.starts_with('<') {
+            return Err(invalid(
+                "base64 and URL values are not supported; use plain values",
+            ));
+        }
+        record.push((line, (key.trim().to_owned(), value.trim().to_owned())));
+    }
+    Ok(())
+}
+
+/// The values of the attribute `key` in `record`
+fn ldif_values<'a>(record: &'a LdifRecord, key: &'a str) -> impl Iterator<Item = &'a str> {
+    record
+        .iter()
+        .filter(move |(_, (k, _))| k.eq_ignore_ascii_case(key))
+        .map(|(_, (_, v))| v.as_str())
+}
+
+// PANIC SAFETY unit tests
+#[allow(clippy::panic)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cool_asserts::assert_matches;
+    use serde_json::json;
+
+    fn directory() -> Directory {
+        Directory::new("User".parse().unwrap(), "Group".parse().unwrap())
+    }
+
+    fn uid(s: &str) -> EntityUid {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn scim() {
+        let mut directory = directory();
+        directory
+            .upsert_scim_user(&json!({
+                "id": "alice",
+                "userName": "alice@example.com",
+                "active": true,
+                "emails": [{ "value": "alice@example.com", "primary": true }]
+            }))
+            .unwrap();
+        directory
+            .upsert_scim_group(&json!({
+                "id": "eng",
+                "displayName": "Engineering",
+                "members": [{ "value": "alice", "type": "User" }]
+            }))
+            .unwrap();
+        directory
+            .upsert_scim_group(&json!({
+                "id": "staff",
+                "members": [{ "value": "eng" }]
+            }))
+            .unwrap();
+        let entities = directory.entities(None).unwrap();
+        assert_eq!(entities.iter().count(), 3);
+        assert!(entities.is_ancestor_of(&uid(r#"Group::"staff""#), &uid(r#"User::"alice""#)));
+        let alice = entities.get(&uid(r#"User::"alice""#)).unwrap();
+        assert_eq!(
+            alice.attr("userName").unwrap().unwrap().to_string(),
+            r#""alice@example.com""#
+        );
+        assert_eq!(alice.attr("active").unwrap().unwrap().to_string(), "true");
+
+        // refresh: alice leaves `eng`, via the `groups` of her resource
+        directory
+            .upsert_scim_user(&json!({ "id": "alice", "groups": [] }))
+            .unwrap();
+        let entities = directory.entities(None).unwrap();
+        assert!(!entities.is_ancestor_of(&uid(r#"Group::"staff""#), &uid(r#"User::"alice""#)));
+
+        directory
+            .upsert_scim_user(&json!({ "id": "alice", "groups": [{ "value": "eng" }] }))
+            .unwrap();
+        assert!(directory.remove_group("eng"));
+        let entities = directory.entities(None).unwrap();
+        assert_eq!(entities.iter().count(), 2);
+        assert!(!entities.is_ancestor_of(&uid(r#"Group::"staff""#), &uid(r#"User::"alice""#)));
+
+        assert_matches!(
+            directory.upsert_scim_user(&json!({ "userName": "bob" })),
+            Err(DirectoryError::InvalidScim(_))
+        );
+    }
+
+    #[test]
+    fn ldif() {
+        let ldif = "\
+version: 1
+
+# people
+dn: uid=alice,ou=people,dc=example,dc=com
+objectClass: inetOrPerson
+uid: alice
+cn: Alice
+mail: alice@example.com
+
+dn: cn=eng,ou=groups,dc=example,dc=com
+objectClass: groupOfNames
+cn: eng
+member: uid=alice,ou=people,dc=exam
+ ple,dc=com
+";
+        let mut directory = directory();
+        directory.load_ldif(ldif).unwrap();
+        let alice = uid(r#"User::"uid=alice,ou=people,dc=example,dc=com""#);
+        let eng = uid(r#"Group::"cn=eng,ou=groups,dc=example,dc=com""#);
+        let entities = directory.entities(None).unwrap();
+        assert!(entities.is_ancestor_of(&eng, &alice));
+        assert_eq!(
+            entities
+                .get(&alice)
+                .and_then(|e| e.attr("emails"))
+                .map(|v| v.unwrap().to_string())
+                .as_deref(),
+            Some(r#"["alice@example.com"]"#)
+        );
+
+        let delete = "dn: cn=eng,ou=groups,dc=example,dc=com\nchangetype: delete\n";
+        directory.load_ldif(delete).unwrap();
+        let entities = directory.entities(None).unwrap();
+        assert!(entities.get(&eng).is_none());
+        assert!(!entities.is_ancestor_of(&eng, &alice));
+
+        assert_matches!(
+            directory.load_ldif("dn: cn=x\nchangetype: modify\n"),
+            Err(DirectoryError::InvalidLdif { line: 1, .. })
+        );
+        assert_matches!(
+            directory.load_ldif("dn:: Y249eA==\n"),
+            Err(DirectoryError::InvalidLdif { line: 1, .. })
+        );
+    }
+}
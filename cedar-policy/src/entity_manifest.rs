@@ -0,0 +1,496 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module computes entity manifests: for each action in a schema, the
+//! entity data which evaluating a policy set can possibly read. Integrators
+//! use a manifest to fetch only that data for a request, instead of the whole
+//! entities of the principal and resource.
+//! ```ignore
+//! let manifest = EntityManifest::compute(&policies, &schema)?;
+//! if let Some(trie) = manifest.for_action(&action) {
+//!     if let Some(principal) = trie.get(&EntityRoot::Principal) {
+//!         // fetch `principal.children()`, and its ancestors if
+//!         // `principal.ancestors_required()`
+//!     }
+//! }
+//! ```
+//!
+//! The data is a trie of the attributes read from each [`EntityRoot`]: the
+//! `principal`, `resource`, or `context` variable, or an entity literal. The
+//! children of an entity-typed node are the attributes of that entity which
+//! are read, so a leaf of entity type only needs the entity's id. A record
+//! used as a whole, e.g., compared with `==`, has all its attributes in the
+//! trie, expanded using their types in the schema. An action without a trie
+//! reads no entity data.
+//!
+//! The analysis uses the types of the strict validator, so the policies must
+//! validate against the schema. Attributes of entities which aren't given by
+//! a path of attributes from a root, e.g., of the result of an `if`, are not
+//! supported.
+#![allow(clippy::module_name_repetitions)]
+
+use crate::{EntityUid, PolicyId, PolicySet, Schema};
+use cedar_policy_core::ast::{BinaryOp, Expr, ExprKind, Literal, Var};
+use cedar_policy_validator::typecheck::{PolicyCheck, Typechecker};
+use cedar_policy_validator::types::{EntityRecordKind, Type};
+use cedar_policy_validator::ValidationMode;
+use miette::Diagnostic;
+use ref_cast::RefCast;
+use smol_str::SmolStr;
+use std::collections::{BTreeMap, HashMap};
+use thiserror::Error;
+
+/// The entity data which policies can read, per action
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntityManifest {
+    per_action: HashMap<EntityUid, RootAccessTrie>,
+}
+
+/// Where a path of attributes starts
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EntityRoot {
+    /// The `principal` of the request
+    Principal,
+    /// The `resource` of the request
+    Resource,
+    /// The `context` of the request
+    Context,
+    /// An entity literal in a policy
+    Literal(EntityUid),
+}
+
+/// The attributes read from each root, for one action
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RootAccessTrie {
+    roots: HashMap<EntityRoot, AccessTrie>,
+}
+
+/// The attributes read from one value, and whether the ancestors of the
+/// entity it refers to are needed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessTrie {
+    children: BTreeMap<SmolStr, AccessTrie>,
+    ancestors_required: bool,
+}
+
+/// Errors computing an [`EntityManifest`]
+#[derive(Debug, Diagnostic, Error)]
+#[non_exhaustive]
+pub enum EntityManifestError {
+    /// A policy does not validate against the schema
+    #[error("policy `{0}` does not validate against the schema")]
+    #[diagnostic(help("validate the policies in strict mode to see the errors"))]
+    Validation(PolicyId),
+    /// A policy reads an attribute of an entity which isn't given by a path
+    /// of attributes from a root
+    #[error("policy `{0}` reads an attribute of an entity which cannot be determined statically")]
+    #[diagnostic(help(
+        "only attributes of `principal`, `resource`, `context`, entity literals, and of their \
+         attributes are supported"
+    ))]
+    Unsupported(PolicyId),
+}
+
+impl EntityManifest {
+    /// Compute the entity manifest of `policies`, for every action in `schema`
+    pub fn compute(policies: &PolicySet, schema: &Schema) -> Result<Self, EntityManifestError> {
+        let typechecker = Typechecker::new(&schema.0, ValidationMode::Strict);
+        let mut manifest = Self::default();
+        for template in policies.ast.all_templates() {
+            let policy_id = || PolicyId::ref_cast(template.id()).clone();
+            for (env, check) in typechecker.typecheck_by_request_env(template) {
+                let expr = match check {
+                    PolicyCheck::Success(expr) => expr,
+                    // the policy never applies to this action
+                    PolicyCheck::Irrelevant(_) => continue,
+                    PolicyCheck::Fail(_) => {
+                        return Err(EntityManifestError::Validation(policy_id()))
+                    }
+                };
+                let Some(action) = env.action_entity_uid() else {
+                    continue;
+                };
+                let trie = manifest
+                    .per_action
+                    .entry(EntityUid::ref_cast(action).clone())
+                    .or_default();
+                trie.visit(&expr)
+                    .ok_or_else(|| EntityManifestError::Unsupported(policy_id()))?;
+            }
+        }
+        manifest.per_action.retain(|_, trie| !trie.roots.is_empty());
+        Ok(manifest)
+    }
+
+    /// The data read for `action`, or `None` if it reads no entity data
+    pub fn for_action(&self, action: &EntityUid) -> Option<&RootAccessTrie> {
+        self.per_action.get(action)
+    }
+
+    /// Iterate over the actions which read entity data, and that data
+    pub fn iter(&self) -> impl Iterator<Item = (&EntityUid, &RootAccessTrie)> {
+        self.per_action.iter()
+    }
+}
+
+/// A path of attributes from a root
+struct AccessPath {
+    root: EntityRoot,
+    attrs: Vec<SmolStr>,
+}
+
+impl RootAccessTrie {
+    /// The data read from `root`, or `None` if nothing is read from it
+    pub fn get(&self, root: &EntityRoot) -> Option<&AccessTrie> {
+        self.roots.get(root)
+    }
+
+    /// Iterate over the roots from which data is read, and that data
+    pub fn iter(&self) -> impl Iterator<Item = (&EntityRoot, &AccessTrie)> {
+        self.roots.iter()
+    }
+
+    /// Add the data read by evaluating `e` to a value. `None` if it reads an
+    /// entity which isn't given by a path.
+    fn visit(&mut self, e: &Expr<Option<Type>>) -> Option<()> {
+        match e.expr_kind() {
+            ExprKind::Var(_) | ExprKind::Lit(_) | ExprKind::GetAttr { .. } => {
+                if let Some(path) = self.path(e)? {
+                    self.insert(path, e.data().as_ref(), false);
+                }
+            }
+            ExprKind::HasAttr { expr, attr } => match self.path(expr)? {
+                Some(mut path) => {
+                    path.attrs.push(attr.clone());
+                    self.insert(path, None, false);
+                }
+                None if is_entity(expr.data().as_ref()) => return None,
+                None => (),
+            },
+            ExprKind::BinaryApp {
+                op: BinaryOp::In,
+                arg1,
+                arg2,
+            } => {
+                match self.path(arg1)? {
+                    Some(path) => self.insert(path, None, true),
+                    None if is_entity(arg1.data().as_ref()) => return None,
+                    None => (),
+                }
+                self.visit(arg2)?;
+            }
+            ExprKind::Slot(_) | ExprKind::Unknown(_) => (),
+            ExprKind::If {
+                test_expr,
+                then_expr,
+                else_expr,
+            } => {
+                self.visit(test_expr)?;
+                self.visit(then_expr)?;
+                self.visit(else_expr)?;
+            }
+            ExprKind::And { left, right }
+            | ExprKind::Or { left, right }
+            | ExprKind::BinaryApp {
+                arg1: left,
+                arg2: right,
+                ..
+            } => {
+                self.visit(left)?;
+                self.visit(right)?;
+            }
+            ExprKind::UnaryApp { arg: expr, .. }
+            | ExprKind::MulByConst { arg: expr, .. }
+            | ExprKind::Like { expr, .. }
+            | ExprKind::Is { expr, .. } => self.visit(expr)?,
+            ExprKind::ExtensionFunctionApp { args: exprs, .. } | ExprKind::Set(exprs) => {
+                for e in exprs.iter() {
+                    self.visit(e)?;
+                }
+            }
+            ExprKind::Record(fields) => {
+                for e in fields.values() {
+                    self.visit(e)?;
+                }
+            }
+        }
+        Some(())
+    }
+
+    /// The path `e` reads, if it's a root or an attribute of a path. If it
+    /// isn't, the data read by evaluating it is added instead, and the result
+    /// is `Some(None)`. `None` if it reads an entity which isn't given by a
+    /// path.
+    fn path(&mut self, e: &Expr<Option<Type>>) -> Option<Option<AccessPath>> {
+        let root = match e.expr_kind() {
+            ExprKind::Var(Var::Principal) => EntityRoot::Principal,
+            ExprKind::Var(Var::Resource) => EntityRoot::Resource,
+            ExprKind::Var(Var::Context) => EntityRoot::Context,
+            ExprKind::Lit(Literal::EntityUID(uid)) => {
+                EntityRoot::Literal(EntityUid::ref_cast(uid).clone())
+            }
+            ExprKind::Var(Var::Action) | ExprKind::Lit(_) => return Some(None),
+            ExprKind::GetAttr { expr, attr } => {
+                return match self.path(expr)? {
+                    Some(mut path) => {
+                        path.attrs.push(attr.clone());
+                        Some(Some(path))
+                    }
+                    None if is_entity(expr.data().as_ref()) => None,
+                    None => Some(None),
+                };
+            }
+            _ => {
+                self.visit(e)?;
+                return Some(None);
+            }
+        };
+        Some(Some(AccessPath {
+            root,
+            attrs: vec![],
+        }))
+    }
+
+    /// Add `path`, whose value has the type `ty` if it's used as a whole
+    fn insert(&mut self, path: AccessPath, ty: Option<&Type>, ancestors_required: bool) {
+        // the id of an entity is known without reading it
+        if path.attrs.is_empty() && !ancestors_required && path.root != EntityRoot::Context {
+            return;
+        }
+        let mut trie = self.roots.entry(path.root).or_default();
+        for attr in path.attrs {
+            trie = trie.children.entry(attr).or_default();
+        }
+        trie.ancestors_required |= ancestors_required;
+        if let Some(ty) = ty {
+            trie.expand(ty);
+        }
+    }
+}
+
+impl AccessTrie {
+    /// The attributes read from this value
+    pub fn children(&self) -> impl Iterator<Item = (&SmolStr, &AccessTrie)> {
+        self.children.iter()
+    }
+
+    /// The data read from the attribute `attr`, or `None` if it isn't read
+    pub fn child(&self, attr: &str) -> Option<&AccessTrie> {
+        self.children.get(attr)
+    }
+
+    /// Whether the ancestors of the entity this value refers to are needed,
+    /// to evaluate `in`
+    pub fn ancestors_required(&self) -> bool {
+        self.ancestors_required
+    }
+
+    /// Add all attributes of a record of type `ty`, and of the records in it
+    fn expand(&mut self, ty: &Type) {
+        match ty {
+            Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) => {
+                for (attr, attr_ty) in attrs.iter() {
+                    self.children
+                        .entry(attr.clone())
+                        .or_default()
+                        .expand(&attr_ty.attr_type);
+                }
+            }
+            Type::Set {
+                element_type: Some(element_type),
+            } => self.expand(element_type),
+            _ => (),
+        }
+    }
+}
+
+/// Whether `ty` is an entity type other than an action, whose attributes are
+/// in the schema
+fn is_entity(ty: Option<&Type>) -> bool {
+    matches!(
+        ty,
+        Some(Type::EntityOrRecord(
+            EntityRecordKind::Entity(_) | EntityRecordKind::AnyEntity
+        ))
+    )
+}
+
+// PANIC SAFETY unit tests
+#[allow(clippy::panic)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cool_asserts::assert_matches;
+    use std::str::FromStr;
+
+    fn schema() -> Schema {
+        Schema::from_json_value(serde_json::json!({ "": {
+            "entityTypes": {
+                "User": {
+                    "memberOfTypes": ["Group"],
+                    "shape": {
+                        "type": "Record",
+                        "attributes": {
+                            "name": { "type": "String" },
+                            "manager": { "type": "Entity", "name": "User" },
+                            "address": {
+                                "type": "Record",
+                                "attributes": {
+                                    "city": { "type": "String" },
+                                    "zip": { "type": "String" }
+                                }
+                            }
+                        }
+                    }
+                },
+                "Group": {},
+                "Photo": {
+                    "shape": {
+                        "type": "Record",
+                        "attributes": {
+                            "owner": { "type": "Entity", "name": "User" },
+                            "private": { "type": "Boolean", "required": false }
+                        }
+                    }
+                }
+            },
+            "actions": {
+                "view": {
+                    "appliesTo": {
+                        "principalTypes": ["User"],
+                        "resourceTypes": ["Photo"],
+                        "context": {
+                            "type": "Record",
+                            "attributes": {
+                                "ip": { "type": "String" },
+                                "location": {
+                                    "type": "Record",
+                                    "attributes": { "city": { "type": "String" } }
+                                }
+                            }
+                        }
+                    }
+                },
+                "edit": {
+                    "appliesTo": {
+                        "principalTypes": ["User"],
+                        "resourceTypes": ["Photo"]
+                    }
+                }
+            }
+        }}))
+        .unwrap()
+    }
+
+    fn manifest(policies: &str) -> Result<EntityManifest, EntityManifestError> {
+        EntityManifest::compute(&PolicySet::from_str(policies).unwrap(), &schema())
+    }
+
+    fn action(name: &str) -> EntityUid {
+        EntityUid::from_str(&format!(r#"Action::"{name}""#)).unwrap()
+    }
+
+    fn attrs(trie: &AccessTrie) -> Vec<&str> {
+        trie.children().map(|(attr, _)| attr.as_str()).collect()
+    }
+
+    #[test]
+    fn attribute_paths() {
+        let manifest = manifest(
+            r#"permit(principal, action == Action::"view", resource)
+            when { resource.owner.manager == principal && principal.address.city == "Seattle" };"#,
+        )
+        .unwrap();
+        assert!(manifest.for_action(&action("edit")).is_none());
+        let trie = manifest.for_action(&action("view")).unwrap();
+        let principal = trie.get(&EntityRoot::Principal).unwrap();
+        assert_eq!(attrs(principal), vec!["address"]);
+        assert_eq!(attrs(principal.child("address").unwrap()), vec!["city"]);
+        assert!(!principal.ancestors_required());
+        let owner = trie
+            .get(&EntityRoot::Resource)
+            .unwrap()
+            .child("owner")
+            .unwrap();
+        assert_eq!(attrs(owner), vec!["manager"]);
+        assert!(attrs(owner.child("manager").unwrap()).is_empty());
+        assert!(trie.get(&EntityRoot::Context).is_none());
+    }
+
+    #[test]
+    fn ancestors() {
+        let manifest = manifest(
+            r#"permit(principal in Group::"admins", action, resource)
+            when { resource.owner in Group::"friends" };"#,
+        )
+        .unwrap();
+        for name in ["view", "edit"] {
+            let trie = manifest.for_action(&action(name)).unwrap();
+            assert!(trie
+                .get(&EntityRoot::Principal)
+                .unwrap()
+                .ancestors_required());
+            let resource = trie.get(&EntityRoot::Resource).unwrap();
+            assert!(!resource.ancestors_required());
+            assert!(resource.child("owner").unwrap().ancestors_required());
+        }
+    }
+
+    #[test]
+    fn whole_records() {
+        let manifest = manifest(
+            r#"permit(principal, action == Action::"view", resource)
+            when { context == { ip: "10.0.0.1", location: { city: "Seattle" } } &&
+                resource has private };"#,
+        )
+        .unwrap();
+        let trie = manifest.for_action(&action("view")).unwrap();
+        let context = trie.get(&EntityRoot::Context).unwrap();
+        assert_eq!(attrs(context), vec!["ip", "location"]);
+        assert_eq!(attrs(context.child("location").unwrap()), vec!["city"]);
+        let resource = trie.get(&EntityRoot::Resource).unwrap();
+        assert_eq!(attrs(resource), vec!["private"]);
+    }
+
+    #[test]
+    fn entity_literals() {
+        let manifest = manifest(
+            r#"permit(principal, action, resource)
+            when { User::"alice".manager == principal };"#,
+        )
+        .unwrap();
+        let trie = manifest.for_action(&action("edit")).unwrap();
+        let alice = EntityRoot::Literal(EntityUid::from_str(r#"User::"alice""#).unwrap());
+        assert_eq!(attrs(trie.get(&alice).unwrap()), vec!["manager"]);
+        assert!(trie.get(&EntityRoot::Principal).is_none());
+    }
+
+    #[test]
+    fn errors() {
+        assert_matches!(
+            manifest(r#"permit(principal, action, resource) when { principal.age > 3 };"#),
+            Err(EntityManifestError::Validation(id)) => assert_eq!(id.to_string(), "policy0")
+        );
+        assert_matches!(
+            manifest(
+                r#"permit(principal, action, resource)
+                when { (if principal.name == "a" then principal else resource.owner).name == "b" };"#
+            ),
+            Err(EntityManifestError::Unsupported(id)) => assert_eq!(id.to_string(), "policy0")
+        );
+    }
+}
@@ -0,0 +1,306 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module contains [`EntityDataSource`], for authorizing requests against
+//! entities in an external store, e.g., a database, without loading all of
+//! them up front.
+//!
+//! Rather than looking up entities one at a time while evaluating policies,
+//! [`Authorizer::is_authorized_with_source`] first computes every uid the
+//! request may need with [`prefetch_hints`]: the principal, action, and
+//! resource of the request, and the entity literals in the policies. It then
+//! fetches them in a single batch, followed by one batch per level of their
+//! ancestors, and only then evaluates the policies.
+//!
+//! Entities which are only referenced from attribute values or from the
+//! context, e.g., `principal.manager` in `principal.manager.level > 3`, are not
+//! fetched. Use [`load_entities`] with additional uids if policies need them.
+
+use crate::{
+    Authorizer, Entities, EntitiesError, Entity, EntityUid, PolicySet, Request, Response, Schema,
+};
+use cedar_policy_core::ast::{ExprKind, Literal};
+use miette::Diagnostic;
+use std::collections::HashSet;
+use thiserror::Error;
+
+/// A store of entities which can be queried in batches
+pub trait EntityDataSource {
+    /// Errors querying the store
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Fetch the entities with `uids`, in one query if possible. Uids which
+    /// aren't in the store are left out of the result. The result may also
+    /// include entities which weren't asked for, e.g., the ancestors of the
+    /// entities asked for, which saves queries for them.
+    fn fetch_entities(&self, uids: &[EntityUid]) -> Result<Vec<Entity>, Self::Error>;
+}
+
+/// Errors loading entities from an [`EntityDataSource`]
+#[derive(Debug, Diagnostic, Error)]
+#[non_exhaustive]
+pub enum EntityDataSourceError<E: std::error::Error + 'static> {
+    /// Querying the store failed
+    #[error("failed to fetch entities: {0}")]
+    Source(#[source] E),
+    /// The entities are not valid, e.g., they don't conform to the schema
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Entities(#[from] EntitiesError),
+}
+
+impl Authorizer {
+    /// Authorize `r` against `p`, with the entities it needs from `source`.
+    /// See the [module documentation](crate::entity_source) for which entities
+    /// those are.
+    ///
+    /// `schema`, if given, is used to validate the entities and to add the
+    /// action entities, which `source` then doesn't need to store.
+    pub fn is_authorized_with_source<S: EntityDataSource>(
+        &self,
+        r: &Request,
+        p: &PolicySet,
+        source: &S,
+        schema: Option<&Schema>,
+    ) -> Result<Response, EntityDataSourceError<S::Error>> {
+        let entities = load_entities(source, prefetch_hints(r, p), schema)?;
+        Ok(self.is_authorized(r, p, &entities))
+    }
+}
+
+/// The uids of the entities which authorizing `r` against `p` may need: the
+/// principal, action, and resource of `r`, the entity literals in `p`, and
+/// the entities template-linked policies fill their slots with. Ancestors of
+/// these entities are not included.
+pub fn prefetch_hints(r: &Request, p: &PolicySet) -> HashSet<EntityUid> {
+    let mut uids: HashSet<EntityUid> = [r.principal(), r.action(), r.resource()]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    for policy in p.ast.policies() {
+        for expr in policy.condition().subexpressions() {
+            if let ExprKind::Lit(Literal::EntityUID(uid)) = expr.expr_kind() {
+                uids.insert(EntityUid(uid.as_ref().clone()));
+            }
+        }
+        // the scope of a linked policy has slots, rather than the literals
+        // they're linked to
+        uids.extend(policy.env().values().cloned().map(EntityUid));
+    }
+    uids
+}
+
+/// Fetch the entities with `uids` from `source` in one batch, then all their
+/// ancestors, in one batch for each level of the hierarchy. Uids which aren't
+/// in `source` are left out of the [`Entities`] returned.
+pub fn load_entities<S: EntityDataSource>(
+    source: &S,
+    uids: impl IntoIterator<Item = EntityUid>,
+    schema: Option<&Schema>,
+) -> Result<Entities, EntityDataSourceError<S::Error>> {
+    let mut seen: HashSet<EntityUid> = HashSet::new();
+    let mut pending: Vec<EntityUid> = uids
+        .into_iter()
+        .filter(|uid| seen.insert(uid.clone()))
+        .collect();
+    let mut fetched: HashSet<EntityUid> = HashSet::new();
+    let mut entities: Vec<Entity> = Vec::new();
+    // each iteration fetches the next level of the hierarchy
+    while !pending.is_empty() {
+        let batch: Vec<Entity> = source
+            .fetch_entities(&pending)
+            .map_err(EntityDataSourceError::Source)?
+            .into_iter()
+            // entities which weren't asked for may have been fetched already
+            .filter(|entity| fetched.insert(entity.uid()))
+            .collect();
+        seen.extend(batch.iter().map(Entity::uid));
+        // the ancestors of each entity within the batch, including its
+        // parents, which may not be in the batch
+        let level = Entities::from_entities(batch.iter().cloned(), None)?;
+        pending = batch
+            .iter()
+            .flat_map(|entity| level.ancestors(&entity.uid()).into_iter().flatten())
+            .filter(|uid| seen.insert((*uid).clone()))
+            .cloned()
+            .collect();
+        entities.extend(batch);
+    }
+    Ok(Entities::from_entities(entities, schema)?)
+}
+
+// PANIC SAFETY unit tests
+#[allow(clippy::panic)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Context, Decision, PolicyId, SlotId};
+    use cool_asserts::assert_matches;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::convert::Infallible;
+    use std::str::FromStr;
+
+    /// An in-memory store, which records the queries made to it
+    struct Store {
+        entities: HashMap<EntityUid, Entity>,
+        queries: RefCell<Vec<Vec<String>>>,
+    }
+
+    impl Store {
+        fn new() -> Self {
+            let entities = Entities::from_json_value(
+                serde_json::json!([
+                    { "uid": { "type": "User", "id": "alice" }, "attrs": {},
+                      "parents": [{ "type": "Group", "id": "admins" }] },
+                    { "uid": { "type": "Group", "id": "admins" }, "attrs": {},
+                      "parents": [{ "type": "Group", "id": "staff" }] },
+                    { "uid": { "type": "Group", "id": "staff" }, "attrs": {}, "parents": [] },
+                    { "uid": { "type": "Photo", "id": "vacation" }, "attrs": {}, "parents": [] },
+                    { "uid": { "type": "Photo", "id": "unused" }, "attrs": {}, "parents": [] },
+                ]),
+                None,
+            )
+            .unwrap();
+            Self {
+                entities: entities.iter().map(|e| (e.uid(), e.clone())).collect(),
+                queries: RefCell::new(Vec::new()),
+            }
+        }
+    }
+
+    impl EntityDataSource for Store {
+        type Error = Infallible;
+
+        fn fetch_entities(&self, uids: &[EntityUid]) -> Result<Vec<Entity>, Infallible> {
+            let mut query: Vec<String> = uids.iter().map(ToString::to_string).collect();
+            query.sort();
+            self.queries.borrow_mut().push(query);
+            Ok(uids
+                .iter()
+                .filter_map(|uid| self.entities.get(uid))
+                .cloned()
+                .collect())
+        }
+    }
+
+    fn request() -> Request {
+        Request::new(
+            Some(EntityUid::from_str(r#"User::"alice""#).unwrap()),
+            Some(EntityUid::from_str(r#"Action::"view""#).unwrap()),
+            Some(EntityUid::from_str(r#"Photo::"vacation""#).unwrap()),
+            Context::empty(),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn hints() {
+        let policies = PolicySet::from_str(
+            r#"permit(principal in Group::"staff", action, resource)
+            when { resource != Photo::"secret" };"#,
+        )
+        .unwrap();
+        let mut hints: Vec<String> = prefetch_hints(&request(), &policies)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        hints.sort();
+        assert_eq!(
+            hints,
+            vec![
+                r#"Action::"view""#,
+                r#"Group::"staff""#,
+                r#"Photo::"secret""#,
+                r#"Photo::"vacation""#,
+                r#"User::"alice""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn linked_hints() {
+        let mut policies = PolicySet::from_str(
+            r#"permit(principal in ?principal, action, resource == ?resource);"#,
+        )
+        .unwrap();
+        policies
+            .link(
+                PolicyId::from_str("policy0").unwrap(),
+                PolicyId::from_str("link").unwrap(),
+                HashMap::from([
+                    (
+                        SlotId::principal(),
+                        EntityUid::from_str(r#"Group::"staff""#).unwrap(),
+                    ),
+                    (
+                        SlotId::resource(),
+                        EntityUid::from_str(r#"Photo::"shared""#).unwrap(),
+                    ),
+                ]),
+            )
+            .unwrap();
+        let hints = prefetch_hints(&request(), &policies);
+        assert!(hints.contains(&EntityUid::from_str(r#"Group::"staff""#).unwrap()));
+        assert!(hints.contains(&EntityUid::from_str(r#"Photo::"shared""#).unwrap()));
+        assert_eq!(hints.len(), 5);
+    }
+
+    #[test]
+    fn batches_by_level() {
+        let store = Store::new();
+        let policies = PolicySet::from_str(
+            r#"permit(principal in Group::"staff", action, resource == Photo::"vacation");"#,
+        )
+        .unwrap();
+        let response = Authorizer::new()
+            .is_authorized_with_source(&request(), &policies, &store, None)
+            .unwrap();
+        assert_eq!(response.decision(), Decision::Allow);
+        assert_eq!(
+            response.diagnostics().reason().collect::<Vec<_>>(),
+            vec![&PolicyId::from_str("policy0").unwrap()]
+        );
+        // one query for the hints, then one for each level of ancestors which
+        // weren't fetched already
+        assert_eq!(
+            *store.queries.borrow(),
+            vec![
+                vec![
+                    r#"Action::"view""#.to_owned(),
+                    r#"Group::"staff""#.to_owned(),
+                    r#"Photo::"vacation""#.to_owned(),
+                    r#"User::"alice""#.to_owned(),
+                ],
+                vec![r#"Group::"admins""#.to_owned()],
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_entities() {
+        let store = Store::new();
+        let entities = load_entities(
+            &store,
+            [EntityUid::from_str(r#"User::"bob""#).unwrap()],
+            None,
+        )
+        .unwrap();
+        assert_matches!(entities.iter().next(), None);
+    }
+}
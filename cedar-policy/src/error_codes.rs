@@ -0,0 +1,949 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module contains the catalog of the codes of the errors of parsing,
+//! evaluation, validation, requests, and entities, which are the
+//! [`Diagnostic::code`]s of the errors, e.g., for links to documentation, or
+//! for routing alerts.
+//! ```ignore
+//! if let Some(code) = error_codes::of(&err) {
+//!     log::warn!("{}: {} ({})", code, err, code.description());
+//! }
+//! ```
+//!
+//! A code is `cedar::<category>::<name>`, e.g.,
+//! `cedar::evaluation::type_error`. Unlike the messages of errors, codes never
+//! change, and the code of a removed kind of error isn't reused. Errors
+//! which contain a list of errors, e.g., [`ParseErrors`](crate::ParseErrors),
+//! have the code of the first one.
+
+use miette::Diagnostic;
+use std::fmt;
+
+/// The categories of error codes, which are the middle segments of the codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum ErrorCategory {
+    /// Errors parsing policies, templates, and expressions
+    Parse,
+    /// Errors evaluating policies and expressions
+    Evaluation,
+    /// Errors and warnings of validating policies against a schema
+    Validation,
+    /// Errors of requests and contexts which don't match the schema
+    Request,
+    /// Errors of entities, e.g., which don't match the schema
+    Entities,
+    /// Errors of the JSON formats of entities and values
+    Json,
+}
+
+impl ErrorCategory {
+    /// The segment of the codes of this category, e.g., `evaluation`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Parse => "parse",
+            Self::Evaluation => "evaluation",
+            Self::Validation => "validation",
+            Self::Request => "request",
+            Self::Entities => "entities",
+            Self::Json => "json",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An error code of the catalog
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ErrorCode {
+    category: ErrorCategory,
+    code: &'static str,
+    description: &'static str,
+}
+
+impl ErrorCode {
+    /// The code, e.g., `cedar::evaluation::type_error`
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    /// The category of the code
+    pub fn category(&self) -> ErrorCategory {
+        self.category
+    }
+
+    /// A one-line description of the errors with the code
+    pub fn description(&self) -> &'static str {
+        self.description
+    }
+}
+
+/// The code
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code)
+    }
+}
+
+const fn code(category: ErrorCategory, code: &'static str, description: &'static str) -> ErrorCode {
+    ErrorCode {
+        category,
+        code,
+        description,
+    }
+}
+
+const CODES: &[ErrorCode] = &[
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_token",
+        "the text contains a character or token that isn't valid Cedar",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::unexpected_end_of_input",
+        "the text ends before the policy or expression is complete",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::unexpected_token",
+        "a token appears where the grammar doesn't allow it",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::extra_token",
+        "there is text after a complete policy or expression",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::syntax",
+        "the text doesn't follow the Cedar grammar",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::duplicate_template_id",
+        "two templates in a policy set have the same id",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::duplicate_policy_id",
+        "two policies in a policy set have the same id",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::unexpected_template",
+        "a static policy contains a template slot",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::bad_annotations",
+        "a policy has a malformed or duplicate annotation",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::slots_in_condition_clause",
+        "a template slot appears in a `when` or `unless` clause",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::missing_scope_constraint",
+        "the scope of a policy is missing `principal`, `action`, or `resource`",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::extra_head_constraints",
+        "the scope of a policy has more than three constraints",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::reserved_identifier",
+        "a reserved identifier is used as a name",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_identifier",
+        "a name isn't a valid identifier",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_effect",
+        "the effect of a policy isn't `permit` or `forbid`",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_condition",
+        "a condition of a policy isn't `when` or `unless`",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_scope_constraint_variable",
+        "a scope constraint isn't on `principal`, `action`, or `resource`",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_method_name",
+        "a method name isn't valid",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::incorrect_variable",
+        "the variables of the scope of a policy are in the wrong order",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_constraint_operator",
+        "a scope constraint uses an operator other than `==`, `in`, or `is`",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_scope_equality_rhs",
+        "the right hand side of `==` in the scope isn't an entity or slot",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_action_type",
+        "an action in the scope doesn't have the type `Action`",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::empty_clause",
+        "a `when` or `unless` clause has no condition",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::annotation_invariant_violation",
+        "an internal error of the parser with annotations",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::membership_invariant_violation",
+        "an internal error of the parser with `in` expressions",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_string",
+        "a string literal isn't valid",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::arbitrary_variable",
+        "a variable other than `principal`, `action`, `resource`, or `context` is used",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_attribute",
+        "an attribute name isn't an identifier or string literal",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_attributes_in_record_literal",
+        "a record literal has invalid attributes",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::path_as_attribute",
+        "an attribute name contains a namespace",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::function_call_on_method",
+        "a method is called as a function",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::method_call_on_function",
+        "a function is called as a method",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_pattern",
+        "the right hand side of `like` isn't a pattern literal",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::is_invalid_name",
+        "the right hand side of `is` isn't an entity type",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::wrong_node",
+        "an expression of the wrong kind appears",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::ambiguous_operators",
+        "relational operators are chained without parentheses",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::unsupported_division",
+        "division is used, which isn't supported",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::unsupported_modulo",
+        "remainder is used, which isn't supported",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::non_constant_multiplication",
+        "a multiplication isn't by an integer literal",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::integer_literal_too_large",
+        "an integer literal is too large for a long",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::unary_op_limit",
+        "a unary operator is applied more than four times in a row",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::variable_call",
+        "a variable is called as a function",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::no_methods",
+        "a method is called on a name which has no methods",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::not_a_function",
+        "a name which isn't a function is called",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::unsupported_entity_literals",
+        "an entity literal is used, which isn't supported",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::expression_call",
+        "a function call isn't of a function name",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_access",
+        "an attribute is accessed on a name which has none",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_index",
+        "a name which has no attributes is indexed",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::non_string_index",
+        "an index expression isn't a string literal",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::duplicate_key_in_record_literal",
+        "a record literal has the same key more than once",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::type_constraints",
+        "a type constraint with `:` is used, which isn't supported",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_path",
+        "a path appears where it isn't valid",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::non_normalized_string",
+        "a string, e.g., an entity uid, isn't in normalized form",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::missing_node_data",
+        "an internal error of the parser with missing data",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::has_non_literal_rhs",
+        "the right hand side of `has` isn't an attribute name",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_expression",
+        "a name isn't a valid expression",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::wrong_arity",
+        "a function or method is called with the wrong number of arguments",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_slot",
+        "a template slot other than `?principal` or `?resource` is used",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::not_a_literal",
+        "a literal was expected, but the text is another expression",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::wrong_entity_argument",
+        "an operator of the scope has the wrong kind of entity argument",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::is_in_action_scope",
+        "`is` appears in the action constraint of the scope",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::is_with_scope_operator",
+        "`is` appears in the scope together with another operator",
+    ),
+    code(
+        ErrorCategory::Parse,
+        "cedar::parse::invalid_escape",
+        "a string literal has an invalid escape sequence",
+    ),
+    code(
+        ErrorCategory::Evaluation,
+        "cedar::evaluation::entity_does_not_exist",
+        "an entity which doesn't exist in the entities is used",
+    ),
+    code(
+        ErrorCategory::Evaluation,
+        "cedar::evaluation::entity_attr_does_not_exist",
+        "an entity doesn't have the accessed attribute",
+    ),
+    code(
+        ErrorCategory::Evaluation,
+        "cedar::evaluation::unspecified_entity_access",
+        "an attribute of an unspecified entity is accessed",
+    ),
+    code(
+        ErrorCategory::Evaluation,
+        "cedar::evaluation::record_attr_does_not_exist",
+        "a record doesn't have the accessed attribute",
+    ),
+    code(
+        ErrorCategory::Evaluation,
+        "cedar::evaluation::extension_function_lookup",
+        "an extension function doesn't exist or is defined more than once",
+    ),
+    code(
+        ErrorCategory::Evaluation,
+        "cedar::evaluation::type_error",
+        "an operator is applied to a value of the wrong type",
+    ),
+    code(
+        ErrorCategory::Evaluation,
+        "cedar::evaluation::wrong_num_arguments",
+        "an extension function is called with the wrong number of arguments",
+    ),
+    code(
+        ErrorCategory::Evaluation,
+        "cedar::evaluation::integer_overflow",
+        "an arithmetic operation overflows a long",
+    ),
+    code(
+        ErrorCategory::Evaluation,
+        "cedar::evaluation::invalid_restricted_expression",
+        "an expression isn't a restricted expression, where one is required",
+    ),
+    code(
+        ErrorCategory::Evaluation,
+        "cedar::evaluation::unlinked_slot",
+        "a template slot is evaluated without being linked",
+    ),
+    code(
+        ErrorCategory::Evaluation,
+        "cedar::evaluation::extension_function_application",
+        "an extension function fails, e.g., on an invalid argument",
+    ),
+    code(
+        ErrorCategory::Evaluation,
+        "cedar::evaluation::non_value",
+        "an expression containing unknowns is evaluated without partial evaluation",
+    ),
+    code(
+        ErrorCategory::Evaluation,
+        "cedar::evaluation::recursion_limit",
+        "an expression is nested too deeply to evaluate",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::unrecognized_entity_type",
+        "a policy uses an entity type not declared in the schema",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::unrecognized_action_id",
+        "a policy uses an action not declared in the schema",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::invalid_action_application",
+        "no action of the scope applies to its principal and resource types",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::unspecified_entity",
+        "a policy uses an unspecified entity",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::unexpected_type",
+        "an expression has a type other than the expected one",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::incompatible_types",
+        "the operands of an operator have incompatible types",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::unsafe_attribute_access",
+        "an attribute which may not exist is accessed",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::unsafe_optional_attribute_access",
+        "an optional attribute is accessed without a `has` check",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::impossible_policy",
+        "a policy evaluates to false for all valid requests",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::undefined_function",
+        "an extension function which doesn't exist is called",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::multiply_defined_function",
+        "an extension function is defined more than once",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::wrong_number_arguments",
+        "an extension function is called with the wrong number of arguments",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::wrong_call_style",
+        "an extension function is called with the wrong style, method or function",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::function_argument_validation",
+        "an argument of an extension function is invalid",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::empty_set_forbidden",
+        "an empty set literal is used, whose type can't be determined",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::non_lit_ext_constructor",
+        "an extension constructor is called on a non-literal",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::hierarchy_not_respected",
+        "the operands of `in` can't be related in the entity hierarchy",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::mixed_script_string",
+        "a string mixes characters of different scripts",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::bidi_chars_in_string",
+        "a string contains bidirectional control characters",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::bidi_chars_in_identifier",
+        "an identifier contains bidirectional control characters",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::mixed_script_identifier",
+        "an identifier mixes characters of different scripts",
+    ),
+    code(
+        ErrorCategory::Validation,
+        "cedar::validation::confusable_identifier",
+        "an identifier contains characters which may be confused with others",
+    ),
+    code(
+        ErrorCategory::Request,
+        "cedar::request::undeclared_action",
+        "the action of a request isn't declared in the schema",
+    ),
+    code(
+        ErrorCategory::Request,
+        "cedar::request::undeclared_principal_type",
+        "the principal type of a request isn't declared in the schema",
+    ),
+    code(
+        ErrorCategory::Request,
+        "cedar::request::undeclared_resource_type",
+        "the resource type of a request isn't declared in the schema",
+    ),
+    code(
+        ErrorCategory::Request,
+        "cedar::request::invalid_principal_type",
+        "the principal type of a request isn't valid for its action",
+    ),
+    code(
+        ErrorCategory::Request,
+        "cedar::request::invalid_resource_type",
+        "the resource type of a request isn't valid for its action",
+    ),
+    code(
+        ErrorCategory::Request,
+        "cedar::request::invalid_context",
+        "the context of a request doesn't match the schema of its action",
+    ),
+    code(
+        ErrorCategory::Request,
+        "cedar::request::unknown_insufficient_type_info",
+        "the type of an unknown in the context can't be determined",
+    ),
+    code(
+        ErrorCategory::Request,
+        "cedar::request::nontrivial_residual",
+        "the type of a residual in the context can't be determined",
+    ),
+    code(
+        ErrorCategory::Request,
+        "cedar::request::heterogeneous_set",
+        "a set has elements of different types",
+    ),
+    code(
+        ErrorCategory::Request,
+        "cedar::request::context_not_a_record",
+        "a context isn't a record",
+    ),
+    code(
+        ErrorCategory::Request,
+        "cedar::request::duplicate_key_in_record_literal",
+        "a context has the same key more than once",
+    ),
+    code(
+        ErrorCategory::Entities,
+        "cedar::entities::duplicate_entity",
+        "an entity is given more than once",
+    ),
+    code(
+        ErrorCategory::Entities,
+        "cedar::entities::conflicting_duplicate_entity",
+        "an entity is given more than once with different data",
+    ),
+    code(
+        ErrorCategory::Entities,
+        "cedar::entities::entity_not_found",
+        "an entity which doesn't exist is updated or removed",
+    ),
+    code(
+        ErrorCategory::Entities,
+        "cedar::entities::hierarchy_changed",
+        "a change would require recomputing the ancestors of other entities",
+    ),
+    code(
+        ErrorCategory::Entities,
+        "cedar::entities::missing_tc_edge",
+        "the entity hierarchy isn't transitively closed",
+    ),
+    code(
+        ErrorCategory::Entities,
+        "cedar::entities::hierarchy_cycle",
+        "the entity hierarchy has a cycle",
+    ),
+    code(
+        ErrorCategory::Entities,
+        "cedar::entities::attr_evaluation",
+        "an attribute of an entity fails to evaluate",
+    ),
+    code(
+        ErrorCategory::Entities,
+        "cedar::entities::unexpected_entity_attr",
+        "an entity has an attribute not declared in the schema",
+    ),
+    code(
+        ErrorCategory::Entities,
+        "cedar::entities::missing_required_entity_attr",
+        "an entity is missing a required attribute of the schema",
+    ),
+    code(
+        ErrorCategory::Entities,
+        "cedar::entities::type_mismatch",
+        "an attribute of an entity has a type other than the declared one",
+    ),
+    code(
+        ErrorCategory::Entities,
+        "cedar::entities::heterogeneous_set",
+        "an attribute of an entity is a set with elements of different types",
+    ),
+    code(
+        ErrorCategory::Entities,
+        "cedar::entities::invalid_ancestor_type",
+        "an entity has a parent of a type not allowed by the schema",
+    ),
+    code(
+        ErrorCategory::Entities,
+        "cedar::entities::unexpected_entity_type",
+        "an entity has a type not declared in the schema",
+    ),
+    code(
+        ErrorCategory::Entities,
+        "cedar::entities::undeclared_action",
+        "an action entity isn't declared in the schema",
+    ),
+    code(
+        ErrorCategory::Entities,
+        "cedar::entities::action_declaration_mismatch",
+        "an action entity differs from its declaration in the schema",
+    ),
+    code(
+        ErrorCategory::Entities,
+        "cedar::entities::extension_function_lookup",
+        "an extension function needed for an attribute doesn't exist",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::invalid_json",
+        "the input isn't valid JSON, or doesn't have the expected structure",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::invalid_escape",
+        "an `__entity` or `__extn` escape is invalid",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::expected_literal_entity_ref",
+        "a value which must be an entity reference is something else",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::expected_extn_value",
+        "a value which must be an extension value is something else",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::action_parent_is_not_action",
+        "an action has a parent which isn't an action",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::duplicate_entity_attr",
+        "an attribute is in both `attrs` and `unknownAttrs`",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::missing_implied_constructor",
+        "no extension constructor makes a value of the type of the schema",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::duplicate_key_in_record_literal",
+        "a record has the same key more than once",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::unexpected_record_attr",
+        "a record has an attribute not declared in the schema",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::missing_required_record_attr",
+        "a record is missing a required attribute of the schema",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::type_mismatch",
+        "a value has a type other than the declared one",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::heterogeneous_set",
+        "a set has elements of different types",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::extension_function_lookup",
+        "an extension function needed for a value doesn't exist",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::unknown_in_implicit_constructor_arg",
+        "the argument of an implicit extension constructor is unknown",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::expr_tag",
+        "the `__expr` escape is used, which is no longer supported",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::serialization",
+        "a value fails to serialize to JSON",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::extension_call_0_arguments",
+        "an extension function call with no arguments can't be serialized",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::extension_call_2_or_more_arguments",
+        "an extension function call with several arguments can't be serialized",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::reserved_key",
+        "a record with a key reserved for escapes can't be serialized",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::unexpected_restricted_expr_kind",
+        "an internal error serializing a restricted expression",
+    ),
+    code(
+        ErrorCategory::Json,
+        "cedar::json::residual",
+        "a residual can't be serialized",
+    ),
+];
+
+/// All the error codes, by category
+pub fn all() -> &'static [ErrorCode] {
+    CODES
+}
+
+/// The error code `code`, if it's in the catalog
+pub fn lookup(code: &str) -> Option<&'static ErrorCode> {
+    CODES.iter().find(|c| c.code == code)
+}
+
+/// The error code of `diagnostic`, if it has one in the catalog
+pub fn of(diagnostic: &dyn Diagnostic) -> Option<&'static ErrorCode> {
+    lookup(&diagnostic.code()?.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Authorizer, Context, Entities, EntityUid, PolicySet, Request, Schema, ValidationMode,
+        Validator,
+    };
+    use std::collections::HashSet;
+    use std::str::FromStr;
+
+    #[test]
+    fn codes_are_unique_and_in_their_category() {
+        let mut codes = HashSet::new();
+        for code in all() {
+            assert!(codes.insert(code.code()), "duplicate code {code}");
+            assert!(
+                code.code()
+                    .starts_with(&format!("cedar::{}::", code.category())),
+                "{code} isn't in the category {}",
+                code.category()
+            );
+        }
+    }
+
+    #[test]
+    fn errors_have_codes() {
+        let code = |diagnostic: &dyn Diagnostic| of(diagnostic).map(ErrorCode::code);
+
+        let err =
+            PolicySet::from_str("permit(principal, action, resource) when { 1 / 2 };").unwrap_err();
+        assert_eq!(code(&err), Some("cedar::parse::unsupported_division"));
+        let err = PolicySet::from_str("permit(principal, action, resource").unwrap_err();
+        assert_eq!(code(&err), Some("cedar::parse::unexpected_end_of_input"));
+
+        let schema = Schema::from_json_value(serde_json::json!({ "": {
+            "entityTypes": { "User": {}, "Photo": {} },
+            "actions": { "view": { "appliesTo": {
+                "principalTypes": ["User"], "resourceTypes": ["Photo"]
+            } } }
+        } }))
+        .unwrap();
+        let policies =
+            PolicySet::from_str(r#"permit(principal == Usr::"alice", action, resource);"#).unwrap();
+        let result = Validator::new(schema.clone()).validate(&policies, ValidationMode::Strict);
+        let err = result.validation_errors().next().unwrap();
+        assert_eq!(
+            code(err),
+            Some("cedar::validation::unrecognized_entity_type")
+        );
+
+        let euid = |s: &str| Some(EntityUid::from_str(s).unwrap());
+        let err = Request::new(
+            euid(r#"Photo::"a""#),
+            euid(r#"Action::"view""#),
+            euid(r#"Photo::"a""#),
+            Context::empty(),
+            Some(&schema),
+        )
+        .unwrap_err();
+        assert_eq!(code(&err), Some("cedar::request::invalid_principal_type"));
+
+        let request = Request::new(
+            euid(r#"User::"alice""#),
+            euid(r#"Action::"view""#),
+            euid(r#"Photo::"a""#),
+            Context::empty(),
+            None,
+        )
+        .unwrap();
+        let policies = PolicySet::from_str(
+            r#"permit(principal, action, resource) when { principal.age > 3 };"#,
+        )
+        .unwrap();
+        let response = Authorizer::new().is_authorized(&request, &policies, &Entities::empty());
+        let err = response.diagnostics().errors().next().unwrap();
+        assert_eq!(code(err), Some("cedar::evaluation::entity_does_not_exist"));
+
+        let err = Entities::from_json_value(
+            serde_json::json!([{ "uid": { "type": "User", "id": "alice" }, "attrs": {}, "parents": [] },
+                               { "uid": { "type": "User", "id": "alice" }, "attrs": { "a": 1 }, "parents": [] }]),
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(code(&err), Some("cedar::entities::duplicate_entity"));
+        let err = Entities::from_json_str("[", None).unwrap_err();
+        assert_eq!(code(&err), Some("cedar::json::invalid_json"));
+    }
+}
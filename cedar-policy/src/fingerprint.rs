@@ -0,0 +1,216 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module contains the [`Fingerprint`]s of policy sets, schemas, and
+//! entity stores, which are hashes of their contents, e.g., for keys of
+//! decision caches, or for checking that the nodes of a distributed service
+//! have the same data.
+//! ```ignore
+//! if policies.fingerprint() != expected {
+//!     log::warn!("policies have drifted");
+//! }
+//! ```
+//!
+//! A fingerprint is the same for equal contents, however they were written or
+//! built, e.g., with different whitespace, or order of policies, entities, or
+//! attributes, and in every process, since the hash isn't seeded. It may
+//! change between versions of this crate, though, as what's hashed is built
+//! from the crate's own representation of the contents, so only compare
+//! fingerprints computed by the same version. It isn't a cryptographic hash,
+//! so it doesn't protect against deliberate collisions: use
+//! [`signing`](crate::signing) for that.
+
+use crate::{Entities, EntitiesError, PolicySet, Schema};
+use std::fmt;
+
+/// A 128-bit hash of the contents of a policy set, schema, or entity store
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fingerprint(u128);
+
+impl Fingerprint {
+    /// The 128-bit FNV-1a hash of `bytes`
+    pub(crate) fn of(bytes: &[u8]) -> Self {
+        const OFFSET_BASIS: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+        const PRIME: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+        Self(bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+            (hash ^ u128::from(*byte)).wrapping_mul(PRIME)
+        }))
+    }
+
+    /// The hash as an integer
+    pub fn as_u128(&self) -> u128 {
+        self.0
+    }
+}
+
+/// 32 hex digits
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+/// The bytes of `value` with the keys of every object sorted, and every array
+/// sorted by the bytes of its elements. The arrays of the JSON forms of
+/// schemas and entities are all sets, e.g., of parents, of set values, or of
+/// entity types, so their order doesn't matter.
+fn canonical(value: serde_json::Value) -> Vec<u8> {
+    fn sort(value: serde_json::Value) -> (Vec<u8>, serde_json::Value) {
+        let value = match value {
+            serde_json::Value::Object(map) => {
+                let mut entries: Vec<_> = map.into_iter().map(|(k, v)| (k, sort(v).1)).collect();
+                entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                serde_json::Value::Object(entries.into_iter().collect())
+            }
+            serde_json::Value::Array(values) => {
+                let mut values: Vec<_> = values.into_iter().map(sort).collect();
+                values.sort_by(|(a, _), (b, _)| a.cmp(b));
+                serde_json::Value::Array(values.into_iter().map(|(_, v)| v).collect())
+            }
+            value => value,
+        };
+        // PANIC SAFETY: a `serde_json::Value` serializes
+        #[allow(clippy::expect_used)]
+        let bytes = serde_json::to_vec(&value).expect("value should serialize");
+        (bytes, value)
+    }
+    sort(value).0
+}
+
+impl PolicySet {
+    /// The fingerprint of the policies, templates, and template links. It's
+    /// the hash of their [canonical encoding](crate::signing::canonical_bytes),
+    /// so it doesn't depend on whitespace or comments.
+    pub fn fingerprint(&self) -> Fingerprint {
+        Fingerprint::of(&crate::signing::canonical_bytes(self, None))
+    }
+}
+
+impl Schema {
+    /// The fingerprint of the entity types and actions, so schemas which
+    /// declare the same types have the same fingerprint, e.g., even if one
+    /// declares them with common types and the other doesn't. It's the hash
+    /// of the validator's internal form of the schema, which is why it's
+    /// likelier than the others to change between versions of this crate.
+    pub fn fingerprint(&self) -> Fingerprint {
+        // PANIC SAFETY: a schema only has string keys, so it serializes
+        #[allow(clippy::expect_used)]
+        let value = serde_json::to_value(&self.0).expect("schema should serialize");
+        Fingerprint::of(&canonical(value))
+    }
+}
+
+impl Entities {
+    /// The fingerprint of the entities, their attributes, and their
+    /// ancestors. This fails if an attribute's value isn't known, which is
+    /// only possible with the `partial-eval` feature.
+    pub fn fingerprint(&self) -> Result<Fingerprint, EntitiesError> {
+        Ok(Fingerprint::of(&canonical(self.0.to_json_value()?)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn fnv() {
+        // the FNV-1a test vectors
+        assert_eq!(
+            Fingerprint::of(b"").to_string(),
+            "6c62272e07bb014262b821756295c58d"
+        );
+        assert_eq!(
+            Fingerprint::of(b"a").to_string(),
+            "d228cb696f1a8caf78912b704e4a8964"
+        );
+    }
+
+    #[test]
+    fn policy_sets() {
+        let a = PolicySet::from_str(
+            r#"permit(principal == User::"alice", action, resource);
+            forbid(principal, action, resource) when { context.risk > 3 };"#,
+        )
+        .unwrap();
+        let b = PolicySet::from_str(
+            r#"permit (principal == User::"alice", action, resource) ;
+            // the same policies, written differently
+            forbid(principal,action,resource) when { context.risk > 3 };"#,
+        )
+        .unwrap();
+        let c = PolicySet::from_str(r#"permit(principal == User::"alice", action, resource);"#)
+            .unwrap();
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert_ne!(a.fingerprint(), c.fingerprint());
+    }
+
+    #[test]
+    fn schemas() {
+        let schema = |json| Schema::from_json_value(json).unwrap().fingerprint();
+        let a = schema(serde_json::json!({ "": {
+            "entityTypes": {
+                "User": { "memberOfTypes": ["Group", "Team"] },
+                "Group": {}, "Team": {}
+            },
+            "actions": {}
+        } }));
+        let b = schema(serde_json::json!({ "": {
+            "actions": {},
+            "entityTypes": {
+                "Team": {}, "Group": {},
+                "User": { "memberOfTypes": ["Team", "Group"] }
+            }
+        } }));
+        let c = schema(serde_json::json!({ "": {
+            "entityTypes": { "User": { "memberOfTypes": ["Group"] }, "Group": {} },
+            "actions": {}
+        } }));
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn entities() {
+        let entities = |json| {
+            Entities::from_json_value(json, None)
+                .unwrap()
+                .fingerprint()
+                .unwrap()
+        };
+        let a = entities(serde_json::json!([
+            { "uid": { "type": "User", "id": "alice" },
+              "attrs": { "age": 19, "tags": ["a", "b"] },
+              "parents": [{ "type": "Group", "id": "admins" }, { "type": "Group", "id": "all" }] },
+            { "uid": { "type": "Group", "id": "admins" }, "attrs": {}, "parents": [] },
+        ]));
+        let b = entities(serde_json::json!([
+            { "uid": { "type": "Group", "id": "admins" }, "attrs": {}, "parents": [] },
+            { "uid": { "type": "User", "id": "alice" },
+              "attrs": { "tags": ["b", "a"], "age": 19 },
+              "parents": [{ "type": "Group", "id": "all" }, { "type": "Group", "id": "admins" }] },
+        ]));
+        let c = entities(serde_json::json!([
+            { "uid": { "type": "User", "id": "alice" },
+              "attrs": { "age": 20, "tags": ["a", "b"] },
+              "parents": [{ "type": "Group", "id": "admins" }, { "type": "Group", "id": "all" }] },
+            { "uid": { "type": "Group", "id": "admins" }, "attrs": {}, "parents": [] },
+        ]));
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+}
@@ -22,12 +22,13 @@ use crate::api::EntityId;
 use crate::api::EntityTypeName;
 use crate::PolicyId;
 use crate::{
-    Authorizer, Context, Decision, Entities, EntityUid, ParseErrors, Policy, PolicySet, Request,
-    Response, Schema, SlotId, Template,
+    AuthorizationError, Authorizer, Context, Decision, Entities, EntityUid, ParseErrors, Policy,
+    PolicySet, Request, Response, Schema, SlotId, Template,
 };
 use cedar_policy_core::jsonvalue::JsonValueWithNoDuplicateKeys;
 use itertools::Itertools;
 use miette::Diagnostic;
+use ref_cast::RefCast;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use serde_with::MapPreventDuplicates;
@@ -35,16 +36,30 @@ use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 use thiserror::Error;
 
+#[cfg(not(target_arch = "wasm32"))]
 thread_local!(
     /// Per-thread authorizer instance, initialized on first use
     static AUTHORIZER: Authorizer = Authorizer::new();
 );
 
+/// Call `f` with an authorizer. The authorizer is reused across calls on the
+/// same thread, except on wasm32, where a new one is created for each call.
+fn with_authorizer<T>(f: impl FnOnce(&Authorizer) -> T) -> T {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        AUTHORIZER.with(f)
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        f(&Authorizer::new())
+    }
+}
+
 /// Construct and ask the authorizer the request.
 fn is_authorized(call: AuthorizationCall) -> AuthorizationAnswer {
     match call.get_components() {
         Ok((request, policies, entities)) => {
-            AUTHORIZER.with(|authorizer| AuthorizationAnswer::Success {
+            with_authorizer(|authorizer| AuthorizationAnswer::Success {
                 response: authorizer
                     .is_authorized(&request, &policies, &entities)
                     .into(),
@@ -80,13 +95,65 @@ pub struct InterfaceResponse {
 }
 
 /// Interface version of `Diagnostics` that stores error messages as strings for simpler (de)serialization
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct InterfaceDiagnostics {
     /// `PolicyId`s of the policies that contributed to the decision.
     /// If no policies applied to the request, this set will be empty.
     reason: HashSet<PolicyId>,
     /// Set of error messages that occurred
     errors: HashSet<String>,
+    /// The errors that occurred, with the stable codes of their kinds, in
+    /// order of policy id and code
+    #[serde(default)]
+    error_details: Vec<InterfaceError>,
+}
+
+/// An error that occurred during authorization, usually while evaluating a
+/// policy
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct InterfaceError {
+    /// Id of the policy with the error, if the error is about a policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    policy_id: Option<PolicyId>,
+    /// Stable code of the kind of the error, see
+    /// [`EvaluationErrorKind::error_code`](crate::EvaluationErrorKind::error_code)
+    code: String,
+    /// Error message
+    message: String,
+}
+
+impl InterfaceError {
+    /// Get the id of the policy with the error, if the error is about a policy
+    pub fn policy_id(&self) -> Option<&PolicyId> {
+        self.policy_id.as_ref()
+    }
+
+    /// Get the code of the kind of the error
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Get the error message
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl From<&AuthorizationError> for InterfaceError {
+    fn from(err: &AuthorizationError) -> Self {
+        match err {
+            AuthorizationError::PolicyEvaluationError { id, error } => Self {
+                policy_id: Some(PolicyId::ref_cast(id).clone()),
+                code: error.error_code().to_string(),
+                message: error.to_string(),
+            },
+            AuthorizationError::UnverifiedPolicySet => Self {
+                policy_id: None,
+                code: err.error_code().to_string(),
+                message: err.to_string(),
+            },
+        }
+    }
 }
 
 impl InterfaceResponse {
@@ -94,7 +161,11 @@ impl InterfaceResponse {
     pub fn new(decision: Decision, reason: HashSet<PolicyId>, errors: HashSet<String>) -> Self {
         Self {
             decision,
-            diagnostics: InterfaceDiagnostics { reason, errors },
+            diagnostics: InterfaceDiagnostics {
+                reason,
+                errors,
+                error_details: Vec::new(),
+            },
         }
     }
 
@@ -111,7 +182,15 @@ impl InterfaceResponse {
 
 impl From<Response> for InterfaceResponse {
     fn from(response: Response) -> Self {
-        Self::new(
+        let mut error_details: Vec<InterfaceError> =
+            response.diagnostics().errors().map(Into::into).collect();
+        error_details.sort_by_key(|err| {
+            (
+                err.policy_id.as_ref().map(ToString::to_string),
+                err.code.clone(),
+            )
+        });
+        let mut response = Self::new(
             response.decision(),
             response.diagnostics().reason().cloned().collect(),
             response
@@ -119,7 +198,9 @@ impl From<Response> for InterfaceResponse {
                 .errors()
                 .map(ToString::to_string)
                 .collect(),
-        )
+        );
+        response.diagnostics.error_details = error_details;
+        response
     }
 }
 
@@ -133,6 +214,11 @@ impl InterfaceDiagnostics {
     pub fn errors(&self) -> impl Iterator<Item = &str> + '_ {
         self.errors.iter().map(String::as_str)
     }
+
+    /// Get the errors with the codes of their kinds
+    pub fn error_details(&self) -> impl Iterator<Item = &InterfaceError> {
+        self.error_details.iter()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -162,6 +248,10 @@ struct AuthorizationCall {
     /// If a schema is not provided, this option has no effect.
     #[serde(default = "constant_true")]
     enable_request_validation: bool,
+    /// If this is `true` and a schema is provided, error if `context` has
+    /// attributes which aren't declared for the action.
+    #[serde(default)]
+    strict_context: bool,
     slice: RecvdSlice,
 }
 
@@ -195,8 +285,13 @@ impl AuthorizationCall {
 
         let context = serde_json::to_value(self.context)
             .map_err(|e| [format!("Error encoding the context as JSON: {e}")])?;
-        let context = Context::from_json_value(context, schema.as_ref().map(|s| (s, &action)))
-            .map_err(|e| [e.to_string()])?;
+        let context = match &schema {
+            Some(schema) if self.strict_context => {
+                Context::from_json_value_strict(context, (schema, &action))
+            }
+            schema => Context::from_json_value(context, schema.as_ref().map(|s| (s, &action))),
+        }
+        .map_err(|e| [e.to_string()])?;
         let q = Request::new(
             principal,
             Some(action),
@@ -311,10 +406,7 @@ fn parse_instantiation(v: &Link) -> Result<(SlotId, EntityUid), Vec<String>> {
         }
     };
     let type_name = EntityTypeName::from_str(v.value.ty.as_str());
-    let eid = match EntityId::from_str(v.value.eid.as_str()) {
-        Ok(eid) => eid,
-        Err(err) => match err {},
-    };
+    let eid = EntityId::from_raw(v.value.eid.as_str());
     match type_name {
         Ok(type_name) => {
             let entity_uid = EntityUid::from_type_name_and_id(type_name, eid);
@@ -523,6 +615,118 @@ mod test {
             );
     }
 
+    #[test]
+    fn test_json_schema_matches() {
+        use crate::frontend::json_schema::{
+            assert_properties, authorization_answer, authorization_call,
+        };
+
+        let call: AuthorizationCall = serde_json::from_value(serde_json::json!({
+            "principal": { "type": "User", "id": "alice" },
+            "action": { "type": "Action", "id": "view" },
+            "resource": null,
+            "context": {},
+            "slice": {
+                "policies": {},
+                "entities": [],
+                "templates": {},
+                "template_instantiations": [{
+                    "template_id": "t",
+                    "result_policy_id": "p",
+                    "instantiations": [
+                        { "slot": "?principal", "value": { "ty": "User", "eid": "alice" } }
+                    ]
+                }]
+            }
+        }))
+        .unwrap();
+        let call = serde_json::to_value(call).unwrap();
+        let schema = authorization_call();
+        let defs = &schema["$defs"];
+        assert_properties(&schema, &call);
+        let slice = &call["slice"];
+        assert_properties(&defs["Slice"], slice);
+        let link = &slice["template_instantiations"][0];
+        assert_properties(&defs["TemplateLink"], link);
+        assert_properties(&defs["Link"], &link["instantiations"][0]);
+        assert_properties(
+            &defs["Link"]["properties"]["value"],
+            &link["instantiations"][0]["value"],
+        );
+
+        let answer = AuthorizationAnswer::Success {
+            response: InterfaceResponse::new(Decision::Allow, HashSet::new(), HashSet::new()),
+        };
+        let answer = serde_json::to_value(answer).unwrap();
+        let schema = authorization_answer();
+        let defs = &schema["$defs"];
+        assert_properties(&schema, &answer);
+        assert_properties(&defs["InterfaceResponse"], &answer["response"]);
+        assert_properties(
+            &defs["InterfaceDiagnostics"],
+            &answer["response"]["diagnostics"],
+        );
+        let error = InterfaceError {
+            policy_id: Some(PolicyId::from_str("p").unwrap()),
+            code: "type_error".into(),
+            message: "type error".into(),
+        };
+        assert_properties(
+            &defs["InterfaceError"],
+            &serde_json::to_value(error).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_error_details() {
+        let call = r#"
+        {
+            "principal": { "type": "User", "id": "alice" },
+            "action": { "type": "Photo", "id": "view" },
+            "resource": { "type": "Photo", "id": "door" },
+            "context": {},
+            "slice": {
+             "policies": {
+                "p0": "permit(principal, action, resource) when { principal.is_admin };",
+                "p1": "permit(principal, action, resource) when { 1 + \"a\" == 2 };"
+             },
+             "entities": []
+            }
+        }
+        "#;
+        assert_matches!(json_is_authorized(call), InterfaceResult::Success { result } => {
+            let answer: AuthorizationAnswer = serde_json::from_str(&result).unwrap();
+            assert_matches!(answer, AuthorizationAnswer::Success { response } => {
+                let details = response
+                    .diagnostics()
+                    .error_details()
+                    .map(|err| (err.policy_id().unwrap().to_string(), err.code()))
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    details,
+                    vec![
+                        ("p0".to_string(), "entity_does_not_exist"),
+                        ("p1".to_string(), "type_error"),
+                    ]
+                );
+                // the details are compared too
+                let json = serde_json::to_string(&response).unwrap();
+                assert_eq!(
+                    serde_json::from_str::<InterfaceResponse>(&json).unwrap(),
+                    response
+                );
+                assert_ne!(
+                    InterfaceResponse::new(
+                        response.decision(),
+                        response.diagnostics().reason().cloned().collect(),
+                        response.diagnostics().errors().map(ToString::to_string).collect(),
+                    ),
+                    response
+                );
+            });
+        });
+    }
+
     #[test]
     fn test_failure_on_invalid_syntax() {
         assert_is_failure(
@@ -1398,4 +1602,38 @@ mod test {
         }"#;
         assert_is_failure(&json_is_authorized(call), true, "found duplicate key");
     }
+
+    #[test]
+    fn test_authorized_fails_on_undeclared_context_attr_with_strict_context() {
+        let call = |strict_context: bool| {
+            serde_json::json!({
+                "principal": { "type": "User", "id": "alice" },
+                "action": { "type": "Action", "id": "view" },
+                "resource": { "type": "Photo", "id": "door" },
+                "context": { "is_authenticated": true, "sorce_ip": "222.222.222.222" },
+                "schema": { "": {
+                    "entityTypes": { "User": {}, "Photo": {} },
+                    "actions": { "view": { "appliesTo": {
+                        "principalTypes": ["User"],
+                        "resourceTypes": ["Photo"],
+                        "context": { "type": "Record", "attributes": {
+                            "is_authenticated": { "type": "Boolean" },
+                            "source_ip": { "type": "String", "required": false }
+                        } }
+                    } } }
+                } },
+                "strict_context": strict_context,
+                "slice": { "policies": {}, "entities": [] }
+            })
+            .to_string()
+        };
+        assert_is_failure(
+            &json_is_authorized(&call(true)),
+            false,
+            "`sorce_ip` (did you mean `source_ip`?)",
+        );
+        // without `strict_context`, the schema-based parser reports the first
+        // unexpected attribute
+        assert_is_failure(&json_is_authorized(&call(false)), false, "sorce_ip");
+    }
 }
@@ -0,0 +1,422 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module contains [JSON Schema](https://json-schema.org) documents
+//! (draft 2020-12) describing the input and output of `json_is_authorized` and
+//! `json_validate`, for authors of bindings in other languages.
+//!
+//! Both functions return an [`InterfaceResult`](super::utils::InterfaceResult),
+//! described by [`interface_result`]. On success, its `result` is a string
+//! containing JSON, described by [`authorization_answer`] or
+//! [`validation_answer`] respectively.
+//!
+//! Unknown properties are ignored in the input of both functions, and never
+//! present in their output. The documents are checked against the serialized
+//! form of the Rust types in tests, so they change whenever the wire format
+//! does.
+use serde_json::{json, Value};
+
+/// The version of JSON Schema the documents in this module use
+const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Names and documents of every JSON Schema in this module, e.g., for writing
+/// them to files
+pub fn all() -> Vec<(&'static str, Value)> {
+    vec![
+        ("AuthorizationCall", authorization_call()),
+        ("AuthorizationAnswer", authorization_answer()),
+        ("ValidationCall", validation_call()),
+        ("ValidationAnswer", validation_answer()),
+        ("InterfaceResult", interface_result()),
+    ]
+}
+
+/// JSON Schema of the input of `json_is_authorized`
+pub fn authorization_call() -> Value {
+    json!({
+        "$schema": DIALECT,
+        "title": "AuthorizationCall",
+        "description": "Input of `json_is_authorized`",
+        "type": "object",
+        "properties": {
+            "principal": {
+                "description": "Principal of the request, or null if it's unspecified",
+                "oneOf": [{ "$ref": "#/$defs/EntityUid" }, { "type": "null" }]
+            },
+            "action": {
+                "description": "Action of the request",
+                "$ref": "#/$defs/EntityUid"
+            },
+            "resource": {
+                "description": "Resource of the request, or null if it's unspecified",
+                "oneOf": [{ "$ref": "#/$defs/EntityUid" }, { "type": "null" }]
+            },
+            "context": {
+                "description": "Context of the request, with attribute values in the JSON \
+                                entity format",
+                "type": "object"
+            },
+            "schema": {
+                "description": "Schema in the JSON schema format, used to parse the context \
+                                and entities, and to validate the request",
+                "type": ["object", "null"]
+            },
+            "enable_request_validation": {
+                "description": "Whether to validate the request against `schema`, if one \
+                                is given",
+                "type": "boolean",
+                "default": true
+            },
+            "strict_context": {
+                "description": "Whether to error if `context` has attributes which aren't \
+                                declared for the action in `schema`, if one is given",
+                "type": "boolean",
+                "default": false
+            },
+            "slice": { "$ref": "#/$defs/Slice" }
+        },
+        "required": ["action", "context", "slice"],
+        "$defs": {
+            "EntityUid": entity_uid(),
+            "PolicySpecification": policy_specification(),
+            "Slice": {
+                "description": "Policies, templates, and entities to authorize the request \
+                                with",
+                "type": "object",
+                "properties": {
+                    "policies": { "$ref": "#/$defs/PolicySpecification" },
+                    "entities": {
+                        "description": "Entities in the JSON entity format",
+                        "type": "array"
+                    },
+                    "templates": {
+                        "description": "Templates, by template id",
+                        "type": ["object", "null"],
+                        "additionalProperties": { "type": "string" }
+                    },
+                    "template_instantiations": {
+                        "description": "Template-linked policies",
+                        "type": ["array", "null"],
+                        "items": { "$ref": "#/$defs/TemplateLink" }
+                    }
+                },
+                "required": ["policies", "entities"]
+            },
+            "TemplateLink": {
+                "type": "object",
+                "properties": {
+                    "template_id": {
+                        "description": "Id of the template to link",
+                        "type": "string"
+                    },
+                    "result_policy_id": {
+                        "description": "Id of the template-linked policy",
+                        "type": "string"
+                    },
+                    "instantiations": {
+                        "description": "Values of the slots of the template, with at most \
+                                        one per slot",
+                        "type": "array",
+                        "items": { "$ref": "#/$defs/Link" }
+                    }
+                },
+                "required": ["template_id", "result_policy_id", "instantiations"]
+            },
+            "Link": {
+                "type": "object",
+                "properties": {
+                    "slot": { "enum": ["?principal", "?resource"] },
+                    "value": {
+                        "type": "object",
+                        "properties": {
+                            "ty": { "description": "Entity type", "type": "string" },
+                            "eid": { "description": "Entity id", "type": "string" }
+                        },
+                        "required": ["ty", "eid"]
+                    }
+                },
+                "required": ["slot", "value"]
+            }
+        }
+    })
+}
+
+/// JSON Schema of the `result` of a successful `json_is_authorized`
+pub fn authorization_answer() -> Value {
+    json!({
+        "$schema": DIALECT,
+        "title": "AuthorizationAnswer",
+        "description": "`result` of a successful `json_is_authorized`",
+        "type": "object",
+        "properties": {
+            "response": { "$ref": "#/$defs/InterfaceResponse" }
+        },
+        "required": ["response"],
+        "additionalProperties": false,
+        "$defs": {
+            "InterfaceResponse": {
+                "type": "object",
+                "properties": {
+                    "decision": { "enum": ["Allow", "Deny"] },
+                    "diagnostics": { "$ref": "#/$defs/InterfaceDiagnostics" }
+                },
+                "required": ["decision", "diagnostics"],
+                "additionalProperties": false
+            },
+            "InterfaceDiagnostics": {
+                "type": "object",
+                "properties": {
+                    "reason": {
+                        "description": "Ids of the policies that determined the decision",
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "uniqueItems": true
+                    },
+                    "errors": {
+                        "description": "Errors that occurred while evaluating policies",
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "uniqueItems": true
+                    },
+                    "error_details": {
+                        "description": "Errors that occurred while evaluating policies, with \
+                                        the stable codes of their kinds, in order of policy id \
+                                        and code",
+                        "type": "array",
+                        "items": { "$ref": "#/$defs/InterfaceError" },
+                        "default": []
+                    }
+                },
+                "required": ["reason", "errors"],
+                "additionalProperties": false
+            },
+            "InterfaceError": {
+                "type": "object",
+                "properties": {
+                    "policy_id": {
+                        "description": "Id of the policy with the error, if the error is \
+                                        about a policy",
+                        "type": "string"
+                    },
+                    "code": {
+                        "description": "Stable code of the kind of the error, e.g., \
+                                        `entity_does_not_exist`",
+                        "type": "string"
+                    },
+                    "message": { "type": "string" }
+                },
+                "required": ["code", "message"],
+                "additionalProperties": false
+            }
+        }
+    })
+}
+
+/// JSON Schema of the input of `json_validate`
+pub fn validation_call() -> Value {
+    json!({
+        "$schema": DIALECT,
+        "title": "ValidationCall",
+        "description": "Input of `json_validate`",
+        "type": "object",
+        "properties": {
+            "validationSettings": {
+                "type": "object",
+                "properties": {
+                    "mode": { "enum": ["regular", "off"] }
+                },
+                "required": ["mode"],
+                "default": { "mode": "regular" }
+            },
+            "schema": {
+                "description": "Schema in the JSON schema format",
+                "type": "object"
+            },
+            "policySet": { "$ref": "#/$defs/PolicySpecification" }
+        },
+        "required": ["schema", "policySet"],
+        "$defs": {
+            "PolicySpecification": policy_specification()
+        }
+    })
+}
+
+/// JSON Schema of the `result` of a successful `json_validate`
+pub fn validation_answer() -> Value {
+    json!({
+        "$schema": DIALECT,
+        "title": "ValidationAnswer",
+        "description": "`result` of a successful `json_validate`",
+        "type": "object",
+        "properties": {
+            "notes": {
+                "description": "Validation errors, which are empty if the policies are valid",
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "policyId": { "type": "string" },
+                        "note": { "type": "string" }
+                    },
+                    "required": ["policyId", "note"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["notes"],
+        "additionalProperties": false
+    })
+}
+
+/// JSON Schema of the output of `json_is_authorized` and `json_validate`
+pub fn interface_result() -> Value {
+    json!({
+        "$schema": DIALECT,
+        "title": "InterfaceResult",
+        "description": "Output of `json_is_authorized` and `json_validate`",
+        "oneOf": [
+            {
+                "type": "object",
+                "properties": {
+                    "success": { "const": "true" },
+                    "result": {
+                        "description": "JSON string containing the result of the call",
+                        "type": "string",
+                        "contentMediaType": "application/json"
+                    }
+                },
+                "required": ["success", "result"],
+                "additionalProperties": false
+            },
+            {
+                "type": "object",
+                "properties": {
+                    "success": { "const": "false" },
+                    "isInternal": {
+                        "description": "Whether the failure is internal, e.g., the call \
+                                        is not valid JSON, rather than caused by the \
+                                        user-supplied parts of the call, e.g., a policy \
+                                        that doesn't parse",
+                        "type": "boolean"
+                    },
+                    "errors": { "type": "array", "items": { "type": "string" } }
+                },
+                "required": ["success", "isInternal", "errors"],
+                "additionalProperties": false
+            }
+        ]
+    })
+}
+
+/// Subschema of an entity uid in the JSON entity format
+fn entity_uid() -> Value {
+    let uid = json!({
+        "type": "object",
+        "properties": {
+            "type": { "type": "string" },
+            "id": { "type": "string" }
+        },
+        "required": ["type", "id"]
+    });
+    json!({
+        "oneOf": [
+            uid,
+            {
+                "type": "object",
+                "properties": { "__entity": uid },
+                "required": ["__entity"]
+            }
+        ]
+    })
+}
+
+/// Subschema of a [`PolicySpecification`](super::utils::PolicySpecification)
+fn policy_specification() -> Value {
+    json!({
+        "description": "Policies, either as one string of Cedar policies with generated ids \
+                        `policy0`, `policy1`, etc., or as an object of single policies by id",
+        "oneOf": [
+            { "type": "string" },
+            { "type": "object", "additionalProperties": { "type": "string" } }
+        ]
+    })
+}
+
+/// Assert that the properties of `schema`, an object schema, are the keys of
+/// `value`, the serialized form of the type it describes. Every optional
+/// field of the type must be present in `value`.
+#[cfg(test)]
+#[track_caller] // report the caller's location as the location of the panic, not the location in this function
+pub(crate) fn assert_properties(schema: &Value, value: &Value) {
+    use std::collections::BTreeSet;
+
+    let properties: BTreeSet<&String> = schema["properties"]
+        .as_object()
+        .map(|properties| properties.keys().collect())
+        .unwrap_or_default();
+    let keys: BTreeSet<&String> = value
+        .as_object()
+        .map(|value| value.keys().collect())
+        .unwrap_or_default();
+    assert_eq!(properties, keys, "schema properties differ from {value}");
+}
+
+// PANIC SAFETY unit tests
+#[allow(clippy::panic)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::frontend::utils::InterfaceResult;
+
+    /// Every `$ref` in `value` points into `root["$defs"]`
+    fn check_refs(root: &Value, value: &Value) {
+        match value {
+            Value::Object(obj) => {
+                if let Some(r) = obj.get("$ref") {
+                    let name = r.as_str().and_then(|r| r.strip_prefix("#/$defs/"));
+                    assert!(
+                        name.is_some_and(|name| root["$defs"].get(name).is_some()),
+                        "dangling reference {r}"
+                    );
+                }
+                obj.values().for_each(|v| check_refs(root, v));
+            }
+            Value::Array(values) => values.iter().for_each(|v| check_refs(root, v)),
+            _ => (),
+        }
+    }
+
+    #[test]
+    fn documents_are_well_formed() {
+        for (name, schema) in all() {
+            assert_eq!(schema["$schema"], DIALECT, "{name}");
+            assert_eq!(schema["title"], name);
+            check_refs(&schema, &schema);
+        }
+    }
+
+    #[test]
+    fn interface_result_matches() {
+        let [success, failure] = [
+            InterfaceResult::succeed("result"),
+            InterfaceResult::fail_bad_request(vec![]),
+        ]
+        .map(|result| serde_json::to_value(result).unwrap_or_default());
+        let schema = interface_result();
+        assert_properties(&schema["oneOf"][0], &success);
+        assert_properties(&schema["oneOf"][1], &failure);
+    }
+}
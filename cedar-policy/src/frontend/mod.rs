@@ -15,5 +15,8 @@
  */
 
 pub mod is_authorized;
+pub mod json_schema;
 pub mod utils;
 pub mod validate;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -453,6 +453,35 @@ mod test {
         });
     }
 
+    #[test]
+    fn test_json_schema_matches() {
+        use crate::frontend::json_schema::{assert_properties, validation_answer, validation_call};
+
+        let call = ValidateCall {
+            validation_settings: ValidationSettings::default(),
+            schema: cedar_policy_validator::SchemaFragment(HashMap::new()),
+            policy_set: PolicySpecification::Map(HashMap::new()),
+        };
+        let call = serde_json::to_value(call).unwrap();
+        let schema = validation_call();
+        assert_properties(&schema, &call);
+        assert_properties(
+            &schema["properties"]["validationSettings"],
+            &call["validationSettings"],
+        );
+
+        let answer = ValidateAnswer::Success {
+            notes: vec![ValidationNote {
+                policy_id: "policy0".to_string(),
+                note: "note".to_string(),
+            }],
+        };
+        let answer = serde_json::to_value(answer).unwrap();
+        let schema = validation_answer();
+        assert_properties(&schema, &answer);
+        assert_properties(&schema["properties"]["notes"]["items"], &answer["notes"][0]);
+    }
+
     #[test]
     fn test_validate_fails_on_duplicate_policy_id() {
         let call_json = r#"{
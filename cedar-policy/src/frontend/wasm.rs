@@ -0,0 +1,101 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module exposes `json_is_authorized` and `json_validate` to JavaScript
+//! through `wasm-bindgen`, when built for `wasm32-unknown-unknown` with the
+//! `wasm` feature.
+//!
+//! Both functions take the same JSON input as the functions they wrap, and
+//! return the [`InterfaceResult`] serialized as JSON, so that no types other
+//! than strings cross the JavaScript boundary.
+// the glue code generated by `#[wasm_bindgen]` is unsafe
+#![allow(unsafe_code)]
+
+use super::is_authorized::json_is_authorized;
+use super::utils::InterfaceResult;
+use super::validate::json_validate;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Serialize `result` for JavaScript
+fn to_json(result: &InterfaceResult) -> String {
+    serde_json::to_string(result).unwrap_or_else(|e| {
+        serde_json::json!({
+            "success": "false",
+            "isInternal": true,
+            "errors": [format!("error serializing result: {e}")],
+        })
+        .to_string()
+    })
+}
+
+/// `json_is_authorized`, exported to JavaScript as `isAuthorized`
+#[wasm_bindgen(js_name = "isAuthorized")]
+pub fn is_authorized(input: &str) -> String {
+    to_json(&json_is_authorized(input))
+}
+
+/// `json_validate`, exported to JavaScript as `validate`
+#[wasm_bindgen(js_name = "validate")]
+pub fn validate(input: &str) -> String {
+    to_json(&json_validate(input))
+}
+
+// PANIC SAFETY unit tests
+#[allow(clippy::panic)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cool_asserts::assert_matches;
+
+    #[test]
+    fn is_authorized_returns_json() {
+        let call = r#"
+        {
+            "principal": { "type": "User", "id": "alice" },
+            "action": { "type": "Photo", "id": "view" },
+            "resource": { "type": "Photo", "id": "door" },
+            "context": {},
+            "slice": {
+                "policies": "permit(principal == User::\"alice\", action, resource);",
+                "entities": []
+            }
+        }
+        "#;
+        let result: InterfaceResult =
+            serde_json::from_str(&is_authorized(call)).expect("result should be JSON");
+        assert_matches!(result, InterfaceResult::Success { result } => {
+            assert!(result.contains("\"decision\":\"Allow\""), "{result}");
+        });
+    }
+
+    #[test]
+    fn validate_returns_json() {
+        let result: InterfaceResult =
+            serde_json::from_str(&validate(r#"{ "schema": {}, "policySet": {} }"#))
+                .expect("result should be JSON");
+        assert_matches!(result, InterfaceResult::Success { .. });
+
+        let result: InterfaceResult =
+            serde_json::from_str(&validate("not json")).expect("result should be JSON");
+        assert_matches!(
+            result,
+            InterfaceResult::Failure {
+                is_internal: true,
+                ..
+            }
+        );
+    }
+}
@@ -0,0 +1,587 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module contains generators of Cedar values for property testing and
+//! fuzzing integrations with Cedar.
+//!
+//! With the `arbitrary` feature, [`EntityUid`], [`RestrictedExpression`],
+//! [`Context`], [`Policy`], [`PolicySet`], [`Request`], and [`Entities`]
+//! implement [`arbitrary::Arbitrary`]. These values are not tied to any schema.
+//! Entity types, ids, and attribute names are drawn from small pools, so that
+//! generated policies, requests, and entities often refer to each other.
+//!
+//! [`arbitrary_entities`] and [`arbitrary_request`] generate entities and
+//! requests which conform to a schema instead. Their entity ids are `0`, `1`,
+//! and `2`, so the requests often refer to the entities.
+//!
+//! With the `proptest` feature, the functions in the `strategies` module turn
+//! these generators into `proptest` strategies.
+
+use crate::{
+    Context, Entities, Entity, EntityId, EntityUid, Policy, PolicySet, Request,
+    RestrictedExpression, Schema,
+};
+use arbitrary::{Arbitrary, Error, Result, Unstructured};
+use cedar_policy_core::ast;
+use cedar_policy_validator::types::{AttributeType, EntityRecordKind, Primitive, Type};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
+
+/// Entity types of the values which aren't tied to a schema
+const ENTITY_TYPES: &[&str] = &["User", "Group", "Photo", "Album"];
+/// Entity ids of the values which aren't tied to a schema
+const ENTITY_IDS: &[&str] = &["alice", "bob", "admins", "vacation", "trip"];
+/// Actions of the values which aren't tied to a schema
+const ACTIONS: &[&str] = &["view", "edit", "delete"];
+/// Attribute names of the values which aren't tied to a schema
+const ATTRIBUTES: &[&str] = &["name", "age", "owner", "tags", "level", "public"];
+/// Patterns of `like` expressions
+const PATTERNS: &[&str] = &["*", "a*", "*@example.com", "\\*"];
+/// Maximum depth of generated expressions and values
+const MAX_DEPTH: usize = 3;
+/// Number of entities of each type generated by [`arbitrary_entities`]
+const IDS_PER_TYPE: usize = 3;
+
+impl<'a> Arbitrary<'a> for EntityUid {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let entity_type = u.choose(ENTITY_TYPES)?;
+        let id = if u.ratio(1, 4)? {
+            u.arbitrary::<String>()?
+        } else {
+            (*u.choose(ENTITY_IDS)?).to_owned()
+        };
+        uid(entity_type, &id)
+    }
+}
+
+impl<'a> Arbitrary<'a> for RestrictedExpression {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        restricted_expression(u, MAX_DEPTH)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Context {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let pairs = record_pairs(u, MAX_DEPTH)?;
+        Self::from_pairs(pairs).map_err(|_| Error::IncorrectFormat)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Policy {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let id = format!("policy{}", u.int_in_range(0..=9)?);
+        Self::parse(Some(id), policy_text(u)?).map_err(|_| Error::IncorrectFormat)
+    }
+}
+
+impl<'a> Arbitrary<'a> for PolicySet {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut set = Self::new();
+        for i in 0..u.int_in_range(0..=5)? {
+            let policy = Policy::parse(Some(format!("policy{i}")), policy_text(u)?)
+                .map_err(|_| Error::IncorrectFormat)?;
+            set.add(policy).map_err(|_| Error::IncorrectFormat)?;
+        }
+        Ok(set)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Request {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let principal = u.arbitrary()?;
+        let action = uid("Action", u.choose(ACTIONS)?)?;
+        let resource = u.arbitrary()?;
+        let context = u.arbitrary()?;
+        Self::new(Some(principal), Some(action), Some(resource), context, None)
+            .map_err(|_| Error::IncorrectFormat)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Entities {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut uids: Vec<EntityUid> = vec![];
+        for _ in 0..u.int_in_range(0..=8)? {
+            let uid = u.arbitrary()?;
+            if !uids.contains(&uid) {
+                uids.push(uid);
+            }
+        }
+        let mut entities = vec![];
+        for (i, uid) in uids.iter().enumerate() {
+            let attrs = record_pairs(u, MAX_DEPTH - 1)?.into_iter().collect();
+            // parents only among the entities before this one, so that the
+            // hierarchy is a DAG
+            let parents = parents(u, uids.iter().take(i))?;
+            entities.push(
+                Entity::new(uid.clone(), attrs, parents).map_err(|_| Error::IncorrectFormat)?,
+            );
+        }
+        Self::from_entities(entities, None).map_err(|_| Error::IncorrectFormat)
+    }
+}
+
+/// Generate [`Entities`] which conform to `schema`: between zero and three
+/// entities of each entity type, with ids `0`, `1`, and `2`, attributes of the
+/// declared types, and parents of the declared types
+pub fn arbitrary_entities(schema: &Schema, u: &mut Unstructured<'_>) -> Result<Entities> {
+    let mut entity_types: Vec<_> = schema.0.entity_types().collect();
+    // sort, so that the same data generates the same entities
+    entity_types.sort_by_key(|(name, _)| name.to_string());
+    let mut uids: Vec<(EntityUid, &ast::Name)> = vec![];
+    for (name, _) in &entity_types {
+        for id in 0..u.int_in_range(0..=IDS_PER_TYPE)? {
+            uids.push((uid(&name.to_string(), &id.to_string())?, name));
+        }
+    }
+    let mut entities = vec![];
+    for (i, (uid, name)) in uids.iter().enumerate() {
+        let Some(entity_type) = schema.0.get_entity_type(name) else {
+            return Err(Error::IncorrectFormat);
+        };
+        let attrs = conforming_pairs(u, entity_type.attributes(), MAX_DEPTH)?
+            .into_iter()
+            .collect();
+        // parents only among the entities before this one, so that the
+        // hierarchy is a DAG
+        let candidates = uids.iter().take(i).filter(|(_, parent_type)| {
+            schema
+                .0
+                .get_entity_type(parent_type)
+                .is_some_and(|parent| parent.descendants.contains(*name))
+        });
+        let parents = parents(u, candidates.map(|(uid, _)| uid))?;
+        entities
+            .push(Entity::new(uid.clone(), attrs, parents).map_err(|_| Error::IncorrectFormat)?);
+    }
+    Entities::from_entities(entities, Some(schema)).map_err(|_| Error::IncorrectFormat)
+}
+
+/// Generate a [`Request`] which conforms to `schema`: an action of the schema,
+/// a principal and resource of types it applies to, with ids `0`, `1`, or `2`,
+/// and a context of its context type
+pub fn arbitrary_request(schema: &Schema, u: &mut Unstructured<'_>) -> Result<Request> {
+    let actions = schema
+        .0
+        .action_entities()
+        .map_err(|_| Error::IncorrectFormat)?;
+    let mut actions: Vec<ast::EntityUID> = actions.iter().map(ast::Entity::uid).collect();
+    actions.sort_by_key(ToString::to_string);
+    let action = u.choose(&actions)?;
+    let Some(action_id) = schema.0.get_action_id(action) else {
+        return Err(Error::IncorrectFormat);
+    };
+    let mut principal_types: Vec<_> = action_id.applicable_principal_types().collect();
+    principal_types.sort_by_key(|ty| ty.to_string());
+    let mut resource_types: Vec<_> = action_id.applicable_resource_types().collect();
+    resource_types.sort_by_key(|ty| ty.to_string());
+    let principal = request_uid(u, &principal_types)?;
+    let resource = request_uid(u, &resource_types)?;
+    let context = match action_id.context_type() {
+        Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) => {
+            let pairs = conforming_pairs(u, attrs.iter(), MAX_DEPTH)?;
+            Context::from_pairs(pairs).map_err(|_| Error::IncorrectFormat)?
+        }
+        _ => Context::empty(),
+    };
+    let action = EntityUid::from_str(&action.to_string()).map_err(|_| Error::IncorrectFormat)?;
+    Request::new(principal, Some(action), resource, context, Some(schema))
+        .map_err(|_| Error::IncorrectFormat)
+}
+
+/// The principal or resource of a request, of one of `types`, which is not
+/// specified if the type is unspecified
+fn request_uid(u: &mut Unstructured<'_>, types: &[&ast::EntityType]) -> Result<Option<EntityUid>> {
+    match u.choose(types)? {
+        ast::EntityType::Specified(name) => Ok(Some(uid(
+            &name.to_string(),
+            &u.int_in_range(0..=IDS_PER_TYPE - 1)?.to_string(),
+        )?)),
+        ast::EntityType::Unspecified => Ok(None),
+    }
+}
+
+fn uid(entity_type: &str, id: &str) -> Result<EntityUid> {
+    let entity_type = entity_type.parse().map_err(|_| Error::IncorrectFormat)?;
+    Ok(EntityUid::from_type_name_and_id(
+        entity_type,
+        EntityId::from_raw(id),
+    ))
+}
+
+/// A random subset of `candidates`
+fn parents<'a>(
+    u: &mut Unstructured<'_>,
+    candidates: impl Iterator<Item = &'a EntityUid>,
+) -> Result<HashSet<EntityUid>> {
+    let mut parents = HashSet::new();
+    for candidate in candidates {
+        if u.ratio(1, 3)? {
+            parents.insert(candidate.clone());
+        }
+    }
+    Ok(parents)
+}
+
+fn restricted_expression(u: &mut Unstructured<'_>, depth: usize) -> Result<RestrictedExpression> {
+    let choices = if depth == 0 { 4 } else { 6 };
+    Ok(match u.choose_index(choices)? {
+        0 => RestrictedExpression::new_bool(u.arbitrary()?),
+        1 => RestrictedExpression::new_long(u.arbitrary()?),
+        2 => RestrictedExpression::new_string(u.arbitrary()?),
+        3 => RestrictedExpression::new_entity_uid(u.arbitrary()?),
+        4 => {
+            let mut elements = vec![];
+            for _ in 0..u.int_in_range(0..=3)? {
+                elements.push(restricted_expression(u, depth - 1)?);
+            }
+            RestrictedExpression::new_set(elements)
+        }
+        _ => RestrictedExpression::new_record(record_pairs(u, depth - 1)?)
+            .map_err(|_| Error::IncorrectFormat)?,
+    })
+}
+
+/// Attributes of a record, with names from [`ATTRIBUTES`]
+fn record_pairs(
+    u: &mut Unstructured<'_>,
+    depth: usize,
+) -> Result<Vec<(String, RestrictedExpression)>> {
+    let mut pairs = BTreeMap::new();
+    for _ in 0..u.int_in_range(0..=3)? {
+        let attr = (*u.choose(ATTRIBUTES)?).to_owned();
+        pairs.insert(attr, restricted_expression(u, depth)?);
+    }
+    Ok(pairs.into_iter().collect())
+}
+
+/// Attributes of a record of type `attrs`, including each optional attribute
+/// or not at random
+fn conforming_pairs<'a>(
+    u: &mut Unstructured<'_>,
+    attrs: impl Iterator<Item = (impl ToString, &'a AttributeType)>,
+    depth: usize,
+) -> Result<Vec<(String, RestrictedExpression)>> {
+    let mut pairs = vec![];
+    for (attr, ty) in attrs {
+        if ty.is_required || u.arbitrary()? {
+            pairs.push((attr.to_string(), conforming_value(u, &ty.attr_type, depth)?));
+        }
+    }
+    Ok(pairs)
+}
+
+/// A value of type `ty`
+fn conforming_value(
+    u: &mut Unstructured<'_>,
+    ty: &Type,
+    depth: usize,
+) -> Result<RestrictedExpression> {
+    match ty {
+        Type::True => Ok(RestrictedExpression::new_bool(true)),
+        Type::False => Ok(RestrictedExpression::new_bool(false)),
+        Type::Primitive {
+            primitive_type: Primitive::Bool,
+        } => Ok(RestrictedExpression::new_bool(u.arbitrary()?)),
+        Type::Primitive {
+            primitive_type: Primitive::Long,
+        } => Ok(RestrictedExpression::new_long(u.arbitrary()?)),
+        Type::Primitive {
+            primitive_type: Primitive::String,
+        } => Ok(RestrictedExpression::new_string(u.arbitrary()?)),
+        Type::Set {
+            element_type: Some(element_type),
+        } => {
+            let mut elements = vec![];
+            if depth > 0 {
+                for _ in 0..u.int_in_range(0..=3)? {
+                    elements.push(conforming_value(u, element_type, depth - 1)?);
+                }
+            }
+            Ok(RestrictedExpression::new_set(elements))
+        }
+        Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) => {
+            let pairs = conforming_pairs(u, attrs.iter(), depth.saturating_sub(1))?;
+            RestrictedExpression::new_record(pairs).map_err(|_| Error::IncorrectFormat)
+        }
+        Type::EntityOrRecord(EntityRecordKind::Entity(lub)) => {
+            let Some(name) = lub.get_single_entity() else {
+                return Err(Error::IncorrectFormat);
+            };
+            let id = u.int_in_range(0..=IDS_PER_TYPE - 1)?.to_string();
+            Ok(RestrictedExpression::new_entity_uid(uid(
+                &name.to_string(),
+                &id,
+            )?))
+        }
+        Type::ExtensionType { name } => {
+            let call = match name.to_string().as_str() {
+                "ipaddr" => format!(
+                    "ip(\"10.0.{}.{}/{}\")",
+                    u.arbitrary::<u8>()?,
+                    u.arbitrary::<u8>()?,
+                    u.int_in_range(16..=32)?
+                ),
+                "decimal" => format!(
+                    "decimal(\"{}.{}\")",
+                    u.int_in_range(-1000..=1000)?,
+                    u.int_in_range(0..=9999)?
+                ),
+                _ => return Err(Error::IncorrectFormat),
+            };
+            RestrictedExpression::from_str(&call).map_err(|_| Error::IncorrectFormat)
+        }
+        _ => Err(Error::IncorrectFormat),
+    }
+}
+
+/// The text of a policy
+fn policy_text(u: &mut Unstructured<'_>) -> Result<String> {
+    let effect = if u.arbitrary()? { "permit" } else { "forbid" };
+    let principal = scope_constraint(u)?;
+    let action = match u.choose_index(3)? {
+        0 => String::new(),
+        1 => format!(" == Action::{:?}", u.choose(ACTIONS)?),
+        _ => format!(
+            " in [{}]",
+            ACTIONS
+                .iter()
+                .take(u.int_in_range(1..=ACTIONS.len())?)
+                .map(|action| format!("Action::{action:?}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let resource = scope_constraint(u)?;
+    let mut text = format!("{effect}(principal{principal}, action{action}, resource{resource})");
+    for _ in 0..u.int_in_range(0..=2)? {
+        let keyword = if u.arbitrary()? { "when" } else { "unless" };
+        text.push_str(&format!(" {keyword} {{ {} }}", expr(u, MAX_DEPTH)?));
+    }
+    text.push(';');
+    Ok(text)
+}
+
+/// The constraint on the principal or resource in the scope of a policy
+fn scope_constraint(u: &mut Unstructured<'_>) -> Result<String> {
+    Ok(match u.choose_index(3)? {
+        0 => String::new(),
+        1 => format!(" == {}", u.arbitrary::<EntityUid>()?),
+        _ => format!(" in {}", u.arbitrary::<EntityUid>()?),
+    })
+}
+
+/// The text of an expression
+fn expr(u: &mut Unstructured<'_>, depth: usize) -> Result<String> {
+    if depth == 0 || u.ratio(1, 3)? {
+        return leaf(u);
+    }
+    let depth = depth - 1;
+    Ok(match u.choose_index(9)? {
+        0 => format!("!({})", expr(u, depth)?),
+        1 => {
+            let op = u.choose(&[
+                "==", "!=", "<", "<=", ">", ">=", "&&", "||", "+", "-", "*", "in",
+            ])?;
+            format!("({}) {op} ({})", expr(u, depth)?, expr(u, depth)?)
+        }
+        2 => format!("({}).{}", expr(u, depth)?, u.choose(ATTRIBUTES)?),
+        3 => format!("({}) has {}", expr(u, depth)?, u.choose(ATTRIBUTES)?),
+        4 => format!(
+            "if {} then {} else {}",
+            expr(u, depth)?,
+            expr(u, depth)?,
+            expr(u, depth)?
+        ),
+        5 => format!("({}) like \"{}\"", expr(u, depth)?, u.choose(PATTERNS)?),
+        6 => {
+            let mut elements = vec![];
+            for _ in 0..u.int_in_range(0..=3)? {
+                elements.push(expr(u, depth)?);
+            }
+            format!("[{}]", elements.join(", "))
+        }
+        7 => {
+            let mut fields = HashMap::new();
+            for _ in 0..u.int_in_range(0..=3)? {
+                fields.insert(*u.choose(ATTRIBUTES)?, expr(u, depth)?);
+            }
+            let mut fields: Vec<_> = fields
+                .into_iter()
+                .map(|(attr, value)| format!("{attr}: {value}"))
+                .collect();
+            fields.sort();
+            format!("{{{}}}", fields.join(", "))
+        }
+        _ => {
+            let method = u.choose(&["contains", "containsAll", "containsAny"])?;
+            format!("({}).{method}({})", expr(u, depth)?, expr(u, depth)?)
+        }
+    })
+}
+
+/// The text of an expression without subexpressions
+fn leaf(u: &mut Unstructured<'_>) -> Result<String> {
+    Ok(match u.choose_index(6)? {
+        0 => u.arbitrary::<bool>()?.to_string(),
+        // `i64::MIN` can't be written as a literal
+        1 => u.arbitrary::<i64>()?.max(-i64::MAX).to_string(),
+        2 => format!("{:?}", u.arbitrary::<String>()?),
+        3 => u.arbitrary::<EntityUid>()?.to_string(),
+        4 => format!("Action::{:?}", u.choose(ACTIONS)?),
+        _ => (*u.choose(&["principal", "action", "resource", "context"])?).to_owned(),
+    })
+}
+
+/// `proptest` strategies generating values with the generators of this
+/// module. They shrink by shrinking the data the values are generated from.
+#[cfg(feature = "proptest")]
+pub mod strategies {
+    use super::{arbitrary_entities, arbitrary_request};
+    use crate::{Entities, Request, Schema};
+    use arbitrary::{Arbitrary, Unstructured};
+    use proptest::prelude::*;
+    use std::fmt::Debug;
+
+    /// Size of the data values are generated from
+    const DATA_SIZE: usize = 1024;
+
+    /// Strategy for any `T` implementing [`Arbitrary`], e.g., `any::<Policy>()`
+    pub fn any<T: for<'a> Arbitrary<'a> + Debug>() -> impl Strategy<Value = T> {
+        proptest::collection::vec(proptest::arbitrary::any::<u8>(), 0..DATA_SIZE)
+            .prop_filter_map("invalid data", |data| {
+                T::arbitrary(&mut Unstructured::new(&data)).ok()
+            })
+    }
+
+    /// Strategy for [`Entities`] which conform to `schema`, as generated by
+    /// [`arbitrary_entities`]
+    pub fn entities_for_schema(schema: Schema) -> impl Strategy<Value = Entities> {
+        proptest::collection::vec(proptest::arbitrary::any::<u8>(), 0..DATA_SIZE)
+            .prop_filter_map("invalid data", move |data| {
+                arbitrary_entities(&schema, &mut Unstructured::new(&data)).ok()
+            })
+    }
+
+    /// Strategy for a [`Request`] which conforms to `schema`, as generated by
+    /// [`arbitrary_request`]
+    pub fn request_for_schema(schema: Schema) -> impl Strategy<Value = Request> {
+        proptest::collection::vec(proptest::arbitrary::any::<u8>(), 0..DATA_SIZE)
+            .prop_filter_map("invalid data", move |data| {
+                arbitrary_request(&schema, &mut Unstructured::new(&data)).ok()
+            })
+    }
+}
+
+// PANIC SAFETY unit tests
+#[allow(clippy::panic)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Authorizer, ValidationMode, Validator};
+
+    /// Deterministic pseudo-random data
+    fn data(seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..4096)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                u8::try_from(state >> 56).unwrap_or_default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn generates_policies_requests_and_entities() {
+        let mut generated = 0;
+        for seed in 0..100 {
+            let data = data(seed);
+            let mut u = Unstructured::new(&data);
+            let (Ok(policies), Ok(request), Ok(entities)) = (
+                PolicySet::arbitrary(&mut u),
+                Request::arbitrary(&mut u),
+                Entities::arbitrary(&mut u),
+            ) else {
+                continue;
+            };
+            generated += 1;
+            // the policies round-trip through their text
+            for policy in policies.policies() {
+                let text = policy.to_string();
+                assert!(Policy::from_str(&text).is_ok(), "{text}");
+            }
+            let _ = Authorizer::new().is_authorized(&request, &policies, &entities);
+        }
+        assert!(generated > 50, "only {generated} generated");
+    }
+
+    #[test]
+    fn generates_schema_conformant_values() {
+        let schema = Schema::from_str(
+            r#"{ "": {
+                "entityTypes": {
+                    "User": {
+                        "memberOfTypes": ["Group"],
+                        "shape": { "type": "Record", "attributes": {
+                            "age": { "type": "Long" },
+                            "manager": { "type": "Entity", "name": "User", "required": false },
+                            "tags": { "type": "Set", "element": { "type": "String" } }
+                        } }
+                    },
+                    "Group": { "memberOfTypes": ["Group"] },
+                    "Photo": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User"],
+                            "resourceTypes": ["Photo"],
+                            "context": { "type": "Record", "attributes": {
+                                "authenticated": { "type": "Boolean" }
+                            } }
+                        }
+                    }
+                }
+            } }"#,
+        )
+        .unwrap();
+        let validator = Validator::new(schema.clone());
+        let mut generated = 0;
+        for seed in 0..100 {
+            let data = data(seed);
+            let mut u = Unstructured::new(&data);
+            let (Ok(policies), Ok(request), Ok(entities)) = (
+                PolicySet::arbitrary(&mut u),
+                arbitrary_request(&schema, &mut u),
+                arbitrary_entities(&schema, &mut u),
+            ) else {
+                continue;
+            };
+            generated += 1;
+            assert!(entities.iter().all(|entity| {
+                let ty = entity.uid().type_name().to_string();
+                ty == "User" || ty == "Group" || ty == "Photo" || ty == "Action"
+            }));
+            assert_eq!(
+                request.principal().map(|uid| uid.type_name().to_string()),
+                Some("User".to_owned())
+            );
+            let _ = validator.validate(&policies, ValidationMode::default());
+        }
+        assert!(generated > 50, "only {generated} generated");
+    }
+}
@@ -0,0 +1,324 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module contains [`PolicyContainer`] and [`EntityContainer`], which
+//! hold the current policies and entities of a long-lived service and
+//! replace them atomically when they're reloaded.
+//! ```ignore
+//! let policies = PolicyContainer::new(initial, Some(schema.clone()));
+//! // on a background thread, e.g., when the file changes
+//! if let Err(e) = policies.reload_from_file("policies.cedar") {
+//!     log::warn!("keeping the current policies: {e}");
+//! }
+//! // on the request path
+//! let response = authorizer.is_authorized(&request, &policies.load(), &entities.load());
+//! ```
+//!
+//! A reload parses, and validates against the container's schema, on the
+//! calling thread without holding any lock, and only then swaps the new data
+//! in, so calls of [`PolicyContainer::load`] aren't blocked while it parses,
+//! and never see partially loaded data. If the new data doesn't parse or
+//! validate, the container keeps the current data. A snapshot returned by
+//! `load` isn't changed by later reloads, so an `is_authorized` call which is
+//! in flight finishes with the data it started with.
+
+use crate::{
+    Entities, EntitiesError, ParseErrors, PolicySet, Schema, ValidationError, ValidationMode,
+    Validator,
+};
+use itertools::Itertools;
+use miette::Diagnostic;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, PoisonError, RwLock};
+use thiserror::Error;
+
+/// Errors reloading a container. The container keeps its current data.
+#[derive(Debug, Diagnostic, Error)]
+#[non_exhaustive]
+pub enum ReloadError {
+    /// The file couldn't be read
+    #[error("failed to read the file: {0}")]
+    Io(#[from] io::Error),
+    /// The policies don't parse
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Parse(#[from] ParseErrors),
+    /// The policies don't validate against the schema
+    #[error("the policies do not validate against the schema: {}", .0.iter().join("; "))]
+    Validation(Vec<ValidationError<'static>>),
+    /// The entities don't parse, or don't conform to the schema
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Entities(#[from] EntitiesError),
+}
+
+/// The current value of a container, which is replaced as a whole
+#[derive(Debug)]
+struct Current<T>(RwLock<Arc<T>>);
+
+impl<T> Current<T> {
+    fn new(value: T) -> Self {
+        Self(RwLock::new(Arc::new(value)))
+    }
+
+    // a poisoned lock still holds a whole value, as it's only ever replaced
+    fn load(&self) -> Arc<T> {
+        Arc::clone(&self.0.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    fn store(&self, value: T) {
+        let value = Arc::new(value);
+        // the old value is dropped after the lock is released, as dropping a
+        // large policy set or entity store takes a while
+        let _old = std::mem::replace(
+            &mut *self.0.write().unwrap_or_else(PoisonError::into_inner),
+            value,
+        );
+    }
+}
+
+/// The current policies of a service, which are replaced atomically when
+/// they're reloaded
+#[derive(Debug)]
+pub struct PolicyContainer {
+    current: Current<PolicySet>,
+    /// The schema which reloaded policies must validate against, if any
+    validator: Option<Validator>,
+}
+
+impl PolicyContainer {
+    /// A container of `policies`. If there's a `schema`, reloaded policies
+    /// must validate against it in strict mode. `policies` themselves aren't
+    /// validated.
+    pub fn new(policies: PolicySet, schema: Option<Schema>) -> Self {
+        Self {
+            current: Current::new(policies),
+            validator: schema.map(Validator::new),
+        }
+    }
+
+    /// A snapshot of the current policies
+    pub fn load(&self) -> Arc<PolicySet> {
+        self.current.load()
+    }
+
+    /// Replace the policies with `policies`, after validating them
+    pub fn reload(&self, policies: PolicySet) -> Result<(), ReloadError> {
+        if let Some(validator) = &self.validator {
+            let result = validator.validate(&policies, ValidationMode::Strict);
+            if !result.validation_passed() {
+                return Err(ReloadError::Validation(
+                    result.validation_errors().cloned().collect(),
+                ));
+            }
+        }
+        self.current.store(policies);
+        Ok(())
+    }
+
+    /// Replace the policies with those parsed from `src`, in the Cedar
+    /// syntax, after validating them
+    pub fn reload_from_str(&self, src: &str) -> Result<(), ReloadError> {
+        self.reload(PolicySet::from_str(src)?)
+    }
+
+    /// Replace the policies with those parsed from the file at `path`, in the
+    /// Cedar syntax, after validating them
+    pub fn reload_from_file(&self, path: impl AsRef<Path>) -> Result<(), ReloadError> {
+        self.reload_from_str(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// The current entities of a service, which are replaced atomically when
+/// they're reloaded
+#[derive(Debug)]
+pub struct EntityContainer {
+    current: Current<Entities>,
+    /// The schema which reloaded entities must conform to, if any
+    schema: Option<Schema>,
+}
+
+impl EntityContainer {
+    /// A container of `entities`. If there's a `schema`, reloaded entities
+    /// must conform to it.
+    pub fn new(entities: Entities, schema: Option<Schema>) -> Self {
+        Self {
+            current: Current::new(entities),
+            schema,
+        }
+    }
+
+    /// A snapshot of the current entities
+    pub fn load(&self) -> Arc<Entities> {
+        self.current.load()
+    }
+
+    /// Replace the entities with `entities`, which aren't checked against
+    /// the schema
+    pub fn reload(&self, entities: Entities) {
+        self.current.store(entities);
+    }
+
+    /// Replace the entities with those parsed from `json`, in the Cedar
+    /// entity JSON format, after checking them against the schema
+    pub fn reload_from_json_str(&self, json: &str) -> Result<(), ReloadError> {
+        self.reload(Entities::from_json_str(json, self.schema.as_ref())?);
+        Ok(())
+    }
+
+    /// Replace the entities with those parsed from the file at `path`, in
+    /// the Cedar entity JSON format, after checking them against the schema
+    pub fn reload_from_file(&self, path: impl AsRef<Path>) -> Result<(), ReloadError> {
+        let file = io::BufReader::new(std::fs::File::open(path)?);
+        self.reload(Entities::from_json_file(file, self.schema.as_ref())?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Authorizer, Context, Decision, EntityUid, Request};
+    use cool_asserts::assert_matches;
+
+    fn schema() -> Schema {
+        Schema::from_json_value(serde_json::json!({ "": {
+            "entityTypes": {
+                "User": { "memberOfTypes": ["Group"] },
+                "Group": {},
+                "Photo": {}
+            },
+            "actions": {
+                "view": { "appliesTo": {
+                    "principalTypes": ["User"], "resourceTypes": ["Photo"]
+                } }
+            }
+        } }))
+        .unwrap()
+    }
+
+    fn request() -> Request {
+        let euid = |s: &str| Some(EntityUid::from_str(s).unwrap());
+        Request::new(
+            euid(r#"User::"alice""#),
+            euid(r#"Action::"view""#),
+            euid(r#"Photo::"a""#),
+            Context::empty(),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn reloads_policies() {
+        let policies = PolicyContainer::new(PolicySet::new(), Some(schema()));
+        let entities = Entities::empty();
+        let authorizer = Authorizer::new();
+        let decide = || {
+            authorizer
+                .is_authorized(&request(), &policies.load(), &entities)
+                .decision()
+        };
+        assert_eq!(decide(), Decision::Deny);
+
+        let before = policies.load();
+        policies
+            .reload_from_str(r#"permit(principal == User::"alice", action, resource);"#)
+            .unwrap();
+        assert_eq!(decide(), Decision::Allow);
+        // the snapshot taken before isn't changed
+        assert_eq!(before.policies().count(), 0);
+
+        assert_matches!(
+            policies.reload_from_str("permit(principal, action, resource"),
+            Err(ReloadError::Parse(_))
+        );
+        assert_matches!(
+            policies.reload_from_str(r#"permit(principal == Usr::"alice", action, resource);"#),
+            Err(ReloadError::Validation(errors)) => assert!(!errors.is_empty())
+        );
+        assert_matches!(
+            policies.reload_from_file("/nonexistent/policies.cedar"),
+            Err(ReloadError::Io(_))
+        );
+        // the failed reloads keep the current policies
+        assert_eq!(decide(), Decision::Allow);
+    }
+
+    #[test]
+    fn reloads_entities() {
+        let policies =
+            PolicySet::from_str(r#"permit(principal in Group::"admins", action, resource);"#)
+                .unwrap();
+        let entities = EntityContainer::new(Entities::empty(), Some(schema()));
+        let authorizer = Authorizer::new();
+        let decide = || {
+            authorizer
+                .is_authorized(&request(), &policies, &entities.load())
+                .decision()
+        };
+        assert_eq!(decide(), Decision::Deny);
+
+        entities
+            .reload_from_json_str(
+                r#"[{ "uid": { "type": "User", "id": "alice" }, "attrs": {},
+                      "parents": [{ "type": "Group", "id": "admins" }] }]"#,
+            )
+            .unwrap();
+        assert_eq!(decide(), Decision::Allow);
+
+        // `Photo` has no attributes in the schema
+        assert_matches!(
+            entities.reload_from_json_str(
+                r#"[{ "uid": { "type": "Photo", "id": "a" }, "attrs": { "size": 1 },
+                      "parents": [] }]"#,
+            ),
+            Err(ReloadError::Entities(_))
+        );
+        assert_eq!(decide(), Decision::Allow);
+    }
+
+    #[test]
+    fn concurrent_loads() {
+        let policies = Arc::new(PolicyContainer::new(PolicySet::new(), None));
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let policies = Arc::clone(&policies);
+                std::thread::spawn(move || {
+                    for _ in 0..100 {
+                        // every snapshot is one of the whole policy sets
+                        let count = policies.load().policies().count();
+                        assert!(count == 0 || count == 2, "torn policy set of {count}");
+                    }
+                })
+            })
+            .collect();
+        for _ in 0..100 {
+            policies
+                .reload_from_str(
+                    r#"permit(principal, action, resource);
+                    forbid(principal, action, resource);"#,
+                )
+                .unwrap();
+            policies.reload(PolicySet::new()).unwrap();
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,503 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module contains [`HttpEntityResolver`], a reference implementation of
+//! fetching the [`Entities`] needed for a request from a remote service over
+//! HTTP, with batching and caching.
+//!
+//! The resolver `POST`s the uids of the entities it needs to the endpoint as
+//! ```json
+//! { "uids": [ { "type": "User", "id": "alice" }, ... ] }
+//! ```
+//! and expects a response in the entities JSON format, i.e., an array of
+//! entities with their attributes and parents. Uids the service doesn't know
+//! are left out of the response. The service may also include entities which
+//! weren't requested, e.g., the ancestors of the requested entities, which
+//! saves round trips.
+//!
+//! The resolver then fetches the parents of the entities it got, and their
+//! parents, and so on, so that the [`Entities`] it returns contain all the
+//! ancestors of the requested entities. Entities, and uids the service didn't
+//! know, are kept in a least-recently-used cache, which is never invalidated
+//! automatically; see [`HttpEntityResolver::clear_cache`].
+#![allow(clippy::module_name_repetitions)]
+
+use crate::entity_source::EntityDataSource;
+use crate::{Entities, EntitiesError, Entity, EntityUid, Request, Schema};
+use lru::LruCache;
+use miette::Diagnostic;
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Default number of uids fetched in one HTTP request
+const DEFAULT_BATCH_SIZE: usize = 100;
+
+/// Default number of uids kept in the cache
+const DEFAULT_CACHE_CAPACITY: NonZeroUsize = match NonZeroUsize::new(1024) {
+    Some(capacity) => capacity,
+    None => NonZeroUsize::MIN,
+};
+
+/// Fetches entities, and their ancestors, from an HTTP endpoint, see comments
+/// in the module itself
+#[derive(Debug)]
+pub struct HttpEntityResolver {
+    endpoint: String,
+    agent: ureq::Agent,
+    headers: Vec<(String, String)>,
+    batch_size: usize,
+    schema: Option<Schema>,
+    /// `None` for uids the service didn't know
+    cache: Mutex<LruCache<EntityUid, Option<CachedEntity>>>,
+}
+
+/// An entity in the cache
+#[derive(Debug, Clone)]
+struct CachedEntity {
+    entity: Entity,
+    /// Ancestors of the entity known when it was fetched, which are fetched
+    /// next
+    ancestors: Vec<EntityUid>,
+}
+
+impl HttpEntityResolver {
+    /// Fetch entities from `endpoint`, a URL
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            agent: ureq::Agent::new(),
+            headers: Vec::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            schema: None,
+            cache: Mutex::new(LruCache::new(DEFAULT_CACHE_CAPACITY)),
+        }
+    }
+
+    /// Fetch at most `batch_size` uids in one HTTP request (by default, 100).
+    /// A `batch_size` of 0 is treated as 1.
+    #[must_use]
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Keep at most `capacity` uids in the cache (by default, 1024)
+    #[must_use]
+    pub fn with_cache_capacity(self, capacity: NonZeroUsize) -> Self {
+        Self {
+            cache: Mutex::new(LruCache::new(capacity)),
+            ..self
+        }
+    }
+
+    /// Fail HTTP requests which take longer than `timeout` (by default, there
+    /// is no timeout)
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.agent = ureq::AgentBuilder::new().timeout(timeout).build();
+        self
+    }
+
+    /// Send the header `name` with `value` in every HTTP request, e.g., for
+    /// authentication
+    #[must_use]
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Parse the entities fetched according to `schema`, and validate them
+    /// against it. The action entities of the [`Entities`] returned come from
+    /// the `schema`, instead of the endpoint.
+    #[must_use]
+    pub fn with_schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Forget all the entities fetched so far
+    pub fn clear_cache(&self) {
+        self.lock_cache().clear();
+    }
+
+    /// Fetch the principal and resource of `request`, and its action unless
+    /// there is a schema, along with all their ancestors.
+    ///
+    /// Entities referenced by the context of the request are not fetched; use
+    /// [`HttpEntityResolver::resolve`] for those.
+    pub fn resolve_request(&self, request: &Request) -> Result<Entities, HttpEntitiesError> {
+        let action = if self.schema.is_some() {
+            None
+        } else {
+            request.action()
+        };
+        self.resolve(
+            [request.principal(), action, request.resource()]
+                .into_iter()
+                .flatten()
+                .cloned(),
+        )
+    }
+
+    /// Fetch the entities with `uids`, along with all their ancestors. Uids
+    /// the service doesn't know are left out of the [`Entities`] returned.
+    pub fn resolve(
+        &self,
+        uids: impl IntoIterator<Item = EntityUid>,
+    ) -> Result<Entities, HttpEntitiesError> {
+        let mut seen = HashSet::new();
+        let mut pending: Vec<EntityUid> = uids
+            .into_iter()
+            .filter(|uid| seen.insert(uid.clone()))
+            .collect();
+        let mut entities = Vec::new();
+        // each iteration fetches the next level of the hierarchy
+        while !pending.is_empty() {
+            let level = self.get_all(&pending)?;
+            pending = level
+                .iter()
+                .flat_map(|cached| cached.ancestors.iter())
+                .filter(|uid| seen.insert((*uid).clone()))
+                .cloned()
+                .collect();
+            entities.extend(level.into_iter().map(|cached| cached.entity));
+        }
+        Ok(Entities::from_entities(entities, self.schema.as_ref())?)
+    }
+
+    /// Get the entities with `uids` from the cache, fetching those which
+    /// aren't in it
+    fn get_all(&self, uids: &[EntityUid]) -> Result<Vec<CachedEntity>, HttpEntitiesError> {
+        let mut found = Vec::new();
+        let mut missing = Vec::new();
+        {
+            let mut cache = self.lock_cache();
+            for uid in uids {
+                match cache.get(uid) {
+                    Some(Some(cached)) => found.push(cached.clone()),
+                    Some(None) => (),
+                    None => missing.push(uid.clone()),
+                }
+            }
+        }
+        for batch in missing.chunks(self.batch_size) {
+            let mut fetched: HashMap<EntityUid, CachedEntity> = self
+                .fetch(batch)?
+                .into_iter()
+                .map(|cached| (cached.entity.uid(), cached))
+                .collect();
+            let requested: Vec<_> = batch.iter().map(|uid| (uid, fetched.remove(uid))).collect();
+            let mut cache = self.lock_cache();
+            // entities which weren't requested, e.g., ancestors, go in the
+            // cache first, so they are the first to be evicted
+            for (uid, cached) in fetched {
+                cache.put(uid, Some(cached));
+            }
+            for (uid, cached) in requested {
+                if let Some(cached) = &cached {
+                    found.push(cached.clone());
+                }
+                cache.put(uid.clone(), cached);
+            }
+        }
+        Ok(found)
+    }
+
+    /// Fetch one batch of entities from the endpoint
+    fn fetch(&self, uids: &[EntityUid]) -> Result<Vec<CachedEntity>, HttpEntitiesError> {
+        let body = serde_json::json!({
+            "uids": uids
+                .iter()
+                .map(|uid| serde_json::json!({
+                    "type": uid.type_name().to_string(),
+                    "id": uid.id().as_ref(),
+                }))
+                .collect::<Vec<_>>(),
+        });
+        let mut request = self
+            .agent
+            .post(&self.endpoint)
+            .set("Content-Type", "application/json");
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+        let response = request.send_string(&body.to_string()).map_err(Box::new)?;
+        let json: serde_json::Value = serde_json::from_reader(response.into_reader())?;
+        let entities = Entities::from_json_value(json, self.schema.as_ref())?;
+        Ok(entities
+            .iter()
+            .map(|entity| CachedEntity {
+                entity: entity.clone(),
+                ancestors: entities
+                    .ancestors(&entity.uid())
+                    .into_iter()
+                    .flatten()
+                    .cloned()
+                    .collect(),
+            })
+            .collect())
+    }
+
+    fn lock_cache(&self) -> MutexGuard<'_, LruCache<EntityUid, Option<CachedEntity>>> {
+        // the cache is consistent even if another thread panicked
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl EntityDataSource for HttpEntityResolver {
+    type Error = HttpEntitiesError;
+
+    /// Get the entities with `uids` from the cache, fetching those which
+    /// aren't in it in batches
+    fn fetch_entities(&self, uids: &[EntityUid]) -> Result<Vec<Entity>, HttpEntitiesError> {
+        Ok(self
+            .get_all(uids)?
+            .into_iter()
+            .map(|cached| cached.entity)
+            .collect())
+    }
+}
+
+/// Errors fetching entities over HTTP
+#[derive(Debug, Diagnostic, Error)]
+#[non_exhaustive]
+pub enum HttpEntitiesError {
+    /// The HTTP request failed, or the response has an error status
+    #[error("failed to fetch entities: {0}")]
+    Http(#[from] Box<ureq::Error>),
+    /// The response is not JSON
+    #[error("failed to parse the response as JSON: {0}")]
+    InvalidResponse(#[from] serde_json::Error),
+    /// The response is not in the entities JSON format, or the entities are
+    /// not valid
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Entities(#[from] EntitiesError),
+}
+
+// PANIC SAFETY unit tests
+#[allow(clippy::panic)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Authorizer, Context, Decision, PolicySet};
+    use cool_asserts::assert_matches;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    /// Serve `entities` in a background thread, returning the endpoint and the
+    /// uids of every request made
+    fn serve(entities: serde_json::Value, status: u16) -> (String, Arc<Mutex<Vec<Vec<String>>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/entities", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&requests);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                let uids: Vec<String> = body["uids"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|uid| format!("{}::{}", uid["type"], uid["id"]))
+                    .collect();
+                let response: Vec<&serde_json::Value> = entities
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .filter(|entity| {
+                        let uid = &entity["uid"];
+                        uids.contains(&format!("{}::{}", uid["type"], uid["id"]))
+                    })
+                    .collect();
+                log.lock().unwrap().push(uids);
+                let response = serde_json::to_string(&response).unwrap();
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} Status\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                    response.len()
+                )
+                .unwrap();
+            }
+        });
+        (endpoint, requests)
+    }
+
+    fn entities() -> serde_json::Value {
+        serde_json::json!([
+            {
+                "uid": { "type": "User", "id": "alice" },
+                "attrs": { "level": 3 },
+                "parents": [{ "type": "Group", "id": "engineering" }]
+            },
+            {
+                "uid": { "type": "Group", "id": "engineering" },
+                "attrs": {},
+                "parents": [{ "type": "Group", "id": "employees" }]
+            },
+            {
+                "uid": { "type": "Group", "id": "employees" },
+                "attrs": {},
+                "parents": []
+            },
+            {
+                "uid": { "type": "Photo", "id": "vacation" },
+                "attrs": {},
+                "parents": [{ "type": "Album", "id": "trips" }]
+            }
+        ])
+    }
+
+    #[test]
+    fn resolves_ancestors() {
+        let (endpoint, requests) = serve(entities(), 200);
+        let resolver = HttpEntityResolver::new(endpoint);
+        let request = Request::new(
+            Some(r#"User::"alice""#.parse().unwrap()),
+            Some(r#"Action::"view""#.parse().unwrap()),
+            Some(r#"Photo::"vacation""#.parse().unwrap()),
+            Context::empty(),
+            None,
+        )
+        .unwrap();
+        let entities = resolver.resolve_request(&request).unwrap();
+        assert_eq!(entities.iter().count(), 4);
+        let alice = EntityUid::from_str(r#"User::"alice""#).unwrap();
+        let employees = EntityUid::from_str(r#"Group::"employees""#).unwrap();
+        assert!(entities
+            .ancestors(&alice)
+            .unwrap()
+            .any(|uid| uid == &employees));
+
+        let policies = PolicySet::from_str(
+            r#"permit(principal in Group::"employees", action, resource in Album::"trips");"#,
+        )
+        .unwrap();
+        let response = Authorizer::new().is_authorized(&request, &policies, &entities);
+        assert_eq!(response.decision(), Decision::Allow);
+
+        // one request for each level of the hierarchy
+        assert_eq!(requests.lock().unwrap().len(), 3);
+        assert_eq!(requests.lock().unwrap()[0].len(), 3);
+
+        // everything is cached, including the unknown action and album
+        resolver.resolve_request(&request).unwrap();
+        assert_eq!(requests.lock().unwrap().len(), 3);
+        resolver.clear_cache();
+        resolver.resolve_request(&request).unwrap();
+        assert_eq!(requests.lock().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn data_source() {
+        let (endpoint, requests) = serve(entities(), 200);
+        let resolver = HttpEntityResolver::new(endpoint);
+        let request = Request::new(
+            Some(r#"User::"alice""#.parse().unwrap()),
+            Some(r#"Action::"view""#.parse().unwrap()),
+            Some(r#"Photo::"vacation""#.parse().unwrap()),
+            Context::empty(),
+            None,
+        )
+        .unwrap();
+        let policies = PolicySet::from_str(
+            r#"permit(principal in Group::"employees", action, resource in Album::"trips");"#,
+        )
+        .unwrap();
+        let response = Authorizer::new()
+            .is_authorized_with_source(&request, &policies, &resolver, None)
+            .unwrap();
+        assert_eq!(response.decision(), Decision::Allow);
+
+        // the entity literals of the policies are fetched with the request, so
+        // only `Group::"engineering"` is left for the second level
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        assert_eq!(requests.lock().unwrap()[0].len(), 5);
+        assert_eq!(requests.lock().unwrap()[1].len(), 1);
+    }
+
+    #[test]
+    fn batches() {
+        let (endpoint, requests) = serve(entities(), 200);
+        let resolver = HttpEntityResolver::new(endpoint)
+            .with_batch_size(2)
+            .with_cache_capacity(NonZeroUsize::new(1).unwrap());
+        let entities = resolver
+            .resolve([
+                EntityUid::from_str(r#"User::"alice""#).unwrap(),
+                EntityUid::from_str(r#"Group::"employees""#).unwrap(),
+                EntityUid::from_str(r#"Photo::"vacation""#).unwrap(),
+            ])
+            .unwrap();
+        assert_eq!(entities.iter().count(), 4);
+        let requests = requests.lock().unwrap();
+        // `employees` was already fetched when it's found as an ancestor
+        assert_eq!(requests.iter().map(Vec::len).collect::<Vec<_>>(), [2, 1, 2]);
+    }
+
+    #[test]
+    fn errors() {
+        let (endpoint, _) = serve(entities(), 500);
+        let resolver = HttpEntityResolver::new(endpoint).with_timeout(Duration::from_secs(10));
+        assert_matches!(
+            resolver.resolve([EntityUid::from_str(r#"User::"alice""#).unwrap()]),
+            Err(HttpEntitiesError::Http(_))
+        );
+
+        let invalid = serde_json::json!([{
+            "uid": { "type": "User", "id": "alice" },
+            "attrs": {},
+            "parents": "Group::\"employees\""
+        }]);
+        let (endpoint, _) = serve(invalid, 200);
+        let resolver = HttpEntityResolver::new(endpoint);
+        assert_matches!(
+            resolver.resolve([EntityUid::from_str(r#"User::"alice""#).unwrap()]),
+            Err(HttpEntitiesError::Entities(_))
+        );
+
+        // unknown uids are left out
+        let (endpoint, _) = serve(entities(), 200);
+        let resolver = HttpEntityResolver::new(endpoint);
+        assert_matches!(
+            resolver.resolve([EntityUid::from_str(r#"User::"bob""#).unwrap()]),
+            Ok(entities) => assert_eq!(entities.iter().count(), 0)
+        );
+    }
+}
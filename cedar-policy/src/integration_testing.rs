@@ -352,6 +352,16 @@ pub fn perform_integration_test_from_json_custom(
         } else {
             json_request.errors.into_iter().collect()
         };
+        // the test files have no error details, so they aren't compared
+        let response = InterfaceResponse::new(
+            response.decision(),
+            response.diagnostics().reason().cloned().collect(),
+            response
+                .diagnostics()
+                .errors()
+                .map(std::string::ToString::to_string)
+                .collect(),
+        );
         let expected_response = InterfaceResponse::new(
             json_request.decision,
             json_request
@@ -0,0 +1,1031 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module has the building blocks of a language server for policies: a
+//! [`Document`] is the text of a policy file open in an editor, and gives its
+//! diagnostics, and the hover information, definitions, and completions at
+//! offsets in it.
+//! ```ignore
+//! let document = Document::new(text);
+//! for diagnostic in document.diagnostics(Some(&validator)) {
+//!     publish(diagnostic.range(), diagnostic.message());
+//! }
+//! let hover = document.hover(offset, &schema);
+//! let definition = document.definition(offset, &schema_json_text);
+//! let completions = document.completions(offset, &schema);
+//! ```
+//!
+//! Offsets and ranges are in bytes of the text; converting them to the lines
+//! and UTF-16 columns of the Language Server Protocol is up to the server.
+//! Each policy, up to the `;` which ends it, is parsed on its own, so a
+//! document with errors still has hover information in its other policies,
+//! and [`Document::edit`] only re-parses the policies an edit changes.
+//! Completions are found from the tokens before the offset, so they work in
+//! the policy being typed, which usually doesn't parse.
+
+use crate::tokens::{policy_tokens, Token, TokenKind};
+use crate::{
+    EntityUid, ParseErrors, PolicyId, PolicySet, Schema, SourceLocation, ValidationMode, Validator,
+};
+use cedar_policy_core::ast::{EntityType, Expr, Name};
+use cedar_policy_validator::typecheck::{PolicyCheck, Typechecker};
+use cedar_policy_validator::types::{AttributeType, Attributes, EntityRecordKind, Primitive, Type};
+use cedar_policy_validator::ValidatorSchema;
+use itertools::Itertools;
+use smol_str::SmolStr;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The text of a policy file open in an editor, and the policies parsed from
+/// it
+#[derive(Debug, Clone)]
+pub struct Document {
+    text: String,
+    /// The policies of the text, which cover it from start to end
+    segments: Vec<Segment>,
+    /// The policies of all the segments, with the ids `policy0`, `policy1`,
+    /// etc. of their segments
+    policies: PolicySet,
+    /// The start of the segment of each policy id
+    starts: HashMap<PolicyId, usize>,
+}
+
+/// A policy of a [`Document`], up to and including the `;` which ends it, or
+/// the text after the last `;`
+#[derive(Debug, Clone)]
+struct Segment {
+    range: Range<usize>,
+    /// The policy parsed from the segment's text alone, so its source
+    /// locations are relative to the start of the segment
+    policies: PolicySet,
+    errors: Option<ParseErrors>,
+}
+
+impl Segment {
+    fn parse(text: &str, range: Range<usize>) -> Self {
+        let (policies, errors) =
+            PolicySet::parse_with_recovery(text.get(range.clone()).unwrap_or_default());
+        Self {
+            range,
+            policies,
+            errors,
+        }
+    }
+
+    /// `range` of the segment's text as a range of the document's text
+    fn shift(&self, range: Range<usize>) -> Range<usize> {
+        range.start + self.range.start..range.end + self.range.start
+    }
+}
+
+/// The ranges of the policies of `text` from `start`, each up to and
+/// including the `;` which ends it, stopping after a policy whose end
+/// `stop` returns true for. If it doesn't stop, the text after the last `;`
+/// is the last range, if there is any.
+fn split(text: &str, start: usize, mut stop: impl FnMut(usize) -> bool) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut begin = start;
+    for token in policy_tokens(text.get(start..).unwrap_or_default()) {
+        if token.text() == ";" {
+            let end = start + token.span().end;
+            ranges.push(begin..end);
+            begin = end;
+            if stop(end) {
+                return ranges;
+            }
+        }
+    }
+    if begin < text.len() {
+        ranges.push(begin..text.len());
+    }
+    ranges
+}
+
+/// An error of [`Document::edit`]
+#[derive(Debug, Clone, PartialEq, Eq, Error, miette::Diagnostic)]
+#[error("`{}..{}` isn't a range of the document's text", .range.start, .range.end)]
+pub struct EditError {
+    range: Range<usize>,
+}
+
+/// How severe a [`Diagnostic`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    /// A parse or validation error
+    Error,
+    /// A validation warning
+    Warning,
+}
+
+/// An error or warning about a range of a [`Document`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    range: Option<Range<usize>>,
+    severity: Severity,
+    message: String,
+    code: Option<String>,
+}
+
+impl Diagnostic {
+    fn new(diagnostic: &dyn miette::Diagnostic, range: Option<Range<usize>>) -> Self {
+        Self {
+            range,
+            severity: match diagnostic.severity() {
+                Some(miette::Severity::Warning | miette::Severity::Advice) => Severity::Warning,
+                _ => Severity::Error,
+            },
+            message: diagnostic.to_string(),
+            code: diagnostic.code().map(|code| code.to_string()),
+        }
+    }
+
+    /// The range of the text it's about, if known
+    pub fn range(&self) -> Option<Range<usize>> {
+        self.range.clone()
+    }
+
+    /// Whether it's an error or a warning
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// The message
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The stable code of the error, see [`error_codes`](crate::error_codes)
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
+}
+
+/// Information about the entity or expression at an offset of a
+/// [`Document`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hover {
+    range: Range<usize>,
+    contents: String,
+}
+
+impl Hover {
+    /// The range of the entity or expression
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// The information, e.g., the type of an expression, or the attributes
+    /// of an entity type
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+}
+
+/// An attribute which may be accessed at an offset of a [`Document`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    label: String,
+    detail: String,
+}
+
+impl Completion {
+    /// The attribute
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Its type, and whether it's optional
+    pub fn detail(&self) -> &str {
+        &self.detail
+    }
+}
+
+impl Document {
+    /// Parse the policies of `text`, continuing after errors
+    pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
+        let segments = split(&text, 0, |_| false)
+            .into_iter()
+            .map(|range| Segment::parse(&text, range))
+            .collect();
+        let mut document = Self {
+            text,
+            segments,
+            policies: PolicySet::new(),
+            starts: HashMap::new(),
+        };
+        document.collect_policies();
+        document
+    }
+
+    /// Replace `range` of the text with `text`, re-parsing only the policies
+    /// the edit changes. The policies after them are kept, and just moved.
+    /// This fails if `range` isn't a range of the text, or doesn't start and
+    /// end at character boundaries, leaving the document unchanged.
+    pub fn edit(&mut self, range: Range<usize>, text: &str) -> Result<(), EditError> {
+        if self.text.get(range.clone()).is_none() {
+            return Err(EditError { range });
+        }
+        self.text.replace_range(range.clone(), text);
+        // where a position after the edit moves to
+        let moved = |pos: usize| pos - range.end + range.start + text.len();
+        // the segments the edit touches, including those it starts or ends
+        // at the boundary of, as the edit may join their tokens
+        let first = self
+            .segments
+            .iter()
+            .position(|segment| segment.range.end >= range.start)
+            .unwrap_or(self.segments.len());
+        let after = self
+            .segments
+            .iter()
+            .position(|segment| segment.range.start > range.end)
+            .unwrap_or(self.segments.len());
+        let start = self.segments.get(first).map_or_else(
+            || self.segments.last().map_or(0, |segment| segment.range.end),
+            |segment| segment.range.start,
+        );
+        // re-split from the first segment until a `;` ends a policy where
+        // one of the segments after the edit now starts, which are kept
+        let resume = self
+            .segments
+            .iter()
+            .enumerate()
+            .skip(after)
+            .map(|(i, segment)| (moved(segment.range.start), i))
+            .collect::<HashMap<_, _>>();
+        let mut kept = self.segments.len();
+        let ranges = split(&self.text, start, |end| match resume.get(&end) {
+            Some(i) => {
+                kept = *i;
+                true
+            }
+            None => false,
+        });
+        let mut segments = std::mem::take(&mut self.segments);
+        let kept = segments
+            .drain(kept..)
+            .map(|mut segment| {
+                segment.range = moved(segment.range.start)..moved(segment.range.end);
+                segment
+            })
+            .collect::<Vec<_>>();
+        segments.truncate(first);
+        segments.extend(
+            ranges
+                .into_iter()
+                .map(|range| Segment::parse(&self.text, range)),
+        );
+        segments.extend(kept);
+        self.segments = segments;
+        self.collect_policies();
+        Ok(())
+    }
+
+    /// Renumber the policies of the segments into the policy set
+    // A segment has at most one policy or template, as a policy ends with a `;`
+    // PANIC SAFETY: any string is a policy id, and the ids are unique
+    #[allow(clippy::expect_used)]
+    fn collect_policies(&mut self) {
+        self.policies = PolicySet::new();
+        self.starts.clear();
+        for (i, segment) in self.segments.iter().enumerate() {
+            let id = PolicyId::from_str(&format!("policy{i}")).expect("should be a policy id");
+            for policy in segment.policies.policies() {
+                self.policies
+                    .add(policy.new_id(id.clone()))
+                    .expect("policy ids of segments should be unique");
+            }
+            for template in segment.policies.templates() {
+                self.policies
+                    .add_template(template.new_id(id.clone()))
+                    .expect("template ids of segments should be unique");
+            }
+            self.starts.insert(id, segment.range.start);
+        }
+    }
+
+    /// The text
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The policies without parse errors. A policy's id is `policy{i}` if
+    /// it's the `i`th policy of the text, counting policies with errors,
+    /// and its source locations are relative to its start.
+    pub fn policies(&self) -> &PolicySet {
+        &self.policies
+    }
+
+    /// The parse errors, and the validation errors and warnings of the
+    /// policies without parse errors if `validator` is given
+    pub fn diagnostics(&self, validator: Option<&Validator>) -> Vec<Diagnostic> {
+        let mut diagnostics = self
+            .segments
+            .iter()
+            .flat_map(|segment| {
+                segment.errors.iter().flat_map(|errors| {
+                    errors
+                        .iter()
+                        .map(|e| Diagnostic::new(e, e.source_range().map(|r| segment.shift(r))))
+                })
+            })
+            .collect::<Vec<_>>();
+        if let Some(validator) = validator {
+            let result = validator.validate(&self.policies, ValidationMode::default());
+            let range = |loc: &SourceLocation<'_>| {
+                let start = self.starts.get(loc.policy_id())?;
+                let range = range_of_location(loc)?;
+                Some(range.start + start..range.end + start)
+            };
+            diagnostics.extend(
+                result
+                    .validation_errors()
+                    .map(|e| Diagnostic::new(e, range(e.location()))),
+            );
+            diagnostics.extend(
+                result
+                    .validation_warnings()
+                    .map(|w| Diagnostic::new(w, range(w.location()))),
+            );
+        }
+        diagnostics
+    }
+
+    /// Information about what's at `offset` from `schema`: the attributes of
+    /// an entity type, or the principals, resources, and context of an
+    /// action, for an entity literal, and otherwise the type of the innermost
+    /// expression, as inferred by the validator, if the policy validates
+    pub fn hover(&self, offset: usize, schema: &Schema) -> Option<Hover> {
+        if let Some((uid, range)) = self.entity_literal_at(offset) {
+            let contents = describe_entity(&uid, &schema.0)?;
+            return Some(Hover { range, contents });
+        }
+        self.expression_hover(offset, &schema.0)
+    }
+
+    /// The range of the declaration in `schema`, the text of a JSON schema, of
+    /// the entity type or action of the entity literal at `offset`. The range
+    /// is of the declaration's key, e.g., of `"User"` in
+    /// `"entityTypes": { "User": { ... } }`.
+    pub fn definition(&self, offset: usize, schema: &str) -> Option<Range<usize>> {
+        let (uid, _) = self.entity_literal_at(offset)?;
+        let ty = uid.type_name();
+        let namespace = ty.namespace();
+        let path = if ty.basename() == "Action" {
+            [namespace.as_str(), "actions", uid.id().as_ref()]
+        } else {
+            [namespace.as_str(), "entityTypes", ty.basename()]
+        };
+        JsonScanner::new(schema).key_range(&path)
+    }
+
+    /// The attributes in `schema` which may be accessed at `offset`, which is
+    /// after a `.`, or in the attribute after it, e.g., `principal.` or
+    /// `context.location.ci`. Attributes are completed on the variables
+    /// `principal`, `resource`, and `context`, and on their attributes, and
+    /// may be attributes of any principal, resource, or context in the
+    /// schema, as the policy being typed usually doesn't parse.
+    pub fn completions(&self, offset: usize, schema: &Schema) -> Vec<Completion> {
+        let tokens = policy_tokens(&self.text)
+            .take_while(|token| token.span().start < offset)
+            .collect::<Vec<_>>();
+        let (prefix, receiver) = match tokens.as_slice() {
+            [receiver @ .., dot] if dot.text() == "." => ("", receiver),
+            [receiver @ .., dot, attr] if dot.text() == "." && is_word(attr) => {
+                let typed = offset.saturating_sub(attr.span().start);
+                match attr.text().get(..typed) {
+                    Some(prefix) => (prefix, receiver),
+                    None => return Vec::new(),
+                }
+            }
+            _ => return Vec::new(),
+        };
+        let mut attributes = BTreeMap::new();
+        let schema = &schema.0;
+        let contexts = schema
+            .action_ids()
+            .map(|(_, action)| action.context_type())
+            .collect::<Vec<_>>();
+        for receiver in receivers(receiver, schema, &contexts) {
+            for (attr, ty) in receiver.attributes(schema) {
+                if attr.starts_with(prefix) {
+                    attributes.insert(attr.to_string(), attribute_type_name(ty));
+                }
+            }
+        }
+        attributes
+            .into_iter()
+            .map(|(label, detail)| Completion { label, detail })
+            .collect()
+    }
+
+    /// The entity literal at `offset`, and its range
+    fn entity_literal_at(&self, offset: usize) -> Option<(EntityUid, Range<usize>)> {
+        let tokens = policy_tokens(&self.text).collect::<Vec<_>>();
+        let in_literal =
+            |token: &Token<'_>| token.kind() == TokenKind::Identifier || token.text() == "::";
+        let at = tokens
+            .iter()
+            .position(|token| token.span().contains(&offset) || token.span().end == offset)?;
+        // the literal is the names and `::`s before a string
+        let end = at
+            + tokens
+                .get(at..)?
+                .iter()
+                .position(|token| !in_literal(token))?;
+        let start = tokens
+            .get(..end)?
+            .iter()
+            .rposition(|token| !in_literal(token))
+            .map_or(0, |i| i + 1);
+        if start == end || tokens.get(end)?.kind() != TokenKind::String {
+            return None;
+        }
+        let range = tokens.get(start)?.span().start..tokens.get(end)?.span().end;
+        let uid = EntityUid::from_str(self.text.get(range.clone())?).ok()?;
+        Some((uid, range))
+    }
+
+    /// The types of the innermost expression at `offset` in the request
+    /// environments its policy validates in
+    fn expression_hover(&self, offset: usize, schema: &ValidatorSchema) -> Option<Hover> {
+        let segment = self
+            .segments
+            .iter()
+            .find(|segment| segment.range.contains(&offset))?;
+        let offset = offset - segment.range.start;
+        let template = segment.policies.ast.all_templates().find(|template| {
+            template
+                .non_head_constraints()
+                .subexpressions()
+                .any(|expr| range_of(expr).is_some_and(|range| range.contains(&offset)))
+        })?;
+        let typechecker =
+            Typechecker::new(schema, cedar_policy_validator::ValidationMode::default());
+        let mut hover: Option<(Range<usize>, Vec<String>)> = None;
+        for (_, check) in typechecker.typecheck_by_request_env(template) {
+            let PolicyCheck::Success(typed) = check else {
+                continue;
+            };
+            let innermost = typed
+                .subexpressions()
+                .filter_map(|expr| Some((expr, range_of(expr)?)))
+                .filter(|(_, range)| range.contains(&offset))
+                .min_by_key(|(_, range)| range.end - range.start);
+            if let Some((expr, range)) = innermost {
+                let (_, types) = hover.get_or_insert_with(|| (range, Vec::new()));
+                if let Some(ty) = expr.data() {
+                    let name = type_name(ty);
+                    if !types.contains(&name) {
+                        types.push(name);
+                    }
+                }
+            }
+        }
+        let (range, types) = hover?;
+        if types.is_empty() {
+            None
+        } else {
+            Some(Hover {
+                range: segment.shift(range),
+                contents: types.join(" | "),
+            })
+        }
+    }
+}
+
+/// Whether `token` is a name, which may be an attribute
+fn is_word(token: &Token<'_>) -> bool {
+    matches!(
+        token.kind(),
+        TokenKind::Identifier | TokenKind::Keyword | TokenKind::Variable | TokenKind::Boolean
+    )
+}
+
+/// The range of the policy source at `loc`, if known
+fn range_of_location(loc: &SourceLocation<'_>) -> Option<Range<usize>> {
+    Some(loc.range_start()?..loc.range_end()?)
+}
+
+/// The range of the source of `expr`, if known
+fn range_of<T>(expr: &Expr<T>) -> Option<Range<usize>> {
+    expr.source_loc().map(|loc| loc.start()..loc.end())
+}
+
+/// Something attributes may be accessed on
+#[derive(Debug, Clone, Copy)]
+enum Receiver<'s> {
+    Entity(&'s Name),
+    Record(&'s Attributes),
+}
+
+impl<'s> Receiver<'s> {
+    /// The receivers of type `ty`
+    fn of(ty: &'s Type) -> Vec<Self> {
+        match ty {
+            Type::EntityOrRecord(EntityRecordKind::Entity(lub)) => {
+                lub.iter().map(Self::Entity).collect()
+            }
+            Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) => {
+                vec![Self::Record(attrs)]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn attributes(self, schema: &'s ValidatorSchema) -> Vec<(&'s SmolStr, &'s AttributeType)> {
+        match self {
+            Self::Entity(name) => schema
+                .get_entity_type(name)
+                .map(|entity_type| entity_type.attributes().collect())
+                .unwrap_or_default(),
+            Self::Record(attrs) => attrs.iter().collect(),
+        }
+    }
+}
+
+/// The receivers of an attribute accessed after `tokens`, which are a
+/// variable followed by `.`s and attributes
+fn receivers<'s>(
+    tokens: &[Token<'_>],
+    schema: &'s ValidatorSchema,
+    contexts: &'s [Type],
+) -> Vec<Receiver<'s>> {
+    let mut path = Vec::new();
+    let mut rest = tokens;
+    let root = loop {
+        match rest {
+            [before @ .., dot, attr] if dot.text() == "." && is_word(attr) => {
+                path.push(attr.text());
+                rest = before;
+            }
+            [.., root] => break root,
+            [] => return Vec::new(),
+        }
+    };
+    let entity_types = |types: Vec<&'s EntityType>| {
+        types
+            .into_iter()
+            .filter_map(|ty| match ty {
+                EntityType::Specified(name) => Some(Receiver::Entity(name)),
+                EntityType::Unspecified => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut receivers = match (root.kind(), root.text()) {
+        (TokenKind::Variable, "principal") => entity_types(
+            schema
+                .action_ids()
+                .flat_map(|(_, action)| action.applicable_principal_types())
+                .collect(),
+        ),
+        (TokenKind::Variable, "resource") => entity_types(
+            schema
+                .action_ids()
+                .flat_map(|(_, action)| action.applicable_resource_types())
+                .collect(),
+        ),
+        (TokenKind::Variable, "context") => contexts.iter().flat_map(Receiver::of).collect(),
+        _ => return Vec::new(),
+    };
+    for attr in path.into_iter().rev() {
+        receivers = receivers
+            .into_iter()
+            .flat_map(|receiver| receiver.attributes(schema))
+            .filter(|(name, _)| name.as_str() == attr)
+            .flat_map(|(_, ty)| Receiver::of(&ty.attr_type))
+            .collect();
+    }
+    receivers
+}
+
+/// The entity type of `uid`, and its attributes, or the action `uid`, and
+/// its principals, resources, and context, if the schema declares it
+fn describe_entity(uid: &EntityUid, schema: &ValidatorSchema) -> Option<String> {
+    if let Some(action) = schema.get_action_id(&uid.0) {
+        let types = |types: Vec<&EntityType>| {
+            format!(
+                "[{}]",
+                types.iter().map(ToString::to_string).sorted().join(", ")
+            )
+        };
+        return Some(format!(
+            "action {uid}\nprincipals: {}\nresources: {}\ncontext: {}",
+            types(action.applicable_principal_types().collect()),
+            types(action.applicable_resource_types().collect()),
+            type_name(&action.context_type())
+        ));
+    }
+    let entity_type = schema.get_entity_type(&uid.type_name().0)?;
+    Some(format!(
+        "entity {} {}",
+        uid.type_name(),
+        record_type_name(entity_type.attributes())
+    ))
+}
+
+/// The name of `ty` in the Cedar schema format, e.g., `Set<String>` or
+/// `{ name: String, age?: Long }`
+fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Never => "Never".to_string(),
+        Type::True
+        | Type::False
+        | Type::Primitive {
+            primitive_type: Primitive::Bool,
+        } => "Bool".to_string(),
+        Type::Primitive {
+            primitive_type: Primitive::Long,
+        } => "Long".to_string(),
+        Type::Primitive {
+            primitive_type: Primitive::String,
+        } => "String".to_string(),
+        Type::Set {
+            element_type: Some(element_type),
+        } => format!("Set<{}>", type_name(element_type)),
+        Type::Set { element_type: None } => "Set".to_string(),
+        Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) => {
+            record_type_name(attrs.iter())
+        }
+        Type::EntityOrRecord(EntityRecordKind::AnyEntity) => "entity".to_string(),
+        Type::EntityOrRecord(EntityRecordKind::Entity(lub)) => lub.iter().join(" | "),
+        Type::EntityOrRecord(EntityRecordKind::ActionEntity { name, .. })
+        | Type::ExtensionType { name } => name.to_string(),
+    }
+}
+
+fn record_type_name<'a>(attrs: impl Iterator<Item = (&'a SmolStr, &'a AttributeType)>) -> String {
+    let attrs = attrs
+        .sorted_by_key(|(name, _)| *name)
+        .map(|(name, ty)| {
+            let optional = if ty.is_required { "" } else { "?" };
+            format!("{name}{optional}: {}", type_name(&ty.attr_type))
+        })
+        .join(", ");
+    if attrs.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{ {attrs} }}")
+    }
+}
+
+fn attribute_type_name(ty: &AttributeType) -> String {
+    if ty.is_required {
+        type_name(&ty.attr_type)
+    } else {
+        format!("{} (optional)", type_name(&ty.attr_type))
+    }
+}
+
+/// Finds the ranges of keys in JSON text, which `serde_json` doesn't keep
+struct JsonScanner<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> JsonScanner<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        self.text.get(self.pos..).unwrap_or_default()
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.pos = self.text.len() - self.rest().trim_start().len();
+    }
+
+    /// Skip whitespace and `c`, if it's next
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Skip whitespace and a string, returning its range and its value
+    fn string(&mut self) -> Option<(Range<usize>, String)> {
+        self.skip_whitespace();
+        let token = policy_tokens(self.rest())
+            .next()
+            .filter(|token| token.kind() == TokenKind::String)?;
+        let range = self.pos..self.pos + token.text().len();
+        self.pos = range.end;
+        Some((range, serde_json::from_str(token.text()).ok()?))
+    }
+
+    /// Skip whitespace and a value
+    fn skip_value(&mut self) -> Option<()> {
+        if self.eat('{') {
+            if !self.eat('}') {
+                loop {
+                    self.string()?;
+                    self.eat(':').then_some(())?;
+                    self.skip_value()?;
+                    if !self.eat(',') {
+                        break;
+                    }
+                }
+                self.eat('}').then_some(())?;
+            }
+        } else if self.eat('[') {
+            if !self.eat(']') {
+                loop {
+                    self.skip_value()?;
+                    if !self.eat(',') {
+                        break;
+                    }
+                }
+                self.eat(']').then_some(())?;
+            }
+        } else if self.rest().starts_with('"') {
+            self.string()?;
+        } else {
+            let rest = self.rest();
+            let len = rest
+                .find(|c: char| matches!(c, ',' | '}' | ']') || c.is_whitespace())
+                .unwrap_or(rest.len());
+            if len == 0 {
+                return None;
+            }
+            self.pos += len;
+        }
+        Some(())
+    }
+
+    /// The range of the key at `path` of the object at the position
+    fn key_range(&mut self, path: &[&str]) -> Option<Range<usize>> {
+        let (key, path) = path.split_first()?;
+        self.eat('{').then_some(())?;
+        if self.eat('}') {
+            return None;
+        }
+        loop {
+            let (range, name) = self.string()?;
+            self.eat(':').then_some(())?;
+            if name == *key {
+                return if path.is_empty() {
+                    Some(range)
+                } else {
+                    self.key_range(path)
+                };
+            }
+            self.skip_value()?;
+            if !self.eat(',') {
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn schema_json() -> &'static str {
+        r#"{
+    "App": {
+        "entityTypes": {
+            "User": {
+                "memberOfTypes": ["Group"],
+                "shape": {
+                    "type": "Record",
+                    "attributes": {
+                        "name": { "type": "String" },
+                        "address": {
+                            "type": "Record",
+                            "attributes": {
+                                "city": { "type": "String" },
+                                "country": { "type": "String", "required": false }
+                            }
+                        }
+                    }
+                }
+            },
+            "Group": {},
+            "Photo": {
+                "shape": {
+                    "type": "Record",
+                    "attributes": { "owner": { "type": "Entity", "name": "User" } }
+                }
+            }
+        },
+        "actions": {
+            "view": {
+                "appliesTo": {
+                    "principalTypes": ["User"],
+                    "resourceTypes": ["Photo"],
+                    "context": {
+                        "type": "Record",
+                        "attributes": { "mfa": { "type": "Boolean" } }
+                    }
+                }
+            }
+        }
+    }
+}"#
+    }
+
+    fn schema() -> Schema {
+        Schema::from_str(schema_json()).unwrap()
+    }
+
+    #[test]
+    fn diagnostics() {
+        let text = r#"permit(principal, action, resource) when { principal.nmae == "a" };
+permit(principal, action resource);
+"#;
+        let document = Document::new(text);
+        let validator = Validator::new(schema());
+        let second = text.find('\n').unwrap();
+
+        let parse = document.diagnostics(None);
+        assert!(!parse.is_empty());
+        for diagnostic in &parse {
+            assert_eq!(diagnostic.severity(), Severity::Error);
+            assert!(diagnostic.range().unwrap().start > second);
+        }
+
+        let diagnostics = document.diagnostics(Some(&validator));
+        assert!(diagnostics.len() > parse.len());
+        assert!(diagnostics.iter().any(|diagnostic| {
+            diagnostic.severity() == Severity::Error
+                && diagnostic.range().map(|range| &text[range]) == Some("principal.nmae")
+        }));
+    }
+
+    #[test]
+    fn hover() {
+        let text = r#"permit(principal == App::User::"alice", action == App::Action::"view", resource)
+when { resource.owner.address.city == "Paris" && context.mfa };"#;
+        let document = Document::new(text);
+        let schema = schema();
+
+        let alice = document.hover(text.find("User").unwrap(), &schema).unwrap();
+        assert_eq!(&text[alice.range()], r#"App::User::"alice""#);
+        assert_eq!(
+            alice.contents(),
+            "entity App::User { address: { city: String, country?: String }, name: String }"
+        );
+
+        let view = document.hover(text.find("view").unwrap(), &schema).unwrap();
+        assert_eq!(
+            view.contents(),
+            "action App::Action::\"view\"\nprincipals: [App::User]\nresources: [App::Photo]\ncontext: { mfa: Bool }"
+        );
+
+        let city = document.hover(text.find("city").unwrap(), &schema).unwrap();
+        assert_eq!(&text[city.range()], "resource.owner.address.city");
+        assert_eq!(city.contents(), "String");
+        let owner = document
+            .hover(text.find("resource.").unwrap(), &schema)
+            .unwrap();
+        assert_eq!(owner.contents(), "App::Photo");
+    }
+
+    #[test]
+    fn definition() {
+        let text = r#"permit(principal in App::Group::"admins", action == App::Action::"view", resource);"#;
+        let document = Document::new(text);
+        let schema = schema_json();
+        let group = document
+            .definition(text.find("admins").unwrap(), schema)
+            .unwrap();
+        assert_eq!(&schema[group.clone()], r#""Group""#);
+        assert!(schema[group.end..].starts_with(": {}"));
+        let view = document
+            .definition(text.find("Action").unwrap(), schema)
+            .unwrap();
+        assert_eq!(&schema[view], r#""view""#);
+        assert_eq!(
+            document.definition(text.find("principal").unwrap(), schema),
+            None
+        );
+    }
+
+    #[test]
+    fn completions() {
+        let schema = schema();
+        let complete = |text: &str| {
+            Document::new(text)
+                .completions(text.len(), &schema)
+                .into_iter()
+                .map(|c| (c.label().to_string(), c.detail().to_string()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            complete("permit(principal, action, resource) when { principal."),
+            vec![
+                (
+                    "address".to_string(),
+                    "{ city: String, country?: String }".to_string()
+                ),
+                ("name".to_string(), "String".to_string()),
+            ]
+        );
+        assert_eq!(
+            complete("permit(principal, action, resource) when { resource.owner.address.c"),
+            vec![
+                ("city".to_string(), "String".to_string()),
+                ("country".to_string(), "String (optional)".to_string()),
+            ]
+        );
+        assert_eq!(
+            complete("permit(principal, action, resource) when { context."),
+            vec![("mfa".to_string(), "Bool".to_string())]
+        );
+        assert!(complete("permit(principal, action, resource) when { principal").is_empty());
+    }
+
+    #[test]
+    fn edits() {
+        let text = r#"permit(principal, action, resource) when { context.mfa };
+// a comment; with a semicolon
+forbid(principal, action, resource) when { resource.owner.name == "a;b" };
+permit(principal, action, resource);
+"#;
+        let mut document = Document::new(text);
+        let mut edit = |range: Range<usize>, new_text: &str| {
+            document.edit(range, new_text).unwrap();
+            let fresh = Document::new(document.text());
+            assert_eq!(document.policies(), fresh.policies());
+            assert_eq!(document.diagnostics(None), fresh.diagnostics(None));
+            document.clone()
+        };
+
+        // an error in the second policy, and its fix
+        let name = text.find("name").unwrap();
+        let broken = edit(name..name, "(");
+        assert_eq!(broken.policies().policies().count(), 2);
+        assert!(broken.diagnostics(None).iter().all(|diagnostic| {
+            diagnostic
+                .range()
+                .is_some_and(|range| range.start > text.find('\n').unwrap())
+        }));
+        let fixed = edit(name..name + 1, "");
+        assert_eq!(fixed.text(), text);
+        assert!(fixed.diagnostics(None).is_empty());
+
+        // joining and splitting policies
+        let semicolon = text.find("};").unwrap() + 1;
+        edit(semicolon..semicolon + 1, "");
+        edit(semicolon..semicolon, ";");
+        // an unterminated string, which takes the rest of the text
+        let quote = text.find("\"a;b\"").unwrap();
+        edit(quote..quote + 1, "");
+        edit(quote..quote, "\"");
+        // appending, and replacing everything
+        let len = edit(0..0, "").text().len();
+        let len = edit(len..len, "forbid(principal, action, resource)")
+            .text()
+            .len();
+        let all = edit(len..len, ";");
+        assert_eq!(all.policies().policies().count(), 4);
+        let len = all.text().len();
+        assert!(edit(0..len, "").policies().is_empty());
+
+        let mut document = Document::new("permit(principal, action, resource);");
+        assert_eq!(
+            document.edit(10..100, "x"),
+            Err(EditError { range: 10..100 })
+        );
+    }
+
+    #[test]
+    fn hover_after_edit() {
+        let mut document =
+            Document::new("permit(principal, action, resource) when { context.mfa };");
+        document
+            .edit(
+                0..0,
+                "forbid(principal, action, resource) when { context.mfa };\n",
+            )
+            .unwrap();
+        let text = document.text().to_string();
+        let mfa = text.rfind("mfa").unwrap();
+        let hover = document.hover(mfa, &schema()).unwrap();
+        assert_eq!(&text[hover.range()], "context.mfa");
+        assert_eq!(hover.range().start, text.rfind("context").unwrap());
+        assert_eq!(hover.contents(), "Bool");
+    }
+}
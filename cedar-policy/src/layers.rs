@@ -0,0 +1,271 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module authorizes requests against an ordered stack of policy sets,
+//! e.g., organization guardrails, then team policies, then resource policies,
+//! and reports which layer made the decision.
+//! ```ignore
+//! let layers = [
+//!     PolicyLayer::boundary("org", org_policies),
+//!     PolicyLayer::new("team", team_policies),
+//! ];
+//! let response = authorizer.is_authorized_layered(&request, &layers, &entities);
+//! println!("{:?} by {:?}", response.decision(), response.decided_by());
+//! ```
+//!
+//! The layers are authorized from the outermost, the first, to the innermost.
+//! A satisfied `forbid` in a layer denies the request, and the inner layers
+//! aren't authorized, so no inner `permit` can override it. A boundary layer
+//! denies the request if none of its `permit`s is satisfied, but its
+//! `permit`s don't allow the request by themselves: they bound what the other
+//! layers can allow. Otherwise, the request is allowed if a `permit` of a
+//! layer which isn't a boundary is satisfied, and decided by the innermost
+//! such layer.
+
+use crate::{Authorizer, Decision, Entities, PolicySet, Request, Response};
+
+/// A named policy set in a stack of layers
+#[derive(Debug, Clone)]
+pub struct PolicyLayer {
+    name: String,
+    policies: PolicySet,
+    boundary: bool,
+}
+
+impl PolicyLayer {
+    /// A layer which can allow requests, and deny them with a `forbid`
+    pub fn new(name: impl Into<String>, policies: PolicySet) -> Self {
+        Self {
+            name: name.into(),
+            policies,
+            boundary: false,
+        }
+    }
+
+    /// A layer which denies the requests which none of its `permit`s
+    /// satisfies, and doesn't allow any request by itself, so the other layers
+    /// can only allow requests which it permits
+    pub fn boundary(name: impl Into<String>, policies: PolicySet) -> Self {
+        Self {
+            boundary: true,
+            ..Self::new(name, policies)
+        }
+    }
+
+    /// The name of the layer
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The policies of the layer
+    pub fn policies(&self) -> &PolicySet {
+        &self.policies
+    }
+
+    /// Whether the layer is a boundary
+    pub fn is_boundary(&self) -> bool {
+        self.boundary
+    }
+}
+
+/// Why the layer which decided a request decided it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerDecision {
+    /// A `permit` of the layer was satisfied, and no `forbid` of any layer
+    Permitted,
+    /// A `forbid` of the layer was satisfied
+    Forbidden,
+    /// The layer is a boundary and none of its `permit`s was satisfied
+    OutsideBoundary,
+}
+
+/// Authorization response for a stack of layers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayeredResponse {
+    decision: Decision,
+    decided_by: Option<(String, LayerDecision)>,
+    responses: Vec<(String, Response)>,
+}
+
+impl LayeredResponse {
+    /// The authorization decision
+    pub fn decision(&self) -> Decision {
+        self.decision
+    }
+
+    /// The name of the layer which made the decision, and why. This is `None`
+    /// if no layer denied the request but none which isn't a boundary
+    /// permitted it, so it's denied by default.
+    pub fn decided_by(&self) -> Option<(&str, LayerDecision)> {
+        self.decided_by
+            .as_ref()
+            .map(|(name, decision)| (name.as_str(), *decision))
+    }
+
+    /// The name and response of each layer which was authorized, from the
+    /// outermost. The layers inside the one which denied the request are not
+    /// authorized.
+    pub fn responses(&self) -> impl Iterator<Item = (&str, &Response)> {
+        self.responses
+            .iter()
+            .map(|(name, response)| (name.as_str(), response))
+    }
+}
+
+impl Authorizer {
+    /// Authorize `r` against the stack of `layers`, from the outermost, with
+    /// the entities `e`. See the [module documentation](crate::layers) for
+    /// how the layers decide.
+    pub fn is_authorized_layered(
+        &self,
+        r: &Request,
+        layers: &[PolicyLayer],
+        e: &Entities,
+    ) -> LayeredResponse {
+        let mut responses = Vec::with_capacity(layers.len());
+        let mut permitted_by = None;
+        for layer in layers {
+            let response = self.is_authorized(r, &layer.policies, e);
+            // a deny with a reason is from a satisfied `forbid`
+            let denied = match response.decision() {
+                Decision::Allow => {
+                    if !layer.boundary {
+                        permitted_by = Some(layer.name.clone());
+                    }
+                    None
+                }
+                Decision::Deny if response.diagnostics().reason().next().is_some() => {
+                    Some(LayerDecision::Forbidden)
+                }
+                Decision::Deny if layer.boundary => Some(LayerDecision::OutsideBoundary),
+                Decision::Deny => None,
+            };
+            responses.push((layer.name.clone(), response));
+            if let Some(denied) = denied {
+                return LayeredResponse {
+                    decision: Decision::Deny,
+                    decided_by: Some((layer.name.clone(), denied)),
+                    responses,
+                };
+            }
+        }
+        LayeredResponse {
+            decision: if permitted_by.is_some() {
+                Decision::Allow
+            } else {
+                Decision::Deny
+            },
+            decided_by: permitted_by.map(|name| (name, LayerDecision::Permitted)),
+            responses,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Context, EntityUid};
+    use std::str::FromStr;
+
+    fn request(principal: &str, resource: &str) -> Request {
+        let euid = |s: &str| Some(EntityUid::from_str(s).unwrap());
+        Request::new(
+            euid(principal),
+            euid(r#"Action::"view""#),
+            euid(resource),
+            Context::empty(),
+            None,
+        )
+        .unwrap()
+    }
+
+    fn layers() -> Vec<PolicyLayer> {
+        let policies = |src: &str| PolicySet::from_str(src).unwrap();
+        vec![
+            PolicyLayer::new(
+                "org",
+                policies(r#"forbid(principal == User::"mallory", action, resource);"#),
+            ),
+            PolicyLayer::boundary(
+                "team",
+                policies(r#"permit(principal, action, resource in Folder::"team");"#),
+            ),
+            PolicyLayer::new(
+                "resource",
+                policies(
+                    r#"permit(principal == User::"mallory", action, resource);
+                    permit(principal == User::"alice", action, resource);"#,
+                ),
+            ),
+        ]
+    }
+
+    fn entities() -> Entities {
+        Entities::from_json_value(
+            serde_json::json!([
+                { "uid": { "type": "Photo", "id": "a" }, "attrs": {},
+                  "parents": [{ "type": "Folder", "id": "team" }] },
+                { "uid": { "type": "Photo", "id": "b" }, "attrs": {}, "parents": [] },
+            ]),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn layers_decide() {
+        let authorizer = Authorizer::new();
+        let decide = |principal: &str, resource: &str| {
+            authorizer.is_authorized_layered(&request(principal, resource), &layers(), &entities())
+        };
+
+        let response = decide(r#"User::"alice""#, r#"Photo::"a""#);
+        assert_eq!(response.decision(), Decision::Allow);
+        assert_eq!(
+            response.decided_by(),
+            Some(("resource", LayerDecision::Permitted))
+        );
+        assert_eq!(response.responses().count(), 3);
+
+        // the inner `permit` can't override the outer `forbid`
+        let response = decide(r#"User::"mallory""#, r#"Photo::"a""#);
+        assert_eq!(response.decision(), Decision::Deny);
+        assert_eq!(
+            response.decided_by(),
+            Some(("org", LayerDecision::Forbidden))
+        );
+        assert_eq!(
+            response
+                .responses()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>(),
+            ["org"]
+        );
+
+        // the photo isn't in the team's folder
+        let response = decide(r#"User::"alice""#, r#"Photo::"b""#);
+        assert_eq!(response.decision(), Decision::Deny);
+        assert_eq!(
+            response.decided_by(),
+            Some(("team", LayerDecision::OutsideBoundary))
+        );
+
+        // the boundary permits it, but no other layer does
+        let response = decide(r#"User::"bob""#, r#"Photo::"a""#);
+        assert_eq!(response.decision(), Decision::Deny);
+        assert_eq!(response.decided_by(), None);
+    }
+}
@@ -20,7 +20,11 @@
 // to add a separate test specifically for README examples by introducing a
 // private, empty, and unused function with `#[doc = include_str!("../README.md")]`.
 #![doc = include_str!("../README.md")]
-#![forbid(unsafe_code)]
+// the bindings generated by `wasm-bindgen` contain unsafe code, which is
+// allowed only in `frontend::wasm`, and memory-mapping a file is unsafe, which
+// is allowed only in `mmap_entities::MmapEntityStore::open`
+#![cfg_attr(not(any(feature = "wasm", feature = "mmap")), forbid(unsafe_code))]
+#![cfg_attr(any(feature = "wasm", feature = "mmap"), deny(unsafe_code))]
 #![warn(rust_2018_idioms, clippy::pedantic, clippy::nursery)]
 #![deny(
     missing_docs,
@@ -42,6 +46,109 @@ pub use api::*;
 /// Frontend utilities, see comments in the module itself
 pub mod frontend;
 
+/// Audit events of authorization decisions, see comments in the module itself
+pub mod audit;
+
+/// Authorization against layers of policy sets, see comments in the module itself
+pub mod layers;
+
+/// Policy sets attached to a resource hierarchy, see comments in the module itself
+pub mod scoped_store;
+
+/// Signing and verifying policy sets, see comments in the module itself
+pub mod signing;
+
+/// Atomically reloaded policies and entities, see comments in the module itself
+pub mod hot_reload;
+
+/// Durable policy stores, see comments in the module itself
+pub mod storage;
+
+/// Fingerprints of policy sets, schemas, and entities, see comments in the module itself
+pub mod fingerprint;
+
+/// Stable codes of errors, see comments in the module itself
+pub mod error_codes;
+
+mod render;
+pub use render::{render_diagnostic, PolicySource, SourceMap, SourcePosition};
+
+/// Protobuf messages, see comments in the module itself
+#[cfg(feature = "protobufs")]
+pub mod proto;
+
+/// Loading entities from columnar data, see comments in the module itself
+#[cfg(feature = "columnar")]
+pub mod columnar;
+
+/// Serde implementations for policies, see comments in the module itself
+#[cfg(feature = "serde-policies")]
+mod policy_serde;
+
+/// Compiling residual policies to SQL, see comments in the module itself
+#[cfg(feature = "sql")]
+pub mod sql;
+
+/// Importing relation tuples, see comments in the module itself
+#[cfg(feature = "relation-tuples")]
+pub mod relation_tuples;
+
+/// Importing users and groups from a directory, see comments in the module itself
+#[cfg(feature = "directory")]
+pub mod directory;
+
+/// Names of the metrics of authorization calls, see comments in the module itself
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
+/// Fetching entities over HTTP, see comments in the module itself
+#[cfg(feature = "http-entities")]
+pub mod http_entities;
+
+/// Differential testing helpers, see comments in the module itself
+#[cfg(feature = "test-util")]
+pub mod test_util;
+
+/// Generators of arbitrary values, see comments in the module itself
+#[cfg(feature = "arbitrary")]
+pub mod generators;
+
+/// Loading entities from external stores in batches, see comments in the module itself
+pub mod entity_source;
+
+/// Memory-mapped entity stores, see comments in the module itself
+#[cfg(feature = "mmap")]
+pub mod mmap_entities;
+
+/// Entity manifests of policy sets, see comments in the module itself
+#[cfg(feature = "entity-manifest")]
+pub mod entity_manifest;
+
+/// Equivalence checking of policies, see comments in the module itself
+#[cfg(feature = "analysis")]
+pub mod analysis;
+
+/// Simplification of policies, see comments in the module itself
+pub mod simplify;
+
+/// Renaming across policies and schemas, see comments in the module itself
+pub mod refactor;
+
+/// Tokens of policy and schema text for editors, see comments in the module itself
+pub mod tokens;
+
+/// The Cedar schema format, see comments in the module itself
+pub mod cedar_schema;
+
+/// Loading schemas from several files, see comments in the module itself
+pub mod schema_files;
+
+/// Validating against several versions of a schema, see comments in the module itself
+pub mod schema_versions;
+
+/// Building blocks of language servers, see comments in the module itself
+pub mod language_server;
+
 mod prop_test_policy_set;
 mod tests;
 
@@ -0,0 +1,156 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module contains the names of the metrics recorded by the
+//! [`Authorizer`](crate::Authorizer) with the `metrics` feature. They are
+//! recorded to the [`metrics`] facade, so they go to whichever recorder the
+//! application installs, e.g., a Prometheus exporter, or an exporter to
+//! OpenTelemetry.
+//!
+//! Every call to `is_authorized` or `is_authorized_partial` records:
+//! - [`DECISIONS`], a counter labeled with the `decision`: `allow`, `deny`, or
+//!   `residual` for a partial evaluation without a decision
+//! - [`DURATION`], a histogram of the time taken, in seconds
+//! - [`POLICIES_EVALUATED`], a histogram of the number of policies in the
+//!   policy set, all of which are evaluated
+//! - [`ERRORS`], a counter of the errors evaluating policies, labeled with the
+//!   `policy` id and the `kind` of error, e.g., `entity_does_not_exist`
+//!
+//! The `policy` label has one value per policy, so a policy set with many
+//! policies produces as many time series.
+
+use crate::{AuthorizationError, EvaluationErrorKind};
+use std::time::Duration;
+
+/// Counter of authorization decisions, by `decision`
+pub const DECISIONS: &str = "cedar_authorization_decisions_total";
+/// Histogram of the duration of authorization calls, in seconds
+pub const DURATION: &str = "cedar_authorization_duration_seconds";
+/// Histogram of the number of policies evaluated by authorization calls
+pub const POLICIES_EVALUATED: &str = "cedar_authorization_policies_evaluated";
+/// Counter of errors evaluating policies, by `policy` and `kind`
+pub const ERRORS: &str = "cedar_authorization_errors_total";
+
+/// Record the metrics of an authorization call
+pub(crate) fn record<'a>(
+    decision: &'static str,
+    policies_evaluated: usize,
+    duration: Duration,
+    errors: impl IntoIterator<Item = &'a AuthorizationError>,
+) {
+    ::metrics::counter!(DECISIONS, "decision" => decision).increment(1);
+    ::metrics::histogram!(DURATION).record(duration.as_secs_f64());
+    // precision is lost only beyond 2^52 policies
+    #[allow(clippy::cast_precision_loss)]
+    ::metrics::histogram!(POLICIES_EVALUATED).record(policies_evaluated as f64);
+    for error in errors {
+        match error {
+            AuthorizationError::PolicyEvaluationError { id, error } => {
+                ::metrics::counter!(
+                    ERRORS,
+                    "policy" => id.to_string(),
+                    "kind" => error_kind(error.error_kind()),
+                )
+                .increment(1);
+            }
+            AuthorizationError::UnverifiedPolicySet => {
+                ::metrics::counter!(ERRORS, "kind" => "unverified_policy_set").increment(1);
+            }
+        }
+    }
+}
+
+/// The value of the `kind` label for an error
+fn error_kind(kind: &EvaluationErrorKind) -> &'static str {
+    match kind {
+        EvaluationErrorKind::EntityDoesNotExist(..) => "entity_does_not_exist",
+        EvaluationErrorKind::EntityAttrDoesNotExist { .. } => "entity_attr_does_not_exist",
+        EvaluationErrorKind::UnspecifiedEntityAccess(..) => "unspecified_entity_access",
+        EvaluationErrorKind::RecordAttrDoesNotExist(..) => "record_attr_does_not_exist",
+        EvaluationErrorKind::FailedExtensionFunctionLookup(..) => {
+            "failed_extension_function_lookup"
+        }
+        EvaluationErrorKind::TypeError { .. } => "type_error",
+        EvaluationErrorKind::WrongNumArguments { .. } => "wrong_num_arguments",
+        EvaluationErrorKind::IntegerOverflow(..) => "integer_overflow",
+        EvaluationErrorKind::InvalidRestrictedExpression(..) => "invalid_restricted_expression",
+        EvaluationErrorKind::UnlinkedSlot(..) => "unlinked_slot",
+        EvaluationErrorKind::FailedExtensionFunctionApplication { .. } => {
+            "failed_extension_function_application"
+        }
+        EvaluationErrorKind::NonValue(..) => "non_value",
+        EvaluationErrorKind::RecursionLimit => "recursion_limit",
+    }
+}
+
+// PANIC SAFETY unit tests
+#[allow(clippy::panic)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Authorizer, Context, Entities, PolicySet, Request};
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use std::str::FromStr;
+
+    #[test]
+    fn records_metrics() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let policies = PolicySet::from_str(
+            r#"
+            permit(principal, action, resource);
+            forbid(principal, action, resource) when { principal.banned };
+            "#,
+        )
+        .unwrap();
+        let request = Request::new(
+            Some(r#"User::"alice""#.parse().unwrap()),
+            Some(r#"Action::"view""#.parse().unwrap()),
+            Some(r#"Photo::"a""#.parse().unwrap()),
+            Context::empty(),
+            None,
+        )
+        .unwrap();
+        ::metrics::with_local_recorder(&recorder, || {
+            Authorizer::new().is_authorized(&request, &policies, &Entities::empty())
+        });
+
+        let snapshot = snapshotter.snapshot().into_vec();
+        let find = |name: &str| {
+            snapshot
+                .iter()
+                .find(|(key, ..)| key.key().name() == name)
+                .map(|(key, _, _, value)| (key.key().labels().cloned().collect::<Vec<_>>(), value))
+        };
+        let (labels, value) = find(DECISIONS).unwrap();
+        assert_eq!(labels, [::metrics::Label::new("decision", "allow")]);
+        assert_eq!(value, &DebugValue::Counter(1));
+        let (labels, value) = find(ERRORS).unwrap();
+        assert_eq!(
+            labels,
+            [
+                ::metrics::Label::new("policy", "policy1"),
+                ::metrics::Label::new("kind", "entity_does_not_exist")
+            ]
+        );
+        assert_eq!(value, &DebugValue::Counter(1));
+        assert!(find(DURATION).is_some());
+        assert_eq!(
+            find(POLICIES_EVALUATED).map(|(_, value)| value),
+            Some(&DebugValue::Histogram(vec![2.0.into()]))
+        );
+    }
+}
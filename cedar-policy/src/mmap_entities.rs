@@ -0,0 +1,399 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module contains [`MmapEntityStore`], a read-only store of entities in
+//! a format which is looked up in place, so only the entities which are
+//! looked up are parsed. It implements [`EntityDataSource`], for authorizing
+//! requests with [`crate::Authorizer::is_authorized_with_source`].
+//!
+//! [`MmapEntityStore::open`] memory-maps a store in a file, so the store isn't
+//! read into memory: the operating system pages in the parts of the file which
+//! lookups touch. [`MmapEntityStore::from_bytes`] looks entities up in a store
+//! which is already in memory.
+//!
+//! Stores are written with [`write_entity_store`]. The format is:
+//! - a header of 24 bytes: the magic bytes `CEDARENT`, the format version as
+//!   a little-endian `u32`, four reserved zero bytes, and the number of
+//!   entities as a little-endian `u64`;
+//! - an index with an entry of 16 bytes for each entity, sorted by uid: the
+//!   offset of its record from the start of the file as a little-endian
+//!   `u64`, then the lengths of the uid and of the entity, as little-endian
+//!   `u32`s;
+//! - the records, each of which is the uid of an entity, as in its `Display`,
+//!   followed by the entity in the entities JSON format.
+//!
+//! Looking up an entity is a binary search of the index, comparing uids in
+//! place, followed by parsing the JSON of that one entity.
+
+use crate::entity_source::EntityDataSource;
+use crate::{Entities, EntitiesError, Entity, EntityUid, Schema};
+use cedar_policy_core::entities::{EntityJsonParser, TCComputation};
+use cedar_policy_core::extensions::Extensions;
+use memmap2::Mmap;
+use miette::Diagnostic;
+use std::cmp::Ordering;
+use std::io::Write;
+use std::path::Path;
+use thiserror::Error;
+
+/// Magic bytes at the start of every store
+const MAGIC: &[u8; 8] = b"CEDARENT";
+/// Version of the format written by [`write_entity_store`]
+const VERSION: u32 = 1;
+/// Length of the header, in bytes
+const HEADER_LEN: usize = 24;
+/// Length of an entry of the index, in bytes
+const ENTRY_LEN: usize = 16;
+
+/// Write `entities` to `writer` in the format of [`MmapEntityStore`]
+pub fn write_entity_store(
+    entities: &Entities,
+    mut writer: impl Write,
+) -> Result<(), MmapEntitiesError> {
+    let mut json = Vec::new();
+    entities.write_to_json(&mut json)?;
+    let serde_json::Value::Array(values) = serde_json::from_slice(&json)? else {
+        return Err(MmapEntitiesError::Corrupt("entities JSON is not an array"));
+    };
+    let mut records = Vec::with_capacity(values.len());
+    for value in values {
+        let uid = EntityUid::from_json(value.get("uid").cloned().unwrap_or_default())
+            .map_err(|_| MmapEntitiesError::Corrupt("entity has an invalid uid"))?;
+        records.push((uid.to_string(), serde_json::to_vec(&value)?));
+    }
+    records.sort_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&[0; 4])?;
+    writer.write_all(&(records.len() as u64).to_le_bytes())?;
+    let mut offset = (HEADER_LEN + ENTRY_LEN * records.len()) as u64;
+    for (uid, entity) in &records {
+        let uid_len = u32::try_from(uid.len()).map_err(|_| MmapEntitiesError::TooLarge)?;
+        let entity_len = u32::try_from(entity.len()).map_err(|_| MmapEntitiesError::TooLarge)?;
+        writer.write_all(&offset.to_le_bytes())?;
+        writer.write_all(&uid_len.to_le_bytes())?;
+        writer.write_all(&entity_len.to_le_bytes())?;
+        offset += u64::from(uid_len) + u64::from(entity_len);
+    }
+    for (uid, entity) in &records {
+        writer.write_all(uid.as_bytes())?;
+        writer.write_all(entity)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read-only store of entities in `bytes`, by default a memory-mapped file,
+/// see the [module documentation](self)
+#[derive(Debug)]
+pub struct MmapEntityStore<B = Mmap> {
+    bytes: B,
+    len: usize,
+    schema: Option<Schema>,
+}
+
+impl MmapEntityStore {
+    /// Memory-map the store in the file at `path`, which must have been
+    /// written by [`write_entity_store`]
+    ///
+    /// The file must not be modified or truncated while the store is open,
+    /// e.g., replace it by writing a new file and renaming it over the old
+    /// one. Lookups may read garbage from a modified file, and a process which
+    /// reads past the end of a truncated file is killed.
+    ///
+    /// Only the header is checked here; a corrupt index or record is reported
+    /// when an entity which uses it is looked up.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, MmapEntitiesError> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: the map is only read, and only through bounds-checked
+        // slices, so a file whose contents change only makes lookups fail or
+        // return other entities. Truncating the file while it's mapped is
+        // ruled out by the documentation above.
+        #[allow(unsafe_code)]
+        let map = unsafe { Mmap::map(&file)? };
+        Self::from_bytes(map)
+    }
+}
+
+impl<B: AsRef<[u8]>> MmapEntityStore<B> {
+    /// The store in `bytes`, which must have been written by
+    /// [`write_entity_store`]
+    ///
+    /// Only the header is checked here; a corrupt index or record is reported
+    /// when an entity which uses it is looked up.
+    pub fn from_bytes(bytes: B) -> Result<Self, MmapEntitiesError> {
+        let header = bytes
+            .as_ref()
+            .get(..HEADER_LEN)
+            .ok_or(MmapEntitiesError::InvalidFormat)?;
+        if header.get(..8) != Some(MAGIC.as_slice()) || read_u32(header, 8) != Some(VERSION) {
+            return Err(MmapEntitiesError::InvalidFormat);
+        }
+        let len = read_u64(header, 16)
+            .and_then(|len| usize::try_from(len).ok())
+            .ok_or(MmapEntitiesError::InvalidFormat)?;
+        let index_len = len
+            .checked_mul(ENTRY_LEN)
+            .and_then(|index_len| index_len.checked_add(HEADER_LEN))
+            .ok_or(MmapEntitiesError::InvalidFormat)?;
+        if bytes.as_ref().len() < index_len {
+            return Err(MmapEntitiesError::InvalidFormat);
+        }
+        Ok(Self {
+            bytes,
+            len,
+            schema: None,
+        })
+    }
+
+    /// Parse the entities which are looked up with `schema`, as in
+    /// [`Entities::from_json_str`]. Action entities of the schema are not
+    /// added to the store.
+    #[must_use]
+    pub fn with_schema(self, schema: Schema) -> Self {
+        Self {
+            schema: Some(schema),
+            ..self
+        }
+    }
+
+    /// Number of entities in the store
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Is the store empty?
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Look up the entity with `uid`
+    pub fn get(&self, uid: &EntityUid) -> Result<Option<Entity>, MmapEntitiesError> {
+        let key = uid.to_string();
+        let mut low = 0;
+        let mut high = self.len;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let (record_uid, entity) = self.record(mid)?;
+            match record_uid.cmp(key.as_bytes()) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return self.parse(uid, entity).map(Some),
+            }
+        }
+        Ok(None)
+    }
+
+    /// The uid and entity of the `i`th record
+    fn record(&self, i: usize) -> Result<(&[u8], &[u8]), MmapEntitiesError> {
+        let bytes = self.bytes.as_ref();
+        let entry = HEADER_LEN + ENTRY_LEN * i;
+        let corrupt = || MmapEntitiesError::Corrupt("index entry is out of bounds");
+        let offset = read_u64(bytes, entry)
+            .and_then(|offset| usize::try_from(offset).ok())
+            .ok_or_else(corrupt)?;
+        let uid_len = read_u32(bytes, entry + 8).ok_or_else(corrupt)? as usize;
+        let entity_len = read_u32(bytes, entry + 12).ok_or_else(corrupt)? as usize;
+        let uid_end = offset.checked_add(uid_len).ok_or_else(corrupt)?;
+        let entity_end = uid_end.checked_add(entity_len).ok_or_else(corrupt)?;
+        Ok((
+            bytes.get(offset..uid_end).ok_or_else(corrupt)?,
+            bytes.get(uid_end..entity_end).ok_or_else(corrupt)?,
+        ))
+    }
+
+    /// Parse the JSON of the entity with `uid`
+    fn parse(&self, uid: &EntityUid, entity: &[u8]) -> Result<Entity, MmapEntitiesError> {
+        let schema = self
+            .schema
+            .as_ref()
+            .map(|s| cedar_policy_validator::CoreSchema::new(&s.0));
+        let entity = EntityJsonParser::new(
+            schema.as_ref(),
+            Extensions::all_available(),
+            TCComputation::ComputeNow,
+        )
+        .single_from_json_slice(entity)?;
+        if entity.uid() != uid.0 {
+            return Err(MmapEntitiesError::Corrupt("record has a different uid"));
+        }
+        Ok(Entity(entity))
+    }
+}
+
+impl<B: AsRef<[u8]>> EntityDataSource for MmapEntityStore<B> {
+    type Error = MmapEntitiesError;
+
+    fn fetch_entities(&self, uids: &[EntityUid]) -> Result<Vec<Entity>, MmapEntitiesError> {
+        let mut entities = Vec::new();
+        for uid in uids {
+            entities.extend(self.get(uid)?);
+        }
+        Ok(entities)
+    }
+}
+
+/// Read a little-endian `u64` at `offset` of `bytes`
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    let bytes = bytes.get(offset..offset.checked_add(8)?)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// Read a little-endian `u32` at `offset` of `bytes`
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// Errors writing or reading a [`MmapEntityStore`]
+#[derive(Debug, Diagnostic, Error)]
+#[non_exhaustive]
+pub enum MmapEntitiesError {
+    /// Reading or writing the file failed
+    #[error("failed to access entity store: {0}")]
+    Io(#[from] std::io::Error),
+    /// The file is not an entity store, or has an unsupported version of the
+    /// format
+    #[error("not an entity store, or an unsupported version of the format")]
+    InvalidFormat,
+    /// The file is an entity store, but an index entry or a record is invalid
+    #[error("entity store is corrupt: {0}")]
+    Corrupt(&'static str),
+    /// The JSON of an entity is invalid
+    #[error("entity store is corrupt: {0}")]
+    Json(#[from] serde_json::Error),
+    /// An entity doesn't fit in the format, whose records are limited to 4 GiB
+    #[error("entity is too large for the entity store format")]
+    TooLarge,
+    /// Serializing or parsing an entity failed, e.g., because it doesn't
+    /// conform to the schema
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Entities(#[from] EntitiesError),
+}
+
+// PANIC SAFETY unit tests
+#[allow(clippy::panic)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Authorizer, Context, Decision, PolicySet, Request};
+    use cool_asserts::assert_matches;
+    use std::str::FromStr;
+
+    fn entities() -> Entities {
+        Entities::from_json_value(
+            serde_json::json!([
+                { "uid": { "type": "User", "id": "alice" }, "attrs": { "level": 3 },
+                  "parents": [{ "type": "Group", "id": "admins" }] },
+                { "uid": { "type": "Group", "id": "admins" }, "attrs": {}, "parents": [] },
+                { "uid": { "type": "Photo", "id": "vacation \"2023\"" },
+                  "attrs": { "owner": { "__entity": { "type": "User", "id": "alice" } } },
+                  "parents": [] },
+            ]),
+            None,
+        )
+        .unwrap()
+    }
+
+    fn store(entities: &Entities) -> (tempfile::TempDir, MmapEntityStore) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("entities.cedar");
+        write_entity_store(entities, std::fs::File::create(&path).unwrap()).unwrap();
+        let store = MmapEntityStore::open(&path).unwrap();
+        (dir, store)
+    }
+
+    #[test]
+    fn round_trips() {
+        let entities = entities();
+        let (_dir, store) = store(&entities);
+        assert_eq!(store.len(), 3);
+        for entity in entities.iter() {
+            assert_eq!(store.get(&entity.uid()).unwrap().as_ref(), Some(entity));
+        }
+        let bob = EntityUid::from_str(r#"User::"bob""#).unwrap();
+        assert_matches!(store.get(&bob), Ok(None));
+
+        // a store which is already in memory
+        let mut bytes = Vec::new();
+        write_entity_store(&entities, &mut bytes).unwrap();
+        let in_memory = MmapEntityStore::from_bytes(bytes.as_slice()).unwrap();
+        assert_eq!(in_memory.len(), 3);
+        for entity in entities.iter() {
+            assert_eq!(in_memory.get(&entity.uid()).unwrap().as_ref(), Some(entity));
+        }
+
+        let (_dir, empty) = store(&Entities::empty());
+        assert!(empty.is_empty());
+        assert_matches!(empty.get(&bob), Ok(None));
+    }
+
+    #[test]
+    fn authorizes() {
+        let (_dir, store) = store(&entities());
+        let request = Request::new(
+            Some(EntityUid::from_str(r#"User::"alice""#).unwrap()),
+            Some(EntityUid::from_str(r#"Action::"view""#).unwrap()),
+            Some(EntityUid::from_str(r#"Photo::"vacation \"2023\"""#).unwrap()),
+            Context::empty(),
+            None,
+        )
+        .unwrap();
+        let policies = PolicySet::from_str(
+            r#"permit(principal in Group::"admins", action, resource)
+            when { resource.owner == principal && principal.level > 2 };"#,
+        )
+        .unwrap();
+        let response = Authorizer::new()
+            .is_authorized_with_source(&request, &policies, &store, None)
+            .unwrap();
+        assert_eq!(response.decision(), Decision::Allow);
+    }
+
+    #[test]
+    fn invalid_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("entities.json");
+        std::fs::write(&path, "[]").unwrap();
+        assert_matches!(
+            MmapEntityStore::open(&path),
+            Err(MmapEntitiesError::InvalidFormat)
+        );
+
+        // a header claiming more entities than the file has
+        let mut bytes = Vec::new();
+        write_entity_store(&entities(), &mut bytes).unwrap();
+        bytes.truncate(HEADER_LEN + ENTRY_LEN);
+        std::fs::write(&path, &bytes).unwrap();
+        assert_matches!(
+            MmapEntityStore::open(&path),
+            Err(MmapEntitiesError::InvalidFormat)
+        );
+
+        // an index pointing past the end of the file
+        let mut bytes = Vec::new();
+        write_entity_store(&entities(), &mut bytes).unwrap();
+        bytes.truncate(HEADER_LEN + 3 * ENTRY_LEN);
+        std::fs::write(&path, &bytes).unwrap();
+        let store = MmapEntityStore::open(&path).unwrap();
+        assert_matches!(
+            store.get(&EntityUid::from_str(r#"User::"alice""#).unwrap()),
+            Err(MmapEntitiesError::Corrupt(_))
+        );
+    }
+}
@@ -0,0 +1,349 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module contains `Serialize` and `Deserialize` implementations for
+//! [`Policy`], [`Template`], and [`PolicySet`], based on the JSON policy format
+//! (EST). A policy serializes as `{ "id": ..., "policy": <EST> }`, and a
+//! template as `{ "id": ..., "template": <EST> }`. A policy set serializes as
+//! ```json
+//! {
+//!     "staticPolicies": { "<id>": <EST>, ... },
+//!     "templates": { "<id>": <EST>, ... },
+//!     "templateLinks": [
+//!         {
+//!             "templateId": "<id>",
+//!             "newId": "<id>",
+//!             "values": { "?principal": { "type": "User", "id": "alice" } }
+//!         },
+//!         ...
+//!     ]
+//! }
+//! ```
+//!
+//! A template-linked [`Policy`] on its own serializes with its slots filled
+//! in, so it deserializes as a static policy. In a [`PolicySet`], the link is
+//! kept.
+
+use crate::{EntityUid, Policy, PolicyId, PolicySet, SlotId, Template};
+use serde::de::Error as _;
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+/// Serialized form of a [`Policy`]
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyJson {
+    id: String,
+    policy: serde_json::Value,
+}
+
+/// Serialized form of a [`Template`]
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TemplateJson {
+    id: String,
+    template: serde_json::Value,
+}
+
+/// Serialized form of a [`PolicySet`]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+struct PolicySetJson {
+    #[serde(default)]
+    static_policies: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    templates: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    template_links: Vec<TemplateLinkJson>,
+}
+
+/// Serialized form of a template-linked policy in a [`PolicySet`]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+struct TemplateLinkJson {
+    template_id: String,
+    new_id: String,
+    /// Values of the slots, by slot, e.g., `?principal`
+    values: BTreeMap<String, serde_json::Value>,
+}
+
+impl Serialize for Policy {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PolicyJson {
+            id: self.id().to_string(),
+            policy: self.to_json().map_err(S::Error::custom)?,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Policy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = PolicyJson::deserialize(deserializer)?;
+        let id = policy_id(&json.id)?;
+        Self::from_json(Some(id), json.policy).map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for Template {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        TemplateJson {
+            id: self.id().to_string(),
+            template: self.to_json().map_err(S::Error::custom)?,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Template {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = TemplateJson::deserialize(deserializer)?;
+        let id = policy_id(&json.id)?;
+        Self::from_json(Some(id), json.template).map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for PolicySet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut json = PolicySetJson {
+            static_policies: BTreeMap::new(),
+            templates: BTreeMap::new(),
+            template_links: vec![],
+        };
+        for template in self.templates() {
+            let est = template.to_json().map_err(S::Error::custom)?;
+            json.templates.insert(template.id().to_string(), est);
+        }
+        for policy in self.policies() {
+            match (policy.template_id(), policy.template_links()) {
+                (Some(template_id), Some(values)) => json.template_links.push(TemplateLinkJson {
+                    template_id: template_id.to_string(),
+                    new_id: policy.id().to_string(),
+                    values: values
+                        .iter()
+                        .map(|(slot, uid)| (slot.to_string(), entity_uid_to_json(uid)))
+                        .collect(),
+                }),
+                _ => {
+                    let est = policy.to_json().map_err(S::Error::custom)?;
+                    json.static_policies.insert(policy.id().to_string(), est);
+                }
+            }
+        }
+        // sort for a deterministic output, like the maps above
+        json.template_links.sort_by(|a, b| a.new_id.cmp(&b.new_id));
+        json.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PolicySet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = PolicySetJson::deserialize(deserializer)?;
+        let mut set = Self::new();
+        for (id, est) in json.templates {
+            let template = Template::from_json(Some(policy_id(&id)?), est)
+                .map_err(|e| D::Error::custom(format!("template `{id}`: {e}")))?;
+            set.add_template(template).map_err(D::Error::custom)?;
+        }
+        for (id, est) in json.static_policies {
+            let policy = Policy::from_json(Some(policy_id(&id)?), est)
+                .map_err(|e| D::Error::custom(format!("policy `{id}`: {e}")))?;
+            set.add(policy).map_err(D::Error::custom)?;
+        }
+        for link in json.template_links {
+            let values = link
+                .values
+                .into_iter()
+                .map(|(slot, uid)| Ok((slot_id(&slot)?, entity_uid_from_json(uid)?)))
+                .collect::<Result<HashMap<_, _>, D::Error>>()?;
+            set.link(
+                policy_id(&link.template_id)?,
+                policy_id(&link.new_id)?,
+                values,
+            )
+            .map_err(D::Error::custom)?;
+        }
+        Ok(set)
+    }
+}
+
+fn policy_id<E: serde::de::Error>(id: &str) -> Result<PolicyId, E> {
+    PolicyId::from_str(id).map_err(|e| E::custom(format!("invalid policy id `{id}`: {e}")))
+}
+
+fn slot_id<E: serde::de::Error>(slot: &str) -> Result<SlotId, E> {
+    match slot {
+        "?principal" => Ok(SlotId::principal()),
+        "?resource" => Ok(SlotId::resource()),
+        _ => Err(E::unknown_variant(slot, &["?principal", "?resource"])),
+    }
+}
+
+fn entity_uid_to_json(uid: &EntityUid) -> serde_json::Value {
+    serde_json::json!({
+        "type": uid.type_name().to_string(),
+        "id": uid.id().as_ref(),
+    })
+}
+
+fn entity_uid_from_json<E: serde::de::Error>(json: serde_json::Value) -> Result<EntityUid, E> {
+    EntityUid::from_json(json).map_err(E::custom)
+}
+
+// PANIC SAFETY unit tests
+#[allow(clippy::panic)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    /// A config struct embedding policies, as users of this feature would have
+    #[derive(Serialize, Deserialize)]
+    struct Config {
+        policies: PolicySet,
+        fallback: Policy,
+    }
+
+    #[test]
+    fn policy_round_trip() {
+        let policy = Policy::parse(
+            Some("p".to_string()),
+            r#"@id("p") permit(principal == User::"alice", action, resource);"#,
+        )
+        .unwrap();
+        let json = serde_json::to_value(&policy).unwrap();
+        assert_eq!(json["id"], "p");
+        assert_eq!(json["policy"], policy.to_json().unwrap());
+        let converted: Policy = serde_json::from_value(json).unwrap();
+        assert_eq!(converted, policy);
+        assert_eq!(converted.annotation("id"), Some("p"));
+    }
+
+    #[test]
+    fn template_round_trip() {
+        let template = Template::parse(
+            Some("t".to_string()),
+            "permit(principal == ?principal, action, resource);",
+        )
+        .unwrap();
+        let json = serde_json::to_value(&template).unwrap();
+        assert_eq!(json["id"], "t");
+        let converted: Template = serde_json::from_value(json).unwrap();
+        assert_eq!(converted, template);
+    }
+
+    #[test]
+    fn policy_set_round_trip() {
+        let mut set = PolicySet::from_str(
+            r#"
+            permit(principal, action == Action::"view", resource);
+            forbid(principal, action, resource) when { context.risk > 3 };
+            "#,
+        )
+        .unwrap();
+        let template = Template::parse(
+            Some("t".to_string()),
+            "permit(principal == ?principal, action, resource in ?resource);",
+        )
+        .unwrap();
+        set.add_template(template).unwrap();
+        set.link(
+            PolicyId::from_str("t").unwrap(),
+            PolicyId::from_str("link").unwrap(),
+            HashMap::from([
+                (
+                    SlotId::principal(),
+                    EntityUid::from_str(r#"User::"alice""#).unwrap(),
+                ),
+                (
+                    SlotId::resource(),
+                    EntityUid::from_str(r#"Folder::"a""#).unwrap(),
+                ),
+            ]),
+        )
+        .unwrap();
+
+        let fallback = Policy::from_str("forbid(principal, action, resource);").unwrap();
+        let config = Config {
+            policies: set.clone(),
+            fallback: fallback.clone(),
+        };
+        let json = serde_json::to_value(&config).unwrap();
+        let policies = &json["policies"];
+        assert_eq!(
+            policies["staticPolicies"]
+                .as_object()
+                .map(serde_json::Map::len),
+            Some(2)
+        );
+        assert_eq!(
+            policies["templateLinks"],
+            json!([{
+                "templateId": "t",
+                "newId": "link",
+                "values": {
+                    "?principal": { "type": "User", "id": "alice" },
+                    "?resource": { "type": "Folder", "id": "a" }
+                }
+            }])
+        );
+
+        let converted: Config = serde_json::from_value(json).unwrap();
+        assert_eq!(converted.policies, set);
+        assert_eq!(converted.fallback, fallback);
+        let link = converted
+            .policies
+            .policy(&PolicyId::from_str("link").unwrap())
+            .unwrap();
+        assert_eq!(link.template_id(), Some(&PolicyId::from_str("t").unwrap()));
+    }
+
+    #[test]
+    fn deserialize_errors() {
+        let policy = json!({ "id": "p", "policy": { "effect": "permit" } });
+        assert!(serde_json::from_value::<Policy>(policy).is_err());
+
+        let set = json!({
+            "templateLinks": [
+                { "templateId": "missing", "newId": "link", "values": {} }
+            ]
+        });
+        assert!(serde_json::from_value::<PolicySet>(set).is_err());
+
+        let set = json!({
+            "templates": {
+                "t": Template::parse(None, "permit(principal == ?principal, action, resource);")
+                    .unwrap()
+                    .to_json()
+                    .unwrap()
+            },
+            "templateLinks": [{
+                "templateId": "t",
+                "newId": "link",
+                "values": { "?action": { "type": "Action", "id": "view" } }
+            }]
+        });
+        let err = serde_json::from_value::<PolicySet>(set)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("?action"), "{err}");
+    }
+}
@@ -0,0 +1,200 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Conversions between entity messages and [`Entities`], through the JSON
+//! entity format
+
+use super::{models, ProtoError};
+use crate::{Entities, Schema};
+use serde_json::{json, Map, Value};
+
+impl TryFrom<&Entities> for models::Entities {
+    type Error = ProtoError;
+
+    /// Entities are sorted by uid, so that equal entities produce equal
+    /// messages
+    fn try_from(entities: &Entities) -> Result<Self, Self::Error> {
+        let json = entities.0.to_json_value()?;
+        let mut entities = json
+            .as_array()
+            .ok_or_else(|| unsupported(&json))?
+            .iter()
+            .map(entity_from_json)
+            .collect::<Result<Vec<_>, _>>()?;
+        entities.sort_by(|a, b| {
+            let key = |e: &models::Entity| {
+                e.uid
+                    .as_ref()
+                    .map(|uid| (uid.r#type.clone(), uid.id.clone()))
+            };
+            key(a).cmp(&key(b))
+        });
+        Ok(Self { entities })
+    }
+}
+
+impl TryFrom<&models::Entities> for Entities {
+    type Error = ProtoError;
+
+    fn try_from(message: &models::Entities) -> Result<Self, Self::Error> {
+        message.to_entities(None)
+    }
+}
+
+impl models::Entities {
+    /// Construct [`Entities`] from this message. If a `schema` is given, it's
+    /// used to check the entities, and to add action entities, as with
+    /// [`Entities::from_json_value`].
+    pub fn to_entities(&self, schema: Option<&Schema>) -> Result<Entities, ProtoError> {
+        let json = self
+            .entities
+            .iter()
+            .map(|e| {
+                let uid = e
+                    .uid
+                    .as_ref()
+                    .ok_or(ProtoError::MissingField("Entity.uid"))?;
+                let attrs = e
+                    .attrs
+                    .iter()
+                    .map(|(k, v)| Ok((k.clone(), value_to_json(v)?)))
+                    .collect::<Result<Map<_, _>, ProtoError>>()?;
+                Ok(json!({
+                    "uid": uid_to_json(uid),
+                    "attrs": attrs,
+                    "parents": e.parents.iter().map(uid_to_json).collect::<Vec<_>>(),
+                }))
+            })
+            .collect::<Result<Vec<_>, ProtoError>>()?;
+        Ok(Entities::from_json_value(Value::Array(json), schema)?)
+    }
+}
+
+/// Convert an entity in the JSON entity format
+fn entity_from_json(json: &Value) -> Result<models::Entity, ProtoError> {
+    let attrs = match &json["attrs"] {
+        Value::Object(attrs) => attrs
+            .iter()
+            .map(|(k, v)| Ok((k.clone(), value_from_json(v)?)))
+            .collect::<Result<_, ProtoError>>()?,
+        Value::Null => Default::default(),
+        attrs => return Err(unsupported(attrs)),
+    };
+    let parents = match &json["parents"] {
+        Value::Array(parents) => parents
+            .iter()
+            .map(uid_from_json)
+            .collect::<Result<_, _>>()?,
+        Value::Null => vec![],
+        parents => return Err(unsupported(parents)),
+    };
+    Ok(models::Entity {
+        uid: Some(uid_from_json(&json["uid"])?),
+        attrs,
+        parents,
+    })
+}
+
+/// Convert an entity uid in the JSON entity format, with or without the
+/// `__entity` escape
+pub(super) fn uid_from_json(json: &Value) -> Result<models::EntityUid, ProtoError> {
+    let uid = json.get("__entity").unwrap_or(json);
+    match (uid["type"].as_str(), uid["id"].as_str()) {
+        (Some(ty), Some(id)) => Ok(models::EntityUid {
+            r#type: ty.to_owned(),
+            id: id.to_owned(),
+        }),
+        _ => Err(unsupported(json)),
+    }
+}
+
+fn uid_to_json(uid: &models::EntityUid) -> Value {
+    json!({ "type": uid.r#type, "id": uid.id })
+}
+
+/// Convert a value in the JSON entity format
+pub(super) fn value_from_json(json: &Value) -> Result<models::Value, ProtoError> {
+    use models::value::Value as V;
+    let value = match json {
+        Value::Bool(b) => V::Bool(*b),
+        Value::Number(n) => V::Long(n.as_i64().ok_or_else(|| unsupported(json))?),
+        Value::String(s) => V::String(s.clone()),
+        Value::Array(elements) => V::Set(models::Set {
+            elements: elements
+                .iter()
+                .map(value_from_json)
+                .collect::<Result<_, _>>()?,
+        }),
+        Value::Object(obj) if obj.contains_key("__entity") => V::Entity(uid_from_json(json)?),
+        Value::Object(obj) if obj.contains_key("__extn") => {
+            let extn = &json["__extn"];
+            let function = extn["fn"].as_str().ok_or_else(|| unsupported(json))?;
+            V::Extension(models::Extension {
+                function: function.to_owned(),
+                arg: Some(Box::new(value_from_json(&extn["arg"])?)),
+            })
+        }
+        Value::Object(attrs) => V::Record(models::Record {
+            attrs: attrs
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), value_from_json(v)?)))
+                .collect::<Result<_, ProtoError>>()?,
+        }),
+        Value::Null => return Err(unsupported(json)),
+    };
+    Ok(models::Value { value: Some(value) })
+}
+
+/// Convert a value to the JSON entity format, with explicit escapes
+pub(super) fn value_to_json(value: &models::Value) -> Result<Value, ProtoError> {
+    use models::value::Value as V;
+    Ok(
+        match value
+            .value
+            .as_ref()
+            .ok_or(ProtoError::MissingField("Value.value"))?
+        {
+            V::Bool(b) => Value::Bool(*b),
+            V::Long(l) => Value::from(*l),
+            V::String(s) => Value::String(s.clone()),
+            V::Set(set) => Value::Array(
+                set.elements
+                    .iter()
+                    .map(value_to_json)
+                    .collect::<Result<_, _>>()?,
+            ),
+            V::Record(record) => Value::Object(
+                record
+                    .attrs
+                    .iter()
+                    .map(|(k, v)| Ok((k.clone(), value_to_json(v)?)))
+                    .collect::<Result<_, ProtoError>>()?,
+            ),
+            V::Entity(uid) => json!({ "__entity": uid_to_json(uid) }),
+            V::Extension(extn) => {
+                let arg = extn
+                    .arg
+                    .as_deref()
+                    .ok_or(ProtoError::MissingField("Extension.arg"))?;
+                json!({ "__extn": { "fn": extn.function, "arg": value_to_json(arg)? } })
+            }
+        },
+    )
+}
+
+fn unsupported(json: &Value) -> ProtoError {
+    ProtoError::UnsupportedValue(json.to_string())
+}
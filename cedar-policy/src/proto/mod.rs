@@ -0,0 +1,352 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Protobuf messages for policy sets, schemas, and entities, e.g., for
+//! services distributing policies over gRPC, and their conversions to and
+//! from the Cedar types. The messages are defined in
+//! `protobuf_schema/cedar.proto`, and encoded and decoded with
+//! [`prost::Message`].
+//!
+//! Policies and templates are carried as Cedar source, and template-linked
+//! policies as the values of their slots. Entities and schemas are carried as
+//! structured messages, with the same shape as the JSON entity and schema
+//! formats.
+//!
+//! A [`Schema`](crate::Schema) doesn't keep the schema it was constructed
+//! from, so a [`Schema`] message is constructed from a schema in the JSON
+//! schema format instead, with [`Schema::from_json_value`].
+#![allow(clippy::module_name_repetitions)]
+
+mod entities;
+mod models;
+mod policies;
+mod schema;
+
+pub use models::*;
+
+use crate::{EntitiesError, ParseErrors, PolicySetError, SchemaError};
+use miette::Diagnostic;
+use thiserror::Error;
+
+/// Errors converting between protobuf messages and Cedar types
+#[derive(Debug, Diagnostic, Error)]
+#[non_exhaustive]
+pub enum ProtoError {
+    /// A message is missing a field which is required
+    #[error("missing required field `{0}`")]
+    MissingField(&'static str),
+    /// A value can't be represented in protobuf, or in Cedar
+    #[error("unsupported value: {0}")]
+    UnsupportedValue(String),
+    /// A policy, template, or name in a message failed to parse
+    #[error("failed to parse {what}")]
+    Parse {
+        /// What failed to parse, e.g., ``policy `policy0` ``
+        what: String,
+        /// Underlying parse error
+        #[source]
+        #[diagnostic_source]
+        source: ParseErrors,
+    },
+    /// Error constructing a policy set
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    PolicySet(#[from] PolicySetError),
+    /// Error constructing or serializing entities
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Entities(#[from] EntitiesError),
+    /// Error constructing a schema
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Schema(#[from] SchemaError),
+}
+
+// PANIC SAFETY unit tests
+#[allow(clippy::panic, clippy::indexing_slicing)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Entities, EntityUid, PolicyId, PolicySet, SlotId};
+    use cool_asserts::assert_matches;
+    use prost::Message;
+    use std::collections::HashMap;
+    use std::str::FromStr;
+
+    /// Encode `message` to bytes and decode it again
+    fn round_trip<M: Message + Default>(message: &M) -> M {
+        M::decode(message.encode_to_vec().as_slice()).expect("decoding should succeed")
+    }
+
+    #[test]
+    fn policy_set_round_trip() {
+        let mut set = PolicySet::from_str(
+            r#"
+            @id("view")
+            permit(principal, action == Action::"view", resource);
+            forbid(principal == User::"mallory", action, resource) when { context.risk > 3 };
+            "#,
+        )
+        .unwrap();
+        let template = crate::Template::parse(
+            Some("t".to_string()),
+            "permit(principal == ?principal, action, resource in ?resource);",
+        )
+        .unwrap();
+        set.add_template(template).unwrap();
+        set.link(
+            PolicyId::from_str("t").unwrap(),
+            PolicyId::from_str("link").unwrap(),
+            HashMap::from([
+                (
+                    SlotId::principal(),
+                    EntityUid::from_str(r#"User::"alice""#).unwrap(),
+                ),
+                (
+                    SlotId::resource(),
+                    EntityUid::from_str(r#"Folder::"a""#).unwrap(),
+                ),
+            ]),
+        )
+        .unwrap();
+
+        let message = round_trip(&models::PolicySet::from(&set));
+        assert_eq!(message.policies.len(), 2);
+        assert_eq!(message.templates.len(), 1);
+        assert_eq!(message.links.len(), 1);
+        assert_eq!(
+            message.links[0].principal,
+            Some(models::EntityUid {
+                r#type: "User".to_string(),
+                id: "alice".to_string()
+            })
+        );
+
+        let converted = PolicySet::try_from(&message).unwrap();
+        assert_eq!(converted.policies().count(), 3);
+        for p in set.policies() {
+            assert_eq!(converted.policy(p.id()), Some(p), "{}", p.id());
+        }
+        assert_eq!(
+            converted.annotation(&"policy0".parse().unwrap(), "id"),
+            Some("view")
+        );
+        assert_eq!(message, models::PolicySet::from(&converted));
+    }
+
+    #[test]
+    fn policy_set_errors() {
+        let message = models::PolicySet {
+            policies: vec![models::Policy {
+                id: "p".to_string(),
+                text: "permit(".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert_matches!(
+            PolicySet::try_from(&message),
+            Err(ProtoError::Parse { what, .. }) => assert_eq!(what, "policy `p`")
+        );
+
+        let message = models::PolicySet {
+            links: vec![models::TemplateLink {
+                template_id: "missing".to_string(),
+                policy_id: "link".to_string(),
+                principal: None,
+                resource: None,
+            }],
+            ..Default::default()
+        };
+        assert_matches!(PolicySet::try_from(&message), Err(ProtoError::PolicySet(_)));
+    }
+
+    #[test]
+    fn entities_round_trip() {
+        let json = serde_json::json!([
+            {
+                "uid": { "type": "User", "id": "alice" },
+                "attrs": {
+                    "age": 42,
+                    "name": "Alice",
+                    "admin": false,
+                    "tags": ["a", "b"],
+                    "address": { "city": "Seattle" },
+                    "manager": { "__entity": { "type": "User", "id": "bob" } },
+                    "ip": { "__extn": { "fn": "ip", "arg": "10.0.0.1" } }
+                },
+                "parents": [{ "type": "Group", "id": "admins" }]
+            },
+            { "uid": { "type": "User", "id": "bob" }, "attrs": {}, "parents": [] }
+        ]);
+        let entities = Entities::from_json_value(json, None).unwrap();
+
+        let message = round_trip(&models::Entities::try_from(&entities).unwrap());
+        assert_eq!(message.entities.len(), 2);
+        let alice = message
+            .entities
+            .iter()
+            .find(|e| e.uid.as_ref().is_some_and(|uid| uid.id == "alice"))
+            .unwrap();
+        assert_eq!(alice.attrs.len(), 7);
+        assert_matches!(
+            &alice.attrs["ip"].value,
+            Some(value::Value::Extension(models::Extension { function, .. })) => {
+                assert_eq!(function, "ip");
+            }
+        );
+
+        let converted = Entities::try_from(&message).unwrap();
+        let uid = EntityUid::from_str(r#"User::"alice""#).unwrap();
+        let admins = EntityUid::from_str(r#"Group::"admins""#).unwrap();
+        assert!(converted.is_ancestor_of(&admins, &uid));
+        for attr in alice.attrs.keys() {
+            let value = |entities: &Entities| {
+                entities
+                    .get(&uid)
+                    .and_then(|e| e.attr(attr))
+                    .and_then(Result::ok)
+            };
+            assert_eq!(value(&converted), value(&entities), "{attr}");
+        }
+    }
+
+    #[test]
+    fn entities_errors() {
+        let message = models::Entities {
+            entities: vec![models::Entity::default()],
+        };
+        assert_matches!(
+            Entities::try_from(&message),
+            Err(ProtoError::MissingField("Entity.uid"))
+        );
+    }
+
+    #[test]
+    fn schema_round_trip() {
+        let json = serde_json::json!({
+            "NS": {
+                "commonTypes": {
+                    "Address": {
+                        "type": "Record",
+                        "attributes": { "city": { "type": "String", "required": false } }
+                    }
+                },
+                "entityTypes": {
+                    "User": {
+                        "annotations": { "owner": "identity" },
+                        "memberOfTypes": ["Group"],
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "age": {
+                                    "type": "Long",
+                                    "deprecated": "use `birthday`",
+                                    "annotations": { "sensitivity": "pii" }
+                                },
+                                "address": { "type": "Address" },
+                                "tags": { "type": "Set", "element": { "type": "String" } },
+                                "ip": { "type": "Extension", "name": "ipaddr" },
+                                "manager": { "type": "Entity", "name": "User" }
+                            }
+                        }
+                    },
+                    "Group": { "deprecated": "use roles" },
+                    "Photo": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["User"],
+                            "resourceTypes": ["Photo"],
+                            "context": {
+                                "type": "Record",
+                                "attributes": { "mfa": { "type": "Boolean" } }
+                            }
+                        },
+                        "memberOf": [{ "id": "read" }],
+                        "attributes": { "level": 3 },
+                        "annotations": { "label": "View photo" }
+                    },
+                    "read": { "deprecated": "use `view`" }
+                }
+            }
+        });
+        let message = round_trip(&Schema::from_json_value(json.clone()).unwrap());
+        let user = &message.namespaces["NS"].entity_types["User"];
+        assert_eq!(user.member_of_types, vec!["Group".to_string()]);
+        let view = &message.namespaces["NS"].actions["view"];
+        assert_matches!(
+            &view.applies_to,
+            Some(AppliesTo {
+                resource_types: Some(_),
+                ..
+            })
+        );
+        assert_matches!(
+            &view.applies_to,
+            Some(AppliesTo {
+                principal_types: Some(_),
+                ..
+            })
+        );
+
+        crate::Schema::try_from(&message).unwrap();
+        crate::SchemaFragment::try_from(&message).unwrap();
+        assert_eq!(
+            message.to_json_value().unwrap(),
+            cedar_policy_validator::SchemaFragment::from_json_value(json)
+                .map(|fragment| serde_json::to_value(fragment).unwrap())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn schema_errors() {
+        let mut message = Schema::default();
+        message.namespaces.insert(
+            String::new(),
+            Namespace {
+                common_types: HashMap::from([("T".to_string(), Type::default())]),
+                ..Default::default()
+            },
+        );
+        assert_matches!(
+            crate::Schema::try_from(&message),
+            Err(ProtoError::MissingField("Type.type"))
+        );
+
+        let mut message = Schema::default();
+        message.namespaces.insert(
+            String::new(),
+            Namespace {
+                entity_types: HashMap::from([(
+                    "User".to_string(),
+                    EntityType {
+                        member_of_types: vec!["Undeclared".to_string()],
+                        shape: None,
+                        deprecated: None,
+                        annotations: HashMap::new(),
+                    },
+                )]),
+                ..Default::default()
+            },
+        );
+        assert_matches!(
+            crate::Schema::try_from(&message),
+            Err(ProtoError::Schema(_))
+        );
+    }
+}
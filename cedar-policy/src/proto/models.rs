@@ -0,0 +1,265 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Rust definitions of the messages in `protobuf_schema/cedar.proto`. These
+//! are written by hand rather than generated, so that building doesn't need
+//! `protoc`, and must be kept in sync with the `.proto` file.
+#![allow(missing_docs, clippy::derive_partial_eq_without_eq)]
+
+use std::collections::HashMap;
+
+#[derive(Clone, PartialEq, Eq, Hash, prost::Message)]
+pub struct EntityUid {
+    #[prost(string, tag = "1")]
+    pub r#type: String,
+    #[prost(string, tag = "2")]
+    pub id: String,
+}
+
+/// A policy set, with its static policies, templates, and template-linked
+/// policies
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PolicySet {
+    #[prost(message, repeated, tag = "1")]
+    pub policies: Vec<Policy>,
+    #[prost(message, repeated, tag = "2")]
+    pub templates: Vec<Policy>,
+    #[prost(message, repeated, tag = "3")]
+    pub links: Vec<TemplateLink>,
+}
+
+/// A policy or template, as Cedar source including its annotations
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Policy {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub text: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TemplateLink {
+    #[prost(string, tag = "1")]
+    pub template_id: String,
+    #[prost(string, tag = "2")]
+    pub policy_id: String,
+    #[prost(message, optional, tag = "3")]
+    pub principal: Option<EntityUid>,
+    #[prost(message, optional, tag = "4")]
+    pub resource: Option<EntityUid>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Entities {
+    #[prost(message, repeated, tag = "1")]
+    pub entities: Vec<Entity>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Entity {
+    #[prost(message, optional, tag = "1")]
+    pub uid: Option<EntityUid>,
+    #[prost(map = "string, message", tag = "2")]
+    pub attrs: HashMap<String, Value>,
+    #[prost(message, repeated, tag = "3")]
+    pub parents: Vec<EntityUid>,
+}
+
+/// An attribute value
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Value {
+    #[prost(oneof = "value::Value", tags = "1, 2, 3, 4, 5, 6, 7")]
+    pub value: Option<value::Value>,
+}
+
+pub mod value {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Value {
+        #[prost(bool, tag = "1")]
+        Bool(bool),
+        #[prost(int64, tag = "2")]
+        Long(i64),
+        #[prost(string, tag = "3")]
+        String(String),
+        #[prost(message, tag = "4")]
+        Set(super::Set),
+        #[prost(message, tag = "5")]
+        Record(super::Record),
+        #[prost(message, tag = "6")]
+        Entity(super::EntityUid),
+        #[prost(message, tag = "7")]
+        Extension(super::Extension),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Set {
+    #[prost(message, repeated, tag = "1")]
+    pub elements: Vec<Value>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Record {
+    #[prost(map = "string, message", tag = "1")]
+    pub attrs: HashMap<String, Value>,
+}
+
+/// Call of an extension function constructing a value, e.g., `ip("10.0.0.1")`
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Extension {
+    #[prost(string, tag = "1")]
+    pub function: String,
+    #[prost(message, optional, boxed, tag = "2")]
+    pub arg: Option<Box<Value>>,
+}
+
+/// A schema, following the JSON schema format
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Schema {
+    #[prost(map = "string, message", tag = "1")]
+    pub namespaces: HashMap<String, Namespace>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Namespace {
+    #[prost(map = "string, message", tag = "1")]
+    pub common_types: HashMap<String, Type>,
+    #[prost(map = "string, message", tag = "2")]
+    pub entity_types: HashMap<String, EntityType>,
+    #[prost(map = "string, message", tag = "3")]
+    pub actions: HashMap<String, ActionType>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct EntityType {
+    #[prost(string, repeated, tag = "1")]
+    pub member_of_types: Vec<String>,
+    #[prost(message, optional, tag = "2")]
+    pub shape: Option<Type>,
+    /// Present when the entity type is deprecated
+    #[prost(string, optional, tag = "3")]
+    pub deprecated: Option<String>,
+    #[prost(map = "string, string", tag = "4")]
+    pub annotations: HashMap<String, String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ActionType {
+    #[prost(message, optional, tag = "1")]
+    pub attributes: Option<ActionAttributes>,
+    #[prost(message, optional, tag = "2")]
+    pub applies_to: Option<AppliesTo>,
+    #[prost(message, optional, tag = "3")]
+    pub member_of: Option<ActionUids>,
+    #[prost(message, optional, tag = "4")]
+    pub context_defaults: Option<ActionAttributes>,
+    /// Present when the action is deprecated
+    #[prost(string, optional, tag = "5")]
+    pub deprecated: Option<String>,
+    #[prost(map = "string, string", tag = "6")]
+    pub annotations: HashMap<String, String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ActionAttributes {
+    #[prost(map = "string, message", tag = "1")]
+    pub attrs: HashMap<String, Value>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ActionUids {
+    #[prost(message, repeated, tag = "1")]
+    pub uids: Vec<ActionUid>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ActionUid {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, optional, tag = "2")]
+    pub r#type: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AppliesTo {
+    #[prost(message, optional, tag = "1")]
+    pub principal_types: Option<EntityTypeNames>,
+    #[prost(message, optional, tag = "2")]
+    pub resource_types: Option<EntityTypeNames>,
+    #[prost(message, optional, tag = "3")]
+    pub context: Option<Type>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct EntityTypeNames {
+    #[prost(string, repeated, tag = "1")]
+    pub names: Vec<String>,
+}
+
+/// A type in a schema
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Type {
+    #[prost(oneof = "r#type::Type", tags = "1, 2, 3, 4, 5, 6, 7, 8")]
+    pub r#type: Option<r#type::Type>,
+}
+
+pub mod r#type {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Type {
+        #[prost(message, tag = "1")]
+        String(super::Empty),
+        #[prost(message, tag = "2")]
+        Long(super::Empty),
+        #[prost(message, tag = "3")]
+        Boolean(super::Empty),
+        #[prost(message, tag = "4")]
+        Set(Box<super::Type>),
+        #[prost(message, tag = "5")]
+        Record(super::RecordType),
+        #[prost(string, tag = "6")]
+        Entity(String),
+        #[prost(string, tag = "7")]
+        Extension(String),
+        /// Name of a common type
+        #[prost(string, tag = "8")]
+        Common(String),
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct Empty {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RecordType {
+    #[prost(map = "string, message", tag = "1")]
+    pub attributes: HashMap<String, Attribute>,
+    #[prost(bool, tag = "2")]
+    pub additional_attributes: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Attribute {
+    #[prost(message, optional, tag = "1")]
+    pub r#type: Option<Type>,
+    /// Defaults to `true`
+    #[prost(bool, optional, tag = "2")]
+    pub required: Option<bool>,
+    /// Present when the attribute is deprecated
+    #[prost(string, optional, tag = "3")]
+    pub deprecated: Option<String>,
+    #[prost(map = "string, string", tag = "4")]
+    pub annotations: HashMap<String, String>,
+}
@@ -0,0 +1,128 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Conversions between policy set messages and [`PolicySet`]
+
+use super::{models, ProtoError};
+use crate::{EntityId, EntityTypeName, EntityUid, Policy, PolicyId, PolicySet, SlotId, Template};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+impl From<&EntityUid> for models::EntityUid {
+    fn from(uid: &EntityUid) -> Self {
+        Self {
+            r#type: uid.type_name().to_string(),
+            id: uid.id().as_ref().to_owned(),
+        }
+    }
+}
+
+impl TryFrom<&models::EntityUid> for EntityUid {
+    type Error = ProtoError;
+
+    fn try_from(uid: &models::EntityUid) -> Result<Self, Self::Error> {
+        let ty = EntityTypeName::from_str(&uid.r#type).map_err(|source| ProtoError::Parse {
+            what: format!("entity type `{}`", uid.r#type),
+            source,
+        })?;
+        Ok(Self::from_type_name_and_id(ty, EntityId::from_raw(&uid.id)))
+    }
+}
+
+impl From<&PolicySet> for models::PolicySet {
+    /// Policies, templates, and links are sorted by id, so that equal policy
+    /// sets produce equal messages
+    fn from(set: &PolicySet) -> Self {
+        let mut policies = vec![];
+        let mut links = vec![];
+        for p in set.policies() {
+            match (p.template_id(), p.template_links()) {
+                (Some(template_id), Some(mut values)) => links.push(models::TemplateLink {
+                    template_id: template_id.to_string(),
+                    policy_id: p.id().to_string(),
+                    principal: values.remove(&SlotId::principal()).as_ref().map(Into::into),
+                    resource: values.remove(&SlotId::resource()).as_ref().map(Into::into),
+                }),
+                _ => policies.push(models::Policy {
+                    id: p.id().to_string(),
+                    text: p.to_string(),
+                }),
+            }
+        }
+        let mut templates: Vec<_> = set
+            .templates()
+            .map(|t| models::Policy {
+                id: t.id().to_string(),
+                text: t.to_string(),
+            })
+            .collect();
+        policies.sort_by(|a, b| a.id.cmp(&b.id));
+        templates.sort_by(|a, b| a.id.cmp(&b.id));
+        links.sort_by(|a, b| a.policy_id.cmp(&b.policy_id));
+        Self {
+            policies,
+            templates,
+            links,
+        }
+    }
+}
+
+impl TryFrom<&models::PolicySet> for PolicySet {
+    type Error = ProtoError;
+
+    fn try_from(message: &models::PolicySet) -> Result<Self, Self::Error> {
+        let mut set = Self::new();
+        for t in &message.templates {
+            let template = Template::parse(Some(t.id.clone()), &t.text).map_err(|source| {
+                ProtoError::Parse {
+                    what: format!("template `{}`", t.id),
+                    source,
+                }
+            })?;
+            set.add_template(template)?;
+        }
+        for p in &message.policies {
+            let policy =
+                Policy::parse(Some(p.id.clone()), &p.text).map_err(|source| ProtoError::Parse {
+                    what: format!("policy `{}`", p.id),
+                    source,
+                })?;
+            set.add(policy)?;
+        }
+        for link in &message.links {
+            let mut values = HashMap::new();
+            if let Some(principal) = &link.principal {
+                values.insert(SlotId::principal(), principal.try_into()?);
+            }
+            if let Some(resource) = &link.resource {
+                values.insert(SlotId::resource(), resource.try_into()?);
+            }
+            set.link(
+                policy_id(&link.template_id)?,
+                policy_id(&link.policy_id)?,
+                values,
+            )?;
+        }
+        Ok(set)
+    }
+}
+
+fn policy_id(id: &str) -> Result<PolicyId, ProtoError> {
+    PolicyId::from_str(id).map_err(|source| ProtoError::Parse {
+        what: format!("policy id `{id}`"),
+        source,
+    })
+}
@@ -0,0 +1,332 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Conversions between schema messages and the JSON schema format, and from
+//! schema messages to [`Schema`] and [`SchemaFragment`]
+
+use super::entities::{value_from_json, value_to_json};
+use super::{models, ProtoError};
+use crate::{Schema, SchemaError, SchemaFragment};
+use cedar_policy_core::entities::CedarValueJson;
+use cedar_policy_validator::{
+    ActionEntityUID, ActionType, ApplySpec, AttributesOrContext, EntityType, NamespaceDefinition,
+    SchemaType, SchemaTypeVariant, TypeOfAttribute,
+};
+use models::r#type::Type as T;
+use smol_str::SmolStr;
+use std::collections::{BTreeMap, HashMap};
+
+impl models::Schema {
+    /// Construct a message from a schema in the JSON schema format
+    pub fn from_json_value(json: serde_json::Value) -> Result<Self, ProtoError> {
+        let fragment = cedar_policy_validator::SchemaFragment::from_json_value(json)
+            .map_err(SchemaError::from)?;
+        Ok(Self {
+            namespaces: fragment
+                .0
+                .iter()
+                .map(|(name, ns)| Ok((name.to_string(), namespace_from(ns)?)))
+                .collect::<Result<_, ProtoError>>()?,
+        })
+    }
+
+    /// This schema in the JSON schema format
+    pub fn to_json_value(&self) -> Result<serde_json::Value, ProtoError> {
+        let fragment = cedar_policy_validator::SchemaFragment(
+            self.namespaces
+                .iter()
+                .map(|(name, ns)| Ok((SmolStr::from(name.as_str()), namespace_to(ns)?)))
+                .collect::<Result<_, ProtoError>>()?,
+        );
+        Ok(serde_json::to_value(fragment).map_err(SchemaError::from)?)
+    }
+}
+
+impl TryFrom<&models::Schema> for Schema {
+    type Error = ProtoError;
+
+    fn try_from(message: &models::Schema) -> Result<Self, Self::Error> {
+        Ok(Self::from_json_value(message.to_json_value()?)?)
+    }
+}
+
+impl TryFrom<&models::Schema> for SchemaFragment {
+    type Error = ProtoError;
+
+    fn try_from(message: &models::Schema) -> Result<Self, Self::Error> {
+        Ok(Self::from_json_value(message.to_json_value()?)?)
+    }
+}
+
+fn namespace_from(ns: &NamespaceDefinition) -> Result<models::Namespace, ProtoError> {
+    Ok(models::Namespace {
+        common_types: ns
+            .common_types
+            .iter()
+            .map(|(name, ty)| (name.to_string(), type_from(ty)))
+            .collect(),
+        entity_types: ns
+            .entity_types
+            .iter()
+            .map(|(name, ty)| {
+                let ty = models::EntityType {
+                    member_of_types: ty.member_of_types.iter().map(ToString::to_string).collect(),
+                    shape: Some(type_from(&ty.shape.0)),
+                    deprecated: ty.deprecated.as_ref().map(ToString::to_string),
+                    annotations: annotations_from(&ty.annotations),
+                };
+                (name.to_string(), ty)
+            })
+            .collect(),
+        actions: ns
+            .actions
+            .iter()
+            .map(|(name, action)| Ok((name.to_string(), action_from(action)?)))
+            .collect::<Result<_, ProtoError>>()?,
+    })
+}
+
+fn namespace_to(ns: &models::Namespace) -> Result<NamespaceDefinition, ProtoError> {
+    Ok(NamespaceDefinition {
+        common_types: ns
+            .common_types
+            .iter()
+            .map(|(name, ty)| Ok((SmolStr::from(name.as_str()), type_to(ty)?)))
+            .collect::<Result<_, ProtoError>>()?,
+        entity_types: ns
+            .entity_types
+            .iter()
+            .map(|(name, ty)| {
+                let ty = EntityType {
+                    member_of_types: ty
+                        .member_of_types
+                        .iter()
+                        .map(|t| t.as_str().into())
+                        .collect(),
+                    shape: shape_to(ty.shape.as_ref())?,
+                    deprecated: ty.deprecated.as_deref().map(SmolStr::from),
+                    annotations: annotations_to(&ty.annotations),
+                };
+                Ok((SmolStr::from(name.as_str()), ty))
+            })
+            .collect::<Result<_, ProtoError>>()?,
+        actions: ns
+            .actions
+            .iter()
+            .map(|(name, action)| Ok((SmolStr::from(name.as_str()), action_to(action)?)))
+            .collect::<Result<_, ProtoError>>()?,
+    })
+}
+
+fn action_from(action: &ActionType) -> Result<models::ActionType, ProtoError> {
+    let attributes = action.attributes.as_ref().map(attrs_from).transpose()?;
+    let context_defaults = action
+        .context_defaults
+        .as_ref()
+        .map(attrs_from)
+        .transpose()?;
+    let names = |names: &Vec<SmolStr>| models::EntityTypeNames {
+        names: names.iter().map(ToString::to_string).collect(),
+    };
+    Ok(models::ActionType {
+        attributes,
+        context_defaults,
+        applies_to: action.applies_to.as_ref().map(|spec| models::AppliesTo {
+            principal_types: spec.principal_types.as_ref().map(names),
+            resource_types: spec.resource_types.as_ref().map(names),
+            context: Some(type_from(&spec.context.0)),
+        }),
+        member_of: action.member_of.as_ref().map(|uids| models::ActionUids {
+            uids: uids
+                .iter()
+                .map(|uid| models::ActionUid {
+                    id: uid.id.to_string(),
+                    r#type: uid.ty.as_ref().map(ToString::to_string),
+                })
+                .collect(),
+        }),
+        deprecated: action.deprecated.as_ref().map(ToString::to_string),
+        annotations: annotations_from(&action.annotations),
+    })
+}
+
+fn action_to(action: &models::ActionType) -> Result<ActionType, ProtoError> {
+    let attributes = action.attributes.as_ref().map(attrs_to).transpose()?;
+    let context_defaults = action.context_defaults.as_ref().map(attrs_to).transpose()?;
+    let names = |names: &models::EntityTypeNames| {
+        names
+            .names
+            .iter()
+            .map(|name| name.as_str().into())
+            .collect()
+    };
+    let applies_to = match &action.applies_to {
+        Some(spec) => Some(ApplySpec {
+            principal_types: spec.principal_types.as_ref().map(names),
+            resource_types: spec.resource_types.as_ref().map(names),
+            context: shape_to(spec.context.as_ref())?,
+        }),
+        None => None,
+    };
+    Ok(ActionType {
+        attributes,
+        context_defaults,
+        applies_to,
+        member_of: action.member_of.as_ref().map(|uids| {
+            uids.uids
+                .iter()
+                .map(|uid| ActionEntityUID {
+                    id: uid.id.as_str().into(),
+                    ty: uid.r#type.as_deref().map(SmolStr::from),
+                })
+                .collect()
+        }),
+        deprecated: action.deprecated.as_deref().map(SmolStr::from),
+        annotations: annotations_to(&action.annotations),
+    })
+}
+
+/// The values of an action's attributes, or of its context defaults
+fn attrs_from(
+    attrs: &HashMap<SmolStr, CedarValueJson>,
+) -> Result<models::ActionAttributes, ProtoError> {
+    Ok(models::ActionAttributes {
+        attrs: attrs
+            .iter()
+            .map(|(name, value)| {
+                let value = serde_json::to_value(value).map_err(SchemaError::from)?;
+                Ok((name.to_string(), value_from_json(&value)?))
+            })
+            .collect::<Result<_, ProtoError>>()?,
+    })
+}
+
+fn attrs_to(
+    attrs: &models::ActionAttributes,
+) -> Result<HashMap<SmolStr, CedarValueJson>, ProtoError> {
+    attrs
+        .attrs
+        .iter()
+        .map(|(name, value)| {
+            let value = serde_json::from_value(value_to_json(value)?).map_err(SchemaError::from)?;
+            Ok((SmolStr::from(name.as_str()), value))
+        })
+        .collect()
+}
+
+/// The annotations on an entity type, attribute, or action
+fn annotations_from(annotations: &BTreeMap<SmolStr, SmolStr>) -> HashMap<String, String> {
+    annotations
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+fn annotations_to(annotations: &HashMap<String, String>) -> BTreeMap<SmolStr, SmolStr> {
+    annotations
+        .iter()
+        .map(|(key, value)| (SmolStr::from(key.as_str()), SmolStr::from(value.as_str())))
+        .collect()
+}
+
+/// The shape of an entity type or the context of an action, which is an
+/// empty record if absent
+fn shape_to(ty: Option<&models::Type>) -> Result<AttributesOrContext, ProtoError> {
+    Ok(match ty {
+        Some(ty) => AttributesOrContext(type_to(ty)?),
+        None => AttributesOrContext::default(),
+    })
+}
+
+fn type_from(ty: &SchemaType) -> models::Type {
+    let ty = match ty {
+        SchemaType::TypeDef { type_name } => T::Common(type_name.to_string()),
+        SchemaType::Type(SchemaTypeVariant::String) => T::String(models::Empty {}),
+        SchemaType::Type(SchemaTypeVariant::Long) => T::Long(models::Empty {}),
+        SchemaType::Type(SchemaTypeVariant::Boolean) => T::Boolean(models::Empty {}),
+        SchemaType::Type(SchemaTypeVariant::Set { element }) => {
+            T::Set(Box::new(type_from(element)))
+        }
+        SchemaType::Type(SchemaTypeVariant::Record {
+            attributes,
+            additional_attributes,
+        }) => T::Record(models::RecordType {
+            attributes: attributes
+                .iter()
+                .map(|(name, attr)| {
+                    let attr = models::Attribute {
+                        r#type: Some(type_from(&attr.ty)),
+                        required: Some(attr.required),
+                        deprecated: attr.deprecated.as_ref().map(ToString::to_string),
+                        annotations: annotations_from(&attr.annotations),
+                    };
+                    (name.to_string(), attr)
+                })
+                .collect(),
+            additional_attributes: *additional_attributes,
+        }),
+        SchemaType::Type(SchemaTypeVariant::Entity { name }) => T::Entity(name.to_string()),
+        SchemaType::Type(SchemaTypeVariant::Extension { name }) => T::Extension(name.to_string()),
+    };
+    models::Type { r#type: Some(ty) }
+}
+
+fn type_to(ty: &models::Type) -> Result<SchemaType, ProtoError> {
+    let variant = match ty
+        .r#type
+        .as_ref()
+        .ok_or(ProtoError::MissingField("Type.type"))?
+    {
+        T::Common(name) => {
+            return Ok(SchemaType::TypeDef {
+                type_name: name.as_str().into(),
+            })
+        }
+        T::String(_) => SchemaTypeVariant::String,
+        T::Long(_) => SchemaTypeVariant::Long,
+        T::Boolean(_) => SchemaTypeVariant::Boolean,
+        T::Set(element) => SchemaTypeVariant::Set {
+            element: Box::new(type_to(element)?),
+        },
+        T::Record(record) => SchemaTypeVariant::Record {
+            attributes: record
+                .attributes
+                .iter()
+                .map(|(name, attr)| {
+                    let ty = attr
+                        .r#type
+                        .as_ref()
+                        .ok_or(ProtoError::MissingField("Attribute.type"))?;
+                    let attr = TypeOfAttribute {
+                        ty: type_to(ty)?,
+                        required: attr.required.unwrap_or(true),
+                        deprecated: attr.deprecated.as_deref().map(SmolStr::from),
+                        annotations: annotations_to(&attr.annotations),
+                    };
+                    Ok((SmolStr::from(name.as_str()), attr))
+                })
+                .collect::<Result<BTreeMap<_, _>, ProtoError>>()?,
+            additional_attributes: record.additional_attributes,
+        },
+        T::Entity(name) => SchemaTypeVariant::Entity {
+            name: name.as_str().into(),
+        },
+        T::Extension(name) => SchemaTypeVariant::Extension {
+            name: name.as_str().into(),
+        },
+    };
+    Ok(SchemaType::Type(variant))
+}
@@ -0,0 +1,1101 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module renames entity types, attributes of entity types, and actions
+//! consistently across a policy set and its schema.
+//! ```ignore
+//! let rename = Rename::EntityType {
+//!     from: "User".parse()?,
+//!     to: "Person".parse()?,
+//! };
+//! let (policies, schema, edits) = refactor::rename(&policies, schema, &rename)?;
+//! for edit in &edits {
+//!     println!("{edit}");
+//! }
+//! ```
+//!
+//! Entity types and actions are renamed in the scopes and conditions of
+//! static policies and templates, in the values of template-linked policies,
+//! and in the declarations of and references to them in the schema.
+//! Attributes are renamed in the declaration of the entity type in the schema,
+//! and where the validator infers that the attribute is accessed on an entity
+//! of the type: renaming an attribute requires the policies to validate
+//! against the schema in strict mode, and to be parsed from text, as the
+//! accesses are found by their location in the policy source. Policies which
+//! never access an attribute of that name are left alone.
+//!
+//! The new name of an entity type or action must be in the same namespace as
+//! the old one, and must not already be declared in the schema.
+#![allow(clippy::module_name_repetitions)]
+
+use crate::{EntityTypeName, EntityUid, PolicyId, PolicySet, Schema, SchemaError, SourceLocation};
+use cedar_policy_core::ast::{
+    self, ActionConstraint, EntityReference, EntityType, EntityUID, Expr, ExprBuilder, ExprKind,
+    Literal, Name, PrincipalOrResource, PrincipalOrResourceConstraint,
+};
+use cedar_policy_core::parser::Loc;
+use cedar_policy_validator::typecheck::{PolicyCheck, Typechecker};
+use cedar_policy_validator::types::{EntityRecordKind, Type};
+use cedar_policy_validator::{
+    NamespaceDefinition, SchemaFragment, SchemaType, SchemaTypeVariant, ValidationMode,
+};
+use itertools::Itertools;
+use miette::Diagnostic;
+use ref_cast::RefCast;
+use smol_str::SmolStr;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// A rename made by [`rename`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Rename {
+    /// Rename the entity type `from` to `to`
+    EntityType {
+        /// The entity type
+        from: EntityTypeName,
+        /// Its new name, in the same namespace
+        to: EntityTypeName,
+    },
+    /// Rename the attribute `from` of the entity type `entity_type` to `to`
+    Attribute {
+        /// The entity type which declares the attribute
+        entity_type: EntityTypeName,
+        /// The attribute
+        from: String,
+        /// Its new name
+        to: String,
+    },
+    /// Rename the action `from` to `to`
+    Action {
+        /// The action
+        from: EntityUid,
+        /// Its new name, of the same action entity type
+        to: EntityUid,
+    },
+}
+
+/// Where an [`Edit`] was made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditLocation {
+    /// In a static policy, template, or template-linked policy. Scope
+    /// constraints and the values of template-linked policies have no range.
+    Policy(SourceLocation<'static>),
+    /// In the schema, at the JSON pointer (RFC 6901) to the edited value or
+    /// object key, e.g., `/NS/entityTypes/User/memberOfTypes/0`
+    Schema(String),
+}
+
+impl Display for EditLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Policy(loc) => write!(f, "{loc}"),
+            Self::Schema(pointer) => write!(f, "schema at `{pointer}`"),
+        }
+    }
+}
+
+/// A location touched by [`rename`], with its text before and after
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    location: EditLocation,
+    before: String,
+    after: String,
+}
+
+impl Edit {
+    /// Where the edit was made
+    pub fn location(&self) -> &EditLocation {
+        &self.location
+    }
+
+    /// The text before the edit: the expression, scope constraint, or entity
+    /// in a policy, or the name in the schema
+    pub fn before(&self) -> &str {
+        &self.before
+    }
+
+    /// The text after the edit
+    pub fn after(&self) -> &str {
+        &self.after
+    }
+}
+
+impl Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: replaced `{}` by `{}`",
+            self.location, self.before, self.after
+        )
+    }
+}
+
+/// Errors renaming across a policy set and schema
+#[derive(Debug, Diagnostic, Error)]
+#[non_exhaustive]
+pub enum RefactorError {
+    /// The schema is invalid
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Schema(#[from] SchemaError),
+    /// The renamed entity type, attribute, or action is not declared in the
+    /// schema
+    #[error("`{0}` is not declared in the schema")]
+    NotFound(String),
+    /// The new name is already declared in the schema
+    #[error("`{0}` is already declared in the schema")]
+    Conflict(String),
+    /// The new name is in a different namespace
+    #[error("`{to}` is not in the same namespace as `{from}`")]
+    #[diagnostic(help("only renames within a namespace are supported"))]
+    Namespace {
+        /// The old name
+        from: String,
+        /// The new name
+        to: String,
+    },
+    /// The attributes of the entity type are declared by a common type
+    #[error("the attributes of `{entity_type}` are declared by the common type `{common_type}`")]
+    #[diagnostic(help("the common type may be shared by other types, so it isn't renamed"))]
+    CommonTypeShape {
+        /// The entity type
+        entity_type: String,
+        /// The common type
+        common_type: String,
+    },
+    /// The policy does not validate against the schema, so the attribute
+    /// accesses to rename can't be found
+    #[error("policy `{0}` does not validate against the schema")]
+    #[diagnostic(help("validate the policy in strict mode to see the errors"))]
+    Validation(PolicyId),
+    /// The policy accesses an attribute with the renamed name, but has no
+    /// source locations to tell which accesses to rename
+    #[error("policy `{id}` accesses `{attr}` but has no source locations")]
+    #[diagnostic(help("attributes can only be renamed in policies parsed from text"))]
+    MissingSourceLocation {
+        /// The policy
+        id: PolicyId,
+        /// The attribute
+        attr: String,
+    },
+}
+
+/// Rename an entity type, attribute, or action across `policies` and the JSON
+/// `schema` they're written against, returning the renamed policy set, the
+/// renamed schema, and every location which was edited. Policy ids,
+/// annotations, and template links are kept. See the [`crate::refactor`]
+/// module for what is renamed.
+pub fn rename(
+    policies: &PolicySet,
+    schema: serde_json::Value,
+    rename: &Rename,
+) -> Result<(PolicySet, serde_json::Value, Vec<Edit>), RefactorError> {
+    let validator_schema = Schema::from_json_value(schema.clone())?;
+    let mut fragment = SchemaFragment::from_json_value(schema).map_err(SchemaError::from)?;
+    let mut schema_edits = Vec::new();
+    match rename {
+        Rename::EntityType { from, to } => {
+            rename_entity_type(&mut fragment, from, to, &mut schema_edits)?;
+        }
+        Rename::Attribute {
+            entity_type,
+            from,
+            to,
+        } => rename_attribute(&mut fragment, entity_type, from, to, &mut schema_edits)?,
+        Rename::Action { from, to } => {
+            rename_action(&mut fragment, from, to, &mut schema_edits)?;
+        }
+    }
+
+    let mut renamer = Renamer {
+        rename,
+        id: None,
+        accesses: HashSet::new(),
+        edits: Vec::new(),
+    };
+    let policies = renamer.policy_set(policies, &validator_schema)?;
+    let mut edits = renamer.edits;
+    edits.extend(schema_edits);
+    let schema = serde_json::to_value(fragment).map_err(SchemaError::from)?;
+    Ok((policies, schema, edits))
+}
+
+/// The state of renaming in the policies
+struct Renamer<'a> {
+    rename: &'a Rename,
+    /// The policy or template being renamed
+    id: Option<PolicyId>,
+    /// For an attribute rename, the source ranges of the accesses to rename in
+    /// the template being renamed
+    accesses: HashSet<(usize, usize)>,
+    edits: Vec<Edit>,
+}
+
+impl<'a> Renamer<'a> {
+    /// Rename in every template, static policy, and template-linked policy
+    fn policy_set(
+        &mut self,
+        policies: &PolicySet,
+        schema: &Schema,
+    ) -> Result<PolicySet, RefactorError> {
+        let mut renamed = ast::PolicySet::new();
+        for template in policies
+            .ast
+            .templates()
+            .sorted_by(|a, b| a.id().as_ref().cmp(b.id().as_ref()))
+        {
+            let template = self.template(template, schema)?;
+            // PANIC SAFETY: the ids are those of the original policy set, so they're unique
+            #[allow(clippy::expect_used)]
+            renamed
+                .add_template(template)
+                .expect("template ids should be unique");
+        }
+        for policy in policies
+            .ast
+            .policies()
+            .sorted_by(|a, b| a.id().as_ref().cmp(b.id().as_ref()))
+        {
+            if policy.is_static() {
+                let template = self.template(policy.template(), schema)?;
+                // PANIC SAFETY: renaming doesn't add slots to a static policy
+                #[allow(clippy::expect_used)]
+                let policy = ast::StaticPolicy::try_from(template)
+                    .expect("a renamed static policy should have no slots");
+                let (_, policy) = ast::Template::link_static_policy(policy);
+                // PANIC SAFETY: the ids are those of the original policy set, so they're unique
+                #[allow(clippy::expect_used)]
+                renamed.add(policy).expect("policy ids should be unique");
+            } else {
+                self.id = Some(PolicyId::ref_cast(policy.id()).clone());
+                let values = policy
+                    .env()
+                    .iter()
+                    .map(|(slot, value)| match self.euid(value) {
+                        Some(renamed) => {
+                            self.edit(None, value, &renamed);
+                            (*slot, renamed)
+                        }
+                        None => (*slot, value.clone()),
+                    })
+                    .collect();
+                // PANIC SAFETY: the link is of an already added template, with the same slots
+                #[allow(clippy::expect_used)]
+                renamed
+                    .link(policy.template().id().clone(), policy.id().clone(), values)
+                    .expect("renamed link should be valid");
+            }
+        }
+        Ok(PolicySet::from_ast(renamed))
+    }
+
+    /// Rename in the scope and conditions of `template`
+    fn template(
+        &mut self,
+        template: &ast::Template,
+        schema: &Schema,
+    ) -> Result<ast::Template, RefactorError> {
+        let id = PolicyId::ref_cast(template.id()).clone();
+        self.accesses = match self.rename {
+            Rename::Attribute {
+                entity_type, from, ..
+            } => attribute_accesses(schema, template, &entity_type.0, from, &id)?,
+            Rename::EntityType { .. } | Rename::Action { .. } => HashSet::new(),
+        };
+        self.id = Some(id);
+
+        let principal = self.scope(
+            template.principal_constraint().as_inner(),
+            PrincipalOrResource::Principal,
+        );
+        let action = self.action_scope(template.action_constraint());
+        let resource = self.scope(
+            template.resource_constraint().as_inner(),
+            PrincipalOrResource::Resource,
+        );
+        let condition = self.expr(template.non_head_constraints());
+        let annotations = template
+            .annotations()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        Ok(ast::Template::new(
+            template.id().clone(),
+            annotations,
+            template.effect(),
+            ast::PrincipalConstraint::new(principal),
+            action,
+            ast::ResourceConstraint::new(resource),
+            condition,
+        ))
+    }
+
+    /// Rename in `expr`, keeping its source locations
+    fn expr(&mut self, expr: &Expr) -> Expr {
+        let builder = || ExprBuilder::new().with_same_source_loc(expr);
+        let renamed = match expr.expr_kind() {
+            ExprKind::Lit(Literal::EntityUID(euid)) => match self.euid(euid) {
+                Some(renamed) => builder().val(renamed),
+                None => return expr.clone(),
+            },
+            ExprKind::Lit(_) | ExprKind::Var(_) | ExprKind::Slot(_) | ExprKind::Unknown(_) => {
+                return expr.clone()
+            }
+            ExprKind::If {
+                test_expr,
+                then_expr,
+                else_expr,
+            } => {
+                return builder().ite(
+                    self.expr(test_expr),
+                    self.expr(then_expr),
+                    self.expr(else_expr),
+                )
+            }
+            ExprKind::And { left, right } => {
+                return builder().and(self.expr(left), self.expr(right))
+            }
+            ExprKind::Or { left, right } => return builder().or(self.expr(left), self.expr(right)),
+            ExprKind::UnaryApp { op, arg } => return builder().unary_app(*op, self.expr(arg)),
+            ExprKind::BinaryApp { op, arg1, arg2 } => {
+                return builder().binary_app(*op, self.expr(arg1), self.expr(arg2))
+            }
+            ExprKind::MulByConst { arg, constant } => {
+                return builder().mul(self.expr(arg), *constant)
+            }
+            ExprKind::ExtensionFunctionApp { fn_name, args } => {
+                return builder().call_extension_fn(
+                    fn_name.clone(),
+                    args.iter().map(|arg| self.expr(arg)).collect::<Vec<_>>(),
+                )
+            }
+            ExprKind::GetAttr {
+                expr: subject,
+                attr,
+            } => {
+                let subject = self.expr(subject);
+                match self.attr(expr, attr) {
+                    Some(renamed) => builder().get_attr(subject, renamed),
+                    None => return builder().get_attr(subject, attr.clone()),
+                }
+            }
+            ExprKind::HasAttr {
+                expr: subject,
+                attr,
+            } => {
+                let subject = self.expr(subject);
+                match self.attr(expr, attr) {
+                    Some(renamed) => builder().has_attr(subject, renamed),
+                    None => return builder().has_attr(subject, attr.clone()),
+                }
+            }
+            ExprKind::Like {
+                expr: subject,
+                pattern,
+            } => return builder().like(self.expr(subject), pattern.iter().copied()),
+            ExprKind::Is {
+                expr: subject,
+                entity_type,
+            } => {
+                let subject = self.expr(subject);
+                match self.entity_type(entity_type) {
+                    Some(renamed) => builder().is_entity_type(subject, renamed),
+                    None => return builder().is_entity_type(subject, entity_type.clone()),
+                }
+            }
+            ExprKind::Set(elements) => return builder().set(elements.iter().map(|e| self.expr(e))),
+            ExprKind::Record(fields) => {
+                return builder().record_arc(Arc::new(
+                    fields
+                        .iter()
+                        .map(|(k, v)| (k.clone(), self.expr(v)))
+                        .collect(),
+                ))
+            }
+        };
+        self.edit(expr.source_loc(), expr, &renamed);
+        renamed
+    }
+
+    /// Rename in the principal or resource scope constraint `scope`
+    fn scope(
+        &mut self,
+        scope: &PrincipalOrResourceConstraint,
+        var: PrincipalOrResource,
+    ) -> PrincipalOrResourceConstraint {
+        let entity = |renamer: &Self, entity: &EntityReference| match entity {
+            EntityReference::EUID(euid) => renamer
+                .euid(euid)
+                .map(|euid| EntityReference::EUID(Arc::new(euid))),
+            EntityReference::Slot => None,
+        };
+        let renamed = match scope {
+            PrincipalOrResourceConstraint::Any => None,
+            PrincipalOrResourceConstraint::In(e) => {
+                entity(self, e).map(PrincipalOrResourceConstraint::In)
+            }
+            PrincipalOrResourceConstraint::Eq(e) => {
+                entity(self, e).map(PrincipalOrResourceConstraint::Eq)
+            }
+            PrincipalOrResourceConstraint::Is(ty) => {
+                self.entity_type(ty).map(PrincipalOrResourceConstraint::Is)
+            }
+            PrincipalOrResourceConstraint::IsIn(ty, e) => {
+                match (self.entity_type(ty), entity(self, e)) {
+                    (None, None) => None,
+                    (ty_renamed, e_renamed) => Some(PrincipalOrResourceConstraint::IsIn(
+                        ty_renamed.unwrap_or_else(|| ty.clone()),
+                        e_renamed.unwrap_or_else(|| e.clone()),
+                    )),
+                }
+            }
+        };
+        match renamed {
+            Some(renamed) => {
+                self.edit(None, scope.display(var), renamed.display(var));
+                renamed
+            }
+            None => scope.clone(),
+        }
+    }
+
+    /// Rename in the action scope constraint `scope`
+    fn action_scope(&mut self, scope: &ActionConstraint) -> ActionConstraint {
+        let renamed = match scope {
+            ActionConstraint::Any => None,
+            ActionConstraint::Eq(action) => self
+                .euid(action)
+                .map(|action| ActionConstraint::Eq(Arc::new(action))),
+            ActionConstraint::In(actions) => actions
+                .iter()
+                .any(|action| self.euid(action).is_some())
+                .then(|| {
+                    ActionConstraint::In(
+                        actions
+                            .iter()
+                            .map(|action| {
+                                self.euid(action).map_or_else(|| action.clone(), Arc::new)
+                            })
+                            .collect(),
+                    )
+                }),
+        };
+        match renamed {
+            Some(renamed) => {
+                self.edit(None, scope, &renamed);
+                renamed
+            }
+            None => scope.clone(),
+        }
+    }
+
+    /// The new name of the entity `euid`, if it's renamed
+    fn euid(&self, euid: &EntityUID) -> Option<EntityUID> {
+        match self.rename {
+            Rename::EntityType { from, to } if matches!(euid.entity_type(), EntityType::Specified(ty) if ty == &from.0) => {
+                Some(EntityUID::from_components(to.0.clone(), euid.eid().clone()))
+            }
+            Rename::Action { from, to } if euid == &from.0 => Some(to.0.clone()),
+            _ => None,
+        }
+    }
+
+    /// The new name of the entity type `ty`, if it's renamed
+    fn entity_type(&self, ty: &Name) -> Option<Name> {
+        match self.rename {
+            Rename::EntityType { from, to } if ty == &from.0 => Some(to.0.clone()),
+            _ => None,
+        }
+    }
+
+    /// The new name of the attribute `attr` accessed by `access`, if it's
+    /// renamed
+    fn attr(&self, access: &Expr, attr: &str) -> Option<SmolStr> {
+        match self.rename {
+            Rename::Attribute { from, to, .. }
+                if attr == from
+                    && access
+                        .source_loc()
+                        .is_some_and(|loc| self.accesses.contains(&(loc.start(), loc.end()))) =>
+            {
+                Some(to.into())
+            }
+            _ => None,
+        }
+    }
+
+    /// Record an edit in the current policy
+    fn edit(&mut self, loc: Option<&Loc>, before: impl Display, after: impl Display) {
+        // PANIC SAFETY: `id` is set before renaming in a policy
+        #[allow(clippy::expect_used)]
+        let id = self.id.clone().expect("edits are in a policy");
+        self.edits.push(Edit {
+            location: EditLocation::Policy(SourceLocation::new(id, loc.cloned())),
+            before: before.to_string(),
+            after: after.to_string(),
+        });
+    }
+}
+
+/// The source ranges of the accesses of the attribute `attr` on entities of
+/// type `entity_type` in `template`, as inferred by the validator
+fn attribute_accesses(
+    schema: &Schema,
+    template: &ast::Template,
+    entity_type: &Name,
+    attr: &str,
+    id: &PolicyId,
+) -> Result<HashSet<(usize, usize)>, RefactorError> {
+    let candidates = template
+        .non_head_constraints()
+        .subexpressions()
+        .filter(|access| {
+            matches!(
+                access.expr_kind(),
+                ExprKind::GetAttr { attr: a, .. } | ExprKind::HasAttr { attr: a, .. } if a == attr
+            )
+        })
+        .collect::<Vec<_>>();
+    // a template which never accesses the attribute needn't validate
+    if candidates.is_empty() {
+        return Ok(HashSet::new());
+    }
+    // every access which may be renamed needs a location to be told apart
+    if candidates
+        .iter()
+        .any(|access| access.source_loc().is_none())
+    {
+        return Err(RefactorError::MissingSourceLocation {
+            id: id.clone(),
+            attr: attr.to_string(),
+        });
+    }
+
+    let typechecker = Typechecker::new(&schema.0, ValidationMode::Strict);
+    let mut accesses = HashSet::new();
+    let mut applies = false;
+    for (_, check) in typechecker.typecheck_by_request_env(template) {
+        let typed = match check {
+            PolicyCheck::Success(typed) => typed,
+            PolicyCheck::Irrelevant(errs) if errs.is_empty() => continue,
+            PolicyCheck::Irrelevant(_) | PolicyCheck::Fail(_) => {
+                return Err(RefactorError::Validation(id.clone()))
+            }
+        };
+        applies = true;
+        for access in typed.subexpressions() {
+            let (ExprKind::GetAttr { expr, attr: a } | ExprKind::HasAttr { expr, attr: a }) =
+                access.expr_kind()
+            else {
+                continue;
+            };
+            let on_entity_type = matches!(
+                expr.data(),
+                Some(Type::EntityOrRecord(EntityRecordKind::Entity(lub)))
+                    if lub.get_single_entity() == Some(entity_type)
+            );
+            if a == attr && on_entity_type {
+                if let Some(loc) = access.source_loc() {
+                    accesses.insert((loc.start(), loc.end()));
+                }
+            }
+        }
+    }
+    if applies {
+        Ok(accesses)
+    } else {
+        Err(RefactorError::Validation(id.clone()))
+    }
+}
+
+/// The JSON pointer to the child `key` of `pointer`
+fn child(pointer: &str, key: impl Display) -> String {
+    let key = key.to_string().replace('~', "~0").replace('/', "~1");
+    format!("{pointer}/{key}")
+}
+
+/// The fully qualified name of the type `name` referenced in the namespace
+/// `namespace`
+fn qualify(namespace: &str, name: &str) -> String {
+    if namespace.is_empty() || name.contains("::") {
+        name.to_string()
+    } else {
+        format!("{namespace}::{name}")
+    }
+}
+
+/// The definition of `namespace` in `fragment`, and the JSON pointer to it
+fn namespace_definition<'f>(
+    fragment: &'f mut SchemaFragment,
+    namespace: &str,
+) -> Option<(&'f mut NamespaceDefinition, String)> {
+    fragment
+        .0
+        .get_mut(namespace)
+        .map(|definition| (definition, child("", namespace)))
+}
+
+/// Rename the entity type `from` to `to` in the schema: its declaration and
+/// every reference to it
+fn rename_entity_type(
+    fragment: &mut SchemaFragment,
+    from: &EntityTypeName,
+    to: &EntityTypeName,
+    edits: &mut Vec<Edit>,
+) -> Result<(), RefactorError> {
+    if from.namespace() != to.namespace() {
+        return Err(RefactorError::Namespace {
+            from: from.to_string(),
+            to: to.to_string(),
+        });
+    }
+    let (definition, pointer) = namespace_definition(fragment, &from.namespace())
+        .ok_or_else(|| RefactorError::NotFound(from.to_string()))?;
+    if definition.entity_types.contains_key(to.basename()) {
+        return Err(RefactorError::Conflict(to.to_string()));
+    }
+    let declaration = definition
+        .entity_types
+        .remove(from.basename())
+        .ok_or_else(|| RefactorError::NotFound(from.to_string()))?;
+    definition
+        .entity_types
+        .insert(to.basename().into(), declaration);
+    edits.push(schema_edit(
+        child(&child(&pointer, "entityTypes"), from.basename()),
+        from.basename(),
+        to.basename(),
+    ));
+
+    let from = from.to_string();
+    let rename =
+        |reference: &mut SmolStr, namespace: &str, pointer: String, edits: &mut Vec<Edit>| {
+            if qualify(namespace, reference) == from {
+                let renamed: SmolStr = if reference.contains("::") {
+                    to.to_string().into()
+                } else {
+                    to.basename().into()
+                };
+                edits.push(schema_edit(pointer, reference.as_str(), renamed.as_str()));
+                *reference = renamed;
+            }
+        };
+    for (namespace, definition) in sorted_mut(&mut fragment.0) {
+        let pointer = child("", namespace);
+        for (name, ty) in sorted_mut(&mut definition.common_types) {
+            let pointer = child(&child(&pointer, "commonTypes"), name);
+            rename_in_type(ty, pointer, &mut |reference, pointer| {
+                rename(reference, namespace, pointer, edits);
+            });
+        }
+        for (name, declaration) in sorted_mut(&mut definition.entity_types) {
+            let pointer = child(&child(&pointer, "entityTypes"), name);
+            for (i, parent) in declaration.member_of_types.iter_mut().enumerate() {
+                rename(
+                    parent,
+                    namespace,
+                    child(&child(&pointer, "memberOfTypes"), i),
+                    edits,
+                );
+            }
+            rename_in_type(
+                &mut declaration.shape.0,
+                child(&pointer, "shape"),
+                &mut |reference, pointer| rename(reference, namespace, pointer, edits),
+            );
+        }
+        for (name, declaration) in sorted_mut(&mut definition.actions) {
+            let Some(applies_to) = &mut declaration.applies_to else {
+                continue;
+            };
+            let pointer = child(&child(&child(&pointer, "actions"), name), "appliesTo");
+            for (key, types) in [
+                ("principalTypes", &mut applies_to.principal_types),
+                ("resourceTypes", &mut applies_to.resource_types),
+            ] {
+                for (i, ty) in types.iter_mut().flatten().enumerate() {
+                    rename(ty, namespace, child(&child(&pointer, key), i), edits);
+                }
+            }
+            rename_in_type(
+                &mut applies_to.context.0,
+                child(&pointer, "context"),
+                &mut |reference, pointer| rename(reference, namespace, pointer, edits),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Call `rename` on every reference to an entity type in `ty`, with its JSON
+/// pointer
+fn rename_in_type(
+    ty: &mut SchemaType,
+    pointer: String,
+    rename: &mut impl FnMut(&mut SmolStr, String),
+) {
+    match ty {
+        SchemaType::Type(SchemaTypeVariant::Entity { name }) => {
+            rename(name, child(&pointer, "name"));
+        }
+        SchemaType::Type(SchemaTypeVariant::Set { element }) => {
+            rename_in_type(element, child(&pointer, "element"), rename);
+        }
+        SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => {
+            for (name, attr) in attributes.iter_mut() {
+                rename_in_type(
+                    &mut attr.ty,
+                    child(&child(&pointer, "attributes"), name),
+                    rename,
+                );
+            }
+        }
+        SchemaType::Type(_) | SchemaType::TypeDef { .. } => {}
+    }
+}
+
+/// Rename the attribute `from` of the entity type `entity_type` to `to` in its
+/// declaration in the schema
+fn rename_attribute(
+    fragment: &mut SchemaFragment,
+    entity_type: &EntityTypeName,
+    from: &str,
+    to: &str,
+    edits: &mut Vec<Edit>,
+) -> Result<(), RefactorError> {
+    let not_found = || RefactorError::NotFound(format!("{entity_type}.{from}"));
+    let (definition, pointer) =
+        namespace_definition(fragment, &entity_type.namespace()).ok_or_else(not_found)?;
+    let declaration = definition
+        .entity_types
+        .get_mut(entity_type.basename())
+        .ok_or_else(not_found)?;
+    let attributes = match &mut declaration.shape.0 {
+        SchemaType::Type(SchemaTypeVariant::Record { attributes, .. }) => attributes,
+        SchemaType::TypeDef { type_name } => {
+            return Err(RefactorError::CommonTypeShape {
+                entity_type: entity_type.to_string(),
+                common_type: type_name.to_string(),
+            })
+        }
+        SchemaType::Type(_) => return Err(not_found()),
+    };
+    if attributes.contains_key(to) {
+        return Err(RefactorError::Conflict(format!("{entity_type}.{to}")));
+    }
+    let attr = attributes.remove(from).ok_or_else(not_found)?;
+    attributes.insert(to.into(), attr);
+    let pointer = child(&child(&pointer, "entityTypes"), entity_type.basename());
+    edits.push(schema_edit(
+        child(&child(&child(&pointer, "shape"), "attributes"), from),
+        from,
+        to,
+    ));
+    Ok(())
+}
+
+/// Rename the action `from` to `to` in the schema: its declaration and every
+/// action which is a member of it
+fn rename_action(
+    fragment: &mut SchemaFragment,
+    from: &EntityUid,
+    to: &EntityUid,
+    edits: &mut Vec<Edit>,
+) -> Result<(), RefactorError> {
+    let action_type = from.type_name();
+    if action_type != to.type_name() {
+        return Err(RefactorError::Namespace {
+            from: from.to_string(),
+            to: to.to_string(),
+        });
+    }
+    let (from_id, to_id) = (from.id().as_ref(), to.id().as_ref());
+    let (definition, pointer) = namespace_definition(fragment, &action_type.namespace())
+        .ok_or_else(|| RefactorError::NotFound(from.to_string()))?;
+    if definition.actions.contains_key(to_id) {
+        return Err(RefactorError::Conflict(to.to_string()));
+    }
+    let declaration = definition
+        .actions
+        .remove(from_id)
+        .ok_or_else(|| RefactorError::NotFound(from.to_string()))?;
+    definition.actions.insert(to_id.into(), declaration);
+    edits.push(schema_edit(
+        child(&child(&pointer, "actions"), from_id),
+        from_id,
+        to_id,
+    ));
+
+    let action_type = action_type.to_string();
+    for (namespace, definition) in sorted_mut(&mut fragment.0) {
+        let pointer = child("", namespace);
+        for (name, declaration) in sorted_mut(&mut definition.actions) {
+            let pointer = child(&child(&child(&pointer, "actions"), name), "memberOf");
+            for (i, parent) in declaration.member_of.iter_mut().flatten().enumerate() {
+                let ty = qualify(namespace, parent.ty.as_deref().unwrap_or("Action"));
+                if ty == action_type && parent.id == from_id {
+                    edits.push(schema_edit(
+                        child(&child(&pointer, i), "id"),
+                        from_id,
+                        to_id,
+                    ));
+                    parent.id = to_id.into();
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// An edit in the schema
+fn schema_edit(pointer: String, before: &str, after: &str) -> Edit {
+    Edit {
+        location: EditLocation::Schema(pointer),
+        before: before.to_string(),
+        after: after.to_string(),
+    }
+}
+
+/// The entries of `map` sorted by key, so that edits are reported in a stable
+/// order
+fn sorted_mut<V>(map: &mut HashMap<SmolStr, V>) -> impl Iterator<Item = (&SmolStr, &mut V)> {
+    map.iter_mut().sorted_by(|(a, _), (b, _)| a.cmp(b))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SlotId;
+    use cool_asserts::assert_matches;
+    use std::str::FromStr;
+
+    fn schema() -> serde_json::Value {
+        let applies_to = serde_json::json!({
+            "principalTypes": ["User"],
+            "resourceTypes": ["Photo"]
+        });
+        serde_json::json!({ "": {
+            "entityTypes": {
+                "User": {
+                    "memberOfTypes": ["Group"],
+                    "shape": {
+                        "type": "Record",
+                        "attributes": {
+                            "age": { "type": "Long" },
+                            "manager": { "type": "Entity", "name": "User" }
+                        }
+                    }
+                },
+                "Group": {},
+                "Photo": {
+                    "shape": {
+                        "type": "Record",
+                        "attributes": {
+                            "age": { "type": "Long" },
+                            "owner": { "type": "Entity", "name": "User" }
+                        }
+                    }
+                }
+            },
+            "actions": {
+                "view": { "appliesTo": applies_to.clone() },
+                "read": { "memberOf": [{ "id": "view" }], "appliesTo": applies_to }
+            }
+        }})
+    }
+
+    /// A static policy `policy0`, a template `policy1`, and its link `link`
+    fn policies() -> PolicySet {
+        let mut policies = PolicySet::from_str(
+            r#"permit(principal is User in Group::"admins", action == Action::"view", resource)
+            when { principal.age > 18 && resource.age < 10 && resource.owner.manager == principal };
+            permit(principal == ?principal, action in [Action::"read", Action::"view"], resource);"#,
+        )
+        .expect("policies should parse");
+        policies
+            .link(
+                PolicyId::from_str("policy1").unwrap(),
+                PolicyId::from_str("link").unwrap(),
+                HashMap::from([(SlotId::principal(), r#"User::"alice""#.parse().unwrap())]),
+            )
+            .expect("link should be valid");
+        policies
+    }
+
+    /// Assert that the policy or template `id` in `renamed` has the scope and
+    /// condition of the policy `expected`
+    fn assert_same(renamed: &PolicySet, id: &str, expected: &str) {
+        let renamed = renamed
+            .ast
+            .get_template(&ast::PolicyID::from_string(id))
+            .expect("policy should exist");
+        let expected = cedar_policy_core::parser::parse_policy_template(None, expected)
+            .expect("policy should parse");
+        assert_eq!(
+            renamed.principal_constraint(),
+            expected.principal_constraint()
+        );
+        assert_eq!(renamed.action_constraint(), expected.action_constraint());
+        assert_eq!(
+            renamed.resource_constraint(),
+            expected.resource_constraint()
+        );
+        assert!(
+            renamed
+                .non_head_constraints()
+                .eq_shape(expected.non_head_constraints()),
+            "expected `{}`, got `{}`",
+            expected.non_head_constraints(),
+            renamed.non_head_constraints()
+        );
+    }
+
+    #[test]
+    fn renames_entity_type() {
+        let change = Rename::EntityType {
+            from: "User".parse().unwrap(),
+            to: "Person".parse().unwrap(),
+        };
+        let (renamed, schema, edits) = rename(&policies(), schema(), &change).unwrap();
+        assert_same(
+            &renamed,
+            "policy0",
+            r#"permit(principal is Person in Group::"admins", action == Action::"view", resource)
+            when { principal.age > 18 && resource.age < 10 && resource.owner.manager == principal };"#,
+        );
+        let link = renamed
+            .policy(&PolicyId::from_str("link").unwrap())
+            .unwrap();
+        assert_eq!(
+            link.template_links().unwrap()[&SlotId::principal()],
+            r#"Person::"alice""#.parse().unwrap()
+        );
+
+        let entity_types = &schema[""]["entityTypes"];
+        assert!(entity_types.get("User").is_none());
+        assert_eq!(
+            entity_types["Person"]["shape"]["attributes"]["manager"]["name"],
+            "Person"
+        );
+        assert_eq!(
+            entity_types["Photo"]["shape"]["attributes"]["owner"]["name"],
+            "Person"
+        );
+        assert_eq!(
+            schema[""]["actions"]["view"]["appliesTo"]["principalTypes"][0],
+            "Person"
+        );
+        Schema::from_json_value(schema).expect("renamed schema should be valid");
+
+        // the `is` scope, the link, the declaration, two attributes, and two
+        // `appliesTo`s
+        assert_eq!(edits.len(), 7);
+        assert!(edits.iter().any(|edit| matches!(
+            edit.location(),
+            EditLocation::Schema(pointer) if pointer == "//entityTypes/Photo/shape/attributes/owner/name"
+        )));
+    }
+
+    #[test]
+    fn renames_attribute() {
+        let change = Rename::Attribute {
+            entity_type: "User".parse().unwrap(),
+            from: "age".into(),
+            to: "years".into(),
+        };
+        let (renamed, schema, edits) = rename(&policies(), schema(), &change).unwrap();
+        // `resource.age` is an attribute of `Photo`, so it's kept
+        assert_same(
+            &renamed,
+            "policy0",
+            r#"permit(principal is User in Group::"admins", action == Action::"view", resource)
+            when { principal.years > 18 && resource.age < 10 && resource.owner.manager == principal };"#,
+        );
+        let attributes = &schema[""]["entityTypes"]["User"]["shape"]["attributes"];
+        assert!(attributes.get("age").is_none());
+        assert_eq!(attributes["years"]["type"], "Long");
+
+        assert_eq!(edits.len(), 2);
+        assert_matches!(edits[0].location(), EditLocation::Policy(loc) => {
+            assert_eq!(loc.policy_id(), &PolicyId::from_str("policy0").unwrap());
+            assert!(loc.range_start().is_some());
+        });
+        assert_eq!(edits[0].before(), r#"principal["age"]"#);
+        assert_eq!(edits[0].after(), r#"principal["years"]"#);
+    }
+
+    #[test]
+    fn renames_action() {
+        let change = Rename::Action {
+            from: r#"Action::"view""#.parse().unwrap(),
+            to: r#"Action::"get""#.parse().unwrap(),
+        };
+        let (renamed, schema, _) = rename(&policies(), schema(), &change).unwrap();
+        assert_same(
+            &renamed,
+            "policy1",
+            r#"permit(principal == ?principal, action in [Action::"read", Action::"get"], resource);"#,
+        );
+        assert!(schema[""]["actions"].get("view").is_none());
+        assert_eq!(schema[""]["actions"]["read"]["memberOf"][0]["id"], "get");
+        Schema::from_json_value(schema).expect("renamed schema should be valid");
+    }
+
+    #[test]
+    fn errors() {
+        let rename_type = |from: &str, to: &str| {
+            rename(
+                &policies(),
+                schema(),
+                &Rename::EntityType {
+                    from: from.parse().unwrap(),
+                    to: to.parse().unwrap(),
+                },
+            )
+        };
+        assert_matches!(
+            rename_type("User", "Group"),
+            Err(RefactorError::Conflict(_))
+        );
+        assert_matches!(
+            rename_type("Admin", "Person"),
+            Err(RefactorError::NotFound(_))
+        );
+        assert_matches!(
+            rename_type("User", "NS::Person"),
+            Err(RefactorError::Namespace { .. })
+        );
+
+        let invalid = PolicySet::from_str(
+            r#"permit(principal, action == Action::"view", resource) when { principal.age.foo };"#,
+        )
+        .unwrap();
+        assert_matches!(
+            rename(
+                &invalid,
+                schema(),
+                &Rename::Attribute {
+                    entity_type: "User".parse().unwrap(),
+                    from: "age".into(),
+                    to: "years".into(),
+                },
+            ),
+            Err(RefactorError::Validation(_))
+        );
+    }
+}
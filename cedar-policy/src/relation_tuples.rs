@@ -0,0 +1,453 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module imports Zanzibar-style relation tuples, e.g.,
+//! `doc:readme#viewer@user:alice`, for migrating from relationship-based
+//! access control systems to Cedar.
+//!
+//! Each relation of a namespace is mapped by a [`RelationRule`] to either:
+//! - the entity hierarchy: `group:eng#member@user:alice` makes `User::"alice"`
+//!   a member of `Group::"eng"`, and `doc:readme#parent@folder:a` makes
+//!   `Doc::"readme"` a child of `Folder::"a"`; or
+//! - a link of a template: `doc:readme#viewer@user:alice` links a template
+//!   such as `permit(principal in ?principal, action, resource in ?resource)`
+//!   with `?principal` the subject and `?resource` the object.
+//!
+//! A userset subject, e.g., `group:eng#member`, is the entity of the group,
+//! and must be a relation with a [`RelationRule::Member`] rule. Templates
+//! linked with usersets should use `principal in ?principal` rather than
+//! `principal == ?principal`, so that they apply to members of the group.
+#![allow(clippy::module_name_repetitions)]
+
+use crate::{
+    Entities, EntitiesError, Entity, EntityAttrEvaluationError, EntityId, EntityTypeName,
+    EntityUid, ParseErrors, PolicyId, PolicySet, PolicySetError, Schema, SlotId,
+};
+use miette::Diagnostic;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// A relation tuple `namespace:object#relation@subject`, where the subject is
+/// either `namespace:id` or a userset `namespace:id#relation`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RelationTuple {
+    object: (String, String),
+    relation: String,
+    subject: (String, String),
+    subject_relation: Option<String>,
+}
+
+impl RelationTuple {
+    /// The namespace and id of the object
+    pub fn object(&self) -> (&str, &str) {
+        (&self.object.0, &self.object.1)
+    }
+
+    /// The relation between the object and subject
+    pub fn relation(&self) -> &str {
+        &self.relation
+    }
+
+    /// The namespace and id of the subject
+    pub fn subject(&self) -> (&str, &str) {
+        (&self.subject.0, &self.subject.1)
+    }
+
+    /// The relation of a userset subject, e.g., `member` for
+    /// `group:eng#member`
+    pub fn subject_relation(&self) -> Option<&str> {
+        self.subject_relation.as_deref()
+    }
+}
+
+impl FromStr for RelationTuple {
+    type Err = RelationTupleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |message: &str| RelationTupleError::InvalidTuple {
+            tuple: s.to_owned(),
+            message: message.to_owned(),
+        };
+        let object_ref = |s: &str| match s.split_once(':') {
+            Some((namespace, id)) if !namespace.is_empty() && !id.is_empty() => {
+                Ok((namespace.to_owned(), id.to_owned()))
+            }
+            _ => Err(invalid("expected `namespace:id`")),
+        };
+        let (object, rest) = s.split_once('#').ok_or_else(|| invalid("missing `#`"))?;
+        let (relation, subject) = rest.split_once('@').ok_or_else(|| invalid("missing `@`"))?;
+        if relation.is_empty() {
+            return Err(invalid("empty relation"));
+        }
+        let (subject, subject_relation) = match subject.split_once('#') {
+            Some((_, "")) => return Err(invalid("empty subject relation")),
+            Some((subject, relation)) => (subject, Some(relation.to_owned())),
+            None => (subject, None),
+        };
+        Ok(Self {
+            object: object_ref(object)?,
+            relation: relation.to_owned(),
+            subject: object_ref(subject)?,
+            subject_relation,
+        })
+    }
+}
+
+impl std::fmt::Display for RelationTuple {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}#{}@{}:{}",
+            self.object.0, self.object.1, self.relation, self.subject.0, self.subject.1
+        )?;
+        if let Some(relation) = &self.subject_relation {
+            write!(f, "#{relation}")?;
+        }
+        Ok(())
+    }
+}
+
+/// What a relation of a namespace means in Cedar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelationRule {
+    /// The subject is a member of the object, i.e., the object is a parent of
+    /// the subject
+    Member,
+    /// The subject is a parent of the object
+    Parent,
+    /// Link the template with this id, with `?principal` the subject and
+    /// `?resource` the object
+    Link(PolicyId),
+}
+
+/// Rules for importing relation tuples
+#[derive(Debug, Clone, Default)]
+pub struct TupleMapping {
+    types: HashMap<String, EntityTypeName>,
+    rules: HashMap<(String, String), RelationRule>,
+}
+
+/// Errors importing relation tuples
+#[derive(Debug, Diagnostic, Error)]
+#[non_exhaustive]
+pub enum RelationTupleError {
+    /// A relation tuple doesn't have the form `namespace:object#relation@subject`
+    #[error("invalid relation tuple `{tuple}`: {message}")]
+    InvalidTuple {
+        /// The invalid tuple
+        tuple: String,
+        /// What is wrong with it
+        message: String,
+    },
+    /// A relation has no rule
+    #[error("relation `{relation}` of namespace `{namespace}` has no rule")]
+    UnmappedRelation {
+        /// Namespace of the relation
+        namespace: String,
+        /// The relation
+        relation: String,
+    },
+    /// The relation of a userset subject isn't a membership
+    #[error("userset `{0}` is not a membership relation")]
+    #[diagnostic(help("the relation of a userset must have the `Member` rule"))]
+    UnsupportedUserset(String),
+    /// A namespace without a type mapping isn't a valid entity type name
+    #[error("invalid entity type name for namespace `{namespace}`")]
+    InvalidNamespace {
+        /// The namespace
+        namespace: String,
+        /// Underlying parse error
+        #[source]
+        #[diagnostic_source]
+        source: ParseErrors,
+    },
+    /// The template of a [`RelationRule::Link`] is not in the policy set
+    #[error("template `{0}` is not in the policy set")]
+    MissingTemplate(PolicyId),
+    /// Error parsing the id of a template-linked policy
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    PolicyId(#[from] ParseErrors),
+    /// Error linking a template
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    PolicySet(#[from] PolicySetError),
+    /// Error constructing an entity
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Entity(#[from] EntityAttrEvaluationError),
+    /// Error constructing the entities, e.g., because they don't conform to
+    /// the schema
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Entities(#[from] EntitiesError),
+}
+
+impl TupleMapping {
+    /// A mapping without rules, where each namespace is the entity type of
+    /// the same name
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Objects and subjects in `namespace` are entities of type `entity_type`
+    #[must_use]
+    pub fn with_type(mut self, namespace: impl Into<String>, entity_type: EntityTypeName) -> Self {
+        self.types.insert(namespace.into(), entity_type);
+        self
+    }
+
+    /// `relation` of objects in `namespace` means `rule`
+    #[must_use]
+    pub fn with_rule(
+        mut self,
+        namespace: impl Into<String>,
+        relation: impl Into<String>,
+        rule: RelationRule,
+    ) -> Self {
+        self.rules.insert((namespace.into(), relation.into()), rule);
+        self
+    }
+
+    /// Import `tuples`, returning the entities of their hierarchy, and adding
+    /// template-linked policies to `policies`. The id of each linked policy
+    /// is the tuple which it comes from, e.g., `doc:readme#viewer@user:alice`.
+    ///
+    /// Every object and subject is an entity without attributes. If `schema`
+    /// is given, the entities must conform to it, as with
+    /// [`Entities::from_entities`].
+    pub fn import<'a>(
+        &self,
+        tuples: impl IntoIterator<Item = &'a RelationTuple>,
+        policies: &mut PolicySet,
+        schema: Option<&Schema>,
+    ) -> Result<Entities, RelationTupleError> {
+        // `BTreeMap` so that the entities are constructed in a deterministic
+        // order
+        let mut parents: BTreeMap<String, (EntityUid, HashSet<EntityUid>)> = BTreeMap::new();
+        let mut linked = HashSet::new();
+        for tuple in tuples {
+            let object = self.entity_uid(&tuple.object)?;
+            let subject = self.subject_uid(tuple)?;
+            parents_of(&mut parents, &object);
+            parents_of(&mut parents, &subject);
+            match self.rule(&tuple.object.0, &tuple.relation)? {
+                RelationRule::Member => {
+                    parents_of(&mut parents, &subject).insert(object);
+                }
+                RelationRule::Parent => {
+                    parents_of(&mut parents, &object).insert(subject);
+                }
+                RelationRule::Link(template_id) => {
+                    let id = tuple.to_string();
+                    if !linked.insert(id.clone()) {
+                        continue;
+                    }
+                    let template = policies
+                        .template(template_id)
+                        .ok_or_else(|| RelationTupleError::MissingTemplate(template_id.clone()))?;
+                    let values = template
+                        .slots()
+                        .map(|slot| {
+                            let uid = if *slot == SlotId::principal() {
+                                subject.clone()
+                            } else {
+                                object.clone()
+                            };
+                            (slot.clone(), uid)
+                        })
+                        .collect();
+                    policies.link(template_id.clone(), PolicyId::from_str(&id)?, values)?;
+                }
+            }
+        }
+        let entities = parents
+            .into_values()
+            .map(|(uid, parents)| Entity::new(uid, HashMap::new(), parents))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Entities::from_entities(entities, schema)?)
+    }
+
+    fn rule(&self, namespace: &str, relation: &str) -> Result<&RelationRule, RelationTupleError> {
+        self.rules
+            .get(&(namespace.to_owned(), relation.to_owned()))
+            .ok_or_else(|| RelationTupleError::UnmappedRelation {
+                namespace: namespace.to_owned(),
+                relation: relation.to_owned(),
+            })
+    }
+
+    fn entity_uid(
+        &self,
+        (namespace, id): &(String, String),
+    ) -> Result<EntityUid, RelationTupleError> {
+        let entity_type = match self.types.get(namespace) {
+            Some(entity_type) => entity_type.clone(),
+            None => EntityTypeName::from_str(namespace).map_err(|source| {
+                RelationTupleError::InvalidNamespace {
+                    namespace: namespace.clone(),
+                    source,
+                }
+            })?,
+        };
+        Ok(EntityUid::from_type_name_and_id(
+            entity_type,
+            EntityId::from_raw(id),
+        ))
+    }
+
+    /// The entity of the subject of `tuple`, which for a userset is the
+    /// entity whose members it contains
+    fn subject_uid(&self, tuple: &RelationTuple) -> Result<EntityUid, RelationTupleError> {
+        if let Some(relation) = &tuple.subject_relation {
+            if self.rule(&tuple.subject.0, relation)? != &RelationRule::Member {
+                return Err(RelationTupleError::UnsupportedUserset(format!(
+                    "{}:{}#{relation}",
+                    tuple.subject.0, tuple.subject.1
+                )));
+            }
+        }
+        self.entity_uid(&tuple.subject)
+    }
+}
+
+/// The parents of `uid` in `parents`, adding it without parents if it's not
+/// there yet
+fn parents_of<'a>(
+    parents: &'a mut BTreeMap<String, (EntityUid, HashSet<EntityUid>)>,
+    uid: &EntityUid,
+) -> &'a mut HashSet<EntityUid> {
+    &mut parents
+        .entry(uid.to_string())
+        .or_insert_with(|| (uid.clone(), HashSet::new()))
+        .1
+}
+
+// PANIC SAFETY unit tests
+#[allow(clippy::panic)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Authorizer, Context, Decision, Request, Template};
+    use cool_asserts::assert_matches;
+
+    fn tuples(tuples: &[&str]) -> Vec<RelationTuple> {
+        tuples.iter().map(|t| t.parse().unwrap()).collect()
+    }
+
+    fn mapping() -> TupleMapping {
+        TupleMapping::new()
+            .with_type("user", "User".parse().unwrap())
+            .with_type("group", "Group".parse().unwrap())
+            .with_type("doc", "Doc".parse().unwrap())
+            .with_type("folder", "Folder".parse().unwrap())
+            .with_rule("group", "member", RelationRule::Member)
+            .with_rule("doc", "parent", RelationRule::Parent)
+            .with_rule(
+                "folder",
+                "viewer",
+                RelationRule::Link("viewer".parse().unwrap()),
+            )
+    }
+
+    #[test]
+    fn parse() {
+        let tuple: RelationTuple = "doc:readme#viewer@group:eng#member".parse().unwrap();
+        assert_eq!(tuple.object(), ("doc", "readme"));
+        assert_eq!(tuple.relation(), "viewer");
+        assert_eq!(tuple.subject(), ("group", "eng"));
+        assert_eq!(tuple.subject_relation(), Some("member"));
+        assert_eq!(tuple.to_string(), "doc:readme#viewer@group:eng#member");
+
+        for invalid in [
+            "doc:readme",
+            "doc:readme#viewer",
+            "readme#viewer@user:alice",
+        ] {
+            assert_matches!(
+                invalid.parse::<RelationTuple>(),
+                Err(RelationTupleError::InvalidTuple { .. })
+            );
+        }
+    }
+
+    #[test]
+    fn import() {
+        let mut policies = PolicySet::new();
+        let template = Template::parse(
+            Some("viewer".to_string()),
+            r#"permit(principal in ?principal, action == Action::"view", resource in ?resource);"#,
+        )
+        .unwrap();
+        policies.add_template(template).unwrap();
+        let entities = mapping()
+            .import(
+                &tuples(&[
+                    "group:eng#member@user:alice",
+                    "doc:readme#parent@folder:a",
+                    "folder:a#viewer@group:eng#member",
+                    "folder:a#viewer@group:eng#member",
+                ]),
+                &mut policies,
+                None,
+            )
+            .unwrap();
+        assert_eq!(entities.iter().count(), 4);
+        assert_eq!(policies.policies().count(), 1);
+        assert!(policies
+            .policy(&"folder:a#viewer@group:eng#member".parse().unwrap())
+            .is_some());
+
+        let request = Request::new(
+            Some(r#"User::"alice""#.parse().unwrap()),
+            Some(r#"Action::"view""#.parse().unwrap()),
+            Some(r#"Doc::"readme""#.parse().unwrap()),
+            Context::empty(),
+            None,
+        )
+        .unwrap();
+        let response = Authorizer::new().is_authorized(&request, &policies, &entities);
+        assert_eq!(response.decision(), Decision::Allow);
+    }
+
+    #[test]
+    fn errors() {
+        let mut policies = PolicySet::new();
+        assert_matches!(
+            mapping().import(&tuples(&["doc:readme#owner@user:alice"]), &mut policies, None),
+            Err(RelationTupleError::UnmappedRelation { relation, .. }) => {
+                assert_eq!(relation, "owner");
+            }
+        );
+        assert_matches!(
+            mapping().import(
+                &tuples(&["doc:readme#parent@doc:other#parent"]),
+                &mut policies,
+                None
+            ),
+            Err(RelationTupleError::UnsupportedUserset(_))
+        );
+        assert_matches!(
+            mapping().import(
+                &tuples(&["folder:a#viewer@user:alice"]),
+                &mut policies,
+                None
+            ),
+            Err(RelationTupleError::MissingTemplate(_))
+        );
+    }
+}
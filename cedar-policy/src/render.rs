@@ -0,0 +1,460 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module has the source texts of policies, e.g., the files they were
+//! parsed from, and renders errors as text with the lines of the source
+//! they're on, and carets under the parts of the lines they're about, without
+//! `miette`'s report handlers.
+
+use cedar_policy_core::parser::Loc;
+use miette::{Diagnostic, LabeledSpan, Severity, SourceSpan};
+use std::fmt::{self, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+/// The text of policies and the name of where it's from, e.g., of a file, for
+/// [`PolicySet::from_sources`](crate::PolicySet::from_sources)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicySource {
+    name: String,
+    text: Arc<str>,
+}
+
+impl PolicySource {
+    /// The policies `text`, from where `name` names, e.g., a file
+    pub fn new(name: impl Into<String>, text: impl Into<Arc<str>>) -> Self {
+        Self {
+            name: name.into(),
+            text: text.into(),
+        }
+    }
+
+    /// The policies of the file at `path`, named by the path
+    pub fn read(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        Ok(Self::new(
+            path.display().to_string(),
+            std::fs::read_to_string(path)?,
+        ))
+    }
+
+    /// The name of the source
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The text of the policies
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// A position in a source, e.g., of an error or a policy, which displays as
+/// `name:line:column`, or `line:column` if the source has no name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourcePosition {
+    name: Option<String>,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl SourcePosition {
+    /// The name of the source, if it has one
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The byte offset in the source
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The line, from 1
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The column, from 1, in characters
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl fmt::Display for SourcePosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{name}:{}:{}", self.line, self.column),
+            None => write!(f, "{}:{}", self.line, self.column),
+        }
+    }
+}
+
+/// Where a policy was parsed from: the index of its source in a
+/// [`SourceMap`], and the byte range of its text in the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PolicyOrigin {
+    pub(crate) source: usize,
+    pub(crate) range: Range<usize>,
+}
+
+/// A source text, with its name if it has one
+#[derive(Debug, Clone)]
+struct Source {
+    name: Option<String>,
+    text: Arc<str>,
+}
+
+/// The source texts of policies, e.g., the files they were parsed from, which
+/// [`render_diagnostic`] shows the lines of errors from
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    sources: Vec<Source>,
+}
+
+impl SourceMap {
+    /// An empty source map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A source map of the single source `text` named `name`, e.g., the name
+    /// of the file it's from
+    pub fn from_source(name: impl Into<String>, text: impl Into<Arc<str>>) -> Self {
+        let mut sources = Self::new();
+        sources.add(name, text);
+        sources
+    }
+
+    /// Add the source `text` named `name`
+    pub fn add(&mut self, name: impl Into<String>, text: impl Into<Arc<str>>) {
+        self.push(Some(name.into()), text);
+    }
+
+    /// Add the source `text`, named `name` if it has a name, returning its
+    /// index
+    pub(crate) fn push(&mut self, name: Option<String>, text: impl Into<Arc<str>>) -> usize {
+        self.sources.push(Source {
+            name,
+            text: text.into(),
+        });
+        self.sources.len() - 1
+    }
+
+    /// Add the sources of `other`, returning the index of the first of them
+    pub(crate) fn append(&mut self, other: Self) -> usize {
+        let first = self.sources.len();
+        self.sources.extend(other.sources);
+        first
+    }
+
+    /// Iterate over the sources, as their names and texts
+    pub fn sources(&self) -> impl Iterator<Item = (Option<&str>, &str)> {
+        self.sources
+            .iter()
+            .map(|source| (source.name.as_deref(), &*source.text))
+    }
+
+    /// Whether there are no sources
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// The location of `diagnostic`, i.e., of the first of its labels, in
+    /// the source with the same text as its source code. As for
+    /// [`render_diagnostic`], if `diagnostic` doesn't have source code, this
+    /// is the location in the first source. This is `None` if `diagnostic`
+    /// doesn't have labels.
+    pub fn locate(&self, diagnostic: &(impl Diagnostic + ?Sized)) -> Option<SourcePosition> {
+        let offset = diagnostic
+            .labels()
+            .into_iter()
+            .flatten()
+            .map(|label| label.offset())
+            .min()?;
+        match diagnostic.source_code().and_then(whole_text) {
+            Some(text) => Some(Self::location_in(
+                self.find(&text).and_then(|source| source.name.clone()),
+                &text,
+                offset,
+            )),
+            None => self.location(0, offset),
+        }
+    }
+
+    /// The location of the byte `offset` of the source with index `source`
+    pub(crate) fn location(&self, source: usize, offset: usize) -> Option<SourcePosition> {
+        let source = self.sources.get(source)?;
+        Some(Self::location_in(source.name.clone(), &source.text, offset))
+    }
+
+    /// The byte `range` of the source with index `source`
+    pub(crate) fn loc(&self, source: usize, range: Range<usize>) -> Option<Loc> {
+        let source = self.sources.get(source)?;
+        Some(Loc::new(range, Arc::clone(&source.text)))
+    }
+
+    fn location_in(name: Option<String>, text: &str, offset: usize) -> SourcePosition {
+        let (line, column) = line_and_column(text, offset);
+        SourcePosition {
+            name,
+            offset,
+            line,
+            column,
+        }
+    }
+
+    /// The source with the text `text`, if any
+    fn find(&self, text: &str) -> Option<&Source> {
+        self.sources.iter().find(|source| &*source.text == text)
+    }
+}
+
+/// The whole text of `source`
+fn whole_text(source: &dyn miette::SourceCode) -> Option<String> {
+    // the contents of an empty span at the start with every line after it
+    let contents = source
+        .read_span(&SourceSpan::from(0..0), 0, usize::MAX)
+        .ok()?;
+    String::from_utf8(contents.data().to_vec()).ok()
+}
+
+/// Render `diagnostic`, and its related diagnostics, as text with the lines
+/// its labels are on and carets under the labelled parts of the lines, e.g.,
+/// ```text
+/// error[cedar::parse::unsupported_division]: division is not supported
+///  --> policies.cedar:1:44
+///   |
+/// 1 | permit(principal, action, resource) when { 1 / 2 };
+///   |                                            ^^^^^
+/// ```
+/// The text of the labels is the source code of `diagnostic`, and the name is
+/// that of the source with the same text in `sources`. If `diagnostic`
+/// doesn't have source code, the text is that of the first source of
+/// `sources`.
+pub fn render_diagnostic(diagnostic: &(impl Diagnostic + ?Sized), sources: &SourceMap) -> String {
+    let mut out = String::new();
+    // PANIC SAFETY: writing to a `String` doesn't fail
+    #[allow(clippy::expect_used)]
+    render(&mut out, diagnostic, sources).expect("writing to a string should not fail");
+    for related in diagnostic.related().into_iter().flatten() {
+        out.push('\n');
+        // PANIC SAFETY: writing to a `String` doesn't fail
+        #[allow(clippy::expect_used)]
+        render(&mut out, related, sources).expect("writing to a string should not fail");
+    }
+    out
+}
+
+fn render(
+    out: &mut String,
+    diagnostic: &(impl Diagnostic + ?Sized),
+    sources: &SourceMap,
+) -> fmt::Result {
+    let severity = match diagnostic.severity() {
+        Some(Severity::Warning) => "warning",
+        Some(Severity::Advice) => "advice",
+        Some(Severity::Error) | None => "error",
+    };
+    match diagnostic.code() {
+        Some(code) => writeln!(out, "{severity}[{code}]: {diagnostic}")?,
+        None => writeln!(out, "{severity}: {diagnostic}")?,
+    }
+
+    let (name, text) = match diagnostic.source_code().and_then(whole_text) {
+        Some(text) => (
+            sources.find(&text).and_then(|source| source.name.clone()),
+            Some(text),
+        ),
+        None => match sources.sources.first() {
+            Some(source) => (source.name.clone(), Some(source.text.to_string())),
+            None => (None, None),
+        },
+    };
+    let mut labels: Vec<LabeledSpan> = diagnostic.labels().into_iter().flatten().collect();
+    labels.sort_by_key(LabeledSpan::offset);
+    match text {
+        Some(text) if !labels.is_empty() => render_labels(out, name.as_deref(), &text, &labels)?,
+        _ => {
+            for label in labels.iter().filter_map(LabeledSpan::label) {
+                writeln!(out, "  = {label}")?;
+            }
+        }
+    }
+
+    if let Some(help) = diagnostic.help() {
+        writeln!(out, "  = help: {help}")?;
+    }
+    Ok(())
+}
+
+/// The line number and the column, from 1, of the byte `offset` of `text`
+fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = text
+        .get(..floor_char_boundary(text, offset))
+        .unwrap_or_default();
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let column = before.get(line_start..).unwrap_or_default().chars().count() + 1;
+    (line, column)
+}
+
+/// The largest char boundary of `text` which is at most `offset`
+fn floor_char_boundary(text: &str, offset: usize) -> usize {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+fn render_labels(
+    out: &mut String,
+    name: Option<&str>,
+    text: &str,
+    labels: &[LabeledSpan],
+) -> fmt::Result {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let positions: Vec<_> = labels
+        .iter()
+        .map(|label| line_and_column(text, label.offset()))
+        .collect();
+    let width = positions
+        .iter()
+        .map(|(line, _)| line.to_string().len())
+        .max()
+        .unwrap_or(1);
+    let pad = " ".repeat(width);
+
+    // the first label is the location of the error
+    if let Some((line, column)) = positions.first() {
+        match name {
+            Some(name) => writeln!(out, "{pad}--> {name}:{line}:{column}")?,
+            None => writeln!(out, "{pad}--> {line}:{column}")?,
+        }
+    }
+    writeln!(out, "{pad} |")?;
+    for (label, (line, column)) in labels.iter().zip(positions) {
+        let source_line = lines
+            .get(line - 1)
+            .copied()
+            .unwrap_or_default()
+            .trim_end_matches('\r');
+        // a label spanning several lines is underlined to the end of its
+        // first line
+        let rest = source_line.chars().count().saturating_sub(column - 1);
+        let len = text
+            .get(
+                floor_char_boundary(text, label.offset())
+                    ..floor_char_boundary(text, label.offset() + label.len()),
+            )
+            .unwrap_or_default()
+            .chars()
+            .count()
+            .clamp(1, rest.max(1));
+        writeln!(out, "{line:>width$} | {source_line}")?;
+        write!(out, "{pad} | {}{}", " ".repeat(column - 1), "^".repeat(len))?;
+        match label.label() {
+            Some(label) => writeln!(out, " {label}")?,
+            None => writeln!(out)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Authorizer, Context, Entities, PolicyId, PolicySet, Request, Schema, ValidationMode,
+        Validator,
+    };
+    use std::str::FromStr;
+
+    #[test]
+    fn parse_errors() {
+        let src = "permit(principal, action, resource)\nwhen { 1 / 2 };";
+        let err = PolicySet::from_str(src).unwrap_err();
+        let rendered = render_diagnostic(&err, &SourceMap::from_source("policies.cedar", src));
+        assert!(rendered.starts_with(
+            "error[cedar::parse::unsupported_division]: division is not supported\n \
+             --> policies.cedar:2:8\n  |\n2 | when { 1 / 2 };\n  |        ^"
+        ));
+        // without a name
+        assert!(render_diagnostic(&err, &SourceMap::new()).contains(" --> 2:8\n"));
+    }
+
+    #[test]
+    fn validation_errors() {
+        let src = r#"permit(principal == User::"alice", action, resource);"#;
+        let policies = PolicySet::from_source("policies.cedar", src).unwrap();
+        let schema = Schema::from_json_value(serde_json::json!({ "": {
+            "entityTypes": { "Usr": {} },
+            "actions": {}
+        } }))
+        .unwrap();
+        let result = Validator::new(schema).validate(&policies, ValidationMode::Strict);
+        let err = result.validation_errors().next().unwrap();
+        let rendered = render_diagnostic(err, policies.source_map());
+        assert!(rendered.starts_with(
+            "error[cedar::validation::unrecognized_entity_type]: validation error on policy `policy0`"
+        ));
+        assert!(rendered.contains(" --> policies.cedar:1:1\n"));
+        assert!(rendered.contains(&format!("1 | {src}\n")));
+    }
+
+    #[test]
+    fn multiple_sources() {
+        let policies = PolicySet::from_sources([
+            PolicySource::new("a.cedar", "permit(principal, action, resource);"),
+            PolicySource::new(
+                "b.cedar",
+                "// the second file\nforbid(principal, action, resource) when { 1 + \"a\" == 2 };",
+            ),
+        ])
+        .unwrap();
+        let location = |id: &str| {
+            policies
+                .policy_location(&PolicyId::from_str(id).unwrap())
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(location("policy0"), "a.cedar:1:1");
+        assert_eq!(location("policy1"), "b.cedar:2:1");
+
+        let request = Request::new(None, None, None, Context::empty(), None).unwrap();
+        let response = Authorizer::new().is_authorized(&request, &policies, &Entities::empty());
+        let err = response.diagnostics().errors().next().unwrap();
+        let location = policies.source_map().locate(err).unwrap();
+        assert_eq!(location.name(), Some("b.cedar"));
+        assert_eq!((location.line(), location.column()), (2, 44));
+
+        // the errors of every source
+        let errs = PolicySet::from_sources([
+            PolicySource::new(
+                "a.cedar",
+                "permit(principal, action, resource) when { 1 / 2 };",
+            ),
+            PolicySource::new(
+                "b.cedar",
+                "forbid(principal, action, resource) when { 1 % 2 };",
+            ),
+        ])
+        .unwrap_err();
+        assert_eq!(errs.len(), 2);
+    }
+}
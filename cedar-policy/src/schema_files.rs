@@ -0,0 +1,399 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module contains [`Schema::from_files`], which loads a schema split
+//! across several files, e.g., one per namespace. Files ending in `.json` are
+//! in the JSON schema format, and other files are in the Cedar schema format.
+//! A file can include other files, relative to its own directory:
+//! ```text
+//! include "common.cedarschema";
+//!
+//! namespace PhotoApp {
+//!   entity Photo { owner: Common::User };
+//! }
+//! ```
+//! or, in the JSON schema format,
+//! ```json
+//! { "$include": ["common.cedarschema"], "PhotoApp": { ... } }
+//! ```
+//!
+//! Every file is loaded once, however many files include it, and files which
+//! include each other are an error. The namespaces of the files are merged, so
+//! a namespace can be split across files, and a declaration repeated
+//! identically in several files, e.g., a common type every file needs, is
+//! only declared once. Differing declarations of the same name are an error.
+
+use crate::cedar_schema::{self, SyntaxError, INCLUDE_KEY};
+use crate::{Schema, SchemaError};
+use miette::Diagnostic;
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Errors loading a schema with [`Schema::from_files`]
+#[derive(Debug, Diagnostic, Error)]
+#[non_exhaustive]
+pub enum SchemaFilesError {
+    /// Reading a schema file failed
+    #[error("failed to read schema file `{}`: {source}", .path.display())]
+    Io {
+        /// The file which couldn't be read
+        path: PathBuf,
+        /// The underlying error
+        source: std::io::Error,
+    },
+    /// A file in the JSON schema format isn't a JSON object
+    #[error("failed to parse schema file `{}` as JSON: {source}", .path.display())]
+    Json {
+        /// The file which couldn't be parsed
+        path: PathBuf,
+        /// The underlying error
+        source: serde_json::Error,
+    },
+    /// A file in the Cedar schema format isn't syntactically valid
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Syntax(#[from] SyntaxError),
+    /// The `"$include"` of a file in the JSON schema format isn't an array of
+    /// strings
+    #[error("`$include` of schema file `{}` must be an array of paths", .path.display())]
+    InvalidInclude {
+        /// The file with the invalid include
+        path: PathBuf,
+    },
+    /// Files include each other
+    #[error("schema files include each other: {}", display_cycle(.cycle))]
+    IncludeCycle {
+        /// The files of the cycle, starting and ending with the same file
+        cycle: Vec<PathBuf>,
+    },
+    /// Two files declare the same name differently
+    #[error(
+        "{kind} `{name}` is declared differently in `{}` and `{}`",
+        .first.display(),
+        .second.display()
+    )]
+    ConflictingDeclaration {
+        /// What's declared, e.g., `entity type`
+        kind: &'static str,
+        /// The name which is declared, with its namespace
+        name: String,
+        /// The file with the first declaration
+        first: PathBuf,
+        /// The file with the second declaration
+        second: PathBuf,
+    },
+    /// The merged schema is invalid, e.g., because it references an entity
+    /// type which no file declares
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Schema(#[from] SchemaError),
+}
+
+fn display_cycle(cycle: &[PathBuf]) -> String {
+    cycle
+        .iter()
+        .map(|path| format!("`{}`", path.display()))
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+impl Schema {
+    /// Load a schema from `paths` and the files they include, in either
+    /// format, see comments in the [`schema_files`](crate::schema_files)
+    /// module
+    pub fn from_files(
+        paths: impl IntoIterator<Item = impl AsRef<Path>>,
+    ) -> Result<Self, SchemaFilesError> {
+        let mut loader = Loader::default();
+        for path in paths {
+            loader.load(path.as_ref())?;
+        }
+        Ok(Self::from_json_value(Value::Object(loader.namespaces))?)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Loader {
+    /// Canonical paths of the files which were loaded, or are being loaded
+    loaded: HashSet<PathBuf>,
+    /// Canonical paths of the files being loaded, each included by the one
+    /// before it
+    stack: Vec<PathBuf>,
+    /// The merged namespaces, in the JSON schema format
+    namespaces: Map<String, Value>,
+    /// The file of each declaration, by namespace, section, and name
+    origins: HashMap<(String, String, String), PathBuf>,
+}
+
+impl Loader {
+    fn load(&mut self, path: &Path) -> Result<(), SchemaFilesError> {
+        let io_error = |source| SchemaFilesError::Io {
+            path: path.to_owned(),
+            source,
+        };
+        let canonical = std::fs::canonicalize(path).map_err(io_error)?;
+        if let Some(pos) = self.stack.iter().position(|p| *p == canonical) {
+            let mut cycle = self.stack.split_off(pos);
+            cycle.push(canonical);
+            return Err(SchemaFilesError::IncludeCycle { cycle });
+        }
+        if !self.loaded.insert(canonical.clone()) {
+            return Ok(());
+        }
+        let src = std::fs::read_to_string(path).map_err(io_error)?;
+        let mut json = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str::<Map<String, Value>>(&src).map_err(|source| {
+                SchemaFilesError::Json {
+                    path: path.to_owned(),
+                    source,
+                }
+            })?
+        } else {
+            cedar_schema::parse(&path.display().to_string(), &src)?.0
+        };
+
+        if let Some(includes) = json.remove(INCLUDE_KEY) {
+            let invalid = || SchemaFilesError::InvalidInclude {
+                path: path.to_owned(),
+            };
+            let dir = path.parent().unwrap_or(Path::new(""));
+            self.stack.push(canonical.clone());
+            for include in includes.as_array().ok_or_else(invalid)? {
+                let include = include.as_str().ok_or_else(invalid)?;
+                self.load(&dir.join(include))?;
+            }
+            self.stack.pop();
+        }
+
+        for (namespace, ns_def) in json {
+            let merged = self
+                .namespaces
+                .entry(namespace.clone())
+                .or_insert_with(|| Value::Object(Map::new()));
+            // anything but objects isn't a valid schema, so it's kept for the
+            // merged schema to report
+            match (ns_def, merged) {
+                (Value::Object(sections), Value::Object(merged)) => {
+                    for (section, decls) in sections {
+                        let merged_decls = merged
+                            .entry(section.clone())
+                            .or_insert_with(|| Value::Object(Map::new()));
+                        match (decls, merged_decls) {
+                            (Value::Object(decls), Value::Object(merged_decls)) => {
+                                for (name, decl) in decls {
+                                    let key = (namespace.clone(), section.clone(), name);
+                                    declare(&mut self.origins, key, decl, merged_decls, path)?;
+                                }
+                            }
+                            (decls, merged_decls) => *merged_decls = decls,
+                        }
+                    }
+                }
+                (ns_def, merged) => *merged = ns_def,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Add the declaration of `key`, which is its namespace, section, and name,
+/// from the file at `path` to `merged`, unless it's already there
+fn declare(
+    origins: &mut HashMap<(String, String, String), PathBuf>,
+    key: (String, String, String),
+    decl: Value,
+    merged: &mut Map<String, Value>,
+    path: &Path,
+) -> Result<(), SchemaFilesError> {
+    match merged.get(&key.2) {
+        Some(existing) if *existing == decl => Ok(()),
+        Some(_) => {
+            let (namespace, section, name) = key.clone();
+            Err(SchemaFilesError::ConflictingDeclaration {
+                kind: kind(&section),
+                name: if namespace.is_empty() {
+                    name
+                } else {
+                    format!("{namespace}::{name}")
+                },
+                first: origins.remove(&key).unwrap_or_default(),
+                second: path.to_owned(),
+            })
+        }
+        None => {
+            merged.insert(key.2.clone(), decl);
+            origins.insert(key, path.to_owned());
+            Ok(())
+        }
+    }
+}
+
+/// What the declarations of a section of a namespace are
+fn kind(section: &str) -> &'static str {
+    match section {
+        "commonTypes" => "common type",
+        "entityTypes" => "entity type",
+        "actions" => "action",
+        _ => "declaration",
+    }
+}
+
+// PANIC SAFETY unit tests
+#[allow(clippy::panic)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cool_asserts::assert_matches;
+
+    fn write(dir: &Path, name: &str, src: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, src).unwrap();
+        path
+    }
+
+    #[test]
+    fn includes_across_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "common.cedarschema",
+            "namespace Common { type Name = String; entity User { name: Name }; }",
+        );
+        write(
+            dir.path(),
+            "photos.cedarschema",
+            r#"
+            include "common.cedarschema";
+            namespace PhotoApp {
+                type Name = String;
+                entity Photo { owner: Common::User };
+            }
+            "#,
+        );
+        std::fs::create_dir(dir.path().join("albums")).unwrap();
+        let albums = write(
+            &dir.path().join("albums"),
+            "albums.json",
+            r#"{
+                "$include": ["../common.cedarschema", "../photos.cedarschema"],
+                "PhotoApp": {
+                    "commonTypes": {
+                        "Name": { "type": "String" }
+                    },
+                    "entityTypes": {
+                        "Album": {}
+                    },
+                    "actions": {
+                        "view": {
+                            "appliesTo": {
+                                "principalTypes": ["Common::User"],
+                                "resourceTypes": ["Photo", "Album"]
+                            }
+                        }
+                    }
+                }
+            }"#,
+        );
+        let schema = Schema::from_files([albums]).unwrap();
+        let expected = Schema::from_json_value(serde_json::json!({
+            "Common": {
+                "commonTypes": { "Name": { "type": "String" } },
+                "entityTypes": {
+                    "User": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": { "name": { "type": "String" } }
+                        }
+                    }
+                },
+                "actions": {}
+            },
+            "PhotoApp": {
+                "entityTypes": {
+                    "Photo": {
+                        "shape": {
+                            "type": "Record",
+                            "attributes": {
+                                "owner": { "type": "Entity", "name": "Common::User" }
+                            }
+                        }
+                    },
+                    "Album": {}
+                },
+                "actions": {
+                    "view": {
+                        "appliesTo": {
+                            "principalTypes": ["Common::User"],
+                            "resourceTypes": ["Photo", "Album"]
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        assert_eq!(schema.fingerprint(), expected.fingerprint());
+    }
+
+    #[test]
+    fn include_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = write(
+            dir.path(),
+            "a.cedarschema",
+            r#"include "b.json"; entity A;"#,
+        );
+        write(dir.path(), "b.json", r#"{ "$include": ["a.cedarschema"] }"#);
+        assert_matches!(
+            Schema::from_files([&a]),
+            Err(SchemaFilesError::IncludeCycle { cycle }) => {
+                assert_eq!(cycle.len(), 3);
+                assert_eq!(cycle.first(), cycle.last());
+            }
+        );
+    }
+
+    #[test]
+    fn conflicting_declarations() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = write(dir.path(), "a.cedarschema", "type Name = String;");
+        let b = write(dir.path(), "b.cedarschema", "type Name = Long;");
+        assert_matches!(
+            Schema::from_files([&a, &b]),
+            Err(SchemaFilesError::ConflictingDeclaration { kind: "common type", name, first, second }) => {
+                assert_eq!(name, "Name");
+                assert_eq!(first, a);
+                assert_eq!(second, b);
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_include() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = write(dir.path(), "a.json", r#"{ "$include": "b.json" }"#);
+        assert_matches!(
+            Schema::from_files([a]),
+            Err(SchemaFilesError::InvalidInclude { .. })
+        );
+        let missing = dir.path().join("missing.cedarschema");
+        assert_matches!(
+            Schema::from_files([missing]),
+            Err(SchemaFilesError::Io { .. })
+        );
+    }
+}
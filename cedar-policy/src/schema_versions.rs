@@ -0,0 +1,222 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module validates a policy set against several versions of a schema
+//! at once, e.g., the versions deployed to each environment during a rolling
+//! schema upgrade, and reports which policies fail to validate against which
+//! versions.
+//! ```ignore
+//! let versions = [
+//!     SchemaVersion::new("prod", prod_schema),
+//!     SchemaVersion::new("staging", staging_schema),
+//! ];
+//! let result = schema_versions::validate(&policies, &versions, ValidationMode::default());
+//! for (policy, versions) in result.failures() {
+//!     println!("{policy} fails to validate against {}", versions.join(", "));
+//! }
+//! ```
+//!
+//! The result against each version is the same as that of
+//! [`Validator::validate`] with the schema of the version, so the errors and
+//! warnings of a version are available too.
+
+use crate::{PolicyId, PolicySet, Schema, ValidationMode, ValidationResult, Validator};
+
+/// A named version of a schema
+#[derive(Debug)]
+pub struct SchemaVersion {
+    name: String,
+    validator: Validator,
+}
+
+impl SchemaVersion {
+    /// A version named `name`, e.g., the environment it's deployed to
+    pub fn new(name: impl Into<String>, schema: Schema) -> Self {
+        Self {
+            name: name.into(),
+            validator: Validator::new(schema),
+        }
+    }
+
+    /// The name of the version
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The results of validating a policy set against each of several versions
+/// of a schema
+#[derive(Debug)]
+pub struct VersionsValidationResult {
+    results: Vec<(String, ValidationResult<'static>)>,
+}
+
+impl VersionsValidationResult {
+    /// True when every policy validates against every version. There may be
+    /// warnings.
+    pub fn validation_passed(&self) -> bool {
+        self.results
+            .iter()
+            .all(|(_, result)| result.validation_passed())
+    }
+
+    /// The names of the versions, in the order they were validated against
+    pub fn versions(&self) -> impl Iterator<Item = &str> {
+        self.results.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// The result of validating against the version named `version`, if
+    /// there is one
+    pub fn result(&self, version: &str) -> Option<&ValidationResult<'static>> {
+        self.results
+            .iter()
+            .find(|(name, _)| name == version)
+            .map(|(_, result)| result)
+    }
+
+    /// The policies which fail to validate against the version named
+    /// `version`, each once, ordered by id
+    pub fn failing_policies(&self, version: &str) -> Vec<&PolicyId> {
+        let mut policies: Vec<&PolicyId> = self
+            .result(version)
+            .into_iter()
+            .flat_map(|result| result.validation_errors())
+            .map(|err| err.location().policy_id())
+            .collect();
+        policies.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+        policies.dedup();
+        policies
+    }
+
+    /// The names of the versions which `policy` fails to validate against,
+    /// in the order they were validated against
+    pub fn failing_versions(&self, policy: &PolicyId) -> Vec<&str> {
+        self.results
+            .iter()
+            .filter(|(_, result)| {
+                result
+                    .validation_errors()
+                    .any(|err| err.location().policy_id() == policy)
+            })
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Each policy which fails to validate against some version, ordered by
+    /// id, with the names of the versions it fails to validate against
+    pub fn failures(&self) -> Vec<(&PolicyId, Vec<&str>)> {
+        let mut policies: Vec<&PolicyId> = self
+            .results
+            .iter()
+            .flat_map(|(_, result)| result.validation_errors())
+            .map(|err| err.location().policy_id())
+            .collect();
+        policies.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+        policies.dedup();
+        policies
+            .into_iter()
+            .map(|policy| (policy, self.failing_versions(policy)))
+            .collect()
+    }
+}
+
+/// Validate `policies` against each of `versions` in `mode`
+pub fn validate(
+    policies: &PolicySet,
+    versions: &[SchemaVersion],
+    mode: ValidationMode,
+) -> VersionsValidationResult {
+    VersionsValidationResult {
+        results: versions
+            .iter()
+            .map(|version| {
+                (
+                    version.name.clone(),
+                    version.validator.validate(policies, mode),
+                )
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    fn schema(user_attrs: &str) -> Schema {
+        Schema::from_str(&format!(
+            r#"{{ "": {{
+                "entityTypes": {{
+                    "User": {{
+                        "shape": {{ "type": "Record", "attributes": {user_attrs} }}
+                    }},
+                    "Photo": {{}}
+                }},
+                "actions": {{
+                    "view": {{
+                        "appliesTo": {{ "principalTypes": ["User"], "resourceTypes": ["Photo"] }}
+                    }}
+                }}
+            }} }}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn failures_by_version() {
+        let policies = PolicySet::from_str(
+            r#"
+            permit(principal, action == Action::"view", resource) when { principal.admin };
+            permit(principal, action == Action::"view", resource) when { principal.level > 2 };
+            permit(principal, action == Action::"view", resource);
+            "#,
+        )
+        .unwrap();
+        let versions = [
+            SchemaVersion::new(
+                "v1",
+                schema(r#"{ "admin": { "type": "Boolean" }, "level": { "type": "Long" } }"#),
+            ),
+            SchemaVersion::new("v2", schema(r#"{ "level": { "type": "Long" } }"#)),
+            SchemaVersion::new("v3", schema(r#"{ "level": { "type": "String" } }"#)),
+        ];
+        let result = validate(&policies, &versions, ValidationMode::default());
+
+        assert!(!result.validation_passed());
+        assert_eq!(result.versions().collect::<Vec<_>>(), ["v1", "v2", "v3"]);
+        assert!(result.result("v1").unwrap().validation_passed());
+        assert!(result.result("v4").is_none());
+
+        let admin = PolicyId::from_str("policy0").unwrap();
+        let level = PolicyId::from_str("policy1").unwrap();
+        let any = PolicyId::from_str("policy2").unwrap();
+        assert!(result.failing_policies("v1").is_empty());
+        assert_eq!(result.failing_policies("v2"), [&admin]);
+        assert_eq!(result.failing_policies("v3"), [&admin, &level]);
+        assert_eq!(result.failing_versions(&admin), ["v2", "v3"]);
+        assert_eq!(result.failing_versions(&level), ["v3"]);
+        assert!(result.failing_versions(&any).is_empty());
+        assert_eq!(
+            result.failures(),
+            [(&admin, vec!["v2", "v3"]), (&level, vec!["v3"])]
+        );
+
+        let result = validate(&policies, &versions[..1], ValidationMode::default());
+        assert!(result.validation_passed());
+        assert!(result.failures().is_empty());
+    }
+}
@@ -0,0 +1,325 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module contains [`ScopedPolicyStore`], which attaches policy sets to
+//! the containers of a resource hierarchy, e.g., organizations, folders, and
+//! projects, and authorizes a request against the policies attached to its
+//! resource and the resource's ancestors.
+//! ```ignore
+//! let mut store = ScopedPolicyStore::new();
+//! store.insert(r#"Org::"acme""#.parse()?, org_policies)?;
+//! store.insert(r#"Folder::"finance""#.parse()?, finance_policies)?;
+//! // authorized against the policies of `Org::"acme"` and `Folder::"finance"`
+//! // if the resource is in both
+//! let response = authorizer.is_authorized_scoped(&request, &store, &entities);
+//! ```
+//!
+//! The policy ids of all the policy sets in a store are distinct, so they can
+//! be united without renaming, and the diagnostics of a response identify the
+//! policies. The union for each combination of containers is cached until the
+//! store is modified.
+
+use crate::{
+    Authorizer, Entities, EntityUid, PolicyId, PolicySet, PolicySetError, Request, Response,
+};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// Policy sets attached to the containers of a resource hierarchy
+#[derive(Debug, Default)]
+pub struct ScopedPolicyStore {
+    nodes: BTreeMap<EntityUid, PolicySet>,
+    /// The union of the policy sets of each combination of containers which
+    /// was asked for
+    cache: Mutex<BTreeMap<BTreeSet<EntityUid>, Arc<PolicySet>>>,
+}
+
+impl ScopedPolicyStore {
+    /// An empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attach `policies` to the container `node`, replacing and returning the
+    /// policies attached to it before, if any. Fails if an id of `policies` is
+    /// used by the policies of another container.
+    pub fn insert(
+        &mut self,
+        node: EntityUid,
+        policies: PolicySet,
+    ) -> Result<Option<PolicySet>, PolicySetError> {
+        let ids = policy_ids(&policies);
+        for (other, other_policies) in &self.nodes {
+            if other == &node {
+                continue;
+            }
+            if let Some(id) = policy_ids(other_policies)
+                .intersection(&ids)
+                .min_by(|a, b| a.as_ref().cmp(b.as_ref()))
+            {
+                return Err(PolicySetError::AlreadyDefined { id: (*id).clone() });
+            }
+        }
+        self.clear_cache();
+        Ok(self.nodes.insert(node, policies))
+    }
+
+    /// Detach and return the policies attached to the container `node`
+    pub fn remove(&mut self, node: &EntityUid) -> Option<PolicySet> {
+        self.clear_cache();
+        self.nodes.remove(node)
+    }
+
+    /// The policies attached to the container `node`
+    pub fn get(&self, node: &EntityUid) -> Option<&PolicySet> {
+        self.nodes.get(node)
+    }
+
+    /// The containers with policies attached, and their policies
+    pub fn iter(&self) -> impl Iterator<Item = (&EntityUid, &PolicySet)> {
+        self.nodes.iter()
+    }
+
+    /// The union of the policies attached to `resource` and to its ancestors
+    /// in `entities`
+    pub fn policies_for(&self, resource: &EntityUid, entities: &Entities) -> Arc<PolicySet> {
+        let nodes: BTreeSet<EntityUid> = std::iter::once(resource)
+            .chain(entities.ancestors(resource).into_iter().flatten())
+            .filter(|node| self.nodes.contains_key(node))
+            .cloned()
+            .collect();
+        // a poisoned cache is still consistent, as it's only inserted into
+        let mut cache = self
+            .cache
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if let Some(policies) = cache.get(&nodes) {
+            return Arc::clone(policies);
+        }
+        let policies = Arc::new(self.union(&nodes));
+        cache.insert(nodes, Arc::clone(&policies));
+        policies
+    }
+
+    /// The union of the policies attached to `nodes`
+    // PANIC SAFETY: ids are distinct across the store, and links are of templates of the same set
+    #[allow(clippy::expect_used)]
+    fn union(&self, nodes: &BTreeSet<EntityUid>) -> PolicySet {
+        let mut union = PolicySet::new();
+        let sets = nodes.iter().filter_map(|node| self.nodes.get(node));
+        for policies in sets {
+            for template in policies.templates() {
+                union
+                    .add_template(template.clone())
+                    .expect("template ids should be distinct");
+            }
+            for policy in policies.policies() {
+                match (policy.template_id(), policy.template_links()) {
+                    (Some(template), Some(values)) => union
+                        .link(template.clone(), policy.id().clone(), values)
+                        .expect("link should be valid"),
+                    _ => union
+                        .add(policy.clone())
+                        .expect("policy ids should be distinct"),
+                }
+            }
+        }
+        union
+    }
+
+    fn clear_cache(&mut self) {
+        self.cache
+            .get_mut()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clear();
+    }
+}
+
+/// The ids of the policies and templates of `policies`
+fn policy_ids(policies: &PolicySet) -> HashSet<&PolicyId> {
+    policies
+        .policies()
+        .map(crate::Policy::id)
+        .chain(policies.templates().map(crate::Template::id))
+        .collect()
+}
+
+impl Authorizer {
+    /// Authorize `r` against the policies of `store` attached to the
+    /// resource of `r` and its ancestors in `e`. A request whose resource is
+    /// unknown is authorized against no policies, so it's denied.
+    pub fn is_authorized_scoped(
+        &self,
+        r: &Request,
+        store: &ScopedPolicyStore,
+        e: &Entities,
+    ) -> Response {
+        let policies = r
+            .resource()
+            .map(|resource| store.policies_for(resource, e))
+            .unwrap_or_default();
+        self.is_authorized(r, &policies, e)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Context, Decision, Policy};
+    use cool_asserts::assert_matches;
+    use std::str::FromStr;
+
+    fn uid(s: &str) -> EntityUid {
+        EntityUid::from_str(s).unwrap()
+    }
+
+    fn policies(policies: &[(&str, &str)]) -> PolicySet {
+        PolicySet::from_policies(
+            policies
+                .iter()
+                .map(|(id, src)| Policy::parse(Some((*id).to_string()), src).unwrap()),
+        )
+        .unwrap()
+    }
+
+    fn store() -> ScopedPolicyStore {
+        let mut store = ScopedPolicyStore::new();
+        store
+            .insert(
+                uid(r#"Org::"acme""#),
+                policies(&[(
+                    "org",
+                    r#"forbid(principal == User::"mallory", action, resource);"#,
+                )]),
+            )
+            .unwrap();
+        store
+            .insert(
+                uid(r#"Folder::"finance""#),
+                policies(&[(
+                    "finance",
+                    r#"permit(principal in Group::"finance", action, resource);"#,
+                )]),
+            )
+            .unwrap();
+        store
+            .insert(
+                uid(r#"Folder::"eng""#),
+                policies(&[(
+                    "eng",
+                    r#"permit(principal == User::"bob", action, resource);"#,
+                )]),
+            )
+            .unwrap();
+        store
+    }
+
+    fn entities() -> Entities {
+        Entities::from_json_value(
+            serde_json::json!([
+                { "uid": { "type": "Photo", "id": "budget" }, "attrs": {},
+                  "parents": [{ "type": "Folder", "id": "finance" }] },
+                { "uid": { "type": "Folder", "id": "finance" }, "attrs": {},
+                  "parents": [{ "type": "Org", "id": "acme" }] },
+                { "uid": { "type": "Folder", "id": "eng" }, "attrs": {},
+                  "parents": [{ "type": "Org", "id": "acme" }] },
+                { "uid": { "type": "Org", "id": "acme" }, "attrs": {}, "parents": [] },
+                { "uid": { "type": "User", "id": "alice" }, "attrs": {},
+                  "parents": [{ "type": "Group", "id": "finance" }] },
+                { "uid": { "type": "User", "id": "mallory" }, "attrs": {},
+                  "parents": [{ "type": "Group", "id": "finance" }] },
+            ]),
+            None,
+        )
+        .unwrap()
+    }
+
+    fn request(principal: &str) -> Request {
+        Request::new(
+            Some(uid(principal)),
+            Some(uid(r#"Action::"view""#)),
+            Some(uid(r#"Photo::"budget""#)),
+            Context::empty(),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn authorizes_along_ancestors() {
+        let store = store();
+        let authorizer = Authorizer::new();
+        let decide = |principal: &str| {
+            authorizer
+                .is_authorized_scoped(&request(principal), &store, &entities())
+                .decision()
+        };
+        assert_eq!(decide(r#"User::"alice""#), Decision::Allow);
+        assert_eq!(decide(r#"User::"mallory""#), Decision::Deny);
+        // `eng` isn't an ancestor of the resource
+        assert_eq!(decide(r#"User::"bob""#), Decision::Deny);
+
+        let budget = uid(r#"Photo::"budget""#);
+        let united = store.policies_for(&budget, &entities());
+        let mut ids: Vec<&str> = united.policies().map(|p| p.id().as_ref()).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, ["finance", "org"]);
+        assert!(Arc::ptr_eq(
+            &united,
+            &store.policies_for(&budget, &entities())
+        ));
+    }
+
+    #[test]
+    fn modifications() {
+        let mut store = store();
+        let budget = uid(r#"Photo::"budget""#);
+        let before = store.policies_for(&budget, &entities());
+        assert_matches!(
+            store.insert(
+                uid(r#"Folder::"eng""#),
+                policies(&[("org", r#"permit(principal, action, resource);"#)]),
+            ),
+            Err(PolicySetError::AlreadyDefined { id }) => {
+                assert_eq!(id.as_ref(), "org");
+            }
+        );
+
+        // replacing the policies of a container can reuse their ids
+        let replaced = store
+            .insert(
+                uid(r#"Folder::"finance""#),
+                policies(&[("finance", r#"permit(principal, action, resource);"#)]),
+            )
+            .unwrap();
+        assert!(replaced.is_some());
+        let after = store.policies_for(&budget, &entities());
+        assert!(!Arc::ptr_eq(&before, &after));
+        let authorizer = Authorizer::new();
+        assert_eq!(
+            authorizer
+                .is_authorized_scoped(&request(r#"User::"bob""#), &store, &entities())
+                .decision(),
+            Decision::Allow
+        );
+
+        assert!(store.remove(&uid(r#"Folder::"finance""#)).is_some());
+        assert_eq!(
+            store.policies_for(&budget, &entities()).policies().count(),
+            1
+        );
+    }
+}
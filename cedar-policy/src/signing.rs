@@ -0,0 +1,516 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module signs policy sets, with an optional schema, so they can be
+//! distributed as artifacts and verified where they're used. Signing and
+//! verifying are pluggable: implement [`Signer`] and [`Verifier`] with the
+//! cryptography library of your choice.
+//! ```ignore
+//! let signed = SignedPolicySet::sign(policies, Some(schema_json), &signer)?;
+//! std::fs::write("policies.signed", signed.to_bytes())?;
+//!
+//! let bytes = std::fs::read("policies.signed")?;
+//! let signed = SignedPolicySet::from_bytes(&bytes, verifier.as_ref())?;
+//! let authorizer = Authorizer::new().with_verifier(verifier);
+//! let response = authorizer.is_authorized_signed(&request, &signed, &entities)?;
+//! ```
+//!
+//! What's signed is the [canonical encoding](canonical_bytes) of the policy
+//! set and schema, which is the same for equal policy sets and schemas
+//! however they were written, e.g., with different whitespace, comments, or
+//! order of policies. It isn't stable across versions of this crate, though:
+//! policies are normalized by printing their ASTs, and how they're printed
+//! may change. Artifacts written by [`SignedPolicySet::to_bytes`] carry the
+//! encoding they were signed over, so they keep verifying after an upgrade,
+//! but signatures of [`canonical_bytes`] computed elsewhere must be
+//! recomputed with the new version.
+
+use crate::{
+    Authorizer, Entities, EntityUid, ParseErrors, Policy, PolicyId, PolicySet, PolicySetError,
+    Request, Response, SlotId, Template,
+};
+use miette::Diagnostic;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The version of the canonical encoding
+const VERSION: u32 = 1;
+
+/// Signs the canonical encoding of policy sets
+pub trait Signer {
+    /// Errors signing
+    type Error;
+
+    /// The signature of `message`
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Self::Error>;
+}
+
+/// Verifies the signatures of the canonical encoding of policy sets
+pub trait Verifier: Send + Sync {
+    /// Whether `signature` is a valid signature of `message`
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool;
+}
+
+/// Errors verifying or decoding a [`SignedPolicySet`]
+#[derive(Debug, Diagnostic, Error)]
+#[non_exhaustive]
+pub enum SignatureError {
+    /// The signature is not valid for the policies and schema
+    #[error("invalid signature")]
+    Invalid,
+    /// The artifact is not a signed policy set
+    #[error("malformed signed policy set: {0}")]
+    Malformed(String),
+    /// A policy of the artifact doesn't parse
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Parse(#[from] ParseErrors),
+    /// The policies of the artifact don't form a policy set
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    PolicySet(#[from] PolicySetError),
+}
+
+/// The canonical encoding of `policies` and `schema`, a schema in the JSON
+/// format: a JSON document with no whitespace, whose object keys are sorted,
+/// and which has the policies and templates as text normalized from their
+/// ASTs, so it doesn't depend on how they were written. The normalized text
+/// may differ between versions of this crate.
+pub fn canonical_bytes(policies: &PolicySet, schema: Option<&serde_json::Value>) -> Vec<u8> {
+    let mut document = Document {
+        version: VERSION,
+        policies: BTreeMap::new(),
+        templates: BTreeMap::new(),
+        links: BTreeMap::new(),
+        schema: schema.cloned(),
+    };
+    for template in policies.ast.templates() {
+        document
+            .templates
+            .insert(template.id().to_string(), template.to_string());
+    }
+    for policy in policies.ast.policies() {
+        if policy.is_static() {
+            document
+                .policies
+                .insert(policy.id().to_string(), policy.template().to_string());
+        } else {
+            let link = Link {
+                template: policy.template().id().to_string(),
+                values: policy
+                    .env()
+                    .iter()
+                    .map(|(slot, euid)| (slot.to_string(), euid.to_string()))
+                    .collect(),
+            };
+            document.links.insert(policy.id().to_string(), link);
+        }
+    }
+    // PANIC SAFETY: the document has only string keys, so it serializes
+    #[allow(clippy::expect_used)]
+    let value = serde_json::to_value(document).expect("document should serialize");
+    // PANIC SAFETY: a `serde_json::Value` serializes
+    #[allow(clippy::expect_used)]
+    serde_json::to_vec(&sorted(value)).expect("value should serialize")
+}
+
+/// `value` with the keys of every object sorted. `serde_json` is built with
+/// `preserve_order` in this workspace, so its objects keep insertion order.
+fn sorted(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let sorted: BTreeMap<String, serde_json::Value> =
+                map.into_iter().map(|(k, v)| (k, sorted(v))).collect();
+            serde_json::Value::Object(sorted.into_iter().collect())
+        }
+        serde_json::Value::Array(values) => {
+            serde_json::Value::Array(values.into_iter().map(sorted).collect())
+        }
+        value => value,
+    }
+}
+
+/// The canonical encoding, before its keys are sorted
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Document {
+    version: u32,
+    policies: BTreeMap<String, String>,
+    templates: BTreeMap<String, String>,
+    links: BTreeMap<String, Link>,
+    schema: Option<serde_json::Value>,
+}
+
+/// A template-linked policy in the canonical encoding
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Link {
+    template: String,
+    values: BTreeMap<String, String>,
+}
+
+/// The signed artifact, as written by [`SignedPolicySet::to_bytes`]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Artifact {
+    /// The canonical encoding, as a string so that it's verified as signed
+    payload: String,
+    /// The signature, in hex
+    signature: String,
+}
+
+/// A policy set and optional schema, with the signature of their
+/// [canonical encoding](canonical_bytes)
+#[derive(Debug, Clone)]
+pub struct SignedPolicySet {
+    policies: PolicySet,
+    schema: Option<serde_json::Value>,
+    /// The canonical encoding of `policies` and `schema`
+    payload: String,
+    signature: Vec<u8>,
+}
+
+impl SignedPolicySet {
+    /// Sign `policies` and `schema`, a schema in the JSON format, with
+    /// `signer`
+    pub fn sign<S: Signer>(
+        policies: PolicySet,
+        schema: Option<serde_json::Value>,
+        signer: &S,
+    ) -> Result<Self, S::Error> {
+        let payload = canonical_bytes(&policies, schema.as_ref());
+        let signature = signer.sign(&payload)?;
+        // PANIC SAFETY: `serde_json` writes UTF-8
+        #[allow(clippy::expect_used)]
+        let payload = String::from_utf8(payload).expect("canonical encoding should be UTF-8");
+        Ok(Self {
+            policies,
+            schema,
+            payload,
+            signature,
+        })
+    }
+
+    /// Decode a signed policy set written by [`SignedPolicySet::to_bytes`],
+    /// verifying its signature with `verifier` before its policies are parsed
+    pub fn from_bytes(bytes: &[u8], verifier: &dyn Verifier) -> Result<Self, SignatureError> {
+        let artifact: Artifact =
+            serde_json::from_slice(bytes).map_err(|e| SignatureError::Malformed(e.to_string()))?;
+        let signature = from_hex(&artifact.signature)
+            .ok_or_else(|| SignatureError::Malformed("signature is not hex".into()))?;
+        if !verifier.verify(artifact.payload.as_bytes(), &signature) {
+            return Err(SignatureError::Invalid);
+        }
+        let document: Document = serde_json::from_str(&artifact.payload)
+            .map_err(|e| SignatureError::Malformed(e.to_string()))?;
+        if document.version != VERSION {
+            return Err(SignatureError::Malformed(format!(
+                "unsupported version {}",
+                document.version
+            )));
+        }
+        let mut policies = PolicySet::new();
+        for (id, src) in document.templates {
+            policies.add_template(Template::parse(Some(id), src)?)?;
+        }
+        for (id, src) in document.policies {
+            policies.add(Policy::parse(Some(id), src)?)?;
+        }
+        for (id, link) in document.links {
+            let values = link
+                .values
+                .into_iter()
+                .map(|(slot, euid)| Ok((parse_slot(&slot)?, EntityUid::from_str(&euid)?)))
+                .collect::<Result<HashMap<_, _>, SignatureError>>()?;
+            policies.link(
+                PolicyId::from_str(&link.template)?,
+                PolicyId::from_str(&id)?,
+                values,
+            )?;
+        }
+        Ok(Self {
+            policies,
+            schema: document.schema,
+            payload: artifact.payload,
+            signature,
+        })
+    }
+
+    /// The signed artifact, which [`SignedPolicySet::from_bytes`] decodes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let artifact = Artifact {
+            payload: self.payload.clone(),
+            signature: to_hex(&self.signature),
+        };
+        // PANIC SAFETY: the artifact has only strings
+        #[allow(clippy::expect_used)]
+        serde_json::to_vec(&artifact).expect("artifact should serialize")
+    }
+
+    /// Check the signature with `verifier`
+    pub fn verify(&self, verifier: &dyn Verifier) -> Result<(), SignatureError> {
+        if verifier.verify(self.payload.as_bytes(), &self.signature) {
+            Ok(())
+        } else {
+            Err(SignatureError::Invalid)
+        }
+    }
+
+    /// The policies
+    pub fn policies(&self) -> &PolicySet {
+        &self.policies
+    }
+
+    /// The schema, in the JSON format, if one was signed
+    pub fn schema(&self) -> Option<&serde_json::Value> {
+        self.schema.as_ref()
+    }
+
+    /// The signature
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+}
+
+fn parse_slot(slot: &str) -> Result<SlotId, SignatureError> {
+    match slot {
+        "?principal" => Ok(SlotId::principal()),
+        "?resource" => Ok(SlotId::resource()),
+        _ => Err(SignatureError::Malformed(format!("unknown slot `{slot}`"))),
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        // writing to a `String` doesn't fail
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+impl Authorizer {
+    /// Authorize `r` against the policies of `p` with the entities `e`,
+    /// after checking the signature of `p` with the verifier of this
+    /// `Authorizer`, if it has one. The signature is checked on every call,
+    /// which is one call of [`Verifier::verify`].
+    pub fn is_authorized_signed(
+        &self,
+        r: &Request,
+        p: &SignedPolicySet,
+        e: &Entities,
+    ) -> Result<Response, SignatureError> {
+        if let Some(verifier) = &self.verifier {
+            p.verify(verifier.as_ref())?;
+        }
+        Ok(self.authorize(r, &p.policies, e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{AuthorizationError, Context, Decision};
+    use cool_asserts::assert_matches;
+    use std::convert::Infallible;
+    use std::sync::Arc;
+
+    /// A toy keyed checksum, standing in for a real signature scheme
+    struct Checksum(u8);
+
+    impl Checksum {
+        fn of(&self, message: &[u8]) -> Vec<u8> {
+            let sum = message
+                .iter()
+                .fold(self.0, |sum, byte| sum.rotate_left(3) ^ byte);
+            vec![sum, self.0]
+        }
+    }
+
+    impl Signer for Checksum {
+        type Error = Infallible;
+
+        fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Infallible> {
+            Ok(self.of(message))
+        }
+    }
+
+    impl Verifier for Checksum {
+        fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+            self.of(message) == signature
+        }
+    }
+
+    fn policies(src: &str) -> PolicySet {
+        let mut policies = PolicySet::new();
+        let mut templates = Vec::new();
+        for (i, src) in src.split(';').filter(|s| !s.trim().is_empty()).enumerate() {
+            let src = format!("{src};");
+            let id = format!("p{i}");
+            match Policy::parse(Some(id.clone()), &src) {
+                Ok(policy) => policies.add(policy).unwrap(),
+                Err(_) => {
+                    policies
+                        .add_template(Template::parse(Some(id.clone()), &src).unwrap())
+                        .unwrap();
+                    templates.push(id);
+                }
+            }
+        }
+        for template in templates {
+            policies
+                .link(
+                    PolicyId::from_str(&template).unwrap(),
+                    PolicyId::from_str(&format!("{template}-alice")).unwrap(),
+                    HashMap::from([(
+                        SlotId::principal(),
+                        EntityUid::from_str(r#"User::"alice""#).unwrap(),
+                    )]),
+                )
+                .unwrap();
+        }
+        policies
+    }
+
+    #[test]
+    fn canonical() {
+        let a = policies(
+            r#"permit(principal == ?principal, action, resource);
+            forbid(principal, action, resource) when { context.risk > 3 };"#,
+        );
+        let b = policies(
+            r#"permit(principal==?principal,action,resource) ;
+            // the same policies, written differently
+            forbid (principal, action, resource) when { (context.risk) > 3 };"#,
+        );
+        let schema = serde_json::json!({ "": { "entityTypes": {}, "actions": {} } });
+        let reordered = serde_json::json!({ "": { "actions": {}, "entityTypes": {} } });
+        assert_eq!(
+            canonical_bytes(&a, Some(&schema)),
+            canonical_bytes(&b, Some(&reordered))
+        );
+        assert_ne!(
+            canonical_bytes(&a, Some(&schema)),
+            canonical_bytes(&a, None)
+        );
+        let c = policies(r#"permit(principal == ?principal, action, resource);"#);
+        assert_ne!(canonical_bytes(&a, None), canonical_bytes(&c, None));
+    }
+
+    #[test]
+    fn round_trip() {
+        let original = policies(
+            r#"permit(principal == ?principal, action, resource);
+            @reason("risky") forbid(principal, action, resource) when { context.risk > 3 };"#,
+        );
+        let schema = serde_json::json!({ "": { "entityTypes": {}, "actions": {} } });
+        let signed =
+            SignedPolicySet::sign(original.clone(), Some(schema.clone()), &Checksum(7)).unwrap();
+        let bytes = signed.to_bytes();
+
+        let decoded = SignedPolicySet::from_bytes(&bytes, &Checksum(7)).unwrap();
+        let mut ids: Vec<&str> = decoded
+            .policies()
+            .policies()
+            .map(|p| p.id().as_ref())
+            .collect();
+        ids.sort_unstable();
+        assert_eq!(ids, ["p0-alice", "p1"]);
+        assert_eq!(decoded.schema(), Some(&schema));
+        assert_eq!(decoded.signature(), signed.signature());
+        assert_eq!(
+            canonical_bytes(decoded.policies(), decoded.schema()),
+            canonical_bytes(&original, Some(&schema))
+        );
+
+        assert_matches!(
+            SignedPolicySet::from_bytes(&bytes, &Checksum(8)),
+            Err(SignatureError::Invalid)
+        );
+        // `context.risk > 3` is stored as `!(context.risk <= 3)`
+        let text = String::from_utf8(bytes).unwrap();
+        let tampered = text.replace("<= 3", "<= 4");
+        assert_ne!(tampered, text);
+        assert_matches!(
+            SignedPolicySet::from_bytes(tampered.as_bytes(), &Checksum(7)),
+            Err(SignatureError::Invalid)
+        );
+        assert_matches!(
+            SignedPolicySet::from_bytes(b"{}", &Checksum(7)),
+            Err(SignatureError::Malformed(_))
+        );
+    }
+
+    #[test]
+    fn authorizer_refuses() {
+        let request = Request::new(
+            Some(EntityUid::from_str(r#"User::"alice""#).unwrap()),
+            Some(EntityUid::from_str(r#"Action::"view""#).unwrap()),
+            Some(EntityUid::from_str(r#"Photo::"a""#).unwrap()),
+            Context::empty(),
+            None,
+        )
+        .unwrap();
+        let policies = policies(r#"permit(principal, action, resource);"#);
+        let signed = SignedPolicySet::sign(policies.clone(), None, &Checksum(7)).unwrap();
+        let forged = SignedPolicySet::sign(policies.clone(), None, &Checksum(8)).unwrap();
+        let entities = Entities::empty();
+
+        let authorizer = Authorizer::new().with_verifier(Arc::new(Checksum(7)));
+        assert_eq!(
+            authorizer
+                .is_authorized_signed(&request, &signed, &entities)
+                .unwrap()
+                .decision(),
+            Decision::Allow
+        );
+        assert_matches!(
+            authorizer.is_authorized_signed(&request, &forged, &entities),
+            Err(SignatureError::Invalid)
+        );
+        // unsigned policy sets are refused, with an error saying why
+        let response = authorizer.is_authorized(&request, &policies, &entities);
+        assert_eq!(response.decision(), Decision::Deny);
+        assert_eq!(
+            response.diagnostics().errors().collect::<Vec<_>>(),
+            vec![&AuthorizationError::UnverifiedPolicySet]
+        );
+
+        // without a verifier, nothing is checked
+        let authorizer = Authorizer::new();
+        assert_eq!(
+            authorizer
+                .is_authorized(&request, &policies, &entities)
+                .decision(),
+            Decision::Allow
+        );
+        assert!(authorizer
+            .is_authorized_signed(&request, &forged, &entities)
+            .is_ok());
+    }
+}
@@ -0,0 +1,908 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module simplifies policies, e.g., machine-generated ones, which are
+//! often needlessly verbose and slow to evaluate.
+//! ```ignore
+//! let (simplified, rewrites) = policy.simplify(&schema)?;
+//! for rewrite in &rewrites {
+//!     println!("{rewrite}");
+//! }
+//! ```
+//!
+//! The simplification folds expressions over constants, e.g., `true && e`
+//! into `e` and `1 + 2` into `3`; replaces tests which hold for every request
+//! which is valid for the schema by `true`, e.g., `context has a` for a
+//! required attribute `a` of the context, or `principal is User` when every
+//! action the policy applies to only has `User` principals; moves conditions
+//! on the scope variables into the scope, e.g., `principal == User::"alice"`
+//! into the principal constraint if it's unconstrained; and replaces `in`
+//! scope constraints by `==` when the entity can't have descendants in the
+//! schema.
+//!
+//! The simplified policy makes the same decision as the original for every
+//! request which is valid for the schema, and for all entity data which is
+//! valid for the schema. It may report different errors: e.g., a condition
+//! moved into the scope is checked before conditions which may error. The
+//! policy must validate against the schema in strict mode, and only static
+//! policies are simplified.
+#![allow(clippy::module_name_repetitions)]
+
+use crate::{Policy, PolicyId, Schema};
+use cedar_policy_core::ast::{
+    self, ActionConstraint, BinaryOp, EntityReference, EntityType, EntityUID, Expr, ExprKind,
+    Literal, PrincipalOrResource, PrincipalOrResourceConstraint, UnaryOp, Var,
+};
+use cedar_policy_validator::typecheck::{PolicyCheck, Typechecker};
+use cedar_policy_validator::types::{EntityRecordKind, Type};
+use cedar_policy_validator::{ValidationMode, ValidatorSchema};
+use miette::Diagnostic;
+use ref_cast::RefCast;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::{self, Display};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// A rewrite made by [`Policy::simplify`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Rewrite {
+    /// An expression over constants was replaced by its value, or by the
+    /// operand which determines its value
+    Fold {
+        /// The expression
+        before: String,
+        /// What it was replaced by
+        after: String,
+    },
+    /// A test which holds for every request which is valid for the schema was
+    /// replaced by `true`
+    Tautology {
+        /// The test
+        test: String,
+    },
+    /// A condition on a scope variable was moved into the scope
+    Hoist {
+        /// The condition
+        condition: String,
+        /// The new scope constraint on the variable
+        scope: String,
+    },
+    /// A scope constraint was replaced by an equivalent, simpler one
+    Scope {
+        /// The constraint
+        before: String,
+        /// What it was replaced by
+        after: String,
+    },
+}
+
+impl Display for Rewrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fold { before, after } => write!(f, "folded `{before}` into `{after}`"),
+            Self::Tautology { test } => {
+                write!(f, "removed `{test}`, which holds for every valid request")
+            }
+            Self::Hoist { condition, scope } => {
+                write!(f, "moved `{condition}` into the scope as `{scope}`")
+            }
+            Self::Scope { before, after } => write!(f, "replaced `{before}` by `{after}`"),
+        }
+    }
+}
+
+/// Errors simplifying a policy
+#[derive(Debug, Diagnostic, Error)]
+#[non_exhaustive]
+pub enum SimplifyError {
+    /// The policy does not validate against the schema
+    #[error("policy `{0}` does not validate against the schema")]
+    #[diagnostic(help("validate the policy in strict mode to see the errors"))]
+    Validation(PolicyId),
+    /// The policy is a link of a template
+    #[error("policy `{0}` is template-linked, and only static policies can be simplified")]
+    #[diagnostic(help("simplify the template instead"))]
+    Linked(PolicyId),
+}
+
+impl Policy {
+    /// Simplify this policy under `schema`, returning the simplified policy
+    /// and the rewrites which were made, in the order they were made. The
+    /// simplified policy has the same id and annotations, and makes the same
+    /// decision for every request and entity data which are valid for the
+    /// schema. See the [`crate::simplify`] module for the rewrites.
+    pub fn simplify(&self, schema: &Schema) -> Result<(Self, Vec<Rewrite>), SimplifyError> {
+        let id = || PolicyId::ref_cast(self.ast.id()).clone();
+        if !self.ast.is_static() {
+            return Err(SimplifyError::Linked(id()));
+        }
+        let template = self.ast.template();
+        let env =
+            RequestEnvs::new(&schema.0, template).ok_or_else(|| SimplifyError::Validation(id()))?;
+        let mut simplifier = Simplifier {
+            schema: &schema.0,
+            env,
+            rewrites: Vec::new(),
+        };
+
+        let condition = simplifier.expr(template.non_head_constraints());
+        let mut principal = template.principal_constraint().as_inner().clone();
+        let mut action = template.action_constraint().clone();
+        let mut resource = template.resource_constraint().as_inner().clone();
+        let mut conjuncts = Vec::new();
+        for conjunct in conjuncts_of(&condition) {
+            let hoisted = match scope_var(conjunct) {
+                Some(Var::Principal) => {
+                    simplifier.hoist(&mut principal, PrincipalOrResource::Principal, conjunct)
+                }
+                Some(Var::Resource) => {
+                    simplifier.hoist(&mut resource, PrincipalOrResource::Resource, conjunct)
+                }
+                Some(Var::Action) => simplifier.hoist_action(&mut action, conjunct),
+                _ => false,
+            };
+            if !hoisted && !is_bool(conjunct, true) {
+                conjuncts.push(conjunct.clone());
+            }
+        }
+        let principal = simplifier.scope(principal, PrincipalOrResource::Principal);
+        let action = simplifier.action_scope(action);
+        let resource = simplifier.scope(resource, PrincipalOrResource::Resource);
+
+        let condition = conjuncts
+            .into_iter()
+            .reduce(Expr::and)
+            .unwrap_or_else(|| Expr::val(true));
+        let annotations = template
+            .annotations()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        // INVARIANT: the policy is static, so none of its parts have slots
+        let policy = ast::StaticPolicy::new(
+            self.ast.id().clone(),
+            annotations,
+            template.effect(),
+            ast::PrincipalConstraint::new(principal),
+            action,
+            ast::ResourceConstraint::new(resource),
+            condition,
+        )
+        .map_err(|_| SimplifyError::Linked(id()))?;
+        let (_, policy) = ast::Template::link_static_policy(policy);
+        Ok((Self::from_ast(policy), simplifier.rewrites))
+    }
+}
+
+/// The request environments of the schema in which a policy applies
+struct RequestEnvs {
+    principals: BTreeSet<EntityType>,
+    resources: BTreeSet<EntityType>,
+    contexts: Vec<Type>,
+}
+
+impl RequestEnvs {
+    /// The request environments in which the scope of `template` applies, or
+    /// `None` if `template` doesn't validate. The conditions of `template`
+    /// aren't considered, as a condition which excludes an environment isn't
+    /// a tautology there.
+    fn new(schema: &ValidatorSchema, template: &ast::Template) -> Option<Self> {
+        let typechecker = Typechecker::new(schema, ValidationMode::Strict);
+        if typechecker
+            .typecheck_by_request_env(template)
+            .iter()
+            .any(|(_, check)| matches!(check, PolicyCheck::Fail(_)))
+        {
+            return None;
+        }
+        let scope = ast::Template::new(
+            template.id().clone(),
+            BTreeMap::new(),
+            template.effect(),
+            template.principal_constraint().clone(),
+            template.action_constraint().clone(),
+            template.resource_constraint().clone(),
+            Expr::val(true),
+        );
+        let mut env = Self {
+            principals: BTreeSet::new(),
+            resources: BTreeSet::new(),
+            contexts: Vec::new(),
+        };
+        for (request, check) in typechecker.typecheck_by_request_env(&scope) {
+            if !matches!(check, PolicyCheck::Success(_)) {
+                continue;
+            }
+            env.principals
+                .extend(request.principal_entity_type().cloned());
+            env.resources
+                .extend(request.resource_entity_type().cloned());
+            env.contexts.push(request.context_type());
+        }
+        Some(env)
+    }
+
+    /// Whether `var` has entity type `name` in every environment
+    fn always_is(&self, var: Var, name: &ast::Name) -> bool {
+        let types = match var {
+            Var::Principal => &self.principals,
+            Var::Resource => &self.resources,
+            Var::Action | Var::Context => return false,
+        };
+        !types.is_empty()
+            && types
+                .iter()
+                .all(|ty| matches!(ty, EntityType::Specified(ty) if ty == name))
+    }
+
+    /// Whether the context has the required attribute `attr` in every
+    /// environment
+    fn context_always_has(&self, attr: &str) -> bool {
+        !self.contexts.is_empty()
+            && self.contexts.iter().all(|context| match context {
+                Type::EntityOrRecord(EntityRecordKind::Record { attrs, .. }) => {
+                    attrs.attrs.get(attr).is_some_and(|attr| attr.is_required)
+                }
+                _ => false,
+            })
+    }
+}
+
+/// The state of simplifying one policy
+struct Simplifier<'a> {
+    schema: &'a ValidatorSchema,
+    env: RequestEnvs,
+    rewrites: Vec<Rewrite>,
+}
+
+impl<'a> Simplifier<'a> {
+    /// Simplify `expr`, bottom-up
+    fn expr(&mut self, expr: &Expr) -> Expr {
+        let simplified = match expr.expr_kind() {
+            ExprKind::Lit(_) | ExprKind::Var(_) | ExprKind::Slot(_) | ExprKind::Unknown(_) => {
+                return expr.clone()
+            }
+            ExprKind::If {
+                test_expr,
+                then_expr,
+                else_expr,
+            } => Expr::ite(
+                self.expr(test_expr),
+                self.expr(then_expr),
+                self.expr(else_expr),
+            ),
+            ExprKind::And { left, right } => Expr::and(self.expr(left), self.expr(right)),
+            ExprKind::Or { left, right } => Expr::or(self.expr(left), self.expr(right)),
+            ExprKind::UnaryApp { op, arg } => Expr::unary_app(*op, self.expr(arg)),
+            ExprKind::BinaryApp { op, arg1, arg2 } => {
+                Expr::binary_app(*op, self.expr(arg1), self.expr(arg2))
+            }
+            ExprKind::MulByConst { arg, constant } => Expr::mul(self.expr(arg), *constant),
+            ExprKind::ExtensionFunctionApp { fn_name, args } => Expr::call_extension_fn(
+                fn_name.clone(),
+                args.iter().map(|arg| self.expr(arg)).collect(),
+            ),
+            ExprKind::GetAttr { expr, attr } => Expr::get_attr(self.expr(expr), attr.clone()),
+            ExprKind::HasAttr { expr, attr } => Expr::has_attr(self.expr(expr), attr.clone()),
+            ExprKind::Like { expr, pattern } => {
+                Expr::like(self.expr(expr), pattern.iter().copied())
+            }
+            ExprKind::Is { expr, entity_type } => {
+                Expr::is_entity_type(self.expr(expr), entity_type.clone())
+            }
+            ExprKind::Set(elements) => Expr::set(elements.iter().map(|e| self.expr(e))),
+            ExprKind::Record(fields) => Expr::record_arc(Arc::new(
+                fields
+                    .iter()
+                    .map(|(k, v)| (k.clone(), self.expr(v)))
+                    .collect(),
+            )),
+        };
+        if self.is_tautology(&simplified) {
+            self.rewrites.push(Rewrite::Tautology {
+                test: simplified.to_string(),
+            });
+            return Expr::val(true);
+        }
+        match fold(&simplified) {
+            Some(folded) => {
+                self.rewrites.push(Rewrite::Fold {
+                    before: simplified.to_string(),
+                    after: folded.to_string(),
+                });
+                folded
+            }
+            None => simplified,
+        }
+    }
+
+    /// Whether `expr` is a test which holds for every valid request. Attributes
+    /// of entities aren't considered, as an entity may be missing from the
+    /// entity data.
+    fn is_tautology(&self, expr: &Expr) -> bool {
+        match expr.expr_kind() {
+            ExprKind::HasAttr { expr, attr } => {
+                matches!(expr.expr_kind(), ExprKind::Var(Var::Context))
+                    && self.env.context_always_has(attr)
+            }
+            ExprKind::Is { expr, entity_type } => match expr.expr_kind() {
+                ExprKind::Var(var) => self.env.always_is(*var, entity_type),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Move the condition `conjunct` on the principal or resource into its
+    /// scope constraint `scope`, if they can be combined
+    fn hoist(
+        &mut self,
+        scope: &mut PrincipalOrResourceConstraint,
+        var: PrincipalOrResource,
+        conjunct: &Expr,
+    ) -> bool {
+        let euid = |e: &Expr| match e.expr_kind() {
+            ExprKind::Lit(Literal::EntityUID(euid)) => Some(EntityReference::EUID(euid.clone())),
+            _ => None,
+        };
+        let combined = match (&*scope, conjunct.expr_kind()) {
+            (
+                PrincipalOrResourceConstraint::Any,
+                ExprKind::BinaryApp {
+                    op: BinaryOp::Eq,
+                    arg1,
+                    arg2,
+                },
+            ) => euid(arg2)
+                .or_else(|| euid(arg1))
+                .map(PrincipalOrResourceConstraint::Eq),
+            (
+                PrincipalOrResourceConstraint::Any,
+                ExprKind::BinaryApp {
+                    op: BinaryOp::In,
+                    arg2,
+                    ..
+                },
+            ) => euid(arg2).map(PrincipalOrResourceConstraint::In),
+            (PrincipalOrResourceConstraint::Any, ExprKind::Is { entity_type, .. }) => {
+                Some(PrincipalOrResourceConstraint::Is(entity_type.clone()))
+            }
+            (PrincipalOrResourceConstraint::In(entity), ExprKind::Is { entity_type, .. }) => Some(
+                PrincipalOrResourceConstraint::IsIn(entity_type.clone(), entity.clone()),
+            ),
+            (
+                PrincipalOrResourceConstraint::Is(entity_type),
+                ExprKind::BinaryApp {
+                    op: BinaryOp::In,
+                    arg2,
+                    ..
+                },
+            ) => euid(arg2)
+                .map(|entity| PrincipalOrResourceConstraint::IsIn(entity_type.clone(), entity)),
+            _ => None,
+        };
+        match combined {
+            Some(combined) => {
+                self.rewrites.push(Rewrite::Hoist {
+                    condition: conjunct.to_string(),
+                    scope: combined.display(var),
+                });
+                *scope = combined;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move the condition `conjunct` on the action into the unconstrained
+    /// action scope constraint `scope`
+    fn hoist_action(&mut self, scope: &mut ActionConstraint, conjunct: &Expr) -> bool {
+        if !matches!(scope, ActionConstraint::Any) {
+            return false;
+        }
+        let euid = |e: &Expr| match e.expr_kind() {
+            ExprKind::Lit(Literal::EntityUID(euid)) => Some(euid.clone()),
+            _ => None,
+        };
+        let combined = match conjunct.expr_kind() {
+            ExprKind::BinaryApp {
+                op: BinaryOp::Eq,
+                arg1,
+                arg2,
+            } => euid(arg2).or_else(|| euid(arg1)).map(ActionConstraint::Eq),
+            ExprKind::BinaryApp {
+                op: BinaryOp::In,
+                arg2,
+                ..
+            } => match arg2.expr_kind() {
+                ExprKind::Set(elements) => elements
+                    .iter()
+                    .map(euid)
+                    .collect::<Option<Vec<_>>>()
+                    .map(ActionConstraint::In),
+                _ => euid(arg2).map(|euid| ActionConstraint::In(vec![euid])),
+            },
+            _ => None,
+        };
+        match combined {
+            Some(combined) => {
+                self.rewrites.push(Rewrite::Hoist {
+                    condition: conjunct.to_string(),
+                    scope: combined.to_string(),
+                });
+                *scope = combined;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Normalize the principal or resource scope constraint `scope`: `in` an
+    /// entity which can't have descendants is `==`
+    fn scope(
+        &mut self,
+        scope: PrincipalOrResourceConstraint,
+        var: PrincipalOrResource,
+    ) -> PrincipalOrResourceConstraint {
+        let normalized = match &scope {
+            PrincipalOrResourceConstraint::In(EntityReference::EUID(entity))
+                if !self.may_have_descendants(entity) =>
+            {
+                PrincipalOrResourceConstraint::Eq(EntityReference::EUID(entity.clone()))
+            }
+            PrincipalOrResourceConstraint::IsIn(entity_type, EntityReference::EUID(entity))
+                if !self.may_have_descendants(entity)
+                    && matches!(entity.entity_type(), EntityType::Specified(ty) if ty == entity_type) =>
+            {
+                PrincipalOrResourceConstraint::Eq(EntityReference::EUID(entity.clone()))
+            }
+            _ => return scope,
+        };
+        self.rewrites.push(Rewrite::Scope {
+            before: scope.display(var),
+            after: normalized.display(var),
+        });
+        normalized
+    }
+
+    /// Normalize the action scope constraint `scope`: remove duplicate
+    /// actions, and `in` a single action which has no descendants is `==`
+    fn action_scope(&mut self, scope: ActionConstraint) -> ActionConstraint {
+        let ActionConstraint::In(actions) = &scope else {
+            return scope;
+        };
+        let mut seen = HashSet::new();
+        let mut deduplicated: Vec<Arc<EntityUID>> = actions
+            .iter()
+            .filter(|action| seen.insert(action.as_ref().clone()))
+            .cloned()
+            .collect();
+        let normalized = match deduplicated.as_slice() {
+            [action] if !self.action_may_have_descendants(action) => {
+                ActionConstraint::Eq(action.clone())
+            }
+            _ if deduplicated.len() < actions.len() => {
+                ActionConstraint::In(std::mem::take(&mut deduplicated))
+            }
+            _ => return scope,
+        };
+        self.rewrites.push(Rewrite::Scope {
+            before: scope.to_string(),
+            after: normalized.to_string(),
+        });
+        normalized
+    }
+
+    /// Whether an entity of the schema can be in `entity`, other than by
+    /// being `entity`
+    fn may_have_descendants(&self, entity: &EntityUID) -> bool {
+        match entity.entity_type() {
+            EntityType::Specified(name) => self
+                .schema
+                .get_entity_type(name)
+                .map_or(true, |ty| !ty.descendants.is_empty()),
+            EntityType::Unspecified => true,
+        }
+    }
+
+    /// Whether an action of the schema can be in `action`, other than by
+    /// being `action`
+    fn action_may_have_descendants(&self, action: &EntityUID) -> bool {
+        self.schema.action_entities().map_or(true, |actions| {
+            actions
+                .iter()
+                .any(|other| other.ancestors().any(|ancestor| ancestor == action))
+        })
+    }
+}
+
+/// Fold `expr`, whose operands are already simplified, if it's over
+/// constants. Operands of `&&`, `||`, `!`, and `if` are booleans, as the
+/// policy validates.
+fn fold(expr: &Expr) -> Option<Expr> {
+    match expr.expr_kind() {
+        ExprKind::And { left, right } => {
+            if is_bool(left, true) {
+                Some(right.as_ref().clone())
+            } else if is_bool(right, true) || is_bool(left, false) {
+                Some(left.as_ref().clone())
+            } else {
+                None
+            }
+        }
+        ExprKind::Or { left, right } => {
+            if is_bool(left, false) {
+                Some(right.as_ref().clone())
+            } else if is_bool(right, false) || is_bool(left, true) {
+                Some(left.as_ref().clone())
+            } else {
+                None
+            }
+        }
+        ExprKind::UnaryApp {
+            op: UnaryOp::Not,
+            arg,
+        } => match arg.expr_kind() {
+            ExprKind::Lit(Literal::Bool(b)) => Some(Expr::val(!b)),
+            ExprKind::UnaryApp {
+                op: UnaryOp::Not,
+                arg,
+            } => Some(arg.as_ref().clone()),
+            _ => None,
+        },
+        ExprKind::UnaryApp {
+            op: UnaryOp::Neg,
+            arg,
+        } => long(arg)?.checked_neg().map(Expr::val),
+        ExprKind::If {
+            test_expr,
+            then_expr,
+            else_expr,
+        } => {
+            if is_bool(test_expr, true) {
+                Some(then_expr.as_ref().clone())
+            } else if is_bool(test_expr, false) {
+                Some(else_expr.as_ref().clone())
+            } else if is_bool(then_expr, true) && is_bool(else_expr, false) {
+                Some(test_expr.as_ref().clone())
+            } else if is_bool(then_expr, false) && is_bool(else_expr, true) {
+                Some(Expr::not(test_expr.as_ref().clone()))
+            } else {
+                None
+            }
+        }
+        ExprKind::BinaryApp { op, arg1, arg2 } => match op {
+            BinaryOp::Eq => Some(Expr::val(literal(arg1)? == literal(arg2)?)),
+            BinaryOp::Less => Some(Expr::val(order(arg1, arg2)?.is_lt())),
+            BinaryOp::LessEq => Some(Expr::val(order(arg1, arg2)?.is_le())),
+            BinaryOp::Add => long(arg1)?.checked_add(long(arg2)?).map(Expr::val),
+            BinaryOp::Sub => long(arg1)?.checked_sub(long(arg2)?).map(Expr::val),
+            BinaryOp::Contains => match arg1.expr_kind() {
+                ExprKind::Set(elements) => {
+                    let element = literal(arg2)?;
+                    let elements = elements.iter().map(literal).collect::<Option<Vec<_>>>()?;
+                    Some(Expr::val(elements.contains(&element)))
+                }
+                _ => None,
+            },
+            _ => None,
+        },
+        ExprKind::MulByConst { arg, constant } => long(arg)?.checked_mul(*constant).map(Expr::val),
+        ExprKind::Like { expr, pattern } => match expr.expr_kind() {
+            ExprKind::Lit(Literal::String(s)) => Some(Expr::val(pattern.wildcard_match(s))),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The top-level conjuncts of `expr`
+fn conjuncts_of(expr: &Expr) -> Vec<&Expr> {
+    match expr.expr_kind() {
+        ExprKind::And { left, right } => {
+            let mut conjuncts = conjuncts_of(left);
+            conjuncts.extend(conjuncts_of(right));
+            conjuncts
+        }
+        _ => vec![expr],
+    }
+}
+
+/// The scope variable which `conjunct` compares to an entity literal or
+/// tests the type of, if any
+fn scope_var(conjunct: &Expr) -> Option<Var> {
+    let var = |e: &Expr| match e.expr_kind() {
+        ExprKind::Var(var) => Some(*var),
+        _ => None,
+    };
+    let is_entity = |e: &Expr| {
+        matches!(e.expr_kind(), ExprKind::Lit(Literal::EntityUID(_)))
+            || matches!(e.expr_kind(), ExprKind::Set(elements)
+                if elements.iter().all(|e| matches!(e.expr_kind(), ExprKind::Lit(Literal::EntityUID(_)))))
+    };
+    match conjunct.expr_kind() {
+        ExprKind::BinaryApp {
+            op: BinaryOp::Eq,
+            arg1,
+            arg2,
+        } => match (var(arg1), var(arg2)) {
+            (Some(v), None) if is_entity(arg2) => Some(v),
+            (None, Some(v)) if is_entity(arg1) => Some(v),
+            _ => None,
+        },
+        ExprKind::BinaryApp {
+            op: BinaryOp::In,
+            arg1,
+            arg2,
+        } if is_entity(arg2) => var(arg1),
+        ExprKind::Is { expr, .. } => var(expr),
+        _ => None,
+    }
+    .filter(|v| *v != Var::Context)
+}
+
+/// Whether `expr` is the boolean literal `b`
+fn is_bool(expr: &Expr, b: bool) -> bool {
+    matches!(expr.expr_kind(), ExprKind::Lit(Literal::Bool(lit)) if *lit == b)
+}
+
+/// The literal `expr`, if it is one
+fn literal(expr: &Expr) -> Option<Literal> {
+    match expr.expr_kind() {
+        ExprKind::Lit(lit) => Some(lit.clone()),
+        _ => None,
+    }
+}
+
+/// The order of `arg1` and `arg2`, if they're both integer literals, or both
+/// string literals
+fn order(arg1: &Expr, arg2: &Expr) -> Option<Ordering> {
+    match (arg1.expr_kind(), arg2.expr_kind()) {
+        (ExprKind::Lit(Literal::Long(a)), ExprKind::Lit(Literal::Long(b))) => Some(a.cmp(b)),
+        (ExprKind::Lit(Literal::String(a)), ExprKind::Lit(Literal::String(b))) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// The integer literal `expr`, if it is one
+fn long(expr: &Expr) -> Option<i64> {
+    match expr.expr_kind() {
+        ExprKind::Lit(Literal::Long(i)) => Some(*i),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cool_asserts::assert_matches;
+    use std::str::FromStr;
+
+    fn schema() -> Schema {
+        let applies_to = serde_json::json!({
+            "principalTypes": ["User"],
+            "resourceTypes": ["Photo", "Album"],
+            "context": {
+                "type": "Record",
+                "attributes": {
+                    "authenticated": { "type": "Boolean" },
+                    "ip": { "type": "String", "required": false }
+                }
+            }
+        });
+        Schema::from_json_value(serde_json::json!({ "": {
+            "entityTypes": {
+                "User": {
+                    "memberOfTypes": ["Group"],
+                    "shape": {
+                        "type": "Record",
+                        "attributes": {
+                            "age": { "type": "Long" },
+                            "nickname": { "type": "String", "required": false }
+                        }
+                    }
+                },
+                "Group": {},
+                "Photo": {
+                    "memberOfTypes": ["Album"]
+                },
+                "Album": {}
+            },
+            "actions": {
+                "view": { "appliesTo": applies_to.clone() },
+                "read": { "memberOf": [{ "id": "view" }], "appliesTo": applies_to }
+            }
+        }}))
+        .expect("schema should be valid")
+    }
+
+    fn simplify(src: &str) -> (Policy, Vec<Rewrite>) {
+        Policy::parse(Some("p".into()), src)
+            .expect("policy should parse")
+            .simplify(&schema())
+            .expect("policy should simplify")
+    }
+
+    /// Assert that `simplified` has the scope and condition of the policy
+    /// `expected`
+    fn assert_same(simplified: &Policy, expected: &str) {
+        let expected = Policy::parse(Some("p".into()), expected).expect("policy should parse");
+        let (simplified, expected) = (simplified.ast.template(), expected.ast.template());
+        assert_eq!(
+            simplified.principal_constraint(),
+            expected.principal_constraint()
+        );
+        assert_eq!(simplified.action_constraint(), expected.action_constraint());
+        assert_eq!(
+            simplified.resource_constraint(),
+            expected.resource_constraint()
+        );
+        assert!(
+            simplified
+                .non_head_constraints()
+                .eq_shape(expected.non_head_constraints()),
+            "expected `{}`, got `{}`",
+            expected.non_head_constraints(),
+            simplified.non_head_constraints()
+        );
+    }
+
+    #[test]
+    fn folds_constants() {
+        let (simplified, rewrites) = simplify(
+            r#"permit(principal, action == Action::"view", resource)
+            when { true && principal.age < 1 + 2 }
+            unless { false }
+            when { if 2 <= 1 then false else principal has nickname };"#,
+        );
+        assert_same(
+            &simplified,
+            r#"permit(principal, action == Action::"view", resource)
+            when { principal.age < 3 && principal has nickname };"#,
+        );
+        assert!(rewrites
+            .iter()
+            .any(|r| matches!(r, Rewrite::Fold { after, .. } if after == "3")));
+    }
+
+    #[test]
+    fn folds_string_comparisons() {
+        let (simplified, _) = simplify(
+            r#"permit(principal, action == Action::"view", resource)
+            when { "2024-01" <= "2024-02" && principal has nickname }
+            unless { "b" < "a" };"#,
+        );
+        assert_same(
+            &simplified,
+            r#"permit(principal, action == Action::"view", resource)
+            when { principal has nickname };"#,
+        );
+    }
+
+    #[test]
+    fn keeps_overflowing_arithmetic() {
+        let (simplified, rewrites) = simplify(
+            r#"permit(principal, action == Action::"view", resource)
+            when { principal.age < 9223372036854775807 + 1 };"#,
+        );
+        assert_same(
+            &simplified,
+            r#"permit(principal, action == Action::"view", resource)
+            when { principal.age < 9223372036854775807 + 1 };"#,
+        );
+        assert!(rewrites.is_empty());
+    }
+
+    #[test]
+    fn removes_tautologies() {
+        let (simplified, rewrites) = simplify(
+            r#"permit(principal, action == Action::"view", resource)
+            when { context has authenticated && principal is User }
+            when { context has ip };"#,
+        );
+        assert_same(
+            &simplified,
+            r#"permit(principal, action == Action::"view", resource)
+            when { context has ip };"#,
+        );
+        assert_eq!(
+            rewrites
+                .iter()
+                .filter(|r| matches!(r, Rewrite::Tautology { .. }))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn keeps_entity_attribute_tests() {
+        // the principal may be missing from the entity data
+        let (simplified, _) = simplify(
+            r#"forbid(principal, action == Action::"view", resource)
+            unless { principal has age };"#,
+        );
+        assert_same(
+            &simplified,
+            r#"forbid(principal, action == Action::"view", resource)
+            unless { principal has age };"#,
+        );
+    }
+
+    #[test]
+    fn hoists_conditions_into_scope() {
+        let (simplified, rewrites) = simplify(
+            r#"permit(principal, action, resource in Album::"trip")
+            when { principal == User::"alice" && action == Action::"view" }
+            when { resource is Photo };"#,
+        );
+        assert_same(
+            &simplified,
+            r#"permit(principal == User::"alice", action == Action::"view", resource is Photo in Album::"trip");"#,
+        );
+        assert_eq!(
+            rewrites
+                .iter()
+                .filter(|r| matches!(r, Rewrite::Hoist { .. }))
+                .count(),
+            3
+        );
+    }
+
+    #[test]
+    fn normalizes_scope() {
+        let (simplified, rewrites) = simplify(
+            r#"permit(principal in User::"alice", action in [Action::"read", Action::"read"], resource);"#,
+        );
+        assert_same(
+            &simplified,
+            r#"permit(principal == User::"alice", action == Action::"read", resource);"#,
+        );
+        assert_eq!(rewrites.len(), 2);
+
+        // `view` has a descendant, and groups have members
+        let (simplified, rewrites) = simplify(
+            r#"permit(principal in Group::"admins", action in [Action::"view"], resource);"#,
+        );
+        assert_same(
+            &simplified,
+            r#"permit(principal in Group::"admins", action in [Action::"view"], resource);"#,
+        );
+        assert!(rewrites.is_empty());
+    }
+
+    #[test]
+    fn keeps_id_and_annotations() {
+        let (simplified, _) = simplify(
+            r#"@reason("test") permit(principal, action == Action::"view", resource) when { true };"#,
+        );
+        assert_eq!(simplified.id(), &PolicyId::from_str("p").unwrap());
+        assert_eq!(simplified.annotation("reason"), Some("test"));
+    }
+
+    #[test]
+    fn errors() {
+        let policy = Policy::parse(
+            None,
+            r#"permit(principal, action == Action::"view", resource) when { principal.missing };"#,
+        )
+        .unwrap();
+        assert_matches!(
+            policy.simplify(&schema()),
+            Err(SimplifyError::Validation(_))
+        );
+    }
+}
@@ -0,0 +1,588 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module compiles the residual policies of partial evaluation into
+//! parameterized SQL `WHERE` clauses, e.g., to filter a list query to the
+//! resources a principal may access.
+//!
+//! Partially evaluate the request with the listed entity, usually the
+//! `resource`, left unknown, then compile the [`PartialResponse`] with a
+//! [`TableMapping`] from the attributes of that entity to the columns of its
+//! table, and a [`SqlDialect`]:
+//! ```ignore
+//! let request = RequestBuilder::default()
+//!     .principal(Some(principal))
+//!     .action(Some(action))
+//!     .context(context)
+//!     .build()?;
+//! let response = Authorizer::new().is_authorized_partial(&request, &policies, &entities);
+//! let mapping = TableMapping::new("resource", "Photo".parse()?, "id")
+//!     .with_attribute("owner", "owner_id");
+//! let predicate = SqlPredicate::from_partial_response(&response, &mapping, &Postgres)?;
+//! // SELECT * FROM photos WHERE <predicate.sql()>, binding predicate.params()
+//! ```
+//!
+//! A row matches the predicate iff the request would be allowed for its
+//! entity: some permit policy is satisfied, and no forbid policy is. A policy
+//! whose condition is `NULL` in SQL, e.g., because it reads a `NULL` column,
+//! is not satisfied, like a policy whose evaluation errors in Cedar.
+//!
+//! Entity-typed attributes are mapped to columns holding the entity id. Only
+//! the constructs which have a direct SQL equivalent are supported; in
+//! particular, the entity hierarchy (`in`) and extension functions are not.
+//! Type errors, e.g., comparing a string to a number, may behave differently
+//! than in Cedar, so policies should be validated first.
+#![allow(clippy::module_name_repetitions)]
+
+use crate::{Decision, EntityTypeName, PartialResponse, PolicySet};
+use cedar_policy_core::ast::{
+    BinaryOp, Effect, EntityUID, Expr, ExprKind, Literal, PatternElem, UnaryOp,
+};
+use miette::Diagnostic;
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// The SQL syntax differing between databases
+pub trait SqlDialect {
+    /// The placeholder of the `index`th parameter, counting from 1, e.g., `$1`
+    fn placeholder(&self, index: usize) -> String;
+
+    /// `identifier` quoted, e.g., a column name. Defaults to the SQL standard
+    /// double quotes.
+    fn quote_identifier(&self, identifier: &str) -> String {
+        format!("\"{}\"", identifier.replace('"', "\"\""))
+    }
+}
+
+/// Postgres, with placeholders `$1`, `$2`, etc.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Postgres;
+
+impl SqlDialect for Postgres {
+    fn placeholder(&self, index: usize) -> String {
+        format!("${index}")
+    }
+}
+
+/// `SQLite`, with placeholders `?1`, `?2`, etc.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sqlite;
+
+impl SqlDialect for Sqlite {
+    fn placeholder(&self, index: usize) -> String {
+        format!("?{index}")
+    }
+}
+
+/// `MySQL`, with placeholders `?` and identifiers quoted with backticks
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MySql;
+
+impl SqlDialect for MySql {
+    fn placeholder(&self, _index: usize) -> String {
+        "?".to_owned()
+    }
+
+    fn quote_identifier(&self, identifier: &str) -> String {
+        format!("`{}`", identifier.replace('`', "``"))
+    }
+}
+
+/// How the unknown entity of a partial evaluation maps to the rows of a table
+#[derive(Debug, Clone)]
+pub struct TableMapping {
+    unknown: String,
+    entity_type: EntityTypeName,
+    id_column: String,
+    table: Option<String>,
+    attributes: HashMap<String, String>,
+}
+
+impl TableMapping {
+    /// The unknown named `unknown`, e.g., `resource`, is an entity of type
+    /// `entity_type`, whose id is in the column `id_column`
+    pub fn new(
+        unknown: impl Into<String>,
+        entity_type: EntityTypeName,
+        id_column: impl Into<String>,
+    ) -> Self {
+        Self {
+            unknown: unknown.into(),
+            entity_type,
+            id_column: id_column.into(),
+            table: None,
+            attributes: HashMap::new(),
+        }
+    }
+
+    /// Qualify columns with `table`, the name or alias of the table
+    #[must_use]
+    pub fn with_table(mut self, table: impl Into<String>) -> Self {
+        self.table = Some(table.into());
+        self
+    }
+
+    /// The attribute `attr` is in the column `column`, which is `NULL` if the
+    /// attribute is absent. Attributes of records are given by their path,
+    /// e.g., `address.city`.
+    #[must_use]
+    pub fn with_attribute(mut self, attr: impl Into<String>, column: impl Into<String>) -> Self {
+        self.attributes.insert(attr.into(), column.into());
+        self
+    }
+
+    fn column(&self, column: &str, dialect: &dyn SqlDialect) -> String {
+        match &self.table {
+            Some(table) => format!(
+                "{}.{}",
+                dialect.quote_identifier(table),
+                dialect.quote_identifier(column)
+            ),
+            None => dialect.quote_identifier(column),
+        }
+    }
+}
+
+/// A value of a parameter of an [`SqlPredicate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SqlValue {
+    /// A 64-bit integer
+    Long(i64),
+    /// A string, including entity ids
+    String(String),
+}
+
+/// A parameterized SQL boolean expression, for a `WHERE` clause
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqlPredicate {
+    sql: String,
+    params: Vec<SqlValue>,
+}
+
+/// Errors compiling residual policies to SQL
+#[derive(Debug, Diagnostic, Error)]
+#[non_exhaustive]
+pub enum SqlError {
+    /// A residual contains an expression without an SQL equivalent
+    #[error("expression has no SQL equivalent: {0}")]
+    #[diagnostic(help(
+        "only `&&`, `||`, `!`, `if`, `==`, `<`, `<=`, `+`, `-`, `*`, `has`, `like`, `is`, and \
+         `.contains()` of a set literal are supported"
+    ))]
+    Unsupported(String),
+    /// A residual reads an attribute which isn't mapped to a column
+    #[error("attribute `{0}` is not mapped to a column")]
+    UnmappedAttribute(String),
+    /// A residual contains an unknown other than the mapped one
+    #[error("unknown `{0}` is not mapped to a table")]
+    UnmappedUnknown(String),
+}
+
+impl SqlPredicate {
+    /// The SQL expression, with placeholders for the parameters
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// The values of the parameters, in the order of their placeholders
+    pub fn params(&self) -> &[SqlValue] {
+        &self.params
+    }
+
+    /// Compile the residual policies of a partial evaluation, which is
+    /// satisfied by the rows whose entity would be allowed
+    pub fn from_residuals(
+        residuals: &PolicySet,
+        mapping: &TableMapping,
+        dialect: &dyn SqlDialect,
+    ) -> Result<Self, SqlError> {
+        let mut compiler = Compiler {
+            mapping,
+            dialect,
+            params: vec![],
+        };
+        let mut policies = residuals.ast.policies().collect::<Vec<_>>();
+        policies.sort_by_key(|policy| policy.id().to_string());
+        let mut permits = vec![];
+        let mut forbids = vec![];
+        for policy in policies {
+            let condition = compiler.expr(&policy.condition())?;
+            let condition = format!("COALESCE({condition}, FALSE)");
+            match policy.effect() {
+                Effect::Permit => permits.push(condition),
+                Effect::Forbid => forbids.push(condition),
+            }
+        }
+        let sql = match (permits.is_empty(), forbids.is_empty()) {
+            (true, _) => "FALSE".to_owned(),
+            (false, true) => format!("({})", permits.join(" OR ")),
+            (false, false) => format!(
+                "({}) AND NOT ({})",
+                permits.join(" OR "),
+                forbids.join(" OR ")
+            ),
+        };
+        Ok(Self {
+            sql,
+            params: compiler.params,
+        })
+    }
+
+    /// Compile the result of [`is_authorized_partial`](crate::Authorizer::is_authorized_partial).
+    /// A concrete response is `TRUE` or `FALSE`, matching every row or none.
+    pub fn from_partial_response(
+        response: &PartialResponse,
+        mapping: &TableMapping,
+        dialect: &dyn SqlDialect,
+    ) -> Result<Self, SqlError> {
+        match response {
+            PartialResponse::Concrete(response) => Ok(Self {
+                sql: match response.decision() {
+                    Decision::Allow => "TRUE".to_owned(),
+                    Decision::Deny => "FALSE".to_owned(),
+                },
+                params: vec![],
+            }),
+            PartialResponse::Residual(response) => {
+                Self::from_residuals(response.residuals(), mapping, dialect)
+            }
+        }
+    }
+}
+
+/// An operand of `==`, which may be an entity rather than an SQL value
+enum Operand {
+    /// The entity of the row
+    Row,
+    /// An entity literal
+    Entity(EntityUID),
+    /// An SQL expression
+    Sql(String),
+}
+
+struct Compiler<'a> {
+    mapping: &'a TableMapping,
+    dialect: &'a dyn SqlDialect,
+    params: Vec<SqlValue>,
+}
+
+impl<'a> Compiler<'a> {
+    fn param(&mut self, value: SqlValue) -> String {
+        self.params.push(value);
+        self.dialect.placeholder(self.params.len())
+    }
+
+    fn expr(&mut self, e: &Expr) -> Result<String, SqlError> {
+        let unsupported = || SqlError::Unsupported(e.to_string());
+        match e.expr_kind() {
+            ExprKind::Lit(Literal::Bool(b)) => Ok(if *b { "TRUE" } else { "FALSE" }.to_owned()),
+            ExprKind::Lit(Literal::Long(i)) => Ok(self.param(SqlValue::Long(*i))),
+            ExprKind::Lit(Literal::String(s)) => Ok(self.param(SqlValue::String(s.to_string()))),
+            ExprKind::Lit(Literal::EntityUID(uid)) => Ok(self.param(entity_id(uid))),
+            ExprKind::Unknown(u) if u.name != self.mapping.unknown => {
+                Err(SqlError::UnmappedUnknown(u.name.to_string()))
+            }
+            ExprKind::Unknown(_) => Ok(self.mapping.column(&self.mapping.id_column, self.dialect)),
+            ExprKind::If {
+                test_expr,
+                then_expr,
+                else_expr,
+            } => Ok(format!(
+                "(CASE WHEN {} THEN {} ELSE {} END)",
+                self.expr(test_expr)?,
+                self.expr(then_expr)?,
+                self.expr(else_expr)?
+            )),
+            // partial evaluation leaves `true && <residual>` for heads, and
+            // conditions which are true
+            ExprKind::And { left, right } => match (self.expr(left)?, self.expr(right)?) {
+                (left, right) if left == "TRUE" => Ok(right),
+                (left, right) if right == "TRUE" => Ok(left),
+                (left, right) => Ok(format!("({left} AND {right})")),
+            },
+            ExprKind::Or { left, right } => match (self.expr(left)?, self.expr(right)?) {
+                (left, right) if left == "FALSE" => Ok(right),
+                (left, right) if right == "FALSE" => Ok(left),
+                (left, right) => Ok(format!("({left} OR {right})")),
+            },
+            ExprKind::UnaryApp { op, arg } => match op {
+                UnaryOp::Not => Ok(format!("(NOT {})", self.expr(arg)?)),
+                UnaryOp::Neg => Ok(format!("(-{})", self.expr(arg)?)),
+            },
+            ExprKind::BinaryApp { op, arg1, arg2 } => match op {
+                BinaryOp::Eq => self.eq(arg1, arg2),
+                BinaryOp::Less => self.binary("<", arg1, arg2),
+                BinaryOp::LessEq => self.binary("<=", arg1, arg2),
+                BinaryOp::Add => self.binary("+", arg1, arg2),
+                BinaryOp::Sub => self.binary("-", arg1, arg2),
+                BinaryOp::Contains => match arg1.expr_kind() {
+                    ExprKind::Set(elements) if elements.is_empty() => Ok("FALSE".to_owned()),
+                    ExprKind::Set(elements) => {
+                        let disjuncts = elements
+                            .iter()
+                            .map(|element| self.eq(element, arg2))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        Ok(format!("({})", disjuncts.join(" OR ")))
+                    }
+                    _ => Err(unsupported()),
+                },
+                BinaryOp::In | BinaryOp::ContainsAll | BinaryOp::ContainsAny => Err(unsupported()),
+            },
+            ExprKind::MulByConst { arg, constant } => {
+                let arg = self.expr(arg)?;
+                Ok(format!(
+                    "({arg} * {})",
+                    self.param(SqlValue::Long(*constant))
+                ))
+            }
+            ExprKind::GetAttr { .. } => {
+                let path = self.attribute_path(e).ok_or_else(unsupported)?;
+                self.attribute(&path)
+            }
+            ExprKind::HasAttr { expr, attr } => {
+                let mut path = self.attribute_path(expr).ok_or_else(unsupported)?;
+                path.push(attr.as_str());
+                Ok(format!("({} IS NOT NULL)", self.attribute(&path)?))
+            }
+            ExprKind::Like { expr, pattern } => {
+                let expr = self.expr(expr)?;
+                // `!` rather than `\`, which MySQL treats as an escape in
+                // string literals
+                let pattern = pattern
+                    .iter()
+                    .map(|elem| match elem {
+                        PatternElem::Wildcard => "%".to_owned(),
+                        PatternElem::Char(c @ ('%' | '_' | '!')) => format!("!{c}"),
+                        PatternElem::Char(c) => c.to_string(),
+                    })
+                    .collect::<String>();
+                let pattern = self.param(SqlValue::String(pattern));
+                Ok(format!("({expr} LIKE {pattern} ESCAPE '!')"))
+            }
+            ExprKind::Is { expr, entity_type } => match expr.expr_kind() {
+                ExprKind::Unknown(u) if u.name == self.mapping.unknown => Ok(if entity_type
+                    .to_string()
+                    == self.mapping.entity_type.to_string()
+                {
+                    "TRUE"
+                } else {
+                    "FALSE"
+                }
+                .to_owned()),
+                _ => Err(unsupported()),
+            },
+            ExprKind::Var(_)
+            | ExprKind::Slot(_)
+            | ExprKind::ExtensionFunctionApp { .. }
+            | ExprKind::Set(_)
+            | ExprKind::Record(_) => Err(unsupported()),
+        }
+    }
+
+    fn binary(&mut self, op: &str, arg1: &Expr, arg2: &Expr) -> Result<String, SqlError> {
+        Ok(format!("({} {op} {})", self.expr(arg1)?, self.expr(arg2)?))
+    }
+
+    fn operand(&mut self, e: &Expr) -> Result<Operand, SqlError> {
+        match e.expr_kind() {
+            ExprKind::Unknown(u) if u.name == self.mapping.unknown => Ok(Operand::Row),
+            ExprKind::Lit(Literal::EntityUID(uid)) => Ok(Operand::Entity(uid.as_ref().clone())),
+            _ => Ok(Operand::Sql(self.expr(e)?)),
+        }
+    }
+
+    fn eq(&mut self, arg1: &Expr, arg2: &Expr) -> Result<String, SqlError> {
+        let id_column = self.mapping.column(&self.mapping.id_column, self.dialect);
+        match (self.operand(arg1)?, self.operand(arg2)?) {
+            (Operand::Row, Operand::Row) => Ok("TRUE".to_owned()),
+            (Operand::Row, Operand::Entity(uid)) | (Operand::Entity(uid), Operand::Row) => {
+                if uid.entity_type().to_string() == self.mapping.entity_type.to_string() {
+                    let id = self.param(entity_id(&uid));
+                    Ok(format!("({id_column} = {id})"))
+                } else {
+                    Ok("FALSE".to_owned())
+                }
+            }
+            (Operand::Entity(a), Operand::Entity(b)) => {
+                Ok(if a == b { "TRUE" } else { "FALSE" }.to_owned())
+            }
+            (Operand::Row, Operand::Sql(sql)) | (Operand::Sql(sql), Operand::Row) => {
+                Ok(format!("({id_column} = {sql})"))
+            }
+            (Operand::Sql(sql), Operand::Entity(uid))
+            | (Operand::Entity(uid), Operand::Sql(sql)) => {
+                let id = self.param(entity_id(&uid));
+                Ok(format!("({sql} = {id})"))
+            }
+            (Operand::Sql(a), Operand::Sql(b)) => Ok(format!("({a} = {b})")),
+        }
+    }
+
+    /// The path of attributes of the mapped unknown that `e` reads, e.g.,
+    /// `["address", "city"]` for `resource.address.city`
+    fn attribute_path<'e>(&self, e: &'e Expr) -> Option<Vec<&'e str>> {
+        match e.expr_kind() {
+            ExprKind::Unknown(u) if u.name == self.mapping.unknown => Some(vec![]),
+            ExprKind::GetAttr { expr, attr } => {
+                let mut path = self.attribute_path(expr)?;
+                path.push(attr.as_str());
+                Some(path)
+            }
+            _ => None,
+        }
+    }
+
+    fn attribute(&self, path: &[&str]) -> Result<String, SqlError> {
+        let attr = path.join(".");
+        match self.mapping.attributes.get(&attr) {
+            Some(column) => Ok(self.mapping.column(column, self.dialect)),
+            None => Err(SqlError::UnmappedAttribute(attr)),
+        }
+    }
+}
+
+/// The id of `uid`, to compare with an id column
+fn entity_id(uid: &EntityUID) -> SqlValue {
+    SqlValue::String(AsRef::<str>::as_ref(uid.eid()).to_owned())
+}
+
+// PANIC SAFETY unit tests
+#[allow(clippy::panic)]
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Authorizer, Context, Entities, EntityUid, RequestBuilder};
+    use cool_asserts::assert_matches;
+    use std::str::FromStr;
+
+    fn compile(policies: &str, dialect: &dyn SqlDialect) -> Result<SqlPredicate, SqlError> {
+        let policies = PolicySet::from_str(policies).unwrap();
+        let request = RequestBuilder::default()
+            .principal(Some(EntityUid::from_str(r#"User::"alice""#).unwrap()))
+            .action(Some(EntityUid::from_str(r#"Action::"view""#).unwrap()))
+            .context(Context::empty())
+            .build()
+            .unwrap();
+        let response =
+            Authorizer::new().is_authorized_partial(&request, &policies, &Entities::empty());
+        let mapping = TableMapping::new("resource", "Photo".parse().unwrap(), "id")
+            .with_attribute("owner", "owner_id")
+            .with_attribute("private", "is_private")
+            .with_attribute("name", "name")
+            .with_attribute("address.city", "city");
+        SqlPredicate::from_partial_response(&response, &mapping, dialect)
+    }
+
+    #[test]
+    fn permit_and_forbid() {
+        let predicate = compile(
+            r#"
+            permit(principal, action, resource) when { resource.owner == principal };
+            forbid(principal, action, resource) when { resource.private };
+            "#,
+            &Postgres,
+        )
+        .unwrap();
+        assert_eq!(
+            predicate.sql(),
+            r#"(COALESCE(("owner_id" = $1), FALSE)) AND NOT (COALESCE("is_private", FALSE))"#
+        );
+        assert_eq!(predicate.params(), [SqlValue::String("alice".to_owned())]);
+    }
+
+    #[test]
+    fn expressions() {
+        let predicate = compile(
+            r#"
+            permit(principal, action, resource == Photo::"a");
+            permit(principal, action, resource) when {
+                resource has name && resource.address.city like "S*_" &&
+                ["x", "y"].contains(resource.name)
+            };
+            "#,
+            &MySql,
+        )
+        .unwrap();
+        let sql = predicate.sql();
+        assert!(sql.contains("(`id` = ?)"), "{sql}");
+        assert!(sql.contains("(`city` LIKE ? ESCAPE '!')"), "{sql}");
+        assert!(sql.contains("((`name` = ?) OR (`name` = ?))"), "{sql}");
+        assert!(
+            predicate
+                .params()
+                .contains(&SqlValue::String("S%!_".to_owned())),
+            "{:?}",
+            predicate.params()
+        );
+    }
+
+    #[test]
+    fn table_and_dialect() {
+        let policies = PolicySet::from_str(
+            "permit(principal, action, resource) when { resource.owner == principal };",
+        )
+        .unwrap();
+        let response = Authorizer::new().is_authorized_partial(
+            &RequestBuilder::default()
+                .principal(Some(EntityUid::from_str(r#"User::"alice""#).unwrap()))
+                .action(Some(EntityUid::from_str(r#"Action::"view""#).unwrap()))
+                .context(Context::empty())
+                .build()
+                .unwrap(),
+            &policies,
+            &Entities::empty(),
+        );
+        let mapping = TableMapping::new("resource", "Photo".parse().unwrap(), "id")
+            .with_table("p")
+            .with_attribute("owner", "owner_id");
+        let predicate = SqlPredicate::from_partial_response(&response, &mapping, &Sqlite).unwrap();
+        assert_eq!(
+            predicate.sql(),
+            r#"(COALESCE(("p"."owner_id" = ?1), FALSE))"#
+        );
+    }
+
+    #[test]
+    fn concrete_responses() {
+        let predicate = compile("permit(principal, action, resource);", &Postgres).unwrap();
+        assert_eq!(predicate.sql(), "TRUE");
+        let predicate = compile(
+            r#"permit(principal == User::"bob", action, resource);"#,
+            &Postgres,
+        )
+        .unwrap();
+        assert_eq!(predicate.sql(), "FALSE");
+    }
+
+    #[test]
+    fn errors() {
+        assert_matches!(
+            compile(
+                "permit(principal, action, resource) when { resource.size > 3 };",
+                &Postgres
+            ),
+            Err(SqlError::UnmappedAttribute(attr)) => assert_eq!(attr, "size")
+        );
+        assert_matches!(
+            compile(
+                r#"permit(principal, action, resource in Album::"trip");"#,
+                &Postgres
+            ),
+            Err(SqlError::Unsupported(_))
+        );
+    }
+}
@@ -0,0 +1,86 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A [`PolicyStorage`] backend in a JSON file
+
+use super::{PolicyStorage, StoredPolicies};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A [`PolicyStorage`] backend which stores the state as a JSON file. A save
+/// writes a temporary file next to it and renames it over the file, so the
+/// file is always a whole state.
+///
+/// Saves by the same `FileStorage` are serialized, but the version check
+/// isn't atomic with saves by other processes, so only one process should
+/// write to the file.
+#[derive(Debug)]
+pub struct FileStorage {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileStorage {
+    /// A backend storing the state in the file at `path`, which is created
+    /// by the first save
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// The path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn temporary_path(&self) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".tmp");
+        self.path.with_file_name(name)
+    }
+}
+
+impl PolicyStorage for FileStorage {
+    type Error = io::Error;
+
+    fn load(&self) -> Result<StoredPolicies, io::Error> {
+        match fs::read(&self.path) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(StoredPolicies::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn save(&self, policies: &StoredPolicies) -> Result<bool, io::Error> {
+        let _guard = self
+            .lock
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if self.load()?.version + 1 != policies.version {
+            return Ok(false);
+        }
+        let temporary = self.temporary_path();
+        let mut file = fs::File::create(&temporary)?;
+        file.write_all(&serde_json::to_vec_pretty(policies)?)?;
+        file.sync_all()?;
+        fs::rename(&temporary, &self.path)?;
+        Ok(true)
+    }
+}
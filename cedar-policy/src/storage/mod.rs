@@ -0,0 +1,436 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module contains [`DurablePolicyStore`], which persists every change
+//! to a policy set and its schema to a [`PolicyStorage`] backend, and the
+//! backends [`FileStorage`] and, with the `sqlite` feature, `SqliteStorage`.
+//! ```ignore
+//! let mut store = DurablePolicyStore::open(FileStorage::new("policies.json"))?;
+//! store.add_template(template)?;
+//! store.link(template_id, PolicyId::from_str("alice-photos")?, values)?;
+//! // after a restart, `store.policies()` has the template and the link
+//! ```
+//!
+//! The backend stores the whole state, i.e., the text of the static policies
+//! and templates, the template links, and the schema, with a version which is
+//! incremented by every change. A change is only saved if the stored version
+//! is the one the store loaded, so concurrent writers can't overwrite each
+//! other's changes: the loser gets [`StoreError::Conflict`], and can
+//! [reload](DurablePolicyStore::reload) and retry.
+
+use crate::{
+    EntityUid, ParseErrors, Policy, PolicyId, PolicySet, PolicySetError, SlotId, Template,
+};
+use miette::Diagnostic;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use thiserror::Error;
+
+mod fs;
+pub use fs::FileStorage;
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStorage;
+
+/// A backend which stores the state of a [`DurablePolicyStore`]
+pub trait PolicyStorage {
+    /// Errors reading or writing the backend
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// The stored state, or the empty state with version 0 if nothing is
+    /// stored yet
+    fn load(&self) -> Result<StoredPolicies, Self::Error>;
+
+    /// Replace the stored state with `policies` if the stored version is one
+    /// less than `policies.version`, atomically, and return whether it did
+    fn save(&self, policies: &StoredPolicies) -> Result<bool, Self::Error>;
+}
+
+/// The state of a [`DurablePolicyStore`], as it's stored
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredPolicies {
+    /// The number of changes saved
+    pub version: u64,
+    /// The static policies, templates, and template links, by id
+    pub policies: BTreeMap<String, StoredPolicy>,
+    /// The schema, in the Cedar schema JSON format
+    pub schema: Option<String>,
+}
+
+/// A static policy, template, or template link, as it's stored
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum StoredPolicy {
+    /// A static policy
+    Static {
+        /// The text of the policy
+        text: String,
+    },
+    /// A template
+    Template {
+        /// The text of the template
+        text: String,
+    },
+    /// A template link
+    Link {
+        /// The id of the template
+        template: String,
+        /// The entity linked to each slot, e.g., `?principal`
+        values: BTreeMap<String, String>,
+    },
+}
+
+/// Errors changing or loading a [`DurablePolicyStore`]. A change which fails
+/// isn't made, in memory or in the backend.
+#[derive(Debug, Diagnostic, Error)]
+#[non_exhaustive]
+pub enum StoreError<E: std::error::Error + Send + Sync + 'static> {
+    /// The backend failed
+    #[error("policy storage failed: {0}")]
+    Storage(#[source] E),
+    /// Another writer saved a change since the store was loaded
+    #[error("the stored policies were changed by another writer")]
+    #[diagnostic(help("reload the store and retry the change"))]
+    Conflict,
+    /// The change isn't valid for the policy set
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    PolicySet(#[from] PolicySetError),
+    /// A stored policy doesn't parse
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Parse(#[from] ParseErrors),
+    /// A stored template link has an unknown slot
+    #[error("stored link `{id}` has the unknown slot `{slot}`")]
+    UnknownSlot {
+        /// The id of the link
+        id: String,
+        /// The slot
+        slot: String,
+    },
+}
+
+/// A policy set and schema whose changes are saved to a [`PolicyStorage`]
+/// backend
+#[derive(Debug)]
+pub struct DurablePolicyStore<S> {
+    storage: S,
+    stored: StoredPolicies,
+    policies: PolicySet,
+}
+
+impl<S: PolicyStorage> DurablePolicyStore<S> {
+    /// Load the state stored in `storage`
+    pub fn open(storage: S) -> Result<Self, StoreError<S::Error>> {
+        let stored = storage.load().map_err(StoreError::Storage)?;
+        let policies = decode(&stored)?;
+        Ok(Self {
+            storage,
+            stored,
+            policies,
+        })
+    }
+
+    /// Load the state stored in the backend again, discarding this store's
+    /// state, e.g., after a [`StoreError::Conflict`]
+    pub fn reload(&mut self) -> Result<(), StoreError<S::Error>> {
+        let stored = self.storage.load().map_err(StoreError::Storage)?;
+        self.policies = decode(&stored)?;
+        self.stored = stored;
+        Ok(())
+    }
+
+    /// The policies
+    pub fn policies(&self) -> &PolicySet {
+        &self.policies
+    }
+
+    /// The schema, in the Cedar schema JSON format, if one is stored
+    pub fn schema(&self) -> Option<&str> {
+        self.stored.schema.as_deref()
+    }
+
+    /// The number of changes saved
+    pub fn version(&self) -> u64 {
+        self.stored.version
+    }
+
+    /// The backend
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    /// Add and save a static policy
+    pub fn add(&mut self, policy: Policy) -> Result<(), StoreError<S::Error>> {
+        let id = policy.id().to_string();
+        let text = policy.to_string();
+        self.change(
+            |policies| policies.add(policy),
+            |stored| {
+                stored.policies.insert(id, StoredPolicy::Static { text });
+            },
+        )
+    }
+
+    /// Add and save a template
+    pub fn add_template(&mut self, template: Template) -> Result<(), StoreError<S::Error>> {
+        let id = template.id().to_string();
+        let text = template.to_string();
+        self.change(
+            |policies| policies.add_template(template),
+            |stored| {
+                stored.policies.insert(id, StoredPolicy::Template { text });
+            },
+        )
+    }
+
+    /// Link and save a template
+    pub fn link(
+        &mut self,
+        template_id: PolicyId,
+        new_id: PolicyId,
+        vals: HashMap<SlotId, EntityUid>,
+    ) -> Result<(), StoreError<S::Error>> {
+        let link = StoredPolicy::Link {
+            template: template_id.to_string(),
+            values: vals
+                .iter()
+                .map(|(slot, euid)| (slot.to_string(), euid.to_string()))
+                .collect(),
+        };
+        let id = new_id.to_string();
+        self.change(
+            |policies| policies.link(template_id, new_id, vals),
+            |stored| {
+                stored.policies.insert(id, link);
+            },
+        )
+    }
+
+    /// Remove a static policy, and save the removal
+    pub fn remove_static(&mut self, policy_id: PolicyId) -> Result<(), StoreError<S::Error>> {
+        let id = policy_id.to_string();
+        self.change(
+            |policies| policies.remove_static(policy_id).map(drop),
+            |stored| {
+                stored.policies.remove(&id);
+            },
+        )
+    }
+
+    /// Remove a template with no links, and save the removal
+    pub fn remove_template(&mut self, template_id: PolicyId) -> Result<(), StoreError<S::Error>> {
+        let id = template_id.to_string();
+        self.change(
+            |policies| policies.remove_template(template_id).map(drop),
+            |stored| {
+                stored.policies.remove(&id);
+            },
+        )
+    }
+
+    /// Remove a template link, and save the removal
+    pub fn unlink(&mut self, policy_id: PolicyId) -> Result<(), StoreError<S::Error>> {
+        let id = policy_id.to_string();
+        self.change(
+            |policies| policies.unlink(policy_id).map(drop),
+            |stored| {
+                stored.policies.remove(&id);
+            },
+        )
+    }
+
+    /// Replace and save the schema, in the Cedar schema JSON format. The
+    /// policies aren't validated against it.
+    pub fn set_schema(&mut self, schema: Option<String>) -> Result<(), StoreError<S::Error>> {
+        self.change(
+            |_| Ok(()),
+            |stored| {
+                stored.schema = schema;
+            },
+        )
+    }
+
+    /// Apply a change to a copy of the policies with `apply`, and of the
+    /// stored state with `record`, save the copy of the stored state, and
+    /// only then keep the copies
+    fn change(
+        &mut self,
+        apply: impl FnOnce(&mut PolicySet) -> Result<(), PolicySetError>,
+        record: impl FnOnce(&mut StoredPolicies),
+    ) -> Result<(), StoreError<S::Error>> {
+        let mut policies = self.policies.clone();
+        apply(&mut policies)?;
+        let mut stored = self.stored.clone();
+        record(&mut stored);
+        stored.version += 1;
+        if !self.storage.save(&stored).map_err(StoreError::Storage)? {
+            return Err(StoreError::Conflict);
+        }
+        self.policies = policies;
+        self.stored = stored;
+        Ok(())
+    }
+}
+
+/// The policy set of a stored state. The templates are added before the
+/// links.
+fn decode<E: std::error::Error + Send + Sync + 'static>(
+    stored: &StoredPolicies,
+) -> Result<PolicySet, StoreError<E>> {
+    let mut policies = PolicySet::new();
+    for (id, policy) in &stored.policies {
+        match policy {
+            StoredPolicy::Static { text } => {
+                policies.add(Policy::parse(Some(id.clone()), text)?)?;
+            }
+            StoredPolicy::Template { text } => {
+                policies.add_template(Template::parse(Some(id.clone()), text)?)?;
+            }
+            StoredPolicy::Link { .. } => (),
+        }
+    }
+    for (id, policy) in &stored.policies {
+        if let StoredPolicy::Link { template, values } = policy {
+            let values = values
+                .iter()
+                .map(|(slot, euid)| {
+                    let slot = match slot.as_str() {
+                        "?principal" => SlotId::principal(),
+                        "?resource" => SlotId::resource(),
+                        _ => {
+                            return Err(StoreError::UnknownSlot {
+                                id: id.clone(),
+                                slot: slot.clone(),
+                            })
+                        }
+                    };
+                    Ok((slot, EntityUid::from_str(euid)?))
+                })
+                .collect::<Result<HashMap<_, _>, StoreError<E>>>()?;
+            policies.link(
+                PolicyId::from_str(template)?,
+                PolicyId::from_str(id)?,
+                values,
+            )?;
+        }
+    }
+    Ok(policies)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cool_asserts::assert_matches;
+
+    fn open(dir: &tempfile::TempDir) -> DurablePolicyStore<FileStorage> {
+        DurablePolicyStore::open(FileStorage::new(dir.path().join("policies.json"))).unwrap()
+    }
+
+    #[test]
+    fn changes_are_durable() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = open(&dir);
+        assert_eq!(store.version(), 0);
+        assert!(store.policies().is_empty());
+
+        let admins = Policy::parse(
+            Some("admins".into()),
+            r#"permit(principal in Group::"admins", action, resource);"#,
+        )
+        .unwrap();
+        store.add(admins).unwrap();
+        store
+            .add_template(
+                Template::parse(
+                    Some("owners".into()),
+                    "permit(principal == ?principal, action, resource in ?resource);",
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        store
+            .link(
+                PolicyId::from_str("owners").unwrap(),
+                PolicyId::from_str("alice-photos").unwrap(),
+                HashMap::from([
+                    (
+                        SlotId::principal(),
+                        EntityUid::from_str(r#"User::"alice""#).unwrap(),
+                    ),
+                    (
+                        SlotId::resource(),
+                        EntityUid::from_str(r#"Album::"photos""#).unwrap(),
+                    ),
+                ]),
+            )
+            .unwrap();
+        store.set_schema(Some("{}".into())).unwrap();
+        assert_eq!(store.version(), 4);
+
+        let reopened = open(&dir);
+        assert_eq!(reopened.version(), 4);
+        assert_eq!(reopened.schema(), Some("{}"));
+        let mut ids: Vec<&str> = reopened
+            .policies()
+            .policies()
+            .map(|p| p.id().as_ref())
+            .collect();
+        ids.sort_unstable();
+        assert_eq!(ids, ["admins", "alice-photos"]);
+        assert_eq!(reopened.policies().templates().count(), 1);
+
+        // a failed change isn't saved
+        assert_matches!(
+            store.remove_template(PolicyId::from_str("owners").unwrap()),
+            Err(StoreError::PolicySet(
+                PolicySetError::RemoveTemplateWithActiveLinksError(_)
+            ))
+        );
+        store
+            .unlink(PolicyId::from_str("alice-photos").unwrap())
+            .unwrap();
+        store
+            .remove_template(PolicyId::from_str("owners").unwrap())
+            .unwrap();
+        store
+            .remove_static(PolicyId::from_str("admins").unwrap())
+            .unwrap();
+        let reopened = open(&dir);
+        assert_eq!(reopened.version(), 7);
+        assert!(reopened.policies().is_empty());
+    }
+
+    #[test]
+    fn concurrent_writers() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut first = open(&dir);
+        let mut second = open(&dir);
+        let policy = |id: &str| {
+            Policy::parse(Some(id.into()), "permit(principal, action, resource);").unwrap()
+        };
+        first.add(policy("a")).unwrap();
+        assert_matches!(second.add(policy("b")), Err(StoreError::Conflict));
+        assert!(second.policies().is_empty());
+
+        second.reload().unwrap();
+        second.add(policy("b")).unwrap();
+        assert_eq!(open(&dir).policies().policies().count(), 2);
+    }
+}
@@ -0,0 +1,174 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A [`PolicyStorage`] backend in a `SQLite` database
+
+use super::{PolicyStorage, StoredPolicies, StoredPolicy};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// A [`PolicyStorage`] backend which stores the state in the tables
+/// `cedar_policy_store`, which has the version and the schema, and
+/// `cedar_policies`, which has a row of each policy, template, and link.
+/// A save is a transaction which checks the version, so any number of
+/// processes can write to the database.
+#[derive(Debug)]
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    /// A backend storing the state in the database at `path`, which is
+    /// created if it doesn't exist
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, rusqlite::Error> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// A backend storing the state in the database of `connection`,
+    /// creating the tables if they don't exist
+    pub fn from_connection(connection: Connection) -> Result<Self, rusqlite::Error> {
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS cedar_policy_store (
+                 id INTEGER PRIMARY KEY CHECK (id = 0),
+                 version INTEGER NOT NULL,
+                 schema TEXT
+             );
+             CREATE TABLE IF NOT EXISTS cedar_policies (
+                 id TEXT PRIMARY KEY,
+                 policy TEXT NOT NULL
+             );",
+        )?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+}
+
+/// The stored version and schema, if anything is stored
+fn header(connection: &Connection) -> Result<Option<(u64, Option<String>)>, rusqlite::Error> {
+    connection
+        .query_row(
+            "SELECT version, schema FROM cedar_policy_store WHERE id = 0",
+            [],
+            |row| {
+                let version = u64::try_from(row.get::<_, i64>(0)?).map_err(|e| {
+                    rusqlite::Error::FromSqlConversionFailure(0, Type::Integer, e.into())
+                })?;
+                Ok((version, row.get(1)?))
+            },
+        )
+        .optional()
+}
+
+fn to_json(policy: &StoredPolicy) -> Result<String, rusqlite::Error> {
+    serde_json::to_string(policy).map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))
+}
+
+fn from_json(json: &str) -> Result<StoredPolicy, rusqlite::Error> {
+    serde_json::from_str(json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, Type::Text, e.into()))
+}
+
+impl PolicyStorage for SqliteStorage {
+    type Error = rusqlite::Error;
+
+    fn load(&self) -> Result<StoredPolicies, rusqlite::Error> {
+        let connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let Some((version, schema)) = header(&connection)? else {
+            return Ok(StoredPolicies::default());
+        };
+        let mut statement = connection.prepare("SELECT id, policy FROM cedar_policies")?;
+        let policies = statement
+            .query_map([], |row| {
+                let policy: String = row.get(1)?;
+                Ok((row.get(0)?, from_json(&policy)?))
+            })?
+            .collect::<Result<BTreeMap<_, _>, _>>()?;
+        Ok(StoredPolicies {
+            version,
+            policies,
+            schema,
+        })
+    }
+
+    fn save(&self, policies: &StoredPolicies) -> Result<bool, rusqlite::Error> {
+        let mut connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        // an immediate transaction takes the write lock before reading the
+        // version, so no other writer can save in between
+        let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let version = header(&transaction)?.map_or(0, |(version, _)| version);
+        if version + 1 != policies.version {
+            return Ok(false);
+        }
+        // PANIC SAFETY: 2^63 changes can't be made
+        #[allow(clippy::expect_used)]
+        let new_version = i64::try_from(policies.version).expect("version should fit in an i64");
+        transaction.execute(
+            "INSERT OR REPLACE INTO cedar_policy_store (id, version, schema) VALUES (0, ?1, ?2)",
+            params![new_version, policies.schema],
+        )?;
+        transaction.execute("DELETE FROM cedar_policies", [])?;
+        {
+            let mut insert =
+                transaction.prepare("INSERT INTO cedar_policies (id, policy) VALUES (?1, ?2)")?;
+            for (id, policy) in &policies.policies {
+                insert.execute(params![id, to_json(policy)?])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::storage::{DurablePolicyStore, StoreError};
+    use crate::Policy;
+    use cool_asserts::assert_matches;
+
+    #[test]
+    fn changes_are_durable() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policies.db");
+        let open = || DurablePolicyStore::open(SqliteStorage::open(&path).unwrap()).unwrap();
+        let policy = |id: &str| {
+            Policy::parse(Some(id.into()), "permit(principal, action, resource);").unwrap()
+        };
+
+        let mut first = open();
+        let mut second = open();
+        first.add(policy("a")).unwrap();
+        first.set_schema(Some("{}".into())).unwrap();
+        assert_matches!(second.add(policy("b")), Err(StoreError::Conflict));
+        second.reload().unwrap();
+        second.add(policy("b")).unwrap();
+
+        let reopened = open();
+        assert_eq!(reopened.version(), 3);
+        assert_eq!(reopened.schema(), Some("{}"));
+        assert_eq!(reopened.policies().policies().count(), 2);
+    }
+}
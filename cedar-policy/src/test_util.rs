@@ -0,0 +1,406 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Helpers for differential testing of the JSON frontend against the Rust
+//! API, for authors of bindings in other languages.
+//!
+//! A binding typically builds a call to [`json_is_authorized`] or
+//! [`json_validate`] from objects in its own language. To test its
+//! serializer, it can build both the call and the corresponding Rust API
+//! objects from the same inputs, and check with
+//! [`assert_is_authorized_agrees`] or [`assert_validate_agrees`] that the
+//! frontend gives the same answer as the Rust API.
+//!
+//! The inputs can come from a golden corpus in the format of the Cedar
+//! integration tests, loaded with [`load_golden_test`] or
+//! [`load_golden_corpus`]. Each loaded request carries the frontend call the
+//! Cedar integration tests would make, and the expected response.
+//!
+//! Like test code, the helpers in this module panic on failure.
+
+// PANIC SAFETY: This module is used only for testing.
+#![allow(clippy::panic)]
+
+use crate::frontend::is_authorized::{json_is_authorized, InterfaceResponse};
+use crate::frontend::utils::InterfaceResult;
+use crate::frontend::validate::json_validate;
+use crate::{
+    Authorizer, Context, Decision, Entities, EntityUid, PolicyId, PolicySet, Request, Schema,
+    ValidationMode, Validator,
+};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Evaluate `call` with [`json_is_authorized`], and `request` against
+/// `policies` and `entities` with the Rust API, and assert that the responses
+/// agree. `call` is meant to be the serialization of the other arguments.
+///
+/// Returns the response.
+///
+/// # Panics
+///
+/// Panics if the frontend fails, or if the responses differ.
+#[track_caller]
+pub fn assert_is_authorized_agrees(
+    call: &str,
+    request: &Request,
+    policies: &PolicySet,
+    entities: &Entities,
+) -> InterfaceResponse {
+    #[derive(Deserialize)]
+    struct Answer {
+        response: InterfaceResponse,
+    }
+
+    let answer: Answer = serde_json::from_str(&success(json_is_authorized(call), "is_authorized"))
+        .unwrap_or_else(|e| panic!("unexpected answer from `json_is_authorized`: {e}"));
+    let expected: InterfaceResponse = Authorizer::new()
+        .is_authorized(request, policies, entities)
+        .into();
+    assert_eq!(
+        answer.response, expected,
+        "`json_is_authorized` disagrees with the Rust API for call {call}"
+    );
+    answer.response
+}
+
+/// Validate the policies of `call` with [`json_validate`], and `policies`
+/// against `schema` with the Rust API, and assert that they find the same
+/// validation errors, as pairs of policy id and error message. `call` is meant
+/// to be the serialization of the other arguments.
+///
+/// Returns the validation errors.
+///
+/// # Panics
+///
+/// Panics if the frontend fails, or if the validation errors differ.
+#[track_caller]
+pub fn assert_validate_agrees(
+    call: &str,
+    policies: &PolicySet,
+    schema: &Schema,
+) -> HashSet<(String, String)> {
+    #[derive(Deserialize)]
+    struct Answer {
+        notes: Vec<Note>,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Note {
+        policy_id: String,
+        note: String,
+    }
+
+    let answer: Answer = serde_json::from_str(&success(json_validate(call), "validate"))
+        .unwrap_or_else(|e| panic!("unexpected answer from `json_validate`: {e}"));
+    let notes: HashSet<_> = answer
+        .notes
+        .into_iter()
+        .map(|note| (note.policy_id, note.note))
+        .collect();
+    let expected: HashSet<_> = Validator::new(schema.clone())
+        .validate(policies, ValidationMode::default())
+        .validation_errors()
+        .map(|error| {
+            (
+                error.location().policy_id().to_string(),
+                error.error_kind().to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        notes, expected,
+        "`json_validate` disagrees with the Rust API for call {call}"
+    );
+    notes
+}
+
+/// The result of a successful call to the JSON frontend
+#[track_caller]
+fn success(result: InterfaceResult, function: &str) -> String {
+    match result {
+        InterfaceResult::Success { result } => result,
+        InterfaceResult::Failure { errors, .. } => {
+            panic!("`json_{function}` failed: {}", errors.join("; "))
+        }
+    }
+}
+
+/// A test in a golden corpus: policies, entities, and a schema, with requests
+/// and their expected responses
+#[derive(Debug)]
+pub struct GoldenTest {
+    /// Path of the file of the test
+    pub path: PathBuf,
+    /// Text of the policies
+    pub policies_text: String,
+    /// The policies, with ids `policy0`, `policy1`, and so on
+    pub policies: PolicySet,
+    /// JSON of the entities
+    pub entities_json: serde_json::Value,
+    /// The entities
+    pub entities: Entities,
+    /// JSON of the schema
+    pub schema_json: serde_json::Value,
+    /// The schema
+    pub schema: Schema,
+    /// Whether the policies are expected to pass validation against the schema
+    pub should_validate: bool,
+    /// Call to [`json_validate`] for the policies and the schema
+    pub validate_call: serde_json::Value,
+    /// Requests, with their expected responses
+    pub requests: Vec<GoldenRequest>,
+}
+
+/// A request of a [`GoldenTest`]
+#[derive(Debug)]
+pub struct GoldenRequest {
+    /// Description of the request
+    pub description: String,
+    /// The request
+    pub request: Request,
+    /// Call to [`json_is_authorized`] for the request, and the policies and
+    /// entities of the test
+    pub call: serde_json::Value,
+    /// Expected response
+    pub expected: InterfaceResponse,
+}
+
+impl GoldenTest {
+    /// Run every request, and the validation, of the test through both the
+    /// JSON frontend and the Rust API, and assert that they agree with each
+    /// other and with the expected results.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the checks fails.
+    #[track_caller]
+    pub fn check(&self) {
+        let notes = assert_validate_agrees(
+            &self.validate_call.to_string(),
+            &self.policies,
+            &self.schema,
+        );
+        assert_eq!(
+            notes.is_empty(),
+            self.should_validate,
+            "unexpected validation result for {}: {notes:?}",
+            self.path.display()
+        );
+        for request in &self.requests {
+            let response = assert_is_authorized_agrees(
+                &request.call.to_string(),
+                &request.request,
+                &self.policies,
+                &self.entities,
+            );
+            // the expected responses have no error details, so they aren't
+            // compared
+            let response = InterfaceResponse::new(
+                response.decision(),
+                response.diagnostics().reason().cloned().collect(),
+                response
+                    .diagnostics()
+                    .errors()
+                    .map(ToString::to_string)
+                    .collect(),
+            );
+            assert_eq!(
+                response,
+                request.expected,
+                "test {} failed for request \"{}\"",
+                self.path.display(),
+                request.description
+            );
+        }
+    }
+}
+
+/// Format of a test file, as in the Cedar integration tests
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GoldenTestJson {
+    policies: PathBuf,
+    entities: PathBuf,
+    schema: PathBuf,
+    should_validate: bool,
+    #[serde(alias = "queries")]
+    requests: Vec<GoldenRequestJson>,
+}
+
+/// Format of a request in a test file, as in the Cedar integration tests
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GoldenRequestJson {
+    desc: String,
+    #[serde(default)]
+    principal: Option<serde_json::Value>,
+    #[serde(default)]
+    action: Option<serde_json::Value>,
+    #[serde(default)]
+    resource: Option<serde_json::Value>,
+    context: serde_json::Value,
+    #[serde(default = "constant_true")]
+    enable_request_validation: bool,
+    decision: Decision,
+    reasons: Vec<String>,
+    errors: Vec<String>,
+}
+
+fn constant_true() -> bool {
+    true
+}
+
+/// Load the golden test in the file at `path`. The paths of the policies,
+/// entities, and schema files in the test are relative to `root`, as in the
+/// Cedar integration tests.
+///
+/// # Panics
+///
+/// Panics if a file can't be read, or its contents are invalid.
+pub fn load_golden_test(root: impl AsRef<Path>, path: impl AsRef<Path>) -> GoldenTest {
+    let root = root.as_ref();
+    let path = path.as_ref().to_path_buf();
+    let test: GoldenTestJson = serde_json::from_str(&read(&path))
+        .unwrap_or_else(|e| panic!("error parsing {}: {e}", path.display()));
+    let policies_text = read(&root.join(&test.policies));
+    let policies = PolicySet::from_str(&policies_text)
+        .unwrap_or_else(|e| panic!("error parsing {}: {e}", test.policies.display()));
+    let entities_json = read_json(&root.join(&test.entities));
+    let schema_json = read_json(&root.join(&test.schema));
+    let schema = Schema::from_json_value(schema_json.clone())
+        .unwrap_or_else(|e| panic!("error parsing {}: {e}", test.schema.display()));
+    let entities = Entities::from_json_value(entities_json.clone(), Some(&schema))
+        .unwrap_or_else(|e| panic!("error parsing {}: {e}", test.entities.display()));
+    let validate_call = serde_json::json!({
+        "schema": schema_json,
+        "policySet": policies_text,
+    });
+    let requests = test
+        .requests
+        .into_iter()
+        .map(|json| {
+            let request = golden_request(&json, &schema)
+                .unwrap_or_else(|e| panic!("in {}, {e}", path.display()));
+            let call = serde_json::json!({
+                "principal": json.principal,
+                "action": json.action,
+                "resource": json.resource,
+                "context": json.context,
+                "schema": schema_json,
+                "enable_request_validation": json.enable_request_validation,
+                "slice": {
+                    "policies": policies_text,
+                    "entities": entities_json,
+                },
+            });
+            let reasons = json
+                .reasons
+                .iter()
+                .map(|id| {
+                    PolicyId::from_str(id)
+                        .unwrap_or_else(|e| panic!("invalid policy id `{id}`: {e}"))
+                })
+                .collect();
+            GoldenRequest {
+                description: json.desc,
+                request,
+                call,
+                expected: InterfaceResponse::new(
+                    json.decision,
+                    reasons,
+                    json.errors.into_iter().collect(),
+                ),
+            }
+        })
+        .collect();
+    GoldenTest {
+        path,
+        policies_text,
+        policies,
+        entities_json,
+        entities,
+        schema_json,
+        schema,
+        should_validate: test.should_validate,
+        validate_call,
+        requests,
+    }
+}
+
+/// Load the golden tests in all the `.json` files directly in `dir`, in the
+/// order of their names. The paths in the tests are relative to `root`, as in
+/// [`load_golden_test`].
+///
+/// # Panics
+///
+/// Panics if `dir` can't be read, or as [`load_golden_test`] does.
+pub fn load_golden_corpus(root: impl AsRef<Path>, dir: impl AsRef<Path>) -> Vec<GoldenTest> {
+    let dir = dir.as_ref();
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("error reading {}: {e}", dir.display()))
+        .map(|entry| {
+            entry
+                .unwrap_or_else(|e| panic!("error reading {}: {e}", dir.display()))
+                .path()
+        })
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| load_golden_test(root.as_ref(), path))
+        .collect()
+}
+
+/// Build the Rust API request of a request in a test file
+fn golden_request(json: &GoldenRequestJson, schema: &Schema) -> Result<Request, String> {
+    let uid = |value: &Option<serde_json::Value>, component: &str| {
+        value
+            .clone()
+            .map(EntityUid::from_json)
+            .transpose()
+            .map_err(|e| format!("invalid {component} for request \"{}\": {e}", json.desc))
+    };
+    let principal = uid(&json.principal, "principal")?;
+    let action = uid(&json.action, "action")?;
+    let resource = uid(&json.resource, "resource")?;
+    let context = Context::from_json_value(
+        json.context.clone(),
+        action.as_ref().map(|action| (schema, action)),
+    )
+    .map_err(|e| format!("invalid context for request \"{}\": {e}", json.desc))?;
+    Request::new(
+        principal,
+        action,
+        resource,
+        context,
+        json.enable_request_validation.then_some(schema),
+    )
+    .map_err(|e| format!("invalid request \"{}\": {e}", json.desc))
+}
+
+fn read(path: &Path) -> String {
+    std::fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("error reading {}: {e}", path.display()))
+}
+
+fn read_json(path: &Path) -> serde_json::Value {
+    serde_json::from_str(&read(path))
+        .unwrap_or_else(|e| panic!("error parsing {}: {e}", path.display()))
+}
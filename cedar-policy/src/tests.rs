@@ -25,6 +25,60 @@ mod entity_uid_tests {
     use super::*;
     use cool_asserts::assert_matches;
 
+    /// ids with quotes, backslashes, and unicode are escaped in Cedar syntax,
+    /// and raw in JSON
+    #[test]
+    fn entity_uid_escaping() {
+        let ids = [
+            r#"a"b"#,
+            r"back\slash",
+            "new\nline\ttab",
+            "ünïcødé 🦀",
+            "\u{301}combining",
+            "'single'",
+            "nul\0",
+            "esc\u{1b}",
+            "",
+        ];
+        for raw in ids {
+            let id = EntityId::from_raw(raw);
+            assert_eq!(id.as_ref(), raw);
+            assert_eq!(EntityId::from_str(raw).unwrap(), id);
+            let euid = EntityUid::from_type_name_and_id(
+                EntityTypeName::from_str("User").unwrap(),
+                id.clone(),
+            );
+            let escaped = euid.display_escaped();
+            assert_eq!(escaped, format!(r#"User::"{}""#, id.escaped()));
+            assert_eq!(EntityUid::from_str(&escaped).unwrap(), euid, "{escaped}");
+            assert_eq!(
+                euid.to_json(),
+                serde_json::json!({ "__entity": { "type": "User", "id": raw } })
+            );
+            assert_eq!(EntityUid::from_json(euid.to_json()).unwrap(), euid);
+
+            // the same uid in policies and in entities JSON
+            let policy = format!("permit(principal == {escaped}, action, resource);");
+            let policies = PolicySet::from_str(&policy).unwrap();
+            let entities = Entities::from_json_value(
+                serde_json::json!([{ "uid": euid.to_json(), "attrs": {}, "parents": [] }]),
+                None,
+            )
+            .unwrap();
+            assert!(entities.get(&euid).is_some(), "{escaped}");
+            let request = Request::new(
+                Some(euid.clone()),
+                Some(EntityUid::from_strs("Action", "view")),
+                Some(euid.clone()),
+                Context::empty(),
+                None,
+            )
+            .unwrap();
+            let response = Authorizer::new().is_authorized(&request, &policies, &entities);
+            assert_eq!(response.decision(), Decision::Allow, "{escaped}");
+        }
+    }
+
     /// building an `EntityUid` from components
     #[test]
     fn entity_uid_from_parts() {
@@ -493,6 +547,7 @@ mod policy_set_tests {
     use super::*;
     use ast::LinkingError;
     use cool_asserts::assert_matches;
+    use std::collections::BTreeMap;
 
     #[test]
     fn template_link_lookup() {
@@ -1355,6 +1410,231 @@ mod policy_set_tests {
             ))
         );
     }
+
+    #[test]
+    fn metrics() {
+        assert_eq!(PolicySet::new().metrics().max_expression_depth(), 0);
+
+        let mut pset = PolicySet::from_str(
+            r#"permit(principal, action, resource);
+            forbid(principal == User::"mallory", action, resource)
+            when { context.ip.isInRange(ip("10.0.0.0/8")) && context.ip != ip("10.0.0.1") };"#,
+        )
+        .unwrap();
+        let template = Template::parse(
+            Some("t".into()),
+            r#"permit(principal == ?principal, action == Action::"view", resource);"#,
+        )
+        .unwrap();
+        pset.add_template(template).unwrap();
+        for (id, user) in [("alice", "User::\"alice\""), ("bob", "User::\"bob\"")] {
+            pset.link(
+                PolicyId::from_str("t").unwrap(),
+                PolicyId::from_str(id).unwrap(),
+                HashMap::from([(SlotId::principal(), EntityUid::from_str(user).unwrap())]),
+            )
+            .unwrap();
+        }
+
+        let metrics = pset.metrics();
+        assert_eq!(metrics.permits(), 3);
+        assert_eq!(metrics.forbids(), 1);
+        assert_eq!(metrics.templates(), 1);
+        assert_eq!(metrics.template_links(), 2);
+        // the two static policies and the template
+        assert_eq!(metrics.expression_depths().values().sum::<usize>(), 3);
+        assert_eq!(metrics.expression_depths().get(&1), Some(&1));
+        assert!(metrics.max_expression_depth() > 4);
+        assert_eq!(
+            metrics.extension_function_calls(),
+            &BTreeMap::from([("ip".to_string(), 2), ("isInRange".to_string(), 1)])
+        );
+        let literals = metrics
+            .entity_literals()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            literals,
+            vec![
+                r#"Action::"view""#,
+                r#"User::"alice""#,
+                r#"User::"bob""#,
+                r#"User::"mallory""#
+            ]
+        );
+    }
+
+    #[test]
+    fn complexity() {
+        let trivial = Policy::from_str("permit(principal, action, resource);").unwrap();
+        let complex = Policy::from_str(
+            r#"permit(principal, action in [Action::"a", Action::"b", Action::"c"], resource)
+            when {
+                context.tags.containsAny(["x", "y"]) &&
+                resource.owner in principal &&
+                resource.name like "*.jpg"
+            };"#,
+        )
+        .unwrap();
+        let complexity = complex.complexity();
+        assert_eq!(complexity.hierarchy_operations(), 4);
+        assert_eq!(complexity.set_operations(), 2);
+        assert_eq!(complexity.pattern_elements(), 5);
+        assert_eq!(complexity.max_set_literal(), 3);
+        assert!(complexity.expression_size() > trivial.complexity().expression_size());
+        assert!(complexity.score() > 4 * PolicyComplexity::HIERARCHY_WEIGHT);
+        assert!(trivial.complexity().score() < complexity.score());
+
+        let template = Template::parse(
+            None,
+            "permit(principal in ?principal, action, resource in ?resource);",
+        )
+        .unwrap();
+        assert_eq!(template.complexity().hierarchy_operations(), 2);
+    }
+
+    #[test]
+    fn coverage() {
+        let pset = PolicySet::from_str(
+            r#"permit(principal, action == Action::"view", resource)
+            when { principal.level > 2 }
+            unless { resource.private };
+            permit(principal, action == Action::"edit", resource);"#,
+        )
+        .unwrap();
+        let entities = Entities::from_json_value(
+            serde_json::json!([
+                { "uid": { "type": "User", "id": "alice" }, "attrs": { "level": 3 }, "parents": [] },
+                { "uid": { "type": "User", "id": "bob" }, "attrs": { "level": 1 }, "parents": [] },
+                { "uid": { "type": "User", "id": "eve" }, "attrs": {}, "parents": [] },
+                { "uid": { "type": "Photo", "id": "beach" }, "attrs": { "private": false }, "parents": [] }
+            ]),
+            None,
+        )
+        .unwrap();
+        let view = |user: &str| {
+            Request::new(
+                Some(EntityUid::from_strs("User", user)),
+                Some(EntityUid::from_strs("Action", "view")),
+                Some(EntityUid::from_strs("Photo", "beach")),
+                Context::empty(),
+                None,
+            )
+            .unwrap()
+        };
+        let requests = [view("alice"), view("bob"), view("eve")];
+        let coverage = pset.coverage(requests.iter().map(|request| (request, &entities)));
+        assert_eq!(coverage.requests(), 3);
+
+        let view = coverage
+            .policy(&PolicyId::from_str("policy0").unwrap())
+            .unwrap();
+        assert_eq!(
+            (view.satisfied(), view.not_satisfied(), view.errored()),
+            (1, 1, 1)
+        );
+        assert_eq!(view.scope().satisfied(), 3);
+        let clauses = view.clauses();
+        assert_eq!(clauses.len(), 2);
+        assert_eq!(clauses[0].kind(), ClauseKind::When);
+        assert_eq!(
+            (
+                clauses[0].satisfied(),
+                clauses[0].not_satisfied(),
+                clauses[0].errored()
+            ),
+            (1, 1, 1)
+        );
+        // only evaluated for `alice`
+        assert_eq!(clauses[1].kind(), ClauseKind::Unless);
+        assert_eq!(
+            (
+                clauses[1].satisfied(),
+                clauses[1].not_satisfied(),
+                clauses[1].errored()
+            ),
+            (1, 0, 0)
+        );
+
+        let edit = coverage
+            .policy(&PolicyId::from_str("policy1").unwrap())
+            .unwrap();
+        assert_eq!(edit.scope().not_satisfied(), 3);
+        assert!(edit.clauses().is_empty());
+        assert_eq!(
+            coverage.never_satisfied().collect::<Vec<_>>(),
+            vec![&PolicyId::from_str("policy1").unwrap()]
+        );
+    }
+
+    #[test]
+    fn compare_decisions() {
+        let old = PolicySet::from_str(r#"permit(principal in Group::"staff", action, resource);"#)
+            .unwrap();
+        let new = PolicySet::from_str(
+            r#"permit(principal in Group::"staff", action, resource);
+            forbid(principal == User::"bob", action == Action::"delete", resource);
+            permit(principal == User::"carol", action == Action::"view", resource);"#,
+        )
+        .unwrap();
+        let entities = Entities::from_json_value(
+            serde_json::json!([
+                { "uid": { "type": "User", "id": "bob" }, "attrs": {}, "parents": [{ "type": "Group", "id": "staff" }] },
+                { "uid": { "type": "Group", "id": "staff" }, "attrs": {}, "parents": [] }
+            ]),
+            None,
+        )
+        .unwrap();
+        let request = |user: &str, action: &str| {
+            Request::new(
+                Some(EntityUid::from_strs("User", user)),
+                Some(EntityUid::from_strs("Action", action)),
+                Some(EntityUid::from_strs("Photo", "beach")),
+                Context::empty(),
+                None,
+            )
+            .unwrap()
+        };
+        let requests = [
+            request("bob", "view"),
+            request("bob", "delete"),
+            request("carol", "view"),
+            request("carol", "delete"),
+        ];
+        let changes = super::compare_decisions(&old, &new, &requests, &entities);
+        assert_eq!(
+            changes
+                .iter()
+                .map(DecisionChange::index)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        let bob = &changes[0];
+        assert_eq!(bob.old_response().decision(), Decision::Allow);
+        assert_eq!(bob.new_response().decision(), Decision::Deny);
+        assert_eq!(
+            bob.old_response()
+                .diagnostics()
+                .reason()
+                .collect::<Vec<_>>(),
+            vec![&PolicyId::from_str("policy0").unwrap()]
+        );
+        assert_eq!(
+            bob.new_response()
+                .diagnostics()
+                .reason()
+                .collect::<Vec<_>>(),
+            vec![&PolicyId::from_str("policy1").unwrap()]
+        );
+
+        let carol = &changes[1];
+        assert!(std::ptr::eq(carol.request(), &requests[2]));
+        assert_eq!(carol.old_response().decision(), Decision::Deny);
+        assert_eq!(carol.new_response().decision(), Decision::Allow);
+        assert!(super::compare_decisions(&old, &old, &requests, &entities).is_empty());
+    }
 }
 
 mod schema_tests {
@@ -1464,6 +1744,143 @@ mod schema_tests {
             Err(SchemaError::Serde(_))
         );
     }
+
+    /// Test that strict mode reports every misspelled key at once
+    #[test]
+    fn strict_schema() {
+        let schema = json!(
+        { "": {
+            "entityTypes": {
+                "Photo": { "memberOfTypess": [ "Album" ] },
+                "Album": { }
+            },
+            "actions": {
+                "view": {
+                    "appliesTo": {
+                        "principalTypes": [ ],
+                        "resourceType": ["Photo"]
+                    }
+                }
+            }
+        }});
+        assert_matches!(
+            Schema::from_json_value_strict(schema),
+            Err(SchemaError::StrictModeViolations(findings)) => {
+                assert_eq!(findings.len(), 3);
+                assert!(findings.contains(&StrictSchemaFinding::UnknownKey {
+                    location: "//entityTypes/Photo".to_string(),
+                    key: "memberOfTypess".to_string(),
+                    suggestion: Some("did you mean `memberOfTypes`?".to_string()),
+                }));
+            }
+        );
+    }
+
+    /// Test that annotations on schema elements are accessible
+    #[test]
+    fn schema_annotations() {
+        let schema = Schema::from_json_value(json!(
+        { "": {
+            "entityTypes": {
+                "User": {
+                    "annotations": { "owner": "identity" },
+                    "shape": {
+                        "type": "Record",
+                        "attributes": {
+                            "email": {
+                                "type": "String",
+                                "annotations": { "sensitivity": "pii" }
+                            },
+                            "name": { "type": "String" }
+                        }
+                    }
+                }
+            },
+            "actions": {
+                "view": { "annotations": { "label": "View" } }
+            }
+        }}))
+        .expect("schema should be valid");
+        let user = EntityTypeName::from_str("User").unwrap();
+        assert_eq!(
+            schema
+                .entity_type_annotations(&user)
+                .map(Iterator::collect::<Vec<_>>),
+            Some(vec![("owner", "identity")])
+        );
+        assert_eq!(
+            schema
+                .attribute_annotations(&user, "email")
+                .map(Iterator::collect::<Vec<_>>),
+            Some(vec![("sensitivity", "pii")])
+        );
+        assert_eq!(
+            schema
+                .attribute_annotations(&user, "name")
+                .map(Iterator::count),
+            Some(0)
+        );
+        assert!(schema.attribute_annotations(&user, "phone").is_none());
+        let photo = EntityTypeName::from_str("Photo").unwrap();
+        assert!(schema.entity_type_annotations(&photo).is_none());
+        assert_eq!(
+            schema
+                .action_annotations(&EntityUid::from_str(r#"Action::"view""#).unwrap())
+                .map(Iterator::collect::<Vec<_>>),
+            Some(vec![("label", "View")])
+        );
+    }
+
+    /// Test that removing an attribute is reported as a breaking change
+    #[test]
+    fn schema_compatibility() {
+        let old = json!(
+        { "": {
+            "entityTypes": {
+                "User": {
+                    "shape": {
+                        "type": "Record",
+                        "attributes": {
+                            "name": { "type": "String" },
+                            "email": { "type": "String" }
+                        }
+                    }
+                }
+            },
+            "actions": {
+                "view": { "appliesTo": { "principalTypes": ["User"] } }
+            }
+        }});
+        let mut new = old.clone();
+        new[""]["entityTypes"]["User"]["shape"]["attributes"]
+            .as_object_mut()
+            .unwrap()
+            .remove("email");
+        new[""]["entityTypes"]["Admin"] = json!({});
+        let old = Schema::from_json_value(old).expect("schema should be valid");
+        let new = Schema::from_json_value(new).expect("schema should be valid");
+
+        assert!(Schema::check_compatibility(&old, &old).is_backward_compatible());
+        let report = Schema::check_compatibility(&old, &new);
+        assert!(!report.is_backward_compatible());
+        assert_eq!(
+            report
+                .changes()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "entity type `Admin` was added",
+                "attribute `email` of entity type `User` was removed",
+            ]
+        );
+        assert_eq!(
+            report.breaking_changes().collect::<Vec<_>>(),
+            vec![&SchemaChange::AttributeRemoved {
+                owner: AttributeOwner::EntityType("User".to_string()),
+                attr: "email".to_string(),
+            }]
+        );
+    }
 }
 
 mod ancestors_tests {
@@ -1483,11 +1900,445 @@ mod ancestors_tests {
         assert!(ans.contains(&b_euid));
         assert!(ans.contains(&a_euid));
     }
+
+    /// `A <- b <- C`, with or without the `A <- C` edge of the closure
+    fn hierarchy(closed: bool) -> serde_json::Value {
+        let c_parents = if closed {
+            serde_json::json!([{ "type": "test", "id": "b" }, { "type": "test", "id": "A" }])
+        } else {
+            serde_json::json!([{ "type": "test", "id": "b" }])
+        };
+        serde_json::json!([
+            { "uid": { "type": "test", "id": "A" }, "attrs": {}, "parents": [] },
+            { "uid": { "type": "test", "id": "b" }, "attrs": {},
+              "parents": [{ "type": "test", "id": "A" }] },
+            { "uid": { "type": "test", "id": "C" }, "attrs": {}, "parents": c_parents },
+        ])
+    }
+
+    #[test]
+    fn test_tc_computation() {
+        let a_euid: EntityUid = EntityUid::from_strs("test", "A");
+        let c_euid: EntityUid = EntityUid::from_strs("test", "C");
+
+        for tc_computation in [
+            TCComputation::ComputeNow,
+            TCComputation::EnforceAlreadyComputed,
+            TCComputation::AssumeAlreadyComputed,
+        ] {
+            let es = Entities::builder()
+                .tc_computation(tc_computation)
+                .from_json_value(hierarchy(true))
+                .unwrap();
+            assert!(es.is_ancestor_of(&a_euid, &c_euid), "{tc_computation:?}");
+        }
+
+        let es = Entities::builder()
+            .from_json_str(&hierarchy(false).to_string())
+            .unwrap();
+        assert!(es.is_ancestor_of(&a_euid, &c_euid));
+
+        let es = Entities::builder()
+            .tc_computation(TCComputation::ComputeOnDemand)
+            .from_json_value(hierarchy(false))
+            .unwrap();
+        assert!(es.is_ancestor_of(&a_euid, &c_euid));
+        assert_eq!(es.ancestors(&c_euid).unwrap().count(), 2);
+
+        let err = Entities::builder()
+            .tc_computation(TCComputation::EnforceAlreadyComputed)
+            .from_json_value(hierarchy(false))
+            .unwrap_err();
+        assert!(
+            matches!(err, EntitiesError::TransitiveClosureError(_)),
+            "{err:?}"
+        );
+
+        // the closure is assumed, so `A <- C` is missing
+        let es = Entities::builder()
+            .tc_computation(TCComputation::AssumeAlreadyComputed)
+            .from_json_value(hierarchy(false))
+            .unwrap();
+        assert!(!es.is_ancestor_of(&a_euid, &c_euid));
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let a_euid: EntityUid = EntityUid::from_strs("test", "A");
+        let b_euid: EntityUid = EntityUid::from_strs("test", "b");
+        let c_euid: EntityUid = EntityUid::from_strs("test", "C");
+        let entity = |level: i64, parent: &EntityUid| {
+            Entity::new(
+                c_euid.clone(),
+                HashMap::from([("level".into(), RestrictedExpression::new_long(level))]),
+                HashSet::from([parent.clone()]),
+            )
+            .unwrap()
+        };
+        let duplicates = || [entity(1, &a_euid), entity(2, &b_euid)];
+
+        let err = Entities::builder().from_entities(duplicates()).unwrap_err();
+        assert!(matches!(err, EntitiesError::Duplicate(_)), "{err:?}");
+
+        let es = Entities::builder()
+            .duplicates(DuplicateEntities::MergeLastWriterWins)
+            .from_entities(duplicates())
+            .unwrap();
+        assert!(es.is_ancestor_of(&a_euid, &c_euid));
+        assert!(es.is_ancestor_of(&b_euid, &c_euid));
+        let level = es.get(&c_euid).unwrap().attr("level").unwrap().unwrap();
+        assert_eq!(level, EvalResult::Long(2));
+
+        let err = Entities::builder()
+            .duplicates(DuplicateEntities::MergeRejectConflicts)
+            .from_entities(duplicates())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"duplicate entity entry `test::"C"` has a conflicting value for attribute `level`"#
+        );
+        let es = Entities::builder()
+            .duplicates(DuplicateEntities::MergeRejectConflicts)
+            .from_entities([entity(1, &a_euid), entity(1, &b_euid)])
+            .unwrap();
+        assert!(es.is_ancestor_of(&b_euid, &c_euid));
+    }
+}
+
+mod entities_iteration_tests {
+    use super::*;
+
+    fn entities() -> Entities {
+        Entities::from_json_value(
+            serde_json::json!([
+                { "uid": { "type": "User", "id": "alice" }, "attrs": { "admin": true },
+                  "parents": [] },
+                { "uid": { "type": "User", "id": "bob" }, "attrs": { "admin": false },
+                  "parents": [] },
+                { "uid": { "type": "User", "id": "carol" }, "attrs": { "admin": true },
+                  "parents": [] },
+                { "uid": { "type": "Photo", "id": "vacation" }, "attrs": {}, "parents": [] },
+            ]),
+            None,
+        )
+        .unwrap()
+    }
+
+    fn is_admin(e: &Entity) -> bool {
+        matches!(e.attr("admin"), Some(Ok(EvalResult::Bool(true))))
+    }
+
+    fn ids(page: &EntitiesPage<'_>) -> Vec<String> {
+        page.entities().map(|e| e.uid().to_string()).collect()
+    }
+
+    #[test]
+    fn of_type() {
+        let es = entities();
+        let user = EntityTypeName::from_str("User").unwrap();
+        assert_eq!(es.of_type(&user).count(), 3);
+        let group = EntityTypeName::from_str("Group").unwrap();
+        assert_eq!(es.of_type(&group).count(), 0);
+    }
+
+    #[test]
+    fn filtered() {
+        let es = entities();
+        let mut admins: Vec<String> = es
+            .iter_filtered(is_admin)
+            .map(|e| e.uid().id().to_string())
+            .collect();
+        admins.sort();
+        assert_eq!(admins, vec!["alice", "carol"]);
+    }
+
+    #[test]
+    fn pages() {
+        let es = entities();
+        let user = EntityTypeName::from_str("User").unwrap();
+        let page = es.page(Some(&user), None, 1, is_admin);
+        assert_eq!(ids(&page), vec![r#"User::"alice""#]);
+        let page = es.page(Some(&user), page.next_cursor(), 1, is_admin);
+        assert_eq!(ids(&page), vec![r#"User::"carol""#]);
+        assert_eq!(page.next_cursor(), None);
+
+        // all types, ordered by type name first
+        let page = es.page(None, None, 10, |_| true);
+        assert_eq!(
+            ids(&page),
+            vec![
+                r#"Photo::"vacation""#,
+                r#"User::"alice""#,
+                r#"User::"bob""#,
+                r#"User::"carol""#,
+            ]
+        );
+        assert_eq!(page.next_cursor(), None);
+
+        // a limit of zero still returns an entity, so that paging terminates
+        let page = es.page(None, None, 0, |_| true);
+        assert_eq!(ids(&page), vec![r#"Photo::"vacation""#]);
+        assert_eq!(
+            page.next_cursor(),
+            Some(&EntityUid::from_str(r#"Photo::"vacation""#).unwrap())
+        );
+    }
+}
+
+mod entity_attr_update_tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Schema {
+        Schema::from_json_value(json!(
+        {"": {
+            "entityTypes": {
+                "User": {
+                    "shape": {
+                        "type": "Record",
+                        "attributes": {
+                            "logins": { "type": "Long" },
+                            "nickname": { "type": "String", "required": false }
+                        }
+                    }
+                }
+            },
+            "actions": {}
+        }}
+        ))
+        .expect("should be a valid schema")
+    }
+
+    fn entities(schema: &Schema) -> Entities {
+        Entities::from_json_value(
+            json!([{ "uid": { "type": "User", "id": "alice" }, "attrs": { "logins": 1 },
+                     "parents": [] }]),
+            Some(schema),
+        )
+        .unwrap()
+    }
+
+    fn alice() -> EntityUid {
+        EntityUid::from_strs("User", "alice")
+    }
+
+    fn attr(entities: &Entities, attr: &str) -> Option<EvalResult> {
+        entities
+            .get(&alice())
+            .unwrap()
+            .attr(attr)
+            .map(Result::unwrap)
+    }
+
+    #[test]
+    fn updates_and_removes() {
+        let schema = schema();
+        let mut es = entities(&schema);
+        es.update_attr(
+            &alice(),
+            "logins",
+            RestrictedExpression::new_long(2),
+            Some(&schema),
+        )
+        .unwrap();
+        assert_eq!(attr(&es, "logins"), Some(EvalResult::Long(2)));
+
+        es.update_attr(
+            &alice(),
+            "nickname",
+            RestrictedExpression::new_string("al".to_owned()),
+            Some(&schema),
+        )
+        .unwrap();
+        assert_eq!(
+            attr(&es, "nickname"),
+            Some(EvalResult::String("al".to_owned()))
+        );
+        assert!(es.remove_attr(&alice(), "nickname", Some(&schema)).unwrap());
+        assert_eq!(attr(&es, "nickname"), None);
+        assert!(!es.remove_attr(&alice(), "nickname", Some(&schema)).unwrap());
+    }
+
+    #[test]
+    fn invalid_updates_are_rolled_back() {
+        let schema = schema();
+        let mut es = entities(&schema);
+        let err = es
+            .update_attr(
+                &alice(),
+                "logins",
+                RestrictedExpression::new_string("many".to_owned()),
+                Some(&schema),
+            )
+            .unwrap_err();
+        assert!(matches!(err, EntitiesError::InvalidEntity(_)), "{err:?}");
+        let err = es
+            .remove_attr(&alice(), "logins", Some(&schema))
+            .unwrap_err();
+        assert!(matches!(err, EntitiesError::InvalidEntity(_)), "{err:?}");
+        assert_eq!(attr(&es, "logins"), Some(EvalResult::Long(1)));
+
+        let err = es
+            .update_attr(
+                &alice(),
+                "logins",
+                RestrictedExpression::from_str(r#"ip("not an ip")"#).unwrap(),
+                None,
+            )
+            .unwrap_err();
+        assert!(matches!(err, EntitiesError::AttrEvaluation(_)), "{err:?}");
+        assert_eq!(attr(&es, "logins"), Some(EvalResult::Long(1)));
+
+        let err = es
+            .update_attr(
+                &EntityUid::from_strs("User", "bob"),
+                "logins",
+                RestrictedExpression::new_long(1),
+                None,
+            )
+            .unwrap_err();
+        assert!(matches!(err, EntitiesError::NotFound(_)), "{err:?}");
+    }
+
+    #[test]
+    fn snapshots() {
+        let schema = schema();
+        let mut es = entities(&schema);
+        let snapshot = es.snapshot();
+        es.update_attr(
+            &alice(),
+            "logins",
+            RestrictedExpression::new_long(2),
+            Some(&schema),
+        )
+        .unwrap();
+        assert_eq!(attr(&snapshot, "logins"), Some(EvalResult::Long(1)));
+        assert_eq!(attr(&es, "logins"), Some(EvalResult::Long(2)));
+        assert_eq!((snapshot.version(), es.version()), (0, 1));
+        let snapshot = es.snapshot();
+        assert_eq!(snapshot.version(), 1);
+
+        // requests are authorized against the snapshot
+        let policies = PolicySet::from_str(
+            "permit(principal, action, resource) when { principal.logins > 1 };",
+        )
+        .unwrap();
+        let request = Request::new(
+            Some(alice()),
+            Some(EntityUid::from_strs("Action", "view")),
+            Some(alice()),
+            Context::empty(),
+            None,
+        )
+        .unwrap();
+        es.update_attr(&alice(), "logins", RestrictedExpression::new_long(0), None)
+            .unwrap();
+        let authorizer = Authorizer::new();
+        let response = authorizer.is_authorized(&request, &policies, &snapshot);
+        assert_eq!(response.decision(), Decision::Allow);
+        let response = authorizer.is_authorized(&request, &policies, &es);
+        assert_eq!(response.decision(), Decision::Deny);
+    }
 }
 
 /// A few tests of validating entities.
 /// Many other validation-related tests are in the separate module focusing on
 /// schema-based parsing.
+#[cfg(feature = "partial-eval")]
+mod partial_entity_tests {
+    use super::*;
+    use serde_json::json;
+
+    fn policies() -> PolicySet {
+        r#"permit(principal, action, resource) when { principal.department == "CS" };"#
+            .parse()
+            .unwrap()
+    }
+
+    fn request() -> Request {
+        Request::new(
+            Some(EntityUid::from_strs("User", "alice")),
+            Some(EntityUid::from_strs("Action", "view")),
+            Some(EntityUid::from_strs("Photo", "vacation")),
+            Context::empty(),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[track_caller]
+    fn assert_residual(entities: &Entities) {
+        let response = Authorizer::new().is_authorized_partial(&request(), &policies(), entities);
+        assert_matches!(response, PartialResponse::Residual(residual) => {
+            let policies = residual.residuals().policies().collect::<Vec<_>>();
+            assert_matches!(policies.as_slice(), [policy] => {
+                assert!(policy.to_string().contains("unknown"), "{policy}");
+            });
+        });
+    }
+
+    #[test]
+    fn unknown_attrs_from_json() {
+        let entities = Entities::from_json_value(
+            json!([
+                {
+                    "uid": { "type": "User", "id": "alice" },
+                    "attrs": {},
+                    "unknownAttrs": ["department"],
+                    "parents": []
+                }
+            ]),
+            None,
+        )
+        .unwrap();
+        assert_residual(&entities);
+
+        // without the declaration, the attribute is missing
+        let entities = Entities::from_json_value(
+            json!([{ "uid": { "type": "User", "id": "alice" }, "attrs": {}, "parents": [] }]),
+            None,
+        )
+        .unwrap();
+        let response = Authorizer::new().is_authorized_partial(&request(), &policies(), &entities);
+        assert_matches!(response, PartialResponse::Concrete(response) => {
+            assert_eq!(response.decision(), Decision::Deny);
+            assert_eq!(response.diagnostics().errors().count(), 1);
+        });
+    }
+
+    #[test]
+    fn unknown_attrs_from_api() {
+        let alice = Entity::new_with_unknown_attrs(
+            EntityUid::from_strs("User", "alice"),
+            HashMap::new(),
+            HashSet::from(["department".to_string()]),
+            HashSet::new(),
+        )
+        .unwrap();
+        let entities = Entities::from_entities([alice.clone()], None).unwrap();
+        assert_residual(&entities);
+
+        // the same entity can be built from `RestrictedExpression::new_unknown`
+        let same = Entity::new(
+            EntityUid::from_strs("User", "alice"),
+            HashMap::from([(
+                "department".to_string(),
+                RestrictedExpression::new_unknown(r#"User::"alice".department"#),
+            )]),
+            HashSet::new(),
+        )
+        .unwrap();
+        assert_eq!(alice, same);
+
+        // and round-trips through JSON
+        let mut buf = Vec::new();
+        entities.write_to_json(&mut buf).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(json[0]["unknownAttrs"], json!(["department"]));
+        assert_eq!(Entities::from_json_value(json, None).unwrap(), entities);
+    }
+}
+
 mod entity_validate_tests {
     use super::*;
     use serde_json::json;
@@ -2903,6 +3754,210 @@ mod schema_based_parsing_tests {
             Some(Err(e)) => assert_contains_unknown(&e.to_string(), "ttt")
         );
     }
+
+    /// Entities which don't conform to the schema are skipped, and reported
+    #[test]
+    fn collecting_errors() {
+        let schema = Schema::from_json_value(json!(
+        {"": {
+            "entityTypes": {
+                "Employee": {
+                    "memberOfTypes": [],
+                    "shape": {
+                        "type": "Record",
+                        "attributes": {
+                            "numDirectReports": { "type": "Long" }
+                        }
+                    }
+                }
+            },
+            "actions": {
+                "view": { }
+            }
+        }}
+        ))
+        .expect("should be a valid schema");
+        let entitiesjson = json!(
+            [
+                {
+                    "uid": { "type": "Employee", "id": "12UA45" },
+                    "attrs": { "numDirectReports": 3 },
+                    "parents": []
+                },
+                {
+                    "uid": { "type": "Employee", "id": "34FB87" },
+                    "attrs": { "numDirectReports": "3" },
+                    "parents": []
+                },
+                {
+                    "uid": { "type": "Employee", "id": "09AE76" },
+                    "attrs": {},
+                    "parents": []
+                },
+                {
+                    "uid": { "type": "Manager", "id": "09AE76" },
+                    "attrs": {},
+                    "parents": []
+                }
+            ]
+        );
+        let (entities, skipped) =
+            Entities::from_json_value_collecting_errors(entitiesjson.clone(), Some(&schema))
+                .expect("should parse");
+        // the remaining entity and the action from the schema
+        assert_eq!(entities.iter().count(), 2);
+        assert!(entities
+            .get(&EntityUid::from_strs("Employee", "12UA45"))
+            .is_some());
+        let skipped = skipped
+            .iter()
+            .map(|err| (err.index, err.uid.as_ref().map(ToString::to_string)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            skipped,
+            vec![
+                (1, Some(r#"Employee::"34FB87""#.to_string())),
+                (2, Some(r#"Employee::"09AE76""#.to_string())),
+                (3, Some(r#"Manager::"09AE76""#.to_string())),
+            ]
+        );
+
+        // without collecting errors, parsing stops at one of them, which isn't
+        // necessarily the first in the input
+        assert_matches!(
+            Entities::from_json_value(entitiesjson, Some(&schema)),
+            Err(e) => assert!(
+                [
+                    r#"Employee::"34FB87""#,
+                    r#"Employee::"09AE76""#,
+                    r#"Manager::"09AE76""#
+                ]
+                .iter()
+                .any(|uid| e.to_string().contains(uid)),
+                "{e}"
+            )
+        );
+    }
+
+    /// Optional context attributes which are missing get the schema's defaults
+    #[test]
+    fn context_defaults() {
+        let schema_with_defaults = |defaults| {
+            Schema::from_json_value(json!(
+            {"": {
+                "entityTypes": { "User": {}, "Photo": {} },
+                "actions": {
+                    "view": {
+                        "contextDefaults": defaults,
+                        "appliesTo": {
+                            "principalTypes": ["User"],
+                            "resourceTypes": ["Photo"],
+                            "context": {
+                                "type": "Record",
+                                "attributes": {
+                                    "mfa": { "type": "Boolean" },
+                                    "region": { "type": "String", "required": false },
+                                    "owner": { "type": "Entity", "name": "User", "required": false }
+                                }
+                            }
+                        }
+                    }
+                }
+            }}
+            ))
+        };
+        let schema = schema_with_defaults(json!({
+            "region": "us",
+            // the `__entity` escape is implicit, as for the context itself
+            "owner": { "type": "User", "id": "alice" }
+        }))
+        .expect("should be a valid schema");
+        let action = EntityUid::from_strs("Action", "view");
+        let policies = PolicySet::from_str(
+            r#"permit(principal, action, resource)
+            when { context.region == "us" && context.owner == User::"alice" };"#,
+        )
+        .unwrap();
+        let decide = |context| {
+            let context = Context::from_json_value(context, Some((&schema, &action)))
+                .expect("context should parse");
+            let request = Request::new(
+                Some(EntityUid::from_strs("User", "bob")),
+                Some(action.clone()),
+                Some(EntityUid::from_strs("Photo", "a")),
+                context,
+                Some(&schema),
+            )
+            .unwrap();
+            Authorizer::new()
+                .is_authorized(&request, &policies, &Entities::empty())
+                .decision()
+        };
+        assert_eq!(decide(json!({ "mfa": true })), Decision::Allow);
+        // attributes in the context aren't replaced by the defaults
+        assert_eq!(
+            decide(json!({ "mfa": true, "region": "eu" })),
+            Decision::Deny
+        );
+
+        assert_matches!(
+            schema_with_defaults(json!({ "mfa": false })),
+            Err(SchemaError::ContextDefaultNotOptional(_, attr)) => assert_eq!(attr, "mfa")
+        );
+        assert_matches!(
+            schema_with_defaults(json!({ "device": "phone" })),
+            Err(SchemaError::ContextDefaultNotOptional(_, attr)) => assert_eq!(attr, "device")
+        );
+        assert_matches!(
+            schema_with_defaults(json!({ "region": 1 })),
+            Err(SchemaError::InvalidContextDefault(_, attr, _)) => assert_eq!(attr, "region")
+        );
+    }
+
+    /// Strict parsing reports every attribute which isn't declared
+    #[test]
+    fn context_strict() {
+        let schema = Schema::from_json_value(json!(
+        {"": {
+            "entityTypes": {},
+            "actions": {
+                "view": {
+                    "appliesTo": {
+                        "context": {
+                            "type": "Record",
+                            "attributes": {
+                                "mfa": { "type": "Boolean" },
+                                "region": { "type": "String", "required": false }
+                            }
+                        }
+                    }
+                }
+            }
+        }}
+        ))
+        .expect("should be a valid schema");
+        let action = EntityUid::from_strs("Action", "view");
+        Context::from_json_value_strict(json!({ "mfa": true, "region": "us" }), (&schema, &action))
+            .expect("context should parse");
+        assert_matches!(
+            Context::from_json_value_strict(
+                json!({ "mfa": true, "regoin": "us", "device": "phone" }),
+                (&schema, &action)
+            ),
+            Err(ContextJsonError::UndeclaredAttributes { attrs, .. }) => {
+                let names: Vec<_> = attrs.iter().map(|attr| attr.attr.as_str()).collect();
+                assert_eq!(names, vec!["device", "regoin"]);
+                assert_eq!(attrs[1].to_string(), "`regoin` (did you mean `region`?)");
+            }
+        );
+        assert_matches!(
+            Context::from_json_value_strict(
+                json!({ "mfa": true }),
+                (&schema, &EntityUid::from_strs("Action", "edit"))
+            ),
+            Err(ContextJsonError::MissingAction { .. })
+        );
+    }
 }
 
 #[cfg(not(feature = "partial-validate"))]
@@ -2991,6 +4046,93 @@ mod partial_schema {
     }
 }
 
+mod parse_error_tests {
+    use super::*;
+
+    #[test]
+    fn typed_errors() {
+        let src = "permit(principal, action, resource) when { 1 / 2 };";
+        let errs = PolicySet::from_str(src).unwrap_err();
+        let err = errs.errors().next().unwrap();
+        assert_eq!(err.kind(), ParseErrorKind::Structure);
+        assert_eq!(err.message(), "division is not supported");
+        assert_eq!(err.help(), None);
+        assert_eq!(err.source_range().map(|range| &src[range]), Some("1 / 2"));
+
+        let src = "permit(principal, action, resource) when { 1 < };";
+        let errs: Vec<ParseError> = PolicySet::from_str(src).unwrap_err().into_iter().collect();
+        assert_eq!(errs[0].kind(), ParseErrorKind::Syntax);
+        assert_eq!(errs[0].source_range().map(|range| range.start), Some(47));
+    }
+
+    #[test]
+    fn recovery() {
+        let src = r#"
+            permit(principal, action, resource) when { 1 < };
+            permit(principal == User::"alice", action, resource);
+            forbid(principal, action, resource) when { 1 / 2 };
+            forbid(principal, action, resource unless { false };
+        "#;
+        let (policies, errs) = PolicySet::parse_with_recovery(src);
+        let kinds: Vec<_> = errs.unwrap().errors().map(ParseError::kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                ParseErrorKind::Syntax,
+                ParseErrorKind::Syntax,
+                ParseErrorKind::Structure
+            ]
+        );
+        let ids: Vec<_> = policies.policies().map(|p| p.id().to_string()).collect();
+        assert_eq!(ids, ["policy1"]);
+        assert!(!policies.source_map().is_empty());
+
+        let (policies, errs) =
+            PolicySet::parse_with_recovery("permit(principal, action, resource);");
+        assert!(errs.is_none());
+        assert_eq!(policies.policies().count(), 1);
+    }
+}
+
+mod pattern_tests {
+    use super::*;
+
+    /// `*`s in literal text only match `*`, in expressions and in the Cedar
+    /// syntax of the pattern
+    #[test]
+    fn literal_text_is_escaped() {
+        let pattern = Pattern::starts_with("a*b\"").then(&Pattern::ends_with(".txt"));
+        assert_eq!(pattern.to_string(), r#""a\*b\"*.txt""#);
+        assert!(pattern.matches("a*b\"/c.txt"));
+        assert!(!pattern.matches("aXb\"/c.txt"));
+
+        let request = Request::new(None, None, None, Context::empty(), None).unwrap();
+        let like = |text: &str| {
+            let built = Expression::like(Expression::new_string(text.to_string()), &pattern);
+            let src = format!("\"{}\" like {pattern}", text.escape_debug());
+            let parsed = Expression::from_str(&src).unwrap();
+            let built = eval_expression(&request, &Entities::empty(), &built).unwrap();
+            let parsed = eval_expression(&request, &Entities::empty(), &parsed).unwrap();
+            assert_eq!(built, parsed);
+            built
+        };
+        assert_eq!(like("a*b\"/c.txt"), EvalResult::Bool(true));
+        assert_eq!(like("aXb\"/c.txt"), EvalResult::Bool(false));
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(Pattern::new().matches(""));
+        assert!(!Pattern::new().matches("a"));
+        assert!(Pattern::any().matches("anything"));
+        assert!(Pattern::contains("b").matches("abc"));
+        assert!(!Pattern::ends_with("b").matches("abc"));
+        // consecutive wildcards are merged
+        assert_eq!(Pattern::any().wildcard(), Pattern::any());
+        assert_eq!(Pattern::any().then(&Pattern::any()), Pattern::any());
+    }
+}
+
 mod template_tests {
     use crate::Template;
 
@@ -0,0 +1,440 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! This module splits the text of policies, and of schemas in the Cedar
+//! schema format, into [`Token`]s, with their kinds and spans, for syntax
+//! highlighting, folding, and the like in editors.
+//! ```
+//! use cedar_policy::tokens::{policy_tokens, TokenKind};
+//! let tokens: Vec<_> = policy_tokens("permit(principal, action, resource); // all")
+//!     .map(|token| (token.kind(), token.text()))
+//!     .collect();
+//! assert_eq!(tokens[0], (TokenKind::Keyword, "permit"));
+//! assert_eq!(tokens[1], (TokenKind::Punctuation, "("));
+//! assert_eq!(tokens[2], (TokenKind::Variable, "principal"));
+//! assert_eq!(tokens[9], (TokenKind::Comment, "// all"));
+//! ```
+//!
+//! The tokens are those of the policy grammar, and of the parser of the Cedar
+//! schema format in [`crate::cedar_schema`], which lexes with
+//! [`schema_tokens`], plus
+//! comments. Whitespace isn't a token, and text which isn't part of any
+//! token, e.g., an unterminated string, is an [`TokenKind::Invalid`] token, so
+//! the tokens of any text cover all of it but its whitespace. A token doesn't
+//! depend on the tokens before it, so an editor can tokenize just the lines
+//! which changed, as long as they don't start in a string.
+
+use std::ops::Range;
+
+/// The kind of a [`Token`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TokenKind {
+    /// A keyword, e.g., `permit`, `when`, or `in` in policies, or `entity`
+    /// or `appliesTo` in schemas
+    Keyword,
+    /// `true` or `false`
+    Boolean,
+    /// `principal`, `action`, `resource`, or `context` in policies
+    Variable,
+    /// A slot of a template, e.g., `?principal`
+    Slot,
+    /// An identifier, e.g., an entity type, attribute, or function name
+    Identifier,
+    /// A (non-negative) integer literal
+    Number,
+    /// A string literal, including its quotes
+    String,
+    /// An operator, e.g., `==`, `&&`, or `+`
+    Operator,
+    /// Brackets, separators, `.`, `::`, and `@`
+    Punctuation,
+    /// A `//` comment, up to the end of the line
+    Comment,
+    /// Text which isn't a token, e.g., a character which can't start one, or
+    /// an unterminated string, which goes to the end of the text
+    Invalid,
+}
+
+/// A token of policy or schema text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token<'a> {
+    kind: TokenKind,
+    text: &'a str,
+    span: Range<usize>,
+}
+
+impl<'a> Token<'a> {
+    /// The kind of token
+    pub fn kind(&self) -> TokenKind {
+        self.kind
+    }
+
+    /// The text of the token
+    pub fn text(&self) -> &'a str {
+        self.text
+    }
+
+    /// The range of bytes of the token in the text
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Language {
+    Policy,
+    Schema,
+}
+
+// The symbols are in the order they're tried, so that a symbol comes before
+// the symbols which are prefixes of it
+const POLICY_SYMBOLS: [(&str, TokenKind); 26] = [
+    ("==", TokenKind::Operator),
+    ("!=", TokenKind::Operator),
+    ("<=", TokenKind::Operator),
+    (">=", TokenKind::Operator),
+    ("||", TokenKind::Operator),
+    ("&&", TokenKind::Operator),
+    ("::", TokenKind::Punctuation),
+    ("<", TokenKind::Operator),
+    (">", TokenKind::Operator),
+    ("+", TokenKind::Operator),
+    ("-", TokenKind::Operator),
+    ("*", TokenKind::Operator),
+    ("/", TokenKind::Operator),
+    ("%", TokenKind::Operator),
+    ("!", TokenKind::Operator),
+    ("@", TokenKind::Punctuation),
+    (".", TokenKind::Punctuation),
+    (",", TokenKind::Punctuation),
+    (";", TokenKind::Punctuation),
+    (":", TokenKind::Punctuation),
+    ("(", TokenKind::Punctuation),
+    (")", TokenKind::Punctuation),
+    ("{", TokenKind::Punctuation),
+    ("}", TokenKind::Punctuation),
+    ("[", TokenKind::Punctuation),
+    ("]", TokenKind::Punctuation),
+];
+
+const SCHEMA_SYMBOLS: [(&str, TokenKind); 12] = [
+    ("::", TokenKind::Punctuation),
+    ("{", TokenKind::Punctuation),
+    ("}", TokenKind::Punctuation),
+    ("[", TokenKind::Punctuation),
+    ("]", TokenKind::Punctuation),
+    ("<", TokenKind::Punctuation),
+    (">", TokenKind::Punctuation),
+    (",", TokenKind::Punctuation),
+    (";", TokenKind::Punctuation),
+    (":", TokenKind::Punctuation),
+    ("?", TokenKind::Punctuation),
+    ("=", TokenKind::Punctuation),
+];
+
+impl Language {
+    fn word(self, word: &str) -> TokenKind {
+        match (self, word) {
+            (Self::Policy, "true" | "false") => TokenKind::Boolean,
+            (
+                Self::Policy,
+                "permit" | "forbid" | "when" | "unless" | "if" | "then" | "else" | "in" | "has"
+                | "like" | "is",
+            ) => TokenKind::Keyword,
+            (Self::Policy, "principal" | "action" | "resource" | "context") => TokenKind::Variable,
+            (
+                Self::Schema,
+                "namespace" | "include" | "type" | "entity" | "action" | "in" | "appliesTo"
+                | "principal" | "resource" | "context",
+            ) => TokenKind::Keyword,
+            _ => TokenKind::Identifier,
+        }
+    }
+
+    fn symbols(self) -> &'static [(&'static str, TokenKind)] {
+        match self {
+            Self::Policy => &POLICY_SYMBOLS,
+            Self::Schema => &SCHEMA_SYMBOLS,
+        }
+    }
+}
+
+/// The [`Token`]s of policy or schema text, in order
+#[derive(Debug, Clone)]
+pub struct Tokens<'a> {
+    text: &'a str,
+    pos: usize,
+    language: Language,
+}
+
+/// The tokens of the text of policies or templates, which needn't parse
+pub fn policy_tokens(text: &str) -> Tokens<'_> {
+    Tokens {
+        text,
+        pos: 0,
+        language: Language::Policy,
+    }
+}
+
+/// The tokens of the text of a schema in the Cedar schema format, which
+/// needn't parse
+pub fn schema_tokens(text: &str) -> Tokens<'_> {
+    Tokens {
+        text,
+        pos: 0,
+        language: Language::Schema,
+    }
+}
+
+/// The length of the identifier at the start of `text`, which is 0 if it
+/// doesn't start with one
+fn identifier_len(text: &str) -> usize {
+    if !text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        return 0;
+    }
+    text.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(text.len())
+}
+
+/// The length of the string literal at the start of `text`, including its
+/// quotes, or `None` if it isn't terminated
+fn string_len(text: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The length of the raw string literal, `r"..."` or `r#"..."#`, at the start
+/// of `text`, or `None` if it isn't terminated
+fn raw_string_len(text: &str) -> Option<usize> {
+    match text.strip_prefix("r#\"") {
+        Some(rest) => rest.find("\"#").map(|i| i + 5),
+        None => text.strip_prefix("r\"")?.find('"').map(|i| i + 3),
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let rest = self.text.get(self.pos..)?.trim_start();
+        let start = self.text.len() - rest.len();
+        let c = rest.chars().next()?;
+        let (kind, len) = if rest.starts_with("//") {
+            let len = rest.find(['\n', '\r']).unwrap_or(rest.len());
+            (TokenKind::Comment, len)
+        } else if c == '"' {
+            string_len(rest).map_or((TokenKind::Invalid, rest.len()), |len| {
+                (TokenKind::String, len)
+            })
+        } else if self.language == Language::Policy
+            && (rest.starts_with("r\"") || rest.starts_with("r#\""))
+        {
+            raw_string_len(rest).map_or((TokenKind::Invalid, rest.len()), |len| {
+                (TokenKind::String, len)
+            })
+        } else if c == '?' && self.language == Language::Policy {
+            match identifier_len(rest.get(1..).unwrap_or_default()) {
+                0 => (TokenKind::Invalid, 1),
+                len => (TokenKind::Slot, len + 1),
+            }
+        } else if c.is_ascii_digit() && self.language == Language::Policy {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            (TokenKind::Number, len)
+        } else if let len @ 1.. = identifier_len(rest) {
+            (self.language.word(rest.get(..len)?), len)
+        } else if let Some((symbol, kind)) = self
+            .language
+            .symbols()
+            .iter()
+            .find(|(symbol, _)| rest.starts_with(symbol))
+        {
+            (*kind, symbol.len())
+        } else {
+            (TokenKind::Invalid, c.len_utf8())
+        };
+        self.pos = start + len;
+        Some(Token {
+            kind,
+            text: rest.get(..len)?,
+            span: start..self.pos,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn kinds(tokens: Tokens<'_>) -> Vec<(TokenKind, &str)> {
+        tokens.map(|token| (token.kind(), token.text())).collect()
+    }
+
+    #[test]
+    fn policies() {
+        use TokenKind::{
+            Comment, Identifier, Keyword, Number, Operator, Punctuation, Slot, String, Variable,
+        };
+        let text = r#"@id("a")
+permit(principal == ?principal, action in [Action::"view"], resource)
+when { context.n >= 10 && !(resource has owner) }; // a comment
+"#;
+        assert_eq!(
+            kinds(policy_tokens(text)),
+            vec![
+                (Punctuation, "@"),
+                (Identifier, "id"),
+                (Punctuation, "("),
+                (String, r#""a""#),
+                (Punctuation, ")"),
+                (Keyword, "permit"),
+                (Punctuation, "("),
+                (Variable, "principal"),
+                (Operator, "=="),
+                (Slot, "?principal"),
+                (Punctuation, ","),
+                (Variable, "action"),
+                (Keyword, "in"),
+                (Punctuation, "["),
+                (Identifier, "Action"),
+                (Punctuation, "::"),
+                (String, r#""view""#),
+                (Punctuation, "]"),
+                (Punctuation, ","),
+                (Variable, "resource"),
+                (Punctuation, ")"),
+                (Keyword, "when"),
+                (Punctuation, "{"),
+                (Variable, "context"),
+                (Punctuation, "."),
+                (Identifier, "n"),
+                (Operator, ">="),
+                (Number, "10"),
+                (Operator, "&&"),
+                (Operator, "!"),
+                (Punctuation, "("),
+                (Variable, "resource"),
+                (Keyword, "has"),
+                (Identifier, "owner"),
+                (Punctuation, ")"),
+                (Punctuation, "}"),
+                (Punctuation, ";"),
+                (Comment, "// a comment"),
+            ]
+        );
+    }
+
+    #[test]
+    fn spans() {
+        let text = "permit ( // é\n  principal";
+        for token in policy_tokens(text) {
+            assert_eq!(text.get(token.span()), Some(token.text()));
+        }
+        assert_eq!(
+            policy_tokens(text).map(|t| t.span()).collect::<Vec<_>>(),
+            vec![0..6, 7..8, 9..14, 17..26]
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        use TokenKind::{Identifier, Invalid};
+        assert_eq!(
+            kinds(policy_tokens(r#"a = ? # "b\" c"#)),
+            vec![
+                (Identifier, "a"),
+                (Invalid, "="),
+                (Invalid, "?"),
+                (Invalid, "#"),
+                (Invalid, r#""b\" c"#),
+            ]
+        );
+    }
+
+    #[test]
+    fn raw_strings() {
+        use TokenKind::{Identifier, Invalid, Operator, String};
+        assert_eq!(
+            kinds(policy_tokens(r###"r"a\" == r#"b"; "c"# r "d" r"e"###)),
+            vec![
+                (String, r#"r"a\""#),
+                (Operator, "=="),
+                (String, r##"r#"b"; "c"#"##),
+                (Identifier, "r"),
+                (String, r#""d""#),
+                (Invalid, r#"r"e"#),
+            ]
+        );
+        assert_eq!(
+            kinds(schema_tokens(r#"r"a""#)),
+            vec![(Identifier, "r"), (String, r#""a""#)]
+        );
+    }
+
+    #[test]
+    fn schemas() {
+        use TokenKind::{Comment, Identifier, Keyword, Punctuation, String};
+        let text = r#"namespace App {
+  // users
+  entity User in [Group] { name?: String };
+  action "view" appliesTo { principal: [User] };
+}"#;
+        assert_eq!(
+            kinds(schema_tokens(text)),
+            vec![
+                (Keyword, "namespace"),
+                (Identifier, "App"),
+                (Punctuation, "{"),
+                (Comment, "// users"),
+                (Keyword, "entity"),
+                (Identifier, "User"),
+                (Keyword, "in"),
+                (Punctuation, "["),
+                (Identifier, "Group"),
+                (Punctuation, "]"),
+                (Punctuation, "{"),
+                (Identifier, "name"),
+                (Punctuation, "?"),
+                (Punctuation, ":"),
+                (Identifier, "String"),
+                (Punctuation, "}"),
+                (Punctuation, ";"),
+                (Keyword, "action"),
+                (String, r#""view""#),
+                (Keyword, "appliesTo"),
+                (Punctuation, "{"),
+                (Keyword, "principal"),
+                (Punctuation, ":"),
+                (Punctuation, "["),
+                (Identifier, "User"),
+                (Punctuation, "]"),
+                (Punctuation, "}"),
+                (Punctuation, ";"),
+                (Punctuation, "}"),
+            ]
+        );
+    }
+}
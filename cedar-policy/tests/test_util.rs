@@ -0,0 +1,40 @@
+/*
+ * Copyright 2022-2023 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Runs the integration tests through the differential testing helpers of the
+//! `test-util` feature
+#![cfg(feature = "test-util")]
+
+use cedar_policy::integration_testing::resolve_integration_test_path;
+use cedar_policy::test_util::{load_golden_corpus, load_golden_test};
+
+#[test]
+fn example_use_cases_agree() {
+    let root = resolve_integration_test_path("");
+    let tests = load_golden_corpus(&root, root.join("tests/example_use_cases_doc"));
+    assert!(!tests.is_empty());
+    for test in tests {
+        test.check();
+    }
+}
+
+#[test]
+fn multi_agrees() {
+    let root = resolve_integration_test_path("");
+    let test = load_golden_test(&root, root.join("tests/multi/1.json"));
+    assert!(!test.requests.is_empty());
+    test.check();
+}